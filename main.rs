@@ -33,11 +33,35 @@ fontdb = "=0.23.0"
 rayon = "1.11.0"
 crossbeam-channel = "0.5.15"
 parking_lot = "0.12.5"
+sha2 = "0.10"
+base64 = "0.22"
+
+# Подпись артефактов в кэше скомпилированных модулей
+[dependencies.hmac]
+version = "0.12"
+optional = true
 
 [dependencies.boa_engine]
 version = "0.20"
 optional = true
 
+[dependencies.boa_gc]
+version = "0.20"
+optional = true
+
+# Разбор для структурной позиции синтаксических ошибок
+[dependencies.boa_parser]
+version = "0.20"
+optional = true
+
+[dependencies.boa_ast]
+version = "0.20"
+optional = true
+
+[dependencies.boa_interner]
+version = "0.20"
+optional = true
+
 [dependencies.winit]
 version = "0.30.12"
 optional = true
@@ -82,8 +106,17 @@ optional = true
 version = "25.0"
 optional = true
 
+[dependencies.wasmtime-wasi]
+version = "25.0"
+optional = true
+
+[dependencies.cap-rand]
+version = "3.4"
+optional = true
+
 [dependencies.reqwest]
 version = "0.12.24"
+features = ["cookies", "json"]
 optional = true
 
 [dependencies.rustls]
@@ -94,12 +127,8 @@ optional = true
 version = "0.56"
 optional = true
 
-[dependencies.wasmcloud-host]
-version = "0.26"
-optional = true
-
-[dependencies.wasmcloud-control-interface]
-version = "0.27"
+[dependencies.libloading]
+version = "0.8"
 optional = true
 
 [dependencies.ed25519-dalek]
@@ -140,17 +169,21 @@ optional = true
 
 
 [features]
-default = ["ui", "network", "vdom", "sysinfo"] 
+# security включён по умолчанию: без него проверка подписи отклоняет все плагины, библиотеки и модули
+default = ["ui", "network", "vdom", "sysinfo", "security"] 
 ui = ["winit", "wgpu", "egui", "egui-winit", "egui-wgpu", "egui_extras"]
 network = ["reqwest", "rustls"]
 vdom = ["html5ever", "markup5ever", "cssparser", "selectors", "serde_json"]
-js = ["boa_engine"]
-orchestration = ["wasmcloud-host", "wasmcloud-control-interface", "wasmtime"]
+js = ["boa_engine", "boa_gc", "boa_parser", "boa_ast", "boa_interner"]
+# Локальная решётка акторов и провайдеров в процессе (core::orchestrator)
+orchestration = ["wasm"]
 p2p = ["libp2p"]
 security = ["ed25519-dalek", "rand"]
 rendering = ["fontdue", "flate2", "brotli"]
-wasm = ["wasmtime", "prost", "oci-distribution"]
+wasm = ["wasmtime", "wasmtime-wasi", "cap-rand", "prost", "oci-distribution", "serde_json", "hmac", "rand"]
 reactive = ["dashmap"]
+native-plugins = ["libloading"]
+native-libs = ["libloading"]
 
 [profile.release]
 opt-level = "z"  # Минимальный размер кода
@@ -160,19 +193,440 @@ panic = "abort"  # Отключаем unwinding
 codegen-units = 1 # Улучшает оптимизацию, но увеличивает время компиляции
 === CARGO TOML END === */
 
+// === FILE: core\about.rs ===
+use crate::core::config::{mask_secrets, CliOptions, ConfigLayer, LayeredConfig};
+use crate::core::engine::{BroEngine, EngineEvent};
+use crate::core::installer::Installer;
+use crate::core::io_manager::{escape_html, Resource, SchemeHandler};
+use anyhow::Result;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::broadcast::error::RecvError;
+use url::Url;
+
+// Внутренние страницы about:*; история, закладки и счётчики событий
+// собираются подпиской на канал событий движка. История и закладки
+// сохраняются в ~/.cosmonaut/journal.toml
+
+const MAX_HISTORY: usize = 500;
+
+const PAGES: &[(&str, &str)] = &[
+    ("history", "История"),
+    ("bookmarks", "Закладки"),
+    ("cache", "HTTP-кэш"),
+    ("plugins", "Плагины и модули"),
+    ("config", "Конфигурация"),
+    ("events", "События движка"),
+];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub url: String,
+    pub visited: SystemTime,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Journal {
+    bookmarks: Vec<String>,
+    history: VecDeque<HistoryEntry>,
+    #[serde(skip)]
+    events: BTreeMap<&'static str, u64>,
+    #[serde(skip)]
+    lagged: u64,
+}
+
+impl Journal {
+    fn path() -> Result<PathBuf> {
+        Ok(Installer::get_cosmonaut_dir()?.join("journal.toml"))
+    }
+
+    // Повреждённый журнал не мешает запуску: начинаем с пустого
+    fn load() -> Self {
+        let path = match Self::path() {
+            Ok(path) if path.exists() => path,
+            _ => return Self::default(),
+        };
+        match fs::read_to_string(&path).map_err(anyhow::Error::from).and_then(|text| Ok(toml::from_str(&text)?)) {
+            Ok(journal) => journal,
+            Err(e) => {
+                log::warn!("Не удалось прочитать журнал {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    // Запись через временный файл, чтобы сбой не оставил журнал обрезанным
+    async fn save(text: String) -> Result<()> {
+        let path = Self::path()?;
+        let temp = path.with_extension("toml.tmp");
+        tokio::fs::write(&temp, text).await?;
+        tokio::fs::rename(&temp, &path).await?;
+        Ok(())
+    }
+
+    // Возвращает true, если изменились история или закладки
+    fn record(&mut self, event: &EngineEvent) -> bool {
+        *self.events.entry(event.name()).or_default() += 1;
+        match event {
+            EngineEvent::PageLoaded { url, .. } if !url.starts_with("about:") => {
+                self.history.push_front(HistoryEntry { url: url.clone(), visited: SystemTime::now() });
+                self.history.truncate(MAX_HISTORY);
+                true
+            }
+            EngineEvent::BookmarkAdded(url) if !self.bookmarks.contains(url) => {
+                self.bookmarks.push(url.clone());
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+// Закладки прошлых запусков для панели инструментов
+pub fn saved_bookmarks() -> Vec<String> {
+    Journal::load().bookmarks
+}
+
+pub struct AboutPages {
+    engine: Weak<BroEngine>,
+    config: LayeredConfig,
+    // Флаги запуска: about:config заново собирает слои, чтобы показать сохранённые значения
+    options: CliOptions,
+    started: Instant,
+    journal: Arc<Mutex<Journal>>,
+}
+
+impl AboutPages {
+    // Регистрирует схему about: в IoManager движка и начинает вести журнал событий
+    pub fn install(engine: &Arc<BroEngine>, config: LayeredConfig, options: CliOptions) -> Arc<Self> {
+        let pages = Arc::new(Self {
+            engine: Arc::downgrade(engine),
+            config,
+            options,
+            started: Instant::now(),
+            journal: Arc::new(Mutex::new(Journal::load())),
+        });
+        let journal = pages.journal.clone();
+        let mut events = engine.event_receiver();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => {
+                        let text = {
+                            let mut journal = journal.lock();
+                            if !journal.record(&event) {
+                                continue;
+                            }
+                            toml::to_string(&*journal)
+                        };
+                        match text {
+                            Ok(text) => {
+                                if let Err(e) = Journal::save(text).await {
+                                    log::warn!("Не удалось сохранить журнал: {}", e);
+                                }
+                            }
+                            Err(e) => log::warn!("Ошибка сериализации журнала: {}", e),
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => journal.lock().lagged += skipped,
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+        engine.io().register_scheme("about", pages.clone());
+        pages
+    }
+
+    fn index(&self) -> String {
+        let mut html = String::from("<ul>");
+        for (name, title) in PAGES {
+            html.push_str(&format!("<li><a href=\"about:{0}\">about:{0}</a> <span>{1}</span></li>", name, title));
+        }
+        html.push_str("</ul>");
+        html
+    }
+
+    fn history(&self) -> String {
+        let journal = self.journal.lock();
+        if journal.history.is_empty() {
+            return "<p>История пуста</p>".to_string();
+        }
+        let mut html = String::from("<ul>");
+        for entry in &journal.history {
+            html.push_str(&format!(
+                "<li>{} <span>{}</span></li>",
+                link(&entry.url),
+                format_ago(entry.visited.elapsed().unwrap_or_default())
+            ));
+        }
+        html.push_str("</ul>");
+        html
+    }
+
+    fn bookmarks(&self) -> String {
+        let journal = self.journal.lock();
+        if journal.bookmarks.is_empty() {
+            return "<p>Закладок нет</p>".to_string();
+        }
+        let mut html = String::from("<ul>");
+        for url in &journal.bookmarks {
+            html.push_str(&format!("<li>{}</li>", link(url)));
+        }
+        html.push_str("</ul>");
+        html
+    }
+
+    fn cache(&self, engine: &BroEngine) -> String {
+        let mut entries = engine.network().cache_entries();
+        if entries.is_empty() {
+            return "<p>HTTP-кэш пуст</p>".to_string();
+        }
+        entries.sort_by(|a, b| a.url.cmp(&b.url));
+        let total: usize = entries.iter().map(|entry| entry.size).sum();
+        let mut html = format!("<p>Записей: {}, объём: {} байт</p><ul>", entries.len(), total);
+        for entry in &entries {
+            html.push_str(&format!(
+                "<li>{} <span>{} · {} · {} байт · {} · сохранён {}</span></li>",
+                link(&entry.url),
+                entry.status,
+                escape_html(entry.content_type.as_deref().unwrap_or("?")),
+                entry.size,
+                if entry.fresh { "свежий" } else { "требует проверки" },
+                format_ago(entry.age)
+            ));
+        }
+        html.push_str("</ul>");
+        html
+    }
+
+    fn plugins(&self, engine: &BroEngine) -> String {
+        let mut html = String::from("<h2>Плагины</h2><ul>");
+        let plugins = engine.plugins().plugins();
+        if plugins.is_empty() {
+            html.push_str("<li>Плагины не загружены</li>");
+        }
+        for plugin in plugins {
+            html.push_str(&format!(
+                "<li>{} {} <span>{:?} · {} · {}</span></li>",
+                escape_html(&plugin.manifest.name),
+                escape_html(&plugin.manifest.version),
+                plugin.manifest.kind,
+                if plugin.enabled { "включён" } else { "отключён" },
+                escape_html(&plugin.manifest.description)
+            ));
+        }
+        html.push_str("</ul><h2>Библиотеки</h2><ul>");
+        let libraries = engine.libs().libraries();
+        if libraries.is_empty() {
+            html.push_str("<li>Библиотеки не найдены</li>");
+        }
+        for library in libraries {
+            html.push_str(&format!(
+                "<li>{} {} <span>{:?} · {} · поколение {} · аренд {} · {}</span></li>",
+                escape_html(&library.metadata.name),
+                escape_html(&library.metadata.version),
+                library.kind,
+                if library.loaded { "загружена" } else { "не загружена" },
+                library.generation,
+                library.leases,
+                escape_html(&library.path.display().to_string())
+            ));
+        }
+        html.push_str("</ul><h2>Модули</h2><ul>");
+        if let Ok(toml::Value::Table(modules)) = toml::Value::try_from(&self.config.config.modules) {
+            for (name, value) in modules {
+                let enabled = value.as_bool().unwrap_or(false);
+                html.push_str(&format!("<li>{} <span>{}</span></li>", escape_html(&name), if enabled { "включён" } else { "выключен" }));
+            }
+        }
+        html.push_str("</ul>");
+        html
+    }
+
+    // Слои перечитываются при каждом открытии: видны значения, сохранённые в настройках после запуска
+    fn config(&self) -> Result<String> {
+        let current = LayeredConfig::load(&self.options)?;
+        let mut value = toml::Value::try_from(&current.config)?;
+        mask_secrets(&mut value);
+        let text = toml::to_string_pretty(&value)?;
+        let mut html = format!(
+            "<p>Текущая конфигурация{}; модули и число потоков применяются после перезапуска</p><pre>{}</pre><h2>Источники значений</h2><ul>",
+            current.profile.as_deref().map(|p| format!(" (профиль {})", escape_html(p))).unwrap_or_default(),
+            escape_html(&text)
+        );
+        // Значения по умолчанию не перечисляются, чтобы были видны переопределения
+        for source in current.sources.iter().filter(|source| source.layer != ConfigLayer::Default) {
+            html.push_str(&format!(
+                "<li>{} = {} <span>{}</span></li>",
+                escape_html(&source.key),
+                escape_html(&source.value),
+                escape_html(&source.layer.to_string())
+            ));
+        }
+        html.push_str("</ul>");
+        Ok(html)
+    }
+
+    fn events(&self, engine: &BroEngine) -> String {
+        let journal = self.journal.lock();
+        let total: u64 = journal.events.values().sum();
+        let mut html = format!(
+            "<p>Время работы: {}, событий: {}, пропущено при переполнении канала: {}</p><ul>",
+            format_duration(self.started.elapsed()),
+            total,
+            journal.lagged
+        );
+        for (name, count) in &journal.events {
+            html.push_str(&format!("<li>{} <span>{}</span></li>", name, count));
+        }
+        html.push_str("</ul><h2>Планировщик</h2><ul>");
+        let stats = engine.scheduler_stats();
+        html.push_str(&format!("<li>Потоков CPU: {}, асинхронных слотов: {}</li>", stats.cpu_threads, stats.async_slots));
+        for (priority, stats) in &stats.priorities {
+            html.push_str(&format!(
+                "<li>{} <span>в очереди {} · выполняется {} · завершено {} · отменено {} · просрочено {} · ожидание {:?} · работа {:?}</span></li>",
+                priority.as_str(),
+                stats.queued,
+                stats.running,
+                stats.completed,
+                stats.cancelled,
+                stats.expired,
+                stats.avg_wait(),
+                stats.avg_run()
+            ));
+        }
+        html.push_str(&format!("</ul><p>Загрузок в процессе: {}</p>", engine.io().in_flight()));
+        html
+    }
+}
+
+#[async_trait]
+impl SchemeHandler for AboutPages {
+    async fn load(&self, url: &Url) -> Result<Resource> {
+        let page = url.path();
+        if page == "blank" {
+            return Ok(Resource::html(url.as_str(), "<html><head></head><body></body></html>"));
+        }
+        let engine = self.engine.upgrade().ok_or_else(|| anyhow::anyhow!("Движок остановлен"))?;
+        let body = match page {
+            "about" => self.index(),
+            "history" => self.history(),
+            "bookmarks" => self.bookmarks(),
+            "cache" => self.cache(&engine),
+            "plugins" => self.plugins(&engine),
+            "config" => self.config()?,
+            "events" => self.events(&engine),
+            _ => return Err(anyhow::anyhow!("Неизвестная страница about:{}", page)),
+        };
+        let title = PAGES.iter().find(|(name, _)| *name == page).map_or("Внутренние страницы", |(_, title)| *title);
+        Ok(Resource::html(url.as_str(), layout(page, title, &body)))
+    }
+}
+
+fn layout(page: &str, title: &str, body: &str) -> String {
+    let mut nav = String::from("<p><a href=\"about:about\">about:about</a>");
+    for (name, _) in PAGES.iter().filter(|(name, _)| *name != page) {
+        nav.push_str(&format!(" <a href=\"about:{0}\">about:{0}</a>", name));
+    }
+    nav.push_str("</p>");
+    format!(
+        "<html><head><title>about:{} — {}</title></head><body><h1>{}</h1>{}{}</body></html>",
+        page, title, title, nav, body
+    )
+}
+
+fn link(url: &str) -> String {
+    let url = escape_html(url);
+    format!("<a href=\"{0}\">{0}</a>", url)
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        s if s >= 3600 => format!("{} ч {} мин", s / 3600, s % 3600 / 60),
+        s if s >= 60 => format!("{} мин {} с", s / 60, s % 60),
+        s => format!("{} с", s),
+    }
+}
+
+fn format_ago(elapsed: Duration) -> String {
+    format!("{} назад", format_duration(elapsed))
+}
+
 // === FILE: core\config.rs ===
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs::{self, File};
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use dirs::home_dir;
-#[derive(Clone, Deserialize, Serialize)]
+
+// Префикс переменных окружения: YUAIBRO_SETTINGS__MAX_THREADS=8 -> settings.max_threads
+pub const ENV_PREFIX: &str = "YUAIBRO_";
+// Значения этих ключей маскируются в диагностике и about:config
+const SECRET_KEYS: &[&str] = &["token", "password", "secret"];
+
+// Отсутствующие ключи берутся из Default, поэтому старые файлы продолжают загружаться
+#[derive(Clone, Default, Deserialize, Serialize)]
 pub struct Config {
+    #[serde(default)]
     pub modules: ModulesConfig,
+    #[serde(default)]
     pub settings: Settings,
+    #[serde(default)]
+    pub registries: Vec<RegistryConfig>,
+    // Удалённая решётка для вкладки «Решётка»; используется при modules.wasmcloud_enabled
+    #[serde(default)]
+    pub lattice: Option<LatticeConfig>,
+    #[serde(default)]
+    pub plugins: PluginsConfig,
+}
+// [plugins] disabled = ["имя"]; нативные плагины загружаются только при allow_native
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct PluginsConfig {
+    #[serde(default)]
+    pub disabled: Vec<String>,
+    #[serde(default)]
+    pub allow_native: bool,
+}
+// [lattice] url = "http://127.0.0.1:4000", lattice = "default"
+#[derive(Clone, Deserialize, Serialize)]
+pub struct LatticeConfig {
+    pub url: String,
+    #[serde(default = "default_lattice_id")]
+    pub lattice: String,
+    #[serde(default)]
+    pub token: Option<String>,
+}
+fn default_lattice_id() -> String {
+    "default".to_string()
+}
+// OCI-реестр компонентов: [[registries]] host = "localhost:5000", insecure = true
+// Пароль хранится отдельно: password_file = "~/.cosmonaut/registry.pass" (права 0600)
+#[derive(Clone, Deserialize, Serialize)]
+pub struct RegistryConfig {
+    pub host: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    // Устаревший способ: читается для совместимости, но в settings.toml больше не записывается
+    #[serde(default, skip_serializing)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub password_file: Option<PathBuf>,
+    // HTTP без TLS, например локальный registry:2
+    #[serde(default)]
+    pub insecure: bool,
 }
 #[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
 pub struct ModulesConfig {
     pub core_enabled: bool,
     pub ui_enabled: bool,
@@ -188,6 +642,7 @@ pub struct ModulesConfig {
     pub reactive_enabled: bool,
 }
 #[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
 pub struct Settings {
     pub cache_enabled: bool,
     pub logging_level: String,
@@ -196,6 +651,8 @@ pub struct Settings {
     pub font_family: String,
 	pub scale_factor: Option<f32>,
 	pub graphics_backend: Option<String>,
+	// Шлюз для ipfs:// (по умолчанию https://ipfs.io)
+	pub ipfs_gateway: Option<String>,
 }
 impl Config {
     pub fn get_config_path() -> std::path::PathBuf {
@@ -204,6 +661,14 @@ impl Config {
             .join(".cosmonaut")
             .join("settings.toml")
     }
+    // Переопределения профиля: ~/.cosmonaut/profiles/<имя>.toml
+    pub fn get_profile_path(name: &str) -> PathBuf {
+        home_dir()
+            .unwrap_or_default()
+            .join(".cosmonaut")
+            .join("profiles")
+            .join(format!("{}.toml", name))
+    }
     pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self> {
         let path = path.as_ref();
         let config_str = fs::read_to_string(path)
@@ -213,14 +678,29 @@ impl Config {
         log::info!("Конфигурация загружена из {:?}", path);
         Ok(config)
     }
-    pub fn save_to_file(&self, path: impl AsRef<Path>) -> Result<()> {
+    // В файл попадают только правки относительно original: значения профиля, окружения
+    // и флагов (в том числе пароли из YUAIBRO_*) не сохраняются, если их не меняли
+    pub fn save_edits(&self, original: &Config, path: impl AsRef<Path>) -> Result<()> {
         let path = path.as_ref();
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?; // Создаём директорию, если не существует
         }
-        let mut file = File::create(path)?;
-        let toml = toml::to_string(self)
+        let mut table = match path.exists() {
+            true => match read_table(path)? {
+                toml::Value::Table(table) => table,
+                _ => toml::Table::new(),
+            },
+            false => toml::Table::new(),
+        };
+        let (toml::Value::Table(edited), toml::Value::Table(original)) =
+            (toml::Value::try_from(self)?, toml::Value::try_from(original)?)
+        else {
+            return Err(anyhow::anyhow!("Конфигурация должна быть таблицей"));
+        };
+        apply_edits(&mut table, &edited, &original);
+        let toml = toml::to_string(&table)
             .map_err(|e| anyhow::anyhow!("Ошибка сериализации конфигурации: {}", e))?;
+        let mut file = File::create(path)?;
         file.write_all(toml.as_bytes())?;
         log::info!("Конфигурация сохранена в {:?}", path);
         Ok(())
@@ -237,13 +717,318 @@ impl Config {
     }
 }
 
+impl Default for ModulesConfig {
+    fn default() -> Self {
+        Self {
+            core_enabled: true,
+            ui_enabled: true,
+            network_enabled: true,
+            dom_enabled: false,
+            js_enabled: false,
+            wasmcloud_enabled: false,
+            p2p_enabled: false,
+            vdom_enabled: false,
+            security_enabled: false,
+            rendering_enabled: false,
+            wasm_enabled: false,
+            reactive_enabled: false,
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            cache_enabled: true,
+            logging_level: "warn".to_string(),
+            max_threads: 4,
+            default_url: "https://yuai.ru".to_string(),
+            font_family: "System Monospace".to_string(),
+            scale_factor: Some(1.0),
+            graphics_backend: Some("auto".to_string()),
+            ipfs_gateway: None,
+        }
+    }
+}
+
+// Слой, из которого взято значение; слои применяются в порядке объявления
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigLayer {
+    Default,
+    File(PathBuf),
+    Profile(String),
+    Env(String),
+    Cli,
+}
+
+impl fmt::Display for ConfigLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "по умолчанию"),
+            Self::File(path) => write!(f, "файл {}", path.display()),
+            Self::Profile(name) => write!(f, "профиль {}", name),
+            Self::Env(var) => write!(f, "окружение {}", var),
+            Self::Cli => write!(f, "командная строка"),
+        }
+    }
+}
+
+// Флаги командной строки: --profile <имя>, --set <ключ>=<значение>, --config-sources
+#[derive(Clone, Debug, Default)]
+pub struct CliOptions {
+    pub profile: Option<String>,
+    pub overrides: Vec<(String, String)>,
+    pub print_sources: bool,
+}
+
+impl CliOptions {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+                _ => (arg.clone(), None),
+            };
+            let mut value = |name: &str| {
+                inline.clone().or_else(|| args.next()).ok_or_else(|| anyhow::anyhow!("Флаг {} требует значения", name))
+            };
+            match flag.as_str() {
+                "--profile" => options.profile = Some(value("--profile")?),
+                "--set" => {
+                    let assignment = value("--set")?;
+                    let (key, value) = assignment
+                        .split_once('=')
+                        .ok_or_else(|| anyhow::anyhow!("--set ожидает ключ=значение, получено {}", assignment))?;
+                    options.overrides.push((key.trim().to_string(), value.to_string()));
+                }
+                "--config-sources" => options.print_sources = true,
+                _ => log::warn!("Неизвестный аргумент командной строки: {}", arg),
+            }
+        }
+        Ok(options)
+    }
+}
+
+// Итоговое значение и слой, который его задал
+#[derive(Clone, Debug)]
+pub struct ConfigSource {
+    pub key: String,
+    pub value: String,
+    pub layer: ConfigLayer,
+}
+
+#[derive(Clone)]
+pub struct LayeredConfig {
+    pub config: Config,
+    pub profile: Option<String>,
+    pub sources: Vec<ConfigSource>,
+}
+
+impl LayeredConfig {
+    // Умолчания -> settings.toml -> профиль -> YUAIBRO_* -> флаги командной строки
+    pub fn load(options: &CliOptions) -> Result<Self> {
+        let mut sources = BTreeMap::new();
+        let mut merged = toml::Value::try_from(Config::default())?;
+        record_leaves("", &merged, &ConfigLayer::Default, &mut sources);
+
+        let path = Config::get_config_path();
+        if path.exists() {
+            let layer = ConfigLayer::File(path.clone());
+            merge(&mut merged, read_table(&path)?, "", &layer, &mut sources);
+        } else {
+            log::warn!("Файл конфигурации {} не найден, используются значения по умолчанию", path.display());
+        }
+
+        let profile = options.profile.clone().or_else(|| std::env::var(format!("{}PROFILE", ENV_PREFIX)).ok());
+        if let Some(name) = &profile {
+            let path = Config::get_profile_path(name);
+            if !path.exists() {
+                return Err(anyhow::anyhow!("Профиль {} не найден: {}", name, path.display()));
+            }
+            merge(&mut merged, read_table(&path)?, "", &ConfigLayer::Profile(name.clone()), &mut sources);
+        }
+
+        let mut env: Vec<(String, String)> = std::env::vars()
+            .filter(|(var, _)| var.starts_with(ENV_PREFIX) && var != &format!("{}PROFILE", ENV_PREFIX))
+            .collect();
+        env.sort();
+        for (var, raw) in env {
+            let key = var[ENV_PREFIX.len()..].to_ascii_lowercase().replace("__", ".");
+            set_override(&mut merged, &key, &raw, &ConfigLayer::Env(var), &mut sources)?;
+        }
+
+        for (key, raw) in &options.overrides {
+            set_override(&mut merged, key, raw, &ConfigLayer::Cli, &mut sources)?;
+        }
+
+        let config: Config = merged
+            .clone()
+            .try_into()
+            .map_err(|e| anyhow::anyhow!("Ошибка в конфигурации после применения слоёв: {}", e))?;
+        // Ключи, которых нет в Config, serde отбрасывает молча; опечатка в YUAIBRO_* или --set
+        // иначе осталась бы незамеченной
+        let known = toml::Value::try_from(&config)?;
+        sources.retain(|key, layer| {
+            let unknown = matches!(layer, ConfigLayer::Env(_) | ConfigLayer::Cli) && lookup(&known, key).is_none();
+            if unknown {
+                log::warn!("Неизвестный ключ конфигурации {} ({}) игнорируется", key, layer);
+            }
+            !unknown
+        });
+        mask_secrets(&mut merged);
+        let sources = sources
+            .into_iter()
+            .map(|(key, layer)| {
+                let value = lookup(&merged, &key).map(|value| value.to_string()).unwrap_or_default();
+                ConfigSource { key, value, layer }
+            })
+            .collect();
+        log::info!("Конфигурация собрана из слоёв{}", profile.as_deref().map(|p| format!(", профиль {}", p)).unwrap_or_default());
+        Ok(Self { config, profile, sources })
+    }
+
+    // Каждое значение и слой, который его задал
+    pub fn report(&self) -> String {
+        let width = self.sources.iter().map(|source| source.key.len() + source.value.len()).max().unwrap_or(0);
+        let mut report = String::new();
+        for source in &self.sources {
+            let assignment = format!("{} = {}", source.key, source.value);
+            report.push_str(&format!("{:<width$}  # {}\n", assignment, source.layer, width = width + 3));
+        }
+        report
+    }
+}
+
+fn read_table(path: &Path) -> Result<toml::Value> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Не удалось прочитать конфигурацию из {}: {}", path.display(), e))?;
+    let table: toml::Table = toml::from_str(&text)
+        .map_err(|e| anyhow::anyhow!("Ошибка парсинга конфигурации {}: {}", path.display(), e))?;
+    Ok(toml::Value::Table(table))
+}
+
+fn join_key(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() { key.to_string() } else { format!("{}.{}", prefix, key) }
+}
+
+fn record_leaves(key: &str, value: &toml::Value, layer: &ConfigLayer, sources: &mut BTreeMap<String, ConfigLayer>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (name, value) in table {
+                record_leaves(&join_key(key, name), value, layer, sources);
+            }
+        }
+        _ => {
+            sources.insert(key.to_string(), layer.clone());
+        }
+    }
+}
+
+// Таблицы сливаются по ключам, остальные значения (включая массивы) заменяются целиком
+fn merge(base: &mut toml::Value, overlay: toml::Value, key: &str, layer: &ConfigLayer, sources: &mut BTreeMap<String, ConfigLayer>) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (name, value) in overlay {
+                let child = join_key(key, &name);
+                match base.get_mut(&name) {
+                    Some(existing) => merge(existing, value, &child, layer, sources),
+                    None => {
+                        record_leaves(&child, &value, layer, sources);
+                        base.insert(name, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => {
+            sources.retain(|existing, _| !existing.starts_with(&format!("{}.", key)));
+            record_leaves(key, &overlay, layer, sources);
+            *base = overlay;
+        }
+    }
+}
+
+// Значение из строки: тип берётся у текущего значения ключа, иначе разбирается как литерал TOML
+fn set_override(
+    merged: &mut toml::Value,
+    key: &str,
+    raw: &str,
+    layer: &ConfigLayer,
+    sources: &mut BTreeMap<String, ConfigLayer>,
+) -> Result<()> {
+    if key.is_empty() || key.split('.').any(str::is_empty) {
+        return Err(anyhow::anyhow!("Недопустимый ключ конфигурации «{}» ({})", key, layer));
+    }
+    let value = match lookup(merged, key) {
+        Some(toml::Value::String(_)) => toml::Value::String(raw.to_string()),
+        _ => toml::from_str::<toml::Table>(&format!("value = {}", raw))
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or_else(|| toml::Value::String(raw.to_string())),
+    };
+    let mut overlay = value;
+    for name in key.rsplit('.') {
+        let mut table = toml::Table::new();
+        table.insert(name.to_string(), overlay);
+        overlay = toml::Value::Table(table);
+    }
+    merge(merged, overlay, "", layer, sources);
+    Ok(())
+}
+
+// Переносит в таблицу файла значения, изменённые относительно original; удалённые ключи убираются
+fn apply_edits(file: &mut toml::Table, edited: &toml::Table, original: &toml::Table) {
+    for (name, value) in edited {
+        match (value, original.get(name)) {
+            (toml::Value::Table(edited), Some(toml::Value::Table(original))) => {
+                let mut table = match file.remove(name) {
+                    Some(toml::Value::Table(table)) => table,
+                    _ => toml::Table::new(),
+                };
+                apply_edits(&mut table, edited, original);
+                if !table.is_empty() {
+                    file.insert(name.clone(), toml::Value::Table(table));
+                }
+            }
+            (value, original) if original != Some(value) => {
+                file.insert(name.clone(), value.clone());
+            }
+            _ => {}
+        }
+    }
+    for name in original.keys().filter(|name| !edited.contains_key(*name)) {
+        file.remove(name);
+    }
+}
+
+fn lookup<'a>(value: &'a toml::Value, key: &str) -> Option<&'a toml::Value> {
+    key.split('.').try_fold(value, |value, name| value.as_table()?.get(name))
+}
+
+pub fn mask_secrets(value: &mut toml::Value) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                if SECRET_KEYS.iter().any(|secret| key.to_ascii_lowercase().contains(secret)) {
+                    *value = toml::Value::String("••••••".to_string());
+                } else {
+                    mask_secrets(value);
+                }
+            }
+        }
+        toml::Value::Array(items) => items.iter_mut().for_each(mask_secrets),
+        _ => {}
+    }
+}
+
 // === FILE: core\engine.rs ===
 use anyhow::Result;
 use tokio::sync::broadcast::{channel, Sender as BroadcastSender, Receiver as BroadcastReceiver};
 use std::sync::{Arc, Mutex};
 use crate::dom::parser::FrameworkType;
-use reqwest::header::HeaderMap;
+use reqwest::header::{HeaderMap, HeaderValue, REFERER};
 use std::time::{Duration, Instant};
+use url::Url;
 
 use crate::core::config::Config;
 use crate::core::interfaces::{
@@ -251,25 +1036,118 @@ use crate::core::interfaces::{
     LibManagerTrait, YuaidbTrait, SecurityManagerTrait, ReactiveCoreTrait, SessionManagerTrait,
     ProfileManagerTrait, ServiceWorkerTrait, SchedulerTrait, IoManagerTrait, WasmManifestTrait,
 };
+use crate::core::policy::{HstsStore, PagePolicy, PolicyViolation, ResourceKind};
+use crate::core::interfaces::{Credentials, NetRequest, NetResponse};
+use crate::core::origin::Origin;
+use crate::core::permissions::{Permission, PermissionManager, PermissionState};
+use crate::core::yuaidb::{OriginStore, DEFAULT_ORIGIN_QUOTA, EXTENDED_ORIGIN_QUOTA};
+use crate::net::cors::{self, PreflightCache};
+use crate::core::js_runtime::{ConsoleMessage, HostRequest, RealmBindings, RealmOptions, ScriptError};
+use crate::core::web_storage::{StorageArea, StorageChange, StorageKind, WebStorage};
+use crate::core::page_state::PageId;
+use crate::core::io_manager::ResourceType;
+use crate::core::plugins::RequestDecision;
+use crate::core::scheduler::{run_task, SchedulerStats, TaskOptions, TaskPriority};
+use crate::dom::parser::ScriptKind;
+use crate::dom::live::{DomEvent, SharedDom};
+use crate::wasm_api::bindings::Bindings;
+use crate::wasm_api::cloud_bindings::{CloudBindings, RemoteLatticeEvent};
+use crate::wasm_api::sandbox::{Sandbox, WasmOutput};
+use std::collections::HashMap;
+
+fn subresource_type(kind: ResourceKind) -> ResourceType {
+    match kind {
+        ResourceKind::Script => ResourceType::Script,
+        ResourceKind::Style => ResourceType::Stylesheet,
+        ResourceKind::Image => ResourceType::Image,
+        ResourceKind::Frame => ResourceType::Document,
+        ResourceKind::Font | ResourceKind::Media | ResourceKind::Connect | ResourceKind::Object => ResourceType::Other,
+    }
+}
+
+// Срок доставки события пользователя обработчикам страницы
+const DOM_EVENT_TIMEOUT: Duration = Duration::from_secs(5);
+// Длина цепочки перенаправлений для запросов скриптов, как в fetch
+const MAX_REDIRECTS: usize = 20;
+
+pub fn referrer_headers(policy: &PagePolicy, target: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let referrer = Url::parse(target).ok().and_then(|target| policy.referrer_for(&target));
+    if let Some(value) = referrer.and_then(|r| HeaderValue::from_str(&r).ok()) {
+        headers.insert(REFERER, value);
+    }
+    headers
+}
 
 // События движка
 #[derive(Clone, Debug)]
 pub enum EngineEvent {
 	FrameworkDetected(FrameworkType),
-    PageLoaded,
+    // Документ страницы разобран и его скрипты запущены
+    PageLoaded { page: PageId, url: String },
     Error(String),
     ScriptExecuted,
     PluginLoaded(String),
     UrlRequest { url: String, response_tx: tokio::sync::mpsc::UnboundedSender<UrlResponse> },
     UrlResponse(UrlResponse),
+    PolicyViolation(PolicyViolation),
+    ConsoleMessage(ConsoleMessage),
+    // Запрос, выполненный от имени страницы (fetch, XMLHttpRequest)
+    SubresourceResponse { page: PageId, method: String, initiator: RequestInitiator, response: UrlResponse },
+    StorageChanged(StorageChange),
+    // stdout/stderr модуля, запущенного в песочнице
+    WasmOutput(WasmOutput),
+    // Событие удалённой решётки из CloudBindings::watch
+    Lattice(RemoteLatticeEvent),
+    BookmarkAdded(String),
 }
 
-// Структура ответа сети
-#[derive(Clone, Debug)]
-pub struct UrlResponse {
-    pub url: String,
-    pub html: String,
-    pub headers: HeaderMap,
+impl EngineEvent {
+    // Имя варианта для статистики about:events
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::FrameworkDetected(_) => "FrameworkDetected",
+            Self::PageLoaded { .. } => "PageLoaded",
+            Self::Error(_) => "Error",
+            Self::ScriptExecuted => "ScriptExecuted",
+            Self::PluginLoaded(_) => "PluginLoaded",
+            Self::UrlRequest { .. } => "UrlRequest",
+            Self::UrlResponse(_) => "UrlResponse",
+            Self::PolicyViolation(_) => "PolicyViolation",
+            Self::ConsoleMessage(_) => "ConsoleMessage",
+            Self::SubresourceResponse { .. } => "SubresourceResponse",
+            Self::StorageChanged(_) => "StorageChanged",
+            Self::WasmOutput(_) => "WasmOutput",
+            Self::Lattice(_) => "Lattice",
+            Self::BookmarkAdded(_) => "BookmarkAdded",
+        }
+    }
+}
+
+// Кто инициировал сетевой запрос; отображается во вкладке «Сеть»
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestInitiator {
+    Document,
+    Fetch,
+    XmlHttpRequest,
+}
+
+impl RequestInitiator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Document => "document",
+            Self::Fetch => "fetch",
+            Self::XmlHttpRequest => "xhr",
+        }
+    }
+}
+
+// Структура ответа сети
+#[derive(Clone, Debug)]
+pub struct UrlResponse {
+    pub url: String,
+    pub html: String,
+    pub headers: HeaderMap,
     pub status: u16,
     pub duration: Duration,
 }
@@ -290,6 +1168,17 @@ pub struct BroEngine {
     scheduler: Arc<dyn SchedulerTrait + Send + Sync>,
     io_manager: Arc<dyn IoManagerTrait + Send + Sync>,
     wasm_manifest: Arc<dyn WasmManifestTrait + Send + Sync>,
+    hsts: HstsStore,
+    preflight_cache: PreflightCache,
+    permissions: Arc<PermissionManager>,
+    page_doms: parking_lot::Mutex<HashMap<PageId, SharedDom>>,
+    page_policies: parking_lot::Mutex<HashMap<PageId, Arc<PagePolicy>>>,
+    web_storage: WebStorage,
+    origin_stores: parking_lot::Mutex<HashMap<String, Arc<OriginStore>>>,
+    page_storage: parking_lot::Mutex<HashMap<PageId, Vec<Arc<StorageArea>>>>,
+    sandbox: Arc<Sandbox>,
+    bindings: Arc<Bindings>,
+    cloud: Option<Arc<CloudBindings>>,
     tx: BroadcastSender<EngineEvent>, // Broadcast Sender для событий
     rx: Arc<Mutex<BroadcastReceiver<EngineEvent>>>, // Обернутый Receiver для shared mutable доступа
 }
@@ -311,6 +1200,8 @@ impl BroEngine {
         scheduler: Arc<dyn SchedulerTrait + Send + Sync>,
         io_manager: Arc<dyn IoManagerTrait + Send + Sync>,
         wasm_manifest: Arc<dyn WasmManifestTrait + Send + Sync>,
+        bindings: Arc<Bindings>,
+        cloud: Option<Arc<CloudBindings>>,
         _config: Option<Config>,
         _tx: Option<BroadcastSender<EngineEvent>>,
         _rx: Option<BroadcastReceiver<EngineEvent>>,
@@ -320,6 +1211,10 @@ impl BroEngine {
             _ => channel(1024), // Broadcast канал с буфером
         };
         let rx = Arc::new(Mutex::new(rx)); // Оборачиваем в Arc<Mutex> для shared доступа
+        let hsts = HstsStore::new(db.clone());
+        let permissions = Arc::new(PermissionManager::new(db.clone()));
+        let web_storage = WebStorage::new();
+        let sandbox = Arc::new(Sandbox::new(wasm_runtime.clone(), tx.clone())?);
         Ok(Self {
             network,
             js_runtime,
@@ -336,6 +1231,17 @@ impl BroEngine {
             scheduler,
             io_manager,
             wasm_manifest,
+            hsts,
+            preflight_cache: PreflightCache::new(),
+            permissions,
+            page_doms: parking_lot::Mutex::new(HashMap::new()),
+            page_policies: parking_lot::Mutex::new(HashMap::new()),
+            web_storage,
+            origin_stores: parking_lot::Mutex::new(HashMap::new()),
+            page_storage: parking_lot::Mutex::new(HashMap::new()),
+            sandbox,
+            bindings,
+            cloud,
             tx,
             rx,
         })
@@ -347,6 +1253,66 @@ impl BroEngine {
         }
     }
 
+    pub fn sandbox(&self) -> Arc<Sandbox> {
+        self.sandbox.clone()
+    }
+
+    pub fn bindings(&self) -> Arc<Bindings> {
+        self.bindings.clone()
+    }
+
+    pub fn plugins(&self) -> Arc<dyn PluginManagerTrait + Send + Sync> {
+        self.plugin_manager.clone()
+    }
+
+    // Перехватчики запросов плагинов: блокировка или перенаправление до обращения к сети.
+    // Адрес перенаправления проходит те же проверки, что и исходный: только http(s)
+    // и, для запросов страницы, её CSP
+    async fn intercept(&self, url: String, method: &str, check: Option<(&PagePolicy, ResourceKind)>) -> Result<String> {
+        match self.plugin_manager.intercept_request(&url, method).await {
+            RequestDecision::Allow => Ok(url),
+            RequestDecision::Redirect { url: target } => {
+                let parsed = Url::parse(&target)
+                    .map_err(|e| anyhow::anyhow!("Плагин перенаправил {} на некорректный адрес {}: {}", url, target, e))?;
+                if !matches!(parsed.scheme(), "http" | "https") {
+                    return Err(anyhow::anyhow!("Плагин перенаправил {} на недопустимый адрес {}", url, target));
+                }
+                match check {
+                    Some((policy, kind)) => policy
+                        .check_resource(kind, parsed.as_str())
+                        .map(|allowed| allowed.to_string())
+                        .map_err(|violation| {
+                            self.report_violation(violation.clone());
+                            anyhow::Error::from(violation)
+                        }),
+                    None => Ok(parsed.to_string()),
+                }
+            }
+            RequestDecision::Block { reason } => Err(anyhow::anyhow!("Запрос {} заблокирован плагином: {}", url, reason)),
+        }
+    }
+
+    pub fn scheduler_stats(&self) -> SchedulerStats {
+        self.scheduler.stats()
+    }
+
+    // None, если удалённая решётка не настроена
+    pub fn cloud(&self) -> Option<Arc<CloudBindings>> {
+        self.cloud.clone()
+    }
+
+    pub fn io(&self) -> Arc<dyn IoManagerTrait + Send + Sync> {
+        self.io_manager.clone()
+    }
+
+    pub fn network(&self) -> Arc<dyn NetworkTrait + Send + Sync> {
+        self.network.clone()
+    }
+
+    pub fn libs(&self) -> Arc<dyn LibManagerTrait + Send + Sync> {
+        self.lib_manager.clone()
+    }
+
     pub fn receive_event(&self) -> Option<EngineEvent> {
         self.rx.lock().unwrap().try_recv().ok() // Неблокирующий приём через lock
     }
@@ -357,28 +1323,32 @@ impl BroEngine {
 
     // Централизованный метод для выполнения сетевых запросов
     pub async fn fetch_url(&self, url: String) -> Result<UrlResponse> {
-        // Проверка валидности URL
-        if url.is_empty() || url == "about:blank" {
+        // Проверка валидности URL; about: обслуживается IoManager
+        if url.is_empty() {
             return Err(anyhow::anyhow!("Недопустимый URL: {}", url));
         }
 
+        let url = self.intercept(url, "GET", None).await?;
+        // Хосты из HSTS-списка всегда запрашиваются по HTTPS
+        let url = self.hsts.upgrade_url(&url).await;
         let start_time = Instant::now();
         log::info!("Отправка сетевого запроса для URL: {}", url);
 
-        // Выполнение запроса через NetworkTrait
-        let (html, headers) = self
-            .network
-            .fetch_html_with_headers(&url)
+        // Загрузка через IoManager: схема URL выбирает обработчик
+        let resource = self
+            .io_manager
+            .load(&url, ResourceType::Document)
             .await
             .map_err(|e| anyhow::anyhow!("Ошибка сетевого запроса для URL {}: {}", url, e))?;
+        self.hsts.record(&url, &resource.headers).await;
 
         let duration = start_time.elapsed();
-        let status = 200; // Предполагаем успешный статус, так как fetch_html_with_headers не возвращает статус явно
+        let status = resource.status;
 
         let response = UrlResponse {
             url: url.clone(),
-            html,
-            headers,
+            html: resource.document_html(),
+            headers: resource.headers,
             status,
             duration,
         };
@@ -394,6 +1364,402 @@ impl BroEngine {
 
         Ok(response)
     }
+
+    pub fn report_violation(&self, violation: PolicyViolation) {
+        self.send_event(EngineEvent::PolicyViolation(violation));
+    }
+
+    // Загрузка подресурса страницы с учётом CSP и смешанного содержимого;
+    // Referer выставляется по Referrer-Policy документа
+    pub async fn fetch_subresource(&self, policy: &PagePolicy, kind: ResourceKind, url: &str) -> Result<UrlResponse> {
+        let allowed_url = policy.check_resource(kind, url).map_err(|violation| {
+            self.report_violation(violation.clone());
+            anyhow::Error::from(violation)
+        })?;
+        let url = self.intercept(allowed_url.to_string(), "GET", Some((policy, kind))).await?;
+        let url = self.hsts.upgrade_url(&url).await;
+        let start_time = Instant::now();
+        let resource = self
+            .io_manager
+            .load_with_headers(&url, subresource_type(kind), referrer_headers(policy, &url))
+            .await
+            .map_err(|e| anyhow::anyhow!("Ошибка загрузки подресурса {}: {}", url, e))?;
+        self.hsts.record(&url, &resource.headers).await;
+        let response = UrlResponse {
+            url: resource.url.clone(),
+            html: resource.text(),
+            headers: resource.headers,
+            status: resource.status,
+            duration: start_time.elapsed(),
+        };
+        self.send_event(EngineEvent::UrlResponse(response.clone()));
+        Ok(response)
+    }
+
+    // Встроенный скрипт передаётся в JsRuntimeTrait только если его разрешает CSP страницы
+    pub async fn run_inline_script(
+        &self,
+        page: PageId,
+        policy: &PagePolicy,
+        kind: &ScriptKind,
+        script: &str,
+        nonce: Option<&str>,
+    ) -> Result<()> {
+        policy
+            .check_inline(ResourceKind::Script, script, nonce)
+            .map_err(|violation| {
+                self.report_violation(violation.clone());
+                anyhow::Error::from(violation)
+            })?;
+        self.run_script_source(page, kind, script).await
+    }
+
+    // Источник уже прошёл проверки политики (встроенный или загруженный через fetch_subresource)
+    pub async fn run_script_source(&self, page: PageId, kind: &ScriptKind, source: &str) -> Result<()> {
+        match kind {
+            ScriptKind::Classic => {
+                self.evaluate_in_page(page, source).await?;
+            }
+            ScriptKind::Module => {
+                if let Err(e) = self.js_runtime.evaluate_module(page, source).await {
+                    self.send_event(EngineEvent::ConsoleMessage(ConsoleMessage {
+                        page,
+                        level: crate::core::js_runtime::ConsoleLevel::Error,
+                        text: format!("Uncaught {}", e),
+                    }));
+                    return Err(e.into());
+                }
+            }
+            ScriptKind::Data(_) => return Ok(()),
+        }
+        self.send_event(EngineEvent::ScriptExecuted);
+        Ok(())
+    }
+
+    // Новый realm заменяет прежний: глобальное состояние страницы не переживает навигацию
+    pub async fn create_page_realm(&self, page: PageId, mut options: RealmOptions) -> Result<()> {
+        // Без 'unsafe-eval' в CSP страницы eval и конструктор Function недоступны
+        if let Some(policy) = self.page_policies.lock().get(&page) {
+            options.block_eval |= !policy.allows_eval();
+        }
+        let dom = self.page_doms.lock().get(&page).cloned();
+        let (local_storage, session_storage) = match self.page_origin(page) {
+            Some(origin) if !origin.is_opaque() => {
+                let local = match self.origin_storage(&origin).await {
+                    Ok(store) => self.web_storage.local(&origin, store).await,
+                    Err(e) => Err(e),
+                };
+                let local = match local {
+                    Ok(area) => Some(area),
+                    Err(e) => {
+                        log::warn!("localStorage страницы {} недоступен: {}", page, e);
+                        None
+                    }
+                };
+                (local, self.web_storage.session(page, &origin).ok())
+            }
+            _ => (None, None),
+        };
+        let areas = local_storage.iter().chain(session_storage.iter()).cloned().collect();
+        self.page_storage.lock().insert(page, areas);
+        let bindings = RealmBindings { dom, local_storage, session_storage };
+        self.js_runtime.create_realm(page, options, bindings).await
+    }
+
+    pub async fn destroy_page_realm(&self, page: PageId) {
+        self.page_doms.lock().remove(&page);
+        self.page_policies.lock().remove(&page);
+        self.page_storage.lock().remove(&page);
+        self.web_storage.drop_session(page);
+        self.js_runtime.destroy_realm(page).await
+    }
+
+    // Документ страницы, который получит следующий realm через create_page_realm
+    pub fn set_page_dom(&self, page: PageId, dom: SharedDom) {
+        self.page_doms.lock().insert(page, dom);
+    }
+
+    // Политика документа задаёт базовый URL, источник и connect-src для запросов скриптов
+    pub fn set_page_policy(&self, page: PageId, policy: Arc<PagePolicy>) {
+        self.page_policies.lock().insert(page, policy);
+    }
+
+    pub async fn fetch_for_page(&self, page: PageId, mut request: NetRequest, initiator: RequestInitiator) -> Result<NetResponse> {
+        let policy = self.page_policies.lock().get(&page).cloned().unwrap_or_default();
+        let url = policy.check_resource(ResourceKind::Connect, &request.url).map_err(|violation| {
+            self.report_violation(violation.clone());
+            anyhow::Error::from(violation)
+        })?;
+        request.url = self.intercept(url.to_string(), &request.method, Some((&policy, ResourceKind::Connect))).await?;
+        // Referer — запрещённый для скриптов заголовок: его задаёт только политика документа
+        request.headers.remove(REFERER);
+        request.headers.extend(referrer_headers(&policy, &request.url));
+        let origin = policy.page_url().map(Origin::from_url).unwrap_or_else(|| Origin::parse(""));
+        let method = request.method.clone();
+        let start_time = Instant::now();
+        let result = self.fetch_cors(&origin, request).await;
+        let response = match &result {
+            Ok(response) => UrlResponse {
+                url: response.url.clone(),
+                html: String::from_utf8_lossy(&response.body).into_owned(),
+                headers: response.headers.clone(),
+                status: response.status,
+                duration: start_time.elapsed(),
+            },
+            Err(e) => UrlResponse {
+                url: url.to_string(),
+                html: e.to_string(),
+                headers: HeaderMap::new(),
+                status: 0,
+                duration: start_time.elapsed(),
+            },
+        };
+        self.send_event(EngineEvent::SubresourceResponse { page, method, initiator, response });
+        result
+    }
+
+    fn page_origin(&self, page: PageId) -> Option<Origin> {
+        let policies = self.page_policies.lock();
+        policies.get(&page)?.page_url().map(Origin::from_url)
+    }
+
+    // Области Web Storage, открытые страницей (для вкладки «Хранилище»)
+    pub fn page_storage(&self, page: PageId) -> Vec<Arc<StorageArea>> {
+        self.page_storage.lock().get(&page).cloned().unwrap_or_default()
+    }
+
+    // Изменение хранилища сохраняется и рассылается остальным страницам источника как событие storage
+    pub async fn storage_changed(&self, mut change: StorageChange) {
+        if let Some(source) = change.source {
+            if let Some(url) = self.page_policies.lock().get(&source).and_then(|p| p.page_url().cloned()) {
+                change.url = url.to_string();
+            }
+        }
+        // Несохранённое изменение уже откачено и другим страницам не рассылается
+        if let Err(e) = self.web_storage.persist(&change).await {
+            log::error!("Не удалось сохранить {} для {}: {}", change.kind.as_str(), change.partition, e);
+            if let Some(page) = change.source {
+                self.send_event(EngineEvent::ConsoleMessage(ConsoleMessage {
+                    page,
+                    level: crate::core::js_runtime::ConsoleLevel::Error,
+                    text: format!("Изменение {} не сохранено и отменено: {}", change.kind.as_str(), e),
+                }));
+            }
+            return;
+        }
+        self.send_event(EngineEvent::StorageChanged(change.clone()));
+        if change.kind != StorageKind::Local {
+            return;
+        }
+        let detail = serde_json::json!({
+            "key": change.key,
+            "oldValue": change.old_value,
+            "newValue": change.new_value,
+            "url": change.url,
+            "storageArea": change.kind.as_str(),
+        });
+        let targets: Vec<PageId> = self
+            .page_policies
+            .lock()
+            .iter()
+            .filter(|(page, _)| Some(**page) != change.source)
+            .filter(|(_, policy)| {
+                policy.page_url().and_then(|url| Origin::from_url(url).partition_key()).as_deref()
+                    == Some(change.partition.as_str())
+            })
+            .map(|(page, _)| *page)
+            .collect();
+        // Задачи ставятся в очередь страниц сразу и по порядку, а их выполнение не задерживает
+        // сохранение следующих изменений
+        for page in targets {
+            let script = format!("__yuaiStorageEvent({})", detail);
+            let done = self.scheduler.schedule_js_task(page, &script, TaskOptions::new(TaskPriority::Page));
+            tokio::spawn(async move {
+                if let Err(e) = done.await {
+                    log::debug!("Событие storage не доставлено странице {}: {}", page, e);
+                }
+            });
+        }
+    }
+
+    // Обслуживание запросов JS realm. Сетевые запросы выполняются параллельно,
+    // изменения хранилища — строго по порядку
+    pub async fn serve_host_requests(self: Arc<Self>, mut requests: tokio::sync::mpsc::UnboundedReceiver<HostRequest>) {
+        while let Some(request) = requests.recv().await {
+            match request {
+                HostRequest::Fetch { page, id, request, initiator, reply } => {
+                    let engine = self.clone();
+                    tokio::spawn(async move {
+                        let result = engine
+                            .fetch_for_page(page, request, initiator)
+                            .await
+                            .map_err(|e| e.to_string());
+                        let _ = reply.send((id, result));
+                    });
+                }
+                HostRequest::StorageChanged(change) => self.storage_changed(change).await,
+                // Диалог разрешения не должен задерживать сохранение хранилища
+                HostRequest::QuotaExceeded { page } => {
+                    let engine = self.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = engine.request_storage_quota(page).await {
+                            log::warn!("Запрос квоты хранилища для страницы {}: {}", page, e);
+                        }
+                    });
+                }
+                HostRequest::EvalBlocked { page, sample } => {
+                    let policy = self.page_policies.lock().get(&page).cloned().unwrap_or_default();
+                    self.report_violation(policy.eval_violation(&sample));
+                }
+            }
+        }
+    }
+
+    // true, если обработчики не вызвали preventDefault и действие по умолчанию выполняется
+    pub async fn dispatch_dom_event(self: Arc<Self>, page: PageId, event: DomEvent) -> Result<bool> {
+        let dom = self
+            .page_doms
+            .lock()
+            .get(&page)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("У страницы {} нет документа", page))?;
+        dom.write().apply_event(&event)?;
+        // Обработчики выполняются в очереди realm между таймерами страницы; ввод пользователя
+        // обрабатывается раньше страничных и фоновых задач, а зависший обработчик не держит доставку
+        let script = format!("__yuaiDispatch({}, {:?})", event.node.0, event.kind.event_type());
+        let engine = self.clone();
+        let options = TaskOptions::new(TaskPriority::UiCritical).with_timeout(DOM_EVENT_TIMEOUT);
+        let result = run_task(self.scheduler.as_ref(), options, async move {
+            engine.evaluate_in_page(page, &script).await.map_err(|e| anyhow::anyhow!("{}", e))
+        })
+        .await?;
+        Ok(result != "false")
+    }
+
+    // Ошибки скриптов страницы дублируются в консоль devtools
+    pub async fn evaluate_in_page(&self, page: PageId, script: &str) -> std::result::Result<String, ScriptError> {
+        let result = self.js_runtime.evaluate(page, script).await;
+        if let Err(e) = &result {
+            self.send_event(EngineEvent::ConsoleMessage(ConsoleMessage {
+                page,
+                level: crate::core::js_runtime::ConsoleLevel::Error,
+                text: format!("Uncaught {}", e),
+            }));
+        }
+        result
+    }
+
+    pub fn permissions(&self) -> Arc<PermissionManager> {
+        self.permissions.clone()
+    }
+
+    // Раздел хранилища источника, один на источник; увеличенная квота требует разрешения StorageQuota
+    pub async fn origin_storage(&self, origin: &Origin) -> Result<Arc<OriginStore>> {
+        let partition = origin
+            .partition_key()
+            .ok_or_else(|| anyhow::anyhow!("Непрозрачный источник не имеет доступа к хранилищу"))?;
+        if let Some(store) = self.origin_stores.lock().get(&partition) {
+            return Ok(store.clone());
+        }
+        let quota = match self.permissions.query(origin, Permission::StorageQuota).await? {
+            PermissionState::Granted => EXTENDED_ORIGIN_QUOTA,
+            _ => DEFAULT_ORIGIN_QUOTA,
+        };
+        let store = Arc::new(OriginStore::new(self.db.clone(), origin, quota)?);
+        Ok(self.origin_stores.lock().entry(partition).or_insert(store).clone())
+    }
+
+    // Страница упёрлась в квоту: пользователь решает, дать ли источнику больше места
+    pub async fn request_storage_quota(&self, page: PageId) -> Result<()> {
+        let origin = self
+            .page_origin(page)
+            .ok_or_else(|| anyhow::anyhow!("У страницы {} нет источника", page))?;
+        if self.permissions.request(&origin, Permission::StorageQuota).await? {
+            self.origin_storage(&origin).await?.set_quota(EXTENDED_ORIGIN_QUOTA);
+            log::info!("Квота хранилища {} увеличена", origin);
+        }
+        Ok(())
+    }
+
+    // Запрос от имени документа: чужие источники проходят CORS с preflight при необходимости.
+    // Перенаправления разбираются здесь, чтобы каждый переход проходил ту же проверку
+    pub async fn fetch_cors(&self, origin: &Origin, mut request: NetRequest) -> Result<NetResponse> {
+        let mode = request.credentials;
+        request.follow_redirects = false;
+        // После перехода на чужой источник CORS проверяется до конца цепочки
+        let mut cross_origin = false;
+        for _ in 0..=MAX_REDIRECTS {
+            request.url = self.hsts.upgrade_url(&request.url).await;
+            let target = url::Url::parse(&request.url)
+                .map_err(|e| anyhow::anyhow!("Недопустимый URL {}: {}", request.url, e))?;
+            cross_origin |= !origin.is_same_origin_url(&target);
+            let mut hop = request.clone();
+            hop.credentials = match (mode, cross_origin) {
+                (Credentials::SameOrigin, false) => Credentials::Include,
+                (Credentials::SameOrigin, true) => Credentials::Omit,
+                (mode, _) => mode,
+            };
+            let mut response = if cross_origin {
+                self.fetch_cross_origin(origin, hop).await?
+            } else {
+                self.network.fetch_request(hop).await?
+            };
+            self.hsts.record(&response.url, &response.headers).await;
+
+            let location = response.headers.get("location").and_then(|value| value.to_str().ok()).map(str::to_string);
+            let next = match (response.status, location) {
+                (301 | 302 | 303 | 307 | 308, Some(location)) => target
+                    .join(&location)
+                    .map_err(|e| anyhow::anyhow!("Недопустимое перенаправление {} -> {}: {}", target, location, e))?,
+                _ => {
+                    if cross_origin {
+                        response.headers = cors::filter_response_headers(&response.headers);
+                    }
+                    return Ok(response);
+                }
+            };
+            if !matches!(next.scheme(), "http" | "https") {
+                return Err(self.cors_violation(origin, next.as_str(), "Перенаправление возможно только на http(s)".to_string()));
+            }
+            if response.status == 303 || (matches!(response.status, 301 | 302) && request.method == "POST") {
+                request.method = "GET".to_string();
+                request.body = None;
+            }
+            log::debug!("Перенаправление {} -> {}", request.url, next);
+            request.url = next.to_string();
+        }
+        Err(anyhow::anyhow!("Слишком много перенаправлений: {}", request.url))
+    }
+
+    // Один переход к чужому источнику: preflight при необходимости и проверка ответа,
+    // в том числе ответа-перенаправления
+    async fn fetch_cross_origin(&self, origin: &Origin, mut request: NetRequest) -> Result<NetResponse> {
+        if cors::needs_preflight(&request) && !self.preflight_cache.is_allowed(origin, &request) {
+            let preflight = self.network.fetch_request(cors::preflight_request(origin, &request)).await?;
+            if let Err(message) = self.preflight_cache.record(origin, &request, &preflight) {
+                return Err(self.cors_violation(origin, &request.url, message));
+            }
+        }
+
+        let (url, credentials) = (request.url.clone(), request.credentials);
+        cors::insert_header(&mut request.headers, "origin", &origin.ascii_serialization());
+        let response = self.network.fetch_request(request).await?;
+        if let Err(message) = cors::check_response(origin, &response, credentials) {
+            return Err(self.cors_violation(origin, &url, message));
+        }
+        Ok(response)
+    }
+
+    fn cors_violation(&self, origin: &Origin, url: &str, message: String) -> anyhow::Error {
+        let violation = PolicyViolation {
+            page_url: origin.ascii_serialization(),
+            blocked_url: url.to_string(),
+            directive: "cors".to_string(),
+            message,
+        };
+        self.report_violation(violation.clone());
+        violation.into()
+    }
 }
 
 // === FILE: core\event_loop.rs ===
@@ -547,6 +1913,7 @@ use std::fs::{create_dir_all, File};
 use std::io::Write;
 use std::path::PathBuf;
 use log::info;
+use crate::core::config::Config;
 pub struct Installer;
 impl Installer {
     pub fn get_cosmonaut_dir() -> Result<PathBuf> {
@@ -561,6 +1928,7 @@ impl Installer {
         let plugins_dir = cosmonaut_dir.join("plugins");
         let providers_dir = cosmonaut_dir.join("providers");
         let libs_dir = cosmonaut_dir.join("libs");
+        let profiles_dir = cosmonaut_dir.join("profiles");
         let models_dir = cosmonaut_dir.join("models");
         let devtools_dir = cosmonaut_dir.join("devtools");
         let wasm_manifests_dir = cosmonaut_dir.join("wasm_manifests");
@@ -576,6 +1944,7 @@ impl Installer {
             plugins_dir,
             providers_dir,
             libs_dir,
+            profiles_dir,
             models_dir,
             devtools_dir,
             wasm_manifests_dir,
@@ -592,28 +1961,16 @@ impl Installer {
         }
         let settings_path = cosmonaut_dir.join("settings.toml");
         if !settings_path.exists() {
-            let default_settings = r#"
-[modules]
-core_enabled = true
-ui_enabled = true
-network_enabled = true
-dom_enabled = false
-js_enabled = false
-wasmcloud_enabled = false
-p2p_enabled = false
-vdom_enabled = false
-security_enabled = false
-rendering_enabled = false
-wasm_enabled = false
-reactive_enabled = false
-[settings]
-cache_enabled = true
-logging_level = "warn"
-max_threads = 4
-default_url = "https://yuai.ru"
-font_family = "System Monospace"
-graphics_backend = "auto"  # auto, vulkan, dx12, metal, gl
-"#;
+            // Файл содержит все ключи со значениями по умолчанию, включая scale_factor
+            let defaults = toml::to_string_pretty(&Config::default())
+                .context("Не удалось сериализовать конфигурацию по умолчанию")?;
+            let default_settings = format!(
+                "# Конфигурация Cosmonaut. Переопределения: ~/.cosmonaut/profiles/<имя>.toml,\n\
+                 # переменные {}<РАЗДЕЛ>__<КЛЮЧ>, флаги --profile и --set ключ=значение.\n\
+                 # graphics_backend: auto, vulkan, dx12, metal, gl\n\n{}",
+                crate::core::config::ENV_PREFIX,
+                defaults
+            );
             let mut file = File::create(&settings_path)
                 .with_context(|| format!("Не удалось создать файл {}", settings_path.display()))?;
             file.write_all(default_settings.as_bytes())
@@ -635,11 +1992,76 @@ use reqwest::header::HeaderMap;
 use egui::Ui; // Необходимо для метода incremental_render
 use serde_json::Value as JsonValue; // Необходимо для методов create_signals/stores
 use crate::dom::parser::ParsedNode; // Необходимо для методов bind/apply/load
+use crate::wasm_api::signals::PageDocument;
+use crate::core::js_runtime::{RealmBindings, RealmOptions, ScriptError};
+use crate::core::page_state::PageId;
+use crate::core::runtime::{InstanceId, WasmValue};
+use crate::core::wasm_manifest::ComponentManifest;
+use crate::core::config::Config;
+use crate::core::engine::EngineEvent;
+use crate::core::libs::{LibraryInfo, LibraryLease};
+use crate::core::io_manager::{Resource, ResourceType, SchemeHandler};
+use crate::net::cache::CacheEntryInfo;
+use crate::core::orchestrator::{LatticeEvent, LatticeInventory};
+use crate::core::plugins::{PanelContent, PluginButton, PluginInfo, RequestDecision, SidePanelSpec};
+use crate::core::scheduler::{CancelToken, SchedulerStats, TaskHandle, TaskOptions};
+use futures::future::BoxFuture;
+use crate::core::provider::{CapabilityProvider, LinkDefinition};
+use crate::wasm_api::provider_api::ActorCall;
+use crate::wasm_api::sandbox::SandboxConfig;
+use std::collections::BTreeMap;
+
+// Режим credentials из Fetch: cookie отправляются и сохраняются только при Include.
+// SameOrigin движок сводит к Include или Omit по источнику запроса до отправки
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Credentials {
+    Omit,
+    SameOrigin,
+    Include,
+}
+
+// Произвольный HTTP-запрос (CORS, preflight, подресурсы)
+#[derive(Clone, Debug)]
+pub struct NetRequest {
+    pub url: String,
+    pub method: String,
+    pub headers: HeaderMap,
+    pub body: Option<Vec<u8>>,
+    pub credentials: Credentials,
+    // Без перенаправлений ответ 3xx возвращается как есть (preflight, WASM-компоненты)
+    pub follow_redirects: bool,
+}
+
+impl NetRequest {
+    // Навигация и подресурсы документа идут с cookie
+    pub fn get(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            method: "GET".to_string(),
+            headers: HeaderMap::new(),
+            body: None,
+            credentials: Credentials::Include,
+            follow_redirects: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NetResponse {
+    pub url: String,
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
 
 #[async_trait]
 pub trait NetworkTrait: Send + Sync {
     async fn fetch_html(&self, url: &str) -> Result<String>;
     async fn fetch_html_with_headers(&self, url: &str) -> Result<(String, HeaderMap)>;
+    // Статус ответа не проверяется: ошибки HTTP разбирает вызывающий код
+    async fn fetch_request(&self, request: NetRequest) -> Result<NetResponse>;
+    // Содержимое HTTP-кэша для about:cache
+    fn cache_entries(&self) -> Vec<CacheEntryInfo>;
 }
 #[async_trait]
 pub trait DomTrait: Send + Sync {
@@ -647,21 +2069,62 @@ pub trait DomTrait: Send + Sync {
 }
 #[async_trait]
 pub trait JsRuntimeTrait: Send + Sync {
+    // Выполнение вне страницы, в общем realm по умолчанию
     async fn execute_script(&self, script: &str) -> Result<()>;
+    async fn create_realm(&self, page: PageId, options: RealmOptions, bindings: RealmBindings) -> Result<()>;
+    async fn destroy_realm(&self, page: PageId);
+    async fn evaluate(&self, page: PageId, script: &str) -> std::result::Result<String, ScriptError>;
+    async fn evaluate_module(&self, page: PageId, source: &str) -> std::result::Result<(), ScriptError>;
+    // Задача ставится в очередь realm при вызове и завершается после выполнения; ошибки скрипта
+    // уходят в консоль страницы. Задача, которую перестали ждать до её начала, пропускается
+    async fn execute_task(&self, page: PageId, script: &str) -> Result<()>;
 }
 #[async_trait]
 pub trait OrchestratorTrait: Send + Sync {
-    async fn deploy_actor(&self, actor_id: &str, wasm_bytes: &[u8]) -> Result<()>;
-    async fn start_provider(&self, provider_id: &str) -> Result<()>;
+    fn register_provider(&self, provider: Arc<dyn CapabilityProvider>);
+    fn subscribe(&self) -> tokio::sync::broadcast::Receiver<LatticeEvent>;
+    async fn deploy_actor(&self, actor_id: &str, wasm_bytes: &[u8], signature: &[u8]) -> Result<()>;
+    async fn scale_actor(&self, actor_id: &str, instances: usize) -> Result<()>;
+    async fn stop_actor(&self, actor_id: &str) -> Result<()>;
+    async fn invoke_actor(&self, actor_id: &str, export: &str, args: Vec<WasmValue>) -> Result<Vec<WasmValue>>;
+    async fn start_provider(&self, provider_id: &str, config: BTreeMap<String, String>) -> Result<()>;
+    async fn stop_provider(&self, provider_id: &str) -> Result<()>;
+    async fn link(&self, link: LinkDefinition) -> Result<()>;
+    async fn unlink(&self, actor_id: &str, contract_id: &str, link_name: &str) -> Result<()>;
+    // Тот же путь, что и у импорта cosmonaut.call внутри актора
+    async fn call_provider(&self, actor_id: &str, call: ActorCall) -> Result<Vec<u8>>;
+    async fn inventory(&self) -> LatticeInventory;
+    async fn shutdown(&self);
 }
 #[async_trait]
 pub trait PluginManagerTrait: Send + Sync {
-    async fn new(plugins_dir: impl AsRef<Path> + Send, providers_dir: impl AsRef<Path> + Send) -> Result<Self> where Self: Sized;
+    async fn new(
+        plugins_dir: impl AsRef<Path> + Send,
+        config: &Config,
+        runtime: Arc<dyn WasmRuntimeTrait + Send + Sync>,
+        security: Arc<dyn SecurityManagerTrait + Send + Sync>,
+        events: tokio::sync::broadcast::Sender<EngineEvent>,
+    ) -> Result<Self> where Self: Sized;
+    fn plugins(&self) -> Vec<PluginInfo>;
+    fn set_enabled(&self, name: &str, enabled: bool) -> Result<()>;
+    fn toolbar_buttons(&self) -> Vec<PluginButton>;
+    fn side_panel(&self, plugin: &str, panel: &str) -> Option<SidePanelSpec>;
+    async fn toolbar_clicked(&self, plugin: &str, button: &str) -> Result<()>;
+    async fn render_panel(&self, plugin: &str, panel: &str) -> Result<PanelContent>;
+    async fn intercept_request(&self, url: &str, method: &str) -> RequestDecision;
+    async fn shutdown(&self);
 }
 #[async_trait]
 pub trait LibManagerTrait: Send + Sync {
-    async fn new(libs_dir: impl AsRef<Path> + Send) -> Result<Self> where Self: Sized;
+    async fn new(
+        libs_dir: impl AsRef<Path> + Send,
+        runtime: Arc<dyn WasmRuntimeTrait + Send + Sync>,
+        security: Arc<dyn SecurityManagerTrait + Send + Sync>,
+    ) -> Result<Self> where Self: Sized;
+    fn libraries(&self) -> Vec<LibraryInfo>;
     async fn load_library(&self, library_name: &str) -> Result<()>;
+    // Загружает библиотеку при необходимости и запрещает её выгрузку до конца аренды
+    async fn acquire(&self, library_name: &str) -> Result<LibraryLease>;
     async fn unload_library(&self, library_name: &str) -> Result<()>;
 }
 #[async_trait]
@@ -670,6 +2133,11 @@ pub trait YuaidbTrait: Send + Sync {
     async fn insert(&self, key: &str, value: &str) -> Result<()>;
     async fn get(&self, key: &str) -> Result<Option<String>>;
     async fn delete(&self, key: &str) -> Result<()>;
+    async fn scan_prefix(&self, prefix: &str) -> Result<Vec<(String, String)>>;
+    // Удаление всех ключей с префиксом одной операцией; возвращает число удалённых
+    async fn delete_prefix(&self, prefix: &str) -> Result<usize>;
+    // Принудительная запись отложенных изменений на диск
+    async fn flush(&self) -> Result<()>;
 }
 
 
@@ -683,9 +2151,9 @@ pub trait SecurityManagerTrait: Send + Sync {
 
 #[async_trait]
 pub trait ReactiveCoreTrait: Send + Sync {
-    fn create_signals(&self, state: &JsonValue) -> Result<()>;
+    fn create_signals(&self, document: &PageDocument, node: &ParsedNode, state: &JsonValue) -> Result<()>;
     fn bind_angular_scopes(&self, node: &ParsedNode) -> Result<()>;
-    fn create_stores(&self, state: &JsonValue) -> Result<()>;
+    fn create_stores(&self, document: &PageDocument, node: &ParsedNode, state: &JsonValue) -> Result<()>;
     fn apply_svelte_hashes(&self, node: &ParsedNode) -> Result<()>;
     fn load_wasm_module(&self, node: &ParsedNode) -> Result<()>;
     async fn incremental_render(&self, ui: &mut Ui, node: &ParsedNode) -> Result<()>;
@@ -721,4174 +2189,16800 @@ pub trait ServiceWorkerTrait: Send + Sync {
 #[async_trait]
 pub trait SchedulerTrait: Send + Sync {
     async fn new(
+        max_threads: usize,
         wasm_runtime: Option<Arc<dyn WasmRuntimeTrait + Send + Sync>>,
         js_runtime: Option<Arc<dyn JsRuntimeTrait + Send + Sync>>,
         orchestrator: Option<Arc<dyn OrchestratorTrait + Send + Sync>>,
     ) -> Result<Self> where Self: Sized;
-    async fn schedule_wasm_task(&self, module: &[u8]) -> Result<()>;
-    async fn schedule_js_task(&self, script: &str) -> Result<()>;
-    async fn schedule_orchestration_task(&self, actor_id: &str) -> Result<()>;
+    fn spawn(&self, options: TaskOptions, task: BoxFuture<'static, ()>) -> TaskHandle;
+    // Вычисление в пуле CPU размером settings.max_threads
+    fn spawn_cpu(&self, options: TaskOptions, task: Box<dyn FnOnce(&CancelToken) + Send>) -> TaskHandle;
+    fn stats(&self) -> SchedulerStats;
+    async fn schedule_wasm_task(&self, module: &[u8], signature: &[u8], export: &str, args: Vec<WasmValue>) -> Result<Vec<WasmValue>>;
+    // Задачи одной страницы выполняются в порядке вызовов; приоритет и срок действуют на каждую
+    fn schedule_js_task(&self, page: PageId, script: &str, options: TaskOptions) -> BoxFuture<'static, Result<()>>;
+    async fn schedule_orchestration_task(&self, actor_id: &str, export: &str, args: Vec<WasmValue>) -> Result<Vec<WasmValue>>;
 }
 #[async_trait]
 pub trait IoManagerTrait: Send + Sync {
-    async fn new(network: Arc<dyn NetworkTrait + Send + Sync>, db: Arc<dyn YuaidbTrait + Send + Sync>) -> Result<Self> where Self: Sized;
+    async fn new(config: &Config, network: Arc<dyn NetworkTrait + Send + Sync>, db: Arc<dyn YuaidbTrait + Send + Sync>) -> Result<Self> where Self: Sized;
+    // Загрузка по http(s), file, data, ipfs, about и зарегистрированным схемам
+    async fn load(&self, url: &str, expected: ResourceType) -> Result<Resource> {
+        self.load_with_headers(url, expected, HeaderMap::new()).await
+    }
+    // Подресурс страницы: заголовки запроса (Referer) передаются обработчику схемы
+    async fn load_with_headers(&self, url: &str, expected: ResourceType, headers: HeaderMap) -> Result<Resource>;
+    fn register_scheme(&self, scheme: &str, handler: Arc<dyn SchemeHandler>);
+    // Адресная строка: локальный путь -> file://, адрес без схемы -> https://
+    fn resolve_input(&self, input: &str) -> String;
+    fn in_flight(&self) -> usize;
 }
 #[async_trait]
 pub trait WasmRuntimeTrait: Send + Sync {
-    async fn new(config: &super::config::Config) -> Result<Self> where Self: Sized;
+    async fn new(config: &super::config::Config, security: Arc<dyn SecurityManagerTrait + Send + Sync>) -> Result<Self> where Self: Sized;
+    // Модуль принимается только с действительной подписью доверенного издателя;
+    // возвращается sha256 модуля, по которому он затем инстанцируется
+    async fn load_module(&self, wasm_bytes: &[u8], signature: &[u8]) -> Result<String>;
+    async fn instantiate(&self, module: &str, sandbox: SandboxConfig) -> Result<InstanceId>;
+    async fn call(&self, instance: InstanceId, export: &str, args: Vec<WasmValue>) -> Result<Vec<WasmValue>>;
+    // Экспорт (ptr, len) -> i64 с буферами в памяти гостя; гость экспортирует alloc(len) -> ptr
+    async fn call_bytes(&self, instance: InstanceId, export: &str, input: Vec<u8>) -> Result<Vec<u8>>;
+    async fn drop_instance(&self, instance: InstanceId);
 }
 #[async_trait]
 pub trait WasmManifestTrait: Send + Sync {
-    async fn new() -> Result<Self> where Self: Sized;
+    async fn new(config: &Config, security: Arc<dyn SecurityManagerTrait + Send + Sync>) -> Result<Self> where Self: Sized;
     async fn load_yaml(&self, path: &Path) -> Result<serde_yaml::Value>;
     async fn load_oci(&self, oci_ref: &str) -> Result<serde_yaml::Value>;
+    // Ошибки содержат путь до поля: capabilities.network[1]: …
     async fn validate(&self, manifest: &serde_yaml::Value) -> Result<()>;
+    // Манифест ~/.cosmonaut/wasm_manifests/<name>.yaml после проверки подписи <name>.yaml.sig и валидации
+    async fn load(&self, name: &str) -> Result<ComponentManifest>;
+    async fn list(&self) -> Result<Vec<String>>;
 }
 
 
 // === FILE: core\io_manager.rs ===
-use crate::core::interfaces::{IoManagerTrait, NetworkTrait, YuaidbTrait};
+use crate::core::config::Config;
+use crate::core::interfaces::{IoManagerTrait, NetRequest, NetworkTrait, YuaidbTrait};
 use anyhow::Result;
-use std::sync::Arc;
 use async_trait::async_trait;
-pub struct IoManager;
-#[async_trait]
-impl IoManagerTrait for IoManager {
-    async fn new(
-        _network: Arc<dyn NetworkTrait + Send + Sync>,
-        _db: Arc<dyn YuaidbTrait + Send + Sync>
-    ) -> Result<Self> {
-        log::info!("Менеджер ввода-вывода инициализирован");
-        Ok(Self)
+use base64::Engine as _;
+use bytes::Bytes;
+use futures::future::{BoxFuture, FutureExt, Shared};
+use parking_lot::{Mutex, RwLock};
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use url::Url;
+
+// Единая точка загрузки ресурсов: схема URL выбирает обработчик, одинаковые
+// одновременные запросы объединяются, ответ классифицируется по MIME-типу
+
+pub const DEFAULT_IPFS_GATEWAY: &str = "https://ipfs.io";
+pub const VIEW_SOURCE_PREFIX: &str = "view-source:";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResourceType {
+    Document,
+    Image,
+    Stylesheet,
+    Script,
+    Wasm,
+    Other,
+}
+
+impl ResourceType {
+    fn from_mime(mime: &str) -> Self {
+        let essence = mime.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+        match essence.as_str() {
+            "text/html" | "application/xhtml+xml" => Self::Document,
+            "text/css" => Self::Stylesheet,
+            "text/javascript" | "application/javascript" | "application/ecmascript" | "text/ecmascript" => Self::Script,
+            "application/wasm" => Self::Wasm,
+            _ if essence.starts_with("image/") => Self::Image,
+            _ => Self::Other,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Document => "document",
+            Self::Image => "image",
+            Self::Stylesheet => "stylesheet",
+            Self::Script => "script",
+            Self::Wasm => "wasm",
+            Self::Other => "other",
+        }
     }
 }
 
-// === FILE: core\js_runtime.rs ===
-use crate::core::interfaces::JsRuntimeTrait;
-use anyhow::Result;
-use async_trait::async_trait;
-pub struct JsRuntime;
-impl JsRuntime {
-    pub fn new() -> Result<Self> {
-        log::info!("JS runtime инициализирован");
-        Ok(Self)
+// MIME-тип по расширению для file:// и ответов без Content-Type
+pub fn mime_from_path(path: &str) -> &'static str {
+    let extension = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "xhtml" => "application/xhtml+xml",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "wasm" => "application/wasm",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "txt" | "md" | "log" | "rs" | "toml" | "yaml" | "yml" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
     }
 }
-#[async_trait]
-impl JsRuntimeTrait for JsRuntime {
-    async fn execute_script(&self, _script: &str) -> Result<()> {
-        log::info!(
-            "Выполнение JS-скрипта: {}...",
-            &_script[.._script.len().min(50)]
-        );
-        Ok(())
+
+fn is_textual(mime: &str) -> bool {
+    let mime = mime.to_ascii_lowercase();
+    mime.starts_with("text/") || mime.contains("json") || mime.contains("xml") || mime.contains("javascript")
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
     }
+    escaped
 }
 
-// === FILE: core\libs.rs ===
-use crate::core::interfaces::LibManagerTrait;
-use anyhow::Result;
-use std::path::{Path, PathBuf};
-use async_trait::async_trait;
-pub struct LibManager {
-    _libs_dir: PathBuf,
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1024 * 1024 => format!("{:.1} МБ", b as f64 / (1024.0 * 1024.0)),
+        b if b >= 1024 => format!("{:.1} КБ", b as f64 / 1024.0),
+        b => format!("{} Б", b),
+    }
 }
-#[async_trait]
-impl LibManagerTrait for LibManager {
-    async fn new(libs_dir: impl AsRef<Path> + Send) -> Result<Self> {
-        let libs_dir = libs_dir.as_ref().to_path_buf();
-        log::info!("Менеджер библиотек инициализирован с каталогом: {:?}", libs_dir);
-        Ok(Self { _libs_dir: libs_dir })
+
+// Ввод адресной строки, похожий на локальный путь: /…, ~/…, ./…, C:\…, \\сервер\…
+fn local_path(input: &str) -> Option<PathBuf> {
+    let bytes = input.as_bytes();
+    let drive = bytes.len() >= 2
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes.len() == 2 || matches!(bytes[2], b'\\' | b'/'));
+    let path = if input == "~" {
+        dirs::home_dir()?
+    } else if let Some(rest) = input.strip_prefix("~/") {
+        dirs::home_dir()?.join(rest)
+    } else if drive || ["/", "./", "../", "\\\\"].iter().any(|prefix| input.starts_with(prefix)) {
+        PathBuf::from(input)
+    } else {
+        return None;
+    };
+    Some(if path.is_absolute() { path } else { std::env::current_dir().ok()?.join(path) })
+}
+
+#[derive(Clone, Debug)]
+pub struct Resource {
+    // Итоговый URL после перенаправлений
+    pub url: String,
+    pub kind: ResourceType,
+    pub mime: String,
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl Resource {
+    pub fn new(url: impl Into<String>, mime: impl Into<String>, body: impl Into<Bytes>) -> Self {
+        let mime = mime.into();
+        let mut headers = HeaderMap::new();
+        if let Ok(value) = HeaderValue::from_str(&mime) {
+            headers.insert(CONTENT_TYPE, value);
+        }
+        Self { url: url.into(), kind: ResourceType::Other, mime, status: 200, headers, body: body.into() }
     }
-    async fn load_library(&self, _library_name: &str) -> Result<()> {
-        log::info!("Загрузка библиотеки: {}", _library_name);
-        Ok(())
+
+    pub fn html(url: impl Into<String>, html: impl Into<String>) -> Self {
+        Self::new(url, "text/html; charset=utf-8", html.into())
     }
-    async fn unload_library(&self, _library_name: &str) -> Result<()> {
-        log::info!("Выгрузка библиотеки: {}", _library_name);
-        Ok(())
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn image(&self) -> Result<image::DynamicImage> {
+        Ok(image::load_from_memory(&self.body)?)
+    }
+
+    // Документ для вкладки: текст показывается как <pre>, изображение — через <img>
+    pub fn document_html(&self) -> String {
+        match self.kind {
+            ResourceType::Document => self.text(),
+            ResourceType::Image => format!("<html><body><img src=\"{}\"></body></html>", escape_html(&self.url)),
+            _ if is_textual(&self.mime) => format!("<html><body><pre>{}</pre></body></html>", escape_html(&self.text())),
+            _ => format!(
+                "<html><body><p>{} ({}, {}) нельзя показать как страницу</p></body></html>",
+                escape_html(&self.url),
+                escape_html(&self.mime),
+                format_size(self.body.len() as u64)
+            ),
+        }
+    }
+
+    // Тип по MIME, затем по расширению; явное противоречие ожидаемому типу — ошибка
+    fn classify(mut self, expected: ResourceType) -> Result<Self> {
+        let mut kind = ResourceType::from_mime(&self.mime);
+        if kind == ResourceType::Other {
+            kind = ResourceType::from_mime(mime_from_path(self.url.split(['?', '#']).next().unwrap_or_default()));
+        }
+        self.kind = match (expected, kind) {
+            (ResourceType::Other | ResourceType::Document, kind) => kind,
+            (expected, ResourceType::Other) => expected,
+            (expected, kind) if expected == kind => kind,
+            (expected, kind) => {
+                return Err(anyhow::anyhow!(
+                    "{}: ожидался ресурс {}, получен {} ({})",
+                    self.url,
+                    expected.as_str(),
+                    kind.as_str(),
+                    self.mime
+                ))
+            }
+        };
+        Ok(self)
     }
 }
 
-// === FILE: core\mod.rs ===
-pub mod engine;
-pub mod event_loop;
-pub mod scheduler;
-pub mod runtime;
-pub mod js_runtime;
-pub mod orchestrator;
-pub mod wasm_manifest;
-pub mod provider;
-pub mod plugins;
-pub mod libs;
-pub mod yuaidb;
-pub mod config;
-pub mod security;
-pub mod reactive;
-pub mod service_worker;
-pub mod io_manager;
-pub mod session;
-pub mod profile;
-pub mod installer;
-pub mod interfaces;
-pub mod ssh;
-pub mod page_state;
-
-// === FILE: core\orchestrator.rs ===
-use crate::core::interfaces::OrchestratorTrait;
-use anyhow::Result;
-use async_trait::async_trait;
-pub struct Orchestrator;
-impl Orchestrator {
-    pub fn new() -> Result<Self> {
-        log::info!("Оркестратор инициализирован");
-        Ok(Self)
-    }
-}
+// Обработчик схемы URL; about: и внутренние схемы регистрируются через register_scheme
 #[async_trait]
-impl OrchestratorTrait for Orchestrator {
-    async fn deploy_actor(&self, _actor_id: &str, _wasm_bytes: &[u8]) -> Result<()> {
-        log::info!("Деплой актора: {}", _actor_id);
-        Ok(())
-    }
-    async fn start_provider(&self, _provider_id: &str) -> Result<()> {
-        log::info!("Запуск провайдера: {}", _provider_id);
-        Ok(())
+pub trait SchemeHandler: Send + Sync {
+    async fn load(&self, url: &Url) -> Result<Resource>;
+    // Заголовки запроса имеют смысл только для сетевых схем
+    async fn load_with_headers(&self, url: &Url, _headers: &HeaderMap) -> Result<Resource> {
+        self.load(url).await
     }
 }
 
-// === FILE: core\page_state.rs ===
-use std::sync::Arc;
-use parking_lot::RwLock;
-pub use crate::dom::parser::FrameworkType;
-#[derive(Default, Clone)]
-pub struct PageState {
-    pub url: String,
-    pub html: String,
-    pub dom: String, // Пока как текстовое дерево → потом заменим на норм AST
-    pub detected_framework: Option<FrameworkType>,
+struct HttpHandler {
+    network: Arc<dyn NetworkTrait + Send + Sync>,
 }
 
-impl PageState {
-    pub fn parse_framework_type(name: &str) -> Option<FrameworkType> {
-        match name {
-            "ReactNext" => Some(FrameworkType::ReactNext),
-            "Angular" => Some(FrameworkType::Angular),
-            "VueNuxt" => Some(FrameworkType::VueNuxt),
-            "SvelteKit" => Some(FrameworkType::SvelteKit),
-            "WasmModule" => Some(FrameworkType::WasmModule),
-            _ => None,
-        }
+#[async_trait]
+impl SchemeHandler for HttpHandler {
+    async fn load(&self, url: &Url) -> Result<Resource> {
+        self.load_with_headers(url, &HeaderMap::new()).await
     }
-}
-
-pub type SharedPageState = Arc<RwLock<PageState>>;
-
 
-// === FILE: core\plugins.rs ===
-use crate::core::interfaces::PluginManagerTrait;
-use anyhow::Result;
-use std::path::Path;
-use async_trait::async_trait;
-pub struct PluginManager;
-#[async_trait]
-impl PluginManagerTrait for PluginManager {
-    async fn new(
-        _plugins_dir: impl AsRef<Path> + Send,
-        _providers_dir: impl AsRef<Path> + Send,
-    ) -> Result<Self> {
-        log::info!("Менеджер плагинов инициализирован");
-        Ok(Self)
+    async fn load_with_headers(&self, url: &Url, headers: &HeaderMap) -> Result<Resource> {
+        let mut request = NetRequest::get(url.as_str());
+        request.headers = headers.clone();
+        let response = self.network.fetch_request(request).await?;
+        let mime = response
+            .headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+            .unwrap_or_else(|| mime_from_path(url.path()).to_string());
+        Ok(Resource {
+            url: response.url,
+            kind: ResourceType::Other,
+            mime,
+            status: response.status,
+            headers: response.headers,
+            body: response.body.into(),
+        })
     }
 }
 
-// === FILE: core\profile.rs ===
-use crate::core::interfaces::ProfileManagerTrait;
-use anyhow::Result;
-use std::path::{Path, PathBuf};
-use std::fs;
-use async_trait::async_trait;
-pub struct ProfileManager {
-    _certs_dir: PathBuf,
-}
+struct FileHandler;
+
 #[async_trait]
-impl ProfileManagerTrait for ProfileManager {
-    async fn new(certs_dir: impl AsRef<Path> + Send) -> Result<Self> {
-        let certs_dir = certs_dir.as_ref().to_path_buf();
-        if !certs_dir.exists() {
-            fs::create_dir_all(&certs_dir)?;
+impl SchemeHandler for FileHandler {
+    async fn load(&self, url: &Url) -> Result<Resource> {
+        let path = url.to_file_path().map_err(|_| anyhow::anyhow!("Недопустимый путь: {}", url))?;
+        let metadata = tokio::fs::metadata(&path)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+        if metadata.is_dir() {
+            return Ok(Resource::html(url.as_str(), directory_listing(&path).await?));
         }
-        log::info!("Менеджер профилей инициализирован с каталогом: {:?}", certs_dir);
-        Ok(Self { _certs_dir: certs_dir })
+        let body = tokio::fs::read(&path).await?;
+        Ok(Resource::new(url.as_str(), mime_from_path(url.path()), body))
     }
-    async fn create_profile(&self, _r_id: &str) -> Result<String> {
-        log::info!("Создание профиля для r_id: {}", _r_id);
-        Ok("".to_string())
+}
+
+// Каталоги сначала, затем файлы; ссылки абсолютные, так как рендер не разрешает относительные
+async fn directory_listing(path: &Path) -> Result<String> {
+    let mut entries = Vec::new();
+    let mut dir = tokio::fs::read_dir(path).await?;
+    while let Some(entry) = dir.next_entry().await? {
+        let Ok(metadata) = entry.metadata().await else { continue };
+        entries.push((entry.file_name().to_string_lossy().into_owned(), metadata.is_dir(), metadata.len()));
     }
-    async fn load_profile(&self, _profile_id: &str) -> Result<Option<Vec<u8>>> {
-        log::info!("Загрузка профиля: {}", _profile_id);
-        Ok(None)
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.to_lowercase().cmp(&b.0.to_lowercase())));
+
+    let title = escape_html(&path.display().to_string());
+    let mut html = format!("<html><head><title>Индекс {0}</title></head><body><h1>Индекс {0}</h1><ul>", title);
+    if let Some(parent) = path.parent().and_then(|parent| Url::from_directory_path(parent).ok()) {
+        html.push_str(&format!("<li><a href=\"{}\">..</a></li>", escape_html(parent.as_str())));
     }
-    async fn delete_profile(&self, _profile_id: &str) -> Result<()> {
-        log::info!("Удаление профиля: {}", _profile_id);
-        Ok(())
+    for (name, is_dir, size) in entries {
+        let child = path.join(&name);
+        let href = if is_dir { Url::from_directory_path(&child) } else { Url::from_file_path(&child) };
+        let Ok(href) = href else { continue };
+        if is_dir {
+            html.push_str(&format!("<li><a href=\"{}\">{}/</a></li>", escape_html(href.as_str()), escape_html(&name)));
+        } else {
+            html.push_str(&format!(
+                "<li><a href=\"{}\">{}</a> <span>{}</span></li>",
+                escape_html(href.as_str()),
+                escape_html(&name),
+                format_size(size)
+            ));
+        }
     }
+    html.push_str("</ul></body></html>");
+    Ok(html)
 }
 
-// === FILE: core\provider.rs ===
-use std::sync::Arc;
-pub struct Provider;
-impl Provider {
-    pub fn new() -> Arc<Self> {
-        println!("Provider initialized");
-        Arc::new(Self)
+// data:[<mediatype>][;base64],<данные>
+struct DataHandler;
+
+pub fn percent_decode(input: &str) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = |b: u8| (b as char).to_digit(16);
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                (Some(high), Some(low)) => {
+                    output.push((high * 16 + low) as u8);
+                    i += 3;
+                    continue;
+                }
+                _ => output.push(b'%'),
+            },
+            byte => output.push(byte),
+        }
+        i += 1;
     }
+    output
 }
 
-// === FILE: core\reactive.rs ===
-// === FILE: core\reactive.rs ===
-use crate::dom::parser::ParsedNode;
-// NOTE: egui::Ui требует, чтобы egui был в зависимостях
-use egui::Ui; 
-use serde_json::Value as JsonValue;
-use anyhow::Result;
-use async_trait::async_trait;
-use log;
-
-// ИСПРАВЛЕНИЕ: Импортируем официальный трейт из interfaces
-use crate::core::interfaces::ReactiveCoreTrait; 
-
-pub struct ReactiveCore;
-
-// ИСПРАВЛЕННЫЙ БЛОК: Реализация new для СТРУКТУРЫ
-impl ReactiveCore {
-    // Конструктор структуры - это то, что вы вызываете в main.rs
-    // Arc::new(ReactiveCore::new().await)
-    pub async fn new() -> Self {
-        log::info!("ReactiveCore initialized.");
-        Self
-    }
+pub fn decode_data_url(url: &str) -> Result<(String, Vec<u8>)> {
+    let rest = url.strip_prefix("data:").ok_or_else(|| anyhow::anyhow!("Не data: URL"))?;
+    let (header, data) = rest.split_once(',').ok_or_else(|| anyhow::anyhow!("В data: URL нет запятой"))?;
+    let (media_type, is_base64) = match header.strip_suffix(";base64") {
+        Some(media_type) => (media_type, true),
+        None => (header, false),
+    };
+    let mime = if media_type.is_empty() { "text/plain;charset=US-ASCII".to_string() } else { media_type.to_string() };
+    let body = if is_base64 {
+        // Пробелы и переводы строк в base64 допустимы и отбрасываются
+        let cleaned: String = String::from_utf8_lossy(&percent_decode(data)).chars().filter(|c| !c.is_whitespace()).collect();
+        base64::engine::general_purpose::STANDARD
+            .decode(cleaned.trim_end_matches('='))
+            .or_else(|_| base64::engine::general_purpose::STANDARD_NO_PAD.decode(cleaned.trim_end_matches('=')))
+            .map_err(|e| anyhow::anyhow!("Некорректный base64 в data: URL: {}", e))?
+    } else {
+        percent_decode(data)
+    };
+    Ok((mime, body))
 }
 
 #[async_trait]
-// Теперь ReactiveCore реализует трейт, импортированный из interfaces.rs
-impl ReactiveCoreTrait for ReactiveCore {
-    
-    // Реализация методов трейта (ваши заглушки)
-    fn create_signals(&self, _state: &JsonValue) -> Result<()> {
-        Ok(())
+impl SchemeHandler for DataHandler {
+    async fn load(&self, url: &Url) -> Result<Resource> {
+        let (mime, body) = decode_data_url(url.as_str())?;
+        Ok(Resource::new(url.as_str(), mime, body))
     }
-    fn bind_angular_scopes(&self, _node: &ParsedNode) -> Result<()> {
-        Ok(())
+}
+
+// Исходный HTML с подсветкой: классы vs-* раскрашивает рендер <pre>
+pub fn view_source_html(url: &str, source: &str) -> String {
+    let mut out = String::with_capacity(source.len() * 2);
+    let mut rest = source;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&escape_html(&rest[..start]));
+        rest = &rest[start..];
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").map(|i| i + 3).unwrap_or(rest.len());
+            push_span(&mut out, "vs-comment", &rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+        // «<» без имени тега (например, a < b в скрипте) — обычный текст
+        if !rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || matches!(c, '/' | '!' | '?')) {
+            out.push_str("&lt;");
+            rest = &rest[1..];
+            continue;
+        }
+        let end = tag_end(rest);
+        highlight_tag(&mut out, &rest[..end]);
+        rest = &rest[end..];
     }
-    fn create_stores(&self, _state: &JsonValue) -> Result<()> {
-        Ok(())
+    out.push_str(&escape_html(rest));
+    format!(
+        "<html><head><title>{}{}</title></head><body><pre class=\"view-source\">{}</pre></body></html>",
+        VIEW_SOURCE_PREFIX,
+        escape_html(url),
+        out
+    )
+}
+
+fn push_span(out: &mut String, class: &str, text: &str) {
+    if !text.is_empty() {
+        out.push_str(&format!("<span class=\"{}\">{}</span>", class, escape_html(text)));
     }
-    fn apply_svelte_hashes(&self, _node: &ParsedNode) -> Result<()> {
-        Ok(())
+}
+
+// Позиция после «>», не считая символов внутри кавычек
+fn tag_end(tag: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in tag.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return i + 1,
+            _ => {}
+        }
     }
-    fn load_wasm_module(&self, _node: &ParsedNode) -> Result<()> {
-        Ok(())
+    tag.len()
+}
+
+fn highlight_tag(out: &mut String, tag: &str) {
+    if tag.starts_with("<!") || tag.starts_with("<?") {
+        push_span(out, "vs-doctype", tag);
+        return;
     }
-    // Здесь мы должны реализовать только те методы, которые есть в трейте interfaces::ReactiveCoreTrait
-    // NOTE: incremental_render, bind_angular_scopes и т.д. должны быть в трейте
-    // Если их там нет, компилятор будет ругаться. 
-    // Предполагаем, что они там есть.
-    async fn incremental_render(&self, _ui: &mut Ui, _node: &ParsedNode) -> Result<()> {
-        Ok(())
+    let prefix = if tag.starts_with("</") { 2 } else { 1 };
+    let name_end = prefix + tag[prefix..].find(|c: char| c.is_whitespace() || c == '>' || c == '/').unwrap_or(tag.len() - prefix);
+    push_span(out, "vs-tag", &tag[..name_end]);
+    let body = &tag[name_end..];
+    let close = if body.ends_with("/>") { "/>" } else if body.ends_with('>') { ">" } else { "" };
+    let mut attrs = &body[..body.len() - close.len()];
+    while !attrs.is_empty() {
+        let trimmed = attrs.trim_start();
+        out.push_str(&escape_html(&attrs[..attrs.len() - trimmed.len()]));
+        attrs = trimmed;
+        if let Some(value) = attrs.strip_prefix('=') {
+            out.push('=');
+            let value = value.trim_start();
+            let len = match value.chars().next() {
+                Some(q @ ('"' | '\'')) => value[1..].find(q).map(|i| i + 2).unwrap_or(value.len()),
+                _ => value.find(char::is_whitespace).unwrap_or(value.len()),
+            };
+            push_span(out, "vs-value", &value[..len]);
+            attrs = &value[len..];
+        } else if !attrs.is_empty() {
+            let len = attrs.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(attrs.len());
+            push_span(out, "vs-attr", &attrs[..len]);
+            attrs = &attrs[len..];
+        }
     }
+    push_span(out, "vs-tag", close);
 }
 
+// ipfs://<cid>/<путь> загружается через HTTP-шлюз
+struct IpfsHandler {
+    gateway: String,
+    http: Arc<HttpHandler>,
+}
 
-// === FILE: core\runtime.rs ===
-use crate::core::config::Config;
-use crate::core::interfaces::WasmRuntimeTrait;
-use anyhow::Result;
-use async_trait::async_trait;
-pub struct Runtime {
-    #[cfg(feature = "wasm")]
-    engine: wasmtime::Engine,
+#[async_trait]
+impl SchemeHandler for IpfsHandler {
+    async fn load(&self, url: &Url) -> Result<Resource> {
+        let cid = url.host_str().ok_or_else(|| anyhow::anyhow!("В {} не указан CID", url))?;
+        let mut gateway_url = Url::parse(&format!("{}/ipfs/{}{}", self.gateway.trim_end_matches('/'), cid, url.path()))?;
+        gateway_url.set_query(url.query());
+        let mut resource = self.http.load(&gateway_url).await?;
+        // Для страницы адрес остаётся ipfs://, а не адресом шлюза
+        resource.url = url.to_string();
+        Ok(resource)
+    }
 }
+
+struct AboutBlank;
+
 #[async_trait]
-impl WasmRuntimeTrait for Runtime {
-    async fn new(_config: &Config) -> Result<Self> {
-        #[cfg(feature = "wasm")]
-        {
-            let mut wasmtime_config = wasmtime::Config::new();
-            wasmtime_config.wasm_multi_memory(true);
-            let engine = wasmtime::Engine::new(&wasmtime_config)?;
-            log::info!("WASM runtime инициализирован с wasmtime");
-            Ok(Self { engine })
-        }
-        #[cfg(not(feature = "wasm"))]
-        {
-            log::info!("WASM runtime отключён");
-            Ok(Self {})
+impl SchemeHandler for AboutBlank {
+    async fn load(&self, url: &Url) -> Result<Resource> {
+        match url.path() {
+            "blank" => Ok(Resource::html(url.as_str(), "<html><head></head><body></body></html>")),
+            page => Err(anyhow::anyhow!("Неизвестная страница about:{}", page)),
         }
     }
 }
 
-// === FILE: core\scheduler.rs ===
-use crate::core::interfaces::{JsRuntimeTrait, OrchestratorTrait, SchedulerTrait, WasmRuntimeTrait};
-use anyhow::Result;
-use std::sync::Arc;
-use async_trait::async_trait;
-pub struct Scheduler {
-    wasm_runtime: Option<Arc<dyn WasmRuntimeTrait + Send + Sync>>,
-    js_runtime: Option<Arc<dyn JsRuntimeTrait + Send + Sync>>,
-    orchestrator: Option<Arc<dyn OrchestratorTrait + Send + Sync>>,
+type InFlight = Shared<BoxFuture<'static, std::result::Result<Resource, String>>>;
+
+pub struct IoManager {
+    handlers: RwLock<HashMap<String, Arc<dyn SchemeHandler>>>,
+    in_flight: Arc<Mutex<HashMap<String, InFlight>>>,
 }
+
 #[async_trait]
-impl SchedulerTrait for Scheduler {
+impl IoManagerTrait for IoManager {
     async fn new(
-        wasm_runtime: Option<Arc<dyn WasmRuntimeTrait + Send + Sync>>,
-        js_runtime: Option<Arc<dyn JsRuntimeTrait + Send + Sync>>,
-        orchestrator: Option<Arc<dyn OrchestratorTrait + Send + Sync>>,
+        config: &Config,
+        network: Arc<dyn NetworkTrait + Send + Sync>,
+        _db: Arc<dyn YuaidbTrait + Send + Sync>,
     ) -> Result<Self> {
-        log::info!("Планировщик инициализирован");
-        Ok(Self { wasm_runtime, js_runtime, orchestrator })
+        let http = Arc::new(HttpHandler { network });
+        let gateway = config.settings.ipfs_gateway.clone().unwrap_or_else(|| DEFAULT_IPFS_GATEWAY.to_string());
+        let mut handlers: HashMap<String, Arc<dyn SchemeHandler>> = HashMap::new();
+        handlers.insert("http".to_string(), http.clone());
+        handlers.insert("https".to_string(), http.clone());
+        handlers.insert("file".to_string(), Arc::new(FileHandler));
+        handlers.insert("data".to_string(), Arc::new(DataHandler));
+        handlers.insert("ipfs".to_string(), Arc::new(IpfsHandler { gateway, http }));
+        handlers.insert("about".to_string(), Arc::new(AboutBlank));
+        log::info!("Менеджер ввода-вывода инициализирован");
+        Ok(Self { handlers: RwLock::new(handlers), in_flight: Arc::new(Mutex::new(HashMap::new())) })
     }
-    async fn schedule_wasm_task(&self, _module: &[u8]) -> Result<()> {
-        if self.wasm_runtime.is_some() {
-            log::info!("Планирование WASM-задачи");
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("WASM runtime отключён"))
-        }
+
+    fn register_scheme(&self, scheme: &str, handler: Arc<dyn SchemeHandler>) {
+        self.handlers.write().insert(scheme.to_ascii_lowercase(), handler);
     }
-    async fn schedule_js_task(&self, _script: &str) -> Result<()> {
-        if self.js_runtime.is_some() {
-            log::info!("Планирование JS-задачи");
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("JS runtime отключён"))
+
+    fn resolve_input(&self, input: &str) -> String {
+        let input = input.trim();
+        if input.is_empty() {
+            return String::new();
+        }
+        if let Some(path) = local_path(input) {
+            if let Ok(url) = Url::from_file_path(&path) {
+                return url.to_string();
+            }
+        }
+        if let Some(inner) = input.strip_prefix(VIEW_SOURCE_PREFIX) {
+            return format!("{}{}", VIEW_SOURCE_PREFIX, self.resolve_input(inner));
+        }
+        // «localhost:8080» разбирается как схема localhost, поэтому схема должна быть известна
+        match Url::parse(input) {
+            Ok(url) if self.handlers.read().contains_key(url.scheme()) => input.to_string(),
+            _ => format!("https://{}", input),
         }
     }
-    async fn schedule_orchestration_task(&self, _actor_id: &str) -> Result<()> {
-        if self.orchestrator.is_some() {
-            log::info!("Планирование задачи оркестрации");
-            Ok(())
+
+    // Повторный запрос того же URL с теми же заголовками, пока первый не завершён, ждёт его результата
+    async fn load_with_headers(&self, url: &str, expected: ResourceType, headers: HeaderMap) -> Result<Resource> {
+        if let Some(inner) = url.strip_prefix(VIEW_SOURCE_PREFIX) {
+            let source = self.load(inner, ResourceType::Other).await?;
+            let mut resource = Resource::html(
+                format!("{}{}", VIEW_SOURCE_PREFIX, source.url),
+                view_source_html(&source.url, &source.text()),
+            );
+            resource.status = source.status;
+            return resource.classify(expected);
+        }
+        let parsed = Url::parse(url).map_err(|e| anyhow::anyhow!("Недопустимый URL {}: {}", url, e))?;
+        // Внутренние страницы не отдаются как подресурсы: их нельзя встроить в чужую страницу
+        if parsed.scheme() == "about" && parsed.path() != "blank" && expected != ResourceType::Document {
+            return Err(anyhow::anyhow!("{} открывается только как документ", parsed));
+        }
+        let handler = self
+            .handlers
+            .read()
+            .get(parsed.scheme())
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Схема {}: не поддерживается", parsed.scheme()))?;
+        let key = if headers.is_empty() {
+            parsed.to_string()
         } else {
-            Err(anyhow::anyhow!("Оркестрация отключена"))
+            let mut pairs: Vec<String> = headers
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value.to_str().unwrap_or_default()))
+                .collect();
+            pairs.sort();
+            format!("{} {}", parsed, pairs.join("&"))
+        };
+        let (shared, owner) = {
+            let mut in_flight = self.in_flight.lock();
+            match in_flight.get(&key) {
+                Some(shared) => (shared.clone(), false),
+                None => {
+                    let future = async move { handler.load_with_headers(&parsed, &headers).await.map_err(|e| e.to_string()) }
+                        .boxed()
+                        .shared();
+                    in_flight.insert(key.clone(), future.clone());
+                    (future, true)
+                }
+            }
+        };
+        if !owner {
+            log::debug!("Запрос {} объединён с выполняющимся", key);
+        }
+        let result = shared.clone().await;
+        {
+            // Убирает запись любой из ожидавших, если владелец был отменён
+            let mut in_flight = self.in_flight.lock();
+            if in_flight.get(&key).is_some_and(|current| current.ptr_eq(&shared)) {
+                in_flight.remove(&key);
+            }
         }
+        result.map_err(|e| anyhow::anyhow!(e))?.classify(expected)
+    }
+
+    fn in_flight(&self) -> usize {
+        self.in_flight.lock().len()
     }
 }
 
-// === FILE: core\security.rs ===
-use crate::core::config::Config;
-use crate::core::interfaces::SecurityManagerTrait;
+// === FILE: core\js_runtime.rs ===
+use crate::core::engine::{EngineEvent, RequestInitiator};
+use crate::core::interfaces::{JsRuntimeTrait, NetRequest, NetResponse};
+#[cfg(feature = "js")]
+use crate::core::interfaces::Credentials;
+use crate::core::page_state::PageId;
 use anyhow::Result;
-use std::sync::Arc;
-use rustls::ClientConfig;
 use async_trait::async_trait;
-pub struct Security;
-#[async_trait]
-impl SecurityManagerTrait for Security {
-    async fn new(_config: &Config) -> Result<Self> {
-        log::info!("Менеджер безопасности инициализирован");
-        Ok(Self)
-    }
-    async fn configure_tls(&self) -> Result<Arc<ClientConfig>> {
-        log::info!("Настройка TLS");
-        let root_store = rustls::RootCertStore::empty();
-        let config = ClientConfig::builder()
-            .with_root_certificates(root_store)
-            .with_no_client_auth();
-        Ok(Arc::new(config))
-    }
-    async fn sign_component(&self, _data: &[u8]) -> Result<Vec<u8>> {
-        log::info!("Подпись компонента");
-        Ok(vec![])
-    }
-    async fn verify_component(&self, _data: &[u8], _signature: &[u8]) -> Result<()> {
-        log::info!("Проверка подписи компонента");
-        Ok(())
-    }
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Weak;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::Sender as BroadcastSender;
+use tokio::sync::{mpsc, oneshot};
+#[cfg(feature = "js")]
+use std::{cell::RefCell, rc::Rc};
+#[cfg(feature = "js")]
+use reqwest::header::{HeaderName, HeaderValue};
+#[cfg(feature = "js")]
+use boa_engine::{
+    builtins::promise::PromiseState, error::JsNativeErrorKind, js_string, object::builtins::{JsArray, JsArrayBuffer},
+    object::ObjectInitializer, property::Attribute, Context, JsArgs, JsError, JsNativeError, JsResult, JsString,
+    JsValue, Module, NativeFunction, Source,
+};
+#[cfg(feature = "js")]
+use boa_gc::{Finalize, Trace};
+#[cfg(feature = "js")]
+use crate::dom::live::NodeId;
+use crate::dom::live::SharedDom;
+use crate::core::web_storage::{StorageArea, StorageChange};
+#[cfg(feature = "js")]
+use crate::core::web_storage::StorageKind;
+#[cfg(feature = "js")]
+use crate::core::yuaidb::QuotaExceeded;
+use std::sync::Arc;
+
+// Ограничения выполнения: итерации одного цикла, глубина рекурсии и общее время задачи
+const LOOP_ITERATION_LIMIT: u64 = 10_000_000;
+const RECURSION_LIMIT: usize = 400;
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(5);
+const WATCHDOG_INTERVAL: Duration = Duration::from_millis(250);
+// Потоки прерванных realm одной страницы, которые ещё не вышли из скрипта; сверх этого
+// новые realm не создаются только для этой страницы
+const MAX_ABANDONED_REALMS: usize = 4;
+
+// Начиная с шестого вложенного таймера задержка не меньше 4 мс, как в HTML
+#[cfg(feature = "js")]
+const TIMER_NESTING_THRESHOLD: u32 = 5;
+#[cfg(feature = "js")]
+const TIMER_MIN_DELAY: Duration = Duration::from_millis(4);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScriptError {
+    pub message: String,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
 }
 
-// === FILE: core\service_worker.rs ===
-use crate::core::interfaces::{NetworkTrait, ServiceWorkerTrait, YuaidbTrait};
-use anyhow::Result;
-use std::sync::Arc;
-use async_trait::async_trait;
-pub struct ServiceWorker;
-#[async_trait]
-impl ServiceWorkerTrait for ServiceWorker {
-    async fn new(
-        _network: Arc<dyn NetworkTrait + Send + Sync>,
-        _db: Arc<dyn YuaidbTrait + Send + Sync>
-    ) -> Result<Self> {
-        log::info!("Сервис-воркер инициализирован");
-        Ok(Self)
+impl ScriptError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self { message: message.into(), line: None, column: None }
     }
-    async fn register(&self, _script_url: &str) -> Result<()> {
-        log::info!("Регистрация сервис-воркера для URL: {}", _script_url);
-        Ok(())
+
+    #[cfg(feature = "js")]
+    fn from_js(err: JsError, context: &mut Context) -> Self {
+        match err.try_native(context) {
+            Ok(native) => Self::new(native.to_string()),
+            Err(_) => Self::new(err.to_string()),
+        }
     }
-    async fn cache_resource(&self, _url: &str, _data: &[u8]) -> Result<()> {
-        log::info!("Кэширование ресурса: {}", _url);
-        Ok(())
+
+    // boa 0.20 не хранит позицию в ошибке выполнения; для синтаксической ошибки
+    // исходник разбирается повторно, и позиция берётся из ошибки парсера
+    #[cfg(feature = "js")]
+    fn from_eval(err: JsError, source: &str, context: &mut Context) -> Self {
+        let syntax = err.as_native().is_some_and(|native| matches!(native.kind, JsNativeErrorKind::Syntax));
+        let mut error = Self::from_js(err, context);
+        if let Some((line, column)) = syntax.then(|| syntax_position(source)).flatten() {
+            error.line = Some(line);
+            error.column = Some(column);
+        }
+        error
     }
-    async fn handle_push(&self, _payload: &str) -> Result<()> {
-        log::info!("Обработка push-уведомления: {}", _payload);
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, " (строка {}, столбец {})", line, column)?;
+        }
         Ok(())
     }
 }
 
-// === FILE: core\session.rs ===
-use crate::core::interfaces::{SessionManagerTrait, YuaidbTrait};
-use anyhow::Result;
-use std::sync::Arc;
-use async_trait::async_trait;
-pub struct SessionManager;
-#[async_trait]
-impl SessionManagerTrait for SessionManager {
-    async fn new(_db: Arc<dyn YuaidbTrait + Send + Sync>) -> Result<Self> {
-        log::info!("Менеджер сессий инициализирован");
-        Ok(Self)
-    }
-    async fn create_session(&self, _r_id: &str) -> Result<String> {
-        log::info!("Создание сессии для r_id: {}", _r_id);
-        Ok("".to_string())
+impl std::error::Error for ScriptError {}
+
+#[cfg(feature = "js")]
+fn syntax_position(source: &str) -> Option<(u32, u32)> {
+    use boa_parser::{lexer::Error as LexError, Error as ParseError, Parser};
+    let mut interner = boa_interner::Interner::default();
+    let error = Parser::new(Source::from_bytes(source.as_bytes()))
+        .parse_script(&boa_ast::scope::Scope::new_global(), &mut interner)
+        .err()?;
+    let position = match error {
+        ParseError::Expected { span, .. } | ParseError::Unexpected { span, .. } => span.start(),
+        ParseError::General { position, .. } => position,
+        ParseError::Lex { err: LexError::Syntax(_, position) } => position,
+        _ => return None,
+    };
+    Some((position.line_number(), position.column_number()))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConsoleLevel {
+    Log,
+    Info,
+    Warn,
+    Error,
+    Debug,
+    Result, // Результат вычисления из консоли devtools
+}
+
+impl ConsoleLevel {
+    pub const ALL: [ConsoleLevel; 5] = [Self::Log, Self::Info, Self::Warn, Self::Error, Self::Debug];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Log => "log",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+            Self::Debug => "debug",
+            Self::Result => "result",
+        }
     }
-    async fn get_session(&self, _session_id: &str) -> Result<Option<String>> {
-        log::info!("Получение сессии: {}", _session_id);
-        Ok(None)
+}
+
+#[derive(Clone, Debug)]
+pub struct ConsoleMessage {
+    pub page: PageId,
+    pub level: ConsoleLevel,
+    pub text: String,
+}
+
+// Объекты хоста, доступные скриптам realm
+#[derive(Clone, Default)]
+pub struct RealmBindings {
+    pub dom: Option<SharedDom>,
+    pub local_storage: Option<Arc<StorageArea>>,
+    pub session_storage: Option<Arc<StorageArea>>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RealmOptions {
+    // Math.random с фиксированным зерном и неподвижный Date.now для воспроизводимых прогонов
+    pub deterministic: bool,
+    pub seed: u32,
+    pub fixed_time_ms: u64,
+    // CSP без 'unsafe-eval': eval и new Function бросают EvalError
+    pub block_eval: bool,
+}
+
+enum RealmCommand {
+    Eval { script: String, reply: oneshot::Sender<Result<String, ScriptError>> },
+    EvalModule { source: String, reply: oneshot::Sender<Result<(), ScriptError>> },
+    // Ошибки задачи уходят в консоль страницы; done закрывается по её окончании
+    Task { script: String, done: oneshot::Sender<()> },
+}
+
+pub type FetchCompletion = (u32, Result<NetResponse, String>);
+
+// Обращения realm к движку. Ответ приходит в цикл событий realm по каналу reply
+pub enum HostRequest {
+    Fetch {
+        page: PageId,
+        id: u32,
+        request: NetRequest,
+        initiator: RequestInitiator,
+        reply: mpsc::UnboundedSender<FetchCompletion>,
+    },
+    StorageChanged(StorageChange),
+    // Запись в localStorage отклонена по квоте
+    QuotaExceeded { page: PageId },
+    // Строковый обработчик таймера отклонён: CSP страницы запрещает eval
+    EvalBlocked { page: PageId, sample: String },
+}
+
+// Состояние realm, общее для его потока и сторожа JsRuntime
+#[derive(Default)]
+pub(crate) struct RealmControl {
+    // Начало выполняемой задачи; None, пока realm ждёт событий
+    busy_since: Mutex<Option<Instant>>,
+    aborted: AtomicBool,
+}
+
+impl RealmControl {
+    fn busy_for(&self) -> Option<Duration> {
+        self.busy_since.lock().map(|since| since.elapsed())
     }
-    async fn delete_session(&self, _session_id: &str) -> Result<()> {
-        log::info!("Удаление сессии: {}", _session_id);
-        Ok(())
+
+    #[cfg(feature = "js")]
+    fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::Acquire)
     }
 }
 
-// === FILE: core\ssh.rs ===
-use ssh2::Session;
-use std::net::TcpStream;
-use anyhow::Result;
-use std::sync::{Arc, Mutex};
-use std::io::{Read, Write};
-use tokio::sync::mpsc::UnboundedReceiver;
-pub struct SshClient {
-    session: Arc<Mutex<Session>>,
+// Context boa не Send, поэтому каждый realm живёт в собственном потоке
+// со своим однопоточным tokio runtime, который ведёт очередь задач и таймеры
+struct RealmHandle {
+    commands: mpsc::UnboundedSender<RealmCommand>,
+    control: Arc<RealmControl>,
+    // Для замены зависшего realm новым с теми же объектами хоста
+    options: RealmOptions,
+    bindings: RealmBindings,
 }
-impl SshClient {
-    pub fn connect(user: &str, host: &str) -> Result<Self> {
-        let tcp = TcpStream::connect(host)?;
-        let mut session = Session::new()?;
-        session.set_tcp_stream(tcp);
-        session.handshake()?;
-        session.userauth_agent(user)?;
-        Ok(Self {
-            session: Arc::new(Mutex::new(session)),
-        })
+
+// Число ещё работающих потоков прерванных realm по страницам: зависшая страница
+// не лишает JS остальные
+#[derive(Default)]
+struct Abandoned(Mutex<HashMap<PageId, usize>>);
+
+impl Abandoned {
+    #[cfg(feature = "js")]
+    fn count(&self, page: PageId) -> usize {
+        self.0.lock().get(&page).copied().unwrap_or(0)
     }
-    pub async fn run_command(&self, cmd: &str) -> Result<String> {
-        let cmd = cmd.to_string(); // скопировали команду для 'static
-        let session = self.session.clone();
-        tokio::task::spawn_blocking(move || -> Result<String> {
-            let sess = session.lock().unwrap();
-            let mut channel = sess.channel_session()?;
-            channel.exec(&cmd)?;
-            let mut output = String::new();
-            channel.read_to_string(&mut output)?;
-            channel.wait_close()?;
-            Ok(output)
-        }).await?
+
+    fn add(&self, page: PageId) {
+        *self.0.lock().entry(page).or_default() += 1;
     }
-    pub async fn interactive_shell(&self, mut rx: UnboundedReceiver<String>) -> Result<String> {
-        let session = self.session.clone();
-        tokio::task::spawn_blocking(move || -> Result<String> {
-            let sess = session.lock().unwrap();
-            let mut channel = sess.channel_session()?;
-            channel.request_pty("xterm", None, None)?;
-            channel.shell()?;
-            let mut buffer = Vec::new();
-            let mut tmp = [0u8; 1024];
-            loop {
-                if let Ok(cmd) = rx.try_recv() {
-                    channel.write_all(cmd.as_bytes())?;
-                    channel.write_all(b"\n")?;
-                }
-                let n = channel.read(&mut tmp)?;
-                if n == 0 { break; }
-                buffer.extend_from_slice(&tmp[..n]);
+
+    #[cfg(feature = "js")]
+    fn remove(&self, page: PageId) {
+        let mut counts = self.0.lock();
+        if let Some(count) = counts.get_mut(&page) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&page);
             }
-            channel.wait_close()?;
-            Ok(String::from_utf8_lossy(&buffer).to_string())
-        }).await?
+        }
     }
 }
 
-// === FILE: core\wasm_manifest.rs ===
-use crate::core::interfaces::WasmManifestTrait;
-use anyhow::Result;
-use std::path::Path;
-use async_trait::async_trait;
-use serde_yaml::Value;
-pub struct WasmManifest;
-#[async_trait]
-impl WasmManifestTrait for WasmManifest {
-    async fn new() -> Result<Self> {
-        log::info!("Менеджер WASM-манифестов инициализирован");
-        Ok(Self)
+struct Realms {
+    events: Option<BroadcastSender<EngineEvent>>,
+    host: Option<mpsc::UnboundedSender<HostRequest>>,
+    handles: Mutex<HashMap<PageId, RealmHandle>>,
+    abandoned: Arc<Abandoned>,
+}
+
+impl Realms {
+    #[cfg(feature = "js")]
+    fn spawn(&self, page: PageId, options: RealmOptions, bindings: RealmBindings) -> Result<RealmHandle> {
+        if self.abandoned.count(page) >= MAX_ABANDONED_REALMS {
+            return Err(anyhow::anyhow!("Слишком много прерванных скриптов страницы {} ещё выполняется, JS realm не создан", page));
+        }
+        let (commands, rx) = mpsc::unbounded_channel();
+        let control = Arc::new(RealmControl::default());
+        let realm = RealmThread {
+            page,
+            options: options.clone(),
+            bindings: bindings.clone(),
+            events: self.events.clone(),
+            host: self.host.clone(),
+            control: control.clone(),
+            abandoned: self.abandoned.clone(),
+        };
+        std::thread::Builder::new()
+            .name(format!("js-realm-{}", page))
+            .spawn(move || run_realm(realm, rx))?;
+        Ok(RealmHandle { commands, control, options, bindings })
     }
-    async fn load_yaml(&self, _path: &Path) -> Result<Value> {
-        log::info!("Загрузка WASM-манифеста из {:?}", _path);
-        Ok(Value::Null)
+
+    #[cfg(not(feature = "js"))]
+    fn spawn(&self, _page: PageId, _options: RealmOptions, _bindings: RealmBindings) -> Result<RealmHandle> {
+        let _ = (&self.events, &self.host);
+        Err(anyhow::anyhow!("JS runtime собран без feature \"js\""))
     }
-    async fn load_oci(&self, _oci_ref: &str) -> Result<Value> {
-        log::info!("Загрузка WASM-манифеста из OCI: {}", _oci_ref);
-        Ok(Value::Null)
+
+    // boa не прерывает байткод извне, поэтому зависший realm помечается прерванным:
+    // любой вызов хоста в нём бросает неперехватываемую ошибку лимита, а странице
+    // сразу выдаётся новый realm с тем же документом и хранилищем
+    fn abort(&self, page: PageId, control: &Arc<RealmControl>) {
+        let old = {
+            let mut handles = self.handles.lock();
+            match handles.get(&page) {
+                Some(handle) if Arc::ptr_eq(&handle.control, control) => handles.remove(&page),
+                _ => None,
+            }
+        };
+        let Some(old) = old else { return };
+        old.control.aborted.store(true, Ordering::Release);
+        self.abandoned.add(page);
+        log::warn!("Скрипт страницы {} превысил лимит времени {:?} и прерван", page, SCRIPT_TIMEOUT);
+        if let Some(events) = &self.events {
+            let _ = events.send(EngineEvent::ConsoleMessage(ConsoleMessage {
+                page,
+                level: ConsoleLevel::Error,
+                text: format!("Скрипт прерван: превышено время выполнения ({:?}), состояние скриптов страницы сброшено", SCRIPT_TIMEOUT),
+            }));
+        }
+        match self.spawn(page, old.options, old.bindings) {
+            Ok(handle) => {
+                self.handles.lock().entry(page).or_insert(handle);
+            }
+            Err(e) => log::error!("JS realm страницы {} не пересоздан: {}", page, e),
+        }
     }
-    async fn validate(&self, _manifest: &Value) -> Result<()> {
-        log::info!("Валидация WASM-манифеста");
-        Ok(())
+}
+
+// Сторож снимает задачи, которые выполняются дольше SCRIPT_TIMEOUT, включая задачи без ожидающего
+async fn watchdog(realms: Weak<Realms>) {
+    loop {
+        tokio::time::sleep(WATCHDOG_INTERVAL).await;
+        let Some(realms) = realms.upgrade() else { return };
+        let hung: Vec<(PageId, Arc<RealmControl>)> = realms
+            .handles
+            .lock()
+            .iter()
+            .filter(|(_, handle)| handle.control.busy_for().is_some_and(|busy| busy > SCRIPT_TIMEOUT))
+            .map(|(page, handle)| (*page, handle.control.clone()))
+            .collect();
+        for (page, control) in hung {
+            realms.abort(page, &control);
+        }
     }
 }
 
-// === FILE: core\yuaidb.rs ===
-use crate::core::interfaces::YuaidbTrait;
-use anyhow::Result;
-use std::path::{Path, PathBuf};
-use async_trait::async_trait;
-pub struct Yuaidb {
-    _db_dir: PathBuf,
+pub struct JsRuntime {
+    realms: Arc<Realms>,
+    watchdog_started: AtomicBool,
+}
+
+impl JsRuntime {
+    pub fn new(
+        events: Option<BroadcastSender<EngineEvent>>,
+        host: Option<mpsc::UnboundedSender<HostRequest>>,
+    ) -> Result<Self> {
+        log::info!("JS runtime инициализирован");
+        let realms = Realms { events, host, handles: Mutex::new(HashMap::new()), abandoned: Arc::new(Abandoned::default()) };
+        Ok(Self { realms: Arc::new(realms), watchdog_started: AtomicBool::new(false) })
+    }
+
+    async fn run_command<T: Send>(
+        &self,
+        page: PageId,
+        command: impl FnOnce(oneshot::Sender<Result<T, ScriptError>>) -> RealmCommand + Send,
+    ) -> Result<T, ScriptError> {
+        let (reply, response) = oneshot::channel();
+        let control = {
+            let handles = self.realms.handles.lock();
+            let realm = handles
+                .get(&page)
+                .ok_or_else(|| ScriptError::new(format!("JS realm страницы {} не создан", page)))?;
+            realm
+                .commands
+                .send(command(reply))
+                .map_err(|_| ScriptError::new("JS realm завершён"))?;
+            realm.control.clone()
+        };
+        // Ожидание включает очередь realm; само выполнение ограничивает сторож
+        match tokio::time::timeout(SCRIPT_TIMEOUT, response).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(ScriptError::new("JS realm завершился во время выполнения скрипта")),
+            Err(_) => {
+                self.realms.abort(page, &control);
+                Err(ScriptError::new(format!("Превышено время выполнения скрипта ({:?})", SCRIPT_TIMEOUT)))
+            }
+        }
+    }
 }
+
 #[async_trait]
-impl YuaidbTrait for Yuaidb {
-    async fn new(db_dir: impl AsRef<Path> + Send) -> Result<Self> {
-        let db_dir = db_dir.as_ref().to_path_buf();
-        log::info!("База данных инициализирована с каталогом: {:?}", db_dir);
-        Ok(Self { _db_dir: db_dir })
+impl JsRuntimeTrait for JsRuntime {
+    async fn execute_script(&self, script: &str) -> Result<()> {
+        if !self.realms.handles.lock().contains_key(&PageId::DEFAULT) {
+            self.create_realm(PageId::DEFAULT, RealmOptions::default(), RealmBindings::default()).await?;
+        }
+        self.evaluate(PageId::DEFAULT, script).await?;
+        Ok(())
     }
-    async fn insert(&self, _key: &str, _value: &str) -> Result<()> {
-        log::info!("Вставка данных в db: {} -> {}", _key, _value);
+
+    async fn create_realm(&self, page: PageId, options: RealmOptions, bindings: RealmBindings) -> Result<()> {
+        if !self.watchdog_started.swap(true, Ordering::AcqRel) {
+            tokio::spawn(watchdog(Arc::downgrade(&self.realms)));
+        }
+        let handle = self.realms.spawn(page, options, bindings)?;
+        // Старый realm страницы завершится, когда его канал команд закроется
+        self.realms.handles.lock().insert(page, handle);
+        log::debug!("Создан JS realm для страницы {}", page);
         Ok(())
     }
-    async fn get(&self, _key: &str) -> Result<Option<String>> {
-        log::info!("Получение данных: {}", _key);
-        Ok(None)
+
+    async fn destroy_realm(&self, page: PageId) {
+        if self.realms.handles.lock().remove(&page).is_some() {
+            log::debug!("JS realm страницы {} удалён", page);
+        }
     }
-    async fn delete(&self, _key: &str) -> Result<()> {
-        log::info!("Удаление данных: {}", _key);
-        Ok(())
+
+    async fn evaluate(&self, page: PageId, script: &str) -> Result<String, ScriptError> {
+        let script = script.to_string();
+        self.run_command(page, |reply| RealmCommand::Eval { script, reply }).await
     }
-}
 
-// === FILE: dom\css_parser.rs ===
-use anyhow::Result;
-use std::sync::Arc;
-use crate::net::Network;
-pub struct CSSparser;
-impl CSSparser {
-    pub fn new(_network: Arc<Network>) -> Result<Self> {
-        println!("CSSparser initialized");
-        Ok(Self)
+    async fn evaluate_module(&self, page: PageId, source: &str) -> Result<(), ScriptError> {
+        let source = source.to_string();
+        self.run_command(page, |reply| RealmCommand::EvalModule { source, reply }).await
+    }
+
+    async fn execute_task(&self, page: PageId, script: &str) -> Result<()> {
+        let (done, finished) = oneshot::channel();
+        {
+            let handles = self.realms.handles.lock();
+            let realm = handles
+                .get(&page)
+                .ok_or_else(|| anyhow::anyhow!("JS realm страницы {} не создан", page))?;
+            realm
+                .commands
+                .send(RealmCommand::Task { script: script.to_string(), done })
+                .map_err(|_| anyhow::anyhow!("JS realm страницы {} завершён", page))?;
+        }
+        finished
+            .await
+            .map_err(|_| anyhow::anyhow!("JS realm страницы {} завершён до выполнения задачи", page))
     }
 }
 
-// === FILE: dom\hydrate.rs ===
-// === FILE: dom/hydrate.rs ===
-use crate::dom::parser::{ParsedNode, FrameworkType};
-use crate::dom::tree::DomRenderer;
-use crate::core::interfaces::ReactiveCoreTrait;
-use crate::core::reactive::ReactiveCore; 
-use egui::Ui;
-use anyhow::Result;
-use std::sync::Arc;
-use once_cell::sync::Lazy;
-use std::sync::Mutex;
+#[cfg(feature = "js")]
+struct RealmThread {
+    page: PageId,
+    options: RealmOptions,
+    bindings: RealmBindings,
+    events: Option<BroadcastSender<EngineEvent>>,
+    host: Option<mpsc::UnboundedSender<HostRequest>>,
+    control: Arc<RealmControl>,
+    abandoned: Arc<Abandoned>,
+}
 
-// Статические переменные для состояния гидрации и логов
-static HYDRATION_STATE: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
-static LOG_BUFFER: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+// Отметка выполняемой задачи для сторожа; снимается и при выходе по ошибке
+#[cfg(feature = "js")]
+struct BusyGuard<'a>(&'a RealmControl);
 
-// Функция гидрации и рендеринга DOM
-pub async fn hydrate_and_render(ui: &mut Ui, dom_renderer: &mut DomRenderer, mut node: ParsedNode) -> Result<Option<FrameworkType>> {
-    // Очищаем буфер логов для текущего вызова
-    let mut log_buffer = LOG_BUFFER.lock().unwrap();
-    log_buffer.clear();
+#[cfg(feature = "js")]
+impl<'a> BusyGuard<'a> {
+    fn new(control: &'a RealmControl) -> Self {
+        *control.busy_since.lock() = Some(Instant::now());
+        Self(control)
+    }
+}
 
-    // Переменная для хранения типа фреймворка
-    let mut detected_framework: Option<FrameworkType> = None;
+#[cfg(feature = "js")]
+impl Drop for BusyGuard<'_> {
+    fn drop(&mut self) {
+        *self.0.busy_since.lock() = None;
+    }
+}
 
-    // Проверяем, нужно ли выполнять гидрацию
-    let should_hydrate = match &node {
-        ParsedNode::Element { framework, attrs, .. } => {
-            if let Some(fw) = framework {
-                let mut state = HYDRATION_STATE.lock().unwrap();
-                let node_key = format!("{:?}-{:?}", fw, attrs.get("id").unwrap_or(&String::new()));
-                if state.contains(&node_key) {
-                    false // Гидрация уже выполнена для этого узла
-                } else {
-                    state.push(node_key);
-                    // Сохраняем фреймворк
-                    detected_framework = Some(fw.clone());
-                    // Добавляем лог о распознанном фреймворке
-                    log_buffer.push(match fw {
-                        FrameworkType::ReactNext => "Обнаружен React/Next.js, гидрация состояния".to_string(),
-                        FrameworkType::Angular => "Обнаружен Angular, привязка компонентов".to_string(),
-                        FrameworkType::VueNuxt => "Обнаружен Vue/Nuxt, создание stores".to_string(),
-                        FrameworkType::SvelteKit => "Обнаружен SvelteKit, применение хешей".to_string(),
-                        FrameworkType::WasmModule => "Обнаружен WASM-модуль, загрузка".to_string(),
-                    });
-                    true
-                }
-            } else {
-                false
-            }
+#[cfg(feature = "js")]
+fn run_realm(realm: RealmThread, commands: mpsc::UnboundedReceiver<RealmCommand>) {
+    let RealmThread { page, control, abandoned, .. } = &realm;
+    let (page, control, abandoned) = (*page, control.clone(), abandoned.clone());
+    run_realm_loop(realm, commands);
+    if control.is_aborted() {
+        abandoned.remove(page);
+        log::info!("Поток прерванного JS realm страницы {} завершён", page);
+    }
+}
+
+#[cfg(feature = "js")]
+fn run_realm_loop(realm: RealmThread, mut commands: mpsc::UnboundedReceiver<RealmCommand>) {
+    let RealmThread { page, options, bindings, events, host, control, .. } = realm;
+    let runtime = match tokio::runtime::Builder::new_current_thread().enable_time().build() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            log::error!("Не удалось запустить цикл событий JS realm {}: {}", page, e);
+            return;
         }
-        _ => false,
     };
+    let mut context = Context::default();
+    context.runtime_limits_mut().set_loop_iteration_limit(LOOP_ITERATION_LIMIT);
+    context.runtime_limits_mut().set_recursion_limit(RECURSION_LIMIT);
+    if let Err(e) = install_console(&mut context, page, events.clone(), control.clone()) {
+        log::error!("Не удалось установить console для страницы {}: {}", page, e);
+    }
+    let timers = Rc::new(RefCell::new(TimerQueue::default()));
+    let eval_blocked = options.block_eval.then(|| (page, host.clone()));
+    if let Err(e) = install_timers(&mut context, timers.clone(), eval_blocked, control.clone()) {
+        log::error!("Не удалось установить таймеры для страницы {}: {}", page, e);
+    }
+    let (completions_tx, mut completions) = mpsc::unbounded_channel();
+    if let Err(e) = install_net(&mut context, page, host.clone(), completions_tx, control.clone()) {
+        log::error!("Не удалось установить fetch для страницы {}: {}", page, e);
+    }
+    if let Some(dom) = bindings.dom {
+        if let Err(e) = install_dom(&mut context, dom, control.clone()) {
+            log::error!("Не удалось установить DOM API для страницы {}: {}", page, e);
+        }
+    }
+    if let Err(e) = install_storage(&mut context, page, bindings.local_storage, bindings.session_storage, host, control.clone()) {
+        log::error!("Не удалось установить Web Storage для страницы {}: {}", page, e);
+    }
+    if options.block_eval {
+        if let Err(e) = context.eval(Source::from_bytes(BLOCK_EVAL_PRELUDE.as_bytes())) {
+            log::error!("Не удалось запретить eval для страницы {}: {}", page, e);
+        }
+    }
+    if options.deterministic {
+        let prelude = deterministic_prelude(options.seed, options.fixed_time_ms);
+        if let Err(e) = context.eval(Source::from_bytes(prelude.as_bytes())) {
+            log::error!("Не удалось включить детерминированный режим для страницы {}: {}", page, e);
+        }
+    }
 
-    // Выполняем гидрацию, если требуется
-    if should_hydrate {
-        let reactive_core = Arc::new(ReactiveCore);
-        if let ParsedNode::Element { framework, reactive_state, .. } = &mut node {
-            if let Some(fw) = framework {
-                match fw {
-                    FrameworkType::ReactNext => {
-                        if let Some(state) = reactive_state {
-                            reactive_core.create_signals(state)?;
-                            log_buffer.push("Гидрация React завершена".to_string());
-                        }
+    // Каждая задача завершается контрольной точкой микрозадач (run_jobs). Цикл
+    // заканчивается вместе с каналом команд, и незапущенные таймеры отбрасываются
+    runtime.block_on(async {
+        loop {
+            let deadline = timers.borrow().next_deadline();
+            let wake = tokio::select! {
+                command = commands.recv() => match command {
+                    Some(command) => Wake::Command(command),
+                    None => break,
+                },
+                Some(completion) = completions.recv() => Wake::Fetch(completion),
+                _ = wait_until(deadline) => Wake::Timer,
+            };
+            // Прерванный realm не начинает новых задач
+            if control.is_aborted() {
+                break;
+            }
+            let _busy = BusyGuard::new(&control);
+            match wake {
+                Wake::Command(RealmCommand::Eval { script, reply }) => {
+                    let result = context
+                        .eval(Source::from_bytes(script.as_bytes()))
+                        .map(|value| value.display().to_string())
+                        .map_err(|err| ScriptError::from_eval(err, &script, &mut context));
+                    context.run_jobs();
+                    let _ = reply.send(result);
+                }
+                Wake::Command(RealmCommand::EvalModule { source, reply }) => {
+                    let _ = reply.send(run_module(&mut context, &source));
+                }
+                // Планировщик отказался от задачи (срок или отмена) до её начала
+                Wake::Command(RealmCommand::Task { done, .. }) if done.is_closed() => {}
+                Wake::Command(RealmCommand::Task { script, done }) => {
+                    let result = context.eval(Source::from_bytes(script.as_bytes()));
+                    context.run_jobs();
+                    if let Err(err) = result {
+                        report_uncaught(&events, page, ScriptError::from_eval(err, &script, &mut context));
                     }
-                    FrameworkType::Angular => {
-                        reactive_core.bind_angular_scopes(&node)?;
-                        log_buffer.push("Гидрация Angular завершена".to_string());
+                    let _ = done.send(());
+                }
+                Wake::Fetch((id, result)) => {
+                    let result = complete_fetch(&mut context, id, result);
+                    context.run_jobs();
+                    if let Err(err) = result {
+                        report_uncaught(&events, page, ScriptError::from_js(err, &mut context));
                     }
-                    FrameworkType::VueNuxt => {
-                        if let Some(state) = reactive_state {
-                            reactive_core.create_stores(state)?;
-                            log_buffer.push("Гидрация Vue завершена".to_string());
+                }
+                Wake::Timer => {
+                    let due = timers.borrow_mut().take_due(Instant::now());
+                    if let Some((id, repeat)) = due {
+                        let script = format!("__yuaiRunTimer({}, {})", id, repeat);
+                        let result = context.eval(Source::from_bytes(script.as_bytes()));
+                        context.run_jobs();
+                        timers.borrow_mut().finish_running();
+                        if let Err(err) = result {
+                            report_uncaught(&events, page, ScriptError::from_js(err, &mut context));
                         }
                     }
-                    FrameworkType::SvelteKit => {
-                        reactive_core.apply_svelte_hashes(&node)?;
-                        log_buffer.push("Гидрация Svelte завершена".to_string());
-                    }
-                    FrameworkType::WasmModule => {
-                        reactive_core.load_wasm_module(&node)?;
-                        log_buffer.push("WASM-модуль загружен".to_string());
-                    }
                 }
-                // Инкрементальный рендеринг без VDOM
-                reactive_core.incremental_render(ui, &node).await?;
-                log_buffer.push("Инкрементальный рендер без VDOM".to_string());
             }
         }
-    }
+    });
+    log::debug!("JS realm страницы {} остановлен", page);
+}
 
-    // Рендерим узел
-    dom_renderer.render_node(ui, &node).await;
+#[cfg(feature = "js")]
+enum Wake {
+    Command(RealmCommand),
+    Fetch(FetchCompletion),
+    Timer,
+}
 
-    // Выводим все накопленные логи
-    for log in log_buffer.iter() {
-        log::info!("{}", log);
+#[cfg(feature = "js")]
+async fn wait_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => std::future::pending().await,
     }
-
-    Ok(detected_framework)
 }
 
-// === FILE: dom\layout.rs ===
-use anyhow::Result;
-use std::sync::Arc;
-use crate::net::Network;
-pub struct Layout;
-impl Layout {
-    pub fn new(_network: Arc<Network>) -> Result<Self> {
-        println!("Layout initialized");
-        Ok(Self)
+#[cfg(feature = "js")]
+fn report_uncaught(events: &Option<BroadcastSender<EngineEvent>>, page: PageId, error: ScriptError) {
+    log::debug!("Необработанная ошибка задачи страницы {}: {}", page, error);
+    if let Some(events) = events {
+        let _ = events.send(EngineEvent::ConsoleMessage(ConsoleMessage {
+            page,
+            level: ConsoleLevel::Error,
+            text: format!("Uncaught {}", error),
+        }));
     }
 }
 
-// === FILE: dom\mod.rs ===
-pub mod parser;
-pub mod css_parser;
-pub mod tree;
-pub mod style;
-pub mod hydrate;
-pub mod vdom;
-pub mod mutation;
-pub mod layout;
+#[cfg(feature = "js")]
+struct Timer {
+    deadline: Instant,
+    interval: Option<Duration>,
+    nesting: u32,
+}
 
+// Расписание таймеров realm; сами колбэки хранятся на стороне JS в TIMER_PRELUDE
+#[cfg(feature = "js")]
+#[derive(Default)]
+struct TimerQueue {
+    timers: HashMap<u32, Timer>,
+    next_id: u32,
+    running_nesting: u32, // Уровень вложенности выполняемого сейчас таймера
+}
 
-// === FILE: dom\mutation.rs ===
-use anyhow::Result;
-use std::sync::Arc;
-use crate::net::Network;
-pub struct Mutation;
-impl Mutation {
-    pub fn new(_network: Arc<Network>) -> Result<Self> {
-        println!("Mutation initialized");
-        Ok(Self)
+#[cfg(feature = "js")]
+impl TimerQueue {
+    fn clamp(nesting: u32, delay: Duration) -> Duration {
+        if nesting > TIMER_NESTING_THRESHOLD {
+            delay.max(TIMER_MIN_DELAY)
+        } else {
+            delay
+        }
     }
-}
 
-// === FILE: dom\parser.rs ===
-use html5ever::parse_document;
-use html5ever::tendril::TendrilSink;
-use markup5ever_rcdom::{Handle, NodeData, RcDom};
-use anyhow::Result;
-use std::collections::HashMap;
-use serde_json::Value as JsonValue;
-use tokio::sync::mpsc::{unbounded_channel, UnboundedSender, UnboundedReceiver}; 
-use once_cell::sync::Lazy;
-use std::sync::Mutex;
+    fn schedule(&mut self, delay_ms: f64, repeat: bool) -> u32 {
+        self.next_id += 1;
+        let nesting = self.running_nesting + 1;
+        let delay = if delay_ms.is_finite() && delay_ms > 0.0 {
+            Duration::from_secs_f64(delay_ms.min(i32::MAX as f64) / 1000.0)
+        } else {
+            Duration::ZERO
+        };
+        let delay = Self::clamp(nesting, delay);
+        let timer = Timer {
+            deadline: Instant::now() + delay,
+            interval: repeat.then_some(delay),
+            nesting,
+        };
+        self.timers.insert(self.next_id, timer);
+        self.next_id
+    }
 
-#[derive(Clone, Debug)]
-pub enum ParsedNode {
-    Text(String),
-    Element {
-        tag: String,
-        attrs: HashMap<String, String>,
-        children: Vec<ParsedNode>,
-        reactive_state: Option<JsonValue>,
-        framework: Option<FrameworkType>,
-    },
-}
+    fn clear(&mut self, id: u32) {
+        self.timers.remove(&id);
+    }
 
-#[derive(Clone, Debug, PartialEq)]
-pub enum FrameworkType {
-    ReactNext,
-    Angular,
-    VueNuxt,
-    SvelteKit,
-    WasmModule,
-}
+    fn next_deadline(&self) -> Option<Instant> {
+        self.timers.values().map(|timer| timer.deadline).min()
+    }
 
-// Канал для передачи типа фреймворка
-static FRAMEWORK_CHANNEL: Lazy<(UnboundedSender<FrameworkType>, Mutex<Option<UnboundedReceiver<FrameworkType>>>)> =
-    Lazy::new(|| {
-        let (tx, rx) = unbounded_channel();
-        (tx, Mutex::new(Some(rx))) 
-    });
+    // Среди просроченных первым идёт таймер с ранним сроком, при равенстве — созданный раньше
+    fn take_due(&mut self, now: Instant) -> Option<(u32, bool)> {
+        let id = self
+            .timers
+            .iter()
+            .filter(|(_, timer)| timer.deadline <= now)
+            .min_by_key(|(id, timer)| (timer.deadline, **id))
+            .map(|(id, _)| *id)?;
+        let timer = self.timers.get_mut(&id)?;
+        self.running_nesting = timer.nesting;
+        match timer.interval {
+            Some(interval) => {
+                timer.nesting += 1;
+                let interval = Self::clamp(timer.nesting, interval);
+                timer.interval = Some(interval);
+                timer.deadline = now + interval;
+                Some((id, true))
+            }
+            None => {
+                self.timers.remove(&id);
+                Some((id, false))
+            }
+        }
+    }
 
-pub fn get_framework_receiver() -> UnboundedReceiver<FrameworkType> {
-    let mut lock = FRAMEWORK_CHANNEL.1.lock().expect("Ошибка блокировки Mutex");
-    lock.take().expect("Приёмник фреймворка уже был извлечён")
+    fn finish_running(&mut self) {
+        self.running_nesting = 0;
+    }
 }
 
-pub fn parse_and_process(html: &str) -> Result<(ParsedNode, Option<FrameworkType>)> {
-    let dom = parse_document(RcDom::default(), Default::default()).one(html);
+// Данные хоста в нативных функциях. Объектов сборщика мусора boa в них нет,
+// поэтому трассировка не нужна, и функции создаются без unsafe from_closure
+#[cfg(feature = "js")]
+#[derive(Trace, Finalize)]
+struct HostData<T: 'static> {
+    #[unsafe_ignore_trace]
+    value: T,
+}
 
-    if let Some(body) = extract_body(&dom) {
-        let mut node = process_node(body);
-        let detected_framework = hydrate_framework_markers(&mut node, html)?;
-        Ok((node, detected_framework)) // возвращаем FrameworkType наружу
-    } else {
-        Ok((ParsedNode::Text("".into()), None))
+#[cfg(feature = "js")]
+impl<T: 'static> HostData<T> {
+    fn new(value: T) -> Self {
+        Self { value }
     }
 }
 
-fn extract_body(dom: &RcDom) -> Option<Handle> {
-    fn find_body(handle: &Handle) -> Option<Handle> {
-        match &handle.data {
-            NodeData::Element { name, .. } if &*name.local == "body" => Some(handle.clone()),
-            _ => handle
-                .children
-                .borrow()
-                .iter()
-                .find_map(|child| find_body(child)),
+// Вызов хоста из прерванного realm завершает скрипт: ошибку лимита нельзя перехватить в JS
+#[cfg(feature = "js")]
+fn check_alive(control: &RealmControl) -> JsResult<()> {
+    if control.is_aborted() {
+        return Err(JsNativeError::runtime_limit()
+            .with_message("Скрипт прерван: превышено время выполнения")
+            .into());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "js")]
+type TimerData = HostData<(Rc<RefCell<TimerQueue>>, Arc<RealmControl>)>;
+
+#[cfg(feature = "js")]
+type EvalBlockedData = HostData<(PageId, Option<mpsc::UnboundedSender<HostRequest>>, Arc<RealmControl>)>;
+
+// eval_blocked задан, если CSP страницы запрещает eval: строковые обработчики
+// setTimeout/setInterval тогда не планируются, а нарушение уходит движку
+#[cfg(feature = "js")]
+fn install_timers(
+    context: &mut Context,
+    timers: Rc<RefCell<TimerQueue>>,
+    eval_blocked: Option<(PageId, Option<mpsc::UnboundedSender<HostRequest>>)>,
+    control: Arc<RealmControl>,
+) -> JsResult<()> {
+    let natives = {
+        let schedule = NativeFunction::from_copy_closure_with_captures(
+            |_this, args, data: &TimerData, ctx| {
+                let (timers, control) = &data.value;
+                check_alive(control)?;
+                let delay = args.get_or_undefined(0).to_number(ctx)?;
+                let repeat = args.get_or_undefined(1).to_boolean();
+                Ok(JsValue::from(timers.borrow_mut().schedule(delay, repeat)))
+            },
+            HostData::new((timers.clone(), control.clone())),
+        );
+        let clear = NativeFunction::from_copy_closure_with_captures(
+            |_this, args, data: &TimerData, ctx| {
+                let (timers, control) = &data.value;
+                check_alive(control)?;
+                timers.borrow_mut().clear(args.get_or_undefined(0).to_u32(ctx)?);
+                Ok(JsValue::undefined())
+            },
+            HostData::new((timers, control.clone())),
+        );
+        let mut natives = ObjectInitializer::new(context);
+        natives.function(schedule, js_string!("schedule"), 2);
+        natives.function(clear, js_string!("clear"), 1);
+        if let Some((page, host)) = eval_blocked {
+            let report = NativeFunction::from_copy_closure_with_captures(
+                |_this, args, data: &EvalBlockedData, ctx| {
+                    let (page, host, control) = &data.value;
+                    check_alive(control)?;
+                    let sample = args.get_or_undefined(0).to_string(ctx)?.to_std_string_escaped();
+                    if let Some(host) = host {
+                        let _ = host.send(HostRequest::EvalBlocked { page: *page, sample });
+                    }
+                    Ok(JsValue::undefined())
+                },
+                HostData::new((page, host, control)),
+            );
+            natives.function(report, js_string!("evalBlocked"), 1);
+        }
+        natives.build()
+    };
+    context.register_global_property(js_string!("__timers"), natives, Attribute::empty())?;
+    context.eval(Source::from_bytes(TIMER_PRELUDE.as_bytes()))?;
+    Ok(())
+}
+
+#[cfg(feature = "js")]
+const TIMER_PRELUDE: &str = r#"(() => {
+    const timers = __timers;
+    const callbacks = new Map();
+    const schedule = (repeat) => (handler, delay, ...args) => {
+        if (typeof handler !== "function" && timers.evalBlocked) {
+            // Как в браузерах: строка не выполняется, таймер не создаётся
+            console.error("setTimeout/setInterval со строкой заблокирован CSP: нет 'unsafe-eval'");
+            timers.evalBlocked(String(handler));
+            return 0;
         }
+        const callback = typeof handler === "function" ? () => handler(...args) : () => (0, eval)(String(handler));
+        const id = timers.schedule(Number(delay) || 0, repeat);
+        callbacks.set(id, callback);
+        return id;
+    };
+    const clear = (id) => {
+        id = Number(id) >>> 0;
+        timers.clear(id);
+        callbacks.delete(id);
+    };
+    globalThis.setTimeout = schedule(false);
+    globalThis.setInterval = schedule(true);
+    globalThis.clearTimeout = clear;
+    globalThis.clearInterval = clear;
+    globalThis.queueMicrotask = (callback) => {
+        Promise.resolve().then(() => callback()).catch((e) => console.error("Uncaught " + String(e)));
+    };
+    // Вызывается циклом событий realm, когда срок таймера наступил
+    globalThis.__yuaiRunTimer = (id, repeat) => {
+        const callback = callbacks.get(id);
+        if (!repeat) callbacks.delete(id);
+        if (callback) callback();
+    };
+})();"#;
+
+// Импорты не разрешаются: загрузчик модулей по умолчанию отклоняет любой specifier
+#[cfg(feature = "js")]
+fn run_module(context: &mut Context, source: &str) -> Result<(), ScriptError> {
+    let module = Module::parse(Source::from_bytes(source.as_bytes()), None, context)
+        .map_err(|err| ScriptError::from_js(err, context))?;
+    let promise = module.load_link_evaluate(context);
+    context.run_jobs();
+    match promise.state() {
+        PromiseState::Fulfilled(_) => Ok(()),
+        PromiseState::Rejected(reason) => Err(ScriptError::from_js(JsError::from_opaque(reason), context)),
+        PromiseState::Pending => Err(ScriptError::new("Модуль не завершил выполнение")),
     }
-    find_body(&dom.document)
 }
 
-fn process_node(handle: Handle) -> ParsedNode {
-    match &handle.data {
-        NodeData::Text { contents } => {
-            let text = contents.borrow().trim().to_string();
-            ParsedNode::Text(text)
+#[cfg(feature = "js")]
+fn install_console(
+    context: &mut Context,
+    page: PageId,
+    events: Option<BroadcastSender<EngineEvent>>,
+    control: Arc<RealmControl>,
+) -> boa_engine::JsResult<()> {
+    type ConsoleData = HostData<(PageId, ConsoleLevel, Option<BroadcastSender<EngineEvent>>, Arc<RealmControl>)>;
+    let console = {
+        let mut console = ObjectInitializer::new(context);
+        for level in ConsoleLevel::ALL {
+            let function = NativeFunction::from_copy_closure_with_captures(
+                |_this, args, data: &ConsoleData, _context| {
+                    let (page, level, events, control) = &data.value;
+                    check_alive(control)?;
+                    let text = args.iter().map(format_console_arg).collect::<Vec<_>>().join(" ");
+                    let message = ConsoleMessage { page: *page, level: *level, text };
+                    log::debug!("console.{} [{}]: {}", level.as_str(), page, message.text);
+                    if let Some(events) = events {
+                        let _ = events.send(EngineEvent::ConsoleMessage(message));
+                    }
+                    Ok(JsValue::undefined())
+                },
+                HostData::new((page, level, events.clone(), control.clone())),
+            );
+            console.function(function, JsString::from(level.as_str()), 0);
         }
-        NodeData::Element { name, attrs, .. } => {
-            let tag = name.local.to_lowercase();
-            if tag == "script" || tag == "style" {
-                return ParsedNode::Text("".into());
+        console.build()
+    };
+    context.register_global_property(js_string!("console"), console, Attribute::WRITABLE | Attribute::CONFIGURABLE)
+}
+
+// __net.send(method, url, headers, body, initiator) отдаёт запрос движку и возвращает его номер;
+// результат передаётся в __yuaiFetchDone из цикла событий realm
+#[cfg(feature = "js")]
+fn install_net(
+    context: &mut Context,
+    page: PageId,
+    host: Option<mpsc::UnboundedSender<HostRequest>>,
+    completions: mpsc::UnboundedSender<FetchCompletion>,
+    control: Arc<RealmControl>,
+) -> JsResult<()> {
+    struct NetHost {
+        page: PageId,
+        host: Option<mpsc::UnboundedSender<HostRequest>>,
+        completions: mpsc::UnboundedSender<FetchCompletion>,
+        next_id: std::cell::Cell<u32>,
+        control: Arc<RealmControl>,
+    }
+    let net = NetHost { page, host, completions, next_id: std::cell::Cell::new(0), control };
+    let send = NativeFunction::from_copy_closure_with_captures(
+        |_this, args, data: &HostData<NetHost>, ctx| {
+            let NetHost { page, host, completions, next_id, control } = &data.value;
+            check_alive(control)?;
+            let host = host
+                .as_ref()
+                .ok_or_else(|| JsNativeError::typ().with_message("Сеть недоступна для скриптов"))?;
+            let method = arg_string(args, 0, ctx)?.to_ascii_uppercase();
+            let url = arg_string(args, 1, ctx)?;
+            let mut request = NetRequest::get(&url);
+            request.method = method;
+            for line in arg_string(args, 2, ctx)?.lines() {
+                let Some((name, value)) = line.split_once(':') else { continue };
+                match (HeaderName::from_bytes(name.trim().as_bytes()), HeaderValue::from_str(value.trim())) {
+                    (Ok(name), Ok(value)) => {
+                        request.headers.append(name, value);
+                    }
+                    _ => return Err(JsNativeError::typ().with_message(format!("Недопустимый заголовок: {}", line)).into()),
+                }
             }
-            let mut attrs_map = HashMap::new();
-            for attr in attrs.borrow().iter() {
-                attrs_map.insert(attr.name.local.to_string(), attr.value.to_string());
+            if !args.get_or_undefined(3).is_null_or_undefined() {
+                request.body = Some(arg_string(args, 3, ctx)?.into_bytes());
             }
-            let children = handle
-                .children
-                .borrow()
+            let initiator = match arg_string(args, 4, ctx)?.as_str() {
+                "xhr" => RequestInitiator::XmlHttpRequest,
+                _ => RequestInitiator::Fetch,
+            };
+            request.credentials = match arg_string(args, 5, ctx)?.as_str() {
+                "omit" => Credentials::Omit,
+                "include" => Credentials::Include,
+                _ => Credentials::SameOrigin,
+            };
+            let id = next_id.get() + 1;
+            next_id.set(id);
+            host.send(HostRequest::Fetch { page: *page, id, request, initiator, reply: completions.clone() })
+                .map_err(|_| JsNativeError::typ().with_message("Движок не принимает запросы"))?;
+            Ok(JsValue::from(id))
+        },
+        HostData::new(net),
+    );
+    let natives = {
+        let mut natives = ObjectInitializer::new(context);
+        natives.function(send, js_string!("send"), 6);
+        natives.build()
+    };
+    context.register_global_property(js_string!("__net"), natives, Attribute::empty())?;
+    context.eval(Source::from_bytes(NET_PRELUDE.as_bytes()))?;
+    Ok(())
+}
+
+#[cfg(feature = "js")]
+fn complete_fetch(context: &mut Context, id: u32, result: Result<NetResponse, String>) -> JsResult<()> {
+    let done = context.global_object().get(js_string!("__yuaiFetchDone"), context)?;
+    let Some(done) = done.as_callable() else {
+        return Ok(());
+    };
+    let args = match result {
+        Ok(response) => {
+            let headers = response
+                .headers
                 .iter()
-                .map(|child| process_node(child.clone()))
-                .filter(|node| match node {
-                    ParsedNode::Text(s) => !s.is_empty(),
-                    _ => true,
-                })
-                .collect::<Vec<_>>();
-            ParsedNode::Element {
-                tag,
-                attrs: attrs_map,
-                children,
-                reactive_state: None,
-                framework: None,
-            }
+                .filter_map(|(name, value)| Some(format!("{}: {}", name, value.to_str().ok()?)))
+                .collect::<Vec<_>>()
+                .join("\n");
+            // Текст декодируется как UTF-8 с заменой, а исходные байты остаются для arrayBuffer()
+            let text = JsString::from(String::from_utf8_lossy(&response.body).as_ref());
+            let bytes = JsArrayBuffer::from_byte_block(response.body, context)?;
+            [
+                JsValue::from(id),
+                JsValue::null(),
+                JsValue::from(u32::from(response.status)),
+                JsValue::from(JsString::from(response.url.as_str())),
+                JsValue::from(JsString::from(headers.as_str())),
+                JsValue::from(text),
+                JsValue::from(bytes),
+            ]
         }
-        _ => ParsedNode::Text("".into()),
-    }
+        Err(message) => [
+            JsValue::from(id),
+            JsValue::from(JsString::from(message.as_str())),
+            JsValue::from(0),
+            JsValue::null(),
+            JsValue::null(),
+            JsValue::null(),
+            JsValue::null(),
+        ],
+    };
+    done.call(&JsValue::undefined(), &args, context)?;
+    Ok(())
 }
 
-fn hydrate_framework_markers(node: &mut ParsedNode, html: &str) -> Result<Option<FrameworkType>> {
-    if let ParsedNode::Element {
-        attrs,
-        reactive_state,
-        framework,
-        children,
-        ..
-    } = node
-    {
-        log::info!("Проверка маркеров фреймворка в элементе с атрибутами: {:?}", attrs);
+#[cfg(feature = "js")]
+const NET_PRELUDE: &str = r#"(() => {
+    const net = __net;
+    const pending = new Map();
+
+    class Headers {
+        constructor(init) {
+            this.__map = new Map();
+            if (init instanceof Headers) init.forEach((value, name) => this.append(name, value));
+            else if (Array.isArray(init)) init.forEach(([name, value]) => this.append(name, value));
+            else if (init) Object.keys(init).forEach((name) => this.append(name, init[name]));
+        }
+        append(name, value) {
+            const key = String(name).toLowerCase();
+            const current = this.__map.get(key);
+            this.__map.set(key, current === undefined ? String(value) : current + ", " + String(value));
+        }
+        set(name, value) { this.__map.set(String(name).toLowerCase(), String(value)); }
+        get(name) { const value = this.__map.get(String(name).toLowerCase()); return value === undefined ? null : value; }
+        has(name) { return this.__map.has(String(name).toLowerCase()); }
+        delete(name) { this.__map.delete(String(name).toLowerCase()); }
+        forEach(callback) { this.__map.forEach((value, name) => callback(value, name, this)); }
+        entries() { return this.__map.entries(); }
+        keys() { return this.__map.keys(); }
+        values() { return this.__map.values(); }
+        [Symbol.iterator]() { return this.__map.entries(); }
+        __serialize() { return Array.from(this.__map, ([name, value]) => name + ": " + value).join("\n"); }
+        static __parse(text) {
+            const headers = new Headers();
+            String(text || "").split("\n").forEach((line) => {
+                const index = line.indexOf(":");
+                if (index > 0) headers.append(line.slice(0, index).trim(), line.slice(index + 1).trim());
+            });
+            return headers;
+        }
+    }
 
-        // --- ЛОГИКА ОБНАРУЖЕНИЯ ---
-        let detected_fw = if html.contains(r#"id="__NEXT_DATA__""#) {
-            // Извлечение состояния и обнаружение
-            if let Some(json_str) = extract_script_content(html, "__NEXT_DATA__") {
-                *reactive_state = Some(serde_json::from_str(&json_str)?);
-                log::info!("Обнаружен React/Next.js");
-                Some(FrameworkType::ReactNext)
-            } else {
-                log::info!("Маркер React/Next.js найден, но состояние не извлечено");
-                None
-            }
-        } else if attrs.contains_key("ng-version") {
-            log::info!("Обнаружен Angular");
-            Some(FrameworkType::Angular)
-        } else if html.contains("window.__NUXT__") {
-            // Извлечение состояния и обнаружение
-            if let Some(json_str) = extract_script_content(html, "__NUXT__") {
-                *reactive_state = Some(serde_json::from_str(&json_str)?);
-                log::info!("Обнаружен Vue/Nuxt");
-                Some(FrameworkType::VueNuxt)
-            } else {
-                log::info!("Маркер Vue/Nuxt найден, но состояние не извлечено");
-                None
-            }
-        } else if html.contains(r#"data-sveltekit-fetched"#) || attrs.contains_key("data-h") {
-            // Извлечение состояния и обнаружение
-            if let Some(json_str) = extract_script_content(html, "data-sveltekit-fetched") {
-                *reactive_state = Some(serde_json::from_str(&json_str)?);
-                log::info!("Обнаружен SvelteKit");
-                Some(FrameworkType::SvelteKit)
-            } else {
-                log::info!("Маркер SvelteKit найден, но состояние не извлечено");
-                None
-            }
-        } else if html.contains(".wasm") || html.contains(r#"<script type="module">"#) {
-            log::info!("Обнаружен WASM-модуль");
-            Some(FrameworkType::WasmModule)
-        } else {
-            None
-        };
+    class Request {
+        constructor(input, init = {}) {
+            const base = input instanceof Request ? input : null;
+            this.url = base ? base.url : String(input);
+            this.method = String(init.method || (base ? base.method : "GET")).toUpperCase();
+            this.headers = new Headers(init.headers || (base ? base.headers : undefined));
+            this.body = init.body !== undefined ? init.body : (base ? base.body : null);
+            this.credentials = String(init.credentials || (base ? base.credentials : "same-origin"));
+        }
+        clone() { return new Request(this); }
+    }
 
-        // Отправка обнаруженного фреймворка в канал
-        if let Some(fw) = &detected_fw {
-            if let Err(e) = FRAMEWORK_CHANNEL.0.send(fw.clone()) {
-                log::error!("Ошибка отправки типа фреймворка в канал: {}", e);
-            } else {
-                log::info!("Тип фреймворка отправлен в канал: {:?}", fw);
-            }
+    class Response {
+        constructor(body = null, init = {}) {
+            this.__body = body === null || body === undefined ? "" : String(body);
+            this.__bytes = init.__bytes || null;
+            this.status = init.status === undefined ? 200 : init.status;
+            this.statusText = init.statusText || "";
+            this.headers = new Headers(init.headers);
+            this.url = init.url || "";
+            this.ok = this.status >= 200 && this.status < 300;
+            this.bodyUsed = false;
         }
+        __consume() {
+            if (this.bodyUsed) return Promise.reject(new TypeError("Тело ответа уже прочитано"));
+            this.bodyUsed = true;
+            return Promise.resolve(this.__body);
+        }
+        text() { return this.__consume(); }
+        json() { return this.__consume().then((text) => JSON.parse(text)); }
+        arrayBuffer() {
+            return this.__consume().then((text) => {
+                if (this.__bytes) return this.__bytes.slice(0);
+                const bytes = Array.from(unescape(encodeURIComponent(text)), (c) => c.charCodeAt(0));
+                return new Uint8Array(bytes).buffer;
+            });
+        }
+        clone() { return new Response(this.__body, this); }
+    }
 
-        // --- КОНЕЦ ЛОГИКИ ОБНАРУЖЕНИЯ ---
+    const send = (request, initiator, callback) => {
+        const body = request.body === null || request.body === undefined ? null : String(request.body);
+        const id = net.send(request.method, request.url, request.headers.__serialize(), body, initiator, request.credentials);
+        pending.set(id, callback);
+    };
 
-        // 1. Сохраняем обнаруженный фреймворк в текущем узле (для DOM)
-        *framework = detected_fw.clone();
+    globalThis.Headers = Headers;
+    globalThis.Request = Request;
+    globalThis.Response = Response;
+    globalThis.fetch = (input, init) => new Promise((resolve, reject) => {
+        const request = new Request(input, init);
+        send(request, "fetch", (error, status, url, headers, body, bytes) => {
+            if (error !== null) reject(new TypeError("Ошибка сети: " + error));
+            else resolve(new Response(body, { status, url, headers: Headers.__parse(headers), __bytes: bytes }));
+        });
+    });
 
-        // 2. Если фреймворк обнаружен на этом уровне, возвращаем его
-        if detected_fw.is_some() {
-            return Ok(detected_fw);
+    class XMLHttpRequest {
+        constructor() {
+            this.readyState = 0;
+            this.status = 0;
+            this.responseText = "";
+            this.responseURL = "";
+            this.__headers = new Headers();
+            this.__responseHeaders = new Headers();
+            this.__listeners = {};
+            this.withCredentials = false;
         }
-
-        // 3. Рекурсия по детям, если фреймворк еще не найден
-        for child in children.iter_mut() {
-            if let Some(fw) = hydrate_framework_markers(child, html)? {
-                // Возвращаем первый найденный фреймворк и прекращаем поиск
-                return Ok(Some(fw));
-            }
+        get response() { return this.responseText; }
+        open(method, url) {
+            this.__request = { method: String(method).toUpperCase(), url: String(url) };
+            this.__headers = new Headers();
+            this.__setState(1);
+        }
+        setRequestHeader(name, value) { this.__headers.append(name, value); }
+        getResponseHeader(name) { return this.__responseHeaders.get(name); }
+        getAllResponseHeaders() { return this.__responseHeaders.__serialize(); }
+        addEventListener(type, handler) { (this.__listeners[type] = this.__listeners[type] || []).push(handler); }
+        removeEventListener(type, handler) {
+            const handlers = this.__listeners[type] || [];
+            const index = handlers.indexOf(handler);
+            if (index >= 0) handlers.splice(index, 1);
+        }
+        __emit(type) {
+            const event = { type, target: this };
+            if (typeof this["on" + type] === "function") this["on" + type](event);
+            (this.__listeners[type] || []).slice().forEach((handler) => handler.call(this, event));
+        }
+        __setState(state) {
+            this.readyState = state;
+            this.__emit("readystatechange");
+        }
+        send(body = null) {
+            if (!this.__request) throw new TypeError("XMLHttpRequest.send до open");
+            const credentials = this.withCredentials ? "include" : "same-origin";
+            const request = new Request(this.__request.url, { method: this.__request.method, headers: this.__headers, body, credentials });
+            this.__aborted = false;
+            send(request, "xhr", (error, status, url, headers, text) => {
+                if (this.__aborted) return;
+                if (error !== null) {
+                    this.__setState(4);
+                    this.__emit("error");
+                } else {
+                    this.status = status;
+                    this.responseURL = url;
+                    this.responseText = text;
+                    this.__responseHeaders = Headers.__parse(headers);
+                    this.__setState(4);
+                    this.__emit("load");
+                }
+                this.__emit("loadend");
+            });
+        }
+        abort() {
+            this.__aborted = true;
+            this.__request = null;
+            this.readyState = 0;
+            this.__emit("abort");
         }
     }
-    // Если ничего не найдено в этом узле или его детях, возвращаем None
-    Ok(None)
+    XMLHttpRequest.UNSENT = 0;
+    XMLHttpRequest.OPENED = 1;
+    XMLHttpRequest.DONE = 4;
+    globalThis.XMLHttpRequest = XMLHttpRequest;
+
+    // Вызывается циклом событий realm, когда движок вернул ответ
+    globalThis.__yuaiFetchDone = (id, error, status, url, headers, body, bytes) => {
+        const callback = pending.get(id);
+        pending.delete(id);
+        if (callback) callback(error, status, url, headers, body, bytes);
+    };
+})();"#;
+
+// Объекты __localStorage / __sessionStorage; изменения отправляются движку для сохранения
+// и рассылки события storage другим страницам источника
+#[cfg(feature = "js")]
+fn install_storage(
+    context: &mut Context,
+    page: PageId,
+    local: Option<Arc<StorageArea>>,
+    session: Option<Arc<StorageArea>>,
+    host: Option<mpsc::UnboundedSender<HostRequest>>,
+    control: Arc<RealmControl>,
+) -> JsResult<()> {
+    for (name, area) in [("__localStorage", local), ("__sessionStorage", session)] {
+        let value = match area {
+            Some(area) => storage_object(context, page, area, host.clone(), control.clone()).into(),
+            None => JsValue::null(),
+        };
+        context.register_global_property(JsString::from(name), value, Attribute::empty())?;
+    }
+    context.eval(Source::from_bytes(STORAGE_PRELUDE.as_bytes()))?;
+    Ok(())
 }
 
-fn extract_script_content(html: &str, pattern: &str) -> Option<String> {
-    if let Some(start) = html.find(&format!(r#"<script id="{}" type="application/json">"#, pattern)) {
-        let content_start = start + format!(r#"<script id="{}" type="application/json">"#, pattern).len();
-        if let Some(end) = html[content_start..].find("</script>") {
-            return Some(html[content_start..content_start + end].trim().to_string());
-        }
-    } else if let Some(start) = html.find(pattern) { // Фолбек для window.__NUXT__ или data-sveltekit-fetched
-        // Это грубая эвристика и может требовать уточнения в зависимости от точной разметки,
-        // но она пытается захватить блоб JSON из присвоения переменной JavaScript.
-        let end = html[start..].find("</script>").unwrap_or(html.len());
-        let script_content = &html[start..start + end];
+#[cfg(feature = "js")]
+type StorageFunction = fn(&StorageArea, PageId, &[JsValue], &mut Context) -> JsResult<(JsValue, Option<StorageChange>)>;
+
+#[cfg(feature = "js")]
+fn storage_object(
+    context: &mut Context,
+    page: PageId,
+    area: Arc<StorageArea>,
+    host: Option<mpsc::UnboundedSender<HostRequest>>,
+    control: Arc<RealmControl>,
+) -> boa_engine::JsObject {
+    struct StorageHost {
+        name: &'static str,
+        function: StorageFunction,
+        page: PageId,
+        area: Arc<StorageArea>,
+        host: Option<mpsc::UnboundedSender<HostRequest>>,
+        control: Arc<RealmControl>,
+    }
+    let functions: [(&str, usize, StorageFunction); 6] = [
+        ("length", 0, |area, _, _, _| Ok((JsValue::from(area.len() as u32), None))),
+        ("key", 1, |area, _, args, ctx| {
+            let index = args.get_or_undefined(0).to_u32(ctx)? as usize;
+            Ok((optional_string(area.key(index).as_deref()), None))
+        }),
+        ("getItem", 1, |area, _, args, ctx| {
+            let key = arg_string(args, 0, ctx)?;
+            Ok((optional_string(area.get_item(&key).as_deref()), None))
+        }),
+        ("setItem", 2, |area, page, args, ctx| {
+            let (key, value) = (arg_string(args, 0, ctx)?, arg_string(args, 1, ctx)?);
+            match area.set_item(&key, &value, Some(page)) {
+                Ok(change) => Ok((JsValue::from(true), change)),
+                Err(e) if e.downcast_ref::<QuotaExceeded>().is_some() => Ok((JsValue::from(false), None)),
+                Err(e) => Err(dom_error(e)),
+            }
+        }),
+        ("removeItem", 1, |area, page, args, ctx| {
+            let key = arg_string(args, 0, ctx)?;
+            Ok((JsValue::undefined(), area.remove_item(&key, Some(page))))
+        }),
+        ("clear", 0, |area, page, _, _| Ok((JsValue::undefined(), area.clear(Some(page))))),
+    ];
 
-        // Грубое извлечение JSON-подобного блоба из присвоения переменной JavaScript
-        if let Some(json_start) = script_content.find('{') {
-            let potential_json = &script_content[json_start..];
-            let mut open_brackets = 0;
-            let mut json_end = 0;
-            for (i, c) in potential_json.chars().enumerate() {
-                if c == '{' {
-                    open_brackets += 1;
-                } else if c == '}' {
-                    open_brackets -= 1;
-                    if open_brackets == 0 {
-                        json_end = i + 1;
-                        break;
-                    }
+    let mut object = ObjectInitializer::new(context);
+    for (name, length, function) in functions {
+        let storage = StorageHost { name, function, page, area: area.clone(), host: host.clone(), control: control.clone() };
+        let native = NativeFunction::from_copy_closure_with_captures(
+            |_this, args, data: &HostData<StorageHost>, ctx| {
+                let StorageHost { name, function, page, area, host, control } = &data.value;
+                check_alive(control)?;
+                let (value, change) = function(area, *page, args, ctx)?;
+                if let (Some(change), Some(host)) = (change, host) {
+                    let _ = host.send(HostRequest::StorageChanged(change));
                 }
-            }
-            if json_end > 0 {
-                return Some(potential_json[..json_end].to_string());
-            }
-        }
+                // Отказ setItem по квоте: движок предложит пользователю увеличить квоту источника
+                let rejected = *name == "setItem" && value.as_boolean() == Some(false);
+                if let (true, StorageKind::Local, Some(host)) = (rejected, area.kind(), host) {
+                    let _ = host.send(HostRequest::QuotaExceeded { page: *page });
+                }
+                Ok(value)
+            },
+            HostData::new(storage),
+        );
+        object.function(native, JsString::from(name), length);
     }
-    None
+    object.build()
 }
 
-pub fn collect_text(node: &ParsedNode) -> String {
-    match node {
-        ParsedNode::Text(text) => text.clone(),
-        ParsedNode::Element { children, .. } => {
-            let mut text = String::new();
-            for child in children {
-                let child_text = collect_text(child);
-                if !child_text.is_empty() {
-                    if !text.is_empty() {
-                        text.push(' ');
-                    }
-                    text.push_str(&child_text);
+#[cfg(feature = "js")]
+const STORAGE_PRELUDE: &str = r#"(() => {
+    const makeStorage = (native) => {
+        const storage = {
+            get length() { return native.length(); },
+            key: (index) => native.key(Number(index) >>> 0),
+            getItem: (key) => native.getItem(String(key)),
+            setItem: (key, value) => {
+                if (!native.setItem(String(key), String(value))) {
+                    const error = new Error("Превышена квота хранилища");
+                    error.name = "QuotaExceededError";
+                    throw error;
                 }
-            }
-            text
-        }
+            },
+            removeItem: (key) => native.removeItem(String(key)),
+            clear: () => native.clear(),
+        };
+        // Доступ к ключам как к свойствам: localStorage.theme = "dark"
+        return new Proxy(storage, {
+            get: (target, property) => {
+                if (typeof property === "symbol" || property in target) return target[property];
+                const value = native.getItem(property);
+                return value === null ? undefined : value;
+            },
+            set: (target, property, value) => {
+                if (typeof property === "symbol" || property in target) return false;
+                storage.setItem(property, value);
+                return true;
+            },
+            deleteProperty: (target, property) => {
+                if (typeof property !== "symbol") native.removeItem(property);
+                return true;
+            },
+            has: (target, property) => property in target || (typeof property !== "symbol" && native.getItem(property) !== null),
+        });
+    };
+    const define = (name, native) => {
+        const storage = native ? makeStorage(native) : null;
+        Object.defineProperty(globalThis, name, {
+            configurable: true,
+            get: () => {
+                if (storage === null) {
+                    const error = new Error(name + " недоступен для этого документа");
+                    error.name = "SecurityError";
+                    throw error;
+                }
+                return storage;
+            },
+        });
+    };
+    define("localStorage", __localStorage);
+    define("sessionStorage", __sessionStorage);
+
+    const listeners = {};
+    if (typeof globalThis.addEventListener !== "function") {
+        globalThis.addEventListener = (type, handler) => {
+            const handlers = listeners[type] || (listeners[type] = []);
+            if (typeof handler === "function" && !handlers.includes(handler)) handlers.push(handler);
+        };
+        globalThis.removeEventListener = (type, handler) => {
+            const handlers = listeners[type] || [];
+            const index = handlers.indexOf(handler);
+            if (index >= 0) handlers.splice(index, 1);
+        };
     }
+    // Событие storage из другой страницы того же источника
+    globalThis.__yuaiStorageEvent = (detail) => {
+        const event = typeof Event === "function" ? new Event("storage") : { type: "storage" };
+        Object.assign(event, detail, { storageArea: globalThis[detail.storageArea] });
+        if (typeof globalThis.onstorage === "function") globalThis.onstorage(event);
+        (listeners.storage || []).slice().forEach((handler) => {
+            try { handler(event); } catch (e) { console.error("Uncaught " + String(e)); }
+        });
+    };
+})();"#;
+
+#[cfg(feature = "js")]
+type DomFunction = fn(&SharedDom, &[JsValue], &mut Context) -> JsResult<JsValue>;
+
+// Нативный слой __dom работает с числовыми идентификаторами узлов; объектная
+// обёртка document/Element строится поверх него в DOM_PRELUDE
+#[cfg(feature = "js")]
+fn install_dom(context: &mut Context, dom: SharedDom, control: Arc<RealmControl>) -> JsResult<()> {
+    let functions: [(&str, usize, DomFunction); 19] = [
+        ("root", 0, |dom, _, _| Ok(node_value(Some(dom.read().root())))),
+        ("documentElement", 0, |dom, _, _| Ok(node_value(Some(dom.read().document_element())))),
+        ("parent", 1, |dom, args, ctx| Ok(node_value(dom.read().parent(arg_node(args, 0, ctx)?)))),
+        ("children", 1, |dom, args, ctx| {
+            let children = dom.read().children(arg_node(args, 0, ctx)?);
+            Ok(node_list(children, ctx))
+        }),
+        ("nodeType", 1, |dom, args, ctx| {
+            let dom = dom.read();
+            let id = arg_node(args, 0, ctx)?;
+            Ok(match (dom.contains(id), dom.tag(id)) {
+                (false, _) => JsValue::null(),
+                (true, Some(_)) => JsValue::from(1),
+                (true, None) => JsValue::from(3),
+            })
+        }),
+        ("tagName", 1, |dom, args, ctx| Ok(optional_string(dom.read().tag(arg_node(args, 0, ctx)?)))),
+        ("getAttribute", 2, |dom, args, ctx| {
+            let (id, name) = (arg_node(args, 0, ctx)?, arg_string(args, 1, ctx)?);
+            Ok(optional_string(dom.read().get_attribute(id, &name.to_ascii_lowercase()).as_deref()))
+        }),
+        ("setAttribute", 3, |dom, args, ctx| {
+            let (id, name, value) = (arg_node(args, 0, ctx)?, arg_string(args, 1, ctx)?, arg_string(args, 2, ctx)?);
+            dom.write().set_attribute(id, &name, &value).map_err(dom_error)?;
+            Ok(JsValue::undefined())
+        }),
+        ("removeAttribute", 2, |dom, args, ctx| {
+            let (id, name) = (arg_node(args, 0, ctx)?, arg_string(args, 1, ctx)?);
+            dom.write().remove_attribute(id, &name.to_ascii_lowercase()).map_err(dom_error)?;
+            Ok(JsValue::undefined())
+        }),
+        ("getTextContent", 1, |dom, args, ctx| {
+            Ok(JsValue::from(JsString::from(dom.read().text_content(arg_node(args, 0, ctx)?).as_str())))
+        }),
+        ("setTextContent", 2, |dom, args, ctx| {
+            let (id, text) = (arg_node(args, 0, ctx)?, arg_string(args, 1, ctx)?);
+            dom.write().set_text_content(id, &text).map_err(dom_error)?;
+            Ok(JsValue::undefined())
+        }),
+        ("createElement", 1, |dom, args, ctx| {
+            let tag = arg_string(args, 0, ctx)?;
+            Ok(node_value(Some(dom.write().create_element(&tag))))
+        }),
+        ("createTextNode", 1, |dom, args, ctx| {
+            let text = arg_string(args, 0, ctx)?;
+            Ok(node_value(Some(dom.write().create_text(&text))))
+        }),
+        ("appendChild", 2, |dom, args, ctx| {
+            let (parent, child) = (arg_node(args, 0, ctx)?, arg_node(args, 1, ctx)?);
+            dom.write().append_child(parent, child).map_err(dom_error)?;
+            Ok(JsValue::undefined())
+        }),
+        ("insertBefore", 3, |dom, args, ctx| {
+            let (parent, child) = (arg_node(args, 0, ctx)?, arg_node(args, 1, ctx)?);
+            let reference = if args.get_or_undefined(2).is_null_or_undefined() {
+                None
+            } else {
+                Some(arg_node(args, 2, ctx)?)
+            };
+            dom.write().insert_before(parent, child, reference).map_err(dom_error)?;
+            Ok(JsValue::undefined())
+        }),
+        ("removeChild", 2, |dom, args, ctx| {
+            let (parent, child) = (arg_node(args, 0, ctx)?, arg_node(args, 1, ctx)?);
+            dom.write().remove_child(parent, child).map_err(dom_error)?;
+            Ok(JsValue::undefined())
+        }),
+        ("querySelector", 2, |dom, args, ctx| {
+            let (scope, selector) = (arg_node(args, 0, ctx)?, arg_string(args, 1, ctx)?);
+            Ok(node_value(dom.read().query_selector(scope, &selector).map_err(syntax_error)?))
+        }),
+        ("querySelectorAll", 2, |dom, args, ctx| {
+            let (scope, selector) = (arg_node(args, 0, ctx)?, arg_string(args, 1, ctx)?);
+            let nodes = dom.read().query_selector_all(scope, &selector).map_err(syntax_error)?;
+            Ok(node_list(nodes, ctx))
+        }),
+        ("getElementById", 1, |dom, args, ctx| {
+            let id = arg_string(args, 0, ctx)?;
+            Ok(node_value(dom.read().get_element_by_id(&id)))
+        }),
+    ];
+
+    let natives = {
+        let mut natives = ObjectInitializer::new(context);
+        for (name, length, function) in functions {
+            let native = NativeFunction::from_copy_closure_with_captures(
+                |_this, args, data: &HostData<(DomFunction, SharedDom, Arc<RealmControl>)>, ctx| {
+                    let (function, dom, control) = &data.value;
+                    check_alive(control)?;
+                    function(dom, args, ctx)
+                },
+                HostData::new((function, dom.clone(), control.clone())),
+            );
+            natives.function(native, JsString::from(name), length);
+        }
+        natives.build()
+    };
+    context.register_global_property(js_string!("__dom"), natives, Attribute::empty())?;
+    context.eval(Source::from_bytes(DOM_PRELUDE.as_bytes()))?;
+    Ok(())
 }
 
-// === FILE: dom\style.rs ===
-use anyhow::Result;
-use std::sync::Arc;
-use crate::net::Network;
-pub struct Style;
-impl Style {
-    pub fn new(_network: Arc<Network>) -> Result<Self> {
-        println!("Style initialized");
-        Ok(Self)
-    }
+#[cfg(feature = "js")]
+fn arg_node(args: &[JsValue], index: usize, context: &mut Context) -> JsResult<NodeId> {
+    Ok(NodeId(args.get_or_undefined(index).to_u32(context)?))
 }
 
-// === FILE: dom\tree.rs ===
-use crate::dom::parser::{ParsedNode, collect_text};
-use egui::{Ui, RichText, TextStyle, Label, ColorImage, TextureHandle};
-use anyhow::Result;
-use std::sync::{Arc, Mutex};
-use once_cell::sync::Lazy;
-use image::ImageFormat;
-use tokio::sync::mpsc::{UnboundedSender, UnboundedReceiver};
-use reqwest::Client;
-use std::collections::{HashMap, VecDeque};
+#[cfg(feature = "js")]
+fn arg_string(args: &[JsValue], index: usize, context: &mut Context) -> JsResult<String> {
+    Ok(args.get_or_undefined(index).to_string(context)?.to_std_string_escaped())
+}
 
-static IMAGE_CACHE: Lazy<Mutex<HashMap<String, Arc<TextureHandle>>>> =
-    Lazy::new(|| Mutex::new(HashMap::new()));
+#[cfg(feature = "js")]
+fn node_value(id: Option<NodeId>) -> JsValue {
+    id.map_or_else(JsValue::null, |id| JsValue::from(id.0))
+}
 
-pub struct DomRenderer {
-    pub clicked_links: VecDeque<String>,
-    pub clicked_buttons: VecDeque<String>,
-    image_tx: UnboundedSender<(String, Result<image::DynamicImage>)>,
-    image_rx: UnboundedReceiver<(String, Result<image::DynamicImage>)>,
-    egui_ctx: Option<egui::Context>,
-    needs_repaint: bool, // Флаг необходимости перерисовки
+#[cfg(feature = "js")]
+fn node_list(ids: Vec<NodeId>, context: &mut Context) -> JsValue {
+    JsArray::from_iter(ids.into_iter().map(|id| JsValue::from(id.0)), context).into()
 }
 
-impl DomRenderer {
-    pub fn new() -> Self {
-        let (image_tx, image_rx) = tokio::sync::mpsc::unbounded_channel();
-        log::info!("Создание DomRenderer");
-        Self {
-            clicked_links: VecDeque::new(),
-            clicked_buttons: VecDeque::new(),
-            image_tx,
-            image_rx,
-            egui_ctx: None,
-            needs_repaint: true, // Изначально требуется рендер
+#[cfg(feature = "js")]
+fn optional_string(value: Option<&str>) -> JsValue {
+    value.map_or_else(JsValue::null, |value| JsValue::from(JsString::from(value)))
+}
+
+#[cfg(feature = "js")]
+fn dom_error(e: anyhow::Error) -> JsError {
+    JsNativeError::typ().with_message(e.to_string()).into()
+}
+
+#[cfg(feature = "js")]
+fn syntax_error(e: anyhow::Error) -> JsError {
+    JsNativeError::syntax().with_message(e.to_string()).into()
+}
+
+#[cfg(feature = "js")]
+const DOM_PRELUDE: &str = r#"(() => {
+    const dom = __dom;
+    const wrappers = new Map();
+    const listeners = new Map();
+    const wrap = (id) => {
+        if (id === null || id === undefined) return null;
+        let node = wrappers.get(id);
+        if (!node) {
+            node = new Node(id);
+            wrappers.set(id, node);
+        }
+        return node;
+    };
+
+    class Event {
+        constructor(type, init = {}) {
+            this.type = String(type);
+            this.bubbles = !!init.bubbles;
+            this.cancelable = !!init.cancelable;
+            this.defaultPrevented = false;
+            this.target = null;
+            this.currentTarget = null;
+            this.stopped = false;
         }
+        preventDefault() { if (this.cancelable) this.defaultPrevented = true; }
+        stopPropagation() { this.stopped = true; }
     }
 
-    pub fn set_egui_context(&mut self, ctx: egui::Context) {
-        log::info!("Установка контекста egui для DomRenderer");
-        self.egui_ctx = Some(ctx);
+    const dispatch = (id, event) => {
+        event.target = wrap(id);
+        const path = [];
+        for (let current = id; current !== null; current = dom.parent(current)) path.push(current);
+        for (const nodeId of event.bubbles ? path : [id]) {
+            const handlers = (listeners.get(nodeId) || {})[event.type] || [];
+            event.currentTarget = wrap(nodeId);
+            for (const handler of handlers.slice()) {
+                try { handler.call(event.currentTarget, event); }
+                catch (e) { console.error(String(e)); }
+            }
+            if (event.stopped) break;
+        }
+        event.currentTarget = null;
+        return !event.defaultPrevented;
+    };
+
+    class Node {
+        constructor(id) { this.__id = id; }
+        get nodeType() { return dom.nodeType(this.__id); }
+        get tagName() { const tag = dom.tagName(this.__id); return tag === null ? null : tag.toUpperCase(); }
+        get nodeName() { return this.tagName || '#text'; }
+        get parentNode() { return wrap(dom.parent(this.__id)); }
+        get parentElement() { return this.parentNode; }
+        get childNodes() { return dom.children(this.__id).map(wrap); }
+        get children() { return this.childNodes.filter((node) => node.nodeType === 1); }
+        get firstChild() { return this.childNodes[0] || null; }
+        get lastChild() { const nodes = this.childNodes; return nodes[nodes.length - 1] || null; }
+        get textContent() { return dom.getTextContent(this.__id); }
+        set textContent(value) { dom.setTextContent(this.__id, value === null ? "" : String(value)); }
+        get innerText() { return this.textContent; }
+        set innerText(value) { this.textContent = value; }
+        get id() { return this.getAttribute("id") || ""; }
+        set id(value) { this.setAttribute("id", value); }
+        get className() { return this.getAttribute("class") || ""; }
+        set className(value) { this.setAttribute("class", value); }
+        get value() { return this.getAttribute("value") || ""; }
+        set value(value) { this.setAttribute("value", value); }
+        get checked() { return this.hasAttribute("checked"); }
+        set checked(value) { value ? this.setAttribute("checked", "") : this.removeAttribute("checked"); }
+        getAttribute(name) { return dom.getAttribute(this.__id, String(name)); }
+        setAttribute(name, value) { dom.setAttribute(this.__id, String(name), String(value)); }
+        removeAttribute(name) { dom.removeAttribute(this.__id, String(name)); }
+        hasAttribute(name) { return this.getAttribute(name) !== null; }
+        appendChild(child) { dom.appendChild(this.__id, child.__id); return child; }
+        insertBefore(child, reference) { dom.insertBefore(this.__id, child.__id, reference ? reference.__id : null); return child; }
+        removeChild(child) { dom.removeChild(this.__id, child.__id); return child; }
+        remove() { const parent = dom.parent(this.__id); if (parent !== null) dom.removeChild(parent, this.__id); }
+        querySelector(selector) { return wrap(dom.querySelector(this.__id, String(selector))); }
+        querySelectorAll(selector) { return dom.querySelectorAll(this.__id, String(selector)).map(wrap); }
+        addEventListener(type, handler) {
+            if (typeof handler !== "function") return;
+            const byType = listeners.get(this.__id) || {};
+            const handlers = byType[type] || (byType[type] = []);
+            if (!handlers.includes(handler)) handlers.push(handler);
+            listeners.set(this.__id, byType);
+        }
+        removeEventListener(type, handler) {
+            const handlers = (listeners.get(this.__id) || {})[type];
+            if (handlers) {
+                const index = handlers.indexOf(handler);
+                if (index >= 0) handlers.splice(index, 1);
+            }
+        }
+        dispatchEvent(event) { return dispatch(this.__id, event); }
+        click() { return this.dispatchEvent(new Event("click", { bubbles: true, cancelable: true })); }
     }
 
-    pub fn needs_repaint(&self) -> bool {
-        self.needs_repaint
+    const body = () => wrap(dom.root());
+    globalThis.Event = Event;
+    globalThis.Node = Node;
+    globalThis.window = globalThis;
+    globalThis.document = {
+        get body() { return body(); },
+        get documentElement() { return wrap(dom.documentElement()); },
+        getElementById: (id) => wrap(dom.getElementById(String(id))),
+        querySelector: (selector) => document.documentElement.querySelector(selector),
+        querySelectorAll: (selector) => document.documentElement.querySelectorAll(selector),
+        createElement: (tag) => wrap(dom.createElement(String(tag).toLowerCase())),
+        createTextNode: (text) => wrap(dom.createTextNode(String(text))),
+        addEventListener: (type, handler) => body().addEventListener(type, handler),
+        removeEventListener: (type, handler) => body().removeEventListener(type, handler),
+    };
+    // Точка входа для событий, пришедших из DomRenderer; false, если действие по умолчанию отменено
+    globalThis.__yuaiDispatch = (id, type) => dispatch(id, new Event(type, { bubbles: true, cancelable: true }));
+})();"#;
+
+#[cfg(feature = "js")]
+fn format_console_arg(value: &JsValue) -> String {
+    match value.as_string() {
+        Some(text) => text.to_std_string_escaped(),
+        None => value.display().to_string(),
     }
+}
 
-    pub fn clear_repaint_flag(&mut self) {
-        log::info!("Сброс флага перерисовки в DomRenderer");
-        self.needs_repaint = false;
+// Конструкторы Function, AsyncFunction, GeneratorFunction и AsyncGeneratorFunction доступны
+// через прототип любой функции своего вида, поэтому подменяется constructor каждого прототипа
+#[cfg(feature = "js")]
+const BLOCK_EVAL_PRELUDE: &str = r#"(() => {
+    const blocked = () => { throw new EvalError("Вычисление строки как кода запрещено CSP: нет 'unsafe-eval'"); };
+    const block = (prototype, name) => {
+        const Blocked = function () { blocked(); };
+        Object.defineProperty(Blocked, "name", { value: name });
+        Blocked.prototype = prototype;
+        Object.defineProperty(prototype, "constructor", { value: Blocked, writable: false, configurable: false });
+        return Blocked;
+    };
+    const BlockedFunction = block(Function.prototype, "Function");
+    block(Object.getPrototypeOf(async function () {}), "AsyncFunction");
+    block(Object.getPrototypeOf(function* () {}), "GeneratorFunction");
+    block(Object.getPrototypeOf(async function* () {}), "AsyncGeneratorFunction");
+    Object.defineProperty(globalThis, "eval", { value: function eval() { blocked(); }, writable: false, configurable: false });
+    Object.defineProperty(globalThis, "Function", { value: BlockedFunction, writable: false, configurable: false });
+})();"#;
+
+#[cfg(feature = "js")]
+fn deterministic_prelude(seed: u32, fixed_time_ms: u64) -> String {
+    format!(
+        r#"(() => {{
+    let state = {seed} | 0 || 1;
+    Math.random = () => {{
+        state ^= state << 13;
+        state ^= state >>> 17;
+        state ^= state << 5;
+        return (state >>> 0) / 4294967296;
+    }};
+    Date.now = () => {now};
+}})();"#,
+        seed = seed,
+        now = fixed_time_ms
+    )
+}
+
+#[cfg(all(test, feature = "js"))]
+mod tests {
+    use super::*;
+
+    async fn realm(options: RealmOptions) -> JsRuntime {
+        let runtime = JsRuntime::new(None, None).unwrap();
+        runtime.create_realm(PageId::DEFAULT, options, RealmBindings::default()).await.unwrap();
+        runtime
     }
 
-    pub fn process_image_responses(&mut self, ui: &mut Ui) {
-        let mut image_processed = false;
-        while let Ok((src, result)) = self.image_rx.try_recv() {
-            log::info!("Обработка ответа для изображения: {}", src);
-            let mut cache = IMAGE_CACHE.lock().unwrap();
-            match result {
-                Ok(img) => {
-                    let rgba = img.to_rgba8();
-                    let size = [rgba.width() as usize, rgba.height() as usize];
-                    let color_image = ColorImage::from_rgba_unmultiplied(size, rgba.as_raw());
-                    if let Some(ctx) = &self.egui_ctx {
-                        let tex = ctx.load_texture(src.clone(), color_image, Default::default());
-                        cache.insert(src.clone(), Arc::new(tex));
-                        image_processed = true;
-                        self.needs_repaint = true; // Изображение загружено, нужна перерисовка
-                        log::info!("Изображение {} успешно загружено и добавлено в кэш", src);
-                    }
-                }
-                Err(e) => {
-                    log::warn!("Ошибка загрузки изображения {}: {}", src, e);
-                    cache.insert(src.clone(), Arc::new(ui.ctx().load_texture(
-                        src.clone(),
-                        ColorImage::new([1, 1], vec![egui::Color32::GRAY]),
-                        Default::default(),
-                    )));
-                }
-            }
-        }
-        if image_processed && self.egui_ctx.as_ref().map_or(false, |ctx| ctx.has_requested_repaint()) {
-            log::info!("DomRenderer запросил перерисовку из-за новых изображений");
-            self.needs_repaint = true;
-        }
+    fn deterministic() -> RealmOptions {
+        RealmOptions { deterministic: true, seed: 42, fixed_time_ms: 1_000, ..Default::default() }
     }
 
-    pub async fn render_node(&mut self, ui: &mut Ui, node: &ParsedNode) {
-        match node {
-            ParsedNode::Text(text) => {
-                if !text.is_empty() {
-                    //log::info!("Рендер текста: {} (длина: {})", text, text.len());
-                    ui.label(RichText::new(text));
-                }
-            }
-            ParsedNode::Element { tag, attrs, children, .. } => {
-                //log::info!("Рендер элемента: {}", tag);
-                match tag.as_str() {
-                    "a" => {
-                        if let Some(href) = attrs.get("href") {
-                            let link_text = collect_text(node);
-                            //log::info!("Рендер ссылки: {} ({})", link_text, href);
-                            let response = ui.link(link_text).clicked();
-                            if response {
-                                self.clicked_links.push_back(href.clone());
-                                self.needs_repaint = true; // Клик требует перерисовки
-                                log::info!("Клик по ссылке: {}, добавлено в очередь", href);
-                            }
-                        }
-                    }
-                    "button" => {
-                        let text = collect_text(node);
-                        //log::info!("Рендер кнопки: {}", text);
-                        let response = ui.button(&text).clicked();
-                        if response {
-                            self.clicked_buttons.push_back("button".into());
-                            self.needs_repaint = true; // Клик требует перерисовки
-                            log::info!("Клик по кнопке: {}, добавлено в очередь", text);
-                        }
-                    }
-                    "input" => {
-                        log::info!("Рендер input: {:?}", attrs);
-                        self.render_input(ui, attrs);
-                    }
-                    "img" => {
-                        if let Some(src) = attrs.get("src") {
-                            //log::info!("Рендер изображения: {}", src);
-                            let mut cache = IMAGE_CACHE.lock().unwrap();
-                            if let Some(tex) = cache.get(src) {
-                                ui.image(&**tex);
-                            } else {
-                                ui.label("[Загрузка изображения...]");
-                                let tx = self.image_tx.clone();
-                                tokio::spawn(Self::load_image_async(src.clone(), tx));
-                                cache.insert(src.clone(), Arc::new(ui.ctx().load_texture(
-                                    src.clone(),
-                                    ColorImage::new([1, 1], vec![egui::Color32::GRAY]),
-                                    Default::default(),
-                                )));
-                                log::info!("Запущена асинхронная загрузка изображения: {}", src);
-                                self.needs_repaint = true; // Загрузка изображения требует перерисовки
-                            }
-                        }
-                    }
-                    "span" => {
-                        //log::info!("Рендер span");
-                        let _ = ui.horizontal_wrapped(|ui| {
-                            for child in children {
-                                pollster::block_on(self.render_node(ui, child));
-                            }
-                        });
-                    }
-                    "ul" | "ol" => {
-                        //log::info!("Рендер списка: {}", tag);
-                        for child in children {
-                            pollster::block_on(self.render_node(ui, child));
-                        }
-                    }
-                    "li" => {
-                        //log::info!("Рендер элемента списка");
-                        let _ = ui.horizontal(|ui| {
-                            ui.label("•");
-                            for child in children {
-                                pollster::block_on(self.render_node(ui, child));
-                            }
-                        });
-                    }
-                    "details" => {
-                        //log::info!("Рендер details");
-                        let summary = children
-                            .iter()
-                            .find(|c| matches!(c, ParsedNode::Element { tag, .. } if tag == "summary"))
-                            .map(|c| collect_text(c))
-                            .unwrap_or_else(|| "Подробности".into());
-                        let _ = ui.collapsing(summary, |ui| {
-                            for child in children {
-                                if let ParsedNode::Element { tag, .. } = child {
-                                    if tag != "summary" {
-                                        pollster::block_on(self.render_node(ui, child));
-                                    }
-                                }
-                            }
-                        });
-                        self.needs_repaint = true; // Изменение состояния details требует перерисовки
-                    }
-                    "br" => {
-                        //log::info!("Рендер br");
-                        ui.end_row();
-                    }
-                    "hr" => {
-                        //log::info!("Рендер hr");
-                        let _ = ui.separator();
-                    }
-                    "p" | "div" | "section" => {
-                        //log::info!("Рендер контейнера: {}", tag);
-                        let _ = ui.group(|ui| {
-                            for child in children {
-                                pollster::block_on(self.render_node(ui, child));
-                            }
-                        });
-                    }
-                    "pre" => {
-                        //log::info!("Рендер pre");
-                        let text = collect_text(node);
-                        ui.add(
-                            Label::new(
-                                RichText::new(text)
-                                    .text_style(TextStyle::Monospace)
-                            )
-                            .wrap()
-                        );
-                    }
-                    _ => {
-                        //log::info!("Рендер прочих элементов: {}", tag);
-                        for child in children {
-                            pollster::block_on(self.render_node(ui, child));
-                        }
-                    }
-                }
-            }
-        }
+    #[tokio::test]
+    async fn deterministic_realms_repeat_random_and_time() {
+        let script = "[Math.random(), Math.random(), Date.now()].join()";
+        let first = realm(deterministic()).await.evaluate(PageId::DEFAULT, script).await.unwrap();
+        let second = realm(deterministic()).await.evaluate(PageId::DEFAULT, script).await.unwrap();
+        assert_eq!(first, second);
+        assert!(first.ends_with(",1000"), "{}", first);
     }
 
-    async fn load_image_async(src: String, tx: UnboundedSender<(String, Result<image::DynamicImage>)>) {
-        log::info!("Запуск асинхронной загрузки изображения: {}", src);
-        let client = Client::new();
-        let result = async {
-            let resp = client.get(&src).send().await?;
-            let bytes = resp.bytes().await?;
-            let img = image::load_from_memory_with_format(&bytes, ImageFormat::from_path(&src)?)?;
-            Ok(img)
-        }.await;
-        let _ = tx.send((src.clone(), result));
-        log::info!("Завершена асинхронная загрузка изображения: {}", src);
+    #[tokio::test]
+    async fn inline_script_runs_microtasks_before_timers() {
+        let runtime = realm(deterministic()).await;
+        runtime
+            .evaluate(
+                PageId::DEFAULT,
+                r#"globalThis.log = [];
+                setTimeout(() => log.push("late"), 5);
+                setTimeout(() => log.push("timer"), 0);
+                Promise.resolve().then(() => log.push("micro"));
+                log.push("sync");"#,
+            )
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let log = runtime.evaluate(PageId::DEFAULT, "log.join()").await.unwrap();
+        assert_eq!(log, "sync,micro,timer,late");
     }
 
-    fn render_input(&mut self, ui: &mut Ui, attrs: &HashMap<String, String>) {
-        //log::info!("Рендер поля ввода: {:?}", attrs);
-        let input_type = attrs
-            .get("type")
-            .map(|t| t.to_lowercase())
-            .unwrap_or_else(|| "text".into());
-        match input_type.as_str() {
-            "text" | "email" | "password" | "number" => {
-                let mut value = attrs.get("value").cloned().unwrap_or_default();
-                let placeholder = attrs.get("placeholder").cloned().unwrap_or_default();
-                let edit = ui.add(
-                    egui::TextEdit::singleline(&mut value)
-                        .hint_text(placeholder)
-                        .desired_width(150.0),
-                );
-                if edit.changed() {
-                    self.clicked_buttons.push_back(format!("input_changed:{}", value));
-                    self.needs_repaint = true; // Изменение ввода требует перерисовки
-                    log::info!("Введено значение в поле ввода: {}", value);
-                }
-            }
-            "checkbox" => {
-                let checked = attrs.contains_key("checked");
-                let mut val = checked;
-                if ui.checkbox(&mut val, "").clicked() {
-                    self.clicked_buttons.push_back(format!("checkbox_changed:{}", val));
-                    self.needs_repaint = true; // Изменение чекбокса требует перерисовки
-                    log::info!("Чекбокс изменён: {}", val);
-                }
-            }
-            "radio" => {
-                let selected = attrs.contains_key("checked");
-                let val = selected;
-                if ui.radio(val, "").clicked() {
-                    self.clicked_buttons.push_back(format!("radio_selected:{}", val));
-                    self.needs_repaint = true; // Изменение радиокнопки требует перерисовки
-                    log::info!("Радиокнопка выбрана: {}", val);
-                }
-            }
-            _ => {
-                log::warn!("Неподдерживаемый тип ввода: {}", input_type);
-                ui.label(format!("[Неподдерживаемый тип ввода: {}]", input_type));
-            }
-        }
+    #[tokio::test]
+    async fn syntax_error_reports_parser_position() {
+        let runtime = realm(RealmOptions::default()).await;
+        let error = runtime.evaluate(PageId::DEFAULT, "let a = 1;\nlet b = ;").await.unwrap_err();
+        assert_eq!(error.line, Some(2));
+        assert!(error.column.is_some());
     }
 
-    pub fn get_last_link_click(&mut self) -> Option<String> {
-        log::info!("Получение последнего клика по ссылке");
-        self.clicked_links.pop_back()
+    #[tokio::test]
+    async fn runtime_error_has_no_guessed_position() {
+        let runtime = realm(RealmOptions::default()).await;
+        let error = runtime.evaluate(PageId::DEFAULT, "throw new Error('at line 3, col 14')").await.unwrap_err();
+        assert_eq!((error.line, error.column), (None, None));
     }
 
-    pub fn get_last_button_click(&mut self) -> Option<String> {
-        log::info!("Получение последнего клика по кнопке");
-        self.clicked_buttons.pop_back()
+    #[tokio::test]
+    async fn eval_is_blocked_without_unsafe_eval() {
+        let runtime = realm(RealmOptions { block_eval: true, ..Default::default() }).await;
+        assert!(runtime.evaluate(PageId::DEFAULT, "eval('1 + 1')").await.is_err());
+        assert!(runtime.evaluate(PageId::DEFAULT, "(function () {}).constructor('return 1')()").await.is_err());
+        assert!(runtime.evaluate(PageId::DEFAULT, "Object.getPrototypeOf(async function () {}).constructor('return 1')").await.is_err());
+        assert!(runtime.evaluate(PageId::DEFAULT, "Object.getPrototypeOf(function* () {}).constructor('yield 1')").await.is_err());
+        assert!(runtime.evaluate(PageId::DEFAULT, "Object.getPrototypeOf(async function* () {}).constructor('yield 1')").await.is_err());
+        assert!(runtime.evaluate(PageId::DEFAULT, "(async () => {}).constructor('return 1')").await.is_err());
+        assert_eq!(runtime.evaluate(PageId::DEFAULT, "setTimeout('globalThis.ran = 1', 0)").await.unwrap(), "0");
+        assert_eq!(runtime.evaluate(PageId::DEFAULT, "1 + 1").await.unwrap(), "2");
     }
-}
 
-// === FILE: dom\vdom.rs ===
-use anyhow::Result;
-use std::sync::Arc;
-use crate::net::Network;
-pub struct VDOM;
-impl VDOM {
-    pub fn new(_network: Arc<Network>) -> Result<Self> {
-        println!("VDOM initialized");
-        Ok(Self)
+    // Вложенные циклы обходят лимит итераций; сторож прерывает задачу и выдаёт странице новый realm
+    #[tokio::test]
+    async fn runaway_script_is_aborted_and_realm_replaced() {
+        let runtime = realm(RealmOptions::default()).await;
+        runtime.evaluate(PageId::DEFAULT, "globalThis.marker = 1").await.unwrap();
+        let error = runtime
+            .evaluate(PageId::DEFAULT, "for (;;) { for (let i = 0; i < 1000; i++) {} console.debug('tick'); }")
+            .await
+            .unwrap_err();
+        assert!(error.message.to_lowercase().contains("превышено время"), "{}", error);
+        assert_eq!(runtime.evaluate(PageId::DEFAULT, "typeof marker").await.unwrap(), "undefined");
+        assert_eq!(runtime.evaluate(PageId::DEFAULT, "2 + 2").await.unwrap(), "4");
+        // Поток прерванного realm выходит на первом вызове хоста
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(runtime.realms.abandoned.count(PageId::DEFAULT), 0);
     }
 }
 
-// === FILE: net\cache.rs ===
+// === FILE: core\libs.rs ===
+use crate::core::interfaces::{LibManagerTrait, SecurityManagerTrait, WasmRuntimeTrait};
+use crate::core::runtime::{InstanceId, WasmValue};
+use crate::core::security::{load_verified, signature_path};
+#[cfg(feature = "native-libs")]
+use crate::core::security::stage_verified;
+use crate::wasm_api::sandbox::SandboxConfig;
 use anyhow::Result;
-use std::sync::Arc;
-use crate::net::Network;
-pub struct Cache;
-impl Cache {
-    pub fn new(_network: Arc<Network>) -> Result<Self> {
-        println!("Cache initialized");
-        Ok(Self)
-    }
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, SystemTime};
+
+// Библиотека в ~/.cosmonaut/libs: файл (.so/.dll/.dylib/.wasm), подпись <файл>.sig
+// и метаданные <файл>.yaml с версией и типами экспортируемых символов, подпись <файл>.yaml.sig
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LibraryKind {
+    Native,
+    Wasm,
 }
 
-// === FILE: net\fetch.rs ===
-use std::sync::Arc;
-use std::time::{Duration, Instant};
+// Поддерживаемые сигнатуры символов. bytes: C ABI
+//   u8* f(const u8* input, usize len, usize* out_len), память освобождает символ free из метаданных
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolKind {
+    Bytes,
+    I64,
+    F64,
+}
 
-use anyhow::{Context, Result};
-use reqwest::header::HeaderMap;
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LibraryMetadata {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    // Имя -> сигнатура
+    #[serde(default)]
+    pub symbols: HashMap<String, SymbolKind>,
+    // Освобождение буферов, возвращённых bytes-символами нативной библиотеки
+    #[serde(default = "default_free_symbol")]
+    pub free: String,
+}
 
-use crate::core::config::Config;
-use crate::core::engine::{BroEngine, EngineEvent, UrlResponse};
-use crate::core::interfaces::{NetworkTrait, YuaidbTrait};
-use async_trait::async_trait;
+fn default_free_symbol() -> String {
+    "cosmonaut_free".to_string()
+}
 
-pub struct Network {
-    client: reqwest::Client,
-    db: Option<Arc<dyn YuaidbTrait + Send + Sync>>,
+#[derive(Clone, Debug)]
+pub struct LibraryInfo {
+    pub metadata: LibraryMetadata,
+    pub kind: LibraryKind,
+    pub path: PathBuf,
+    pub loaded: bool,
+    pub leases: usize,
+    // Растёт при каждой горячей перезагрузке
+    pub generation: u64,
 }
 
-impl Network {
-    pub fn new(config: &Config, db: Option<Arc<dyn YuaidbTrait + Send + Sync>>) -> Result<Self> {
-        let client_builder = reqwest::ClientBuilder::new()
-            .timeout(Duration::from_secs(30))
-            .connect_timeout(Duration::from_secs(10));
+// Типизированный доступ к символу: тип Rust задаёт ожидаемую сигнатуру
+pub trait SymbolType: Send + Sync + 'static {
+    const KIND: SymbolKind;
+    type Input: Send + 'static;
+    type Output: Send + 'static;
+    fn to_value(input: Self::Input) -> SymbolValue;
+    fn from_value(value: SymbolValue) -> Result<Self::Output>;
+}
 
-        let client = client_builder.build()?;
-        log::info!("Сетевой модуль инициализирован");
-        Ok(Self { client, db })
-    }
+pub enum SymbolValue {
+    Bytes(Vec<u8>),
+    I64(i64),
+    F64(f64),
 }
 
-#[async_trait]
-impl NetworkTrait for Network {
-    async fn fetch_html(&self, url: &str) -> Result<String> {
-        let start = Instant::now();
-        let response = self
-            .client
-            .get(url)
-            .send()
-            .await
-            .context(format!("Не удалось выполнить запрос к {}", url))?;
+pub struct BytesFn;
+pub struct I64Fn;
+pub struct F64Fn;
 
-        let status = response.status().as_u16();
-        if !response.status().is_success() {
-            return Err(anyhow::anyhow!("Ошибка HTTP: {}", status));
+impl SymbolType for BytesFn {
+    const KIND: SymbolKind = SymbolKind::Bytes;
+    type Input = Vec<u8>;
+    type Output = Vec<u8>;
+    fn to_value(input: Vec<u8>) -> SymbolValue {
+        SymbolValue::Bytes(input)
+    }
+    fn from_value(value: SymbolValue) -> Result<Vec<u8>> {
+        match value {
+            SymbolValue::Bytes(bytes) => Ok(bytes),
+            _ => Err(anyhow::anyhow!("Символ вернул не bytes")),
         }
-
-        let html = response
-            .text()
-            .await
-            .context(format!("Не удалось декодировать ответ от {}", url))?;
-
-        log::info!("Успешно загружен HTML для {} за {:?}", url, start.elapsed());
-        Ok(html)
     }
+}
 
-    async fn fetch_html_with_headers(&self, url: &str) -> Result<(String, HeaderMap)> {
-        let start = Instant::now();
-        let response = self
-            .client
-            .get(url)
-            .send()
-            .await
-            .context(format!("Не удалось выполнить запрос к {}", url))?;
+impl SymbolType for I64Fn {
+    const KIND: SymbolKind = SymbolKind::I64;
+    type Input = i64;
+    type Output = i64;
+    fn to_value(input: i64) -> SymbolValue {
+        SymbolValue::I64(input)
+    }
+    fn from_value(value: SymbolValue) -> Result<i64> {
+        match value {
+            SymbolValue::I64(value) => Ok(value),
+            _ => Err(anyhow::anyhow!("Символ вернул не i64")),
+        }
+    }
+}
 
-        let status = response.status().as_u16();
-        let headers = response.headers().clone();
-        if !response.status().is_success() {
-            return Err(anyhow::anyhow!("Ошибка HTTP: {}", status));
+impl SymbolType for F64Fn {
+    const KIND: SymbolKind = SymbolKind::F64;
+    type Input = f64;
+    type Output = f64;
+    fn to_value(input: f64) -> SymbolValue {
+        SymbolValue::F64(input)
+    }
+    fn from_value(value: SymbolValue) -> Result<f64> {
+        match value {
+            SymbolValue::F64(value) => Ok(value),
+            _ => Err(anyhow::anyhow!("Символ вернул не f64")),
         }
+    }
+}
 
-        let html = response
-            .text()
-            .await
-            .context(format!("Не удалось декодировать ответ от {}", url))?;
+enum Backend {
+    #[cfg(feature = "native-libs")]
+    Native(Arc<libloading::Library>),
+    Wasm { runtime: Arc<dyn WasmRuntimeTrait + Send + Sync>, instance: InstanceId },
+}
 
-        log::info!("Успешно загружен HTML с заголовками для {} за {:?}", url, start.elapsed());
-        Ok((html, headers))
-    }
+// Загруженная версия библиотеки. После перезагрузки старая версия живёт,
+// пока её держат выданные аренды
+pub struct LoadedLibrary {
+    metadata: LibraryMetadata,
+    backend: Backend,
+    generation: u64,
+    leases: AtomicUsize,
 }
 
-// Асинхронный сетевой сервис для обработки событий движка
-pub async fn run_network_service(network: Arc<dyn NetworkTrait + Send + Sync>, engine: Arc<BroEngine>) {
-    log::info!("Запуск сетевого сервиса");
-    loop {
-        // Неблокирующий приём событий
-        match engine.receive_event() {
-            Some(EngineEvent::UrlRequest { url, response_tx }) => {
-                let url_clone = url.clone();
-                log::info!("Обработка сетевого запроса для URL: {}", url_clone);
-                let network_clone = network.clone();
-                let engine_clone = engine.clone();
-                // Запускаем обработку запроса в отдельной задаче
-                tokio::spawn(async move {
-                    let start = Instant::now();
-                    match network_clone.fetch_html_with_headers(&url_clone).await {
-                        Ok((html, headers)) => {
-                            let response = UrlResponse {
-                                url: url_clone.clone(),
-                                html,
-                                headers,
-                                status: 200,
-                                duration: start.elapsed(),
-                            };
-                            if let Err(e) = response_tx.send(response) {
-                                log::error!("Ошибка отправки ответа для URL {}: {}", url_clone, e);
-                                engine_clone.send_event(EngineEvent::Error(format!(
-                                    "Ошибка отправки ответа: {}",
-                                    e
-                                )));
-                            } else {
-                                log::info!("Ответ отправлен для URL: {}", url_clone);
-                            }
-                        }
-                        Err(e) => {
-                            log::error!("Ошибка загрузки URL {}: {}", url_clone, e);
-                            engine_clone.send_event(EngineEvent::Error(format!(
-                                "Ошибка загрузки {}: {}",
-                                url_clone, e
-                            )));
-                        }
-                    }
-                });
-            }
-            Some(event) => {
-                log::debug!("Пропуск события: {:?}", event);
-            }
-            None => {
-                // Небольшая задержка для предотвращения активного опроса
-                tokio::time::sleep(Duration::from_millis(1)).await;
+impl LoadedLibrary {
+    async fn call(&self, symbol: &str, kind: SymbolKind, input: SymbolValue) -> Result<SymbolValue> {
+        match &self.backend {
+            #[cfg(feature = "native-libs")]
+            Backend::Native(library) => {
+                let library = library.clone();
+                let (symbol, free) = (format!("{}\0", symbol), format!("{}\0", self.metadata.free));
+                tokio::task::spawn_blocking(move || unsafe { call_native(&library, &symbol, &free, kind, input) }).await?
             }
+            Backend::Wasm { runtime, instance } => match (kind, input) {
+                (SymbolKind::Bytes, SymbolValue::Bytes(bytes)) => {
+                    Ok(SymbolValue::Bytes(runtime.call_bytes(*instance, symbol, bytes).await?))
+                }
+                (SymbolKind::I64, SymbolValue::I64(value)) => {
+                    match runtime.call(*instance, symbol, vec![WasmValue::I64(value)]).await?.as_slice() {
+                        [WasmValue::I64(result)] => Ok(SymbolValue::I64(*result)),
+                        _ => Err(anyhow::anyhow!("{} вернул не i64", symbol)),
+                    }
+                }
+                (SymbolKind::F64, SymbolValue::F64(value)) => {
+                    match runtime.call(*instance, symbol, vec![WasmValue::F64(value)]).await?.as_slice() {
+                        [WasmValue::F64(result)] => Ok(SymbolValue::F64(*result)),
+                        _ => Err(anyhow::anyhow!("{} вернул не f64", symbol)),
+                    }
+                }
+                _ => Err(anyhow::anyhow!("Аргумент не соответствует сигнатуре {}", symbol)),
+            },
+        }
+    }
+
+    // Без feature native-libs у Backend остаётся один вариант
+    #[allow(irrefutable_let_patterns)]
+    async fn release(&self) {
+        if let Backend::Wasm { runtime, instance } = &self.backend {
+            runtime.drop_instance(*instance).await;
         }
     }
 }
 
-// Функция загрузки URL с использованием движка
-pub async fn load_url_generic(url: String, engine: &Arc<BroEngine>) -> Result<(String, HeaderMap, RequestLog)> {
-    let response = engine.fetch_url(url.clone()).await?;
-    let log = RequestLog {
-        url: url.clone(),
-        method: "GET".to_string(),
-        status: response.status,
-        headers: response.headers.clone(),
-        duration: response.duration,
-    };
-    Ok((response.html, response.headers, log))
+#[cfg(feature = "native-libs")]
+unsafe fn call_native(library: &libloading::Library, symbol: &str, free: &str, kind: SymbolKind, input: SymbolValue) -> Result<SymbolValue> {
+    match (kind, input) {
+        (SymbolKind::Bytes, SymbolValue::Bytes(bytes)) => {
+            let call: libloading::Symbol<unsafe extern "C" fn(*const u8, usize, *mut usize) -> *mut u8> = library.get(symbol.as_bytes())?;
+            let free: libloading::Symbol<unsafe extern "C" fn(*mut u8, usize)> = library.get(free.as_bytes())?;
+            let mut len = 0usize;
+            let ptr = call(bytes.as_ptr(), bytes.len(), &mut len);
+            if ptr.is_null() {
+                return Err(anyhow::anyhow!("Символ {} вернул пустой указатель", symbol.trim_end_matches('\0')));
+            }
+            let output = std::slice::from_raw_parts(ptr, len).to_vec();
+            free(ptr, len);
+            Ok(SymbolValue::Bytes(output))
+        }
+        (SymbolKind::I64, SymbolValue::I64(value)) => {
+            let call: libloading::Symbol<unsafe extern "C" fn(i64) -> i64> = library.get(symbol.as_bytes())?;
+            Ok(SymbolValue::I64(call(value)))
+        }
+        (SymbolKind::F64, SymbolValue::F64(value)) => {
+            let call: libloading::Symbol<unsafe extern "C" fn(f64) -> f64> = library.get(symbol.as_bytes())?;
+            Ok(SymbolValue::F64(call(value)))
+        }
+        _ => Err(anyhow::anyhow!("Аргумент не соответствует сигнатуре символа")),
+    }
 }
 
-pub struct RequestLog {
-    pub url: String,
-    pub method: String,
-    pub status: u16,
-    pub headers: HeaderMap,
-    pub duration: Duration,
+// Аренда библиотеки: пока она жива, библиотеку нельзя выгрузить
+pub struct LibraryLease {
+    library: Arc<LoadedLibrary>,
 }
 
-// === FILE: net\ipfs.rs ===
-use anyhow::Result;
-use std::sync::Arc;
-use crate::net::Network;
-pub struct IPFS;
-impl IPFS {
-    pub fn new(_network: Arc<Network>) -> Result<Self> {
-        println!("IPFS initialized");
-        Ok(Self)
+impl LibraryLease {
+    pub fn metadata(&self) -> &LibraryMetadata {
+        &self.library.metadata
     }
-}
 
-// === FILE: net\load_url.rs ===
-use crate::core::engine::{BroEngine, UrlResponse};
-use crate::ui::devtools::RequestLog;
-use reqwest::header::HeaderMap;
-use anyhow::Result;
-use std::sync::Arc;
-use std::time::{Instant, Duration};
+    pub fn generation(&self) -> u64 {
+        self.library.generation
+    }
 
-pub async fn load_url_generic(
-    url: String,
-    engine: &Arc<BroEngine>,
-) -> Result<(String, HeaderMap, RequestLog)> {
-    let start_time = Instant::now();
-    
-    // Выполняем сетевой запрос через BroEngine
-    match engine.fetch_url(url.clone()).await {
-        Ok(response) => {
-            let headers = response.headers.clone();
-            let log = RequestLog {
-                url: url.clone(),
-                method: "GET".into(),
-                status: response.status,
-                headers: headers.clone(),
-                duration: start_time.elapsed(),
-                html: response.html.clone(),
-            };
-            Ok((response.html, headers, log))
-        }
-        Err(e) => {
-            // Если сеть отключена или произошла ошибка
-            let html = format!(
-                "<html><body><h1>Ошибка загрузки {}</h1><p>{}</p></body></html>",
-                url, e
-            );
-            let headers = HeaderMap::new();
-            let log = RequestLog {
-                url,
-                method: "GET".into(),
-                status: 0,
-                headers: headers.clone(),
-                duration: Duration::from_millis(0),
-                html: html.clone(),
-            };
-            Ok((html, headers, log))
+    pub fn symbol<T: SymbolType>(&self, name: &str) -> Result<TypedSymbol<T>> {
+        match self.library.metadata.symbols.get(name) {
+            Some(kind) if *kind == T::KIND => Ok(TypedSymbol {
+                library: self.library.clone(),
+                name: name.to_string(),
+                _type: PhantomData,
+            }),
+            Some(kind) => Err(anyhow::anyhow!(
+                "Символ {} библиотеки {} имеет сигнатуру {:?}, запрошена {:?}",
+                name,
+                self.library.metadata.name,
+                kind,
+                T::KIND
+            )),
+            None => Err(anyhow::anyhow!("Библиотека {} не экспортирует {}", self.library.metadata.name, name)),
         }
     }
 }
 
-// === FILE: net\mod.rs ===
-pub mod fetch;
-pub mod webrtc;
-pub mod ipfs;
-pub mod websocket;
-pub mod proto;
-pub mod tls;
-pub mod p2p;
-pub mod cache;
-pub mod stream;
-pub mod load_url;
-pub use fetch::Network;
+impl Drop for LibraryLease {
+    fn drop(&mut self) {
+        self.library.leases.fetch_sub(1, Ordering::SeqCst);
+    }
+}
 
+// Символ держит свою версию библиотеки: перезагрузка не подменяет код посреди работы
+pub struct TypedSymbol<T: SymbolType> {
+    library: Arc<LoadedLibrary>,
+    name: String,
+    _type: PhantomData<T>,
+}
 
-// === FILE: net\p2p.rs ===
-use anyhow::Result;
-use std::sync::Arc;
-use crate::net::Network;
-pub struct P2P;
-impl P2P {
-    pub fn new(_network: Arc<Network>) -> Result<Self> {
-        println!("P2P initialized");
-        Ok(Self)
+impl<T: SymbolType> TypedSymbol<T> {
+    pub async fn call(&self, input: T::Input) -> Result<T::Output> {
+        T::from_value(self.library.call(&self.name, T::KIND, T::to_value(input)).await?)
     }
 }
 
-// === FILE: net\proto.rs ===
-use anyhow::Result;
-use std::sync::Arc;
-use crate::net::Network;
-pub struct Proto;
-impl Proto {
-    pub fn new(_network: Arc<Network>) -> Result<Self> {
-        println!("Proto initialized");
-        Ok(Self)
-    }
+struct Entry {
+    path: PathBuf,
+    kind: LibraryKind,
+    metadata: LibraryMetadata,
+    modified: Option<SystemTime>,
+    loaded: Option<Arc<LoadedLibrary>>,
+    generation: u64,
 }
 
-// === FILE: net\stream.rs ===
-use anyhow::Result;
-use std::sync::Arc;
-use crate::net::Network;
-pub struct Stream;
-impl Stream {
-    pub fn new(_network: Arc<Network>) -> Result<Self> {
-        println!("Stream initialized");
-        Ok(Self)
-    }
+struct Inner {
+    libs_dir: PathBuf,
+    security: Arc<dyn SecurityManagerTrait + Send + Sync>,
+    runtime: Arc<dyn WasmRuntimeTrait + Send + Sync>,
+    entries: RwLock<HashMap<String, Entry>>,
 }
 
-// === FILE: net\tls.rs ===
-use anyhow::Result;
-use std::sync::Arc;
-use crate::net::Network;
-pub struct TLS;
-impl TLS {
-    pub fn new(_network: Arc<Network>) -> Result<Self> {
-        println!("TLS initialized");
-        Ok(Self)
+fn library_kind(path: &Path) -> Option<LibraryKind> {
+    match path.extension().and_then(|e| e.to_str())? {
+        "so" | "dll" | "dylib" => Some(LibraryKind::Native),
+        "wasm" => Some(LibraryKind::Wasm),
+        _ => None,
     }
 }
 
-// === FILE: net\webrtc.rs ===
-use anyhow::Result;
-use crate::core::config::Config;
-pub struct WebRTC;
-impl WebRTC {
-    pub fn new(_config: &Config) -> Result<Self> {
-        println!("WebRTC initialized");
-        Ok(Self)
+pub fn metadata_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".yaml");
+    PathBuf::from(name)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+    Some((parts.next()??, parts.next()??, parts.next()??))
+}
+
+impl Inner {
+    // Сканирование каталога; при нескольких файлах с одним именем побеждает старшая версия.
+    // Метаданные здесь не проверены и служат только для списка: символы берутся из подписанных в open
+    fn discover(&self) -> Result<Vec<(PathBuf, LibraryKind, LibraryMetadata)>> {
+        let mut found: HashMap<String, (PathBuf, LibraryKind, LibraryMetadata)> = HashMap::new();
+        if !self.libs_dir.exists() {
+            return Ok(Vec::new());
+        }
+        for entry in std::fs::read_dir(&self.libs_dir)? {
+            let path = entry?.path();
+            let Some(kind) = library_kind(&path) else {
+                continue;
+            };
+            let metadata = match std::fs::read_to_string(metadata_path(&path))
+                .map_err(anyhow::Error::from)
+                .and_then(|text| serde_yaml::from_str::<LibraryMetadata>(&text).map_err(anyhow::Error::from))
+            {
+                Ok(metadata) if parse_version(&metadata.version).is_some() => metadata,
+                Ok(metadata) => {
+                    log::warn!("Библиотека {:?}: версия «{}» не в формате semver", path, metadata.version);
+                    continue;
+                }
+                Err(e) => {
+                    log::warn!("Библиотека {:?} без метаданных пропущена: {}", path, e);
+                    continue;
+                }
+            };
+            let newer = found
+                .get(&metadata.name)
+                .map_or(true, |(_, _, current)| parse_version(&metadata.version) > parse_version(&current.version));
+            if newer {
+                found.insert(metadata.name.clone(), (path, kind, metadata));
+            }
+        }
+        Ok(found.into_values().collect())
     }
-    pub fn create_peer_connection(&self, _peer_id: &str) -> Result<()> {
-        println!("Creating WebRTC peer connection for peer: {}", _peer_id);
-        Ok(())
+
+    async fn open(&self, path: &Path, kind: LibraryKind, name: &str, generation: u64) -> Result<LoadedLibrary> {
+        // Сигнатуры символов определяют, как вызывается код библиотеки, поэтому метаданные подписаны
+        let text = load_verified(self.security.as_ref(), &metadata_path(path)).await?;
+        let metadata: LibraryMetadata = serde_yaml::from_slice(&text)?;
+        if metadata.name != name {
+            return Err(anyhow::anyhow!("Подписанные метаданные {:?} относятся к {}, а не к {}", path, metadata.name, name));
+        }
+        // Подпись проверяется до того, как библиотека попадёт в процесс
+        let bytes = load_verified(self.security.as_ref(), path).await?;
+        let backend = match kind {
+            LibraryKind::Wasm => {
+                let signature = tokio::fs::read(signature_path(path)).await?;
+                let digest = self.runtime.load_module(&bytes, &signature).await?;
+                let sandbox = SandboxConfig { name: metadata.name.clone(), ..SandboxConfig::default() };
+                Backend::Wasm { runtime: self.runtime.clone(), instance: self.runtime.instantiate(&digest, sandbox).await? }
+            }
+            // Каждое поколение открывается из своей копии проверенных байтов: dlopen того же пути
+            // вернул бы уже загруженную версию, а файл в каталоге мог измениться после проверки
+            #[cfg(feature = "native-libs")]
+            LibraryKind::Native => {
+                let staged = stage_verified("libs", path, &bytes)?;
+                let library = unsafe { libloading::Library::new(&staged) };
+                #[cfg(unix)]
+                if let Err(e) = std::fs::remove_file(&staged) {
+                    log::warn!("Не удалось удалить копию библиотеки {}: {}", staged.display(), e);
+                }
+                Backend::Native(Arc::new(library?))
+            }
+            #[cfg(not(feature = "native-libs"))]
+            LibraryKind::Native => {
+                let _ = bytes;
+                return Err(anyhow::anyhow!("Сборка без поддержки нативных библиотек (feature native-libs)"));
+            }
+        };
+        log::info!("Загружена библиотека {} {} (поколение {})", metadata.name, metadata.version, generation);
+        Ok(LoadedLibrary { metadata, backend, generation, leases: AtomicUsize::new(0) })
     }
-    pub fn send_data(&self, _peer_id: &str, _data: &[u8]) -> Result<()> {
-        println!("Sending data to peer: {}", _peer_id);
-        Ok(())
+
+    async fn load(&self, name: &str) -> Result<Arc<LoadedLibrary>> {
+        let (path, kind, generation) = {
+            let entries = self.entries.read();
+            let entry = entries.get(name).ok_or_else(|| anyhow::anyhow!("Библиотека {} не найдена в {:?}", name, self.libs_dir))?;
+            if let Some(loaded) = &entry.loaded {
+                return Ok(loaded.clone());
+            }
+            (entry.path.clone(), entry.kind, entry.generation)
+        };
+        let library = Arc::new(self.open(&path, kind, name, generation).await?);
+        let mut entries = self.entries.write();
+        let entry = entries.get_mut(name).ok_or_else(|| anyhow::anyhow!("Библиотека {} удалена во время загрузки", name))?;
+        // Параллельная загрузка той же версии: остаётся первая
+        let loaded = entry.loaded.get_or_insert(library).clone();
+        entry.metadata = loaded.metadata.clone();
+        Ok(loaded)
     }
-    pub fn close_connection(&self, _peer_id: &str) -> Result<()> {
-        println!("Closing WebRTC connection for peer: {}", _peer_id);
+
+    // Обновление реестра по состоянию каталога; изменённые загруженные библиотеки перезагружаются.
+    // Запись загруженной библиотеки меняется только после загрузки новой версии: при ошибке
+    // остаётся прежняя, и следующее сканирование снова видит изменение и повторяет попытку
+    async fn rescan(&self) -> Result<()> {
+        // Файловая система опрашивается до блокировки реестра
+        let discovered: Vec<_> = self
+            .discover()?
+            .into_iter()
+            .map(|(path, kind, metadata)| {
+                let modified = modified(&path).max(modified(&metadata_path(&path)));
+                (path, kind, metadata, modified)
+            })
+            .collect();
+        let mut reload = Vec::new();
+        {
+            let mut entries = self.entries.write();
+            entries.retain(|name, entry| {
+                let keep = discovered.iter().any(|(_, _, metadata, _)| &metadata.name == name) || entry.loaded.is_some();
+                if !keep {
+                    log::info!("Библиотека {} удалена из каталога", name);
+                }
+                keep
+            });
+            for (path, kind, metadata, modified) in discovered {
+                match entries.get_mut(&metadata.name) {
+                    Some(entry) if entry.path == path && entry.modified == modified => {}
+                    Some(entry) if entry.loaded.is_some() => reload.push((entry.generation, path, kind, metadata.name, modified)),
+                    Some(entry) => {
+                        entry.generation += 1;
+                        entry.path = path;
+                        entry.kind = kind;
+                        entry.metadata = metadata;
+                        entry.modified = modified;
+                    }
+                    None => {
+                        log::info!("Найдена библиотека {} {}", metadata.name, metadata.version);
+                        entries.insert(
+                            metadata.name.clone(),
+                            Entry { path, kind, metadata, modified, loaded: None, generation: 1 },
+                        );
+                    }
+                }
+            }
+        }
+        for (generation, path, kind, name, modified) in reload {
+            let library = match self.open(&path, kind, &name, generation + 1).await {
+                Ok(library) => Arc::new(library),
+                Err(e) => {
+                    log::warn!("Не удалось перезагрузить {}: {}; остаётся прежняя версия", name, e);
+                    continue;
+                }
+            };
+            // Новые аренды получают новую версию, старая освобождается с последней арендой
+            let swapped = {
+                let mut entries = self.entries.write();
+                match entries.get_mut(&name) {
+                    Some(entry) if entry.generation == generation && entry.loaded.is_some() => {
+                        entry.generation += 1;
+                        entry.path = path;
+                        entry.kind = kind;
+                        entry.metadata = library.metadata.clone();
+                        entry.modified = modified;
+                        Some(entry.loaded.replace(library.clone()))
+                    }
+                    // Библиотеку выгрузили или перезагрузили, пока шла загрузка
+                    _ => None,
+                }
+            };
+            match swapped {
+                Some(previous) => {
+                    log::info!("Библиотека {} перезагружена (поколение {})", name, library.generation);
+                    if let Some(previous) = previous {
+                        release_when_unused(previous);
+                    }
+                }
+                None => release_when_unused(library),
+            }
+        }
         Ok(())
     }
 }
 
-// === FILE: net\websocket.rs ===
-use anyhow::Result;
-use std::sync::Arc;
-use crate::net::Network;
-pub struct Websocket;
-impl Websocket {
-    pub fn new(_network: Arc<Network>) -> Result<Self> {
-        println!("Websocket initialized");
-        Ok(Self)
-    }
+// WASM-экземпляр прежней версии удаляется, когда не осталось аренд и символов
+fn release_when_unused(library: Arc<LoadedLibrary>) {
+    tokio::spawn(async move {
+        while Arc::strong_count(&library) > 1 {
+            tokio::time::sleep(WATCH_INTERVAL).await;
+        }
+        library.release().await;
+    });
 }
 
-// === FILE: ui\aichat.rs ===
-use egui::{Context as EguiContext, SidePanel, ScrollArea, TextEdit, Ui};
-use crate::ui::devtools::PanelAction; // Импорт из devtools, так как общий
-pub fn render_aichat(
-    ctx: &EguiContext,
-    ai_chat_input: &mut String,
-    ai_chat_history: &[(String, String)],
-    send_ai_message: &mut bool,
-) {
-    SidePanel::right("ai_chat_panel")
-        .resizable(true)
-        .default_width(400.0)
-        .min_width(200.0)
-        .show(ctx, |ui| {
-            render_aichat_ui(ui, ai_chat_input, ai_chat_history, send_ai_message, false)
-        });
+pub struct LibManager {
+    inner: Arc<Inner>,
 }
-pub fn render_aichat_ui(
-    ui: &mut Ui,
-    ai_chat_input: &mut String,
-    ai_chat_history: &[(String, String)],
-    send_ai_message: &mut bool,
-    is_detached: bool,
-) -> Option<PanelAction> {
-    let mut action: Option<PanelAction> = None;
-    ui.horizontal(|ui| {
-		if ui.button(if is_detached { "🗔" } else { "🗔" }).clicked() {
-            action = Some(if is_detached { PanelAction::Attach } else { PanelAction::Detach });
-        }
-		ui.heading("Чат с AI (F11)");
-    });
-    ScrollArea::vertical().show(ui, |ui| {
-        ui.label("История чата (заглушка):");
-        for (user_msg, ai_response) in ai_chat_history {
-            ui.label(format!("Вы: {}", user_msg));
-            ui.label(format!("AI: {}", ai_response));
-            ui.add_space(5.0);
-        }
-    });
-    ui.add_space(10.0);
-    ui.label("Ваш запрос:");
-    ui.add(TextEdit::multiline(ai_chat_input).desired_rows(3).min_size(egui::vec2(100.0, 50.0)));
-    if ui.button("Отправить").clicked() {
-        *send_ai_message = true;
+
+impl LibManager {
+    // Опрос каталога: изменения файлов библиотек и метаданных подхватываются без перезапуска
+    fn watch(inner: Weak<Inner>) {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(WATCH_INTERVAL).await;
+                let Some(inner) = inner.upgrade() else {
+                    return;
+                };
+                if let Err(e) = inner.rescan().await {
+                    log::warn!("Ошибка сканирования каталога библиотек: {}", e);
+                }
+            }
+        });
     }
-    action
-}
 
-// === FILE: ui\clipboard.rs ===
-use anyhow::Result;
-pub struct Clipboard;
-impl Clipboard {
-    pub fn new() -> Result<Self> {
-        println!("Clipboard initialized");
-        Ok(Self)
+    pub async fn symbol<T: SymbolType>(&self, library: &str, symbol: &str) -> Result<(LibraryLease, TypedSymbol<T>)> {
+        let lease = self.acquire(library).await?;
+        let symbol = lease.symbol::<T>(symbol)?;
+        Ok((lease, symbol))
     }
 }
 
-// === FILE: ui\compositor.rs ===
-use crate::core::interfaces::CompositorTrait;
-use anyhow::Result;
-use winit::window::Window;
-use async_trait::async_trait;
-pub struct Compositor;
 #[async_trait]
-impl CompositorTrait for Compositor {
-    async fn new(_window: &Window, _config: &()) -> Result<Self> {
-        log::info!("Компоновщик инициализирован");
-        Ok(Self)
+impl LibManagerTrait for LibManager {
+    async fn new(
+        libs_dir: impl AsRef<Path> + Send,
+        runtime: Arc<dyn WasmRuntimeTrait + Send + Sync>,
+        security: Arc<dyn SecurityManagerTrait + Send + Sync>,
+    ) -> Result<Self> {
+        let libs_dir = libs_dir.as_ref().to_path_buf();
+        let inner = Arc::new(Inner { libs_dir, security, runtime, entries: RwLock::new(HashMap::new()) });
+        inner.rescan().await?;
+        Self::watch(Arc::downgrade(&inner));
+        log::info!(
+            "Менеджер библиотек инициализирован с каталогом {:?}, найдено библиотек: {}",
+            inner.libs_dir,
+            inner.entries.read().len()
+        );
+        Ok(Self { inner })
     }
-    async fn render_text(&self, _text: &str) {
-        log::info!("Отрисовка текста: {}", _text);
+    fn libraries(&self) -> Vec<LibraryInfo> {
+        let mut libraries: Vec<_> = self
+            .inner
+            .entries
+            .read()
+            .values()
+            .map(|entry| LibraryInfo {
+                metadata: entry.metadata.clone(),
+                kind: entry.kind,
+                path: entry.path.clone(),
+                loaded: entry.loaded.is_some(),
+                leases: entry.loaded.as_ref().map_or(0, |library| library.leases.load(Ordering::SeqCst)),
+                generation: entry.generation,
+            })
+            .collect();
+        libraries.sort_by(|a, b| a.metadata.name.cmp(&b.metadata.name));
+        libraries
     }
-    async fn resize(&self, _width: u32, _height: u32) -> Result<()> {
-        log::info!("Изменение размера: {}x{}", _width, _height);
+    async fn load_library(&self, library_name: &str) -> Result<()> {
+        self.inner.load(library_name).await.map(|_| ())
+    }
+    async fn acquire(&self, library_name: &str) -> Result<LibraryLease> {
+        let library = self.inner.load(library_name).await?;
+        library.leases.fetch_add(1, Ordering::SeqCst);
+        Ok(LibraryLease { library })
+    }
+    // Используемую библиотеку выгрузить нельзя: сначала должны завершиться все аренды
+    async fn unload_library(&self, library_name: &str) -> Result<()> {
+        let library = {
+            let mut entries = self.inner.entries.write();
+            let entry = entries
+                .get_mut(library_name)
+                .ok_or_else(|| anyhow::anyhow!("Библиотека {} не найдена", library_name))?;
+            let leases = entry.loaded.as_ref().map_or(0, |library| library.leases.load(Ordering::SeqCst));
+            if leases > 0 {
+                return Err(anyhow::anyhow!("Библиотека {} используется ({} аренд)", library_name, leases));
+            }
+            entry.loaded.take().ok_or_else(|| anyhow::anyhow!("Библиотека {} не загружена", library_name))?
+        };
+        release_when_unused(library);
+        log::info!("Выгрузка библиотеки: {}", library_name);
         Ok(())
     }
 }
 
-// === FILE: ui\devtools.rs ===
-use crate::core::engine::{EngineEvent, BroEngine, UrlResponse};
-use crate::core::page_state::FrameworkType;
-use egui::{Context as EguiContext, SidePanel, ScrollArea, TextEdit, Ui, TextStyle, Id};
-use std::time::{Duration, Instant};
-use reqwest::header::HeaderMap;
-use crate::ui::heavy_calculator::HeavyCalculator;
-use tokio::sync::broadcast;
-use tokio::sync::mpsc::UnboundedSender;
-use sysinfo::System;
-use std::sync::Arc;
+// === FILE: core\mod.rs ===
+pub mod engine;
+pub mod about;
+pub mod event_loop;
+pub mod scheduler;
+pub mod runtime;
+pub mod js_runtime;
+pub mod orchestrator;
+pub mod wasm_manifest;
+pub mod oci;
+pub mod provider;
+pub mod plugins;
+pub mod libs;
+pub mod yuaidb;
+pub mod config;
+pub mod security;
+pub mod reactive;
+pub mod service_worker;
+pub mod io_manager;
+pub mod session;
+pub mod profile;
+pub mod installer;
+pub mod interfaces;
+pub mod ssh;
+pub mod page_state;
+pub mod policy;
+pub mod origin;
+pub mod permissions;
+pub mod web_storage;
+
+// === FILE: core\oci.rs ===
+use crate::core::config::{Config, RegistryConfig};
+use crate::core::security::to_hex;
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
 
-#[derive(PartialEq, Clone, Copy)]
-pub enum DevToolsTab {
-    Html,
-    Network,
-    Debug,
+// Слои артефакта: сам компонент и, по соглашению yuaibro, его манифест в YAML
+pub const WASM_MEDIA_TYPES: &[&str] = &[
+    "application/wasm",
+    "application/vnd.wasm.content.layer.v1+wasm",
+    "application/vnd.module.wasm.content.layer.v1+wasm",
+];
+pub const MANIFEST_MEDIA_TYPE: &str = "application/vnd.yuaibro.manifest.v1+yaml";
+// Подпись манифеста (как <name>.yaml.sig); без неё load_oci артефакт не устанавливает
+pub const MANIFEST_SIGNATURE_MEDIA_TYPE: &str = "application/vnd.yuaibro.manifest.signature.v1";
+
+pub fn digest_of(bytes: &[u8]) -> String {
+    format!("sha256:{}", to_hex(&Sha256::digest(bytes)))
 }
 
-#[derive(Clone)]
-pub struct RequestLog {
-    pub url: String,
-    pub method: String,
-    pub status: u16,
-    pub headers: HeaderMap,
-    pub duration: Duration,
-    pub html: String,
+pub fn verify_digest(expected: &str, bytes: &[u8]) -> Result<()> {
+    let actual = digest_of(bytes);
+    if actual != expected {
+        return Err(anyhow::anyhow!("Дайджест не совпадает: ожидался {}, получен {}", expected, actual));
+    }
+    Ok(())
 }
 
-pub struct DevToolsState {
-    pub engine: Arc<BroEngine>,
-    pub detected_framework: Option<FrameworkType>,
-    pub network_logs: Vec<RequestLog>,
-    pub debug_info: DebugInfo,
-    pub rx_engine: broadcast::Receiver<EngineEvent>,
-	
+// Хранилище по содержимому: ~/.cosmonaut/oci/blobs/sha256/<hex>
+pub struct ContentStore {
+    root: PathBuf,
 }
 
-impl DevToolsState {
-    pub fn poll_events(&mut self) {
-        while let Ok(event) = self.rx_engine.try_recv() {
-            match event {
-                EngineEvent::FrameworkDetected(fw) => {
-                    self.detected_framework = Some(fw);
-                }
-                EngineEvent::UrlResponse(response) => {
-                    if !self.network_logs.iter().any(|log| log.url == response.url && log.duration == response.duration) {
-                        self.network_logs.push(RequestLog {
-                            url: response.url,
-                            method: "GET".to_string(),
-                            status: response.status,
-                            headers: response.headers,
-                            duration: response.duration,
-                            html: response.html,
-                        });
-                    }
-                }
-                EngineEvent::Error(err) => {
-                    self.network_logs.push(RequestLog {
-                        url: "Ошибка".to_string(),
-                        method: "N/A".to_string(),
-                        status: 0,
-                        headers: HeaderMap::new(),
-                        duration: Duration::ZERO,
-                        html: err,
-                    });
-                }
-                _ => {}
-            }
-        }
+impl ContentStore {
+    pub fn new(root: PathBuf) -> Result<Self> {
+        std::fs::create_dir_all(root.join("blobs").join("sha256"))?;
+        Ok(Self { root })
     }
-}
 
-#[derive(Clone, Default)]
-pub struct DebugInfo {
-    pub data_received: usize,
-    pub data_size: String,
-    pub transfer_speed: f64,
-    pub render_time: Duration,
-    pub server_response_time: Duration,
-    pub dom_elements: usize,
-    pub memory_usage: String,
-    pub frame_rate: f64,
-    pub last_frame_time: Option<Instant>,
-    pub frame_count: u32,
-    pub fps_update_time: Option<Instant>,
-}
+    fn path(&self, digest: &str) -> Result<PathBuf> {
+        let hex = digest
+            .strip_prefix("sha256:")
+            .filter(|hex| hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| anyhow::anyhow!("Неподдерживаемый дайджест: {}", digest))?;
+        Ok(self.root.join("blobs").join("sha256").join(hex.to_ascii_lowercase()))
+    }
 
-impl DebugInfo {
-    pub fn new() -> Self {
-        Self {
-            last_frame_time: Some(Instant::now()),
-            fps_update_time: Some(Instant::now()),
-            ..Default::default()
-        }
+    pub fn contains(&self, digest: &str) -> bool {
+        self.path(digest).is_ok_and(|path| path.exists())
     }
 
-    pub fn update(&mut self, html: &str, render_time: Duration, response_time: Duration) {
-        self.data_received = html.len();
-        self.data_size = Self::format_size(html.len());
-        self.transfer_speed = if response_time.as_secs_f64() > 0.0 {
-            html.len() as f64 / response_time.as_secs_f64() / 1024.0
-        } else {
-            0.0
+    // Содержимое перепроверяется при чтении: повреждённый файл удаляется и скачивается заново
+    pub async fn read(&self, digest: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.path(digest)?;
+        let bytes = match tokio::fs::read(&path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
         };
-        self.render_time = render_time;
-        self.server_response_time = response_time;
-        self.dom_elements = html.matches("<div").count() + html.matches("<p").count() + html.matches("<span").count();
-        let mut sys = System::new_all();
-        sys.refresh_all();
-        self.memory_usage = Self::format_size(sys.used_memory() as usize);
-        let now = Instant::now();
-        if let Some(last) = self.last_frame_time {
-            let delta = now - last;
-            if delta > Duration::from_millis(0) {
-                self.frame_rate = 1.0 / delta.as_secs_f64();
-            }
-        }
-        self.last_frame_time = Some(now);
-        self.frame_count += 1;
-        if let Some(update_time) = self.fps_update_time {
-            if now - update_time > Duration::from_secs(1) {
-                self.frame_rate = self.frame_count as f64 / (now - update_time).as_secs_f64();
-                self.frame_count = 0;
-                self.fps_update_time = Some(now);
-            }
+        if let Err(e) = verify_digest(digest, &bytes) {
+            log::warn!("Повреждённый blob {}: {}", digest, e);
+            tokio::fs::remove_file(&path).await?;
+            return Ok(None);
         }
+        Ok(Some(bytes))
     }
 
-    pub fn format_size(bytes: usize) -> String {
-        if bytes >= 1024 * 1024 {
-            format!("{:.2} MB", bytes as f64 / (1024.0 * 1024.0))
-        } else if bytes >= 1024 {
-            format!("{:.2} KB", bytes as f64 / 1024.0)
-        } else {
-            format!("{} B", bytes)
-        }
+    pub async fn write(&self, digest: &str, bytes: &[u8]) -> Result<()> {
+        verify_digest(digest, bytes)?;
+        let path = self.path(digest)?;
+        let partial = path.with_extension("partial");
+        tokio::fs::write(&partial, bytes).await?;
+        tokio::fs::rename(&partial, &path).await?;
+        Ok(())
     }
+}
 
-    pub fn format_duration(duration: Duration) -> String {
-        if duration.as_secs() > 0 {
-            format!("{:.2} s", duration.as_secs_f64())
-        } else if duration.as_millis() > 0 {
-            format!("{} ms", duration.as_millis())
-        } else {
-            format!("{} μs", duration.as_micros())
+#[derive(Debug)]
+pub struct PulledArtifact {
+    pub reference: String,
+    pub digest: String,
+    pub manifest: Option<Vec<u8>>,
+    pub manifest_signature: Option<Vec<u8>>,
+    pub wasm: Vec<u8>,
+}
+
+// Файл пароля не должен быть доступен группе и остальным, как и ключ подписи
+#[cfg(feature = "wasm")]
+fn read_password_file(path: &std::path::Path) -> Result<String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(path)?.permissions().mode();
+        if mode & 0o077 != 0 {
+            return Err(anyhow::anyhow!("Файл пароля {} доступен другим пользователям (права {:o})", path.display(), mode & 0o777));
         }
     }
+    let password = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Не удалось прочитать файл пароля {}: {}", path.display(), e))?;
+    Ok(password.trim_end_matches(['\r', '\n']).to_string())
 }
 
-#[derive(PartialEq, Debug)]
-pub enum PanelAction {
-    Attach,
-    Detach,
+pub struct OciPuller {
+    store: ContentStore,
+    registries: Vec<RegistryConfig>,
+    #[cfg(feature = "wasm")]
+    client: oci_distribution::Client,
 }
 
-pub fn render_devtools(
-    ctx: &EguiContext,
-    devtools_tab: &mut DevToolsTab,
-    html: &mut String,
-    headers: &HeaderMap,
-    debug_info: &DebugInfo,
-    network_logs: &Vec<RequestLog>,
-    tx_ssh: &UnboundedSender<String>,
-    devtools_state: &DevToolsState,
-) {
-    SidePanel::right("devtools_panel")
-        .resizable(true)
-        .default_width(400.0)
-        .min_width(200.0)
-        .show(ctx, |ui| {
-            render_devtools_ui(ui, devtools_tab, html, headers, debug_info, network_logs, tx_ssh, false, devtools_state);
-        });
-}
-
-pub fn render_devtools_ui(
-    ui: &mut Ui,
-    devtools_tab: &mut DevToolsTab,
-    html: &mut String,
-    headers: &HeaderMap,
-    debug_info: &DebugInfo,
-    network_logs: &Vec<RequestLog>,
-    tx_ssh: &UnboundedSender<String>,
-    is_detached: bool,
-    devtools_state: &DevToolsState,
-) -> Option<PanelAction> {
-    let mut action: Option<PanelAction> = None;
-
-    ui.horizontal(|ui| {
-        if ui.button(if is_detached { "Прикрепить" } else { "Открепить" }).clicked() {
-            action = Some(if is_detached { PanelAction::Attach } else { PanelAction::Detach });
-            log::info!("Переключение состояния панели DevTools: {:?}", action);
+impl OciPuller {
+    pub fn new(config: &Config, root: PathBuf) -> Result<Self> {
+        let store = ContentStore::new(root)?;
+        for registry in config.registries.iter().filter(|registry| registry.password.is_some()) {
+            log::warn!(
+                "Пароль реестра {} указан в settings.toml открытым текстом; перенесите его в password_file — при сохранении настроек он не записывается",
+                registry.host
+            );
         }
-        ui.heading("Инструменты (F12)");
-    });
+        #[cfg(feature = "wasm")]
+        {
+            let insecure: Vec<String> = config
+                .registries
+                .iter()
+                .filter(|registry| registry.insecure)
+                .map(|registry| registry.host.clone())
+                .collect();
+            let client = oci_distribution::Client::new(oci_distribution::client::ClientConfig {
+                protocol: oci_distribution::client::ClientProtocol::HttpsExcept(insecure),
+                ..Default::default()
+            });
+            Ok(Self { store, registries: config.registries.clone(), client })
+        }
+        #[cfg(not(feature = "wasm"))]
+        Ok(Self { store, registries: config.registries.clone() })
+    }
 
-    ui.horizontal_wrapped(|ui| {
-        ui.set_min_width(150.0);
-        ui.selectable_value(devtools_tab, DevToolsTab::Html, "HTML");
-        ui.selectable_value(devtools_tab, DevToolsTab::Network, "Сеть");
-        ui.selectable_value(devtools_tab, DevToolsTab::Debug, "Дебаг");
-    });
+    pub fn store(&self) -> &ContentStore {
+        &self.store
+    }
 
-    ui.separator();
+    #[cfg(feature = "wasm")]
+    fn auth(&self, registry: &str) -> Result<oci_distribution::secrets::RegistryAuth> {
+        Ok(match self.registries.iter().find(|entry| entry.host == registry) {
+            Some(RegistryConfig { username: Some(username), password, password_file, .. }) => {
+                let password = match password_file {
+                    Some(path) => read_password_file(path)?,
+                    None => password.clone().unwrap_or_default(),
+                };
+                oci_distribution::secrets::RegistryAuth::Basic(username.clone(), password)
+            }
+            _ => oci_distribution::secrets::RegistryAuth::Anonymous,
+        })
+    }
 
-    match *devtools_tab {
-        DevToolsTab::Html => {
-            ScrollArea::vertical().show(ui, |ui| {
-                ui.label("Редактировать HTML:");
-                ui.label(format!(
-					"Фреймворк: {}",
-					devtools_state.detected_framework
-						.as_ref()  
-						.map(|fw| format!("{:?}", fw))
-						.unwrap_or("Не обнаружен".to_string())
-				));
-                ui.add_space(5.0);
-                ui.add(
-                    TextEdit::multiline(html)
-                        .desired_rows(20)
-                        .font(TextStyle::Monospace),
-                );
-            });
+    #[cfg(feature = "wasm")]
+    async fn blob(&self, reference: &oci_distribution::Reference, layer: &oci_distribution::manifest::OciDescriptor) -> Result<Vec<u8>> {
+        if let Some(bytes) = self.store.read(&layer.digest).await? {
+            return Ok(bytes);
         }
-        DevToolsTab::Network => {
-            ScrollArea::vertical().show(ui, |ui| {
-                ui.heading("История HTTP-запросов:");
-                for log in devtools_state.network_logs.iter().rev() {
-                    ui.group(|ui| {
-                        ui.label(format!("{} {} {}", log.method, log.url, log.status));
-                        ui.label(format!("Длительность: {}", DebugInfo::format_duration(log.duration)));
-                        ui.label(format!("Размер ответа: {}", DebugInfo::format_size(log.html.len())));
-                        ui.label(format!("Скорость передачи: {:.2} KB/s",
-                            if log.duration.as_secs_f64() > 0.0 {
-                                log.html.len() as f64 / log.duration.as_secs_f64() / 1024.0
-                            } else {
-                                0.0
-                            }
-                        ));
-
-                        let mut headers_text = String::new();
-                        for (name, value) in log.headers.iter() {
-                            if let Ok(value_str) = value.to_str() {
-                                headers_text.push_str(&format!("{}: {}\n", name, value_str));
-                            }
-                        }
+        let mut bytes = Vec::new();
+        self.client
+            .auth(reference, &self.auth(reference.resolve_registry())?, oci_distribution::RegistryOperation::Pull)
+            .await?;
+        self.client.pull_blob(reference, layer, &mut bytes).await?;
+        self.store.write(&layer.digest, &bytes).await?;
+        Ok(bytes)
+    }
 
-                        let desired_size = egui::Vec2::new(ui.available_width(), 20.0);
-                        ui.add_sized(
-                            desired_size,
-                            egui::TextEdit::multiline(&mut headers_text)
-                                .font(TextStyle::Monospace),
-                        );
-                    });
-                    ui.add_space(5.0);
+    // Ссылка вида registry/repo:tag или registry/repo@sha256:…;
+    // ссылка по дайджесту при наличии blob-ов в хранилище не требует сети
+    pub async fn pull(&self, reference: &str) -> Result<PulledArtifact> {
+        #[cfg(feature = "wasm")]
+        {
+            use oci_distribution::manifest::OciImageManifest;
+
+            let parsed: oci_distribution::Reference = reference
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Некорректная OCI-ссылка {}: {}", reference, e))?;
+            let cached = match parsed.digest() {
+                Some(digest) => self.store.read(digest).await?.map(|bytes| (bytes, digest.to_string())),
+                None => None,
+            };
+            let (raw, digest) = match cached {
+                Some(cached) => cached,
+                None => {
+                    let auth = self.auth(parsed.resolve_registry())?;
+                    let (raw, digest) = self
+                        .client
+                        .pull_manifest_raw(&parsed, &auth, &[oci_distribution::manifest::OCI_IMAGE_MEDIA_TYPE])
+                        .await?;
+                    if let Some(expected) = parsed.digest() {
+                        verify_digest(expected, &raw)?;
+                    }
+                    verify_digest(&digest, &raw)?;
+                    self.store.write(&digest, &raw).await?;
+                    (raw, digest)
                 }
-            });
+            };
+            let manifest: OciImageManifest = serde_json::from_slice(&raw)?;
+            let wasm_layer = manifest
+                .layers
+                .iter()
+                .find(|layer| WASM_MEDIA_TYPES.contains(&layer.media_type.as_str()))
+                .ok_or_else(|| anyhow::anyhow!("Артефакт {} не содержит WASM-слоя", reference))?;
+            let wasm = self.blob(&parsed, wasm_layer).await?;
+            let layer_of = |media_type: &str| manifest.layers.iter().find(|layer| layer.media_type == media_type).cloned();
+            let (yaml_layer, signature_layer) = (layer_of(MANIFEST_MEDIA_TYPE), layer_of(MANIFEST_SIGNATURE_MEDIA_TYPE));
+            let manifest_yaml = match yaml_layer {
+                Some(layer) => Some(self.blob(&parsed, &layer).await?),
+                None => None,
+            };
+            let manifest_signature = match signature_layer {
+                Some(layer) => Some(self.blob(&parsed, &layer).await?),
+                None => None,
+            };
+            log::info!("Получен OCI-артефакт {} ({}, {} байт)", reference, digest, wasm.len());
+            Ok(PulledArtifact { reference: reference.to_string(), digest, manifest: manifest_yaml, manifest_signature, wasm })
         }
-        DevToolsTab::Debug => {
-            ScrollArea::vertical().show(ui, |ui| {
-                ui.add_space(10.0);
-                ui.label("Производительность:");
-                ui.indent("performance", |ui| {
-                    ui.label(format!("FPS: {:.1}", debug_info.frame_rate));
-                    ui.label(format!(
-                        "Время рендера: {}",
-                        DebugInfo::format_duration(debug_info.render_time)
-                    ));
-                });
-                ui.add_space(10.0);
-                ui.label("DOM и ресурсы:");
-                ui.indent("resources", |ui| {
-                    ui.label(format!("Элементы DOM: {}", debug_info.dom_elements));
-                    ui.label(format!("Память: {}", debug_info.memory_usage));
-                });
-                ui.add_space(10.0);
-                let heavy_result_id = Id::new("heavy_calculator_result");
-                let mut calculator: HeavyCalculator = ui.memory_mut(|mem| {
-                    mem.data.get_temp(heavy_result_id).unwrap_or_default()
-                });
-                if ui.button("Дай синус бра!").clicked() {
-                    calculator.compute(30_000_000);
-                    ui.memory_mut(|mem| {
-                        mem.data.insert_temp(heavy_result_id, calculator.clone());
-                    });
-                    log::info!("Выполнен тест производительности (синус)");
-                }
-                ui.add_space(10.0);
-                if let Some(result_str) = calculator.format_result(DebugInfo::format_duration) {
-                    ui.add_space(10.0);
-                    ui.label(result_str);
-                }
-            });
+        #[cfg(not(feature = "wasm"))]
+        {
+            let _ = &self.registries;
+            Err(anyhow::anyhow!("Загрузка из OCI недоступна без feature wasm: {}", reference))
         }
     }
-    action
 }
 
-// === FILE: ui\font.rs ===
-use anyhow::Result;
-pub struct Font;
-impl Font {
-    pub fn new() -> Result<Self> {
-        println!("Font initialized");
-        Ok(Self)
+// Нужен локальный реестр: docker run -d -p 5000:5000 registry:2, затем
+// YUAIBRO_TEST_REGISTRY=localhost:5000 cargo test --features wasm -- --ignored
+#[cfg(all(test, feature = "wasm"))]
+mod tests {
+    use super::*;
+    use oci_distribution::client::{Config as OciConfig, ImageLayer};
+    use oci_distribution::secrets::RegistryAuth;
+    use std::path::Path;
+
+    fn registry() -> String {
+        std::env::var("YUAIBRO_TEST_REGISTRY").unwrap_or_else(|_| "localhost:5000".to_string())
     }
-}
 
-// === FILE: ui\heavy_calculator.rs ===
-use rayon::prelude::*;
-use std::time::{Duration, Instant};
-use sysinfo::{System, RefreshKind};
-#[derive(Clone, Default)]
-pub struct HeavyCalculator {
-    pub result: Option<(f64, Duration, i64, f32)>, // (значение, время, дельта памяти, дельта CPU)
-}
-impl HeavyCalculator {
-    pub fn compute(&mut self, n: u32) {
-        let rk = RefreshKind::everything();
-        let mut sys = System::new_with_specifics(rk);
-        sys.refresh_specifics(rk);
-        let before_mem = sys.used_memory();
-        let before_cpu = sys.global_cpu_usage();
-        let start = Instant::now();
-        let value = self.heavy(n);
-        let duration = start.elapsed();
-        sys.refresh_specifics(rk);
-        let after_mem = sys.used_memory();
-        let after_cpu = sys.global_cpu_usage();
-        let mem_delta = (after_mem as i64 - before_mem as i64) / 1024;
-        let cpu_delta = after_cpu - before_cpu;
-        self.result = Some((value, duration, mem_delta, cpu_delta));
+    fn puller(host: &str, root: &Path) -> OciPuller {
+        let registry = RegistryConfig { host: host.to_string(), username: None, password: None, password_file: None, insecure: true };
+        let config = Config { registries: vec![registry], ..Default::default() };
+        OciPuller::new(&config, root.to_path_buf()).unwrap()
     }
-    pub fn format_result(&self, format_duration: impl Fn(Duration) -> String) -> Option<String> {
-        self.result.map(|(value, duration, mem_delta, cpu_delta)| {
-            format!(
-                "Результат: {:.4}\nВремя: {}\nПамять (delta): {} KB\nCPU (delta): {:.2}%",
-                value,
-                format_duration(duration),
-                mem_delta,
-                cpu_delta
-            )
-        })
+
+    async fn push(puller: &OciPuller, reference: &str, layers: Vec<ImageLayer>) -> oci_distribution::Reference {
+        let reference: oci_distribution::Reference = reference.parse().unwrap();
+        let config = OciConfig::new(b"{}".to_vec(), "application/vnd.oci.image.config.v1+json".to_string(), None);
+        puller.client.push(&reference, &layers, config, &RegistryAuth::Anonymous, None).await.unwrap();
+        reference
     }
-    pub fn heavy(&self, n: u32) -> f64 {
-        (0..n)
-            .into_par_iter()
-            .map(|i| {
-                let x = i as f64;
-                x.sqrt() * x.sin()
-            })
-            .sum()
+
+    #[tokio::test]
+    #[ignore]
+    async fn pulls_layers_and_serves_digest_references_from_store() {
+        let host = registry();
+        let root = std::env::temp_dir().join(format!("yuaibro-oci-{}", uuid::Uuid::new_v4()));
+        let puller = puller(&host, &root);
+        let wasm = b"\0asm\x01\0\0\0".to_vec();
+        let yaml = b"name: counter\n".to_vec();
+        let signature = vec![7u8; 96];
+        let layers = vec![
+            ImageLayer::new(wasm.clone(), WASM_MEDIA_TYPES[0].to_string(), None),
+            ImageLayer::new(yaml.clone(), MANIFEST_MEDIA_TYPE.to_string(), None),
+            ImageLayer::new(signature.clone(), MANIFEST_SIGNATURE_MEDIA_TYPE.to_string(), None),
+        ];
+        push(&puller, &format!("{}/yuaibro/counter:1.0.0", host), layers).await;
+
+        let pulled = puller.pull(&format!("{}/yuaibro/counter:1.0.0", host)).await.unwrap();
+        assert_eq!(pulled.wasm, wasm);
+        assert_eq!(pulled.manifest.as_deref(), Some(&yaml[..]));
+        assert_eq!(pulled.manifest_signature.as_deref(), Some(&signature[..]));
+        assert!(puller.store().contains(&pulled.digest));
+        assert!(puller.store().contains(&digest_of(&wasm)));
+
+        // Без реестра в конфигурации: всё берётся из хранилища по содержимому
+        let offline = OciPuller::new(&Config::default(), root.clone()).unwrap();
+        let cached = offline.pull(&format!("{}/yuaibro/counter@{}", host, pulled.digest)).await.unwrap();
+        assert_eq!(cached.wasm, wasm);
+
+        // Повреждённый blob удаляется при чтении
+        let wasm_digest = digest_of(&wasm);
+        tokio::fs::write(puller.store().path(&wasm_digest).unwrap(), b"broken").await.unwrap();
+        assert!(puller.store().read(&wasm_digest).await.unwrap().is_none());
+        assert!(!puller.store().contains(&wasm_digest));
+        let _ = std::fs::remove_dir_all(root);
     }
-}
 
-// === FILE: ui\input.rs ===
-use anyhow::Result;
-pub struct Input;
-impl Input {
-    pub fn new() -> Result<Self> {
-        println!("Input initialized");
-        Ok(Self)
+    #[tokio::test]
+    #[ignore]
+    async fn rejects_reference_with_foreign_digest() {
+        let host = registry();
+        let root = std::env::temp_dir().join(format!("yuaibro-oci-{}", uuid::Uuid::new_v4()));
+        let puller = puller(&host, &root);
+        let layers = vec![ImageLayer::new(b"\0asm\x01\0\0\0".to_vec(), WASM_MEDIA_TYPES[0].to_string(), None)];
+        push(&puller, &format!("{}/yuaibro/other:1.0.0", host), layers).await;
+
+        let foreign = digest_of(b"not this manifest");
+        assert!(puller.pull(&format!("{}/yuaibro/other@{}", host, foreign)).await.is_err());
+        assert!(!puller.store().contains(&foreign));
+        let _ = std::fs::remove_dir_all(root);
     }
 }
 
-// === FILE: ui\mod.rs ===
-// === FILE: ui/mod.rs ===
+// === FILE: core\orchestrator.rs ===
+use crate::core::interfaces::{OrchestratorTrait, WasmRuntimeTrait};
+use crate::core::provider::{CapabilityProvider, LinkDefinition};
+use crate::core::runtime::{InstanceId, WasmValue};
+use crate::wasm_api::provider_api::{ActorCall, ProviderReply, ProviderRequest};
+use crate::wasm_api::sandbox::{HostCall, SandboxConfig};
 use anyhow::Result;
-use crate::core::engine::BroEngine;
-use log;
-
-pub mod aichat;
-pub mod devtools;
-pub mod render;
-pub mod settings;
-pub mod toolbar;
-pub mod window;
-pub mod window_manager;
-pub mod wgpu_render;
-pub mod heavy_calculator;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::broadcast;
 
-pub use window::ToolbarPosition;
+// Верхняя граница масштабирования одного актора
+const MAX_ACTOR_INSTANCES: usize = 64;
 
-// --- Точка входа UI-клиента ---
-// Функция, которая запускает UI-клиент, используя инжектированный Engine.
-pub async fn run(engine: std::sync::Arc<BroEngine>) -> Result<()> {
-    use std::sync::Arc;
-    use winit::event_loop::EventLoop;
+#[derive(Clone, Debug)]
+pub enum LatticeEvent {
+    ActorStarted { actor_id: String, module: String },
+    ActorScaled { actor_id: String, instances: usize },
+    ActorStopped { actor_id: String },
+    ProviderStarted { provider_id: String, contract_id: String },
+    ProviderStopped { provider_id: String },
+    LinkPut(LinkDefinition),
+    LinkDeleted(LinkDefinition),
+}
 
-    log::info!("UI: стартуем реальный UI (winit/egui/wgpu)");
+#[derive(Clone, Debug, Default)]
+pub struct LatticeInventory {
+    pub actors: Vec<ActorDescription>,
+    pub providers: Vec<ProviderDescription>,
+    pub links: Vec<LinkDefinition>,
+}
 
-    let config = crate::core::config::Config::load_from_file(
-        crate::core::config::Config::get_config_path(),
-    )?;
+#[derive(Clone, Debug)]
+pub struct ActorDescription {
+    pub id: String,
+    pub module: String,
+    pub instances: usize,
+}
 
-    // Создаем EventLoop
-    let event_loop = EventLoop::new()?;
+#[derive(Clone, Debug)]
+pub struct ProviderDescription {
+    pub id: String,
+    pub contract_id: String,
+    pub running: bool,
+}
 
-    let ctx = crate::core::event_loop::EventLoopContext {
-        engine,
-        config,
-    };
+struct ActorEntry {
+    module: String,
+    instances: Vec<InstanceId>,
+    next: usize,
+}
 
-    crate::core::event_loop::run(event_loop, ctx)?;
+type RemovedLink = (LinkDefinition, Option<Arc<dyn CapabilityProvider>>);
 
-    Ok(())
+#[derive(Default)]
+struct Lattice {
+    actors: HashMap<String, ActorEntry>,
+    running: HashMap<String, Arc<dyn CapabilityProvider>>,
+    links: Vec<LinkDefinition>,
 }
 
-// === FILE: ui\render.rs ===
-use crate::dom::hydrate::hydrate_and_render;
-use crate::dom::parser::{parse_and_process, ParsedNode, FrameworkType};
-use crate::dom::tree::DomRenderer;
-use egui::Ui;
-use anyhow::Result;
-
-pub struct HtmlRenderer {
-    pub dom_renderer: DomRenderer,
-    pub scale_factor: f32,
-    last_html: String, // Кэш последнего HTML для проверки изменений
-    cached_node: Option<ParsedNode>, // Кэш распарсенного DOM-дерева
-    needs_repaint: bool, // Флаг необходимости перерисовки
+impl Lattice {
+    // Удаляет подходящие связи; провайдер каждой, если он запущен, уведомляется после снятия блокировки
+    fn take_links(&mut self, matches: impl Fn(&LinkDefinition) -> bool) -> Vec<RemovedLink> {
+        let (removed, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.links).into_iter().partition(|link| matches(link));
+        self.links = kept;
+        removed
+            .into_iter()
+            .map(|link| {
+                let provider = self.running.get(&link.provider_id).cloned();
+                (link, provider)
+            })
+            .collect()
+    }
 }
 
-impl HtmlRenderer {
-    pub fn new(scale_factor: f32) -> Self {
-        log::info!("Создание HtmlRenderer с масштабом: {}", scale_factor);
-        Self {
-            dom_renderer: DomRenderer::new(),
-            scale_factor,
-            last_html: String::new(),
-            cached_node: None,
-            needs_repaint: true, // Изначально требуется рендер
-        }
+// Локальная решётка собирается с feature orchestration
+fn ensure_enabled() -> Result<()> {
+    if cfg!(feature = "orchestration") {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Локальная решётка отключена: соберите с feature orchestration"))
     }
+}
 
-    pub fn set_scale_factor(&mut self, scale: f32) {
-        if (self.scale_factor - scale).abs() > 0.01 {
-            log::info!("Изменение масштаба HtmlRenderer: {} -> {}", self.scale_factor, scale);
-            self.scale_factor = scale;
-            self.needs_repaint = true; // Масштаб изменился, нужна перерисовка
-        }
+// Локальная решётка: акторы — экземпляры WASM runtime, провайдеры — объекты в процессе; NATS не нужен.
+// Состояние защищено синхронной блокировкой, которая не удерживается через await: актор при
+// инстанцировании и провайдер в put_link могут сами обращаться к решётке. Изменения жизненного
+// цикла выполняются по одному под lifecycle
+pub struct Orchestrator {
+    runtime: Arc<dyn WasmRuntimeTrait + Send + Sync>,
+    available: Mutex<HashMap<String, Arc<dyn CapabilityProvider>>>,
+    lattice: Arc<Mutex<Lattice>>,
+    lifecycle: tokio::sync::Mutex<()>,
+    events: broadcast::Sender<LatticeEvent>,
+}
+
+impl Orchestrator {
+    pub fn new(runtime: Arc<dyn WasmRuntimeTrait + Send + Sync>) -> Result<Self> {
+        let (events, _) = broadcast::channel(256);
+        log::info!("Оркестратор инициализирован");
+        Ok(Self {
+            runtime,
+            available: Mutex::new(HashMap::new()),
+            lattice: Arc::new(Mutex::new(Lattice::default())),
+            lifecycle: tokio::sync::Mutex::new(()),
+            events,
+        })
     }
 
-    pub fn needs_repaint(&self) -> bool {
-        self.needs_repaint || self.dom_renderer.needs_repaint()
+    fn emit(&self, event: LatticeEvent) {
+        // Подписчиков может не быть
+        let _ = self.events.send(event);
     }
 
-    pub fn clear_repaint_flag(&mut self) {
-        log::info!("Сброс флага перерисовки в HtmlRenderer");
-        self.needs_repaint = false;
-        self.dom_renderer.clear_repaint_flag();
+    // Импорт cosmonaut.call актора: запрос уходит провайдеру по связи актора
+    fn actor_host_call(&self, actor_id: &str) -> HostCall {
+        let lattice = self.lattice.clone();
+        let actor_id = actor_id.to_string();
+        let handle = tokio::runtime::Handle::current();
+        Arc::new(move |request: Vec<u8>| {
+            let reply: ProviderReply = match serde_json::from_slice::<ActorCall>(&request) {
+                Ok(call) => handle.block_on(route_call(&lattice, &actor_id, call)).into(),
+                Err(e) => ProviderReply::Error { message: format!("Некорректный вызов: {}", e) },
+            };
+            serde_json::to_vec(&reply).unwrap_or_default()
+        })
     }
 
+    // Все экземпляры или ни одного: при ошибке уже созданные удаляются
+    async fn spawn_instances(&self, actor_id: &str, module: &str, count: usize) -> Result<Vec<InstanceId>> {
+        let mut instances = Vec::with_capacity(count);
+        for _ in 0..count {
+            let config = SandboxConfig {
+                name: actor_id.to_string(),
+                host_call: Some(self.actor_host_call(actor_id)),
+                ..SandboxConfig::default()
+            };
+            match self.runtime.instantiate(module, config).await {
+                Ok(instance) => instances.push(instance),
+                Err(e) => {
+                    self.drop_instances(instances).await;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(instances)
+    }
 
+    async fn drop_instances(&self, instances: Vec<InstanceId>) {
+        for instance in instances {
+            self.runtime.drop_instance(instance).await;
+        }
+    }
 
+    async fn delete_links(&self, removed: Vec<RemovedLink>) {
+        for (link, provider) in removed {
+            if let Some(provider) = provider {
+                if let Err(e) = provider.delete_link(&link).await {
+                    log::warn!("Провайдер {} не удалил связь с {}: {}", link.provider_id, link.actor_id, e);
+                }
+            }
+            self.emit(LatticeEvent::LinkDeleted(link));
+        }
+    }
+}
 
+// Провайдер выбирается по связи актора, поэтому актор может обращаться только к тому, с чем связан
+async fn route_call(lattice: &Mutex<Lattice>, actor_id: &str, call: ActorCall) -> Result<Vec<u8>> {
+    let provider = {
+        let lattice = lattice.lock();
+        let link = lattice
+            .links
+            .iter()
+            .find(|link| link.same_slot(actor_id, &call.contract_id, &call.link_name))
+            .ok_or_else(|| anyhow::anyhow!("Актор {} не связан с {} ({})", actor_id, call.contract_id, call.link_name))?;
+        lattice
+            .running
+            .get(&link.provider_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Провайдер {} не запущен", link.provider_id))?
+    };
+    provider
+        .handle(ProviderRequest {
+            actor_id: actor_id.to_string(),
+            link_name: call.link_name,
+            operation: call.operation,
+            payload: call.payload,
+        })
+        .await
+}
 
+#[async_trait]
+impl OrchestratorTrait for Orchestrator {
+    fn register_provider(&self, provider: Arc<dyn CapabilityProvider>) {
+        log::info!("Доступен провайдер {} ({})", provider.id(), provider.contract_id());
+        self.available.lock().insert(provider.id().to_string(), provider);
+    }
 
+    fn subscribe(&self) -> broadcast::Receiver<LatticeEvent> {
+        self.events.subscribe()
+    }
 
+    // Повторный деплой заменяет модуль, сохраняя число экземпляров. Прежние экземпляры
+    // обслуживают вызовы, пока не созданы новые; при ошибке актор остаётся как был
+    async fn deploy_actor(&self, actor_id: &str, wasm_bytes: &[u8], signature: &[u8]) -> Result<()> {
+        ensure_enabled()?;
+        log::info!("Деплой актора: {}", actor_id);
+        let module = self.runtime.load_module(wasm_bytes, signature).await?;
+        let _lifecycle = self.lifecycle.lock().await;
+        let count = self.lattice.lock().actors.get(actor_id).map_or(1, |entry| entry.instances.len());
+        let instances = self.spawn_instances(actor_id, &module, count).await?;
+        let entry = ActorEntry { module: module.clone(), instances, next: 0 };
+        let replaced = self.lattice.lock().actors.insert(actor_id.to_string(), entry);
+        if let Some(replaced) = replaced {
+            self.drop_instances(replaced.instances).await;
+        }
+        self.emit(LatticeEvent::ActorStarted { actor_id: actor_id.to_string(), module });
+        Ok(())
+    }
 
-// === FILE: ui/render.rs (Исправленная функция render_html_ui) ===
+    async fn scale_actor(&self, actor_id: &str, instances: usize) -> Result<()> {
+        if instances == 0 || instances > MAX_ACTOR_INSTANCES {
+            return Err(anyhow::anyhow!("Число экземпляров должно быть от 1 до {}", MAX_ACTOR_INSTANCES));
+        }
+        let _lifecycle = self.lifecycle.lock().await;
+        let (module, current) = {
+            let lattice = self.lattice.lock();
+            let entry = lattice
+                .actors
+                .get(actor_id)
+                .ok_or_else(|| anyhow::anyhow!("Актор {} не развёрнут", actor_id))?;
+            (entry.module.clone(), entry.instances.len())
+        };
+        // Под lifecycle актор не может исчезнуть между чтением и записью
+        if instances > current {
+            let added = self.spawn_instances(actor_id, &module, instances - current).await?;
+            if let Some(entry) = self.lattice.lock().actors.get_mut(actor_id) {
+                entry.instances.extend(added);
+                entry.next = 0;
+            }
+        } else {
+            let removed = match self.lattice.lock().actors.get_mut(actor_id) {
+                Some(entry) => {
+                    entry.next = 0;
+                    entry.instances.split_off(instances)
+                }
+                None => Vec::new(),
+            };
+            self.drop_instances(removed).await;
+        }
+        log::info!("Актор {} масштабирован до {} экземпляров", actor_id, instances);
+        self.emit(LatticeEvent::ActorScaled { actor_id: actor_id.to_string(), instances });
+        Ok(())
+    }
 
-pub async fn render_html_ui(&mut self, ui: &mut Ui, html: &str) -> Result<Option<FrameworkType>> {
-    let node: ParsedNode;
-    let mut framework_to_return: Option<FrameworkType> = None;
-    let mut should_hydrate = false;
-    
-    // Объявляем detected_framework заранее, чтобы избежать ошибки E0425.
-    let mut detected_framework: Option<FrameworkType> = None; // 👈 ИСПРАВЛЕНИЕ 1: Добавляем объявление
+    async fn stop_actor(&self, actor_id: &str) -> Result<()> {
+        let _lifecycle = self.lifecycle.lock().await;
+        let (entry, removed) = {
+            let mut lattice = self.lattice.lock();
+            let entry = lattice
+                .actors
+                .remove(actor_id)
+                .ok_or_else(|| anyhow::anyhow!("Актор {} не развёрнут", actor_id))?;
+            (entry, lattice.take_links(|link| link.actor_id == actor_id))
+        };
+        self.drop_instances(entry.instances).await;
+        self.delete_links(removed).await;
+        log::info!("Актор {} остановлен", actor_id);
+        self.emit(LatticeEvent::ActorStopped { actor_id: actor_id.to_string() });
+        Ok(())
+    }
 
-    // 1. Определение, нужно ли парсить и гидрировать
-    let was_repaint_needed = self.needs_repaint; // Сохраняем начальное состояние needs_repaint
+    // Вызовы распределяются между экземплярами по кругу
+    async fn invoke_actor(&self, actor_id: &str, export: &str, args: Vec<WasmValue>) -> Result<Vec<WasmValue>> {
+        let instance = {
+            let mut lattice = self.lattice.lock();
+            let entry = lattice
+                .actors
+                .get_mut(actor_id)
+                .ok_or_else(|| anyhow::anyhow!("Актор {} не развёрнут", actor_id))?;
+            let instance = entry.instances[entry.next % entry.instances.len()];
+            entry.next = entry.next.wrapping_add(1);
+            instance
+        };
+        self.runtime.call(instance, export, args).await
+    }
 
-    if self.last_html != html {
-        // HTML ИЗМЕНИЛСЯ -> ПАРСИНГ и ПОЛНЫЙ РЕНДЕР
-        log::info!("HTML изменился, выполняется парсинг: {} -> {} (длина: {})",
-                     self.last_html.len(), html.len(), html.len());
-        
-        let (new_node, framework) = parse_and_process(html)?; // parse_and_process возвращает фреймворк
-        node = new_node;
-        self.last_html = html.to_string();
-        self.cached_node = Some(node.clone());
-        self.needs_repaint = true;
-        should_hydrate = true;
-        
-        detected_framework = framework; // 👈 ИСПРАВЛЕНИЕ 2: Присваиваем значение
-        
-        // Удаляем: framework_to_return = detected_framework; (Это произойдет ниже в конце функции)
+    async fn start_provider(&self, provider_id: &str, config: BTreeMap<String, String>) -> Result<()> {
+        ensure_enabled()?;
+        let provider = self
+            .available
+            .lock()
+            .get(provider_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Провайдер {} не зарегистрирован", provider_id))?;
+        let _lifecycle = self.lifecycle.lock().await;
+        if self.lattice.lock().running.contains_key(provider_id) {
+            return Err(anyhow::anyhow!("Провайдер {} уже запущен", provider_id));
+        }
+        provider.start(&config).await?;
+        // Связи, объявленные до запуска, доставляются сразу
+        let links: Vec<_> = self.lattice.lock().links.iter().filter(|link| link.provider_id == provider_id).cloned().collect();
+        for link in &links {
+            if let Err(e) = provider.put_link(link).await {
+                if let Err(shutdown) = provider.shutdown().await {
+                    log::warn!("Провайдер {} не остановился после ошибки связи: {}", provider_id, shutdown);
+                }
+                return Err(e);
+            }
+        }
+        log::info!("Запуск провайдера: {}", provider_id);
+        self.emit(LatticeEvent::ProviderStarted {
+            provider_id: provider_id.to_string(),
+            contract_id: provider.contract_id().to_string(),
+        });
+        self.lattice.lock().running.insert(provider_id.to_string(), provider);
+        Ok(())
+    }
 
-    } else if let Some(cached) = &self.cached_node {
-        // HTML НЕ ИЗМЕНИЛСЯ -> ИСПОЛЬЗУЕМ КЭШ
-        log::info!("HTML не изменился, используется кэшированное дерево");
-        node = cached.clone();
-        
-        // Решаем, нужна ли гидрация/полный рендер по другим причинам
-        if was_repaint_needed {
-             // Флаг поднят (клик, масштаб и т.д.) -> НУЖЕН ПОЛНЫЙ РЕНДЕР
-             log::info!("Требуется полный рендер из-за флага needs_repaint.");
-             should_hydrate = true;
+    async fn stop_provider(&self, provider_id: &str) -> Result<()> {
+        let _lifecycle = self.lifecycle.lock().await;
+        // Связи остаются в решётке и будут доставлены при следующем запуске
+        let (provider, links) = {
+            let mut lattice = self.lattice.lock();
+            let provider = lattice
+                .running
+                .remove(provider_id)
+                .ok_or_else(|| anyhow::anyhow!("Провайдер {} не запущен", provider_id))?;
+            let links: Vec<_> = lattice.links.iter().filter(|link| link.provider_id == provider_id).cloned().collect();
+            (provider, links)
+        };
+        for link in &links {
+            if let Err(e) = provider.delete_link(link).await {
+                log::warn!("Провайдер {} не удалил связь с {}: {}", provider_id, link.actor_id, e);
+            }
         }
-    } else {
-        // Кэш пуст при неизменном HTML (случай первого запуска) -> ПАРСИНГ и ПОЛНЫЙ РЕНДЕР
-        log::info!("Кэш пуст при неизменном HTML, выполняется парсинг HTML");
-        
-        let (new_node, framework) = parse_and_process(html)?; // parse_and_process возвращает фреймворк
-        node = new_node;
-        self.cached_node = Some(node.clone());
-        self.needs_repaint = true;
-        should_hydrate = true;
-        
-        detected_framework = framework; // 👈 ИСПРАВЛЕНИЕ 3: Присваиваем значение
+        provider.shutdown().await?;
+        log::info!("Провайдер {} остановлен", provider_id);
+        self.emit(LatticeEvent::ProviderStopped { provider_id: provider_id.to_string() });
+        Ok(())
     }
 
-    // 2. Выполнение Гидрации/Рендера
-    if should_hydrate {
-        // ВЫЗЫВАЕМ ПОЛНЫЙ ЦИКЛ: ГИДРАЦИЯ + РЕНДЕР
-        log::info!("Запуск (условной) гидрации и рендера для HTML (длина: {})", html.len());
-        
-        // Передаем detected_framework в framework_to_return, если он был обнаружен при парсинге.
-        let framework = hydrate_and_render(ui, &mut self.dom_renderer, node).await?;
-        
-        if framework.is_some() {
-            framework_to_return = framework;
-        } else if detected_framework.is_some() { // 👈 ИСПРАВЛЕНИЕ 4: Если framework_to_return не был установлен
-             framework_to_return = detected_framework; 
+    async fn link(&self, link: LinkDefinition) -> Result<()> {
+        let contract_id = self
+            .available
+            .lock()
+            .get(&link.provider_id)
+            .map(|provider| provider.contract_id().to_string())
+            .ok_or_else(|| anyhow::anyhow!("Провайдер {} не зарегистрирован", link.provider_id))?;
+        if contract_id != link.contract_id {
+            return Err(anyhow::anyhow!(
+                "Провайдер {} реализует {}, а не {}",
+                link.provider_id,
+                contract_id,
+                link.contract_id
+            ));
         }
+        let _lifecycle = self.lifecycle.lock().await;
+        let (removed, provider) = {
+            let mut lattice = self.lattice.lock();
+            if !lattice.actors.contains_key(&link.actor_id) {
+                return Err(anyhow::anyhow!("Актор {} не развёрнут", link.actor_id));
+            }
+            let removed = lattice.take_links(|existing| existing.same_slot(&link.actor_id, &link.contract_id, &link.link_name));
+            (removed, lattice.running.get(&link.provider_id).cloned())
+        };
+        self.delete_links(removed).await;
+        if let Some(provider) = provider {
+            provider.put_link(&link).await?;
+        }
+        self.lattice.lock().links.push(link.clone());
+        self.emit(LatticeEvent::LinkPut(link));
+        Ok(())
+    }
 
-        self.needs_repaint = false; // Сброс флага
-        
-    } else {
-        // ВЫЗЫВАЕМ ТОЛЬКО РЕНДЕР (если не нужна гидрация)
-        log::info!("HTML и флаги чисты. Только рендер кэшированного DOM.");
-        self.dom_renderer.render_node(ui, &node).await;
-        // Здесь мы не сбрасываем needs_repaint, т.к. мы его не использовали.
-        // Если он был true, то should_hydrate был бы true.
+    async fn unlink(&self, actor_id: &str, contract_id: &str, link_name: &str) -> Result<()> {
+        let _lifecycle = self.lifecycle.lock().await;
+        let removed = self.lattice.lock().take_links(|link| link.same_slot(actor_id, contract_id, link_name));
+        if removed.is_empty() {
+            return Err(anyhow::anyhow!("Связь {} {} ({}) не найдена", actor_id, contract_id, link_name));
+        }
+        self.delete_links(removed).await;
+        Ok(())
     }
-    
-    // 3. Управление флагами для следующего кадра
-    if ui.ctx().has_requested_repaint() || self.dom_renderer.needs_repaint() {
-        
-		//////////////////////////////////////////////////////////////////
-		self.needs_repaint = false; // true - если имеем дело с хтмл css анимацией!		
-        //log::info!("HtmlRenderer запросил перерисовку после рендера (needs_repaint=true для следующего кадра).");
-		//////////////////////////////////////////////////////////////////
-		
-		
-    } else {
-        self.needs_repaint = false;
+
+    async fn call_provider(&self, actor_id: &str, call: ActorCall) -> Result<Vec<u8>> {
+        route_call(&self.lattice, actor_id, call).await
     }
 
+    async fn inventory(&self) -> LatticeInventory {
+        let lattice = self.lattice.lock();
+        let mut actors: Vec<_> = lattice
+            .actors
+            .iter()
+            .map(|(id, entry)| ActorDescription { id: id.clone(), module: entry.module.clone(), instances: entry.instances.len() })
+            .collect();
+        actors.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut providers: Vec<_> = self
+            .available
+            .lock()
+            .values()
+            .map(|provider| ProviderDescription {
+                id: provider.id().to_string(),
+                contract_id: provider.contract_id().to_string(),
+                running: lattice.running.contains_key(provider.id()),
+            })
+            .collect();
+        providers.sort_by(|a, b| a.id.cmp(&b.id));
+        LatticeInventory { actors, providers, links: lattice.links.clone() }
+    }
 
-    Ok(framework_to_return)
+    // Сначала акторы (с их связями), затем провайдеры
+    async fn shutdown(&self) {
+        let actor_ids: Vec<_> = self.lattice.lock().actors.keys().cloned().collect();
+        for actor_id in actor_ids {
+            if let Err(e) = self.stop_actor(&actor_id).await {
+                log::warn!("Не удалось остановить актор {}: {}", actor_id, e);
+            }
+        }
+        let provider_ids: Vec<_> = self.lattice.lock().running.keys().cloned().collect();
+        for provider_id in provider_ids {
+            if let Err(e) = self.stop_provider(&provider_id).await {
+                log::warn!("Не удалось остановить провайдер {}: {}", provider_id, e);
+            }
+        }
+        log::info!("Решётка остановлена");
+    }
 }
 
+#[cfg(all(test, feature = "orchestration"))]
+mod tests {
+    use super::*;
+    use crate::core::config::Config;
+    use crate::core::interfaces::SecurityManagerTrait;
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    // Runtime без WASM: экземпляры — числа, вызов возвращает номер экземпляра
+    #[derive(Default)]
+    struct FakeRuntime {
+        next: AtomicU64,
+        live: Mutex<HashSet<u64>>,
+        // Инстанцирование не удаётся, когда живых экземпляров столько
+        limit: Option<usize>,
+        // Как стартовая функция актора, обращающаяся к провайдеру через cosmonaut.call
+        call_on_start: AtomicBool,
+    }
+
+    #[async_trait]
+    impl WasmRuntimeTrait for FakeRuntime {
+        async fn new(_config: &Config, _security: Arc<dyn SecurityManagerTrait + Send + Sync>) -> Result<Self> {
+            Ok(Self::default())
+        }
+
+        async fn load_module(&self, wasm_bytes: &[u8], _signature: &[u8]) -> Result<String> {
+            Ok(format!("module-{}", wasm_bytes.len()))
+        }
+
+        async fn instantiate(&self, _module: &str, sandbox: SandboxConfig) -> Result<InstanceId> {
+            if self.limit.is_some_and(|limit| self.live.lock().len() >= limit) {
+                return Err(anyhow::anyhow!("Недостаточно памяти"));
+            }
+            if self.call_on_start.load(Ordering::Relaxed) {
+                let host_call = sandbox.host_call.expect("актор получает cosmonaut.call");
+                let call = ActorCall {
+                    contract_id: "test:kv".to_string(),
+                    link_name: "default".to_string(),
+                    operation: "init".to_string(),
+                    payload: Vec::new(),
+                };
+                let request = serde_json::to_vec(&call)?;
+                tokio::task::spawn_blocking(move || host_call(request)).await?;
+            }
+            let id = self.next.fetch_add(1, Ordering::Relaxed);
+            self.live.lock().insert(id);
+            Ok(InstanceId(id))
+        }
 
+        async fn call(&self, instance: InstanceId, _export: &str, _args: Vec<WasmValue>) -> Result<Vec<WasmValue>> {
+            Ok(vec![WasmValue::I64(instance.0 as i64)])
+        }
 
+        async fn call_bytes(&self, _instance: InstanceId, _export: &str, input: Vec<u8>) -> Result<Vec<u8>> {
+            Ok(input)
+        }
 
+        async fn drop_instance(&self, instance: InstanceId) {
+            self.live.lock().remove(&instance.0);
+        }
+    }
 
+    #[derive(Default)]
+    struct FakeProvider {
+        links: Mutex<Vec<String>>,
+        handled: AtomicUsize,
+    }
 
+    #[async_trait]
+    impl CapabilityProvider for FakeProvider {
+        fn id(&self) -> &str {
+            "kv"
+        }
 
+        fn contract_id(&self) -> &str {
+            "test:kv"
+        }
 
+        async fn put_link(&self, link: &LinkDefinition) -> Result<()> {
+            self.links.lock().push(link.actor_id.clone());
+            Ok(())
+        }
 
+        async fn delete_link(&self, link: &LinkDefinition) -> Result<()> {
+            self.links.lock().retain(|actor| *actor != link.actor_id);
+            Ok(())
+        }
 
+        async fn handle(&self, request: ProviderRequest) -> Result<Vec<u8>> {
+            self.handled.fetch_add(1, Ordering::Relaxed);
+            Ok(format!("{}:{}", request.actor_id, request.operation).into_bytes())
+        }
+    }
 
+    fn lattice(runtime: FakeRuntime) -> (Orchestrator, Arc<FakeRuntime>, Arc<FakeProvider>) {
+        let runtime = Arc::new(runtime);
+        let orchestrator = Orchestrator::new(runtime.clone()).unwrap();
+        let provider = Arc::new(FakeProvider::default());
+        orchestrator.register_provider(provider.clone());
+        (orchestrator, runtime, provider)
+    }
 
-    pub fn get_last_link_click(&mut self) -> Option<String> {
-        if let Some(link) = self.dom_renderer.clicked_links.pop_front() {
-            log::info!("Обнаружен клик по ссылке: {}", link);
-            self.needs_repaint = true; // Клик требует перерисовки
-            Some(link)
-        } else {
-            None
+    fn kv_link(actor_id: &str) -> LinkDefinition {
+        LinkDefinition {
+            actor_id: actor_id.to_string(),
+            provider_id: "kv".to_string(),
+            contract_id: "test:kv".to_string(),
+            link_name: "default".to_string(),
+            values: BTreeMap::new(),
         }
     }
 
-    pub fn get_last_button_click(&mut self) -> Option<String> {
-        if let Some(button) = self.dom_renderer.clicked_buttons.pop_front() {
-            log::info!("Обнаружен клик по кнопке: {}", button);
-            self.needs_repaint = true; // Клик требует перерисовки
-            Some(button)
-        } else {
-            None
+    fn kv_call(operation: &str) -> ActorCall {
+        ActorCall {
+            contract_id: "test:kv".to_string(),
+            link_name: "default".to_string(),
+            operation: operation.to_string(),
+            payload: Vec::new(),
         }
     }
 
-    pub fn process_image_responses(&mut self, ui: &mut Ui) {
-        let had_images = !self.dom_renderer.clicked_links.is_empty() || !self.dom_renderer.clicked_buttons.is_empty();
-        self.dom_renderer.process_image_responses(ui);
-        if had_images && self.dom_renderer.needs_repaint() {
-            log::info!("DomRenderer запросил перерисовку из-за обработки изображений");
-            self.needs_repaint = true;
-            ui.ctx().request_repaint();
+    #[tokio::test]
+    async fn scaled_actor_serves_calls_round_robin_and_stops_cleanly() {
+        let (orchestrator, runtime, _) = lattice(FakeRuntime::default());
+        orchestrator.deploy_actor("echo", b"wasm", b"sig").await.unwrap();
+        orchestrator.scale_actor("echo", 3).await.unwrap();
+        let mut seen = HashSet::new();
+        for _ in 0..3 {
+            seen.insert(format!("{:?}", orchestrator.invoke_actor("echo", "run", Vec::new()).await.unwrap()));
         }
+        assert_eq!(seen.len(), 3);
+        orchestrator.scale_actor("echo", 1).await.unwrap();
+        assert_eq!(runtime.live.lock().len(), 1);
+        orchestrator.stop_actor("echo").await.unwrap();
+        assert!(runtime.live.lock().is_empty());
+        assert!(orchestrator.inventory().await.actors.is_empty());
     }
 
-    pub fn set_egui_context(&mut self, ctx: egui::Context) {
-        log::info!("Установка контекста egui для HtmlRenderer");
-        self.dom_renderer.set_egui_context(ctx);
-        self.needs_repaint = true; // Изменение контекста требует перерисовки
+    #[tokio::test]
+    async fn failed_scale_and_redeploy_keep_running_instances() {
+        let (orchestrator, runtime, _) = lattice(FakeRuntime { limit: Some(2), ..Default::default() });
+        orchestrator.deploy_actor("echo", b"wasm", b"sig").await.unwrap();
+        assert!(orchestrator.scale_actor("echo", 4).await.is_err());
+        assert!(orchestrator.deploy_actor("echo", b"wasm-v2", b"sig").await.is_ok());
+        orchestrator.scale_actor("echo", 2).await.unwrap();
+        assert!(orchestrator.deploy_actor("echo", b"wasm-third", b"sig").await.is_err());
+        let inventory = orchestrator.inventory().await;
+        assert_eq!(inventory.actors.len(), 1);
+        assert_eq!(inventory.actors[0].module, "module-7");
+        assert_eq!(inventory.actors[0].instances, 2);
+        assert_eq!(runtime.live.lock().len(), 2);
+        assert!(orchestrator.invoke_actor("echo", "run", Vec::new()).await.is_ok());
     }
-}
 
+    #[tokio::test]
+    async fn links_route_calls_to_running_provider() {
+        let (orchestrator, _, provider) = lattice(FakeRuntime::default());
+        orchestrator.deploy_actor("counter", b"wasm", b"sig").await.unwrap();
+        orchestrator.link(kv_link("counter")).await.unwrap();
+        assert!(orchestrator.call_provider("counter", kv_call("get")).await.is_err());
+        orchestrator.start_provider("kv", BTreeMap::new()).await.unwrap();
+        assert_eq!(*provider.links.lock(), vec!["counter".to_string()]);
+        let reply = orchestrator.call_provider("counter", kv_call("get")).await.unwrap();
+        assert_eq!(reply, b"counter:get");
+        assert!(orchestrator.call_provider("other", kv_call("get")).await.is_err());
+        orchestrator.unlink("counter", "test:kv", "default").await.unwrap();
+        assert!(provider.links.lock().is_empty());
+        assert!(orchestrator.call_provider("counter", kv_call("get")).await.is_err());
+        orchestrator.shutdown().await;
+        assert!(!orchestrator.inventory().await.providers[0].running);
+    }
 
-// === FILE: ui\settings.rs ===
-use egui::{Context as EguiContext, SidePanel, ScrollArea, ComboBox, Ui};
-use crate::core::config::Config;
-use crate::ui::devtools::PanelAction; // Импорт из devtools
-pub fn render_settings(
-    ctx: &EguiContext,
-    config: &mut Config,
-    save_config: &mut bool,
-) {
-    SidePanel::right("settings_panel")
-        .resizable(true)
-        .default_width(400.0)
-        .min_width(200.0)
-        .show(ctx, |ui| {
-            render_settings_ui(ui, config, save_config, false)
-        });
+    // Раньше деплой держал блокировку решётки во время инстанцирования, и обращение актора
+    // к провайдеру из стартовой функции зависало навсегда
+    #[tokio::test(flavor = "multi_thread")]
+    async fn actor_can_call_provider_while_instantiating() {
+        let (orchestrator, runtime, provider) = lattice(FakeRuntime::default());
+        orchestrator.start_provider("kv", BTreeMap::new()).await.unwrap();
+        orchestrator.deploy_actor("counter", b"wasm", b"sig").await.unwrap();
+        orchestrator.link(kv_link("counter")).await.unwrap();
+        runtime.call_on_start.store(true, Ordering::Relaxed);
+        tokio::time::timeout(Duration::from_secs(5), orchestrator.deploy_actor("counter", b"wasm", b"sig"))
+            .await
+            .expect("деплой не должен зависать")
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(5), orchestrator.scale_actor("counter", 2))
+            .await
+            .expect("масштабирование не должно зависать")
+            .unwrap();
+        assert_eq!(provider.handled.load(Ordering::Relaxed), 2);
+    }
+}
+
+// === FILE: core\origin.rs ===
+use std::fmt;
+use url::Url;
+
+// Источник документа: кортеж (схема, хост, порт) либо непрозрачный источник
+// для data:, file: и прочих URL без сетевого хоста
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Origin {
+    Tuple { scheme: String, host: String, port: u16 },
+    Opaque(uuid::Uuid),
+}
+
+impl Origin {
+    pub fn from_url(url: &Url) -> Self {
+        match url.origin() {
+            url::Origin::Tuple(scheme, host, port) => Self::Tuple {
+                scheme,
+                host: host.to_string(),
+                port,
+            },
+            url::Origin::Opaque(_) => Self::Opaque(uuid::Uuid::new_v4()),
+        }
+    }
+
+    pub fn parse(url: &str) -> Self {
+        match Url::parse(url) {
+            Ok(url) => Self::from_url(&url),
+            Err(_) => Self::Opaque(uuid::Uuid::new_v4()),
+        }
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        matches!(self, Self::Opaque(_))
+    }
+
+    // Непрозрачный источник совпадает только сам с собой
+    pub fn same_origin(&self, other: &Origin) -> bool {
+        self == other
+    }
+
+    pub fn is_same_origin_url(&self, url: &Url) -> bool {
+        !self.is_opaque() && *self == Self::from_url(url)
+    }
+
+    // Сериализация для заголовка Origin: порт по умолчанию опускается
+    pub fn ascii_serialization(&self) -> String {
+        match self {
+            Self::Tuple { scheme, host, port } => {
+                if default_port(scheme) == Some(*port) {
+                    format!("{}://{}", scheme, host)
+                } else {
+                    format!("{}://{}:{}", scheme, host, port)
+                }
+            }
+            Self::Opaque(_) => "null".to_string(),
+        }
+    }
+
+    // Ключ раздела хранилища; у непрозрачного источника собственного раздела нет
+    pub fn partition_key(&self) -> Option<String> {
+        match self {
+            Self::Tuple { scheme, host, port } => Some(format!("{}://{}:{}", scheme, host, port)),
+            Self::Opaque(_) => None,
+        }
+    }
+
+    // Безопасный контекст: https, wss и локальный хост
+    pub fn is_potentially_trustworthy(&self) -> bool {
+        match self {
+            Self::Tuple { scheme, host, .. } => {
+                matches!(scheme.as_str(), "https" | "wss")
+                    || host == "localhost"
+                    || host.ends_with(".localhost")
+                    || host == "127.0.0.1"
+                    || host == "[::1]"
+            }
+            Self::Opaque(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.ascii_serialization())
+    }
+}
+
+fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        "ftp" => Some(21),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn same_origin_compares_scheme_host_and_port() {
+        let origin = Origin::parse("https://example.com/page");
+        assert!(origin.is_same_origin_url(&url("https://example.com:443/other?q=1")));
+        assert!(!origin.is_same_origin_url(&url("http://example.com/")));
+        assert!(!origin.is_same_origin_url(&url("https://sub.example.com/")));
+        assert!(!origin.is_same_origin_url(&url("https://example.com:8443/")));
+    }
+
+    #[test]
+    fn opaque_origins_match_nothing() {
+        let origin = Origin::parse("data:text/html,hi");
+        assert!(origin.is_opaque());
+        assert!(!origin.is_same_origin_url(&url("data:text/html,hi")));
+        assert!(!origin.same_origin(&Origin::parse("data:text/html,hi")));
+        assert!(origin.same_origin(&origin.clone()));
+        assert!(Origin::parse("file:///tmp/a.html").is_opaque());
+        assert!(Origin::parse("not a url").is_opaque());
+        assert_eq!(origin.ascii_serialization(), "null");
+        assert_eq!(origin.partition_key(), None);
+    }
+
+    #[test]
+    fn serialization_omits_default_port() {
+        assert_eq!(Origin::parse("https://example.com:443/").ascii_serialization(), "https://example.com");
+        assert_eq!(Origin::parse("http://example.com:8080/").ascii_serialization(), "http://example.com:8080");
+        assert_eq!(Origin::parse("https://example.com/").partition_key().as_deref(), Some("https://example.com:443"));
+    }
+
+    #[test]
+    fn trustworthy_origins() {
+        assert!(Origin::parse("https://example.com/").is_potentially_trustworthy());
+        assert!(Origin::parse("http://localhost:3000/").is_potentially_trustworthy());
+        assert!(Origin::parse("http://app.localhost/").is_potentially_trustworthy());
+        assert!(Origin::parse("http://127.0.0.1/").is_potentially_trustworthy());
+        assert!(!Origin::parse("http://example.com/").is_potentially_trustworthy());
+    }
+}
+
+// === FILE: core\page_state.rs ===
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use parking_lot::RwLock;
+pub use crate::dom::parser::FrameworkType;
+
+// Идентификатор страницы, к которому привязаны её JS realm и прочее состояние
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PageId(pub u64);
+
+impl PageId {
+    // Realm для скриптов, не привязанных к странице
+    pub const DEFAULT: PageId = PageId(0);
+
+    pub fn next() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl fmt::Display for PageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+#[derive(Default, Clone)]
+pub struct PageState {
+    pub url: String,
+    pub html: String,
+    pub dom: String, // Пока как текстовое дерево → потом заменим на норм AST
+    pub detected_framework: Option<FrameworkType>,
+}
+
+impl PageState {
+    pub fn parse_framework_type(name: &str) -> Option<FrameworkType> {
+        match name {
+            "ReactNext" => Some(FrameworkType::ReactNext),
+            "Angular" => Some(FrameworkType::Angular),
+            "VueNuxt" => Some(FrameworkType::VueNuxt),
+            "SvelteKit" => Some(FrameworkType::SvelteKit),
+            "WasmModule" => Some(FrameworkType::WasmModule),
+            _ => None,
+        }
+    }
+}
+
+pub type SharedPageState = Arc<RwLock<PageState>>;
+
+
+// === FILE: core\permissions.rs ===
+use crate::core::interfaces::YuaidbTrait;
+use crate::core::origin::Origin;
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::oneshot;
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+// Разрешения, которые движок действительно проверяет; новое добавляется вместе с его проверкой
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Permission {
+    StorageQuota,
+}
+
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::StorageQuota => "storage-quota",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::StorageQuota => "увеличенную квоту хранилища",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PermissionState {
+    Granted,
+    Denied,
+    Prompt,
+}
+
+// Запрос, ожидающий решения пользователя
+#[derive(Clone, Debug)]
+pub struct PermissionPrompt {
+    pub id: u64,
+    pub origin: Origin,
+    pub permission: Permission,
+}
+
+struct PendingPrompt {
+    prompt: PermissionPrompt,
+    waiters: Vec<oneshot::Sender<bool>>,
+}
+
+// Решения хранятся в базе под ключом perm:<профиль>:<источник>:<разрешение>
+pub struct PermissionManager {
+    db: Arc<dyn YuaidbTrait + Send + Sync>,
+    profile: Mutex<String>,
+    pending: Mutex<Vec<PendingPrompt>>,
+    next_id: AtomicU64,
+}
+
+impl PermissionManager {
+    pub fn new(db: Arc<dyn YuaidbTrait + Send + Sync>) -> Self {
+        Self {
+            db,
+            profile: Mutex::new(DEFAULT_PROFILE.to_string()),
+            pending: Mutex::new(Vec::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn set_profile(&self, profile_id: &str) {
+        *self.profile.lock() = profile_id.to_string();
+    }
+
+    fn key(&self, origin: &Origin, permission: Permission) -> Option<String> {
+        let partition = origin.partition_key()?;
+        Some(format!("perm:{}:{}:{}", self.profile.lock(), partition, permission.as_str()))
+    }
+
+    pub async fn query(&self, origin: &Origin, permission: Permission) -> Result<PermissionState> {
+        let Some(key) = self.key(origin, permission) else {
+            return Ok(PermissionState::Denied);
+        };
+        Ok(match self.db.get(&key).await?.as_deref() {
+            Some("granted") => PermissionState::Granted,
+            Some("denied") => PermissionState::Denied,
+            _ => PermissionState::Prompt,
+        })
+    }
+
+    // Спрашивает пользователя только если решения для источника ещё нет
+    pub async fn request(&self, origin: &Origin, permission: Permission) -> Result<bool> {
+        match self.query(origin, permission).await? {
+            PermissionState::Granted => return Ok(true),
+            PermissionState::Denied => return Ok(false),
+            PermissionState::Prompt => {}
+        }
+        let (tx, rx) = oneshot::channel();
+        {
+            let mut pending = self.pending.lock();
+            // Повторный запрос того же разрешения ждёт уже показанный диалог
+            if let Some(existing) = pending
+                .iter_mut()
+                .find(|p| p.prompt.origin == *origin && p.prompt.permission == permission)
+            {
+                existing.waiters.push(tx);
+            } else {
+                let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                log::info!("Запрос разрешения {} для {}", permission.as_str(), origin);
+                pending.push(PendingPrompt {
+                    prompt: PermissionPrompt { id, origin: origin.clone(), permission },
+                    waiters: vec![tx],
+                });
+            }
+        }
+        // Закрытый диалог без ответа считается отказом
+        Ok(rx.await.unwrap_or(false))
+    }
+
+    pub fn pending_prompt(&self) -> Option<PermissionPrompt> {
+        self.pending.lock().first().map(|p| p.prompt.clone())
+    }
+
+    pub async fn resolve(&self, prompt_id: u64, granted: bool) -> Result<()> {
+        let resolved = {
+            let mut pending = self.pending.lock();
+            pending
+                .iter()
+                .position(|p| p.prompt.id == prompt_id)
+                .map(|index| pending.remove(index))
+        };
+        let Some(resolved) = resolved else {
+            return Err(anyhow::anyhow!("Запрос разрешения {} не найден", prompt_id));
+        };
+        if let Some(key) = self.key(&resolved.prompt.origin, resolved.prompt.permission) {
+            self.db.insert(&key, if granted { "granted" } else { "denied" }).await?;
+        }
+        log::info!(
+            "Разрешение {} для {}: {}",
+            resolved.prompt.permission.as_str(),
+            resolved.prompt.origin,
+            if granted { "выдано" } else { "отклонено" }
+        );
+        for waiter in resolved.waiters {
+            let _ = waiter.send(granted);
+        }
+        Ok(())
+    }
+
+    // Сброс решения: при следующем запросе пользователь будет спрошен снова
+    pub async fn reset(&self, origin: &Origin, permission: Permission) -> Result<()> {
+        match self.key(origin, permission) {
+            Some(key) => self.db.delete(&key).await,
+            None => Ok(()),
+        }
+    }
+}
+
+// === FILE: core\plugins.rs ===
+use crate::core::config::Config;
+use crate::core::engine::EngineEvent;
+use crate::core::interfaces::{PluginManagerTrait, SecurityManagerTrait, WasmRuntimeTrait};
+use crate::core::runtime::InstanceId;
+use crate::core::security::load_verified;
+#[cfg(feature = "native-plugins")]
+use crate::core::security::stage_verified;
+use crate::wasm_api::sandbox::SandboxConfig;
+use anyhow::Result;
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::Sender as BroadcastSender;
+
+// Версия API плагинов. Меняется только при несовместимых изменениях сообщений;
+// новые поля добавляются с serde(default), и старые плагины продолжают работать
+pub const PLUGIN_API_VERSION: u32 = 1;
+const PLUGIN_MANIFEST: &str = "plugin.yaml";
+// Перехватчик не должен задерживать навигацию: по истечении срока запрос разрешается
+const INTERCEPT_TIMEOUT: Duration = Duration::from_secs(2);
+const CALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginKind {
+    Wasm,
+    Native,
+}
+
+// ~/.cosmonaut/plugins/<каталог>/plugin.yaml; манифест и entry подписаны (plugin.yaml.sig, <entry>.sig)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PluginManifest {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    pub kind: PluginKind,
+    pub entry: String,
+    pub api_version: u32,
+    #[serde(default)]
+    pub contributes: PluginContributions,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginContributions {
+    #[serde(default)]
+    pub toolbar_buttons: Vec<ToolbarButton>,
+    #[serde(default)]
+    pub side_panels: Vec<SidePanelSpec>,
+    // Шаблоны URL с *, например "*://ads.example.com/*"
+    #[serde(default)]
+    pub request_interceptors: Vec<String>,
+    #[serde(default)]
+    pub page_load_hook: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ToolbarButton {
+    pub id: String,
+    pub label: String,
+    #[serde(default)]
+    pub tooltip: Option<String>,
+    // Кнопка открывает или закрывает боковую панель плагина
+    #[serde(default)]
+    pub panel: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SidePanelSpec {
+    pub id: String,
+    pub title: String,
+}
+
+// Вклад плагина в интерфейс вместе с именем плагина-владельца
+#[derive(Clone, Debug, PartialEq)]
+pub struct PluginButton {
+    pub plugin: String,
+    pub button: ToolbarButton,
+}
+
+#[derive(Clone, Debug)]
+pub struct PluginInfo {
+    pub manifest: PluginManifest,
+    pub enabled: bool,
+}
+
+// Сообщения хоста плагину
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PluginEvent {
+    Init { api_version: u32 },
+    ToolbarClicked { button: String },
+    RenderPanel { panel: String },
+    InterceptRequest { url: String, method: String },
+    PageLoaded { url: String },
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PanelContent {
+    #[serde(default)]
+    pub items: Vec<PanelItem>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PanelItem {
+    Heading { text: String },
+    Text { text: String },
+    Link { label: String, url: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RequestDecision {
+    Allow,
+    Block { reason: String },
+    Redirect { url: String },
+}
+
+// Ответы плагина
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PluginReply {
+    Ok,
+    Panel(PanelContent),
+    Request(RequestDecision),
+    Error { message: String },
+}
+
+// Транспорт до плагина: экспорт plugin_event WASM-модуля или C ABI разделяемой библиотеки
+#[async_trait]
+trait PluginBackend: Send + Sync {
+    async fn call(&self, input: Vec<u8>) -> Result<Vec<u8>>;
+    async fn unload(&self) {}
+}
+
+struct WasmPlugin {
+    runtime: Arc<dyn WasmRuntimeTrait + Send + Sync>,
+    instance: InstanceId,
+}
+
+#[async_trait]
+impl PluginBackend for WasmPlugin {
+    async fn call(&self, input: Vec<u8>) -> Result<Vec<u8>> {
+        self.runtime.call_bytes(self.instance, "plugin_event", input).await
+    }
+
+    async fn unload(&self) {
+        self.runtime.drop_instance(self.instance).await;
+    }
+}
+
+// Стабильный C ABI нативного плагина:
+//   u32  cosmonaut_plugin_api_version(void)
+//   u8*  cosmonaut_plugin_call(const u8* input, usize len, usize* out_len)
+//   void cosmonaut_plugin_free(u8* ptr, usize len)
+// Вход и выход — JSON тех же сообщений, что и у WASM-плагинов
+#[cfg(feature = "native-plugins")]
+struct NativePlugin {
+    library: Arc<libloading::Library>,
+}
+
+#[cfg(feature = "native-plugins")]
+type NativeCall = unsafe extern "C" fn(*const u8, usize, *mut usize) -> *mut u8;
+#[cfg(feature = "native-plugins")]
+type NativeFree = unsafe extern "C" fn(*mut u8, usize);
+
+#[cfg(feature = "native-plugins")]
+impl NativePlugin {
+    // bytes — содержимое entry, подпись которого уже проверена
+    fn open(entry: &Path, bytes: &[u8]) -> Result<Self> {
+        let staged = stage_verified("plugins", entry, bytes)?;
+        let library = unsafe { libloading::Library::new(&staged) };
+        // Загруженной библиотеке файл не нужен
+        #[cfg(unix)]
+        if let Err(e) = std::fs::remove_file(&staged) {
+            log::warn!("Не удалось удалить копию плагина {}: {}", staged.display(), e);
+        }
+        let library = library?;
+        let version = unsafe {
+            let version: libloading::Symbol<unsafe extern "C" fn() -> u32> =
+                library.get(b"cosmonaut_plugin_api_version\0")?;
+            version()
+        };
+        if version != PLUGIN_API_VERSION {
+            return Err(anyhow::anyhow!("Нативный плагин собран под API {}, ожидается {}", version, PLUGIN_API_VERSION));
+        }
+        Ok(Self { library: Arc::new(library) })
+    }
+}
+
+#[cfg(feature = "native-plugins")]
+#[async_trait]
+impl PluginBackend for NativePlugin {
+    async fn call(&self, input: Vec<u8>) -> Result<Vec<u8>> {
+        let library = self.library.clone();
+        // Код плагина может блокировать: вызов идёт вне потоков runtime
+        tokio::task::spawn_blocking(move || unsafe {
+            let call: libloading::Symbol<NativeCall> = library.get(b"cosmonaut_plugin_call\0")?;
+            let free: libloading::Symbol<NativeFree> = library.get(b"cosmonaut_plugin_free\0")?;
+            let mut len = 0usize;
+            let ptr = call(input.as_ptr(), input.len(), &mut len);
+            if ptr.is_null() {
+                return Err(anyhow::anyhow!("Нативный плагин не вернул ответ"));
+            }
+            let output = std::slice::from_raw_parts(ptr, len).to_vec();
+            free(ptr, len);
+            Ok(output)
+        })
+        .await?
+    }
+}
+
+struct LoadedPlugin {
+    manifest: PluginManifest,
+    backend: Box<dyn PluginBackend>,
+    enabled: AtomicBool,
+}
+
+impl LoadedPlugin {
+    async fn send(&self, event: &PluginEvent, timeout: Duration) -> Result<PluginReply> {
+        let input = serde_json::to_vec(event)?;
+        let output = tokio::time::timeout(timeout, self.backend.call(input))
+            .await
+            .map_err(|_| anyhow::anyhow!("Плагин {} не ответил за {:?}", self.manifest.name, timeout))??;
+        match serde_json::from_slice(&output)? {
+            PluginReply::Error { message } => Err(anyhow::anyhow!("Плагин {}: {}", self.manifest.name, message)),
+            reply => Ok(reply),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    fn intercepts(&self, url: &str) -> bool {
+        self.manifest.contributes.request_interceptors.iter().any(|pattern| wildcard_match(pattern, url))
+    }
+}
+
+// Шаблон с * (любая последовательность символов), без других метасимволов
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || !text[first.len()..].ends_with(last) || text.len() < first.len() + last.len() {
+        return false;
+    }
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+pub struct PluginManager {
+    plugins_dir: PathBuf,
+    plugins: Arc<RwLock<Vec<Arc<LoadedPlugin>>>>,
+}
+
+impl PluginManager {
+    pub fn plugins_dir(&self) -> &Path {
+        &self.plugins_dir
+    }
+
+    fn enabled(&self) -> Vec<Arc<LoadedPlugin>> {
+        self.plugins.read().iter().filter(|plugin| plugin.is_enabled()).cloned().collect()
+    }
+
+    fn find(&self, name: &str) -> Result<Arc<LoadedPlugin>> {
+        self.plugins
+            .read()
+            .iter()
+            .find(|plugin| plugin.manifest.name == name)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Плагин {} не загружен", name))
+    }
+
+    async fn load(
+        dir: &Path,
+        config: &Config,
+        runtime: &Arc<dyn WasmRuntimeTrait + Send + Sync>,
+        security: &(dyn SecurityManagerTrait + Send + Sync),
+    ) -> Result<LoadedPlugin> {
+        // Манифест определяет вид плагина и его перехватчики, поэтому подписан так же, как entry
+        let text = load_verified(security, &dir.join(PLUGIN_MANIFEST)).await?;
+        let manifest: PluginManifest = serde_yaml::from_slice(&text)?;
+        if manifest.api_version != PLUGIN_API_VERSION {
+            return Err(anyhow::anyhow!(
+                "Плагин {} требует API {}, поддерживается {}",
+                manifest.name,
+                manifest.api_version,
+                PLUGIN_API_VERSION
+            ));
+        }
+        let entry = dir.join(&manifest.entry);
+        if !entry.starts_with(dir) || manifest.entry.contains("..") {
+            return Err(anyhow::anyhow!("entry плагина {} выходит за пределы его каталога", manifest.name));
+        }
+        // Неподписанные и недоверенные плагины не загружаются
+        let bytes = load_verified(security, &entry).await?;
+        let backend: Box<dyn PluginBackend> = match manifest.kind {
+            PluginKind::Wasm => {
+                let signature = tokio::fs::read(crate::core::security::signature_path(&entry)).await?;
+                let digest = runtime.load_module(&bytes, &signature).await?;
+                let sandbox = SandboxConfig { name: manifest.name.clone(), ..SandboxConfig::default() };
+                let instance = runtime.instantiate(&digest, sandbox).await?;
+                Box::new(WasmPlugin { runtime: runtime.clone(), instance })
+            }
+            PluginKind::Native if !config.plugins.allow_native => {
+                return Err(anyhow::anyhow!("Нативные плагины отключены (plugins.allow_native)"));
+            }
+            #[cfg(feature = "native-plugins")]
+            PluginKind::Native => Box::new(NativePlugin::open(&entry, &bytes)?),
+            #[cfg(not(feature = "native-plugins"))]
+            PluginKind::Native => {
+                return Err(anyhow::anyhow!("Сборка без поддержки нативных плагинов (feature native-plugins)"));
+            }
+        };
+        let enabled = !config.plugins.disabled.contains(&manifest.name);
+        let plugin = LoadedPlugin { manifest, backend, enabled: AtomicBool::new(enabled) };
+        plugin.send(&PluginEvent::Init { api_version: PLUGIN_API_VERSION }, CALL_TIMEOUT).await?;
+        Ok(plugin)
+    }
+
+    // Хуки загрузки страницы срабатывают на EngineEvent::PageLoaded
+    fn watch_page_loads(plugins: Arc<RwLock<Vec<Arc<LoadedPlugin>>>>, events: &BroadcastSender<EngineEvent>) {
+        let mut events = events.subscribe();
+        tokio::spawn(async move {
+            loop {
+                let url = match events.recv().await {
+                    Ok(EngineEvent::PageLoaded { url, .. }) => url,
+                    Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+                };
+                let hooks: Vec<_> = plugins
+                    .read()
+                    .iter()
+                    .filter(|plugin| plugin.is_enabled() && plugin.manifest.contributes.page_load_hook)
+                    .cloned()
+                    .collect();
+                for plugin in hooks {
+                    let event = PluginEvent::PageLoaded { url: url.clone() };
+                    if let Err(e) = plugin.send(&event, CALL_TIMEOUT).await {
+                        log::warn!("Хук загрузки страницы: {}", e);
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl PluginManagerTrait for PluginManager {
+    async fn new(
+        plugins_dir: impl AsRef<Path> + Send,
+        config: &Config,
+        runtime: Arc<dyn WasmRuntimeTrait + Send + Sync>,
+        security: Arc<dyn SecurityManagerTrait + Send + Sync>,
+        events: BroadcastSender<EngineEvent>,
+    ) -> Result<Self> {
+        let plugins_dir = plugins_dir.as_ref().to_path_buf();
+        let mut dirs = Vec::new();
+        if plugins_dir.exists() {
+            for entry in std::fs::read_dir(&plugins_dir)? {
+                let dir = entry?.path();
+                let manifest = dir.join(PLUGIN_MANIFEST);
+                if !manifest.exists() {
+                    continue;
+                }
+                // Только для порядка загрузки; подпись проверяется в load
+                let kind = std::fs::read_to_string(&manifest)
+                    .ok()
+                    .and_then(|text| serde_yaml::from_str::<PluginManifest>(&text).ok())
+                    .map(|manifest| manifest.kind);
+                dirs.push((kind != Some(PluginKind::Wasm), dir));
+            }
+        }
+        // Сначала WASM-плагины: они изолированы и не зависят от нативных
+        dirs.sort();
+        let mut plugins = Vec::new();
+        for (_, dir) in dirs {
+            match Self::load(&dir, config, &runtime, security.as_ref()).await {
+                Ok(plugin) => {
+                    log::info!("Загружен плагин {} {}", plugin.manifest.name, plugin.manifest.version);
+                    let _ = events.send(EngineEvent::PluginLoaded(plugin.manifest.name.clone()));
+                    plugins.push(Arc::new(plugin));
+                }
+                Err(e) => log::warn!("Плагин из {:?} пропущен: {:#}", dir, e),
+            }
+        }
+        log::info!("Менеджер плагинов инициализирован, загружено плагинов: {}", plugins.len());
+        let plugins = Arc::new(RwLock::new(plugins));
+        Self::watch_page_loads(plugins.clone(), &events);
+        Ok(Self { plugins_dir, plugins })
+    }
+
+    fn plugins(&self) -> Vec<PluginInfo> {
+        self.plugins
+            .read()
+            .iter()
+            .map(|plugin| PluginInfo { manifest: plugin.manifest.clone(), enabled: plugin.is_enabled() })
+            .collect()
+    }
+
+    fn set_enabled(&self, name: &str, enabled: bool) -> Result<()> {
+        self.find(name)?.enabled.store(enabled, Ordering::Relaxed);
+        log::info!("Плагин {} {}", name, if enabled { "включён" } else { "отключён" });
+        Ok(())
+    }
+
+    fn toolbar_buttons(&self) -> Vec<PluginButton> {
+        self.enabled()
+            .iter()
+            .flat_map(|plugin| {
+                plugin.manifest.contributes.toolbar_buttons.iter().map(|button| PluginButton {
+                    plugin: plugin.manifest.name.clone(),
+                    button: button.clone(),
+                })
+            })
+            .collect()
+    }
+
+    fn side_panel(&self, plugin: &str, panel: &str) -> Option<SidePanelSpec> {
+        let plugin = self.find(plugin).ok()?;
+        plugin.manifest.contributes.side_panels.iter().find(|spec| spec.id == panel).cloned()
+    }
+
+    async fn toolbar_clicked(&self, plugin: &str, button: &str) -> Result<()> {
+        let plugin = self.find(plugin)?;
+        plugin.send(&PluginEvent::ToolbarClicked { button: button.to_string() }, CALL_TIMEOUT).await?;
+        Ok(())
+    }
+
+    async fn render_panel(&self, plugin: &str, panel: &str) -> Result<PanelContent> {
+        let plugin = self.find(plugin)?;
+        match plugin.send(&PluginEvent::RenderPanel { panel: panel.to_string() }, CALL_TIMEOUT).await? {
+            PluginReply::Panel(content) => Ok(content),
+            _ => Ok(PanelContent::default()),
+        }
+    }
+
+    // Первый плагин, заблокировавший или перенаправивший запрос, определяет результат
+    async fn intercept_request(&self, url: &str, method: &str) -> RequestDecision {
+        for plugin in self.enabled().iter().filter(|plugin| plugin.intercepts(url)) {
+            let event = PluginEvent::InterceptRequest { url: url.to_string(), method: method.to_string() };
+            match plugin.send(&event, INTERCEPT_TIMEOUT).await {
+                Ok(PluginReply::Request(decision)) if decision != RequestDecision::Allow => {
+                    log::info!("Плагин {} изменил запрос {}: {:?}", plugin.manifest.name, url, decision);
+                    return decision;
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("Перехватчик запросов: {}", e),
+            }
+        }
+        RequestDecision::Allow
+    }
+
+    async fn shutdown(&self) {
+        let plugins = std::mem::take(&mut *self.plugins.write());
+        for plugin in plugins {
+            plugin.backend.unload().await;
+        }
+    }
+}
+
+// === FILE: core\policy.rs ===
+use crate::core::interfaces::YuaidbTrait;
+use base64::Engine as _;
+use parking_lot::RwLock;
+use reqwest::header::HeaderMap;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+// Типы подресурсов страницы и соответствующие директивы CSP
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    Script,
+    Style,
+    Image,
+    Font,
+    Media,
+    Connect,
+    Frame,
+    Object,
+}
+
+impl ResourceKind {
+    pub fn directive(&self) -> &'static str {
+        match self {
+            Self::Script => "script-src",
+            Self::Style => "style-src",
+            Self::Image => "img-src",
+            Self::Font => "font-src",
+            Self::Media => "media-src",
+            Self::Connect => "connect-src",
+            Self::Frame => "frame-src",
+            Self::Object => "object-src",
+        }
+    }
+
+    // Пассивное смешанное содержимое повышается до HTTPS, активное блокируется
+    fn is_passive(&self) -> bool {
+        matches!(self, Self::Image | Self::Media)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PolicyViolation {
+    pub page_url: String,
+    pub blocked_url: String,
+    pub directive: String,
+    pub message: String,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}: {})", self.message, self.directive, self.blocked_url)
+    }
+}
+
+impl std::error::Error for PolicyViolation {}
+
+#[derive(Clone, Debug, Default)]
+pub struct ContentSecurityPolicy {
+    directives: HashMap<String, Vec<String>>,
+}
+
+impl ContentSecurityPolicy {
+    pub fn parse(header: &str) -> Self {
+        let mut directives = HashMap::new();
+        for part in header.split(';') {
+            let mut tokens = part.split_ascii_whitespace();
+            let Some(name) = tokens.next() else { continue };
+            // По спецификации действует первое вхождение директивы
+            directives
+                .entry(name.to_ascii_lowercase())
+                .or_insert_with(|| tokens.map(str::to_string).collect());
+        }
+        Self { directives }
+    }
+
+    pub fn has(&self, directive: &str) -> bool {
+        self.directives.contains_key(directive)
+    }
+
+    // Директивы *-src наследуют default-src, остальные (frame-ancestors и т.д.) - нет
+    fn sources_for(&self, directive: &str) -> Option<&Vec<String>> {
+        self.directives.get(directive).or_else(|| {
+            if directive.ends_with("-src") {
+                self.directives.get("default-src")
+            } else {
+                None
+            }
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrameOptions {
+    Deny,
+    SameOrigin,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum ReferrerPolicy {
+    NoReferrer,
+    NoReferrerWhenDowngrade,
+    Origin,
+    OriginWhenCrossOrigin,
+    SameOrigin,
+    StrictOrigin,
+    #[default]
+    StrictOriginWhenCrossOrigin,
+    UnsafeUrl,
+}
+
+impl ReferrerPolicy {
+    pub fn from_token(token: &str) -> Option<Self> {
+        match token.trim().to_ascii_lowercase().as_str() {
+            "no-referrer" => Some(Self::NoReferrer),
+            "no-referrer-when-downgrade" => Some(Self::NoReferrerWhenDowngrade),
+            "origin" => Some(Self::Origin),
+            "origin-when-cross-origin" => Some(Self::OriginWhenCrossOrigin),
+            "same-origin" => Some(Self::SameOrigin),
+            "strict-origin" => Some(Self::StrictOrigin),
+            "strict-origin-when-cross-origin" => Some(Self::StrictOriginWhenCrossOrigin),
+            "unsafe-url" => Some(Self::UnsafeUrl),
+            _ => None,
+        }
+    }
+}
+
+// Политика безопасности страницы, собранная из заголовков ответа
+#[derive(Clone, Debug, Default)]
+pub struct PagePolicy {
+    page_url: Option<Url>,
+    csp: Vec<ContentSecurityPolicy>,
+    frame_options: Option<FrameOptions>,
+    referrer_policy: ReferrerPolicy,
+}
+
+impl PagePolicy {
+    pub fn from_headers(page_url: &str, headers: &HeaderMap) -> Self {
+        let csp = headers
+            .get_all("content-security-policy")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter(|v| !v.trim().is_empty())
+            .map(ContentSecurityPolicy::parse)
+            .collect::<Vec<_>>();
+        let frame_options = headers
+            .get("x-frame-options")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| match v.trim().to_ascii_lowercase().as_str() {
+                "deny" => Some(FrameOptions::Deny),
+                "sameorigin" => Some(FrameOptions::SameOrigin),
+                _ => None,
+            });
+        // Берётся последнее распознанное значение списка
+        let referrer_policy = headers
+            .get_all("referrer-policy")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter_map(ReferrerPolicy::from_token)
+            .last()
+            .unwrap_or_default();
+        log::info!(
+            "Политика страницы {}: CSP={}, X-Frame-Options={:?}, Referrer-Policy={:?}",
+            page_url,
+            csp.len(),
+            frame_options,
+            referrer_policy
+        );
+        Self {
+            page_url: Url::parse(page_url).ok(),
+            csp,
+            frame_options,
+            referrer_policy,
+        }
+    }
+
+    pub fn page_url(&self) -> Option<&Url> {
+        self.page_url.as_ref()
+    }
+
+    pub fn resolve(&self, resource: &str) -> Result<Url, url::ParseError> {
+        match &self.page_url {
+            Some(base) => base.join(resource),
+            None => Url::parse(resource),
+        }
+    }
+
+    // Возвращает URL, который разрешено загрузить (возможно, повышенный до HTTPS)
+    pub fn check_resource(&self, kind: ResourceKind, resource: &str) -> Result<Url, PolicyViolation> {
+        let mut url = self.resolve(resource).map_err(|e| {
+            self.violation(resource, kind.directive(), format!("Некорректный URL ресурса: {}", e))
+        })?;
+        // Локальные файлы и служебные страницы доступны только локальным и служебным страницам
+        let local_page = self.page_url.as_ref().is_some_and(|page| matches!(page.scheme(), "file" | "about"));
+        let local_resource = url.scheme() == "file" || (url.scheme() == "about" && url.as_str() != "about:blank");
+        if local_resource && !local_page {
+            return Err(self.violation(
+                url.as_str(),
+                kind.directive(),
+                "Страница из сети не может загружать file: и about: ресурсы".to_string(),
+            ));
+        }
+        if self.csp.iter().any(|p| p.has("upgrade-insecure-requests")) {
+            upgrade_scheme(&mut url);
+        }
+        if let Some(page) = &self.page_url {
+            if page.scheme() == "https" && matches!(url.scheme(), "http" | "ws") {
+                let block_all = self.csp.iter().any(|p| p.has("block-all-mixed-content"));
+                if kind.is_passive() && !block_all {
+                    log::info!("Смешанное содержимое повышено до HTTPS: {}", url);
+                    upgrade_scheme(&mut url);
+                } else {
+                    return Err(self.violation(
+                        url.as_str(),
+                        "mixed-content",
+                        "Смешанное содержимое заблокировано на HTTPS-странице".to_string(),
+                    ));
+                }
+            }
+        }
+        for policy in &self.csp {
+            if let Some(sources) = policy.sources_for(kind.directive()) {
+                if !sources.iter().any(|s| source_matches(s, &url, self.page_url.as_ref())) {
+                    return Err(self.violation(
+                        url.as_str(),
+                        kind.directive(),
+                        "Ресурс не разрешён политикой CSP".to_string(),
+                    ));
+                }
+            }
+        }
+        Ok(url)
+    }
+
+    // Встроенные скрипты и стили: 'unsafe-inline', nonce или хэш
+    pub fn check_inline(&self, kind: ResourceKind, content: &str, nonce: Option<&str>) -> Result<(), PolicyViolation> {
+        for policy in &self.csp {
+            let Some(sources) = policy.sources_for(kind.directive()) else { continue };
+            if !inline_allowed(sources, content, nonce) {
+                return Err(self.violation(
+                    "inline",
+                    kind.directive(),
+                    "Встроенный код заблокирован политикой CSP".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn allows_eval(&self) -> bool {
+        self.csp.iter().all(|policy| {
+            policy
+                .sources_for(ResourceKind::Script.directive())
+                .map_or(true, |sources| sources.iter().any(|s| s.eq_ignore_ascii_case("'unsafe-eval'")))
+        })
+    }
+
+    // Строка, которую страница пыталась выполнить как код при запрещённом eval
+    pub fn eval_violation(&self, sample: &str) -> PolicyViolation {
+        let sample: String = sample.chars().take(40).collect();
+        self.violation(
+            "eval",
+            ResourceKind::Script.directive(),
+            format!("Выполнение строки как кода заблокировано: нет 'unsafe-eval' ({})", sample),
+        )
+    }
+
+    // frame-ancestors имеет приоритет над X-Frame-Options
+    pub fn check_embedding(&self, ancestor_url: &str) -> Result<(), PolicyViolation> {
+        let ancestor = Url::parse(ancestor_url).ok();
+        let ancestors = self
+            .csp
+            .iter()
+            .filter_map(|p| p.directives.get("frame-ancestors"))
+            .collect::<Vec<_>>();
+        if !ancestors.is_empty() {
+            for sources in ancestors {
+                let allowed = ancestor
+                    .as_ref()
+                    .map_or(false, |a| sources.iter().any(|s| source_matches(s, a, self.page_url.as_ref())));
+                if !allowed {
+                    return Err(self.violation(
+                        ancestor_url,
+                        "frame-ancestors",
+                        "Встраивание страницы запрещено политикой CSP".to_string(),
+                    ));
+                }
+            }
+            return Ok(());
+        }
+        let same_origin = match (&ancestor, &self.page_url) {
+            (Some(a), Some(p)) => a.origin() == p.origin(),
+            _ => false,
+        };
+        match self.frame_options {
+            Some(FrameOptions::Deny) => Err(self.violation(
+                ancestor_url,
+                "x-frame-options",
+                "Встраивание страницы запрещено (DENY)".to_string(),
+            )),
+            Some(FrameOptions::SameOrigin) if !same_origin => Err(self.violation(
+                ancestor_url,
+                "x-frame-options",
+                "Встраивание разрешено только для того же источника".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // Значение заголовка Referer для запроса со страницы к target
+    pub fn referrer_for(&self, target: &Url) -> Option<String> {
+        let page = self.page_url.as_ref()?;
+        if !matches!(page.scheme(), "http" | "https") {
+            return None;
+        }
+        let mut full = page.clone();
+        full.set_fragment(None);
+        let _ = full.set_username("");
+        let _ = full.set_password(None);
+        let full = full.to_string();
+        let origin = format!("{}/", page.origin().ascii_serialization());
+        let same_origin = page.origin() == target.origin();
+        let downgrade = page.scheme() == "https" && target.scheme() != "https";
+        match self.referrer_policy {
+            ReferrerPolicy::NoReferrer => None,
+            ReferrerPolicy::NoReferrerWhenDowngrade => (!downgrade).then_some(full),
+            ReferrerPolicy::Origin => Some(origin),
+            ReferrerPolicy::OriginWhenCrossOrigin => Some(if same_origin { full } else { origin }),
+            ReferrerPolicy::SameOrigin => same_origin.then_some(full),
+            ReferrerPolicy::StrictOrigin => (!downgrade).then_some(origin),
+            ReferrerPolicy::StrictOriginWhenCrossOrigin => {
+                if same_origin {
+                    Some(full)
+                } else {
+                    (!downgrade).then_some(origin)
+                }
+            }
+            ReferrerPolicy::UnsafeUrl => Some(full),
+        }
+    }
+
+    fn violation(&self, blocked_url: &str, directive: &str, message: String) -> PolicyViolation {
+        let violation = PolicyViolation {
+            page_url: self.page_url.as_ref().map(|u| u.to_string()).unwrap_or_default(),
+            blocked_url: blocked_url.to_string(),
+            directive: directive.to_string(),
+            message,
+        };
+        log::warn!("Нарушение политики безопасности: {}", violation);
+        violation
+    }
+}
+
+fn upgrade_scheme(url: &mut Url) {
+    let secure = match url.scheme() {
+        "http" => "https",
+        "ws" => "wss",
+        _ => return,
+    };
+    if url.port() == Some(80) {
+        let _ = url.set_port(None);
+    }
+    let _ = url.set_scheme(secure);
+}
+
+fn scheme_compatible(source_scheme: &str, url_scheme: &str) -> bool {
+    source_scheme == url_scheme
+        || (source_scheme == "http" && url_scheme == "https")
+        || (source_scheme == "ws" && url_scheme == "wss")
+}
+
+fn source_matches(source: &str, url: &Url, page: Option<&Url>) -> bool {
+    let source = source.to_ascii_lowercase();
+    match source.as_str() {
+        "'none'" => false,
+        "*" => matches!(url.scheme(), "http" | "https" | "ws" | "wss" | "ftp"),
+        "'self'" => page.map_or(false, |p| {
+            url.host_str() == p.host_str()
+                && scheme_compatible(p.scheme(), url.scheme())
+                && (url.port_or_known_default() == p.port_or_known_default()
+                    || (url.scheme() != p.scheme() && url.port().is_none() && p.port().is_none()))
+        }),
+        // Ключевые слова, nonce и хэши к URL не относятся
+        s if s.starts_with('\'') => false,
+        s if s.ends_with(':') && !s.contains('/') => scheme_compatible(&s[..s.len() - 1], url.scheme()),
+        s => host_source_matches(s, url, page),
+    }
+}
+
+fn host_source_matches(source: &str, url: &Url, page: Option<&Url>) -> bool {
+    let (scheme, rest) = match source.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        None => (None, source),
+    };
+    let scheme_ok = match (scheme, page) {
+        (Some(scheme), _) => scheme_compatible(scheme, url.scheme()),
+        // Без схемы подразумевается схема страницы
+        (None, Some(page)) => scheme_compatible(page.scheme(), url.scheme()),
+        (None, None) => matches!(url.scheme(), "http" | "https"),
+    };
+    if !scheme_ok {
+        return false;
+    }
+    let (host_port, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], Some(&rest[i..])),
+        None => (rest, None),
+    };
+    let (host, port) = match host_port.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => (host, Some(port)),
+        _ => (host_port, None),
+    };
+    let Some(url_host) = url.host_str() else { return false };
+    let host_ok = if host == "*" {
+        true
+    } else if let Some(suffix) = host.strip_prefix("*.") {
+        url_host.to_ascii_lowercase().ends_with(&format!(".{}", suffix))
+    } else {
+        url_host.eq_ignore_ascii_case(host)
+    };
+    if !host_ok {
+        return false;
+    }
+    let port_ok = match port {
+        Some("*") => true,
+        Some(port) => port.parse::<u16>().ok() == url.port_or_known_default(),
+        None => url.port().is_none(),
+    };
+    if !port_ok {
+        return false;
+    }
+    match path {
+        Some(path) if path != "/" => {
+            if path.ends_with('/') {
+                url.path().starts_with(path)
+            } else {
+                url.path() == path
+            }
+        }
+        _ => true,
+    }
+}
+
+// 'unsafe-inline' игнорируется, если в списке есть nonce или хэш
+fn inline_allowed(sources: &[String], content: &str, nonce: Option<&str>) -> bool {
+    let mut has_nonce_or_hash = false;
+    for source in sources {
+        if let Some(expected) = source.strip_prefix("'nonce-").and_then(|s| s.strip_suffix('\'')) {
+            has_nonce_or_hash = true;
+            if nonce == Some(expected) {
+                return true;
+            }
+        } else if let Some(matches) = hash_source_matches(source, content) {
+            has_nonce_or_hash = true;
+            if matches {
+                return true;
+            }
+        }
+    }
+    !has_nonce_or_hash && sources.iter().any(|s| s.eq_ignore_ascii_case("'unsafe-inline'"))
+}
+
+fn hash_source_matches(source: &str, content: &str) -> Option<bool> {
+    let inner = source.strip_prefix('\'')?.strip_suffix('\'')?;
+    let (algorithm, expected) = inner.split_once('-')?;
+    let digest = match algorithm.to_ascii_lowercase().as_str() {
+        "sha256" => Sha256::digest(content.as_bytes()).to_vec(),
+        "sha384" => Sha384::digest(content.as_bytes()).to_vec(),
+        "sha512" => Sha512::digest(content.as_bytes()).to_vec(),
+        _ => return None,
+    };
+    Some(base64::engine::general_purpose::STANDARD.encode(digest) == expected)
+}
+
+const HSTS_PREFIX: &str = "hsts:";
+
+#[derive(Clone, Copy, Debug)]
+struct HstsEntry {
+    expires_at: u64,
+    include_subdomains: bool,
+}
+
+// HSTS-записи хранятся в Yuaidb по ключу hsts:<host>
+pub struct HstsStore {
+    db: Arc<dyn YuaidbTrait + Send + Sync>,
+    cache: RwLock<HashMap<String, Option<HstsEntry>>>,
+}
+
+impl HstsStore {
+    pub fn new(db: Arc<dyn YuaidbTrait + Send + Sync>) -> Self {
+        Self { db, cache: RwLock::new(HashMap::new()) }
+    }
+
+    async fn entry(&self, host: &str) -> Option<HstsEntry> {
+        let cached = self.cache.read().get(host).copied();
+        let entry = match cached {
+            Some(entry) => entry,
+            None => {
+                let stored = match self.db.get(&format!("{}{}", HSTS_PREFIX, host)).await {
+                    Ok(value) => value.and_then(|v| parse_stored_entry(&v)),
+                    Err(e) => {
+                        log::warn!("Ошибка чтения HSTS для {}: {}", host, e);
+                        None
+                    }
+                };
+                self.cache.write().insert(host.to_string(), stored);
+                stored
+            }
+        };
+        entry.filter(|e| e.expires_at > unix_now())
+    }
+
+    pub async fn is_known_host(&self, host: &str) -> bool {
+        if self.entry(host).await.is_some() {
+            return true;
+        }
+        let mut rest = host;
+        while let Some((_, parent)) = rest.split_once('.') {
+            if self.entry(parent).await.map_or(false, |e| e.include_subdomains) {
+                return true;
+            }
+            rest = parent;
+        }
+        false
+    }
+
+    pub async fn upgrade_url(&self, url: &str) -> String {
+        let Ok(mut parsed) = Url::parse(url) else { return url.to_string() };
+        if parsed.scheme() != "http" {
+            return url.to_string();
+        }
+        let Some(host) = parsed.host_str().map(str::to_ascii_lowercase) else { return url.to_string() };
+        if self.is_known_host(&host).await {
+            upgrade_scheme(&mut parsed);
+            log::info!("HSTS: запрос к {} повышен до HTTPS", host);
+            return parsed.to_string();
+        }
+        url.to_string()
+    }
+
+    // Заголовок учитывается только для HTTPS-ответов доменных имён
+    pub async fn record(&self, url: &str, headers: &HeaderMap) {
+        let Ok(parsed) = Url::parse(url) else { return };
+        if parsed.scheme() != "https" {
+            return;
+        }
+        let Some(url::Host::Domain(host)) = parsed.host() else { return };
+        let host = host.to_ascii_lowercase();
+        let Some(value) = headers.get("strict-transport-security").and_then(|v| v.to_str().ok()) else { return };
+        let Some((max_age, include_subdomains)) = parse_hsts_header(value) else {
+            log::warn!("Некорректный заголовок HSTS от {}: {}", host, value);
+            return;
+        };
+        let key = format!("{}{}", HSTS_PREFIX, host);
+        let result = if max_age == 0 {
+            self.cache.write().insert(host.clone(), None);
+            self.db.delete(&key).await
+        } else {
+            let entry = HstsEntry {
+                expires_at: unix_now().saturating_add(max_age),
+                include_subdomains,
+            };
+            self.cache.write().insert(host.clone(), Some(entry));
+            self.db
+                .insert(&key, &format!("{};{}", entry.expires_at, entry.include_subdomains as u8))
+                .await
+        };
+        match result {
+            Ok(()) => log::info!("HSTS для {} обновлён: max-age={}", host, max_age),
+            Err(e) => log::warn!("Не удалось сохранить HSTS для {}: {}", host, e),
+        }
+    }
+}
+
+fn parse_hsts_header(value: &str) -> Option<(u64, bool)> {
+    let mut max_age = None;
+    let mut include_subdomains = false;
+    for directive in value.split(';') {
+        let directive = directive.trim();
+        let (name, arg) = match directive.split_once('=') {
+            Some((name, arg)) => (name.trim(), Some(arg.trim().trim_matches('"'))),
+            None => (directive, None),
+        };
+        if name.eq_ignore_ascii_case("max-age") {
+            max_age = arg.and_then(|a| a.parse().ok());
+        } else if name.eq_ignore_ascii_case("includesubdomains") {
+            include_subdomains = true;
+        }
+    }
+    max_age.map(|max_age| (max_age, include_subdomains))
+}
+
+fn parse_stored_entry(value: &str) -> Option<HstsEntry> {
+    let (expires_at, include) = value.split_once(';')?;
+    Some(HstsEntry {
+        expires_at: expires_at.parse().ok()?,
+        include_subdomains: include == "1",
+    })
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::yuaidb::Yuaidb;
+    use reqwest::header::HeaderValue;
+
+    fn policy(page: &str, headers: &[(&'static str, &str)]) -> PagePolicy {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.append(*name, HeaderValue::from_str(value).unwrap());
+        }
+        PagePolicy::from_headers(page, &map)
+    }
+
+    fn allowed(policy: &PagePolicy, kind: ResourceKind, resource: &str) -> bool {
+        policy.check_resource(kind, resource).is_ok()
+    }
+
+    #[test]
+    fn csp_sources_match_scheme_host_port_and_path() {
+        let page = policy(
+            "https://example.com/",
+            &[("content-security-policy", "script-src 'self' https://cdn.example.net/js/ *.static.org:*")],
+        );
+        assert!(allowed(&page, ResourceKind::Script, "/app.js"));
+        assert!(allowed(&page, ResourceKind::Script, "https://cdn.example.net/js/lib.js"));
+        assert!(!allowed(&page, ResourceKind::Script, "https://cdn.example.net/other.js"));
+        assert!(allowed(&page, ResourceKind::Script, "https://a.static.org:8443/x.js"));
+        assert!(!allowed(&page, ResourceKind::Script, "https://static.org/x.js"));
+        assert!(!allowed(&page, ResourceKind::Script, "https://evil.com/x.js"));
+        // Директивы без своего списка не ограничены, если нет default-src
+        assert!(allowed(&page, ResourceKind::Image, "https://evil.com/a.png"));
+
+        // 'self' на HTTP-странице допускает тот же хост по HTTPS
+        let page = policy("http://example.com/", &[("content-security-policy", "script-src 'self'")]);
+        assert!(allowed(&page, ResourceKind::Script, "https://example.com/app.js"));
+        assert!(!allowed(&page, ResourceKind::Script, "http://example.com:8080/app.js"));
+    }
+
+    #[test]
+    fn src_directives_fall_back_to_default_src() {
+        let page = policy("https://example.com/", &[("content-security-policy", "default-src 'none'; img-src *")]);
+        assert!(allowed(&page, ResourceKind::Image, "https://any.org/a.png"));
+        assert!(!allowed(&page, ResourceKind::Style, "/a.css"));
+        assert!(!allowed(&page, ResourceKind::Connect, "/api"));
+        // Все политики из заголовков должны разрешить ресурс
+        let page = policy(
+            "https://example.com/",
+            &[("content-security-policy", "img-src *"), ("content-security-policy", "img-src 'self'")],
+        );
+        assert!(allowed(&page, ResourceKind::Image, "/a.png"));
+        assert!(!allowed(&page, ResourceKind::Image, "https://any.org/a.png"));
+    }
+
+    #[test]
+    fn inline_code_needs_nonce_hash_or_unsafe_inline() {
+        let hash = base64::engine::general_purpose::STANDARD.encode(Sha256::digest(b"alert(1)"));
+        let header = format!("script-src 'nonce-abc' 'sha256-{}' 'unsafe-inline'", hash);
+        let page = policy("https://example.com/", &[("content-security-policy", header.as_str())]);
+        assert!(page.check_inline(ResourceKind::Script, "anything", Some("abc")).is_ok());
+        assert!(page.check_inline(ResourceKind::Script, "anything", Some("xyz")).is_err());
+        assert!(page.check_inline(ResourceKind::Script, "alert(1)", None).is_ok());
+        // 'unsafe-inline' не действует рядом с nonce и хэшем
+        assert!(page.check_inline(ResourceKind::Script, "alert(2)", None).is_err());
+
+        let page = policy("https://example.com/", &[("content-security-policy", "script-src 'unsafe-inline'")]);
+        assert!(page.check_inline(ResourceKind::Script, "alert(2)", None).is_ok());
+        let page = policy("https://example.com/", &[("content-security-policy", "script-src 'self'")]);
+        assert!(page.check_inline(ResourceKind::Script, "alert(2)", None).is_err());
+        assert!(!page.allows_eval());
+        assert!(policy("https://example.com/", &[]).check_inline(ResourceKind::Style, "a {}", None).is_ok());
+    }
+
+    #[test]
+    fn mixed_content_is_upgraded_or_blocked() {
+        let page = policy("https://example.com/", &[]);
+        let image = page.check_resource(ResourceKind::Image, "http://img.example.com/a.png").unwrap();
+        assert_eq!(image.as_str(), "https://img.example.com/a.png");
+        let error = page.check_resource(ResourceKind::Script, "http://example.com/a.js").unwrap_err();
+        assert_eq!(error.directive, "mixed-content");
+        // На HTTP-странице смешанного содержимого нет
+        assert!(allowed(&policy("http://example.com/", &[]), ResourceKind::Script, "http://example.com/a.js"));
+
+        let page = policy("https://example.com/", &[("content-security-policy", "block-all-mixed-content")]);
+        assert!(!allowed(&page, ResourceKind::Image, "http://img.example.com/a.png"));
+
+        let page = policy("https://example.com/", &[("content-security-policy", "upgrade-insecure-requests")]);
+        let script = page.check_resource(ResourceKind::Script, "http://example.com:80/a.js").unwrap();
+        assert_eq!(script.as_str(), "https://example.com/a.js");
+    }
+
+    #[test]
+    fn frame_ancestors_take_precedence_over_x_frame_options() {
+        let page = policy("https://example.com/", &[("x-frame-options", "DENY")]);
+        assert!(page.check_embedding("https://example.com/").is_err());
+
+        let page = policy("https://example.com/", &[("x-frame-options", "sameorigin")]);
+        assert!(page.check_embedding("https://example.com/other").is_ok());
+        assert!(page.check_embedding("https://evil.com/").is_err());
+
+        let page = policy(
+            "https://example.com/",
+            &[("x-frame-options", "DENY"), ("content-security-policy", "frame-ancestors https://partner.org")],
+        );
+        assert!(page.check_embedding("https://partner.org/page").is_ok());
+        assert!(page.check_embedding("https://example.com/").is_err());
+
+        // frame-ancestors не наследует default-src
+        let page = policy("https://example.com/", &[("content-security-policy", "default-src 'none'")]);
+        assert!(page.check_embedding("https://evil.com/").is_ok());
+    }
+
+    #[test]
+    fn referrer_policy_trims_referer() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        let page = policy("https://user:pw@example.com/path?q=1#frag", &[]);
+        assert_eq!(page.referrer_for(&url("https://example.com/next")).as_deref(), Some("https://example.com/path?q=1"));
+        assert_eq!(page.referrer_for(&url("https://other.org/")).as_deref(), Some("https://example.com/"));
+        assert_eq!(page.referrer_for(&url("http://other.org/")), None);
+
+        // Берётся последнее распознанное значение
+        let page = policy("https://example.com/path", &[("referrer-policy", "unsafe-url, origin, bogus")]);
+        assert_eq!(page.referrer_for(&url("https://example.com/next")).as_deref(), Some("https://example.com/"));
+        let page = policy("https://example.com/path", &[("referrer-policy", "no-referrer")]);
+        assert_eq!(page.referrer_for(&url("https://example.com/next")), None);
+        let page = policy("https://example.com/path", &[("referrer-policy", "no-referrer-when-downgrade")]);
+        assert_eq!(page.referrer_for(&url("https://other.org/")).as_deref(), Some("https://example.com/path"));
+        assert_eq!(page.referrer_for(&url("http://other.org/")), None);
+        // Локальные страницы не отправляют Referer
+        assert_eq!(policy("file:///tmp/a.html", &[]).referrer_for(&url("https://example.com/")), None);
+    }
+
+    #[test]
+    fn hsts_header_parsing() {
+        assert_eq!(parse_hsts_header("max-age=31536000; includeSubDomains"), Some((31536000, true)));
+        assert_eq!(parse_hsts_header("max-age=\"60\""), Some((60, false)));
+        assert_eq!(parse_hsts_header("includeSubDomains"), None);
+        assert_eq!(parse_hsts_header("max-age=abc"), None);
+    }
+
+    #[tokio::test]
+    async fn hsts_store_upgrades_known_hosts() {
+        let dir = std::env::temp_dir().join(format!("yuaibro-hsts-{}", uuid::Uuid::new_v4()));
+        let db = Arc::new(Yuaidb::new(&dir).await.unwrap()) as Arc<dyn YuaidbTrait + Send + Sync>;
+        let store = HstsStore::new(db.clone());
+        let headers = |value: &str| {
+            let mut map = HeaderMap::new();
+            map.insert("strict-transport-security", HeaderValue::from_str(value).unwrap());
+            map
+        };
+
+        store.record("https://example.com/", &headers("max-age=60; includeSubDomains")).await;
+        assert_eq!(store.upgrade_url("http://sub.example.com:80/x").await, "https://sub.example.com/x");
+        // Запись хранится в базе, а не только в кэше
+        assert!(HstsStore::new(db.clone()).is_known_host("example.com").await);
+
+        // HTTP-ответы и IP-адреса заголовок не устанавливают
+        store.record("http://other.org/", &headers("max-age=60")).await;
+        assert_eq!(store.upgrade_url("http://other.org/").await, "http://other.org/");
+        store.record("https://127.0.0.1/", &headers("max-age=60")).await;
+        assert_eq!(store.upgrade_url("http://127.0.0.1/").await, "http://127.0.0.1/");
+
+        store.record("https://example.com/", &headers("max-age=0")).await;
+        assert_eq!(store.upgrade_url("http://example.com/").await, "http://example.com/");
+        drop((store, db));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
+
+// === FILE: core\profile.rs ===
+use crate::core::interfaces::ProfileManagerTrait;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::fs;
+use async_trait::async_trait;
+pub struct ProfileManager {
+    _certs_dir: PathBuf,
+}
+#[async_trait]
+impl ProfileManagerTrait for ProfileManager {
+    async fn new(certs_dir: impl AsRef<Path> + Send) -> Result<Self> {
+        let certs_dir = certs_dir.as_ref().to_path_buf();
+        if !certs_dir.exists() {
+            fs::create_dir_all(&certs_dir)?;
+        }
+        log::info!("Менеджер профилей инициализирован с каталогом: {:?}", certs_dir);
+        Ok(Self { _certs_dir: certs_dir })
+    }
+    async fn create_profile(&self, _r_id: &str) -> Result<String> {
+        log::info!("Создание профиля для r_id: {}", _r_id);
+        Ok("".to_string())
+    }
+    async fn load_profile(&self, _profile_id: &str) -> Result<Option<Vec<u8>>> {
+        log::info!("Загрузка профиля: {}", _profile_id);
+        Ok(None)
+    }
+    async fn delete_profile(&self, _profile_id: &str) -> Result<()> {
+        log::info!("Удаление профиля: {}", _profile_id);
+        Ok(())
+    }
+}
+
+// === FILE: core\provider.rs ===
+use crate::core::installer::Installer;
+use crate::core::interfaces::{SecurityManagerTrait, WasmRuntimeTrait};
+use crate::core::runtime::InstanceId;
+use crate::core::security::from_hex;
+use crate::wasm_api::provider_api::{ProviderEnvelope, ProviderMessage, ProviderReply, ProviderRequest};
+use crate::wasm_api::sandbox::SandboxConfig;
+use anyhow::Result;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::oneshot;
+
+const PROVIDER_MANIFEST: &str = "provider.yaml";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+// Номер копии исполняемого файла провайдера в пределах процесса
+static STAGED: AtomicU64 = AtomicU64::new(0);
+
+// Связь актора с провайдером: пара (актор, контракт, имя связи) уникальна
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkDefinition {
+    pub actor_id: String,
+    pub provider_id: String,
+    pub contract_id: String,
+    pub link_name: String,
+    #[serde(default)]
+    pub values: BTreeMap<String, String>,
+}
+
+impl LinkDefinition {
+    pub fn same_slot(&self, actor_id: &str, contract_id: &str, link_name: &str) -> bool {
+        self.actor_id == actor_id && self.contract_id == contract_id && self.link_name == link_name
+    }
+}
+
+// Контракт провайдера возможности: идентификатор, контракт (wasmcloud:keyvalue и т.п.),
+// жизненный цикл связей и обработка запросов акторов
+#[async_trait]
+pub trait CapabilityProvider: Send + Sync {
+    fn id(&self) -> &str;
+    fn contract_id(&self) -> &str;
+    async fn start(&self, _config: &BTreeMap<String, String>) -> Result<()> {
+        Ok(())
+    }
+    async fn put_link(&self, link: &LinkDefinition) -> Result<()>;
+    async fn delete_link(&self, link: &LinkDefinition) -> Result<()>;
+    async fn handle(&self, request: ProviderRequest) -> Result<Vec<u8>>;
+    async fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    Process,
+    Wasm,
+}
+
+// ~/.cosmonaut/providers/<каталог>/provider.yaml
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProviderManifest {
+    pub id: String,
+    pub contract: String,
+    pub kind: ProviderKind,
+    // Исполняемый файл или .wasm относительно каталога провайдера
+    pub path: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub signature: String,
+}
+
+// Провайдер — отдельный процесс, говорящий на протоколе ProviderAPI::serve_stdio.
+// Запускается проверенная копия образа, а не файл в каталоге провайдера: тот мог быть
+// подменён между проверкой подписи и запуском
+pub struct ProcessProvider {
+    id: String,
+    contract_id: String,
+    dir: PathBuf,
+    command: PathBuf,
+    image: Arc<Vec<u8>>,
+    args: Vec<String>,
+    stdin: tokio::sync::Mutex<Option<tokio::process::ChildStdin>>,
+    child: tokio::sync::Mutex<Option<tokio::process::Child>>,
+    pending: Arc<Mutex<HashMap<u64, oneshot::Sender<ProviderReply>>>>,
+    next_id: AtomicU64,
+}
+
+impl ProcessProvider {
+    // image — байты исполняемого файла, подпись которых уже проверена
+    pub fn new(manifest: &ProviderManifest, dir: &Path, image: Vec<u8>) -> Self {
+        Self {
+            id: manifest.id.clone(),
+            contract_id: manifest.contract.clone(),
+            dir: dir.to_path_buf(),
+            command: dir.join(&manifest.path),
+            image: Arc::new(image),
+            args: manifest.args.clone(),
+            stdin: tokio::sync::Mutex::new(None),
+            child: tokio::sync::Mutex::new(None),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    // Копия образа в ~/.cosmonaut/provider_bin с правами только владельца; имя уникально,
+    // файл создаётся заново и не может оказаться чужим
+    fn stage(image: &[u8], command: &Path) -> Result<PathBuf> {
+        use std::io::Write;
+        let dir = Installer::get_cosmonaut_dir()?.join("provider_bin");
+        let mut builder = std::fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::DirBuilderExt;
+            builder.mode(0o700);
+        }
+        builder.create(&dir)?;
+        let mut path = dir.join(format!("{}-{}", std::process::id(), STAGED.fetch_add(1, Ordering::Relaxed)));
+        if let Some(extension) = command.extension() {
+            path.set_extension(extension);
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o700);
+        }
+        let mut file = options.open(&path)?;
+        file.write_all(image)?;
+        file.sync_all()?;
+        Ok(path)
+    }
+
+    async fn send(&self, message: ProviderMessage) -> Result<Vec<u8>> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().insert(id, tx);
+        let line = serde_json::to_string(&ProviderEnvelope { id, body: message })? + "\n";
+        {
+            let mut stdin = self.stdin.lock().await;
+            let stdin = stdin.as_mut().ok_or_else(|| anyhow::anyhow!("Провайдер {} не запущен", self.id))?;
+            stdin.write_all(line.as_bytes()).await?;
+            stdin.flush().await?;
+        }
+        let reply = tokio::time::timeout(REQUEST_TIMEOUT, rx).await;
+        self.pending.lock().remove(&id);
+        match reply {
+            Ok(Ok(reply)) => reply.into_result(),
+            Ok(Err(_)) => Err(anyhow::anyhow!("Провайдер {} завершился, не ответив", self.id)),
+            Err(_) => Err(anyhow::anyhow!("Провайдер {} не ответил за {:?}", self.id, REQUEST_TIMEOUT)),
+        }
+    }
+}
+
+#[async_trait]
+impl CapabilityProvider for ProcessProvider {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn contract_id(&self) -> &str {
+        &self.contract_id
+    }
+
+    async fn start(&self, config: &BTreeMap<String, String>) -> Result<()> {
+        let (image, command) = (self.image.clone(), self.command.clone());
+        let staged = tokio::task::spawn_blocking(move || Self::stage(&image, &command)).await??;
+        let spawned = tokio::process::Command::new(&staged)
+            .args(&self.args)
+            .current_dir(&self.dir)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn();
+        // Запущенному процессу файл уже не нужен
+        #[cfg(unix)]
+        if let Err(e) = tokio::fs::remove_file(&staged).await {
+            log::warn!("Не удалось удалить копию провайдера {}: {}", staged.display(), e);
+        }
+        let mut child =
+            spawned.map_err(|e| anyhow::anyhow!("Не удалось запустить провайдер {} ({:?}): {}", self.id, self.command, e))?;
+        let stdout = child.stdout.take().ok_or_else(|| anyhow::anyhow!("Нет stdout у провайдера {}", self.id))?;
+        *self.stdin.lock().await = child.stdin.take();
+        *self.child.lock().await = Some(child);
+        let pending = self.pending.clone();
+        let id = self.id.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                match serde_json::from_str::<ProviderEnvelope<ProviderReply>>(&line) {
+                    Ok(reply) => {
+                        if let Some(tx) = pending.lock().remove(&reply.id) {
+                            let _ = tx.send(reply.body);
+                        }
+                    }
+                    Err(e) => log::warn!("Провайдер {}: некорректный ответ: {}", id, e),
+                }
+            }
+            // Ожидающие запросы получают ошибку через закрытые каналы
+            pending.lock().clear();
+        });
+        self.send(ProviderMessage::Start { config: config.clone() }).await?;
+        Ok(())
+    }
+
+    async fn put_link(&self, link: &LinkDefinition) -> Result<()> {
+        self.send(ProviderMessage::PutLink { link: link.clone() }).await.map(|_| ())
+    }
+
+    async fn delete_link(&self, link: &LinkDefinition) -> Result<()> {
+        self.send(ProviderMessage::DeleteLink { link: link.clone() }).await.map(|_| ())
+    }
+
+    async fn handle(&self, request: ProviderRequest) -> Result<Vec<u8>> {
+        self.send(ProviderMessage::Request(request)).await
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        if let Err(e) = self.send(ProviderMessage::Shutdown).await {
+            log::warn!("Провайдер {} не подтвердил остановку: {}", self.id, e);
+        }
+        self.stdin.lock().await.take();
+        if let Some(mut child) = self.child.lock().await.take() {
+            if tokio::time::timeout(SHUTDOWN_GRACE, child.wait()).await.is_err() {
+                child.kill().await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Провайдер — WASM-модуль с экспортом handle, принимающим сообщения протокола
+pub struct WasmProvider {
+    id: String,
+    contract_id: String,
+    module: String,
+    runtime: Arc<dyn WasmRuntimeTrait + Send + Sync>,
+    instance: tokio::sync::Mutex<Option<InstanceId>>,
+}
+
+impl WasmProvider {
+    pub fn new(id: String, contract_id: String, module: String, runtime: Arc<dyn WasmRuntimeTrait + Send + Sync>) -> Self {
+        Self { id, contract_id, module, runtime, instance: tokio::sync::Mutex::new(None) }
+    }
+
+    async fn send(&self, message: ProviderMessage) -> Result<Vec<u8>> {
+        let instance = (*self.instance.lock().await).ok_or_else(|| anyhow::anyhow!("Провайдер {} не запущен", self.id))?;
+        let output = self.runtime.call_bytes(instance, "handle", serde_json::to_vec(&message)?).await?;
+        serde_json::from_slice::<ProviderReply>(&output)?.into_result()
+    }
+}
+
+#[async_trait]
+impl CapabilityProvider for WasmProvider {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn contract_id(&self) -> &str {
+        &self.contract_id
+    }
+
+    async fn start(&self, config: &BTreeMap<String, String>) -> Result<()> {
+        let sandbox = SandboxConfig { name: self.id.clone(), ..SandboxConfig::default() };
+        let instance = self.runtime.instantiate(&self.module, sandbox).await?;
+        *self.instance.lock().await = Some(instance);
+        self.send(ProviderMessage::Start { config: config.clone() }).await.map(|_| ())
+    }
+
+    async fn put_link(&self, link: &LinkDefinition) -> Result<()> {
+        self.send(ProviderMessage::PutLink { link: link.clone() }).await.map(|_| ())
+    }
+
+    async fn delete_link(&self, link: &LinkDefinition) -> Result<()> {
+        self.send(ProviderMessage::DeleteLink { link: link.clone() }).await.map(|_| ())
+    }
+
+    async fn handle(&self, request: ProviderRequest) -> Result<Vec<u8>> {
+        self.send(ProviderMessage::Request(request)).await
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        let result = self.send(ProviderMessage::Shutdown).await.map(|_| ());
+        if let Some(instance) = self.instance.lock().await.take() {
+            self.runtime.drop_instance(instance).await;
+        }
+        result
+    }
+}
+
+// Загрузчик провайдеров из ~/.cosmonaut/providers; каждый в своём каталоге с provider.yaml
+pub struct Provider;
+
+impl Provider {
+    pub async fn load(
+        dir: &Path,
+        runtime: Arc<dyn WasmRuntimeTrait + Send + Sync>,
+        security: Arc<dyn SecurityManagerTrait + Send + Sync>,
+    ) -> Result<Arc<dyn CapabilityProvider>> {
+        let text = tokio::fs::read_to_string(dir.join(PROVIDER_MANIFEST)).await?;
+        let manifest: ProviderManifest = serde_yaml::from_str(&text)?;
+        let path = dir.join(&manifest.path);
+        if !path.starts_with(dir) || manifest.path.contains("..") {
+            return Err(anyhow::anyhow!("path провайдера {} выходит за пределы его каталога", manifest.id));
+        }
+        // Подпись проверяется для обоих видов: запуск бинарника не менее опасен, чем WASM.
+        // Дальше используются только прочитанные байты
+        let bytes = tokio::fs::read(&path).await?;
+        security.verify_component(&bytes, &from_hex(&manifest.signature)?).await?;
+        Ok(match manifest.kind {
+            ProviderKind::Process => Arc::new(ProcessProvider::new(&manifest, dir, bytes)),
+            ProviderKind::Wasm => {
+                let module = runtime.load_module(&bytes, &from_hex(&manifest.signature)?).await?;
+                Arc::new(WasmProvider::new(manifest.id, manifest.contract, module, runtime))
+            }
+        })
+    }
+
+    // Ошибочные провайдеры пропускаются с записью в журнал
+    pub async fn load_all(
+        root: &Path,
+        runtime: Arc<dyn WasmRuntimeTrait + Send + Sync>,
+        security: Arc<dyn SecurityManagerTrait + Send + Sync>,
+    ) -> Vec<Arc<dyn CapabilityProvider>> {
+        let mut providers = Vec::new();
+        let Ok(mut entries) = tokio::fs::read_dir(root).await else {
+            return providers;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let dir = entry.path();
+            if !dir.join(PROVIDER_MANIFEST).exists() {
+                continue;
+            }
+            match Self::load(&dir, runtime.clone(), security.clone()).await {
+                Ok(provider) => {
+                    log::info!("Загружен провайдер {} ({})", provider.id(), provider.contract_id());
+                    providers.push(provider);
+                }
+                Err(e) => log::warn!("Не удалось загрузить провайдер из {:?}: {}", dir, e),
+            }
+        }
+        providers
+    }
+}
+
+// === FILE: core\reactive.rs ===
+// === FILE: core\reactive.rs ===
+use crate::dom::parser::ParsedNode;
+// NOTE: egui::Ui требует, чтобы egui был в зависимостях
+use egui::Ui; 
+use serde_json::Value as JsonValue;
+use anyhow::Result;
+use async_trait::async_trait;
+use log;
+
+// ИСПРАВЛЕНИЕ: Импортируем официальный трейт из interfaces
+use crate::core::interfaces::ReactiveCoreTrait; 
+use crate::wasm_api::signals::PageDocument;
+
+pub struct ReactiveCore;
+
+// Ключи верхнего уровня состояния становятся именованными сигналами документа,
+// поэтому WASM-компоненты страницы видят и обновляют то же состояние, что и она сама
+fn publish_state(document: &PageDocument, node: &ParsedNode, state: &JsonValue, prefix: &str) -> Result<()> {
+    let Some(fields) = state.as_object() else {
+        return Ok(());
+    };
+    for (key, value) in fields {
+        document.signals.set_named(&format!("{}{}", prefix, key), value.clone())?;
+    }
+    let bound = bind_subtree(document, node, prefix)?;
+    log::debug!("Опубликовано {} сигналов состояния, привязано {} узлов", fields.len(), bound);
+    Ok(())
+}
+
+// Элементы гидрированного поддерева с data-bind="ключ" показывают значение сигнала текстом,
+// data-bind-<атрибут>="ключ" — в атрибуте. Привязка идёт к узлу живого DOM, из которого
+// построен элемент, поэтому новое значение попадает в следующий снимок и перерисовывается
+fn bind_subtree(document: &PageDocument, node: &ParsedNode, prefix: &str) -> Result<usize> {
+    let ParsedNode::Element { attrs, children, node_id, .. } = node else {
+        return Ok(0);
+    };
+    let mut bound = 0;
+    if let Some(id) = node_id {
+        for (name, key) in attrs {
+            let target = match name.strip_prefix("data-bind") {
+                Some("") => None,
+                Some(attribute) => match attribute.strip_prefix('-') {
+                    Some(attribute) if !attribute.is_empty() => Some(attribute),
+                    _ => continue,
+                },
+                None => continue,
+            };
+            let Some(signal) = document.signals.named(&format!("{}{}", prefix, key)) else { continue };
+            match target {
+                None => document.signals.bind_text(None, signal, document.dom.clone(), *id)?,
+                Some(attribute) => document.signals.bind_attribute(None, signal, document.dom.clone(), *id, attribute)?,
+            }
+            bound += 1;
+        }
+    }
+    for child in children {
+        bound += bind_subtree(document, child, prefix)?;
+    }
+    Ok(bound)
+}
+
+// ИСПРАВЛЕННЫЙ БЛОК: Реализация new для СТРУКТУРЫ
+impl ReactiveCore {
+    // Конструктор структуры - это то, что вы вызываете в main.rs
+    // Arc::new(ReactiveCore::new().await)
+    pub async fn new() -> Self {
+        log::info!("ReactiveCore initialized.");
+        Self
+    }
+}
+
+#[async_trait]
+// Теперь ReactiveCore реализует трейт, импортированный из interfaces.rs
+impl ReactiveCoreTrait for ReactiveCore {
+    
+    // Реализация методов трейта (ваши заглушки)
+    fn create_signals(&self, document: &PageDocument, node: &ParsedNode, state: &JsonValue) -> Result<()> {
+        publish_state(document, node, state, "")
+    }
+    fn bind_angular_scopes(&self, _node: &ParsedNode) -> Result<()> {
+        Ok(())
+    }
+    fn create_stores(&self, document: &PageDocument, node: &ParsedNode, state: &JsonValue) -> Result<()> {
+        publish_state(document, node, state, "store.")
+    }
+    fn apply_svelte_hashes(&self, _node: &ParsedNode) -> Result<()> {
+        Ok(())
+    }
+    fn load_wasm_module(&self, _node: &ParsedNode) -> Result<()> {
+        Ok(())
+    }
+    // Здесь мы должны реализовать только те методы, которые есть в трейте interfaces::ReactiveCoreTrait
+    // NOTE: incremental_render, bind_angular_scopes и т.д. должны быть в трейте
+    // Если их там нет, компилятор будет ругаться. 
+    // Предполагаем, что они там есть.
+    async fn incremental_render(&self, _ui: &mut Ui, _node: &ParsedNode) -> Result<()> {
+        Ok(())
+    }
+}
+
+
+// === FILE: core\runtime.rs ===
+use crate::core::config::Config;
+use crate::core::installer::Installer;
+use crate::core::interfaces::{SecurityManagerTrait, WasmRuntimeTrait};
+use crate::core::security::to_hex;
+#[cfg(feature = "wasm")]
+use crate::core::security::{from_hex, restrict_permissions, write_private};
+#[cfg(feature = "wasm")]
+use crate::wasm_api::sandbox::{HostCall, SandboxOutput};
+#[cfg(feature = "wasm")]
+use hmac::Mac;
+use crate::wasm_api::sandbox::SandboxConfig;
+use anyhow::Result;
+#[cfg(feature = "wasm")]
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+#[cfg(feature = "wasm")]
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
+#[cfg(feature = "wasm")]
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+#[cfg(feature = "wasm")]
+use std::time::Duration;
+use async_trait::async_trait;
+
+// Лимиты одного вызова экспорта: топливо ограничивает число инструкций,
+// эпохи — реальное время (тик каждые EPOCH_TICK)
+#[cfg(feature = "wasm")]
+pub(crate) const FUEL_PER_CALL: u64 = 1_000_000_000;
+#[cfg(feature = "wasm")]
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+#[cfg(feature = "wasm")]
+pub(crate) const EPOCH_DEADLINE_TICKS: u64 = 500;
+#[cfg(feature = "wasm")]
+pub(crate) const MEMORY_LIMIT: usize = 256 * 1024 * 1024;
+// Модуль, импортирующий функции хоста, обращается к ним как cosmonaut.<имя>
+#[cfg(feature = "wasm")]
+const HOST_MODULE: &str = "cosmonaut";
+// Длиннее строка cosmonaut.log обрезается
+#[cfg(feature = "wasm")]
+const MAX_LOG_LEN: i32 = 16 * 1024;
+#[cfg(feature = "wasm")]
+const WASI_ERRNO_FAULT: i32 = 21;
+// Файл кэша: HMAC-SHA256 (digest || артефакт) ключом установки, затем артефакт
+#[cfg(feature = "wasm")]
+const CACHE_MAC_LEN: usize = 32;
+#[cfg(feature = "wasm")]
+type CacheMac = hmac::Hmac<Sha256>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WasmValue {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+}
+
+impl fmt::Display for WasmValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::I32(v) => write!(f, "{}: i32", v),
+            Self::I64(v) => write!(f, "{}: i64", v),
+            Self::F32(v) => write!(f, "{}: f32", v),
+            Self::F64(v) => write!(f, "{}: f64", v),
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl WasmValue {
+    fn to_val(self) -> wasmtime::Val {
+        match self {
+            Self::I32(v) => wasmtime::Val::I32(v),
+            Self::I64(v) => wasmtime::Val::I64(v),
+            Self::F32(v) => wasmtime::Val::F32(v.to_bits()),
+            Self::F64(v) => wasmtime::Val::F64(v.to_bits()),
+        }
+    }
+
+    fn from_val(val: &wasmtime::Val) -> Result<Self> {
+        match val {
+            wasmtime::Val::I32(v) => Ok(Self::I32(*v)),
+            wasmtime::Val::I64(v) => Ok(Self::I64(*v)),
+            wasmtime::Val::F32(bits) => Ok(Self::F32(f32::from_bits(*bits))),
+            wasmtime::Val::F64(bits) => Ok(Self::F64(f64::from_bits(*bits))),
+            other => Err(anyhow::anyhow!("Неподдерживаемый тип результата: {:?}", other.ty())),
+        }
+    }
+
+    fn matches(&self, ty: &wasmtime::ValType) -> bool {
+        matches!(
+            (self, ty),
+            (Self::I32(_), wasmtime::ValType::I32)
+                | (Self::I64(_), wasmtime::ValType::I64)
+                | (Self::F32(_), wasmtime::ValType::F32)
+                | (Self::F64(_), wasmtime::ValType::F64)
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InstanceId(pub u64);
+
+impl fmt::Display for InstanceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "wasm#{}", self.0)
+    }
+}
+
+#[cfg(feature = "wasm")]
+struct HostState {
+    module: String,
+    limits: wasmtime::StoreLimits,
+    wasi: wasmtime_wasi::preview1::WasiP1Ctx,
+    output: SandboxOutput,
+    host_call: Option<HostCall>,
+    random: bool,
+}
+
+#[cfg(feature = "wasm")]
+struct WasmInstance {
+    store: wasmtime::Store<HostState>,
+    instance: wasmtime::Instance,
+}
+
+pub struct Runtime {
+    #[cfg(feature = "wasm")]
+    engine: wasmtime::Engine,
+    #[cfg(feature = "wasm")]
+    linker: Arc<wasmtime::Linker<HostState>>,
+    #[cfg(feature = "wasm")]
+    modules: Mutex<HashMap<String, wasmtime::Module>>,
+    #[cfg(feature = "wasm")]
+    instances: Mutex<HashMap<InstanceId, Arc<Mutex<WasmInstance>>>>,
+    #[cfg(feature = "wasm")]
+    cache_key: [u8; 32],
+    cache_dir: PathBuf,
+    next_instance: AtomicU64,
+    security: Arc<dyn SecurityManagerTrait + Send + Sync>,
+}
+
+impl Runtime {
+    // Общий движок для модулей и компонентов: тот же поток эпох и те же лимиты
+    #[cfg(feature = "wasm")]
+    pub fn engine(&self) -> &wasmtime::Engine {
+        &self.engine
+    }
+
+    // Скомпилированные модули хранятся под sha256 исходных байтов
+    fn cache_path(&self, digest: &str) -> PathBuf {
+        self.cache_dir.join(format!("{}.cwasm", digest))
+    }
+
+    // Ключ подписи кэша создаётся при первом запуске и доступен только владельцу
+    #[cfg(feature = "wasm")]
+    fn load_or_create_cache_key(cache_dir: &std::path::Path) -> Result<[u8; 32]> {
+        let key_path = cache_dir.join("cache.key");
+        if key_path.exists() {
+            restrict_permissions(&key_path)?;
+            let raw = std::fs::read_to_string(&key_path)?;
+            let bytes = from_hex(raw.trim())?;
+            return <[u8; 32]>::try_from(bytes.as_slice())
+                .map_err(|_| anyhow::anyhow!("Повреждён ключ кэша модулей {}", key_path.display()));
+        }
+        let key: [u8; 32] = rand::random();
+        write_private(&key_path, to_hex(&key).as_bytes())?;
+        Ok(key)
+    }
+
+    #[cfg(feature = "wasm")]
+    fn artifact_mac(&self, digest: &str, artifact: &[u8]) -> CacheMac {
+        let mut mac = CacheMac::new_from_slice(&self.cache_key).expect("HMAC принимает ключ любой длины");
+        mac.update(digest.as_bytes());
+        mac.update(artifact);
+        mac
+    }
+
+    // Десериализация исполняет машинный код из файла, поэтому файл читается один раз
+    // и принимается только с подписью этой установки, выданной под тем же digest
+    #[cfg(feature = "wasm")]
+    fn load_cached(&self, digest: &str) -> Result<Option<wasmtime::Module>> {
+        let data = match std::fs::read(self.cache_path(digest)) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if data.len() < CACHE_MAC_LEN {
+            return Err(anyhow::anyhow!("файл кэша повреждён"));
+        }
+        let (tag, artifact) = data.split_at(CACHE_MAC_LEN);
+        self.artifact_mac(digest, artifact)
+            .verify_slice(tag)
+            .map_err(|_| anyhow::anyhow!("подпись файла кэша не совпадает"))?;
+        // Артефакт записан этим runtime и не менялся; другая версия wasmtime отвергается здесь же
+        let module = unsafe { wasmtime::Module::deserialize(&self.engine, artifact) }?;
+        Ok(Some(module))
+    }
+
+    #[cfg(feature = "wasm")]
+    fn store_cached(&self, digest: &str, module: &wasmtime::Module) -> Result<()> {
+        let artifact = module.serialize()?;
+        let mut data = self.artifact_mac(digest, &artifact).finalize().into_bytes().to_vec();
+        data.extend_from_slice(&artifact);
+        let path = self.cache_path(digest);
+        let tmp = path.with_extension("cwasm.tmp");
+        std::fs::write(&tmp, data)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "wasm")]
+    fn compile(&self, digest: &str, wasm_bytes: &[u8]) -> Result<wasmtime::Module> {
+        if let Some(module) = self.modules.lock().get(digest) {
+            return Ok(module.clone());
+        }
+        let cached = match self.load_cached(digest) {
+            Ok(module) => module,
+            Err(e) => {
+                log::warn!("Скомпилированный модуль {} будет пересобран: {}", digest, e);
+                None
+            }
+        };
+        let module = match cached {
+            Some(module) => module,
+            None => {
+                let module = wasmtime::Module::new(&self.engine, wasm_bytes)?;
+                if let Err(e) = self.store_cached(digest, &module) {
+                    log::warn!("Не удалось сохранить скомпилированный модуль {}: {}", digest, e);
+                }
+                module
+            }
+        };
+        self.modules.lock().insert(digest.to_string(), module.clone());
+        Ok(module)
+    }
+
+    #[cfg(feature = "wasm")]
+    fn host_linker(engine: &wasmtime::Engine) -> Result<wasmtime::Linker<HostState>> {
+        let mut linker = wasmtime::Linker::new(engine);
+        // Возможности WASI ограничиваются контекстом, который песочница собирает для каждого экземпляра
+        wasmtime_wasi::preview1::add_to_linker_sync(&mut linker, |state: &mut HostState| &mut state.wasi)?;
+        // random_get заменяется: без разрешения random модуль останавливается ошибкой,
+        // а не получает предсказуемые байты вместо энтропии
+        linker.allow_shadowing(true);
+        linker.func_wrap(
+            "wasi_snapshot_preview1",
+            "random_get",
+            |mut caller: wasmtime::Caller<'_, HostState>, ptr: i32, len: i32| -> Result<i32> {
+                if !caller.data().random {
+                    return Err(anyhow::anyhow!("Модулю {} не выдано разрешение random", caller.data().module));
+                }
+                let memory = guest_memory(&mut caller)?;
+                let (ptr, len) = (ptr as u32 as usize, len as u32 as usize);
+                if ptr.checked_add(len).is_none_or(|end| end > memory.data_size(&caller)) {
+                    return Ok(WASI_ERRNO_FAULT);
+                }
+                let mut buffer = vec![0u8; len];
+                rand::RngCore::fill_bytes(&mut rand::rng(), &mut buffer);
+                memory.write(&mut caller, ptr, &buffer)?;
+                Ok(0)
+            },
+        )?;
+        linker.allow_shadowing(false);
+        linker.func_wrap(
+            HOST_MODULE,
+            "log",
+            |mut caller: wasmtime::Caller<'_, HostState>, ptr: i32, len: i32| -> Result<()> {
+                let buffer = read_guest(&mut caller, ptr, len.clamp(0, MAX_LOG_LEN))?;
+                let ellipsis = if len > MAX_LOG_LEN { "…" } else { "" };
+                log::info!("[wasm {}] {}{}", caller.data().module, String::from_utf8_lossy(&buffer), ellipsis);
+                Ok(())
+            },
+        )?;
+        // Запрос к хосту в JSON; ответ записывается в память, выделенную через alloc гостя
+        linker.func_wrap(
+            HOST_MODULE,
+            "call",
+            |mut caller: wasmtime::Caller<'_, HostState>, ptr: i32, len: i32| -> Result<i64> {
+                let request = read_guest(&mut caller, ptr, len)?;
+                let host_call = caller
+                    .data()
+                    .host_call
+                    .clone()
+                    .ok_or_else(|| anyhow::anyhow!("Модулю {} не разрешены вызовы хоста", caller.data().module))?;
+                let reply = host_call(request);
+                let alloc = caller
+                    .get_export("alloc")
+                    .and_then(|export| export.into_func())
+                    .ok_or_else(|| anyhow::anyhow!("Модуль не экспортирует alloc"))?
+                    .typed::<i32, i32>(&caller)?;
+                let reply_len = i32::try_from(reply.len())?;
+                let reply_ptr = alloc.call(&mut caller, reply_len)?;
+                guest_memory(&mut caller)?.write(&mut caller, reply_ptr as u32 as usize, &reply)?;
+                Ok(pack(reply_ptr, reply_len))
+            },
+        )?;
+        linker.func_wrap(HOST_MODULE, "now_ms", || -> i64 {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as i64)
+        })?;
+        Ok(linker)
+    }
+
+    #[cfg(feature = "wasm")]
+    fn instance(&self, id: InstanceId) -> Result<Arc<Mutex<WasmInstance>>> {
+        self.instances
+            .lock()
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Экземпляр {} не найден", id))
+    }
+}
+
+#[cfg(feature = "wasm")]
+fn guest_memory(caller: &mut wasmtime::Caller<'_, HostState>) -> Result<wasmtime::Memory> {
+    caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or_else(|| anyhow::anyhow!("Модуль не экспортирует memory"))
+}
+
+#[cfg(feature = "wasm")]
+fn read_guest(caller: &mut wasmtime::Caller<'_, HostState>, ptr: i32, len: i32) -> Result<Vec<u8>> {
+    let memory = guest_memory(caller)?;
+    let (ptr, len) = (ptr as u32 as usize, len.max(0) as usize);
+    check_guest_range(memory.data_size(&*caller), ptr, len)?;
+    let mut buffer = vec![0u8; len];
+    memory.read(&*caller, ptr, &mut buffer)?;
+    Ok(buffer)
+}
+
+// Длина буфера приходит от гостя: проверяется до выделения памяти на хосте
+#[cfg(feature = "wasm")]
+fn check_guest_range(memory_size: usize, ptr: usize, len: usize) -> Result<()> {
+    match ptr.checked_add(len) {
+        Some(end) if end <= memory_size => Ok(()),
+        _ => Err(anyhow::anyhow!("Буфер гостя {}+{} выходит за пределы памяти ({} байт)", ptr, len, memory_size)),
+    }
+}
+
+// Буфер гостя передаётся одним i64: указатель в старших 32 битах, длина в младших
+#[cfg(feature = "wasm")]
+fn pack(ptr: i32, len: i32) -> i64 {
+    (i64::from(ptr as u32) << 32) | i64::from(len as u32)
+}
+
+#[cfg(feature = "wasm")]
+fn describe_trap(export: &str, e: anyhow::Error) -> anyhow::Error {
+    match e.downcast_ref::<wasmtime::Trap>() {
+        Some(wasmtime::Trap::OutOfFuel) => anyhow::anyhow!("{}: исчерпан лимит инструкций", export),
+        Some(wasmtime::Trap::Interrupt) => anyhow::anyhow!("{}: превышено время выполнения", export),
+        _ => e.context(format!("Ошибка выполнения {}", export)),
+    }
+}
+
+// Байтовый вызов: вход копируется в память, выделенную alloc(len) гостя,
+// экспорт (ptr, len) -> i64 возвращает упакованный буфер с результатом
+#[cfg(feature = "wasm")]
+fn call_bytes_export(instance: &mut WasmInstance, export: &str, input: &[u8]) -> Result<Vec<u8>> {
+    let WasmInstance { store, instance } = instance;
+    store.set_fuel(FUEL_PER_CALL)?;
+    store.set_epoch_deadline(EPOCH_DEADLINE_TICKS);
+    let alloc = instance.get_typed_func::<i32, i32>(&mut *store, "alloc")?;
+    let func = instance.get_typed_func::<(i32, i32), i64>(&mut *store, export)?;
+    let memory = instance
+        .get_memory(&mut *store, "memory")
+        .ok_or_else(|| anyhow::anyhow!("Модуль не экспортирует memory"))?;
+    let len = i32::try_from(input.len())?;
+    let outcome = alloc.call(&mut *store, len).and_then(|ptr| {
+        memory.write(&mut *store, ptr as u32 as usize, input)?;
+        func.call(&mut *store, (ptr, len))
+    });
+    store.data_mut().output.flush();
+    let packed = outcome.map_err(|e| describe_trap(export, e))?;
+    let (ptr, len) = ((packed >> 32) as u32 as usize, packed as u32 as usize);
+    check_guest_range(memory.data_size(&*store), ptr, len)?;
+    let mut output = vec![0u8; len];
+    memory.read(&*store, ptr, &mut output)?;
+    Ok(output)
+}
+
+#[cfg(feature = "wasm")]
+fn call_export(instance: &mut WasmInstance, export: &str, args: &[WasmValue]) -> Result<Vec<WasmValue>> {
+    let WasmInstance { store, instance } = instance;
+    let func = instance
+        .get_func(&mut *store, export)
+        .ok_or_else(|| anyhow::anyhow!("Экспорт {} не найден", export))?;
+    let ty = func.ty(&*store);
+    let params: Vec<_> = ty.params().collect();
+    if params.len() != args.len() || !args.iter().zip(&params).all(|(arg, ty)| arg.matches(ty)) {
+        return Err(anyhow::anyhow!(
+            "Аргументы не совпадают с сигнатурой {}: ожидалось {:?}",
+            export,
+            params
+        ));
+    }
+    let mut results = ty
+        .results()
+        .map(|ty| {
+            wasmtime::Val::default_for_ty(&ty)
+                .ok_or_else(|| anyhow::anyhow!("Неподдерживаемый тип результата {:?}", ty))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    store.set_fuel(FUEL_PER_CALL)?;
+    store.set_epoch_deadline(EPOCH_DEADLINE_TICKS);
+    let params: Vec<_> = args.iter().map(|arg| arg.to_val()).collect();
+    let outcome = func.call(&mut *store, &params, &mut results);
+    store.data_mut().output.flush();
+    outcome.map_err(|e| describe_trap(export, e))?;
+    results.iter().map(WasmValue::from_val).collect()
+}
+
+#[async_trait]
+impl WasmRuntimeTrait for Runtime {
+    async fn new(_config: &Config, security: Arc<dyn SecurityManagerTrait + Send + Sync>) -> Result<Self> {
+        let cache_dir = Installer::get_cosmonaut_dir()?.join("wasm_modules");
+        std::fs::create_dir_all(&cache_dir)?;
+        #[cfg(feature = "wasm")]
+        {
+            let mut wasmtime_config = wasmtime::Config::new();
+            wasmtime_config.wasm_multi_memory(true);
+            wasmtime_config.consume_fuel(true);
+            wasmtime_config.epoch_interruption(true);
+            wasmtime_config.wasm_component_model(true);
+            let engine = wasmtime::Engine::new(&wasmtime_config)?;
+            let ticker = engine.weak();
+            // Поток эпох завершается вместе с движком
+            std::thread::Builder::new().name("wasm-epoch".into()).spawn(move || {
+                while let Some(engine) = ticker.upgrade() {
+                    engine.increment_epoch();
+                    drop(engine);
+                    std::thread::sleep(EPOCH_TICK);
+                }
+            })?;
+            let linker = Arc::new(Self::host_linker(&engine)?);
+            let cache_key = Self::load_or_create_cache_key(&cache_dir)?;
+            log::info!("WASM runtime инициализирован с wasmtime, кэш модулей: {:?}", cache_dir);
+            Ok(Self {
+                engine,
+                linker,
+                modules: Mutex::new(HashMap::new()),
+                instances: Mutex::new(HashMap::new()),
+                cache_key,
+                cache_dir,
+                next_instance: AtomicU64::new(1),
+                security,
+            })
+        }
+        #[cfg(not(feature = "wasm"))]
+        {
+            log::info!("WASM runtime отключён");
+            Ok(Self { cache_dir, next_instance: AtomicU64::new(1), security })
+        }
+    }
+
+    async fn load_module(&self, wasm_bytes: &[u8], signature: &[u8]) -> Result<String> {
+        self.security.verify_component(wasm_bytes, signature).await?;
+        let digest = to_hex(&Sha256::digest(wasm_bytes));
+        #[cfg(feature = "wasm")]
+        {
+            self.compile(&digest, wasm_bytes)?;
+            log::info!("WASM-модуль {} проверен и скомпилирован ({} байт)", digest, wasm_bytes.len());
+            Ok(digest)
+        }
+        #[cfg(not(feature = "wasm"))]
+        {
+            let _ = self.cache_path(&digest);
+            Err(anyhow::anyhow!("WASM runtime отключён"))
+        }
+    }
+
+    async fn instantiate(&self, module: &str, sandbox: SandboxConfig) -> Result<InstanceId> {
+        #[cfg(feature = "wasm")]
+        {
+            let compiled = self
+                .modules
+                .lock()
+                .get(module)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Модуль {} не загружен", module))?;
+            let (wasi, output) = sandbox.build_wasi()?;
+            let name = if sandbox.name.is_empty() { module.chars().take(12).collect() } else { sandbox.name.clone() };
+            let state = HostState {
+                module: name,
+                limits: wasmtime::StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).build(),
+                wasi,
+                output,
+                host_call: sandbox.host_call.clone(),
+                random: sandbox.capabilities.random,
+            };
+            let mut store = wasmtime::Store::new(&self.engine, state);
+            store.limiter(|state| &mut state.limits);
+            store.set_fuel(FUEL_PER_CALL)?;
+            store.set_epoch_deadline(EPOCH_DEADLINE_TICKS);
+            store.epoch_deadline_trap();
+            let linker = self.linker.clone();
+            // Инстанцирование выполняет start-функцию модуля, поэтому вне потоков tokio
+            let instance = tokio::task::spawn_blocking(move || {
+                let instance = linker.instantiate(&mut store, &compiled);
+                store.data_mut().output.flush();
+                Ok::<_, anyhow::Error>(WasmInstance { instance: instance?, store })
+            })
+            .await??;
+            let id = InstanceId(self.next_instance.fetch_add(1, Ordering::Relaxed));
+            self.instances.lock().insert(id, Arc::new(Mutex::new(instance)));
+            log::debug!("Создан экземпляр {} модуля {}", id, module);
+            Ok(id)
+        }
+        #[cfg(not(feature = "wasm"))]
+        {
+            let _ = (module, sandbox, &self.next_instance);
+            Err(anyhow::anyhow!("WASM runtime отключён"))
+        }
+    }
+
+    async fn call(&self, instance: InstanceId, export: &str, args: Vec<WasmValue>) -> Result<Vec<WasmValue>> {
+        #[cfg(feature = "wasm")]
+        {
+            let target = self.instance(instance)?;
+            let export = export.to_string();
+            tokio::task::spawn_blocking(move || call_export(&mut target.lock(), &export, &args)).await?
+        }
+        #[cfg(not(feature = "wasm"))]
+        {
+            let _ = (instance, export, args);
+            Err(anyhow::anyhow!("WASM runtime отключён"))
+        }
+    }
+
+    async fn call_bytes(&self, instance: InstanceId, export: &str, input: Vec<u8>) -> Result<Vec<u8>> {
+        #[cfg(feature = "wasm")]
+        {
+            let target = self.instance(instance)?;
+            let export = export.to_string();
+            tokio::task::spawn_blocking(move || call_bytes_export(&mut target.lock(), &export, &input)).await?
+        }
+        #[cfg(not(feature = "wasm"))]
+        {
+            let _ = (instance, export, input);
+            Err(anyhow::anyhow!("WASM runtime отключён"))
+        }
+    }
+
+    async fn drop_instance(&self, instance: InstanceId) {
+        #[cfg(feature = "wasm")]
+        if self.instances.lock().remove(&instance).is_some() {
+            log::debug!("Экземпляр {} удалён", instance);
+        }
+        #[cfg(not(feature = "wasm"))]
+        let _ = instance;
+    }
+}
+
+// === FILE: core\scheduler.rs ===
+use crate::core::interfaces::{JsRuntimeTrait, OrchestratorTrait, SchedulerTrait, WasmRuntimeTrait};
+use crate::core::page_state::PageId;
+use crate::core::runtime::WasmValue;
+use crate::wasm_api::sandbox::SandboxConfig;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use parking_lot::Mutex;
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{oneshot, watch, Notify, Semaphore};
+
+// Приоритет задачи: UI-критичные задачи (ввод, отрисовка) не ждут очереди,
+// страничные обслуживаются раньше фоновых
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TaskPriority {
+    Background,
+    Page,
+    UiCritical,
+}
+
+impl TaskPriority {
+    pub const ALL: [TaskPriority; 3] = [TaskPriority::UiCritical, TaskPriority::Page, TaskPriority::Background];
+
+    fn index(self) -> usize {
+        match self {
+            Self::UiCritical => 0,
+            Self::Page => 1,
+            Self::Background => 2,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UiCritical => "ui",
+            Self::Page => "page",
+            Self::Background => "background",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TaskOptions {
+    pub priority: TaskPriority,
+    // Не начатая к сроку задача не запускается, начатая прерывается
+    pub deadline: Option<Instant>,
+}
+
+impl TaskOptions {
+    pub fn new(priority: TaskPriority) -> Self {
+        Self { priority, deadline: None }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.deadline = Some(Instant::now() + timeout);
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TaskId(pub u64);
+
+impl fmt::Display for TaskId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "task#{}", self.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskOutcome {
+    Completed,
+    Cancelled,
+    DeadlineExceeded,
+}
+
+impl fmt::Display for TaskOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Completed => write!(f, "задача завершена"),
+            Self::Cancelled => write!(f, "задача отменена"),
+            Self::DeadlineExceeded => write!(f, "истёк срок выполнения задачи"),
+        }
+    }
+}
+
+impl std::error::Error for TaskOutcome {}
+
+// Флаг отмены; CPU-задачи проверяют его сами, асинхронные прерываются в точке ожидания
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<(AtomicBool, Notify)>,
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.inner.0.store(true, Ordering::SeqCst);
+        self.inner.1.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.0.load(Ordering::SeqCst)
+    }
+
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.inner.1.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+pub struct TaskHandle {
+    id: TaskId,
+    priority: TaskPriority,
+    cancel: CancelToken,
+    done: watch::Receiver<Option<TaskOutcome>>,
+}
+
+impl TaskHandle {
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    pub fn priority(&self) -> TaskPriority {
+        self.priority
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.done.borrow().is_some()
+    }
+
+    pub async fn wait(mut self) -> TaskOutcome {
+        match self.done.wait_for(Option::is_some).await {
+            Ok(outcome) => (*outcome).unwrap_or(TaskOutcome::Cancelled),
+            // Планировщик уничтожен вместе с задачей
+            Err(_) => TaskOutcome::Cancelled,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PriorityStats {
+    pub queued: usize,
+    pub running: usize,
+    pub completed: u64,
+    pub cancelled: u64,
+    pub expired: u64,
+    pub total_wait: Duration,
+    pub max_wait: Duration,
+    pub total_run: Duration,
+}
+
+impl PriorityStats {
+    fn finished(&self) -> u64 {
+        self.completed + self.cancelled + self.expired
+    }
+
+    // Время от постановки в очередь до запуска
+    pub fn avg_wait(&self) -> Duration {
+        self.total_wait / self.finished().max(1) as u32
+    }
+
+    pub fn avg_run(&self) -> Duration {
+        self.total_run / self.finished().max(1) as u32
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SchedulerStats {
+    pub priorities: Vec<(TaskPriority, PriorityStats)>,
+    pub cpu_threads: usize,
+    pub async_slots: usize,
+}
+
+struct Job {
+    id: TaskId,
+    options: TaskOptions,
+    enqueued: Instant,
+    cancel: CancelToken,
+    done: watch::Sender<Option<TaskOutcome>>,
+    task: BoxFuture<'static, ()>,
+}
+
+// BinaryHeap — max-куча: выше приоритет, при равенстве — раньше поставленная
+impl Ord for Job {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        self.options.priority.cmp(&other.options.priority).then_with(|| other.id.cmp(&self.id))
+    }
+}
+
+impl PartialOrd for Job {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Job {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Job {}
+
+struct Queue {
+    jobs: Mutex<BinaryHeap<Job>>,
+    wake: Notify,
+    slots: Arc<Semaphore>,
+    stats: Mutex<[PriorityStats; 3]>,
+    next_id: AtomicU64,
+}
+
+impl Queue {
+    fn update(&self, priority: TaskPriority, f: impl FnOnce(&mut PriorityStats)) {
+        f(&mut self.stats.lock()[priority.index()]);
+    }
+
+    async fn next_job(&self) -> Job {
+        loop {
+            let notified = self.wake.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if let Some(job) = self.jobs.lock().pop() {
+                return job;
+            }
+            notified.await;
+        }
+    }
+
+    // Страничные и фоновые задачи занимают слот; слотов столько же, сколько потоков
+    async fn dispatch(self: Arc<Self>) {
+        loop {
+            let Ok(permit) = self.slots.clone().acquire_owned().await else {
+                return;
+            };
+            let job = self.next_job().await;
+            let queue = self.clone();
+            tokio::spawn(async move {
+                queue.execute(job).await;
+                drop(permit);
+            });
+        }
+    }
+
+    async fn execute(&self, job: Job) {
+        let priority = job.options.priority;
+        let wait = job.enqueued.elapsed();
+        self.update(priority, |stats| {
+            stats.queued = stats.queued.saturating_sub(1);
+            stats.total_wait += wait;
+            stats.max_wait = stats.max_wait.max(wait);
+        });
+        let expired = job.options.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        let outcome = if job.cancel.is_cancelled() {
+            TaskOutcome::Cancelled
+        } else if expired {
+            TaskOutcome::DeadlineExceeded
+        } else {
+            self.update(priority, |stats| stats.running += 1);
+            let started = Instant::now();
+            let deadline = job.options.deadline.map(tokio::time::Instant::from_std);
+            let outcome = tokio::select! {
+                _ = job.cancel.cancelled() => TaskOutcome::Cancelled,
+                completed = async {
+                    match deadline {
+                        Some(deadline) => tokio::time::timeout_at(deadline, job.task).await.is_ok(),
+                        None => {
+                            job.task.await;
+                            true
+                        }
+                    }
+                } => if completed { TaskOutcome::Completed } else { TaskOutcome::DeadlineExceeded },
+            };
+            // Отмена видна вычислению в пуле CPU и тому, кто ждёт задачу вне планировщика
+            if outcome == TaskOutcome::DeadlineExceeded {
+                job.cancel.cancel();
+            }
+            let run = started.elapsed();
+            self.update(priority, |stats| {
+                stats.running -= 1;
+                stats.total_run += run;
+            });
+            outcome
+        };
+        self.update(priority, |stats| match outcome {
+            TaskOutcome::Completed => stats.completed += 1,
+            TaskOutcome::Cancelled => stats.cancelled += 1,
+            TaskOutcome::DeadlineExceeded => stats.expired += 1,
+        });
+        if outcome != TaskOutcome::Completed {
+            log::debug!("{} ({}): {}", job.id, priority.as_str(), outcome);
+        }
+        let _ = job.done.send(Some(outcome));
+    }
+
+    fn enqueue(self: &Arc<Self>, options: TaskOptions, cancel: CancelToken, task: BoxFuture<'static, ()>) -> TaskHandle {
+        let id = TaskId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let (done, done_rx) = watch::channel(None);
+        let job = Job { id, options, enqueued: Instant::now(), cancel: cancel.clone(), done, task };
+        self.update(options.priority, |stats| stats.queued += 1);
+        if options.priority == TaskPriority::UiCritical {
+            // Без очереди и слота: отклик интерфейса не зависит от фоновой нагрузки
+            let queue = self.clone();
+            tokio::spawn(async move { queue.execute(job).await });
+        } else {
+            self.jobs.lock().push(job);
+            self.wake.notify_one();
+        }
+        TaskHandle { id, priority: options.priority, cancel, done: done_rx }
+    }
+}
+
+struct JsTask {
+    script: String,
+    options: TaskOptions,
+    reply: oneshot::Sender<Result<()>>,
+}
+
+// Очереди JS-задач по страницам. В планировщике находится только первая задача страницы,
+// следующая ставится после её завершения: порядок сохраняется, а слоты не заняты ожиданием
+type JsLanes = Arc<Mutex<HashMap<PageId, VecDeque<JsTask>>>>;
+
+fn run_js_lane(queue: Arc<Queue>, lanes: JsLanes, js_runtime: Arc<dyn JsRuntimeTrait + Send + Sync>, page: PageId) {
+    let front = lanes.lock().get(&page).and_then(VecDeque::front).map(|task| (task.script.clone(), task.options));
+    let Some((script, options)) = front else {
+        return;
+    };
+    let (tx, rx) = oneshot::channel();
+    let runtime = js_runtime.clone();
+    let handle = queue.enqueue(
+        options,
+        CancelToken::default(),
+        Box::pin(async move {
+            let _ = tx.send(runtime.execute_task(page, &script).await);
+        }),
+    );
+    tokio::spawn(async move {
+        let result = match handle.wait().await {
+            TaskOutcome::Completed => rx.await.unwrap_or_else(|_| Err(anyhow::anyhow!(TaskOutcome::Cancelled))),
+            outcome => Err(anyhow::anyhow!(outcome)),
+        };
+        let task = {
+            let mut lanes = lanes.lock();
+            let task = lanes.get_mut(&page).and_then(VecDeque::pop_front);
+            if lanes.get(&page).is_some_and(VecDeque::is_empty) {
+                lanes.remove(&page);
+            }
+            task
+        };
+        if let Some(task) = task {
+            let _ = task.reply.send(result);
+        }
+        run_js_lane(queue, lanes, js_runtime, page);
+    });
+}
+
+pub struct Scheduler {
+    wasm_runtime: Option<Arc<dyn WasmRuntimeTrait + Send + Sync>>,
+    js_runtime: Option<Arc<dyn JsRuntimeTrait + Send + Sync>>,
+    orchestrator: Option<Arc<dyn OrchestratorTrait + Send + Sync>>,
+    queue: Arc<Queue>,
+    js_lanes: JsLanes,
+    cpu_pool: Arc<rayon::ThreadPool>,
+    max_threads: usize,
+}
+
+// Типизированный результат поверх SchedulerTrait::spawn
+pub async fn run_task<T, F>(scheduler: &(dyn SchedulerTrait + Send + Sync), options: TaskOptions, task: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: std::future::Future<Output = Result<T>> + Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    let handle = scheduler.spawn(
+        options,
+        Box::pin(async move {
+            let _ = tx.send(task.await);
+        }),
+    );
+    match handle.wait().await {
+        TaskOutcome::Completed => rx.await.map_err(|_| anyhow::anyhow!(TaskOutcome::Cancelled))?,
+        outcome => Err(anyhow::anyhow!(outcome)),
+    }
+}
+
+// То же для вычислений в пуле CPU
+pub async fn run_cpu_task<T, F>(scheduler: &(dyn SchedulerTrait + Send + Sync), options: TaskOptions, task: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&CancelToken) -> Result<T> + Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    let handle = scheduler.spawn_cpu(
+        options,
+        Box::new(move |cancel| {
+            let _ = tx.send(task(cancel));
+        }),
+    );
+    match handle.wait().await {
+        TaskOutcome::Completed => rx.await.map_err(|_| anyhow::anyhow!(TaskOutcome::Cancelled))?,
+        outcome => Err(anyhow::anyhow!(outcome)),
+    }
+}
+
+#[async_trait]
+impl SchedulerTrait for Scheduler {
+    async fn new(
+        max_threads: usize,
+        wasm_runtime: Option<Arc<dyn WasmRuntimeTrait + Send + Sync>>,
+        js_runtime: Option<Arc<dyn JsRuntimeTrait + Send + Sync>>,
+        orchestrator: Option<Arc<dyn OrchestratorTrait + Send + Sync>>,
+    ) -> Result<Self> {
+        let max_threads = max_threads.max(1);
+        let cpu_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(max_threads)
+            .thread_name(|index| format!("cosmonaut-cpu-{}", index))
+            .build()?;
+        let queue = Arc::new(Queue {
+            jobs: Mutex::new(BinaryHeap::new()),
+            wake: Notify::new(),
+            slots: Arc::new(Semaphore::new(max_threads)),
+            stats: Mutex::new([PriorityStats::default(); 3]),
+            next_id: AtomicU64::new(1),
+        });
+        tokio::spawn(queue.clone().dispatch());
+        log::info!("Планировщик инициализирован: {} потоков", max_threads);
+        Ok(Self {
+            wasm_runtime,
+            js_runtime,
+            orchestrator,
+            queue,
+            js_lanes: Arc::new(Mutex::new(HashMap::new())),
+            cpu_pool: Arc::new(cpu_pool),
+            max_threads,
+        })
+    }
+
+    fn spawn(&self, options: TaskOptions, task: BoxFuture<'static, ()>) -> TaskHandle {
+        self.queue.enqueue(options, CancelToken::default(), task)
+    }
+
+    fn spawn_cpu(&self, options: TaskOptions, task: Box<dyn FnOnce(&CancelToken) + Send>) -> TaskHandle {
+        let pool = self.cpu_pool.clone();
+        // Отмена через дескриптор видна и внутри вычисления
+        let cancel = CancelToken::default();
+        let inner = cancel.clone();
+        self.queue.enqueue(
+            options,
+            cancel,
+            Box::pin(async move {
+                let (tx, rx) = oneshot::channel();
+                pool.spawn(move || {
+                    task(&inner);
+                    let _ = tx.send(());
+                });
+                let _ = rx.await;
+            }),
+        )
+    }
+
+    fn stats(&self) -> SchedulerStats {
+        let stats = *self.queue.stats.lock();
+        SchedulerStats {
+            priorities: TaskPriority::ALL.iter().map(|priority| (*priority, stats[priority.index()])).collect(),
+            cpu_threads: self.cpu_pool.current_num_threads(),
+            async_slots: self.max_threads,
+        }
+    }
+
+    // Одноразовый экземпляр: модуль проверяется, инстанцируется, вызывается и удаляется
+    async fn schedule_wasm_task(&self, module: &[u8], signature: &[u8], export: &str, args: Vec<WasmValue>) -> Result<Vec<WasmValue>> {
+        let wasm_runtime = self
+            .wasm_runtime
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("WASM runtime отключён"))?;
+        let (module, signature, export) = (module.to_vec(), signature.to_vec(), export.to_string());
+        run_task(self, TaskOptions::new(TaskPriority::Background), async move {
+            let digest = wasm_runtime.load_module(&module, &signature).await?;
+            // Без явных разрешений: WASI-возможности модуля закрыты
+            let instance = wasm_runtime.instantiate(&digest, SandboxConfig::default()).await?;
+            log::info!("Планирование WASM-задачи {} ({})", export, instance);
+            let result = wasm_runtime.call(instance, &export, args).await;
+            wasm_runtime.drop_instance(instance).await;
+            result
+        })
+        .await
+    }
+    fn schedule_js_task(&self, page: PageId, script: &str, options: TaskOptions) -> BoxFuture<'static, Result<()>> {
+        let Some(js_runtime) = self.js_runtime.clone() else {
+            return Box::pin(async { Err(anyhow::anyhow!("JS runtime отключён")) });
+        };
+        log::debug!("Планирование JS-задачи для страницы {} ({})", page, options.priority.as_str());
+        let (reply, result) = oneshot::channel();
+        let first = {
+            let mut lanes = self.js_lanes.lock();
+            let lane = lanes.entry(page).or_default();
+            lane.push_back(JsTask { script: script.to_string(), options, reply });
+            lane.len() == 1
+        };
+        if first {
+            run_js_lane(self.queue.clone(), self.js_lanes.clone(), js_runtime, page);
+        }
+        Box::pin(async move { result.await.map_err(|_| anyhow::anyhow!(TaskOutcome::Cancelled))? })
+    }
+    async fn schedule_orchestration_task(&self, actor_id: &str, export: &str, args: Vec<WasmValue>) -> Result<Vec<WasmValue>> {
+        let orchestrator = self.orchestrator.clone().ok_or_else(|| anyhow::anyhow!("Оркестрация отключена"))?;
+        let (actor_id, export) = (actor_id.to_string(), export.to_string());
+        log::debug!("Планирование задачи оркестрации {}.{}", actor_id, export);
+        run_task(self, TaskOptions::new(TaskPriority::Background), async move {
+            orchestrator.invoke_actor(&actor_id, &export, args).await
+        })
+        .await
+    }
+}
+
+// === FILE: core\security.rs ===
+use crate::core::config::Config;
+use crate::core::installer::Installer;
+use crate::core::interfaces::SecurityManagerTrait;
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use parking_lot::{Mutex, RwLock};
+use rustls::ClientConfig;
+use async_trait::async_trait;
+#[cfg(feature = "security")]
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+
+// Подпись компонента: 32 байта публичного ключа издателя + 64 байта подписи Ed25519
+pub const PUBLIC_KEY_LEN: usize = 32;
+pub const SIGNATURE_LEN: usize = 64;
+pub const SIGNED_BLOB_LEN: usize = PUBLIC_KEY_LEN + SIGNATURE_LEN;
+
+// Раскладка ~/.cosmonaut/certs:
+//   user/signing.key  - seed собственного ключа (hex)
+//   user/signing.pub  - собственный публичный ключ (hex)
+//   ca/*.pub          - доверенные издатели, по ключу (hex) на строку
+//   revoked/*         - отозванные ключи, по ключу (hex) на строку
+pub struct Security {
+    certs_dir: PathBuf,
+    #[cfg(feature = "security")]
+    signing_key: SigningKey,
+    trusted: RwLock<HashSet<[u8; PUBLIC_KEY_LEN]>>,
+    revoked: RwLock<HashSet<[u8; PUBLIC_KEY_LEN]>>,
+    // Состояние каталогов ca/ и revoked/ при последнем чтении
+    keyring_stamp: Mutex<Vec<(PathBuf, Option<SystemTime>, u64)>>,
+}
+
+impl Security {
+    async fn open(certs_dir: PathBuf) -> Result<Self> {
+        for sub in ["ca", "user", "revoked"] {
+            fs::create_dir_all(certs_dir.join(sub))?;
+        }
+        let security = Self {
+            #[cfg(feature = "security")]
+            signing_key: Self::load_or_create_signing_key(&certs_dir.join("user"))?,
+            certs_dir,
+            trusted: RwLock::new(HashSet::new()),
+            revoked: RwLock::new(HashSet::new()),
+            keyring_stamp: Mutex::new(Vec::new()),
+        };
+        security.reload_keyring().await?;
+        log::info!("Менеджер безопасности инициализирован");
+        Ok(security)
+    }
+
+    pub fn certs_dir(&self) -> &Path {
+        &self.certs_dir
+    }
+
+    // Перечитывает доверенных издателей и список отзыва, если файлы в ca/ или revoked/ изменились
+    pub async fn reload_keyring(&self) -> Result<()> {
+        let stamp = keyring_stamp(&self.certs_dir).await?;
+        if *self.keyring_stamp.lock() == stamp {
+            return Ok(());
+        }
+        let trusted = read_key_dir(&self.certs_dir.join("ca")).await?;
+        let revoked = read_key_dir(&self.certs_dir.join("revoked")).await?;
+        log::info!(
+            "Связка ключей обновлена: доверенных {}, отозванных {}",
+            trusted.len(),
+            revoked.len()
+        );
+        *self.trusted.write() = trusted;
+        *self.revoked.write() = revoked;
+        *self.keyring_stamp.lock() = stamp;
+        Ok(())
+    }
+
+    pub fn public_key_hex(&self) -> Option<String> {
+        #[cfg(feature = "security")]
+        {
+            Some(to_hex(&self.signing_key.verifying_key().to_bytes()))
+        }
+        #[cfg(not(feature = "security"))]
+        {
+            None
+        }
+    }
+
+    // Добавляет издателя в доверенные: certs/ca/<name>.pub
+    pub fn trust_publisher(&self, name: &str, public_key_hex: &str) -> Result<()> {
+        let key = parse_public_key(public_key_hex)?;
+        let path = self.certs_dir.join("ca").join(format!("{}.pub", sanitize_file_name(name)?));
+        fs::write(&path, format!("{}\n", to_hex(&key)))
+            .with_context(|| format!("Не удалось записать ключ издателя в {}", path.display()))?;
+        self.trusted.write().insert(key);
+        log::info!("Издатель {} добавлен в доверенные", name);
+        Ok(())
+    }
+
+    // Отзывает ключ: certs/revoked/<name>
+    pub fn revoke_publisher(&self, name: &str, public_key_hex: &str) -> Result<()> {
+        let key = parse_public_key(public_key_hex)?;
+        let path = self.certs_dir.join("revoked").join(sanitize_file_name(name)?);
+        fs::write(&path, format!("{}\n", to_hex(&key)))
+            .with_context(|| format!("Не удалось записать отзыв в {}", path.display()))?;
+        self.revoked.write().insert(key);
+        log::warn!("Ключ издателя {} отозван", name);
+        Ok(())
+    }
+
+    #[cfg(feature = "security")]
+    fn load_or_create_signing_key(user_dir: &Path) -> Result<SigningKey> {
+        let key_path = user_dir.join("signing.key");
+        let seed = if key_path.exists() {
+            restrict_permissions(&key_path)?;
+            let raw = fs::read_to_string(&key_path)
+                .with_context(|| format!("Не удалось прочитать ключ {}", key_path.display()))?;
+            let bytes = from_hex(raw.trim())?;
+            <[u8; 32]>::try_from(bytes.as_slice())
+                .map_err(|_| anyhow::anyhow!("Повреждён ключ подписи {}", key_path.display()))?
+        } else {
+            let seed: [u8; 32] = rand::random();
+            write_private(&key_path, to_hex(&seed).as_bytes())
+                .with_context(|| format!("Не удалось сохранить ключ {}", key_path.display()))?;
+            log::info!("Создан новый ключ подписи: {}", key_path.display());
+            seed
+        };
+        let signing_key = SigningKey::from_bytes(&seed);
+        fs::write(user_dir.join("signing.pub"), to_hex(&signing_key.verifying_key().to_bytes()))?;
+        Ok(signing_key)
+    }
+}
+
+#[async_trait]
+impl SecurityManagerTrait for Security {
+    async fn new(_config: &Config) -> Result<Self> {
+        Self::open(Installer::get_cosmonaut_dir()?.join("certs")).await
+    }
+    async fn configure_tls(&self) -> Result<Arc<ClientConfig>> {
+        log::info!("Настройка TLS");
+        let root_store = rustls::RootCertStore::empty();
+        let config = ClientConfig::builder()
+            .with_root_certificates(root_store)
+            .with_no_client_auth();
+        Ok(Arc::new(config))
+    }
+    async fn sign_component(&self, data: &[u8]) -> Result<Vec<u8>> {
+        #[cfg(feature = "security")]
+        {
+            let signature: Signature = self.signing_key.sign(data);
+            let mut blob = Vec::with_capacity(SIGNED_BLOB_LEN);
+            blob.extend_from_slice(&self.signing_key.verifying_key().to_bytes());
+            blob.extend_from_slice(&signature.to_bytes());
+            log::info!("Компонент подписан ({} байт)", data.len());
+            Ok(blob)
+        }
+        #[cfg(not(feature = "security"))]
+        {
+            let _ = data;
+            Err(anyhow::anyhow!("Подпись недоступна: модуль безопасности отключён"))
+        }
+    }
+    async fn verify_component(&self, data: &[u8], signature: &[u8]) -> Result<()> {
+        if signature.len() != SIGNED_BLOB_LEN {
+            return Err(anyhow::anyhow!(
+                "Неверный размер подписи: {} байт, ожидалось {}",
+                signature.len(),
+                SIGNED_BLOB_LEN
+            ));
+        }
+        let (key_bytes, sig_bytes) = signature.split_at(PUBLIC_KEY_LEN);
+        let key: [u8; PUBLIC_KEY_LEN] = key_bytes.try_into()?;
+
+        // Отзыв учитывается без перезапуска: связка перечитывается при изменении каталогов
+        self.reload_keyring().await?;
+        if self.revoked.read().contains(&key) {
+            return Err(anyhow::anyhow!("Ключ издателя {} отозван", to_hex(&key)));
+        }
+        #[cfg(feature = "security")]
+        {
+            let own_key = self.signing_key.verifying_key().to_bytes();
+            if key != own_key && !self.trusted.read().contains(&key) {
+                return Err(anyhow::anyhow!("Издатель {} не входит в доверенные", to_hex(&key)));
+            }
+            let verifying_key = VerifyingKey::from_bytes(&key)
+                .map_err(|e| anyhow::anyhow!("Некорректный ключ издателя: {}", e))?;
+            let sig_bytes: [u8; SIGNATURE_LEN] = sig_bytes.try_into()?;
+            verifying_key
+                .verify_strict(data, &Signature::from_bytes(&sig_bytes))
+                .map_err(|e| anyhow::anyhow!("Подпись компонента недействительна: {}", e))?;
+            log::info!("Подпись компонента проверена, издатель {}", to_hex(&key));
+            Ok(())
+        }
+        #[cfg(not(feature = "security"))]
+        {
+            let _ = (data, sig_bytes);
+            Err(anyhow::anyhow!("Проверка подписи недоступна: модуль безопасности отключён"))
+        }
+    }
+}
+
+// Читает файл компонента и его подпись (<файл>.sig) и проверяет её до любой загрузки
+pub async fn load_verified(
+    security: &(dyn SecurityManagerTrait + Send + Sync),
+    path: &Path,
+) -> Result<Vec<u8>> {
+    let data = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Не удалось прочитать компонент {}", path.display()))?;
+    let sig_path = signature_path(path);
+    let signature = tokio::fs::read(&sig_path)
+        .await
+        .with_context(|| format!("Отсутствует подпись {}", sig_path.display()))?;
+    security
+        .verify_component(&data, &signature)
+        .await
+        .with_context(|| format!("Компонент {} отклонён", path.display()))?;
+    Ok(data)
+}
+
+pub fn signature_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".sig");
+    PathBuf::from(name)
+}
+
+#[cfg(any(feature = "native-plugins", feature = "native-libs"))]
+static STAGED: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+// Копия проверенных байтов в ~/.cosmonaut/staged/<kind> для загрузчика ОС: dlopen получает
+// то, что проверено, а не файл, который могли подменить после проверки. Имя уникально,
+// поэтому повторная загрузка не вернёт уже открытую библиотеку
+#[cfg(any(feature = "native-plugins", feature = "native-libs"))]
+pub(crate) fn stage_verified(kind: &str, source: &Path, data: &[u8]) -> Result<PathBuf> {
+    let dir = Installer::get_cosmonaut_dir()?.join("staged").join(kind);
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(&dir)?;
+    let mut path = dir.join(format!(
+        "{}-{}",
+        std::process::id(),
+        STAGED.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+    ));
+    if let Some(extension) = source.extension() {
+        path.set_extension(extension);
+    }
+    write_private(&path, data)?;
+    Ok(path)
+}
+
+// Закрытый ключ доступен только владельцу
+pub(crate) fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    use std::io::Write;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(data)?;
+    Ok(())
+}
+
+pub(crate) fn restrict_permissions(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(path)?.permissions().mode();
+        if mode & 0o077 != 0 {
+            log::warn!("Ключ {} был доступен другим пользователям, права сброшены до 0600", path.display());
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+// Имя, время изменения и размер каждого файла связки ключей
+async fn keyring_stamp(certs_dir: &Path) -> Result<Vec<(PathBuf, Option<SystemTime>, u64)>> {
+    let mut stamp = Vec::new();
+    for sub in ["ca", "revoked"] {
+        let Ok(mut entries) = tokio::fs::read_dir(certs_dir.join(sub)).await else { continue };
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            stamp.push((entry.path(), metadata.modified().ok(), metadata.len()));
+        }
+    }
+    stamp.sort();
+    Ok(stamp)
+}
+
+async fn read_key_dir(dir: &Path) -> Result<HashSet<[u8; PUBLIC_KEY_LEN]>> {
+    let mut keys = HashSet::new();
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else { return Ok(keys) };
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if !entry.metadata().await?.is_file() {
+            continue;
+        }
+        let content = tokio::fs::read_to_string(&path).await?;
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse_public_key(line) {
+                Ok(key) => {
+                    keys.insert(key);
+                }
+                Err(e) => log::warn!("Пропуск ключа в {}: {}", path.display(), e),
+            }
+        }
+    }
+    Ok(keys)
+}
+
+fn parse_public_key(hex: &str) -> Result<[u8; PUBLIC_KEY_LEN]> {
+    let bytes = from_hex(hex.trim())?;
+    <[u8; PUBLIC_KEY_LEN]>::try_from(bytes.as_slice())
+        .map_err(|_| anyhow::anyhow!("Ключ должен быть {} байта, получено {}", PUBLIC_KEY_LEN, bytes.len()))
+}
+
+// Имя файла в ca/ или revoked/: «.» и «..» не должны выводить запись из каталога
+fn sanitize_file_name(name: &str) -> Result<String> {
+    let sanitized: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect();
+    if sanitized.chars().all(|c| c == '.') {
+        return Err(anyhow::anyhow!("Недопустимое имя издателя «{}»", name));
+    }
+    Ok(sanitized)
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn from_hex(hex: &str) -> Result<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return Err(anyhow::anyhow!("Некорректная hex-строка"));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|e| anyhow::anyhow!("Некорректный hex: {}", e))
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "security"))]
+mod tests {
+    use super::*;
+
+    struct TempCerts(PathBuf);
+
+    impl TempCerts {
+        fn new() -> Self {
+            Self(std::env::temp_dir().join(format!("yuaibro-certs-{}", uuid::Uuid::new_v4())))
+        }
+    }
+
+    impl Drop for TempCerts {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn signature_round_trip() {
+        let dir = TempCerts::new();
+        let security = Security::open(dir.0.clone()).await.unwrap();
+        let blob = security.sign_component(b"component").await.unwrap();
+        assert_eq!(blob.len(), SIGNED_BLOB_LEN);
+        security.verify_component(b"component", &blob).await.unwrap();
+        // Ключ переживает перезапуск
+        let reopened = Security::open(dir.0.clone()).await.unwrap();
+        assert_eq!(reopened.public_key_hex(), security.public_key_hex());
+        reopened.verify_component(b"component", &blob).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn tampered_data_and_signature_are_rejected() {
+        let dir = TempCerts::new();
+        let security = Security::open(dir.0.clone()).await.unwrap();
+        let mut blob = security.sign_component(b"component").await.unwrap();
+        assert!(security.verify_component(b"componenT", &blob).await.is_err());
+        assert!(security.verify_component(b"component", &blob[..SIGNED_BLOB_LEN - 1]).await.is_err());
+        blob[SIGNED_BLOB_LEN - 1] ^= 1;
+        assert!(security.verify_component(b"component", &blob).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn foreign_key_needs_trust_and_loses_it_on_revocation() {
+        let (dir, publisher_dir) = (TempCerts::new(), TempCerts::new());
+        let security = Security::open(dir.0.clone()).await.unwrap();
+        let publisher = Security::open(publisher_dir.0.clone()).await.unwrap();
+        let blob = publisher.sign_component(b"plugin").await.unwrap();
+        let error = security.verify_component(b"plugin", &blob).await.unwrap_err();
+        assert!(error.to_string().contains("не входит в доверенные"), "{}", error);
+
+        let key = publisher.public_key_hex().unwrap();
+        security.trust_publisher("publisher", &key).unwrap();
+        security.verify_component(b"plugin", &blob).await.unwrap();
+
+        security.revoke_publisher("publisher", &key).unwrap();
+        let error = security.verify_component(b"plugin", &blob).await.unwrap_err();
+        assert!(error.to_string().contains("отозван"), "{}", error);
+    }
+
+    // Файл, положенный в revoked/ в обход API, учитывается без перезапуска
+    #[tokio::test]
+    async fn keyring_reloads_revocations_from_disk() {
+        let dir = TempCerts::new();
+        let security = Security::open(dir.0.clone()).await.unwrap();
+        let blob = security.sign_component(b"component").await.unwrap();
+        security.verify_component(b"component", &blob).await.unwrap();
+        let key = security.public_key_hex().unwrap();
+        fs::write(dir.0.join("revoked").join("own"), format!("# отозван вручную\n{}\n", key)).unwrap();
+        assert!(security.verify_component(b"component", &blob).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn publisher_names_cannot_leave_the_keyring() {
+        let dir = TempCerts::new();
+        let security = Security::open(dir.0.clone()).await.unwrap();
+        let key = security.public_key_hex().unwrap();
+        for name in ["", ".", "..", "..."] {
+            assert!(security.revoke_publisher(name, &key).is_err(), "{:?}", name);
+            assert!(security.trust_publisher(name, &key).is_err(), "{:?}", name);
+        }
+        assert_eq!(sanitize_file_name("../evil").unwrap(), ".._evil");
+        security.revoke_publisher("a.b", &key).unwrap();
+        assert!(dir.0.join("revoked").join("a.b").is_file());
+    }
+}
+
+// === FILE: core\service_worker.rs ===
+use crate::core::interfaces::{NetworkTrait, ServiceWorkerTrait, YuaidbTrait};
+use anyhow::Result;
+use std::sync::Arc;
+use async_trait::async_trait;
+pub struct ServiceWorker;
+#[async_trait]
+impl ServiceWorkerTrait for ServiceWorker {
+    async fn new(
+        _network: Arc<dyn NetworkTrait + Send + Sync>,
+        _db: Arc<dyn YuaidbTrait + Send + Sync>
+    ) -> Result<Self> {
+        log::info!("Сервис-воркер инициализирован");
+        Ok(Self)
+    }
+    async fn register(&self, _script_url: &str) -> Result<()> {
+        log::info!("Регистрация сервис-воркера для URL: {}", _script_url);
+        Ok(())
+    }
+    async fn cache_resource(&self, _url: &str, _data: &[u8]) -> Result<()> {
+        log::info!("Кэширование ресурса: {}", _url);
+        Ok(())
+    }
+    async fn handle_push(&self, _payload: &str) -> Result<()> {
+        log::info!("Обработка push-уведомления: {}", _payload);
+        Ok(())
+    }
+}
+
+// === FILE: core\session.rs ===
+use crate::core::interfaces::{SessionManagerTrait, YuaidbTrait};
+use anyhow::Result;
+use std::sync::Arc;
+use async_trait::async_trait;
+pub struct SessionManager;
+#[async_trait]
+impl SessionManagerTrait for SessionManager {
+    async fn new(_db: Arc<dyn YuaidbTrait + Send + Sync>) -> Result<Self> {
+        log::info!("Менеджер сессий инициализирован");
+        Ok(Self)
+    }
+    async fn create_session(&self, _r_id: &str) -> Result<String> {
+        log::info!("Создание сессии для r_id: {}", _r_id);
+        Ok("".to_string())
+    }
+    async fn get_session(&self, _session_id: &str) -> Result<Option<String>> {
+        log::info!("Получение сессии: {}", _session_id);
+        Ok(None)
+    }
+    async fn delete_session(&self, _session_id: &str) -> Result<()> {
+        log::info!("Удаление сессии: {}", _session_id);
+        Ok(())
+    }
+}
+
+// === FILE: core\ssh.rs ===
+use ssh2::Session;
+use std::net::TcpStream;
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+use std::io::{Read, Write};
+use tokio::sync::mpsc::UnboundedReceiver;
+pub struct SshClient {
+    session: Arc<Mutex<Session>>,
+}
+impl SshClient {
+    pub fn connect(user: &str, host: &str) -> Result<Self> {
+        let tcp = TcpStream::connect(host)?;
+        let mut session = Session::new()?;
+        session.set_tcp_stream(tcp);
+        session.handshake()?;
+        session.userauth_agent(user)?;
+        Ok(Self {
+            session: Arc::new(Mutex::new(session)),
+        })
+    }
+    pub async fn run_command(&self, cmd: &str) -> Result<String> {
+        let cmd = cmd.to_string(); // скопировали команду для 'static
+        let session = self.session.clone();
+        tokio::task::spawn_blocking(move || -> Result<String> {
+            let sess = session.lock().unwrap();
+            let mut channel = sess.channel_session()?;
+            channel.exec(&cmd)?;
+            let mut output = String::new();
+            channel.read_to_string(&mut output)?;
+            channel.wait_close()?;
+            Ok(output)
+        }).await?
+    }
+    pub async fn interactive_shell(&self, mut rx: UnboundedReceiver<String>) -> Result<String> {
+        let session = self.session.clone();
+        tokio::task::spawn_blocking(move || -> Result<String> {
+            let sess = session.lock().unwrap();
+            let mut channel = sess.channel_session()?;
+            channel.request_pty("xterm", None, None)?;
+            channel.shell()?;
+            let mut buffer = Vec::new();
+            let mut tmp = [0u8; 1024];
+            loop {
+                if let Ok(cmd) = rx.try_recv() {
+                    channel.write_all(cmd.as_bytes())?;
+                    channel.write_all(b"\n")?;
+                }
+                let n = channel.read(&mut tmp)?;
+                if n == 0 { break; }
+                buffer.extend_from_slice(&tmp[..n]);
+            }
+            channel.wait_close()?;
+            Ok(String::from_utf8_lossy(&buffer).to_string())
+        }).await?
+    }
+}
+
+// === FILE: core\wasm_manifest.rs ===
+use crate::core::config::Config;
+use crate::core::installer::Installer;
+use crate::core::interfaces::{SecurityManagerTrait, WasmManifestTrait};
+use crate::core::oci::OciPuller;
+use crate::core::security::{from_hex, load_verified, signature_path};
+use crate::wasm_api::bindings::ComponentGrants;
+use crate::wasm_api::signals::PageDocument;
+use crate::wasm_api::sandbox::{Capabilities, FsAccess, NetworkGrant};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde_yaml::Value;
+
+const MANIFEST_FIELDS: &[&str] = &["name", "version", "entry", "signature", "capabilities", "dependencies"];
+const CAPABILITY_FIELDS: &[&str] = &["stdio", "filesystem", "env", "args", "clock", "random", "network", "dom", "fetch", "storage"];
+const DEPENDENCY_FIELDS: &[&str] = &["name", "version"];
+
+// Пример ~/.cosmonaut/wasm_manifests/counter.yaml:
+//   name: counter
+//   version: 1.0.0
+//   entry: counter.wasm
+//   signature: 9f86d0…
+//   capabilities: { stdio: true, filesystem: read-only, network: ["10.0.0.2:8080"], fetch: ["https://api.example.com"] }
+//   dependencies: [{ name: ui-kit, version: ^1.2 }]
+// signature подписывает entry, а counter.yaml.sig — сам файл манифеста вместе с возможностями
+// (формат подписи тот же, что у компонентов); без неё манифест не загружается
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ComponentManifest {
+    pub name: String,
+    pub version: String,
+    pub entry: String,
+    pub signature: String,
+    #[serde(default)]
+    pub capabilities: DeclaredCapabilities,
+    #[serde(default)]
+    pub dependencies: Vec<Dependency>,
+    // Каталог файла манифеста; entry разрешается относительно него
+    #[serde(skip)]
+    pub base_dir: PathBuf,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct DeclaredCapabilities {
+    #[serde(default)]
+    pub stdio: bool,
+    #[serde(default)]
+    pub filesystem: DeclaredFs,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub clock: bool,
+    #[serde(default)]
+    pub random: bool,
+    #[serde(default)]
+    pub network: Vec<String>,
+    #[serde(default)]
+    pub dom: bool,
+    // Источники вида https://api.example.com
+    #[serde(default)]
+    pub fetch: Vec<String>,
+    #[serde(default)]
+    pub storage: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeclaredFs {
+    #[default]
+    None,
+    ReadOnly,
+    ReadWrite,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Dependency {
+    pub name: String,
+    pub version: String,
+}
+
+impl ComponentManifest {
+    pub fn entry_path(&self) -> PathBuf {
+        self.base_dir.join(&self.entry)
+    }
+
+    pub fn signature_bytes(&self) -> Result<Vec<u8>> {
+        from_hex(&self.signature)
+    }
+}
+
+impl DeclaredCapabilities {
+    // Манифест уже прошёл валидацию, поэтому адреса разбираются без ошибок
+    pub fn sandbox(&self) -> Capabilities {
+        Capabilities {
+            stdio: self.stdio,
+            filesystem: match self.filesystem {
+                DeclaredFs::None => FsAccess::None,
+                DeclaredFs::ReadOnly => FsAccess::ReadOnly,
+                DeclaredFs::ReadWrite => FsAccess::ReadWrite,
+            },
+            env: self.env.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            args: self.args.clone(),
+            clock: self.clock,
+            random: self.random,
+            network: self.network.iter().filter_map(|entry| parse_network_grant(entry)).collect(),
+        }
+    }
+
+    // DOM и сигналы страницы выдаются только если DOM объявлен и у вызывающего есть документ
+    pub fn component_grants(&self, document: Option<PageDocument>) -> ComponentGrants {
+        ComponentGrants {
+            document: document.filter(|_| self.dom),
+            fetch: self.fetch.iter().filter_map(|entry| parse_fetch_origin(entry)).collect(),
+            storage: self.storage,
+        }
+    }
+}
+
+// Только источник: схема http(s), хост и необязательный порт, без пути и запроса
+fn parse_fetch_origin(entry: &str) -> Option<url::Origin> {
+    let url = url::Url::parse(entry).ok()?;
+    let bare = matches!(url.scheme(), "http" | "https")
+        && url.username().is_empty()
+        && url.password().is_none()
+        && url.path() == "/"
+        && url.query().is_none()
+        && url.fragment().is_none();
+    bare.then(|| url.origin())
+}
+
+fn parse_network_grant(entry: &str) -> Option<NetworkGrant> {
+    if let Ok(addr) = entry.parse::<SocketAddr>() {
+        return Some(NetworkGrant { addr: addr.ip(), port: Some(addr.port()) });
+    }
+    entry.parse::<IpAddr>().ok().map(|addr| NetworkGrant { addr, port: None })
+}
+
+// Ошибка с путём до поля, например capabilities.network[1]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ManifestError {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ManifestErrors(pub Vec<ManifestError>);
+
+impl fmt::Display for ManifestErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Манифест содержит ошибки ({}):", self.0.len())?;
+        for error in &self.0 {
+            write!(f, "\n  {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ManifestErrors {}
+
+struct Validator {
+    errors: Vec<ManifestError>,
+}
+
+impl Validator {
+    fn error(&mut self, path: &str, message: impl Into<String>) {
+        self.errors.push(ManifestError { path: path.to_string(), message: message.into() });
+    }
+
+    fn mapping<'a>(&mut self, path: &str, value: &'a Value, fields: &[&str]) -> Option<&'a serde_yaml::Mapping> {
+        let Some(mapping) = value.as_mapping() else {
+            self.error(path, "ожидался объект");
+            return None;
+        };
+        for key in mapping.keys() {
+            match key.as_str() {
+                Some(key) if fields.contains(&key) => {}
+                Some(key) => self.error(&join(path, key), "неизвестное поле"),
+                None => self.error(path, "ключи должны быть строками"),
+            }
+        }
+        Some(mapping)
+    }
+
+    fn string<'a>(&mut self, path: &str, value: Option<&'a Value>, required: bool) -> Option<&'a str> {
+        match value {
+            None if required => {
+                self.error(path, "обязательное поле отсутствует");
+                None
+            }
+            None => None,
+            Some(Value::String(text)) => Some(text),
+            Some(_) => {
+                self.error(path, "ожидалась строка");
+                None
+            }
+        }
+    }
+
+    fn boolean(&mut self, path: &str, value: Option<&Value>) {
+        if let Some(value) = value {
+            if !value.is_bool() {
+                self.error(path, "ожидалось true или false");
+            }
+        }
+    }
+
+    fn sequence<'a>(&mut self, path: &str, value: Option<&'a Value>) -> &'a [Value] {
+        match value {
+            None => &[],
+            Some(Value::Sequence(items)) => items,
+            Some(_) => {
+                self.error(path, "ожидался список");
+                &[]
+            }
+        }
+    }
+
+    fn name(&mut self, path: &str, name: &str) {
+        if !is_valid_name(name) {
+            self.error(path, "имя: строчные латинские буквы, цифры, '-' и '_', не длиннее 64 символов");
+        }
+    }
+
+    fn manifest(&mut self, value: &Value) {
+        let Some(root) = self.mapping("", value, MANIFEST_FIELDS) else { return };
+        if let Some(name) = self.string("name", root.get("name"), true) {
+            self.name("name", name);
+        }
+        if let Some(version) = self.string("version", root.get("version"), true) {
+            if !is_semver(version) {
+                self.error("version", format!("«{}» не является версией вида 1.2.3", version));
+            }
+        }
+        if let Some(entry) = self.string("entry", root.get("entry"), true) {
+            let path = Path::new(entry);
+            if entry.is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+                self.error("entry", "ожидался относительный путь внутри каталога манифеста");
+            } else if path.extension().and_then(|ext| ext.to_str()) != Some("wasm") {
+                self.error("entry", "ожидался файл .wasm");
+            }
+        }
+        if let Some(signature) = self.string("signature", root.get("signature"), true) {
+            if signature.is_empty() || from_hex(signature).is_err() {
+                self.error("signature", "ожидалась подпись в hex");
+            }
+        }
+        if let Some(capabilities) = root.get("capabilities") {
+            self.capabilities(capabilities);
+        }
+        for (index, dependency) in self.sequence("dependencies", root.get("dependencies")).iter().enumerate() {
+            let path = format!("dependencies[{}]", index);
+            let Some(fields) = self.mapping(&path, dependency, DEPENDENCY_FIELDS) else { continue };
+            if let Some(name) = self.string(&join(&path, "name"), fields.get("name"), true) {
+                self.name(&join(&path, "name"), name);
+            }
+            if let Some(requirement) = self.string(&join(&path, "version"), fields.get("version"), true) {
+                if !is_version_requirement(requirement) {
+                    self.error(&join(&path, "version"), format!("«{}» не является требованием к версии", requirement));
+                }
+            }
+        }
+    }
+
+    fn capabilities(&mut self, value: &Value) {
+        let Some(caps) = self.mapping("capabilities", value, CAPABILITY_FIELDS) else { return };
+        for flag in ["stdio", "clock", "random", "dom", "storage"] {
+            self.boolean(&join("capabilities", flag), caps.get(flag));
+        }
+        if let Some(mode) = self.string("capabilities.filesystem", caps.get("filesystem"), false) {
+            if !matches!(mode, "none" | "read-only" | "read-write") {
+                self.error("capabilities.filesystem", "допустимо none, read-only или read-write");
+            }
+        }
+        match caps.get("env") {
+            None => {}
+            Some(Value::Mapping(env)) => {
+                for (key, value) in env {
+                    match key.as_str() {
+                        Some(key) if !value.is_string() => self.error(&join("capabilities.env", key), "ожидалась строка"),
+                        Some(_) => {}
+                        None => self.error("capabilities.env", "имена переменных должны быть строками"),
+                    }
+                }
+            }
+            Some(_) => self.error("capabilities.env", "ожидался объект"),
+        }
+        for (index, arg) in self.sequence("capabilities.args", caps.get("args")).iter().enumerate() {
+            if !arg.is_string() {
+                self.error(&format!("capabilities.args[{}]", index), "ожидалась строка");
+            }
+        }
+        for (index, entry) in self.sequence("capabilities.network", caps.get("network")).iter().enumerate() {
+            let path = format!("capabilities.network[{}]", index);
+            match entry.as_str() {
+                Some(entry) if parse_network_grant(entry).is_some() => {}
+                Some(entry) => self.error(&path, format!("«{}» не является IP-адресом или адресом с портом", entry)),
+                None => self.error(&path, "ожидалась строка"),
+            }
+        }
+        for (index, entry) in self.sequence("capabilities.fetch", caps.get("fetch")).iter().enumerate() {
+            let path = format!("capabilities.fetch[{}]", index);
+            match entry.as_str() {
+                Some(entry) if parse_fetch_origin(entry).is_some() => {}
+                Some(entry) => self.error(&path, format!("«{}» не является источником вида https://host[:port]", entry)),
+                None => self.error(&path, "ожидалась строка"),
+            }
+        }
+    }
+}
+
+// Имя компонента одновременно имя файла в wasm_manifests, поэтому разделители путей и точки запрещены
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn is_semver(version: &str) -> bool {
+    let core = version.split(['-', '+']).next().unwrap_or_default();
+    let parts: Vec<_> = core.split('.').collect();
+    parts.len() == 3 && parts.iter().all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+}
+
+// ^1.2, ~1.2.3, >=1.0, =2.0.0, 1.x, *
+fn is_version_requirement(requirement: &str) -> bool {
+    let version = requirement.trim_start_matches(['^', '~', '>', '<', '=']).trim();
+    version == "*"
+        || (!version.is_empty()
+            && version.split('.').count() <= 3
+            && version.split('.').all(|part| part == "x" || part == "*" || (!part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))))
+}
+
+pub struct WasmManifest {
+    manifests_dir: PathBuf,
+    oci: OciPuller,
+    security: Arc<dyn SecurityManagerTrait + Send + Sync>,
+}
+
+impl WasmManifest {
+    fn validate_value(value: &Value) -> std::result::Result<(), ManifestErrors> {
+        let mut validator = Validator { errors: Vec::new() };
+        validator.manifest(value);
+        if validator.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ManifestErrors(validator.errors))
+        }
+    }
+}
+
+#[async_trait]
+impl WasmManifestTrait for WasmManifest {
+    async fn new(config: &Config, security: Arc<dyn SecurityManagerTrait + Send + Sync>) -> Result<Self> {
+        let cosmonaut_dir = Installer::get_cosmonaut_dir()?;
+        let manifests_dir = cosmonaut_dir.join("wasm_manifests");
+        let oci = OciPuller::new(config, cosmonaut_dir.join("oci"))?;
+        log::info!("Менеджер WASM-манифестов инициализирован: {:?}", manifests_dir);
+        Ok(Self { manifests_dir, oci, security })
+    }
+    async fn load_yaml(&self, path: &Path) -> Result<Value> {
+        log::info!("Загрузка WASM-манифеста из {:?}", path);
+        let text = tokio::fs::read_to_string(path).await?;
+        serde_yaml::from_str(&text).map_err(|e| anyhow::anyhow!("{:?}: {}", path, e))
+    }
+    // Артефакт устанавливается рядом с локальными манифестами: <name>.yaml и <name>.wasm,
+    // после чего он доступен через load(name). Манифест сохраняется байт в байт, поэтому
+    // его entry уже должен быть <name>.wasm
+    async fn load_oci(&self, oci_ref: &str) -> Result<Value> {
+        log::info!("Загрузка WASM-манифеста из OCI: {}", oci_ref);
+        let artifact = self.oci.pull(oci_ref).await?;
+        let yaml = artifact
+            .manifest
+            .ok_or_else(|| anyhow::anyhow!("Артефакт {} не содержит манифеста yuaibro", oci_ref))?;
+        // Реестру не доверяем: манифест и компонент должны быть подписаны доверенным издателем
+        let manifest_signature = artifact
+            .manifest_signature
+            .ok_or_else(|| anyhow::anyhow!("Артефакт {} не содержит подписи манифеста", oci_ref))?;
+        self.security
+            .verify_component(&yaml, &manifest_signature)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}: манифест отклонён: {}", oci_ref, e))?;
+        let value: Value = serde_yaml::from_slice(&yaml).map_err(|e| anyhow::anyhow!("{}: {}", oci_ref, e))?;
+        Self::validate_value(&value).map_err(|e| anyhow::anyhow!("{}: {}", oci_ref, e))?;
+        let manifest: ComponentManifest = serde_yaml::from_value(value.clone())?;
+        self.security
+            .verify_component(&artifact.wasm, &manifest.signature_bytes()?)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}: компонент отклонён: {}", oci_ref, e))?;
+        let name = manifest.name;
+        let entry = format!("{}.wasm", name);
+        if manifest.entry != entry {
+            return Err(anyhow::anyhow!("{}: entry: ожидалось «{}»", oci_ref, entry));
+        }
+        // Манифест пишется последним: до этого load(name) видит прежнюю установку или ничего
+        let yaml_path = self.manifests_dir.join(format!("{}.yaml", name));
+        tokio::fs::create_dir_all(&self.manifests_dir).await?;
+        tokio::fs::write(self.manifests_dir.join(&entry), &artifact.wasm).await?;
+        tokio::fs::write(signature_path(&yaml_path), &manifest_signature).await?;
+        tokio::fs::write(&yaml_path, &yaml).await?;
+        log::info!("Компонент {} установлен из {} ({})", name, oci_ref, artifact.digest);
+        Ok(value)
+    }
+    async fn validate(&self, manifest: &Value) -> Result<()> {
+        Self::validate_value(manifest)?;
+        Ok(())
+    }
+    async fn load(&self, name: &str) -> Result<ComponentManifest> {
+        if !is_valid_name(name) {
+            return Err(anyhow::anyhow!("Недопустимое имя компонента: {:?}", name));
+        }
+        let path = self.manifests_dir.join(format!("{}.yaml", name));
+        // Разбирается ровно тот текст, подпись которого проверена
+        log::info!("Загрузка WASM-манифеста из {:?}", path);
+        let text = load_verified(self.security.as_ref(), &path).await?;
+        let value: Value = serde_yaml::from_slice(&text).map_err(|e| anyhow::anyhow!("{:?}: {}", path, e))?;
+        Self::validate_value(&value).map_err(|e| anyhow::anyhow!("{:?}: {}", path, e))?;
+        let mut manifest: ComponentManifest = serde_yaml::from_value(value)?;
+        if manifest.name != name {
+            return Err(anyhow::anyhow!("{:?}: name: ожидалось «{}», указано «{}»", path, name, manifest.name));
+        }
+        manifest.base_dir = self.manifests_dir.clone();
+        Ok(manifest)
+    }
+    // Каталог создаётся при первой установке; до неё список пуст
+    async fn list(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        let mut entries = match tokio::fs::read_dir(&self.manifests_dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(names),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) == Some("yaml") {
+                if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()).filter(|stem| is_valid_name(stem)) {
+                    names.push(stem.to_string());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+}
+
+// === FILE: core\web_storage.rs ===
+use crate::core::origin::Origin;
+use crate::core::page_state::PageId;
+use crate::core::yuaidb::{storage_size, OriginStore, QuotaExceeded, DEFAULT_ORIGIN_QUOTA};
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageKind {
+    Local,
+    Session,
+}
+
+impl StorageKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Local => "localStorage",
+            Self::Session => "sessionStorage",
+        }
+    }
+}
+
+// key = None означает clear(); url заполняет движок по странице-источнику
+#[derive(Clone, Debug)]
+pub struct StorageChange {
+    pub kind: StorageKind,
+    pub partition: String,
+    pub key: Option<String>,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub url: String,
+    pub source: Option<PageId>,
+}
+
+// Содержимое хранилища одного источника. Чтение и запись синхронны, как того требует
+// Web Storage API; localStorage сохраняется движком в раздел OriginStore по StorageChange
+// и делит с ним квоту источника
+pub struct StorageArea {
+    kind: StorageKind,
+    partition: String,
+    items: Mutex<Items>,
+    store: Option<Arc<OriginStore>>,
+}
+
+// Занятый объём ведётся вместе с записями, чтобы setItem не пересчитывал всю область
+#[derive(Default)]
+struct Items {
+    map: BTreeMap<String, String>,
+    usage: usize,
+}
+
+impl Items {
+    fn insert(&mut self, key: String, value: String) -> Option<String> {
+        self.usage += storage_size(&key, &value);
+        let old = self.map.insert(key.clone(), value)?;
+        self.usage -= storage_size(&key, &old);
+        Some(old)
+    }
+
+    fn remove(&mut self, key: &str) -> Option<String> {
+        let old = self.map.remove(key)?;
+        self.usage -= storage_size(key, &old);
+        Some(old)
+    }
+}
+
+impl StorageArea {
+    fn new(kind: StorageKind, partition: String, entries: BTreeMap<String, String>, store: Option<Arc<OriginStore>>) -> Self {
+        let mut items = Items::default();
+        for (key, value) in entries {
+            items.insert(key, value);
+        }
+        Self { kind, partition, items: Mutex::new(items), store }
+    }
+
+    pub fn kind(&self) -> StorageKind {
+        self.kind
+    }
+
+    pub fn partition(&self) -> &str {
+        &self.partition
+    }
+
+    pub fn quota(&self) -> usize {
+        self.store.as_ref().map_or(DEFAULT_ORIGIN_QUOTA, |store| store.quota())
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.lock().map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.lock().map.is_empty()
+    }
+
+    pub fn key(&self, index: usize) -> Option<String> {
+        self.items.lock().map.keys().nth(index).cloned()
+    }
+
+    pub fn get_item(&self, key: &str) -> Option<String> {
+        self.items.lock().map.get(key).cloned()
+    }
+
+    pub fn entries(&self) -> Vec<(String, String)> {
+        self.items.lock().map.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+
+    pub fn usage(&self) -> usize {
+        self.items.lock().usage
+    }
+
+    pub fn set_item(&self, key: &str, value: &str, source: Option<PageId>) -> Result<Option<StorageChange>> {
+        let mut items = self.items.lock();
+        let old_value = items.map.get(key).cloned();
+        if old_value.as_deref() == Some(value) {
+            return Ok(None);
+        }
+        let usage = items.usage - old_value.as_ref().map_or(0, |old| storage_size(key, old)) + storage_size(key, value);
+        let quota = self.quota();
+        if usage > quota {
+            return Err(QuotaExceeded { usage, quota }.into());
+        }
+        items.insert(key.to_string(), value.to_string());
+        Ok(Some(self.change(Some(key), old_value, Some(value.to_string()), source)))
+    }
+
+    pub fn remove_item(&self, key: &str, source: Option<PageId>) -> Option<StorageChange> {
+        let old_value = self.items.lock().remove(key)?;
+        Some(self.change(Some(key), Some(old_value), None, source))
+    }
+
+    pub fn clear(&self, source: Option<PageId>) -> Option<StorageChange> {
+        let mut items = self.items.lock();
+        if items.map.is_empty() {
+            return None;
+        }
+        *items = Items::default();
+        Some(self.change(None, None, None, source))
+    }
+
+    // Откат изменения, которое не удалось сохранить. Ключ восстанавливается, только если
+    // его с тех пор не перезаписали; после clear возвращаются записи, оставшиеся в базе
+    fn revert(&self, change: &StorageChange, stored: &[(String, String)]) {
+        let mut items = self.items.lock();
+        match &change.key {
+            Some(key) if items.map.get(key) == change.new_value.as_ref() => match &change.old_value {
+                Some(old) => {
+                    items.insert(key.clone(), old.clone());
+                }
+                None => {
+                    items.remove(key);
+                }
+            },
+            Some(_) => {}
+            None => {
+                for (key, value) in stored {
+                    if !items.map.contains_key(key) {
+                        items.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    fn change(
+        &self,
+        key: Option<&str>,
+        old_value: Option<String>,
+        new_value: Option<String>,
+        source: Option<PageId>,
+    ) -> StorageChange {
+        StorageChange {
+            kind: self.kind,
+            partition: self.partition.clone(),
+            key: key.map(str::to_string),
+            old_value,
+            new_value,
+            url: String::new(),
+            source,
+        }
+    }
+}
+
+// localStorage общий для всех страниц источника и загружается из OriginStore при первом
+// обращении; sessionStorage живёт в памяти, пока существует страница
+#[derive(Default)]
+pub struct WebStorage {
+    local: Mutex<HashMap<String, Arc<StorageArea>>>,
+    session: Mutex<HashMap<(PageId, String), Arc<StorageArea>>>,
+}
+
+impl WebStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn partition(origin: &Origin) -> Result<String> {
+        origin
+            .partition_key()
+            .ok_or_else(|| anyhow::anyhow!("Непрозрачный источник не имеет доступа к Web Storage"))
+    }
+
+    pub async fn local(&self, origin: &Origin, store: Arc<OriginStore>) -> Result<Arc<StorageArea>> {
+        let partition = Self::partition(origin)?;
+        if let Some(area) = self.local.lock().get(&partition) {
+            return Ok(area.clone());
+        }
+        let items = store.entries().await?.into_iter().collect();
+        let area = Arc::new(StorageArea::new(StorageKind::Local, partition.clone(), items, Some(store)));
+        // Параллельная загрузка того же источника не должна создать вторую область
+        Ok(self.local.lock().entry(partition).or_insert(area).clone())
+    }
+
+    pub fn session(&self, page: PageId, origin: &Origin) -> Result<Arc<StorageArea>> {
+        let partition = Self::partition(origin)?;
+        let mut session = self.session.lock();
+        let area = session
+            .entry((page, partition.clone()))
+            .or_insert_with(|| Arc::new(StorageArea::new(StorageKind::Session, partition, BTreeMap::new(), None)));
+        Ok(area.clone())
+    }
+
+    pub fn drop_session(&self, page: PageId) {
+        self.session.lock().retain(|(owner, _), _| *owner != page);
+    }
+
+    // Изменения применяются к разделу источника в порядке поступления. Если запись
+    // в базу не удалась, область в памяти откатывается, чтобы не расходиться с базой
+    pub async fn persist(&self, change: &StorageChange) -> Result<()> {
+        if change.kind != StorageKind::Local {
+            return Ok(());
+        }
+        let area = self.local.lock().get(&change.partition).cloned();
+        let Some((area, store)) = area.and_then(|area| area.store.clone().map(|store| (area, store))) else {
+            return Err(anyhow::anyhow!("Раздел localStorage {} не открыт", change.partition));
+        };
+        let result = match (&change.key, &change.new_value) {
+            (Some(key), Some(value)) => store.insert(key, value).await,
+            (Some(key), None) => store.delete(key).await,
+            (None, _) => store.clear().await,
+        };
+        if result.is_err() {
+            let stored = if change.key.is_none() { store.entries().await.unwrap_or_default() } else { Vec::new() };
+            area.revert(change, &stored);
+        }
+        result
+    }
+}
+
+// === FILE: core\yuaidb.rs ===
+use crate::core::interfaces::YuaidbTrait;
+use crate::core::origin::Origin;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use parking_lot::{Mutex, RwLock};
+use tokio::sync::Notify;
+use async_trait::async_trait;
+
+const DATA_FILE: &str = "data.toml";
+// Изменения за это окно уходят на диск одной записью
+const FLUSH_DELAY: Duration = Duration::from_millis(500);
+const FLUSH_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+// Ключ-значение в памяти; на диск пишет фоновая задача пачками
+pub struct Yuaidb {
+    store: Arc<DbStore>,
+}
+
+struct DbStore {
+    db_dir: PathBuf,
+    data: RwLock<BTreeMap<String, String>>,
+    dirty: AtomicBool,
+    // Последняя ошибка записи: пока она не снята, изменения не принимаются
+    persist_error: Mutex<Option<String>>,
+    // Arc: фоновая запись ждёт уведомления, не удерживая саму базу
+    changed: Arc<Notify>,
+    flush_lock: tokio::sync::Mutex<()>,
+}
+
+impl DbStore {
+    fn check_writable(&self) -> Result<()> {
+        match self.persist_error.lock().as_ref() {
+            Some(error) => Err(anyhow::anyhow!("База данных недоступна для записи: {}", error)),
+            None => Ok(()),
+        }
+    }
+
+    fn mark_dirty(&self) {
+        self.dirty.store(true, Ordering::Release);
+        self.changed.notify_one();
+    }
+
+    // Снимок берётся под коротким чтением, сериализация и запись идут без блокировки данных
+    async fn flush(&self) -> Result<()> {
+        let _guard = self.flush_lock.lock().await;
+        if !self.dirty.swap(false, Ordering::AcqRel) {
+            return Ok(());
+        }
+        let snapshot = self.data.read().clone();
+        let result = Self::write_snapshot(&self.db_dir, &snapshot).await;
+        match &result {
+            Ok(()) => *self.persist_error.lock() = None,
+            Err(e) => {
+                self.dirty.store(true, Ordering::Release);
+                *self.persist_error.lock() = Some(e.to_string());
+            }
+        }
+        result
+    }
+
+    async fn write_snapshot(db_dir: &Path, data: &BTreeMap<String, String>) -> Result<()> {
+        let path = db_dir.join(DATA_FILE);
+        let tmp_path = db_dir.join(format!("{}.tmp", DATA_FILE));
+        let content = toml::to_string(data)
+            .map_err(|e| anyhow::anyhow!("Ошибка сериализации базы данных: {}", e))?;
+        tokio::fs::write(&tmp_path, content)
+            .await
+            .with_context(|| format!("Не удалось записать {}", tmp_path.display()))?;
+        tokio::fs::rename(&tmp_path, &path)
+            .await
+            .with_context(|| format!("Не удалось заменить {}", path.display()))?;
+        Ok(())
+    }
+}
+
+// Фоновая запись завершается вместе с базой
+async fn flush_loop(store: Weak<DbStore>) {
+    loop {
+        let Some(current) = store.upgrade() else { return };
+        let changed = current.changed.clone().notified_owned();
+        drop(current);
+        changed.await;
+        tokio::time::sleep(FLUSH_DELAY).await;
+        let Some(current) = store.upgrade() else { return };
+        if let Err(e) = current.flush().await {
+            log::error!("Ошибка записи базы данных: {}", e);
+            drop(current);
+            tokio::time::sleep(FLUSH_RETRY_DELAY).await;
+            if let Some(current) = store.upgrade() {
+                current.changed.notify_one();
+            }
+        }
+    }
+}
+
+impl Yuaidb {
+    // Дописывает накопленные изменения; вызывается при завершении приложения
+    pub async fn flush(&self) -> Result<()> {
+        self.store.flush().await
+    }
+}
+
+impl Drop for Yuaidb {
+    fn drop(&mut self) {
+        if self.store.dirty.load(Ordering::Acquire) {
+            log::warn!("База данных закрыта с незаписанными изменениями");
+        }
+        // Будит фоновую запись, чтобы она увидела закрытие базы и завершилась
+        self.store.changed.notify_one();
+    }
+}
+
+#[async_trait]
+impl YuaidbTrait for Yuaidb {
+    async fn new(db_dir: impl AsRef<Path> + Send) -> Result<Self> {
+        let db_dir = db_dir.as_ref().to_path_buf();
+        fs::create_dir_all(&db_dir)?;
+        let data_path = db_dir.join(DATA_FILE);
+        let data = if data_path.exists() {
+            let content = fs::read_to_string(&data_path)
+                .with_context(|| format!("Не удалось прочитать {}", data_path.display()))?;
+            toml::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("Ошибка чтения базы данных {}: {}", data_path.display(), e))?
+        } else {
+            BTreeMap::new()
+        };
+        log::info!("База данных инициализирована с каталогом: {:?}", db_dir);
+        let store = Arc::new(DbStore {
+            db_dir,
+            data: RwLock::new(data),
+            dirty: AtomicBool::new(false),
+            persist_error: Mutex::new(None),
+            changed: Arc::new(Notify::new()),
+            flush_lock: tokio::sync::Mutex::new(()),
+        });
+        tokio::spawn(flush_loop(Arc::downgrade(&store)));
+        Ok(Self { store })
+    }
+    async fn insert(&self, key: &str, value: &str) -> Result<()> {
+        log::debug!("Вставка данных в db: {}", key);
+        self.store.check_writable()?;
+        self.store.data.write().insert(key.to_string(), value.to_string());
+        self.store.mark_dirty();
+        Ok(())
+    }
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        log::debug!("Получение данных: {}", key);
+        Ok(self.store.data.read().get(key).cloned())
+    }
+    async fn delete(&self, key: &str) -> Result<()> {
+        log::debug!("Удаление данных: {}", key);
+        self.store.check_writable()?;
+        if self.store.data.write().remove(key).is_some() {
+            self.store.mark_dirty();
+        }
+        Ok(())
+    }
+    async fn flush(&self) -> Result<()> {
+        self.store.flush().await
+    }
+    async fn scan_prefix(&self, prefix: &str) -> Result<Vec<(String, String)>> {
+        log::debug!("Чтение диапазона по префиксу: {}", prefix);
+        let data = self.store.data.read();
+        Ok(data
+            .range(prefix.to_string()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+    async fn delete_prefix(&self, prefix: &str) -> Result<usize> {
+        log::debug!("Удаление диапазона по префиксу: {}", prefix);
+        self.store.check_writable()?;
+        let mut data = self.store.data.write();
+        let keys: Vec<String> = data
+            .range(prefix.to_string()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &keys {
+            data.remove(key);
+        }
+        drop(data);
+        if !keys.is_empty() {
+            self.store.mark_dirty();
+        }
+        Ok(keys.len())
+    }
+}
+
+const ORIGIN_PREFIX: &str = "origin:";
+// Квоты и занятый объём в единицах UTF-16, как длина строк в Web Storage
+pub const DEFAULT_ORIGIN_QUOTA: usize = 5 * 1024 * 1024;
+pub const EXTENDED_ORIGIN_QUOTA: usize = 50 * 1024 * 1024;
+
+pub fn storage_size(key: &str, value: &str) -> usize {
+    key.encode_utf16().count() + value.encode_utf16().count()
+}
+
+// Раздел базы данных одного источника: ключи вида origin:<scheme://host:port>/<key>.
+// Движок держит один экземпляр на источник, поэтому записи раздела идут по очереди
+pub struct OriginStore {
+    db: Arc<dyn YuaidbTrait + Send + Sync>,
+    prefix: String,
+    quota: AtomicUsize,
+    // Занятый объём считается один раз при первой записи, затем обновляется по изменениям
+    usage: tokio::sync::Mutex<Option<usize>>,
+}
+
+impl OriginStore {
+    pub fn new(db: Arc<dyn YuaidbTrait + Send + Sync>, origin: &Origin, quota: usize) -> Result<Self> {
+        let partition = origin
+            .partition_key()
+            .ok_or_else(|| anyhow::anyhow!("Непрозрачный источник не имеет доступа к хранилищу"))?;
+        Ok(Self {
+            db,
+            prefix: format!("{}{}/", ORIGIN_PREFIX, partition),
+            quota: AtomicUsize::new(quota),
+            usage: tokio::sync::Mutex::new(None),
+        })
+    }
+
+    pub fn quota(&self) -> usize {
+        self.quota.load(Ordering::Acquire)
+    }
+
+    // Квота растёт после выдачи разрешения StorageQuota
+    pub fn set_quota(&self, quota: usize) {
+        self.quota.store(quota, Ordering::Release);
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<String>> {
+        self.db.get(&format!("{}{}", self.prefix, key)).await
+    }
+
+    pub async fn insert(&self, key: &str, value: &str) -> Result<()> {
+        let mut usage = self.usage.lock().await;
+        let current = self.cached_usage(&mut usage).await?;
+        let full_key = format!("{}{}", self.prefix, key);
+        let previous = self.db.get(&full_key).await?.map_or(0, |v| storage_size(key, &v));
+        let next = current - previous + storage_size(key, value);
+        let quota = self.quota();
+        if next > quota {
+            return Err(QuotaExceeded { usage: next, quota }.into());
+        }
+        self.db.insert(&full_key, value).await?;
+        *usage = Some(next);
+        Ok(())
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        let mut usage = self.usage.lock().await;
+        let current = self.cached_usage(&mut usage).await?;
+        let full_key = format!("{}{}", self.prefix, key);
+        if let Some(previous) = self.db.get(&full_key).await? {
+            self.db.delete(&full_key).await?;
+            *usage = Some(current - storage_size(key, &previous));
+        }
+        Ok(())
+    }
+
+    pub async fn entries(&self) -> Result<Vec<(String, String)>> {
+        Ok(self
+            .db
+            .scan_prefix(&self.prefix)
+            .await?
+            .into_iter()
+            .map(|(key, value)| (key[self.prefix.len()..].to_string(), value))
+            .collect())
+    }
+
+    // Раздел очищается целиком или не очищается вовсе
+    pub async fn clear(&self) -> Result<()> {
+        let mut usage = self.usage.lock().await;
+        self.db.delete_prefix(&self.prefix).await?;
+        *usage = Some(0);
+        Ok(())
+    }
+
+    pub async fn usage(&self) -> Result<usize> {
+        let mut usage = self.usage.lock().await;
+        self.cached_usage(&mut usage).await
+    }
+
+    // Занятый объём считается по ключам без префикса раздела и значениям
+    async fn cached_usage(&self, usage: &mut Option<usize>) -> Result<usize> {
+        if let Some(usage) = *usage {
+            return Ok(usage);
+        }
+        let total = self
+            .db
+            .scan_prefix(&self.prefix)
+            .await?
+            .iter()
+            .map(|(key, value)| storage_size(&key[self.prefix.len()..], value))
+            .sum();
+        *usage = Some(total);
+        Ok(total)
+    }
+}
+
+#[derive(Debug)]
+pub struct QuotaExceeded {
+    pub usage: usize,
+    pub quota: usize,
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Превышена квота хранилища: {} из {} символов", self.usage, self.quota)
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+// === FILE: dom\css_parser.rs ===
+use anyhow::Result;
+use std::sync::Arc;
+use crate::net::Network;
+pub struct CSSparser;
+impl CSSparser {
+    pub fn new(_network: Arc<Network>) -> Result<Self> {
+        println!("CSSparser initialized");
+        Ok(Self)
+    }
+}
+
+// === FILE: dom\hydrate.rs ===
+// === FILE: dom/hydrate.rs ===
+use crate::dom::parser::{ParsedNode, FrameworkType};
+use crate::dom::tree::DomRenderer;
+use crate::core::interfaces::ReactiveCoreTrait;
+use crate::core::reactive::ReactiveCore; 
+use crate::wasm_api::signals::PageDocument;
+use egui::Ui;
+use anyhow::Result;
+use std::sync::Arc;
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+
+// Статические переменные для состояния гидрации и логов
+static HYDRATION_STATE: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static LOG_BUFFER: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+// Функция гидрации и рендеринга DOM
+pub async fn hydrate_and_render(ui: &mut Ui, dom_renderer: &mut DomRenderer, document: &PageDocument, node: ParsedNode) -> Result<Option<FrameworkType>> {
+    // Очищаем буфер логов для текущего вызова
+    let mut log_buffer = LOG_BUFFER.lock().unwrap();
+    log_buffer.clear();
+
+    // Переменная для хранения типа фреймворка
+    let mut detected_framework: Option<FrameworkType> = None;
+
+    // Проверяем, нужно ли выполнять гидрацию
+    let should_hydrate = match &node {
+        ParsedNode::Element { framework, attrs, .. } => {
+            if let Some(fw) = framework {
+                let mut state = HYDRATION_STATE.lock().unwrap();
+                // Новый документ получает новый граф сигналов и гидрируется заново
+                let node_key = format!("{}-{:?}-{:?}", document.signals.id(), fw, attrs.get("id").unwrap_or(&String::new()));
+                if state.contains(&node_key) {
+                    false // Гидрация уже выполнена для этого узла
+                } else {
+                    state.push(node_key);
+                    // Сохраняем фреймворк
+                    detected_framework = Some(fw.clone());
+                    // Добавляем лог о распознанном фреймворке
+                    log_buffer.push(match fw {
+                        FrameworkType::ReactNext => "Обнаружен React/Next.js, гидрация состояния".to_string(),
+                        FrameworkType::Angular => "Обнаружен Angular, привязка компонентов".to_string(),
+                        FrameworkType::VueNuxt => "Обнаружен Vue/Nuxt, создание stores".to_string(),
+                        FrameworkType::SvelteKit => "Обнаружен SvelteKit, применение хешей".to_string(),
+                        FrameworkType::WasmModule => "Обнаружен WASM-модуль, загрузка".to_string(),
+                    });
+                    true
+                }
+            } else {
+                false
+            }
+        }
+        _ => false,
+    };
+
+    // Выполняем гидрацию, если требуется
+    if should_hydrate {
+        let reactive_core = Arc::new(ReactiveCore);
+        if let ParsedNode::Element { framework, reactive_state, .. } = &node {
+            if let Some(fw) = framework {
+                match fw {
+                    FrameworkType::ReactNext => {
+                        if let Some(state) = reactive_state {
+                            reactive_core.create_signals(document, &node, state)?;
+                            log_buffer.push("Гидрация React завершена".to_string());
+                        }
+                    }
+                    FrameworkType::Angular => {
+                        reactive_core.bind_angular_scopes(&node)?;
+                        log_buffer.push("Гидрация Angular завершена".to_string());
+                    }
+                    FrameworkType::VueNuxt => {
+                        if let Some(state) = reactive_state {
+                            reactive_core.create_stores(document, &node, state)?;
+                            log_buffer.push("Гидрация Vue завершена".to_string());
+                        }
+                    }
+                    FrameworkType::SvelteKit => {
+                        reactive_core.apply_svelte_hashes(&node)?;
+                        log_buffer.push("Гидрация Svelte завершена".to_string());
+                    }
+                    FrameworkType::WasmModule => {
+                        reactive_core.load_wasm_module(&node)?;
+                        log_buffer.push("WASM-модуль загружен".to_string());
+                    }
+                }
+                // Инкрементальный рендеринг без VDOM
+                reactive_core.incremental_render(ui, &node).await?;
+                log_buffer.push("Инкрементальный рендер без VDOM".to_string());
+            }
+        }
+    }
+
+    // Рендерим узел
+    dom_renderer.render_node(ui, &node).await;
+
+    // Выводим все накопленные логи
+    for log in log_buffer.iter() {
+        log::info!("{}", log);
+    }
+
+    Ok(detected_framework)
+}
+
+// === FILE: dom\layout.rs ===
+use anyhow::Result;
+use std::sync::Arc;
+use crate::net::Network;
+pub struct Layout;
+impl Layout {
+    pub fn new(_network: Arc<Network>) -> Result<Self> {
+        println!("Layout initialized");
+        Ok(Self)
+    }
+}
+
+// === FILE: dom\live.rs ===
+use crate::dom::parser::{FrameworkType, ParsedNode, ScriptElement};
+use anyhow::Result;
+use parking_lot::RwLock;
+use serde_json::Value as JsonValue;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(pub u32);
+
+pub enum LiveNodeData {
+    Element {
+        tag: String,
+        attrs: HashMap<String, String>,
+        reactive_state: Option<JsonValue>,
+        framework: Option<FrameworkType>,
+    },
+    Text(String),
+    Script(ScriptElement),
+}
+
+struct LiveNode {
+    data: LiveNodeData,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+}
+
+pub type SharedDom = Arc<RwLock<LiveDom>>;
+
+// Пользовательское событие из DomRenderer, адресованное узлу живого DOM
+#[derive(Clone, Debug, PartialEq)]
+pub struct DomEvent {
+    pub node: NodeId,
+    pub kind: DomEventKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DomEventKind {
+    Click,
+    Input(String),
+    Change(bool),
+}
+
+impl DomEventKind {
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            DomEventKind::Click => "click",
+            DomEventKind::Input(_) => "input",
+            DomEventKind::Change(_) => "change",
+        }
+    }
+}
+
+// Изменяемое дерево документа. ParsedNode для рендера строится из него снимком,
+// при этом неизменённые поддеревья берутся из кэша снимков
+pub struct LiveDom {
+    nodes: HashMap<NodeId, LiveNode>,
+    document_element: NodeId,
+    root: NodeId,
+    next_id: u32,
+    version: u64,
+    snapshots: HashMap<NodeId, ParsedNode>,
+    mutations: watch::Sender<u64>,
+}
+
+impl fmt::Debug for LiveDom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LiveDom")
+            .field("nodes", &self.nodes.len())
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
+impl LiveDom {
+    pub fn new() -> Self {
+        let mut dom = Self {
+            nodes: HashMap::new(),
+            document_element: NodeId(0),
+            root: NodeId(0),
+            next_id: 0,
+            version: 0,
+            snapshots: HashMap::new(),
+            mutations: watch::Sender::new(0),
+        };
+        dom.document_element = dom.create_element("html");
+        dom.root = dom.create_element("body");
+        dom.adopt_root();
+        dom
+    }
+
+    pub fn shared() -> SharedDom {
+        Arc::new(RwLock::new(Self::new()))
+    }
+
+    // Версия после каждого изменения, в том числе из потока JS realm. Подписчики
+    // просыпаются уже после снятия блокировки DOM, а не внутри изменения
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.mutations.subscribe()
+    }
+
+    // Замена документа целиком: узлы прежнего документа становятся недействительными
+    pub fn load(&mut self, node: &ParsedNode) -> ParsedNode {
+        self.nodes.clear();
+        self.snapshots.clear();
+        self.document_element = self.create_element("html");
+        self.root = self.insert_parsed(node, None);
+        self.adopt_root();
+        self.mark_dirty(self.root);
+        self.snapshot()
+    }
+
+    // body всегда единственный ребёнок html
+    fn adopt_root(&mut self) {
+        let (html, body) = (self.document_element, self.root);
+        self.nodes.get_mut(&body).expect("body создан").parent = Some(html);
+        self.nodes.get_mut(&html).expect("html создан").children = vec![body];
+    }
+
+    fn insert_parsed(&mut self, node: &ParsedNode, parent: Option<NodeId>) -> NodeId {
+        let id = self.allocate(match node {
+            ParsedNode::Text(text) => LiveNodeData::Text(text.clone()),
+            ParsedNode::Script(script) => LiveNodeData::Script(script.clone()),
+            ParsedNode::Element { tag, attrs, reactive_state, framework, .. } => LiveNodeData::Element {
+                tag: tag.clone(),
+                attrs: attrs.clone(),
+                reactive_state: reactive_state.clone(),
+                framework: framework.clone(),
+            },
+        });
+        self.nodes.get_mut(&id).expect("узел только что создан").parent = parent;
+        if let ParsedNode::Element { children, .. } = node {
+            let child_ids = children
+                .iter()
+                .map(|child| self.insert_parsed(child, Some(id)))
+                .collect();
+            self.nodes.get_mut(&id).expect("узел только что создан").children = child_ids;
+        }
+        id
+    }
+
+    fn allocate(&mut self, data: LiveNodeData) -> NodeId {
+        let id = NodeId(self.next_id);
+        self.next_id += 1;
+        self.nodes.insert(id, LiveNode { data, parent: None, children: Vec::new() });
+        id
+    }
+
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    pub fn document_element(&self) -> NodeId {
+        self.document_element
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.nodes.contains_key(&id)
+    }
+
+    pub fn data(&self, id: NodeId) -> Option<&LiveNodeData> {
+        self.nodes.get(&id).map(|node| &node.data)
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes.get(&id)?.parent
+    }
+
+    pub fn children(&self, id: NodeId) -> Vec<NodeId> {
+        self.nodes.get(&id).map(|node| node.children.clone()).unwrap_or_default()
+    }
+
+    pub fn tag(&self, id: NodeId) -> Option<&str> {
+        match self.data(id)? {
+            LiveNodeData::Element { tag, .. } => Some(tag),
+            LiveNodeData::Script(_) => Some("script"),
+            LiveNodeData::Text(_) => None,
+        }
+    }
+
+    pub fn create_element(&mut self, tag: &str) -> NodeId {
+        self.allocate(LiveNodeData::Element {
+            tag: tag.to_ascii_lowercase(),
+            attrs: HashMap::new(),
+            reactive_state: None,
+            framework: None,
+        })
+    }
+
+    pub fn create_text(&mut self, text: &str) -> NodeId {
+        self.allocate(LiveNodeData::Text(text.to_string()))
+    }
+
+    pub fn get_attribute(&self, id: NodeId, name: &str) -> Option<String> {
+        match self.data(id)? {
+            LiveNodeData::Element { attrs, .. } => attrs.get(name).cloned(),
+            LiveNodeData::Script(script) => match name {
+                "src" => script.src.clone(),
+                "nonce" => script.nonce.clone(),
+                _ => None,
+            },
+            LiveNodeData::Text(_) => None,
+        }
+    }
+
+    pub fn set_attribute(&mut self, id: NodeId, name: &str, value: &str) -> Result<()> {
+        match self.nodes.get_mut(&id).map(|node| &mut node.data) {
+            Some(LiveNodeData::Element { attrs, .. }) => {
+                let name = name.to_ascii_lowercase();
+                if attrs.get(&name).map(String::as_str) == Some(value) {
+                    return Ok(());
+                }
+                attrs.insert(name, value.to_string());
+            }
+            _ => return Err(anyhow::anyhow!("Узел {:?} не является элементом", id)),
+        }
+        self.mark_dirty(id);
+        Ok(())
+    }
+
+    pub fn remove_attribute(&mut self, id: NodeId, name: &str) -> Result<()> {
+        let removed = match self.nodes.get_mut(&id).map(|node| &mut node.data) {
+            Some(LiveNodeData::Element { attrs, .. }) => attrs.remove(name).is_some(),
+            _ => return Err(anyhow::anyhow!("Узел {:?} не является элементом", id)),
+        };
+        if removed {
+            self.mark_dirty(id);
+        }
+        Ok(())
+    }
+
+    // Состояние поля ввода попадает в DOM до того, как обработчики страницы увидят событие
+    pub fn apply_event(&mut self, event: &DomEvent) -> Result<()> {
+        match &event.kind {
+            DomEventKind::Click => Ok(()),
+            DomEventKind::Input(value) => self.set_attribute(event.node, "value", value),
+            DomEventKind::Change(true) => self.set_attribute(event.node, "checked", ""),
+            DomEventKind::Change(false) => self.remove_attribute(event.node, "checked"),
+        }
+    }
+
+    // Текст всех потомков без разделителей, как у DOM textContent
+    pub fn text_content(&self, id: NodeId) -> String {
+        match self.data(id) {
+            Some(LiveNodeData::Text(text)) => text.clone(),
+            Some(LiveNodeData::Script(script)) => script.text.clone(),
+            Some(LiveNodeData::Element { .. }) => self
+                .children(id)
+                .into_iter()
+                .map(|child| self.text_content(child))
+                .collect(),
+            None => String::new(),
+        }
+    }
+
+    pub fn set_text_content(&mut self, id: NodeId, text: &str) -> Result<()> {
+        let is_element = match self.nodes.get_mut(&id).map(|node| &mut node.data) {
+            Some(LiveNodeData::Text(current)) => {
+                *current = text.to_string();
+                false
+            }
+            Some(LiveNodeData::Script(script)) => {
+                script.text = text.to_string();
+                false
+            }
+            Some(LiveNodeData::Element { .. }) => true,
+            None => return Err(anyhow::anyhow!("Узел {:?} не найден", id)),
+        };
+        if is_element {
+            for child in self.children(id) {
+                self.free_subtree(child);
+            }
+            let children = if text.is_empty() {
+                Vec::new()
+            } else {
+                let text_id = self.create_text(text);
+                self.nodes.get_mut(&text_id).expect("узел только что создан").parent = Some(id);
+                vec![text_id]
+            };
+            self.nodes.get_mut(&id).expect("узел проверен выше").children = children;
+        }
+        self.mark_dirty(id);
+        Ok(())
+    }
+
+    // appendChild/insertBefore: узел сначала отсоединяется от прежнего родителя
+    pub fn insert_before(&mut self, parent: NodeId, child: NodeId, reference: Option<NodeId>) -> Result<()> {
+        if !matches!(self.data(parent), Some(LiveNodeData::Element { .. })) {
+            return Err(anyhow::anyhow!("Родитель {:?} не является элементом", parent));
+        }
+        if !self.contains(child) {
+            return Err(anyhow::anyhow!("Узел {:?} не найден", child));
+        }
+        if self.is_inclusive_ancestor(child, parent) {
+            return Err(anyhow::anyhow!("Узел нельзя вставить внутрь самого себя"));
+        }
+        self.detach(child);
+        let siblings = &mut self.nodes.get_mut(&parent).expect("родитель проверен выше").children;
+        let index = match reference {
+            Some(reference) => siblings
+                .iter()
+                .position(|&id| id == reference)
+                .ok_or_else(|| anyhow::anyhow!("Узел {:?} не является дочерним для {:?}", reference, parent))?,
+            None => siblings.len(),
+        };
+        siblings.insert(index, child);
+        self.nodes.get_mut(&child).expect("узел проверен выше").parent = Some(parent);
+        self.mark_dirty(parent);
+        Ok(())
+    }
+
+    pub fn append_child(&mut self, parent: NodeId, child: NodeId) -> Result<()> {
+        self.insert_before(parent, child, None)
+    }
+
+    // Удалённое поддерево освобождается: его узлы становятся недействительными, как после load
+    pub fn remove_child(&mut self, parent: NodeId, child: NodeId) -> Result<()> {
+        if self.parent(child) != Some(parent) {
+            return Err(anyhow::anyhow!("Узел {:?} не является дочерним для {:?}", child, parent));
+        }
+        self.detach(child);
+        self.free_subtree(child);
+        Ok(())
+    }
+
+    fn free_subtree(&mut self, id: NodeId) {
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if let Some(node) = self.nodes.remove(&id) {
+                self.snapshots.remove(&id);
+                stack.extend(node.children);
+            }
+        }
+    }
+
+    fn detach(&mut self, child: NodeId) {
+        let Some(parent) = self.parent(child) else { return };
+        if let Some(node) = self.nodes.get_mut(&parent) {
+            node.children.retain(|&id| id != child);
+        }
+        if let Some(node) = self.nodes.get_mut(&child) {
+            node.parent = None;
+        }
+        self.mark_dirty(parent);
+    }
+
+    fn is_inclusive_ancestor(&self, ancestor: NodeId, mut node: NodeId) -> bool {
+        loop {
+            if node == ancestor {
+                return true;
+            }
+            match self.parent(node) {
+                Some(parent) => node = parent,
+                None => return false,
+            }
+        }
+    }
+
+    // Сбрасывает кэш снимков узла и его предков; остальные поддеревья переиспользуются
+    fn mark_dirty(&mut self, id: NodeId) {
+        let mut current = Some(id);
+        while let Some(node) = current {
+            self.snapshots.remove(&node);
+            current = self.parent(node);
+        }
+        self.version += 1;
+        self.mutations.send_replace(self.version);
+    }
+
+    pub fn snapshot(&mut self) -> ParsedNode {
+        self.snapshot_node(self.root)
+    }
+
+    fn snapshot_node(&mut self, id: NodeId) -> ParsedNode {
+        if let Some(cached) = self.snapshots.get(&id) {
+            return cached.clone();
+        }
+        let node = match self.data(id) {
+            Some(LiveNodeData::Text(text)) => ParsedNode::Text(text.clone()),
+            Some(LiveNodeData::Script(script)) => ParsedNode::Script(script.clone()),
+            Some(LiveNodeData::Element { tag, attrs, reactive_state, framework }) => ParsedNode::Element {
+                tag: tag.clone(),
+                attrs: attrs.clone(),
+                children: Vec::new(),
+                reactive_state: reactive_state.clone(),
+                framework: framework.clone(),
+                node_id: Some(id),
+            },
+            None => ParsedNode::Text(String::new()),
+        };
+        let node = match node {
+            ParsedNode::Element { tag, attrs, reactive_state, framework, node_id, .. } => ParsedNode::Element {
+                children: self.children(id).into_iter().map(|child| self.snapshot_node(child)).collect(),
+                tag,
+                attrs,
+                reactive_state,
+                framework,
+                node_id,
+            },
+            leaf => leaf,
+        };
+        self.snapshots.insert(id, node.clone());
+        node
+    }
+
+    pub fn get_element_by_id(&self, element_id: &str) -> Option<NodeId> {
+        self.descendants(self.root)
+            .into_iter()
+            .find(|&id| self.get_attribute(id, "id").as_deref() == Some(element_id))
+    }
+
+    pub fn query_selector_all(&self, scope: NodeId, selector: &str) -> Result<Vec<NodeId>> {
+        let selectors = parse_selector_list(selector)?;
+        Ok(self
+            .descendants(scope)
+            .into_iter()
+            .filter(|&id| selectors.iter().any(|complex| self.matches_complex(id, complex)))
+            .collect())
+    }
+
+    pub fn query_selector(&self, scope: NodeId, selector: &str) -> Result<Option<NodeId>> {
+        Ok(self.query_selector_all(scope, selector)?.into_iter().next())
+    }
+
+    // Потомки в порядке документа, без самого узла
+    fn descendants(&self, scope: NodeId) -> Vec<NodeId> {
+        let mut result = Vec::new();
+        let mut stack: Vec<NodeId> = self.children(scope).into_iter().rev().collect();
+        while let Some(id) = stack.pop() {
+            result.push(id);
+            stack.extend(self.children(id).into_iter().rev());
+        }
+        result
+    }
+
+    fn matches_complex(&self, id: NodeId, complex: &[(Combinator, Compound)]) -> bool {
+        let Some(((_, last), rest)) = complex.split_last() else { return false };
+        self.matches_compound(id, last) && self.matches_rest(id, rest, complex.last().map(|(c, _)| *c))
+    }
+
+    // Сопоставление справа налево с возвратом для комбинатора потомка
+    fn matches_rest(&self, id: NodeId, rest: &[(Combinator, Compound)], combinator: Option<Combinator>) -> bool {
+        let Some(((next_combinator, compound), remaining)) = rest.split_last() else { return true };
+        match combinator {
+            Some(Combinator::Child) => self.parent(id).is_some_and(|parent| {
+                self.matches_compound(parent, compound) && self.matches_rest(parent, remaining, Some(*next_combinator))
+            }),
+            _ => {
+                let mut current = self.parent(id);
+                while let Some(ancestor) = current {
+                    if self.matches_compound(ancestor, compound)
+                        && self.matches_rest(ancestor, remaining, Some(*next_combinator))
+                    {
+                        return true;
+                    }
+                    current = self.parent(ancestor);
+                }
+                false
+            }
+        }
+    }
+
+    fn matches_compound(&self, id: NodeId, compound: &Compound) -> bool {
+        let Some(tag) = self.tag(id) else { return false };
+        if compound.tag.as_deref().is_some_and(|expected| expected != "*" && expected != tag) {
+            return false;
+        }
+        if let Some(expected) = &compound.id {
+            if self.get_attribute(id, "id").as_deref() != Some(expected.as_str()) {
+                return false;
+            }
+        }
+        if !compound.classes.is_empty() {
+            let class_attr = self.get_attribute(id, "class").unwrap_or_default();
+            let classes: HashSet<&str> = class_attr.split_ascii_whitespace().collect();
+            if !compound.classes.iter().all(|class| classes.contains(class.as_str())) {
+                return false;
+            }
+        }
+        compound.attrs.iter().all(|(name, expected)| match (self.get_attribute(id, name), expected) {
+            (Some(actual), Some(expected)) => actual == *expected,
+            (Some(_), None) => true,
+            (None, _) => false,
+        })
+    }
+}
+
+impl Default for LiveDom {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Поддерживаемое подмножество CSS-селекторов: тег, #id, .класс, [атрибут], [атрибут=значение],
+// комбинаторы потомка и ребёнка, списки через запятую
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+#[derive(Debug, Default)]
+struct Compound {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+    attrs: Vec<(String, Option<String>)>,
+}
+
+// Каждый составной селектор хранится вместе с комбинатором, связывающим его с предыдущим
+fn parse_selector_list(selector: &str) -> Result<Vec<Vec<(Combinator, Compound)>>> {
+    selector
+        .split(',')
+        .map(|part| parse_complex(part.trim()).ok_or_else(|| anyhow::anyhow!("Недопустимый селектор: '{}'", part.trim())))
+        .collect()
+}
+
+fn parse_complex(selector: &str) -> Option<Vec<(Combinator, Compound)>> {
+    if selector.is_empty() {
+        return None;
+    }
+    let mut result = Vec::new();
+    let mut combinator = Combinator::Descendant;
+    let mut chars = selector.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        match chars.peek() {
+            None => break,
+            Some('>') => {
+                chars.next();
+                combinator = Combinator::Child;
+                continue;
+            }
+            _ => {}
+        }
+        let compound = parse_compound(&mut chars)?;
+        result.push((combinator, compound));
+        combinator = Combinator::Descendant;
+    }
+    (!result.is_empty() && combinator == Combinator::Descendant).then_some(result)
+}
+
+fn parse_compound(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Option<Compound> {
+    let mut compound = Compound::default();
+    let read_ident = |chars: &mut std::iter::Peekable<std::str::Chars<'_>>| -> String {
+        let mut ident = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                ident.push(c);
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        ident
+    };
+    if chars.peek() == Some(&'*') {
+        chars.next();
+        compound.tag = Some("*".to_string());
+    } else if chars.peek().is_some_and(|c| c.is_alphabetic()) {
+        compound.tag = Some(read_ident(chars).to_ascii_lowercase());
+    }
+    while let Some(&c) = chars.peek() {
+        match c {
+            '#' => {
+                chars.next();
+                compound.id = Some(read_ident(chars)).filter(|id| !id.is_empty());
+                compound.id.as_ref()?;
+            }
+            '.' => {
+                chars.next();
+                let class = read_ident(chars);
+                if class.is_empty() {
+                    return None;
+                }
+                compound.classes.push(class);
+            }
+            '[' => {
+                chars.next();
+                let name = read_ident(chars).to_ascii_lowercase();
+                let value = if chars.peek() == Some(&'=') {
+                    chars.next();
+                    let quote = chars.peek().copied().filter(|c| *c == '"' || *c == '\'');
+                    if quote.is_some() {
+                        chars.next();
+                    }
+                    let mut value = String::new();
+                    while let Some(&c) = chars.peek() {
+                        if Some(c) == quote || (quote.is_none() && c == ']') {
+                            break;
+                        }
+                        value.push(c);
+                        chars.next();
+                    }
+                    if quote.is_some() {
+                        chars.next();
+                    }
+                    Some(value)
+                } else {
+                    None
+                };
+                if name.is_empty() || chars.next() != Some(']') {
+                    return None;
+                }
+                compound.attrs.push((name, value));
+            }
+            c if c.is_whitespace() || c == '>' => break,
+            _ => return None,
+        }
+    }
+    let empty = compound.tag.is_none() && compound.id.is_none() && compound.classes.is_empty() && compound.attrs.is_empty();
+    (!empty).then_some(compound)
+}
+
+// === FILE: dom\mod.rs ===
+pub mod parser;
+pub mod live;
+pub mod scripts;
+pub mod css_parser;
+pub mod tree;
+pub mod style;
+pub mod hydrate;
+pub mod vdom;
+pub mod mutation;
+pub mod layout;
+
+
+// === FILE: dom\mutation.rs ===
+use anyhow::Result;
+use std::sync::Arc;
+use crate::net::Network;
+pub struct Mutation;
+impl Mutation {
+    pub fn new(_network: Arc<Network>) -> Result<Self> {
+        println!("Mutation initialized");
+        Ok(Self)
+    }
+}
+
+// === FILE: dom\parser.rs ===
+use html5ever::parse_document;
+use html5ever::tendril::TendrilSink;
+use html5ever::tree_builder::TreeBuilderOpts;
+use html5ever::ParseOpts;
+use markup5ever_rcdom::{Handle, NodeData, RcDom};
+use crate::dom::live::NodeId;
+use anyhow::Result;
+use std::collections::HashMap;
+use serde_json::Value as JsonValue;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender, UnboundedReceiver}; 
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+
+#[derive(Clone, Debug)]
+pub enum ParsedNode {
+    Text(String),
+    Element {
+        tag: String,
+        attrs: HashMap<String, String>,
+        children: Vec<ParsedNode>,
+        reactive_state: Option<JsonValue>,
+        framework: Option<FrameworkType>,
+        node_id: Option<NodeId>, // Узел живого DOM, из снимка которого построен элемент
+    },
+    Script(ScriptElement),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScriptKind {
+    Classic,
+    Module,
+    // Блок данных (application/json, шаблоны и т.п.) не исполняется
+    Data(String),
+}
+
+impl ScriptKind {
+    fn from_type(script_type: Option<&str>) -> Self {
+        let script_type = script_type.map(|t| t.trim().to_ascii_lowercase()).unwrap_or_default();
+        match script_type.as_str() {
+            "" | "text/javascript" | "application/javascript" | "application/ecmascript"
+            | "text/ecmascript" | "application/x-javascript" | "text/jscript" => Self::Classic,
+            "module" => Self::Module,
+            _ => Self::Data(script_type),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ScriptElement {
+    pub kind: ScriptKind,
+    pub src: Option<String>,
+    pub is_async: bool,
+    pub defer: bool,
+    pub nomodule: bool,
+    pub nonce: Option<String>,
+    pub text: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FrameworkType {
+    ReactNext,
+    Angular,
+    VueNuxt,
+    SvelteKit,
+    WasmModule,
+}
+
+// Канал для передачи типа фреймворка
+static FRAMEWORK_CHANNEL: Lazy<(UnboundedSender<FrameworkType>, Mutex<Option<UnboundedReceiver<FrameworkType>>>)> =
+    Lazy::new(|| {
+        let (tx, rx) = unbounded_channel();
+        (tx, Mutex::new(Some(rx))) 
+    });
+
+pub fn get_framework_receiver() -> UnboundedReceiver<FrameworkType> {
+    let mut lock = FRAMEWORK_CHANNEL.1.lock().expect("Ошибка блокировки Mutex");
+    lock.take().expect("Приёмник фреймворка уже был извлечён")
+}
+
+// scripting_enabled влияет на разбор <noscript>: при включённом JS его содержимое не отображается
+pub fn parse_and_process(html: &str, scripting_enabled: bool) -> Result<(ParsedNode, Option<FrameworkType>)> {
+    let opts = ParseOpts {
+        tree_builder: TreeBuilderOpts { scripting_enabled, ..Default::default() },
+        ..Default::default()
+    };
+    let dom = parse_document(RcDom::default(), opts).one(html);
+
+    if let Some(body) = find_element(&dom.document, "body") {
+        let mut node = process_node(body, scripting_enabled, false);
+        // Скрипты из <head> ставятся в начало body, чтобы сохранить порядок документа
+        if let (Some(head), ParsedNode::Element { children, .. }) = (find_element(&dom.document, "head"), &mut node) {
+            let head_scripts = head
+                .children
+                .borrow()
+                .iter()
+                .map(|child| process_node(child.clone(), scripting_enabled, false))
+                .filter(|node| matches!(node, ParsedNode::Script(_)))
+                .collect::<Vec<_>>();
+            children.splice(0..0, head_scripts);
+        }
+        let detected_framework = hydrate_framework_markers(&mut node, html)?;
+        Ok((node, detected_framework)) // возвращаем FrameworkType наружу
+    } else {
+        Ok((ParsedNode::Text("".into()), None))
+    }
+}
+
+fn find_element(handle: &Handle, tag: &str) -> Option<Handle> {
+    match &handle.data {
+        NodeData::Element { name, .. } if &*name.local == tag => Some(handle.clone()),
+        _ => handle
+            .children
+            .borrow()
+            .iter()
+            .find_map(|child| find_element(child, tag)),
+    }
+}
+
+fn process_node(handle: Handle, scripting_enabled: bool, preformatted: bool) -> ParsedNode {
+    match &handle.data {
+        // Внутри <pre> пробелы и переводы строк значимы
+        NodeData::Text { contents } if preformatted => ParsedNode::Text(contents.borrow().to_string()),
+        NodeData::Text { contents } => {
+            let text = contents.borrow().trim().to_string();
+            ParsedNode::Text(text)
+        }
+        NodeData::Element { name, attrs, .. } => {
+            let tag = name.local.to_lowercase();
+            if tag == "style" || (tag == "noscript" && scripting_enabled) {
+                return ParsedNode::Text("".into());
+            }
+            let mut attrs_map = HashMap::new();
+            for attr in attrs.borrow().iter() {
+                attrs_map.insert(attr.name.local.to_string(), attr.value.to_string());
+            }
+            if tag == "script" {
+                return ParsedNode::Script(script_element(&handle, attrs_map));
+            }
+            let children = handle
+                .children
+                .borrow()
+                .iter()
+                .map(|child| process_node(child.clone(), scripting_enabled, preformatted || tag == "pre"))
+                .filter(|node| match node {
+                    ParsedNode::Text(s) => !s.is_empty(),
+                    _ => true,
+                })
+                .collect::<Vec<_>>();
+            ParsedNode::Element {
+                tag,
+                attrs: attrs_map,
+                children,
+                reactive_state: None,
+                framework: None,
+                node_id: None,
+            }
+        }
+        _ => ParsedNode::Text("".into()),
+    }
+}
+
+fn script_element(handle: &Handle, mut attrs: HashMap<String, String>) -> ScriptElement {
+    let text = handle
+        .children
+        .borrow()
+        .iter()
+        .filter_map(|child| match &child.data {
+            NodeData::Text { contents } => Some(contents.borrow().to_string()),
+            _ => None,
+        })
+        .collect::<String>();
+    ScriptElement {
+        kind: ScriptKind::from_type(attrs.get("type").map(String::as_str)),
+        src: attrs.remove("src").filter(|src| !src.trim().is_empty()),
+        is_async: attrs.contains_key("async"),
+        defer: attrs.contains_key("defer"),
+        nomodule: attrs.contains_key("nomodule"),
+        nonce: attrs.remove("nonce"),
+        text,
+    }
+}
+
+fn hydrate_framework_markers(node: &mut ParsedNode, html: &str) -> Result<Option<FrameworkType>> {
+    if let ParsedNode::Element {
+        attrs,
+        reactive_state,
+        framework,
+        children,
+        ..
+    } = node
+    {
+        log::info!("Проверка маркеров фреймворка в элементе с атрибутами: {:?}", attrs);
+
+        // --- ЛОГИКА ОБНАРУЖЕНИЯ ---
+        let detected_fw = if html.contains(r#"id="__NEXT_DATA__""#) {
+            // Извлечение состояния и обнаружение
+            if let Some(json_str) = extract_script_content(html, "__NEXT_DATA__") {
+                *reactive_state = Some(serde_json::from_str(&json_str)?);
+                log::info!("Обнаружен React/Next.js");
+                Some(FrameworkType::ReactNext)
+            } else {
+                log::info!("Маркер React/Next.js найден, но состояние не извлечено");
+                None
+            }
+        } else if attrs.contains_key("ng-version") {
+            log::info!("Обнаружен Angular");
+            Some(FrameworkType::Angular)
+        } else if html.contains("window.__NUXT__") {
+            // Извлечение состояния и обнаружение
+            if let Some(json_str) = extract_script_content(html, "__NUXT__") {
+                *reactive_state = Some(serde_json::from_str(&json_str)?);
+                log::info!("Обнаружен Vue/Nuxt");
+                Some(FrameworkType::VueNuxt)
+            } else {
+                log::info!("Маркер Vue/Nuxt найден, но состояние не извлечено");
+                None
+            }
+        } else if html.contains(r#"data-sveltekit-fetched"#) || attrs.contains_key("data-h") {
+            // Извлечение состояния и обнаружение
+            if let Some(json_str) = extract_script_content(html, "data-sveltekit-fetched") {
+                *reactive_state = Some(serde_json::from_str(&json_str)?);
+                log::info!("Обнаружен SvelteKit");
+                Some(FrameworkType::SvelteKit)
+            } else {
+                log::info!("Маркер SvelteKit найден, но состояние не извлечено");
+                None
+            }
+        } else if html.contains(".wasm") || html.contains(r#"<script type="module">"#) {
+            log::info!("Обнаружен WASM-модуль");
+            Some(FrameworkType::WasmModule)
+        } else {
+            None
+        };
+
+        // Отправка обнаруженного фреймворка в канал
+        if let Some(fw) = &detected_fw {
+            if let Err(e) = FRAMEWORK_CHANNEL.0.send(fw.clone()) {
+                log::error!("Ошибка отправки типа фреймворка в канал: {}", e);
+            } else {
+                log::info!("Тип фреймворка отправлен в канал: {:?}", fw);
+            }
+        }
+
+        // --- КОНЕЦ ЛОГИКИ ОБНАРУЖЕНИЯ ---
+
+        // 1. Сохраняем обнаруженный фреймворк в текущем узле (для DOM)
+        *framework = detected_fw.clone();
+
+        // 2. Если фреймворк обнаружен на этом уровне, возвращаем его
+        if detected_fw.is_some() {
+            return Ok(detected_fw);
+        }
+
+        // 3. Рекурсия по детям, если фреймворк еще не найден
+        for child in children.iter_mut() {
+            if let Some(fw) = hydrate_framework_markers(child, html)? {
+                // Возвращаем первый найденный фреймворк и прекращаем поиск
+                return Ok(Some(fw));
+            }
+        }
+    }
+    // Если ничего не найдено в этом узле или его детях, возвращаем None
+    Ok(None)
+}
+
+fn extract_script_content(html: &str, pattern: &str) -> Option<String> {
+    if let Some(start) = html.find(&format!(r#"<script id="{}" type="application/json">"#, pattern)) {
+        let content_start = start + format!(r#"<script id="{}" type="application/json">"#, pattern).len();
+        if let Some(end) = html[content_start..].find("</script>") {
+            return Some(html[content_start..content_start + end].trim().to_string());
+        }
+    } else if let Some(start) = html.find(pattern) { // Фолбек для window.__NUXT__ или data-sveltekit-fetched
+        // Это грубая эвристика и может требовать уточнения в зависимости от точной разметки,
+        // но она пытается захватить блоб JSON из присвоения переменной JavaScript.
+        let end = html[start..].find("</script>").unwrap_or(html.len());
+        let script_content = &html[start..start + end];
+
+        // Грубое извлечение JSON-подобного блоба из присвоения переменной JavaScript
+        if let Some(json_start) = script_content.find('{') {
+            let potential_json = &script_content[json_start..];
+            let mut open_brackets = 0;
+            let mut json_end = 0;
+            for (i, c) in potential_json.chars().enumerate() {
+                if c == '{' {
+                    open_brackets += 1;
+                } else if c == '}' {
+                    open_brackets -= 1;
+                    if open_brackets == 0 {
+                        json_end = i + 1;
+                        break;
+                    }
+                }
+            }
+            if json_end > 0 {
+                return Some(potential_json[..json_end].to_string());
+            }
+        }
+    }
+    None
+}
+
+pub fn collect_text(node: &ParsedNode) -> String {
+    match node {
+        ParsedNode::Text(text) => text.clone(),
+        ParsedNode::Script(_) => String::new(),
+        ParsedNode::Element { children, .. } => {
+            let mut text = String::new();
+            for child in children {
+                let child_text = collect_text(child);
+                if !child_text.is_empty() {
+                    if !text.is_empty() {
+                        text.push(' ');
+                    }
+                    text.push_str(&child_text);
+                }
+            }
+            text
+        }
+    }
+}
+
+// === FILE: dom\scripts.rs ===
+use crate::core::engine::{BroEngine, UrlResponse};
+use crate::core::page_state::PageId;
+use crate::core::policy::{PagePolicy, ResourceKind};
+use crate::dom::parser::{ParsedNode, ScriptElement, ScriptKind};
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::stream::{FuturesUnordered, StreamExt};
+use reqwest::header::CONTENT_TYPE;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+const JAVASCRIPT_MIME_TYPES: &[&str] = &[
+    "text/javascript",
+    "application/javascript",
+    "application/ecmascript",
+    "application/x-javascript",
+    "text/ecmascript",
+    "text/x-javascript",
+];
+
+// Скрипты страницы в порядке документа
+pub fn collect_scripts(node: &ParsedNode) -> Vec<ScriptElement> {
+    fn walk(node: &ParsedNode, scripts: &mut Vec<ScriptElement>) {
+        match node {
+            ParsedNode::Script(script) => scripts.push(script.clone()),
+            ParsedNode::Element { children, .. } => children.iter().for_each(|child| walk(child, scripts)),
+            ParsedNode::Text(_) => {}
+        }
+    }
+    let mut scripts = Vec::new();
+    walk(node, &mut scripts);
+    scripts
+}
+
+// Загрузка и исполнение скриптов страницы; порядок исполнения задаёт run_page_scripts
+#[async_trait]
+pub trait ScriptHost: Send + Sync + 'static {
+    async fn fetch(&self, policy: &PagePolicy, url: &str) -> Result<UrlResponse>;
+    async fn run_inline(&self, page: PageId, policy: &PagePolicy, kind: &ScriptKind, script: &str, nonce: Option<&str>) -> Result<()>;
+    async fn run_external(&self, page: PageId, kind: &ScriptKind, source: &str) -> Result<()>;
+}
+
+#[async_trait]
+impl ScriptHost for BroEngine {
+    async fn fetch(&self, policy: &PagePolicy, url: &str) -> Result<UrlResponse> {
+        self.fetch_subresource(policy, ResourceKind::Script, url).await
+    }
+
+    async fn run_inline(&self, page: PageId, policy: &PagePolicy, kind: &ScriptKind, script: &str, nonce: Option<&str>) -> Result<()> {
+        self.run_inline_script(page, policy, kind, script, nonce).await
+    }
+
+    async fn run_external(&self, page: PageId, kind: &ScriptKind, source: &str) -> Result<()> {
+        self.run_script_source(page, kind, source).await
+    }
+}
+
+enum ScriptSource {
+    Inline(String),
+    External(String, JoinHandle<Result<String>>),
+}
+
+struct PendingScript {
+    element: ScriptElement,
+    source: ScriptSource,
+}
+
+// Исполняет скрипты в порядке спецификации: синхронные по порядку документа, затем defer
+// и модули без async по порядку; async исполняются по мере загрузки, между упорядоченными.
+// Внешние скрипты загружаются параллельно заранее, как это делает preload-сканер.
+pub async fn run_page_scripts<H: ScriptHost>(
+    engine: Arc<H>,
+    page: PageId,
+    policy: Arc<PagePolicy>,
+    scripts: Vec<ScriptElement>,
+) {
+    let mut sync = Vec::new();
+    let mut deferred = Vec::new();
+    let mut async_scripts = Vec::new();
+
+    for element in scripts {
+        let inline = element.src.is_none();
+        // Браузер с поддержкой модулей пропускает классические скрипты с nomodule
+        match (&element.kind, inline) {
+            (ScriptKind::Data(_), _) => continue,
+            (ScriptKind::Classic, _) if element.nomodule => continue,
+            _ => {}
+        }
+        let source = match &element.src {
+            None => ScriptSource::Inline(element.text.clone()),
+            Some(src) => {
+                let engine = engine.clone();
+                let policy = policy.clone();
+                let url = src.clone();
+                let module = matches!(element.kind, ScriptKind::Module);
+                ScriptSource::External(
+                    src.clone(),
+                    tokio::spawn(async move {
+                        let response = engine.fetch(&policy, &url).await?;
+                        check_script_response(&response, module)?;
+                        Ok(response.html)
+                    }),
+                )
+            }
+        };
+        let queue = match (&element.kind, inline) {
+            // У встроенных классических скриптов async и defer игнорируются
+            (ScriptKind::Classic, true) => &mut sync,
+            (ScriptKind::Classic, false) if element.is_async => &mut async_scripts,
+            (ScriptKind::Classic, false) if element.defer => &mut deferred,
+            (ScriptKind::Classic, false) => &mut sync,
+            (_, _) if element.is_async => &mut async_scripts,
+            _ => &mut deferred,
+        };
+        queue.push(PendingScript { element, source });
+    }
+
+    log::info!(
+        "Скрипты страницы {}: синхронных {}, отложенных {}, async {}",
+        page,
+        sync.len(),
+        deferred.len(),
+        async_scripts.len()
+    );
+
+    // Обе очереди отдают скрипты в один realm, где каждый выполняется целиком
+    let ordered = async {
+        for PendingScript { element, source } in sync.into_iter().chain(deferred) {
+            if let Some(text) = load(source).await {
+                execute(engine.as_ref(), page, &policy, &element, &text).await;
+            }
+        }
+    };
+    let unordered = async {
+        let mut ready: FuturesUnordered<_> = async_scripts
+            .into_iter()
+            .map(|PendingScript { element, source }| async move { (element, load(source).await) })
+            .collect();
+        while let Some((element, text)) = ready.next().await {
+            if let Some(text) = text {
+                execute(engine.as_ref(), page, &policy, &element, &text).await;
+            }
+        }
+    };
+    futures::join!(ordered, unordered);
+}
+
+// Ответ с ошибкой (страница 404) не исполняется как скрипт. Модули требуют JavaScript MIME,
+// как и классические скрипты при X-Content-Type-Options: nosniff; иначе отклоняются
+// типы, которые заведомо не являются скриптом
+fn check_script_response(response: &UrlResponse, module: bool) -> Result<()> {
+    if !(200..300).contains(&response.status) {
+        return Err(anyhow::anyhow!("{}: скрипт не загружен, статус {}", response.url, response.status));
+    }
+    let mime = response
+        .headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(';').next().unwrap_or_default().trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    let nosniff = response
+        .headers
+        .get("x-content-type-options")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case("nosniff"));
+    let javascript = JAVASCRIPT_MIME_TYPES.contains(&mime.as_str());
+    let blocked = mime.starts_with("image/")
+        || mime.starts_with("audio/")
+        || mime.starts_with("video/")
+        || mime == "text/csv"
+        || mime == "text/html";
+    if javascript || (!module && !nosniff && !blocked) {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "{}: MIME-тип {:?} не допускается для {}",
+            response.url,
+            mime,
+            if module { "модуля" } else { "скрипта" }
+        ))
+    }
+}
+
+async fn load(source: ScriptSource) -> Option<String> {
+    let (src, handle) = match source {
+        ScriptSource::Inline(text) => return Some(text),
+        ScriptSource::External(src, handle) => (src, handle),
+    };
+    match handle.await {
+        Ok(Ok(text)) => Some(text),
+        Ok(Err(e)) => {
+            log::warn!("Скрипт {} не загружен: {}", src, e);
+            None
+        }
+        Err(e) => {
+            log::error!("Задача загрузки скрипта {} прервана: {}", src, e);
+            None
+        }
+    }
+}
+
+// Ошибка одного скрипта не останавливает остальные; она уже отражена в консоли
+async fn execute<H: ScriptHost>(engine: &H, page: PageId, policy: &PagePolicy, element: &ScriptElement, text: &str) {
+    let result = match &element.src {
+        None => engine.run_inline(page, policy, &element.kind, text, element.nonce.as_deref()).await,
+        Some(_) => engine.run_external(page, &element.kind, text).await,
+    };
+    if let Err(e) = result {
+        log::warn!("Ошибка выполнения скрипта {}: {}", element.src.as_deref().unwrap_or("(встроенный)"), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::parser::parse_and_process;
+    use parking_lot::Mutex;
+    use reqwest::header::{HeaderMap, HeaderValue};
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    // Ответ на src: тело скрипта — имя файла без .js
+    #[derive(Clone, Copy)]
+    struct Served {
+        delay_ms: u64,
+        mime: &'static str,
+        status: u16,
+        nosniff: bool,
+    }
+
+    fn served(delay_ms: u64, mime: &'static str) -> Served {
+        Served { delay_ms, mime, status: 200, nosniff: false }
+    }
+
+    // Записывает, в каком порядке скрипты были исполнены
+    struct FakeHost {
+        served: HashMap<&'static str, Served>,
+        executed: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl ScriptHost for FakeHost {
+        async fn fetch(&self, _policy: &PagePolicy, url: &str) -> Result<UrlResponse> {
+            let served = *self.served.get(url).ok_or_else(|| anyhow::anyhow!("{} не найден", url))?;
+            tokio::time::sleep(Duration::from_millis(served.delay_ms)).await;
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static(served.mime));
+            if served.nosniff {
+                headers.insert("x-content-type-options", HeaderValue::from_static("nosniff"));
+            }
+            Ok(UrlResponse {
+                url: url.to_string(),
+                html: url.trim_end_matches(".js").to_string(),
+                headers,
+                status: served.status,
+                duration: Duration::ZERO,
+            })
+        }
+
+        async fn run_inline(&self, _page: PageId, _policy: &PagePolicy, _kind: &ScriptKind, script: &str, _nonce: Option<&str>) -> Result<()> {
+            self.executed.lock().push(script.trim().to_string());
+            Ok(())
+        }
+
+        async fn run_external(&self, _page: PageId, _kind: &ScriptKind, source: &str) -> Result<()> {
+            self.executed.lock().push(source.to_string());
+            Ok(())
+        }
+    }
+
+    async fn run(html: &str, served: &[(&'static str, Served)]) -> Vec<String> {
+        let (node, _) = parse_and_process(html, true).unwrap();
+        let host = Arc::new(FakeHost { served: served.iter().copied().collect(), executed: Mutex::new(Vec::new()) });
+        run_page_scripts(host.clone(), PageId::DEFAULT, Arc::new(PagePolicy::default()), collect_scripts(&node)).await;
+        let executed = host.executed.lock().clone();
+        executed
+    }
+
+    #[tokio::test]
+    async fn sync_then_deferred_with_async_in_between() {
+        let html = r#"<html><head>
+            <script src="defer1.js" defer></script>
+            <script>inline-1</script>
+            <script src="sync-slow.js"></script>
+            <script src="async-fast.js" async></script>
+            <script src="async-slow.js" async></script>
+            <script type="module">module-inline</script>
+        </head><body>
+            <script src="defer2.js" defer></script>
+            <script async defer>inline-2</script>
+        </body></html>"#;
+        let executed = run(
+            html,
+            &[
+                ("defer1.js", served(0, "text/javascript")),
+                ("sync-slow.js", served(50, "text/javascript")),
+                ("async-fast.js", served(0, "text/javascript")),
+                ("async-slow.js", served(200, "text/javascript")),
+                ("defer2.js", served(0, "text/javascript")),
+            ],
+        )
+        .await;
+        let ordered: Vec<&str> = executed.iter().map(String::as_str).filter(|name| !name.starts_with("async-")).collect();
+        // У встроенного классического скрипта async и defer не действуют
+        assert_eq!(ordered, ["inline-1", "sync-slow", "inline-2", "defer1", "module-inline", "defer2"]);
+        let position = |name: &str| executed.iter().position(|entry| entry == name).unwrap();
+        // async не ждёт медленный синхронный скрипт, а медленный async — конца документа
+        assert!(position("async-fast") < position("sync-slow"), "{:?}", executed);
+        assert_eq!(executed.last().map(String::as_str), Some("async-slow"), "{:?}", executed);
+    }
+
+    #[tokio::test]
+    async fn nomodule_data_blocks_and_noscript_are_skipped() {
+        let html = r#"<html><body>
+            <script nomodule>legacy</script>
+            <script type="application/json">{"data": 1}</script>
+            <script type="text/template">template</script>
+            <noscript><script>noscript-inner</script></noscript>
+            <script type="module" nomodule>module-nomodule</script>
+            <script type="text/javascript">modern</script>
+        </body></html>"#;
+        assert_eq!(run(html, &[]).await, ["modern", "module-nomodule"]);
+    }
+
+    #[tokio::test]
+    async fn scripts_with_wrong_mime_or_status_are_not_run() {
+        let html = r#"<html><body>
+            <script src="missing.js"></script>
+            <script src="image.js"></script>
+            <script src="page.js"></script>
+            <script src="nosniff.js"></script>
+            <script src="json.js"></script>
+            <script type="module" src="module-plain.js"></script>
+            <script type="module" src="module-ok.js"></script>
+        </body></html>"#;
+        let executed = run(
+            html,
+            &[
+                ("missing.js", Served { status: 404, ..served(0, "text/javascript") }),
+                ("image.js", served(0, "image/png")),
+                ("page.js", served(0, "text/html")),
+                ("nosniff.js", Served { nosniff: true, ..served(0, "text/plain") }),
+                ("json.js", served(0, "application/json")),
+                ("module-plain.js", served(0, "text/plain")),
+                ("module-ok.js", served(0, "text/javascript; charset=utf-8")),
+            ],
+        )
+        .await;
+        // Классический скрипт без nosniff допускает нестрогий MIME, модуль — нет
+        assert_eq!(executed, ["json", "module-ok"]);
+    }
+}
+
+// === FILE: dom\style.rs ===
+use anyhow::Result;
+use std::sync::Arc;
+use crate::net::Network;
+pub struct Style;
+impl Style {
+    pub fn new(_network: Arc<Network>) -> Result<Self> {
+        println!("Style initialized");
+        Ok(Self)
+    }
+}
+
+// === FILE: dom\tree.rs ===
+use crate::dom::parser::{ParsedNode, collect_text, parse_and_process};
+use crate::dom::live::{DomEvent, DomEventKind, NodeId};
+use crate::core::engine::referrer_headers;
+use crate::core::policy::{PagePolicy, PolicyViolation, ResourceKind};
+use crate::core::interfaces::IoManagerTrait;
+use crate::core::io_manager::ResourceType;
+use egui::{Ui, RichText, TextStyle, Label, ColorImage, TextureHandle, Color32};
+use egui::text::{LayoutJob, TextFormat};
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+use once_cell::sync::Lazy;
+use tokio::sync::mpsc::{UnboundedSender, UnboundedReceiver};
+use std::collections::{HashMap, VecDeque};
+
+static IMAGE_CACHE: Lazy<Mutex<HashMap<String, Arc<TextureHandle>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Цвета подсветки исходного кода (view-source:)
+fn source_color(class: &str, dark: bool) -> Option<Color32> {
+    let (dark_color, light_color) = match class {
+        "vs-tag" => (Color32::from_rgb(86, 156, 214), Color32::from_rgb(0, 0, 170)),
+        "vs-attr" => (Color32::from_rgb(156, 220, 254), Color32::from_rgb(153, 69, 0)),
+        "vs-value" => (Color32::from_rgb(206, 145, 120), Color32::from_rgb(26, 26, 166)),
+        "vs-comment" => (Color32::from_rgb(106, 153, 85), Color32::from_rgb(35, 110, 37)),
+        "vs-doctype" => (Color32::GRAY, Color32::DARK_GRAY),
+        _ => return None,
+    };
+    Some(if dark { dark_color } else { light_color })
+}
+
+// Текст <pre> без схлопывания пробелов; span с классом vs-* задаёт цвет
+fn append_preformatted(job: &mut LayoutJob, ui: &Ui, node: &ParsedNode, color: Option<Color32>) {
+    match node {
+        ParsedNode::Text(text) => job.append(
+            text,
+            0.0,
+            TextFormat {
+                font_id: TextStyle::Monospace.resolve(ui.style()),
+                color: color.unwrap_or_else(|| ui.visuals().text_color()),
+                ..Default::default()
+            },
+        ),
+        ParsedNode::Script(_) => {}
+        ParsedNode::Element { tag, attrs, children, .. } => {
+            if tag == "br" {
+                append_preformatted(job, ui, &ParsedNode::Text("\n".into()), color);
+                return;
+            }
+            let color = attrs
+                .get("class")
+                .and_then(|class| source_color(class, ui.visuals().dark_mode))
+                .or(color);
+            for child in children {
+                append_preformatted(job, ui, child, color);
+            }
+        }
+    }
+}
+
+// Содержимое <iframe>: документ рендерится со своей политикой, скрипты в нём не исполняются
+enum FrameState {
+    Loading,
+    Loaded { policy: Arc<PagePolicy>, node: ParsedNode },
+    Blocked(String),
+}
+
+type FrameResult = (String, std::result::Result<(Arc<PagePolicy>, ParsedNode), (String, Option<PolicyViolation>)>);
+
+pub struct DomRenderer {
+    pub clicked_links: VecDeque<String>,
+    pub clicked_buttons: VecDeque<String>,
+    pub dom_events: VecDeque<(DomEvent, Option<String>)>, // События для обработчиков скриптов страницы и ссылка, если действие не отменено
+    image_tx: UnboundedSender<(String, Result<image::DynamicImage>)>,
+    image_rx: UnboundedReceiver<(String, Result<image::DynamicImage>)>,
+    egui_ctx: Option<egui::Context>,
+    needs_repaint: bool, // Флаг необходимости перерисовки
+    page_policy: Arc<PagePolicy>,
+    pub policy_violations: VecDeque<PolicyViolation>,
+    io: Option<Arc<dyn IoManagerTrait + Send + Sync>>,
+    frames: HashMap<String, FrameState>,
+    frame_tx: UnboundedSender<FrameResult>,
+    frame_rx: UnboundedReceiver<FrameResult>,
+}
+
+impl DomRenderer {
+    pub fn new() -> Self {
+        let (image_tx, image_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (frame_tx, frame_rx) = tokio::sync::mpsc::unbounded_channel();
+        log::info!("Создание DomRenderer");
+        Self {
+            clicked_links: VecDeque::new(),
+            clicked_buttons: VecDeque::new(),
+            dom_events: VecDeque::new(),
+            image_tx,
+            image_rx,
+            egui_ctx: None,
+            needs_repaint: true, // Изначально требуется рендер
+            page_policy: Arc::new(PagePolicy::default()),
+            policy_violations: VecDeque::new(),
+            io: None,
+            frames: HashMap::new(),
+            frame_tx,
+            frame_rx,
+        }
+    }
+
+    // Изображения загружаются через IoManager (file:, data:, ipfs: и кэш HTTP)
+    pub fn set_io_manager(&mut self, io: Arc<dyn IoManagerTrait + Send + Sync>) {
+        self.io = Some(io);
+    }
+
+    pub fn set_page_policy(&mut self, policy: Arc<PagePolicy>) {
+        self.page_policy = policy;
+        self.frames.clear();
+        self.needs_repaint = true;
+    }
+
+    pub fn set_egui_context(&mut self, ctx: egui::Context) {
+        log::info!("Установка контекста egui для DomRenderer");
+        self.egui_ctx = Some(ctx);
+    }
+
+    pub fn needs_repaint(&self) -> bool {
+        self.needs_repaint
+    }
+
+    pub fn clear_repaint_flag(&mut self) {
+        log::info!("Сброс флага перерисовки в DomRenderer");
+        self.needs_repaint = false;
+    }
+
+    fn process_frame_responses(&mut self) {
+        while let Ok((src, result)) = self.frame_rx.try_recv() {
+            let state = match result {
+                Ok((policy, node)) => FrameState::Loaded { policy, node },
+                Err((message, violation)) => {
+                    log::warn!("Фрейм {} не показан: {}", src, message);
+                    self.policy_violations.extend(violation);
+                    FrameState::Blocked(message)
+                }
+            };
+            // Ответ для документа, который уже сменился, отбрасывается
+            if let Some(current) = self.frames.get_mut(&src) {
+                *current = state;
+                self.needs_repaint = true;
+            }
+        }
+    }
+
+    pub fn process_image_responses(&mut self, ui: &mut Ui) {
+        self.process_frame_responses();
+        let mut image_processed = false;
+        while let Ok((src, result)) = self.image_rx.try_recv() {
+            log::info!("Обработка ответа для изображения: {}", src);
+            let mut cache = IMAGE_CACHE.lock().unwrap();
+            match result {
+                Ok(img) => {
+                    let rgba = img.to_rgba8();
+                    let size = [rgba.width() as usize, rgba.height() as usize];
+                    let color_image = ColorImage::from_rgba_unmultiplied(size, rgba.as_raw());
+                    if let Some(ctx) = &self.egui_ctx {
+                        let tex = ctx.load_texture(src.clone(), color_image, Default::default());
+                        cache.insert(src.clone(), Arc::new(tex));
+                        image_processed = true;
+                        self.needs_repaint = true; // Изображение загружено, нужна перерисовка
+                        log::info!("Изображение {} успешно загружено и добавлено в кэш", src);
+                    }
+                }
+                Err(e) => {
+                    log::warn!("Ошибка загрузки изображения {}: {}", src, e);
+                    cache.insert(src.clone(), Arc::new(ui.ctx().load_texture(
+                        src.clone(),
+                        ColorImage::new([1, 1], vec![egui::Color32::GRAY]),
+                        Default::default(),
+                    )));
+                }
+            }
+        }
+        if image_processed && self.egui_ctx.as_ref().map_or(false, |ctx| ctx.has_requested_repaint()) {
+            log::info!("DomRenderer запросил перерисовку из-за новых изображений");
+            self.needs_repaint = true;
+        }
+    }
+
+    pub async fn render_node(&mut self, ui: &mut Ui, node: &ParsedNode) {
+        match node {
+            ParsedNode::Text(text) => {
+                if !text.is_empty() {
+                    //log::info!("Рендер текста: {} (длина: {})", text, text.len());
+                    ui.label(RichText::new(text));
+                }
+            }
+            ParsedNode::Script(_) => {}
+            ParsedNode::Element { tag, attrs, children, node_id, .. } => {
+                //log::info!("Рендер элемента: {}", tag);
+                match tag.as_str() {
+                    "a" => {
+                        if let Some(href) = attrs.get("href") {
+                            let link_text = collect_text(node);
+                            //log::info!("Рендер ссылки: {} ({})", link_text, href);
+                            let response = ui.link(link_text).clicked();
+                            if response {
+                                // Относительная ссылка разрешается от адреса страницы
+                                match self.page_policy.resolve(href) {
+                                    Ok(target) => {
+                                        let target = target.to_string();
+                                        // Переход по ссылке из DOM ждёт обработчиков: preventDefault его отменяет
+                                        match node_id {
+                                            Some(node) => self
+                                                .dom_events
+                                                .push_back((DomEvent { node: *node, kind: DomEventKind::Click }, Some(target.clone()))),
+                                            None => self.clicked_links.push_back(target.clone()),
+                                        }
+                                        self.needs_repaint = true; // Клик требует перерисовки
+                                        log::info!("Клик по ссылке: {}, добавлено в очередь", target);
+                                    }
+                                    Err(e) => log::warn!("Некорректная ссылка {}: {}", href, e),
+                                }
+                            }
+                        }
+                    }
+                    "button" => {
+                        let text = collect_text(node);
+                        //log::info!("Рендер кнопки: {}", text);
+                        let response = ui.button(&text).clicked();
+                        if response {
+                            self.push_dom_event(*node_id, DomEventKind::Click);
+                            self.clicked_buttons.push_back("button".into());
+                            self.needs_repaint = true; // Клик требует перерисовки
+                            log::info!("Клик по кнопке: {}, добавлено в очередь", text);
+                        }
+                    }
+                    "input" => {
+                        log::info!("Рендер input: {:?}", attrs);
+                        self.render_input(ui, attrs, *node_id);
+                    }
+                    "img" => {
+                        if let Some(src) = attrs.get("src") {
+                            //log::info!("Рендер изображения: {}", src);
+                            let mut cache = IMAGE_CACHE.lock().unwrap();
+                            if let Some(tex) = cache.get(src) {
+                                ui.image(&**tex);
+                            } else {
+                                // Заглушка в кэше гарантирует одну проверку политики на изображение
+                                cache.insert(src.clone(), Arc::new(ui.ctx().load_texture(
+                                    src.clone(),
+                                    ColorImage::new([1, 1], vec![egui::Color32::GRAY]),
+                                    Default::default(),
+                                )));
+                                match self.page_policy.check_resource(ResourceKind::Image, src) {
+                                    Ok(url) => {
+                                        ui.label("[Загрузка изображения...]");
+                                        let tx = self.image_tx.clone();
+                                        let headers = referrer_headers(&self.page_policy, url.as_str());
+                                        tokio::spawn(Self::load_image_async(self.io.clone(), src.clone(), url.to_string(), headers, tx));
+                                        log::info!("Запущена асинхронная загрузка изображения: {}", url);
+                                    }
+                                    Err(violation) => {
+                                        ui.label("[Изображение заблокировано политикой безопасности]");
+                                        self.policy_violations.push_back(violation);
+                                    }
+                                }
+                                self.needs_repaint = true; // Загрузка изображения требует перерисовки
+                            }
+                        }
+                    }
+                    "iframe" => {
+                        if let Some(src) = attrs.get("src") {
+                            self.render_frame(ui, src);
+                        }
+                    }
+                    // Встроенные медиа и объекты не воспроизводятся: при разрешении политики
+                    // предлагается открыть их во вкладке
+                    "video" | "audio" | "embed" | "object" => {
+                        let (kind, src) = match tag.as_str() {
+                            "object" => (ResourceKind::Object, attrs.get("data")),
+                            "embed" => (ResourceKind::Object, attrs.get("src")),
+                            _ => (ResourceKind::Media, attrs.get("src")),
+                        };
+                        if let Some(src) = src {
+                            match self.page_policy.check_resource(kind, src) {
+                                Ok(url) => {
+                                    if ui.link(format!("[{}: {}]", tag, url)).clicked() {
+                                        self.clicked_links.push_back(url.to_string());
+                                        self.needs_repaint = true;
+                                    }
+                                }
+                                Err(violation) => {
+                                    ui.label(format!("[{} заблокирован политикой безопасности]", tag));
+                                    self.policy_violations.push_back(violation);
+                                }
+                            }
+                        }
+                    }
+                    "span" => {
+                        //log::info!("Рендер span");
+                        let _ = ui.horizontal_wrapped(|ui| {
+                            for child in children {
+                                pollster::block_on(self.render_node(ui, child));
+                            }
+                        });
+                    }
+                    "ul" | "ol" => {
+                        //log::info!("Рендер списка: {}", tag);
+                        for child in children {
+                            pollster::block_on(self.render_node(ui, child));
+                        }
+                    }
+                    "li" => {
+                        //log::info!("Рендер элемента списка");
+                        let _ = ui.horizontal(|ui| {
+                            ui.label("•");
+                            for child in children {
+                                pollster::block_on(self.render_node(ui, child));
+                            }
+                        });
+                    }
+                    "details" => {
+                        //log::info!("Рендер details");
+                        let summary = children
+                            .iter()
+                            .find(|c| matches!(c, ParsedNode::Element { tag, .. } if tag == "summary"))
+                            .map(|c| collect_text(c))
+                            .unwrap_or_else(|| "Подробности".into());
+                        let _ = ui.collapsing(summary, |ui| {
+                            for child in children {
+                                if let ParsedNode::Element { tag, .. } = child {
+                                    if tag != "summary" {
+                                        pollster::block_on(self.render_node(ui, child));
+                                    }
+                                }
+                            }
+                        });
+                        self.needs_repaint = true; // Изменение состояния details требует перерисовки
+                    }
+                    "br" => {
+                        //log::info!("Рендер br");
+                        ui.end_row();
+                    }
+                    "hr" => {
+                        //log::info!("Рендер hr");
+                        let _ = ui.separator();
+                    }
+                    "p" | "div" | "section" => {
+                        //log::info!("Рендер контейнера: {}", tag);
+                        let _ = ui.group(|ui| {
+                            for child in children {
+                                pollster::block_on(self.render_node(ui, child));
+                            }
+                        });
+                    }
+                    "pre" => {
+                        //log::info!("Рендер pre");
+                        let mut job = LayoutJob::default();
+                        append_preformatted(&mut job, ui, node, None);
+                        ui.add(Label::new(job).wrap());
+                    }
+                    _ => {
+                        //log::info!("Рендер прочих элементов: {}", tag);
+                        for child in children {
+                            pollster::block_on(self.render_node(ui, child));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Проверка frame-src выполняется один раз на src; содержимое показывается
+    // только если его X-Frame-Options и frame-ancestors разрешают встраивание в эту страницу
+    fn render_frame(&mut self, ui: &mut Ui, src: &str) {
+        if !self.frames.contains_key(src) {
+            let state = match self.page_policy.check_resource(ResourceKind::Frame, src) {
+                Ok(url) => {
+                    let headers = referrer_headers(&self.page_policy, url.as_str());
+                    let ancestor = self.page_policy.page_url().map(|u| u.to_string()).unwrap_or_default();
+                    tokio::spawn(Self::load_frame_async(
+                        self.io.clone(),
+                        src.to_string(),
+                        url.to_string(),
+                        ancestor,
+                        headers,
+                        self.frame_tx.clone(),
+                    ));
+                    FrameState::Loading
+                }
+                Err(violation) => {
+                    let message = violation.to_string();
+                    self.policy_violations.push_back(violation);
+                    FrameState::Blocked(message)
+                }
+            };
+            self.frames.insert(src.to_string(), state);
+            self.needs_repaint = true;
+        }
+        match self.frames.get(src) {
+            Some(FrameState::Loading) | None => {
+                ui.label("[Загрузка фрейма...]");
+            }
+            Some(FrameState::Blocked(message)) => {
+                ui.label(format!("[Фрейм заблокирован: {}]", message));
+            }
+            Some(FrameState::Loaded { policy, node }) => {
+                let (policy, node) = (policy.clone(), node.clone());
+                // Вложенные ресурсы фрейма проверяются его собственной политикой
+                let parent = std::mem::replace(&mut self.page_policy, policy);
+                let _ = ui.group(|ui| {
+                    pollster::block_on(self.render_node(ui, &node));
+                });
+                self.page_policy = parent;
+            }
+        }
+    }
+
+    async fn load_frame_async(
+        io: Option<Arc<dyn IoManagerTrait + Send + Sync>>,
+        src: String,
+        url: String,
+        ancestor: String,
+        headers: reqwest::header::HeaderMap,
+        tx: UnboundedSender<FrameResult>,
+    ) {
+        let result = async {
+            let io = io.ok_or_else(|| ("IoManager не подключён к рендереру".to_string(), None))?;
+            let resource = io
+                .load_with_headers(&url, ResourceType::Document, headers)
+                .await
+                .map_err(|e| (e.to_string(), None))?;
+            let policy = PagePolicy::from_headers(&resource.url, &resource.headers);
+            policy
+                .check_embedding(&ancestor)
+                .map_err(|violation| (violation.to_string(), Some(violation)))?;
+            let (node, _) = parse_and_process(&resource.text(), false).map_err(|e| (e.to_string(), None))?;
+            Ok((Arc::new(policy), node))
+        }
+        .await;
+        let _ = tx.send((src, result));
+    }
+
+    async fn load_image_async(
+        io: Option<Arc<dyn IoManagerTrait + Send + Sync>>,
+        src: String,
+        url: String,
+        headers: reqwest::header::HeaderMap,
+        tx: UnboundedSender<(String, Result<image::DynamicImage>)>,
+    ) {
+        log::info!("Запуск асинхронной загрузки изображения: {}", url);
+        let result = async {
+            let io = io.ok_or_else(|| anyhow::anyhow!("IoManager не подключён к рендереру"))?;
+            // Формат определяется по содержимому, а не по расширению в src
+            io.load_with_headers(&url, ResourceType::Image, headers).await?.image()
+        }.await;
+        let _ = tx.send((src.clone(), result));
+        log::info!("Завершена асинхронная загрузка изображения: {}", src);
+    }
+
+    fn push_dom_event(&mut self, node: Option<NodeId>, kind: DomEventKind) {
+        if let Some(node) = node {
+            self.dom_events.push_back((DomEvent { node, kind }, None));
+        }
+    }
+
+    fn render_input(&mut self, ui: &mut Ui, attrs: &HashMap<String, String>, node_id: Option<NodeId>) {
+        //log::info!("Рендер поля ввода: {:?}", attrs);
+        let input_type = attrs
+            .get("type")
+            .map(|t| t.to_lowercase())
+            .unwrap_or_else(|| "text".into());
+        match input_type.as_str() {
+            "text" | "email" | "password" | "number" => {
+                let mut value = attrs.get("value").cloned().unwrap_or_default();
+                let placeholder = attrs.get("placeholder").cloned().unwrap_or_default();
+                let edit = ui.add(
+                    egui::TextEdit::singleline(&mut value)
+                        .hint_text(placeholder)
+                        .desired_width(150.0),
+                );
+                if edit.changed() {
+                    self.push_dom_event(node_id, DomEventKind::Input(value.clone()));
+                    self.clicked_buttons.push_back(format!("input_changed:{}", value));
+                    self.needs_repaint = true; // Изменение ввода требует перерисовки
+                    log::info!("Введено значение в поле ввода: {}", value);
+                }
+            }
+            "checkbox" => {
+                let checked = attrs.contains_key("checked");
+                let mut val = checked;
+                if ui.checkbox(&mut val, "").clicked() {
+                    self.push_dom_event(node_id, DomEventKind::Change(val));
+                    self.clicked_buttons.push_back(format!("checkbox_changed:{}", val));
+                    self.needs_repaint = true; // Изменение чекбокса требует перерисовки
+                    log::info!("Чекбокс изменён: {}", val);
+                }
+            }
+            "radio" => {
+                let selected = attrs.contains_key("checked");
+                let val = selected;
+                if ui.radio(val, "").clicked() {
+                    self.push_dom_event(node_id, DomEventKind::Change(true));
+                    self.clicked_buttons.push_back(format!("radio_selected:{}", val));
+                    self.needs_repaint = true; // Изменение радиокнопки требует перерисовки
+                    log::info!("Радиокнопка выбрана: {}", val);
+                }
+            }
+            _ => {
+                log::warn!("Неподдерживаемый тип ввода: {}", input_type);
+                ui.label(format!("[Неподдерживаемый тип ввода: {}]", input_type));
+            }
+        }
+    }
+
+    pub fn get_last_link_click(&mut self) -> Option<String> {
+        log::info!("Получение последнего клика по ссылке");
+        self.clicked_links.pop_back()
+    }
+
+    pub fn get_last_button_click(&mut self) -> Option<String> {
+        log::info!("Получение последнего клика по кнопке");
+        self.clicked_buttons.pop_back()
+    }
+}
+
+// === FILE: dom\vdom.rs ===
+use anyhow::Result;
+use std::sync::Arc;
+use crate::net::Network;
+pub struct VDOM;
+impl VDOM {
+    pub fn new(_network: Arc<Network>) -> Result<Self> {
+        println!("VDOM initialized");
+        Ok(Self)
+    }
+}
+
+// === FILE: net\cache.rs ===
+use crate::core::interfaces::{Credentials, NetRequest, NetResponse};
+use parking_lot::Mutex;
+use reqwest::header::{HeaderMap, HeaderValue};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+const MAX_ENTRIES: usize = 256;
+const MAX_ENTRY_SIZE: usize = 4 * 1024 * 1024;
+
+struct CacheEntry {
+    response: NetResponse,
+    stored: Instant,
+    max_age: Duration,
+    // Значения заголовков запроса, перечисленных в Vary ответа
+    vary: Vec<(String, Option<HeaderValue>)>,
+    // Ответ, полученный с cookie, не отдаётся запросу без них и наоборот
+    credentials: Credentials,
+}
+
+// Снимок записи кэша для about:cache
+#[derive(Clone, Debug)]
+pub struct CacheEntryInfo {
+    pub url: String,
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub size: usize,
+    pub age: Duration,
+    pub fresh: bool,
+}
+
+impl CacheEntry {
+    fn is_fresh(&self) -> bool {
+        self.stored.elapsed() < self.max_age
+    }
+
+    fn matches(&self, request: &NetRequest) -> bool {
+        self.credentials == request.credentials
+            && self
+                .vary
+                .iter()
+                .all(|(name, value)| request.headers.get(name.as_str()) == value.as_ref())
+    }
+}
+
+// Частный HTTP-кэш для GET-запросов: свежесть по Cache-Control max-age,
+// после истечения — условный запрос по ETag / Last-Modified
+pub struct HttpCache {
+    entries: Mutex<HashMap<String, CacheEntry>>,
+}
+
+impl HttpCache {
+    pub fn new() -> Self {
+        Self { entries: Mutex::new(HashMap::new()) }
+    }
+
+    fn is_cacheable_request(request: &NetRequest) -> bool {
+        request.method.eq_ignore_ascii_case("GET")
+            && !request.headers.contains_key("authorization")
+            && !directives(&request.headers).iter().any(|d| d == "no-store")
+    }
+
+    pub fn fresh(&self, request: &NetRequest) -> Option<NetResponse> {
+        if !Self::is_cacheable_request(request) || directives(&request.headers).iter().any(|d| d == "no-cache") {
+            return None;
+        }
+        let entries = self.entries.lock();
+        let entry = entries
+            .get(&request.url)
+            .filter(|entry| entry.is_fresh() && entry.matches(request))?;
+        log::debug!("HTTP-кэш: свежий ответ для {}", request.url);
+        Some(entry.response.clone())
+    }
+
+    // Условные заголовки для устаревшей записи; заголовки, заданные вызывающим, не трогаются
+    pub fn add_validators(&self, request: &mut NetRequest) {
+        if !Self::is_cacheable_request(request) {
+            return;
+        }
+        let entries = self.entries.lock();
+        let Some(entry) = entries.get(&request.url).filter(|entry| entry.matches(request)) else { return };
+        for (validator, condition) in [("etag", "if-none-match"), ("last-modified", "if-modified-since")] {
+            if request.headers.contains_key(condition) {
+                continue;
+            }
+            if let Some(value) = entry.response.headers.get(validator) {
+                request.headers.insert(condition, value.clone());
+            }
+        }
+    }
+
+    // Ответ 304 продлевает запись и возвращает сохранённое тело
+    pub fn revalidated(&self, request: &NetRequest, response: &NetResponse) -> Option<NetResponse> {
+        let mut entries = self.entries.lock();
+        let entry = entries.get_mut(&request.url).filter(|entry| entry.matches(request))?;
+        entry.stored = Instant::now();
+        entry.max_age = max_age(&response.headers).unwrap_or(entry.max_age);
+        log::debug!("HTTP-кэш: запись {} подтверждена сервером", request.url);
+        Some(entry.response.clone())
+    }
+
+    pub fn store(&self, request: &NetRequest, response: &NetResponse) {
+        if !Self::is_cacheable_request(request) || response.status != 200 || response.body.len() > MAX_ENTRY_SIZE {
+            return;
+        }
+        let response_directives = directives(&response.headers);
+        // Vary: * означает, что ответ нельзя сопоставить ни с одним будущим запросом
+        let vary = vary_values(request, response);
+        if vary.is_none() || response_directives.iter().any(|d| d == "no-store" || d == "private") {
+            self.entries.lock().remove(&request.url);
+            return;
+        }
+        let vary = vary.unwrap_or_default();
+        let has_validators = response.headers.contains_key("etag") || response.headers.contains_key("last-modified");
+        let max_age = if response_directives.iter().any(|d| d == "no-cache") {
+            Duration::ZERO
+        } else {
+            max_age(&response.headers).unwrap_or(Duration::ZERO)
+        };
+        if max_age.is_zero() && !has_validators {
+            return;
+        }
+        let mut entries = self.entries.lock();
+        if entries.len() >= MAX_ENTRIES && !entries.contains_key(&request.url) {
+            // Вытесняется самая старая запись
+            if let Some(oldest) = entries.iter().min_by_key(|(_, entry)| entry.stored).map(|(url, _)| url.clone()) {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            request.url.clone(),
+            CacheEntry { response: response.clone(), stored: Instant::now(), max_age, vary, credentials: request.credentials },
+        );
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().clear();
+    }
+
+    pub fn entries(&self) -> Vec<CacheEntryInfo> {
+        self.entries
+            .lock()
+            .iter()
+            .map(|(url, entry)| CacheEntryInfo {
+                url: url.clone(),
+                status: entry.response.status,
+                content_type: entry
+                    .response
+                    .headers
+                    .get("content-type")
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string),
+                size: entry.response.body.len(),
+                age: entry.stored.elapsed(),
+                fresh: entry.is_fresh(),
+            })
+            .collect()
+    }
+}
+
+impl Default for HttpCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn directives(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all("cache-control")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|d| d.trim().to_ascii_lowercase())
+        .filter(|d| !d.is_empty())
+        .collect()
+}
+
+fn vary_values(request: &NetRequest, response: &NetResponse) -> Option<Vec<(String, Option<HeaderValue>)>> {
+    let mut values = Vec::new();
+    for name in response
+        .headers
+        .get_all("vary")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+    {
+        if name == "*" {
+            return None;
+        }
+        let value = request.headers.get(name.as_str()).cloned();
+        values.push((name, value));
+    }
+    Some(values)
+}
+
+fn max_age(headers: &HeaderMap) -> Option<Duration> {
+    directives(headers)
+        .iter()
+        .filter_map(|d| d.strip_prefix("max-age="))
+        .find_map(|v| v.trim_matches('"').parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+// === FILE: net\cors.rs ===
+use crate::core::interfaces::{Credentials, NetRequest, NetResponse};
+use crate::core::origin::Origin;
+use parking_lot::RwLock;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+const DEFAULT_PREFLIGHT_MAX_AGE: u64 = 5;
+const MAX_PREFLIGHT_MAX_AGE: u64 = 7200;
+
+// Простые запросы уходят без предварительного OPTIONS
+pub fn needs_preflight(request: &NetRequest) -> bool {
+    !matches!(request.method.as_str(), "GET" | "HEAD" | "POST") || !unsafe_header_names(&request.headers).is_empty()
+}
+
+// Заголовки вне CORS-safelist, отсортированные и в нижнем регистре
+fn unsafe_header_names(headers: &HeaderMap) -> Vec<String> {
+    let mut names: Vec<String> = headers
+        .iter()
+        .filter(|(name, value)| !is_safelisted_request_header(name.as_str(), value))
+        .map(|(name, _)| name.as_str().to_string())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn is_safelisted_request_header(name: &str, value: &HeaderValue) -> bool {
+    match name {
+        "accept" | "accept-language" | "content-language" => value.len() <= 128,
+        "content-type" => {
+            let mime = value
+                .to_str()
+                .unwrap_or_default()
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase();
+            matches!(mime.as_str(), "application/x-www-form-urlencoded" | "multipart/form-data" | "text/plain")
+        }
+        _ => false,
+    }
+}
+
+pub fn preflight_request(origin: &Origin, request: &NetRequest) -> NetRequest {
+    let mut headers = HeaderMap::new();
+    insert_header(&mut headers, "origin", &origin.ascii_serialization());
+    insert_header(&mut headers, "access-control-request-method", &request.method);
+    let unsafe_headers = unsafe_header_names(&request.headers);
+    if !unsafe_headers.is_empty() {
+        insert_header(&mut headers, "access-control-request-headers", &unsafe_headers.join(","));
+    }
+    // Preflight никогда не несёт cookie
+    NetRequest {
+        url: request.url.clone(),
+        method: "OPTIONS".to_string(),
+        headers,
+        body: None,
+        credentials: Credentials::Omit,
+        follow_redirects: false,
+    }
+}
+
+pub fn insert_header(headers: &mut HeaderMap, name: &'static str, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(HeaderName::from_static(name), value);
+    }
+}
+
+fn header_list(headers: &HeaderMap, name: &str) -> HashSet<String> {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|token| token.trim().to_ascii_lowercase())
+        .filter(|token| !token.is_empty())
+        .collect()
+}
+
+// Access-Control-Allow-Origin должен совпадать с источником или быть "*". Для запроса
+// с cookie "*" не подходит: нужны точный источник и Access-Control-Allow-Credentials: true
+pub fn check_response(origin: &Origin, response: &NetResponse, credentials: Credentials) -> Result<(), String> {
+    let allowed = response
+        .headers
+        .get("access-control-allow-origin")
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim);
+    let credentialed = credentials == Credentials::Include;
+    match allowed {
+        Some("*") if credentialed => Err(format!(
+            "Access-Control-Allow-Origin '*' недопустим для запроса к {} с учётными данными",
+            response.url
+        )),
+        Some("*") => Ok(()),
+        Some(value) if value == origin.ascii_serialization() => {
+            let allow_credentials = response
+                .headers
+                .get("access-control-allow-credentials")
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim);
+            if credentialed && allow_credentials != Some("true") {
+                return Err(format!(
+                    "Ответ {} на запрос с учётными данными не содержит Access-Control-Allow-Credentials: true",
+                    response.url
+                ));
+            }
+            Ok(())
+        }
+        Some(value) => Err(format!(
+            "Access-Control-Allow-Origin '{}' не совпадает с источником {}",
+            value, origin
+        )),
+        None => Err(format!("Ответ {} не содержит Access-Control-Allow-Origin", response.url)),
+    }
+}
+
+// Снаружи видны только safelisted-заголовки и перечисленные в Access-Control-Expose-Headers
+pub fn filter_response_headers(headers: &HeaderMap) -> HeaderMap {
+    const SAFELISTED: [&str; 7] = [
+        "cache-control",
+        "content-language",
+        "content-length",
+        "content-type",
+        "expires",
+        "last-modified",
+        "pragma",
+    ];
+    let exposed = header_list(headers, "access-control-expose-headers");
+    let expose_all = exposed.contains("*");
+    headers
+        .iter()
+        .filter(|(name, _)| {
+            let name = name.as_str();
+            expose_all || SAFELISTED.contains(&name) || exposed.contains(name)
+        })
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+struct PreflightEntry {
+    expires: Instant,
+    methods: HashSet<String>,
+    headers: HashSet<String>,
+}
+
+impl PreflightEntry {
+    fn allows(&self, request: &NetRequest) -> bool {
+        let method_allowed = matches!(request.method.as_str(), "GET" | "HEAD" | "POST")
+            || self.methods.contains("*")
+            || self.methods.contains(&request.method.to_ascii_lowercase());
+        method_allowed
+            && unsafe_header_names(&request.headers)
+                .iter()
+                .all(|name| self.headers.contains("*") || self.headers.contains(name))
+    }
+}
+
+// Кэш результатов preflight по паре (источник, URL)
+#[derive(Default)]
+pub struct PreflightCache {
+    entries: RwLock<HashMap<(String, String), PreflightEntry>>,
+}
+
+impl PreflightCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_allowed(&self, origin: &Origin, request: &NetRequest) -> bool {
+        let key = (origin.ascii_serialization(), request.url.clone());
+        let mut entries = self.entries.write();
+        match entries.get(&key) {
+            Some(entry) if entry.expires <= Instant::now() => {
+                entries.remove(&key);
+                false
+            }
+            Some(entry) => entry.allows(request),
+            None => false,
+        }
+    }
+
+    // Проверяет ответ на preflight и запоминает разрешённые методы и заголовки
+    pub fn record(&self, origin: &Origin, request: &NetRequest, response: &NetResponse) -> Result<(), String> {
+        if !(200..300).contains(&response.status) {
+            return Err(format!("Preflight для {} вернул статус {}", request.url, response.status));
+        }
+        check_response(origin, response, request.credentials)?;
+        let max_age = response
+            .headers
+            .get("access-control-max-age")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .unwrap_or(DEFAULT_PREFLIGHT_MAX_AGE)
+            .min(MAX_PREFLIGHT_MAX_AGE);
+        let entry = PreflightEntry {
+            expires: Instant::now() + Duration::from_secs(max_age),
+            methods: header_list(&response.headers, "access-control-allow-methods"),
+            headers: header_list(&response.headers, "access-control-allow-headers"),
+        };
+        if !entry.allows(request) {
+            return Err(format!(
+                "Preflight не разрешает метод {} или заголовки запроса к {}",
+                request.method, request.url
+            ));
+        }
+        if max_age > 0 {
+            self.entries
+                .write()
+                .insert((origin.ascii_serialization(), request.url.clone()), entry);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://api.example.net/data";
+
+    fn origin() -> Origin {
+        Origin::parse("https://app.example.com:8443/")
+    }
+
+    fn request(method: &str, headers: &[(&'static str, &str)]) -> NetRequest {
+        let mut request = NetRequest::get(URL);
+        request.method = method.to_string();
+        request.credentials = Credentials::Omit;
+        for (name, value) in headers {
+            insert_header(&mut request.headers, *name, value);
+        }
+        request
+    }
+
+    fn response(status: u16, headers: &[(&'static str, &str)]) -> NetResponse {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.append(*name, HeaderValue::from_str(value).unwrap());
+        }
+        NetResponse { url: URL.to_string(), status, headers: map, body: Vec::new() }
+    }
+
+    #[test]
+    fn only_non_simple_requests_need_preflight() {
+        assert!(!needs_preflight(&request("GET", &[])));
+        assert!(!needs_preflight(&request("POST", &[("content-type", "text/plain; charset=utf-8")])));
+        assert!(needs_preflight(&request("POST", &[("content-type", "application/json")])));
+        assert!(needs_preflight(&request("PUT", &[])));
+        assert!(needs_preflight(&request("GET", &[("x-token", "1")])));
+        assert!(needs_preflight(&request("GET", &[("accept", "a".repeat(129).as_str())])));
+    }
+
+    #[test]
+    fn preflight_carries_method_and_unsafe_headers_without_cookies() {
+        let mut actual = request("PUT", &[("x-token", "1"), ("content-type", "application/json"), ("accept", "*/*")]);
+        actual.credentials = Credentials::Include;
+        let preflight = preflight_request(&origin(), &actual);
+        assert_eq!(preflight.method, "OPTIONS");
+        assert_eq!(preflight.credentials, Credentials::Omit);
+        assert!(!preflight.follow_redirects);
+        assert!(preflight.body.is_none());
+        assert_eq!(preflight.headers["origin"], "https://app.example.com:8443");
+        assert_eq!(preflight.headers["access-control-request-method"], "PUT");
+        assert_eq!(preflight.headers["access-control-request-headers"], "content-type,x-token");
+    }
+
+    #[test]
+    fn wildcard_is_rejected_for_credentialed_requests() {
+        let wildcard = response(200, &[("access-control-allow-origin", "*")]);
+        assert!(check_response(&origin(), &wildcard, Credentials::Omit).is_ok());
+        assert!(check_response(&origin(), &wildcard, Credentials::Include).is_err());
+
+        let exact = response(200, &[("access-control-allow-origin", "https://app.example.com:8443")]);
+        assert!(check_response(&origin(), &exact, Credentials::Omit).is_ok());
+        assert!(check_response(&origin(), &exact, Credentials::Include).is_err());
+        let with_credentials = response(
+            200,
+            &[
+                ("access-control-allow-origin", "https://app.example.com:8443"),
+                ("access-control-allow-credentials", "true"),
+            ],
+        );
+        assert!(check_response(&origin(), &with_credentials, Credentials::Include).is_ok());
+    }
+
+    #[test]
+    fn foreign_or_missing_allow_origin_is_rejected() {
+        let foreign = response(200, &[("access-control-allow-origin", "https://app.example.com")]);
+        assert!(check_response(&origin(), &foreign, Credentials::Omit).is_err());
+        assert!(check_response(&origin(), &response(200, &[]), Credentials::Omit).is_err());
+    }
+
+    #[test]
+    fn only_safelisted_and_exposed_headers_are_visible() {
+        let headers = response(
+            200,
+            &[
+                ("content-type", "text/plain"),
+                ("set-cookie", "a=1"),
+                ("x-secret", "1"),
+                ("x-visible", "1"),
+                ("access-control-expose-headers", "X-Visible"),
+            ],
+        )
+        .headers;
+        let filtered = filter_response_headers(&headers);
+        assert!(filtered.contains_key("content-type"));
+        assert!(filtered.contains_key("x-visible"));
+        assert!(!filtered.contains_key("x-secret"));
+        assert!(!filtered.contains_key("set-cookie"));
+
+        let mut headers = headers;
+        headers.insert("access-control-expose-headers", HeaderValue::from_static("*"));
+        assert!(filter_response_headers(&headers).contains_key("x-secret"));
+    }
+
+    #[test]
+    fn preflight_cache_remembers_allowed_methods_and_headers() {
+        let cache = PreflightCache::new();
+        let put = request("PUT", &[("x-token", "1")]);
+        assert!(!cache.is_allowed(&origin(), &put));
+        let allowed = response(
+            200,
+            &[
+                ("access-control-allow-origin", "https://app.example.com:8443"),
+                ("access-control-allow-methods", "PUT, PATCH"),
+                ("access-control-allow-headers", "X-Token"),
+                ("access-control-max-age", "60"),
+            ],
+        );
+        cache.record(&origin(), &put, &allowed).unwrap();
+        assert!(cache.is_allowed(&origin(), &put));
+        assert!(!cache.is_allowed(&origin(), &request("DELETE", &[])));
+        assert!(!cache.is_allowed(&origin(), &request("PUT", &[("x-other", "1")])));
+        assert!(!cache.is_allowed(&Origin::parse("https://other.example.com/"), &put));
+
+        assert!(cache.record(&origin(), &request("DELETE", &[]), &allowed).is_err());
+        let failed = response(500, &[("access-control-allow-origin", "*")]);
+        assert!(cache.record(&origin(), &put, &failed).is_err());
+
+        // max-age=0: ответ проверяется, но не кэшируется
+        let cache = PreflightCache::new();
+        let uncached = response(
+            200,
+            &[
+                ("access-control-allow-origin", "*"),
+                ("access-control-allow-methods", "PUT"),
+                ("access-control-allow-headers", "x-token"),
+                ("access-control-max-age", "0"),
+            ],
+        );
+        cache.record(&origin(), &put, &uncached).unwrap();
+        assert!(!cache.is_allowed(&origin(), &put));
+    }
+}
+
+// === FILE: net\fetch.rs ===
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use reqwest::header::HeaderMap;
+
+use crate::core::config::Config;
+use crate::core::engine::{BroEngine, EngineEvent, UrlResponse};
+use crate::core::interfaces::{Credentials, NetRequest, NetResponse, NetworkTrait, YuaidbTrait};
+use crate::net::cache::{CacheEntryInfo, HttpCache};
+use async_trait::async_trait;
+
+pub struct Network {
+    client: reqwest::Client,
+    // Без хранилища cookie: запросы с credentials, отличными от Include
+    anonymous: reqwest::Client,
+    // Без cookie и без перенаправлений
+    direct: reqwest::Client,
+    // С общими cookie, но без перенаправлений: цепочку проходит движок (fetch_cors)
+    manual: reqwest::Client,
+    db: Option<Arc<dyn YuaidbTrait + Send + Sync>>,
+    cache: HttpCache,
+}
+
+impl Network {
+    pub fn new(config: &Config, db: Option<Arc<dyn YuaidbTrait + Send + Sync>>) -> Result<Self> {
+        let client_builder = || {
+            reqwest::ClientBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .connect_timeout(Duration::from_secs(10))
+        };
+        // Общее хранилище cookie для навигации и запросов скриптов с учётными данными
+        let jar = Arc::new(reqwest::cookie::Jar::default());
+        let client = client_builder().cookie_provider(jar.clone()).build()?;
+        let anonymous = client_builder().build()?;
+        let direct = client_builder().redirect(reqwest::redirect::Policy::none()).build()?;
+        let manual = client_builder().cookie_provider(jar).redirect(reqwest::redirect::Policy::none()).build()?;
+        log::info!("Сетевой модуль инициализирован");
+        Ok(Self { client, anonymous, direct, manual, db, cache: HttpCache::new() })
+    }
+}
+
+#[async_trait]
+impl NetworkTrait for Network {
+    async fn fetch_html(&self, url: &str) -> Result<String> {
+        let start = Instant::now();
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .context(format!("Не удалось выполнить запрос к {}", url))?;
+
+        let status = response.status().as_u16();
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("Ошибка HTTP: {}", status));
+        }
+
+        let html = response
+            .text()
+            .await
+            .context(format!("Не удалось декодировать ответ от {}", url))?;
+
+        log::info!("Успешно загружен HTML для {} за {:?}", url, start.elapsed());
+        Ok(html)
+    }
+
+    async fn fetch_html_with_headers(&self, url: &str) -> Result<(String, HeaderMap)> {
+        let start = Instant::now();
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .context(format!("Не удалось выполнить запрос к {}", url))?;
+
+        let status = response.status().as_u16();
+        let headers = response.headers().clone();
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("Ошибка HTTP: {}", status));
+        }
+
+        let html = response
+            .text()
+            .await
+            .context(format!("Не удалось декодировать ответ от {}", url))?;
+
+        log::info!("Успешно загружен HTML с заголовками для {} за {:?}", url, start.elapsed());
+        Ok((html, headers))
+    }
+
+    async fn fetch_request(&self, mut request: NetRequest) -> Result<NetResponse> {
+        if let Some(response) = self.cache.fresh(&request) {
+            return Ok(response);
+        }
+        self.cache.add_validators(&mut request);
+        let start = Instant::now();
+        let method = reqwest::Method::from_bytes(request.method.as_bytes())
+            .context(format!("Недопустимый HTTP-метод: {}", request.method))?;
+        // Нерешённый SameOrigin сюда доходит только в обход движка и трактуется как Omit
+        let client = match (request.follow_redirects, request.credentials) {
+            (false, Credentials::Include) => &self.manual,
+            (false, _) => &self.direct,
+            (true, Credentials::Include) => &self.client,
+            (true, _) => &self.anonymous,
+        };
+        let mut builder = client.request(method, &request.url).headers(request.headers.clone());
+        if let Some(body) = request.body.clone() {
+            builder = builder.body(body);
+        }
+        let response = builder
+            .send()
+            .await
+            .context(format!("Не удалось выполнить запрос к {}", request.url))?;
+
+        let url = response.url().to_string();
+        let status = response.status().as_u16();
+        let headers = response.headers().clone();
+        let body = response
+            .bytes()
+            .await
+            .context(format!("Не удалось прочитать ответ от {}", request.url))?
+            .to_vec();
+
+        log::info!("{} {} -> {} за {:?}", request.method, url, status, start.elapsed());
+        let response = NetResponse { url, status, headers, body };
+        if response.status == 304 {
+            if let Some(cached) = self.cache.revalidated(&request, &response) {
+                return Ok(cached);
+            }
+        }
+        self.cache.store(&request, &response);
+        Ok(response)
+    }
+
+    fn cache_entries(&self) -> Vec<CacheEntryInfo> {
+        self.cache.entries()
+    }
+}
+
+// Асинхронный сетевой сервис для обработки событий движка
+pub async fn run_network_service(network: Arc<dyn NetworkTrait + Send + Sync>, engine: Arc<BroEngine>) {
+    log::info!("Запуск сетевого сервиса");
+    loop {
+        // Неблокирующий приём событий
+        match engine.receive_event() {
+            Some(EngineEvent::UrlRequest { url, response_tx }) => {
+                let url_clone = url.clone();
+                log::info!("Обработка сетевого запроса для URL: {}", url_clone);
+                let network_clone = network.clone();
+                let engine_clone = engine.clone();
+                // Запускаем обработку запроса в отдельной задаче
+                tokio::spawn(async move {
+                    let start = Instant::now();
+                    match network_clone.fetch_html_with_headers(&url_clone).await {
+                        Ok((html, headers)) => {
+                            let response = UrlResponse {
+                                url: url_clone.clone(),
+                                html,
+                                headers,
+                                status: 200,
+                                duration: start.elapsed(),
+                            };
+                            if let Err(e) = response_tx.send(response) {
+                                log::error!("Ошибка отправки ответа для URL {}: {}", url_clone, e);
+                                engine_clone.send_event(EngineEvent::Error(format!(
+                                    "Ошибка отправки ответа: {}",
+                                    e
+                                )));
+                            } else {
+                                log::info!("Ответ отправлен для URL: {}", url_clone);
+                            }
+                        }
+                        Err(e) => {
+                            log::error!("Ошибка загрузки URL {}: {}", url_clone, e);
+                            engine_clone.send_event(EngineEvent::Error(format!(
+                                "Ошибка загрузки {}: {}",
+                                url_clone, e
+                            )));
+                        }
+                    }
+                });
+            }
+            Some(event) => {
+                log::debug!("Пропуск события: {:?}", event);
+            }
+            None => {
+                // Небольшая задержка для предотвращения активного опроса
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        }
+    }
+}
+
+// Функция загрузки URL с использованием движка
+pub async fn load_url_generic(url: String, engine: &Arc<BroEngine>) -> Result<(String, HeaderMap, RequestLog)> {
+    let response = engine.fetch_url(url.clone()).await?;
+    let log = RequestLog {
+        url: url.clone(),
+        method: "GET".to_string(),
+        status: response.status,
+        headers: response.headers.clone(),
+        duration: response.duration,
+    };
+    Ok((response.html, response.headers, log))
+}
+
+pub struct RequestLog {
+    pub url: String,
+    pub method: String,
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub duration: Duration,
+}
+
+// === FILE: net\ipfs.rs ===
+use anyhow::Result;
+use std::sync::Arc;
+use crate::net::Network;
+pub struct IPFS;
+impl IPFS {
+    pub fn new(_network: Arc<Network>) -> Result<Self> {
+        println!("IPFS initialized");
+        Ok(Self)
+    }
+}
+
+// === FILE: net\load_url.rs ===
+use crate::core::engine::{BroEngine, RequestInitiator, UrlResponse};
+use crate::ui::devtools::RequestLog;
+use reqwest::header::HeaderMap;
+use anyhow::Result;
+use std::sync::Arc;
+use std::time::{Instant, Duration};
+
+pub async fn load_url_generic(
+    url: String,
+    engine: &Arc<BroEngine>,
+) -> Result<(String, HeaderMap, RequestLog)> {
+    let start_time = Instant::now();
+    
+    // Выполняем сетевой запрос через BroEngine
+    match engine.fetch_url(url.clone()).await {
+        Ok(response) => {
+            let headers = response.headers.clone();
+            let log = RequestLog {
+                url: url.clone(),
+                method: "GET".into(),
+                status: response.status,
+                headers: headers.clone(),
+                duration: start_time.elapsed(),
+                html: response.html.clone(),
+                initiator: RequestInitiator::Document,
+            };
+            Ok((response.html, headers, log))
+        }
+        Err(e) => {
+            // Если сеть отключена или произошла ошибка
+            let html = format!(
+                "<html><body><h1>Ошибка загрузки {}</h1><p>{}</p></body></html>",
+                url, e
+            );
+            let headers = HeaderMap::new();
+            let log = RequestLog {
+                url,
+                method: "GET".into(),
+                status: 0,
+                headers: headers.clone(),
+                duration: Duration::from_millis(0),
+                html: html.clone(),
+                initiator: RequestInitiator::Document,
+            };
+            Ok((html, headers, log))
+        }
+    }
+}
+
+// === FILE: net\mod.rs ===
+pub mod fetch;
+pub mod cors;
+pub mod webrtc;
+pub mod ipfs;
+pub mod websocket;
+pub mod proto;
+pub mod tls;
+pub mod p2p;
+pub mod cache;
+pub mod stream;
+pub mod load_url;
+pub use fetch::Network;
+
+
+// === FILE: net\p2p.rs ===
+use anyhow::Result;
+use std::sync::Arc;
+use crate::net::Network;
+pub struct P2P;
+impl P2P {
+    pub fn new(_network: Arc<Network>) -> Result<Self> {
+        println!("P2P initialized");
+        Ok(Self)
+    }
+}
+
+// === FILE: net\proto.rs ===
+use anyhow::Result;
+use std::sync::Arc;
+use crate::net::Network;
+pub struct Proto;
+impl Proto {
+    pub fn new(_network: Arc<Network>) -> Result<Self> {
+        println!("Proto initialized");
+        Ok(Self)
+    }
+}
+
+// === FILE: net\stream.rs ===
+use anyhow::Result;
+use std::sync::Arc;
+use crate::net::Network;
+pub struct Stream;
+impl Stream {
+    pub fn new(_network: Arc<Network>) -> Result<Self> {
+        println!("Stream initialized");
+        Ok(Self)
+    }
+}
+
+// === FILE: net\tls.rs ===
+use anyhow::Result;
+use std::sync::Arc;
+use crate::net::Network;
+pub struct TLS;
+impl TLS {
+    pub fn new(_network: Arc<Network>) -> Result<Self> {
+        println!("TLS initialized");
+        Ok(Self)
+    }
+}
+
+// === FILE: net\webrtc.rs ===
+use anyhow::Result;
+use crate::core::config::Config;
+pub struct WebRTC;
+impl WebRTC {
+    pub fn new(_config: &Config) -> Result<Self> {
+        println!("WebRTC initialized");
+        Ok(Self)
+    }
+    pub fn create_peer_connection(&self, _peer_id: &str) -> Result<()> {
+        println!("Creating WebRTC peer connection for peer: {}", _peer_id);
+        Ok(())
+    }
+    pub fn send_data(&self, _peer_id: &str, _data: &[u8]) -> Result<()> {
+        println!("Sending data to peer: {}", _peer_id);
+        Ok(())
+    }
+    pub fn close_connection(&self, _peer_id: &str) -> Result<()> {
+        println!("Closing WebRTC connection for peer: {}", _peer_id);
+        Ok(())
+    }
+}
+
+// === FILE: net\websocket.rs ===
+use anyhow::Result;
+use std::sync::Arc;
+use crate::net::Network;
+pub struct Websocket;
+impl Websocket {
+    pub fn new(_network: Arc<Network>) -> Result<Self> {
+        println!("Websocket initialized");
+        Ok(Self)
+    }
+}
+
+// === FILE: ui\aichat.rs ===
+use egui::{Context as EguiContext, SidePanel, ScrollArea, TextEdit, Ui};
+use crate::ui::devtools::PanelAction; // Импорт из devtools, так как общий
+pub fn render_aichat(
+    ctx: &EguiContext,
+    ai_chat_input: &mut String,
+    ai_chat_history: &[(String, String)],
+    send_ai_message: &mut bool,
+) {
+    SidePanel::right("ai_chat_panel")
+        .resizable(true)
+        .default_width(400.0)
+        .min_width(200.0)
+        .show(ctx, |ui| {
+            render_aichat_ui(ui, ai_chat_input, ai_chat_history, send_ai_message, false)
+        });
+}
+pub fn render_aichat_ui(
+    ui: &mut Ui,
+    ai_chat_input: &mut String,
+    ai_chat_history: &[(String, String)],
+    send_ai_message: &mut bool,
+    is_detached: bool,
+) -> Option<PanelAction> {
+    let mut action: Option<PanelAction> = None;
+    ui.horizontal(|ui| {
+		if ui.button(if is_detached { "🗔" } else { "🗔" }).clicked() {
+            action = Some(if is_detached { PanelAction::Attach } else { PanelAction::Detach });
+        }
+		ui.heading("Чат с AI (F11)");
+    });
+    ScrollArea::vertical().show(ui, |ui| {
+        ui.label("История чата (заглушка):");
+        for (user_msg, ai_response) in ai_chat_history {
+            ui.label(format!("Вы: {}", user_msg));
+            ui.label(format!("AI: {}", ai_response));
+            ui.add_space(5.0);
+        }
+    });
+    ui.add_space(10.0);
+    ui.label("Ваш запрос:");
+    ui.add(TextEdit::multiline(ai_chat_input).desired_rows(3).min_size(egui::vec2(100.0, 50.0)));
+    if ui.button("Отправить").clicked() {
+        *send_ai_message = true;
+    }
+    action
+}
+
+// === FILE: ui\clipboard.rs ===
+use anyhow::Result;
+pub struct Clipboard;
+impl Clipboard {
+    pub fn new() -> Result<Self> {
+        println!("Clipboard initialized");
+        Ok(Self)
+    }
+}
+
+// === FILE: ui\compositor.rs ===
+use crate::core::interfaces::CompositorTrait;
+use anyhow::Result;
+use winit::window::Window;
+use async_trait::async_trait;
+pub struct Compositor;
+#[async_trait]
+impl CompositorTrait for Compositor {
+    async fn new(_window: &Window, _config: &()) -> Result<Self> {
+        log::info!("Компоновщик инициализирован");
+        Ok(Self)
+    }
+    async fn render_text(&self, _text: &str) {
+        log::info!("Отрисовка текста: {}", _text);
+    }
+    async fn resize(&self, _width: u32, _height: u32) -> Result<()> {
+        log::info!("Изменение размера: {}x{}", _width, _height);
+        Ok(())
+    }
+}
+
+// === FILE: ui\devtools.rs ===
+use crate::core::engine::{EngineEvent, BroEngine, RequestInitiator, UrlResponse};
+use crate::core::page_state::FrameworkType;
+use crate::core::policy::PolicyViolation;
+use crate::core::js_runtime::{ConsoleLevel, ConsoleMessage, RealmOptions};
+use crate::core::page_state::PageId;
+use crate::core::web_storage::StorageChange;
+use crate::wasm_api::cloud_bindings::{CloudBindings, HostInventory, RemoteHost, RemoteLatticeEvent};
+use crate::wasm_api::sandbox::{WasmOutput, WasmStream};
+use egui::{Context as EguiContext, SidePanel, ScrollArea, TextEdit, Ui, TextStyle, Id};
+use std::time::{Duration, Instant};
+use reqwest::header::HeaderMap;
+use crate::ui::heavy_calculator::HeavyCalculator;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::UnboundedSender;
+use sysinfo::System;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum DevToolsTab {
+    Html,
+    Network,
+    Console,
+    Storage,
+    Security,
+    Lattice,
+    Debug,
+}
+
+#[derive(Clone)]
+pub struct RequestLog {
+    pub url: String,
+    pub method: String,
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub duration: Duration,
+    pub html: String,
+    pub initiator: RequestInitiator,
+}
+
+pub struct DevToolsState {
+    pub engine: Arc<BroEngine>,
+    pub detected_framework: Option<FrameworkType>,
+    pub network_logs: Vec<RequestLog>,
+    pub policy_violations: Vec<PolicyViolation>,
+    pub console_messages: Vec<ConsoleMessage>,
+    pub wasm_output: VecDeque<WasmOutput>,
+    pub lattice_events: VecDeque<RemoteLatticeEvent>,
+    pub lattice_view: Arc<parking_lot::Mutex<LatticeView>>,
+    pub page_id: PageId,
+    // Параметры realm страницы; применяются при следующем пересоздании realm
+    pub realm_options: Arc<parking_lot::Mutex<RealmOptions>>,
+    pub debug_info: DebugInfo,
+    pub rx_engine: broadcast::Receiver<EngineEvent>,
+	
+}
+
+impl DevToolsState {
+    pub fn poll_events(&mut self) {
+        while let Ok(event) = self.rx_engine.try_recv() {
+            match event {
+                EngineEvent::FrameworkDetected(fw) => {
+                    self.detected_framework = Some(fw);
+                }
+                EngineEvent::UrlResponse(response) => {
+                    if !self.network_logs.iter().any(|log| log.url == response.url && log.duration == response.duration) {
+                        self.network_logs.push(RequestLog {
+                            url: response.url,
+                            method: "GET".to_string(),
+                            status: response.status,
+                            headers: response.headers,
+                            duration: response.duration,
+                            html: response.html,
+                            initiator: RequestInitiator::Document,
+                        });
+                    }
+                }
+                EngineEvent::SubresourceResponse { page, method, initiator, response } => {
+                    if page == self.page_id {
+                        self.network_logs.push(RequestLog {
+                            url: response.url,
+                            method,
+                            status: response.status,
+                            headers: response.headers,
+                            duration: response.duration,
+                            html: response.html,
+                            initiator,
+                        });
+                    }
+                }
+                EngineEvent::PolicyViolation(violation) => {
+                    self.policy_violations.push(violation);
+                }
+                EngineEvent::ConsoleMessage(message) => {
+                    if message.page == self.page_id {
+                        self.console_messages.push(message);
+                    }
+                }
+                EngineEvent::WasmOutput(output) => {
+                    if self.wasm_output.len() >= MAX_WASM_OUTPUT {
+                        self.wasm_output.pop_front();
+                    }
+                    self.wasm_output.push_back(output);
+                }
+                EngineEvent::Lattice(event) => {
+                    // Событие известного хоста обновляет только его инвентарь; пульс хоста ничего не меняет
+                    let known = self.lattice_view.lock().hosts.iter().any(|host| host.id == event.source);
+                    if !known {
+                        refresh_lattice(self);
+                    } else if event.short_type() != "host_heartbeat" {
+                        refresh_host(self, event.source.clone());
+                    }
+                    if self.lattice_events.len() >= MAX_LATTICE_EVENTS {
+                        self.lattice_events.pop_front();
+                    }
+                    self.lattice_events.push_back(event);
+                }
+                EngineEvent::Error(err) => {
+                    self.network_logs.push(RequestLog {
+                        url: "Ошибка".to_string(),
+                        method: "N/A".to_string(),
+                        status: 0,
+                        headers: HeaderMap::new(),
+                        duration: Duration::ZERO,
+                        html: err,
+                        initiator: RequestInitiator::Document,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct DebugInfo {
+    pub data_received: usize,
+    pub data_size: String,
+    pub transfer_speed: f64,
+    pub render_time: Duration,
+    pub server_response_time: Duration,
+    pub dom_elements: usize,
+    pub memory_usage: String,
+    pub frame_rate: f64,
+    pub last_frame_time: Option<Instant>,
+    pub frame_count: u32,
+    pub fps_update_time: Option<Instant>,
+}
+
+impl DebugInfo {
+    pub fn new() -> Self {
+        Self {
+            last_frame_time: Some(Instant::now()),
+            fps_update_time: Some(Instant::now()),
+            ..Default::default()
+        }
+    }
+
+    pub fn update(&mut self, html: &str, render_time: Duration, response_time: Duration) {
+        self.data_received = html.len();
+        self.data_size = Self::format_size(html.len());
+        self.transfer_speed = if response_time.as_secs_f64() > 0.0 {
+            html.len() as f64 / response_time.as_secs_f64() / 1024.0
+        } else {
+            0.0
+        };
+        self.render_time = render_time;
+        self.server_response_time = response_time;
+        self.dom_elements = html.matches("<div").count() + html.matches("<p").count() + html.matches("<span").count();
+        let mut sys = System::new_all();
+        sys.refresh_all();
+        self.memory_usage = Self::format_size(sys.used_memory() as usize);
+        let now = Instant::now();
+        if let Some(last) = self.last_frame_time {
+            let delta = now - last;
+            if delta > Duration::from_millis(0) {
+                self.frame_rate = 1.0 / delta.as_secs_f64();
+            }
+        }
+        self.last_frame_time = Some(now);
+        self.frame_count += 1;
+        if let Some(update_time) = self.fps_update_time {
+            if now - update_time > Duration::from_secs(1) {
+                self.frame_rate = self.frame_count as f64 / (now - update_time).as_secs_f64();
+                self.frame_count = 0;
+                self.fps_update_time = Some(now);
+            }
+        }
+    }
+
+    pub fn format_size(bytes: usize) -> String {
+        if bytes >= 1024 * 1024 {
+            format!("{:.2} MB", bytes as f64 / (1024.0 * 1024.0))
+        } else if bytes >= 1024 {
+            format!("{:.2} KB", bytes as f64 / 1024.0)
+        } else {
+            format!("{} B", bytes)
+        }
+    }
+
+    pub fn format_duration(duration: Duration) -> String {
+        if duration.as_secs() > 0 {
+            format!("{:.2} s", duration.as_secs_f64())
+        } else if duration.as_millis() > 0 {
+            format!("{} ms", duration.as_millis())
+        } else {
+            format!("{} μs", duration.as_micros())
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum PanelAction {
+    Attach,
+    Detach,
+}
+
+pub fn render_devtools(
+    ctx: &EguiContext,
+    devtools_tab: &mut DevToolsTab,
+    html: &mut String,
+    headers: &HeaderMap,
+    debug_info: &DebugInfo,
+    network_logs: &Vec<RequestLog>,
+    tx_ssh: &UnboundedSender<String>,
+    devtools_state: &DevToolsState,
+) {
+    SidePanel::right("devtools_panel")
+        .resizable(true)
+        .default_width(400.0)
+        .min_width(200.0)
+        .show(ctx, |ui| {
+            render_devtools_ui(ui, devtools_tab, html, headers, debug_info, network_logs, tx_ssh, false, devtools_state);
+        });
+}
+
+pub fn render_devtools_ui(
+    ui: &mut Ui,
+    devtools_tab: &mut DevToolsTab,
+    html: &mut String,
+    headers: &HeaderMap,
+    debug_info: &DebugInfo,
+    network_logs: &Vec<RequestLog>,
+    tx_ssh: &UnboundedSender<String>,
+    is_detached: bool,
+    devtools_state: &DevToolsState,
+) -> Option<PanelAction> {
+    let mut action: Option<PanelAction> = None;
+
+    ui.horizontal(|ui| {
+        if ui.button(if is_detached { "Прикрепить" } else { "Открепить" }).clicked() {
+            action = Some(if is_detached { PanelAction::Attach } else { PanelAction::Detach });
+            log::info!("Переключение состояния панели DevTools: {:?}", action);
+        }
+        ui.heading("Инструменты (F12)");
+    });
+
+    ui.horizontal_wrapped(|ui| {
+        ui.set_min_width(150.0);
+        ui.selectable_value(devtools_tab, DevToolsTab::Html, "HTML");
+        ui.selectable_value(devtools_tab, DevToolsTab::Network, "Сеть");
+        ui.selectable_value(devtools_tab, DevToolsTab::Console, "Консоль");
+        ui.selectable_value(devtools_tab, DevToolsTab::Storage, "Хранилище");
+        ui.selectable_value(devtools_tab, DevToolsTab::Security, "Безопасность");
+        if ui.selectable_value(devtools_tab, DevToolsTab::Lattice, "Решётка").clicked() {
+            refresh_lattice(devtools_state);
+        }
+        ui.selectable_value(devtools_tab, DevToolsTab::Debug, "Дебаг");
+    });
+
+    ui.separator();
+
+    match *devtools_tab {
+        DevToolsTab::Html => {
+            ScrollArea::vertical().show(ui, |ui| {
+                ui.label("Редактировать HTML:");
+                ui.label(format!(
+					"Фреймворк: {}",
+					devtools_state.detected_framework
+						.as_ref()  
+						.map(|fw| format!("{:?}", fw))
+						.unwrap_or("Не обнаружен".to_string())
+				));
+                ui.add_space(5.0);
+                ui.add(
+                    TextEdit::multiline(html)
+                        .desired_rows(20)
+                        .font(TextStyle::Monospace),
+                );
+            });
+        }
+        DevToolsTab::Network => {
+            ScrollArea::vertical().show(ui, |ui| {
+                ui.heading("История HTTP-запросов:");
+                for log in devtools_state.network_logs.iter().rev() {
+                    ui.group(|ui| {
+                        ui.label(format!("{} {} {}", log.method, log.url, log.status));
+                        ui.label(format!("Инициатор: {}", log.initiator.as_str()));
+                        ui.label(format!("Длительность: {}", DebugInfo::format_duration(log.duration)));
+                        ui.label(format!("Размер ответа: {}", DebugInfo::format_size(log.html.len())));
+                        ui.label(format!("Скорость передачи: {:.2} KB/s",
+                            if log.duration.as_secs_f64() > 0.0 {
+                                log.html.len() as f64 / log.duration.as_secs_f64() / 1024.0
+                            } else {
+                                0.0
+                            }
+                        ));
+
+                        let mut headers_text = String::new();
+                        for (name, value) in log.headers.iter() {
+                            if let Ok(value_str) = value.to_str() {
+                                headers_text.push_str(&format!("{}: {}\n", name, value_str));
+                            }
+                        }
+
+                        let desired_size = egui::Vec2::new(ui.available_width(), 20.0);
+                        ui.add_sized(
+                            desired_size,
+                            egui::TextEdit::multiline(&mut headers_text)
+                                .font(TextStyle::Monospace),
+                        );
+                    });
+                    ui.add_space(5.0);
+                }
+            });
+        }
+        DevToolsTab::Console => {
+            render_console_tab(ui, devtools_state);
+        }
+        DevToolsTab::Storage => {
+            render_storage_tab(ui, devtools_state);
+        }
+        DevToolsTab::Security => {
+            ScrollArea::vertical().show(ui, |ui| {
+                ui.heading("Нарушения политик безопасности:");
+                if devtools_state.policy_violations.is_empty() {
+                    ui.label("Нарушений не обнаружено");
+                }
+                for violation in devtools_state.policy_violations.iter().rev() {
+                    ui.group(|ui| {
+                        ui.label(format!("Директива: {}", violation.directive));
+                        ui.label(format!("Ресурс: {}", violation.blocked_url));
+                        ui.label(format!("Страница: {}", violation.page_url));
+                        ui.label(&violation.message);
+                    });
+                    ui.add_space(5.0);
+                }
+            });
+        }
+        DevToolsTab::Lattice => {
+            render_lattice_tab(ui, devtools_state);
+        }
+        DevToolsTab::Debug => {
+            ScrollArea::vertical().show(ui, |ui| {
+                ui.add_space(10.0);
+                ui.label("Производительность:");
+                ui.indent("performance", |ui| {
+                    ui.label(format!("FPS: {:.1}", debug_info.frame_rate));
+                    ui.label(format!(
+                        "Время рендера: {}",
+                        DebugInfo::format_duration(debug_info.render_time)
+                    ));
+                });
+                ui.add_space(10.0);
+                ui.label("DOM и ресурсы:");
+                ui.indent("resources", |ui| {
+                    ui.label(format!("Элементы DOM: {}", debug_info.dom_elements));
+                    ui.label(format!("Память: {}", debug_info.memory_usage));
+                });
+                ui.add_space(10.0);
+                let stats = devtools_state.engine.scheduler_stats();
+                ui.label(format!(
+                    "Планировщик ({} слотов, {} потоков CPU):",
+                    stats.async_slots, stats.cpu_threads
+                ));
+                egui::Grid::new("devtools_scheduler_stats").num_columns(6).striped(true).show(ui, |ui| {
+                    for header in ["Приоритет", "Очередь", "Выполняется", "Готово / отм. / срок", "Ожидание", "Выполнение"] {
+                        ui.label(header);
+                    }
+                    ui.end_row();
+                    for (priority, stats) in &stats.priorities {
+                        ui.label(priority.as_str());
+                        ui.label(stats.queued.to_string());
+                        ui.label(stats.running.to_string());
+                        ui.label(format!("{} / {} / {}", stats.completed, stats.cancelled, stats.expired));
+                        ui.label(DebugInfo::format_duration(stats.avg_wait()))
+                            .on_hover_text(format!("Максимум: {}", DebugInfo::format_duration(stats.max_wait)));
+                        ui.label(DebugInfo::format_duration(stats.avg_run()));
+                        ui.end_row();
+                    }
+                });
+                ui.add_space(10.0);
+                let heavy_result_id = Id::new("heavy_calculator_result");
+                let mut calculator: HeavyCalculator = ui.memory_mut(|mem| {
+                    mem.data.get_temp(heavy_result_id).unwrap_or_default()
+                });
+                if ui.button("Дай синус бра!").clicked() {
+                    calculator.compute(30_000_000);
+                    ui.memory_mut(|mem| {
+                        mem.data.insert_temp(heavy_result_id, calculator.clone());
+                    });
+                    log::info!("Выполнен тест производительности (синус)");
+                }
+                ui.add_space(10.0);
+                if let Some(result_str) = calculator.format_result(DebugInfo::format_duration) {
+                    ui.add_space(10.0);
+                    ui.label(result_str);
+                }
+            });
+        }
+    }
+    action
+}
+
+fn render_console_tab(ui: &mut Ui, devtools_state: &DevToolsState) {
+    let input_id = Id::new("devtools_console_input");
+    let mut input = ui.data_mut(|d| d.get_temp::<String>(input_id).unwrap_or_default());
+    let mut deterministic = devtools_state.realm_options.lock().deterministic;
+
+    ui.horizontal(|ui| {
+        if ui
+            .checkbox(&mut deterministic, "Детерминированный режим")
+            .on_hover_text("Math.random с фиксированным зерном и неподвижный Date.now; realm пересоздаётся")
+            .changed()
+        {
+            let options = {
+                let mut options = devtools_state.realm_options.lock();
+                options.deterministic = deterministic;
+                options.clone()
+            };
+            let engine = devtools_state.engine.clone();
+            let page = devtools_state.page_id;
+            tokio::spawn(async move {
+                if let Err(e) = engine.create_page_realm(page, options).await {
+                    log::error!("Не удалось пересоздать JS realm: {}", e);
+                }
+            });
+        }
+    });
+
+    let response = ui.add(
+        TextEdit::singleline(&mut input)
+            .hint_text("Выражение JavaScript")
+            .font(TextStyle::Monospace)
+            .desired_width(f32::INFINITY),
+    );
+    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) && !input.trim().is_empty() {
+        let engine = devtools_state.engine.clone();
+        let page = devtools_state.page_id;
+        let script = std::mem::take(&mut input);
+        tokio::spawn(async move {
+            // Ошибки попадают в консоль из evaluate_in_page
+            if let Ok(value) = engine.evaluate_in_page(page, &script).await {
+                engine.send_event(EngineEvent::ConsoleMessage(ConsoleMessage {
+                    page,
+                    level: ConsoleLevel::Result,
+                    text: value,
+                }));
+            }
+        });
+        response.request_focus();
+    }
+    ui.data_mut(|d| d.insert_temp(input_id, input));
+
+    ui.separator();
+    ScrollArea::vertical().stick_to_bottom(true).show(ui, |ui| {
+        for message in &devtools_state.console_messages {
+            let color = match message.level {
+                ConsoleLevel::Error => egui::Color32::from_rgb(255, 110, 110),
+                ConsoleLevel::Warn => egui::Color32::from_rgb(255, 210, 90),
+                ConsoleLevel::Debug => egui::Color32::GRAY,
+                ConsoleLevel::Result => egui::Color32::from_rgb(150, 200, 255),
+                ConsoleLevel::Log | ConsoleLevel::Info => egui::Color32::from_rgb(220, 220, 220),
+            };
+            let prefix = if message.level == ConsoleLevel::Result { "← " } else { "" };
+            ui.label(egui::RichText::new(format!("{}{}", prefix, message.text)).monospace().color(color));
+        }
+        // Вывод WASM-модулей не привязан к странице
+        for output in &devtools_state.wasm_output {
+            let color = match output.stream {
+                WasmStream::Stdout => egui::Color32::from_rgb(200, 220, 200),
+                WasmStream::Stderr => egui::Color32::from_rgb(255, 110, 110),
+            };
+            ui.label(egui::RichText::new(format!("[wasm {}] {}", output.module, output.text.trim_end())).monospace().color(color));
+        }
+    });
+}
+
+// Снимок удалённой решётки; обновляется в фоне, вкладка только читает
+#[derive(Clone, Default)]
+pub struct LatticeView {
+    pub hosts: Vec<RemoteHost>,
+    pub inventories: Vec<HostInventory>,
+    pub error: Option<String>,
+    pub loading: bool,
+    // Снимок устарел во время загрузки: после неё загрузка повторяется один раз
+    stale: bool,
+    refreshing: HashSet<String>,
+    stale_hosts: HashSet<String>,
+}
+
+const MAX_LATTICE_EVENTS: usize = 500;
+const MAX_WASM_OUTPUT: usize = 1000;
+
+// Полная загрузка: список хостов и инвентарь каждого. Повторные запросы во время
+// загрузки не порождают новых, а помечают снимок устаревшим
+fn refresh_lattice(devtools_state: &DevToolsState) {
+    let Some(cloud) = devtools_state.engine.cloud() else {
+        return;
+    };
+    let view = devtools_state.lattice_view.clone();
+    {
+        let mut view = view.lock();
+        if view.loading {
+            view.stale = true;
+            return;
+        }
+        view.loading = true;
+    }
+    tokio::spawn(async move {
+        loop {
+            let result: anyhow::Result<(Vec<RemoteHost>, Vec<HostInventory>)> = async {
+                let hosts = cloud.hosts().await?;
+                let mut inventories = Vec::new();
+                for host in &hosts {
+                    inventories.push(cloud.inventory(&host.id).await?);
+                }
+                Ok((hosts, inventories))
+            }
+            .await;
+            let mut state = view.lock();
+            match result {
+                Ok((hosts, inventories)) => {
+                    state.hosts = hosts;
+                    state.inventories = inventories;
+                    state.error = None;
+                }
+                Err(e) => state.error = Some(e.to_string()),
+            }
+            if !std::mem::take(&mut state.stale) {
+                state.loading = false;
+                break;
+            }
+        }
+    });
+}
+
+// Инвентарь одного хоста — один запрос на событие, с тем же объединением повторов
+fn refresh_host(devtools_state: &DevToolsState, host_id: String) {
+    let Some(cloud) = devtools_state.engine.cloud() else {
+        return;
+    };
+    let view = devtools_state.lattice_view.clone();
+    {
+        let mut view = view.lock();
+        if !view.refreshing.insert(host_id.clone()) {
+            view.stale_hosts.insert(host_id);
+            return;
+        }
+    }
+    tokio::spawn(async move {
+        loop {
+            let result = cloud.inventory(&host_id).await;
+            let mut state = view.lock();
+            match result {
+                Ok(inventory) => match state.inventories.iter_mut().find(|existing| existing.host_id == host_id) {
+                    Some(existing) => *existing = inventory,
+                    None => state.inventories.push(inventory),
+                },
+                Err(e) => state.error = Some(e.to_string()),
+            }
+            if !state.stale_hosts.remove(&host_id) {
+                state.refreshing.remove(&host_id);
+                break;
+            }
+        }
+    });
+}
+
+// Команды выполняются в фоне; итог приходит событием решётки и обновлением снимка
+fn lattice_command<F>(devtools_state: &DevToolsState, command: F)
+where
+    F: FnOnce(Arc<CloudBindings>) -> futures::future::BoxFuture<'static, anyhow::Result<()>> + Send + 'static,
+{
+    let Some(cloud) = devtools_state.engine.cloud() else {
+        return;
+    };
+    let view = devtools_state.lattice_view.clone();
+    tokio::spawn(async move {
+        if let Err(e) = command(cloud).await {
+            view.lock().error = Some(e.to_string());
+        }
+    });
+}
+
+fn render_lattice_tab(ui: &mut Ui, devtools_state: &DevToolsState) {
+    if devtools_state.engine.cloud().is_none() {
+        ui.label("Удалённая решётка не настроена: секция [lattice] в settings.toml и модуль WasmCloud");
+        return;
+    }
+    let view = devtools_state.lattice_view.lock().clone();
+    ui.horizontal(|ui| {
+        if ui.button("Обновить").clicked() {
+            refresh_lattice(devtools_state);
+        }
+        if view.loading {
+            ui.spinner();
+        }
+    });
+    if let Some(error) = &view.error {
+        ui.colored_label(egui::Color32::from_rgb(255, 110, 110), error);
+    }
+
+    let deploy_id = Id::new("devtools_lattice_deploy");
+    let (mut host_id, mut actor_ref, mut count) =
+        ui.data(|d| d.get_temp::<(String, String, String)>(deploy_id)).unwrap_or_default();
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_salt("devtools_lattice_host")
+            .selected_text(if host_id.is_empty() { "Хост" } else { host_id.as_str() })
+            .show_ui(ui, |ui| {
+                for host in &view.hosts {
+                    let label = if host.friendly_name.is_empty() { host.id.clone() } else { host.friendly_name.clone() };
+                    ui.selectable_value(&mut host_id, host.id.clone(), label);
+                }
+            });
+        ui.add(TextEdit::singleline(&mut actor_ref).hint_text("OCI-ссылка актора").desired_width(200.0));
+        ui.add(TextEdit::singleline(&mut count).hint_text("1").desired_width(30.0));
+        if ui.button("Развернуть").clicked() && !host_id.is_empty() && !actor_ref.is_empty() {
+            let (host, reference) = (host_id.clone(), actor_ref.clone());
+            let count = count.parse().unwrap_or(1);
+            lattice_command(devtools_state, move |cloud| {
+                Box::pin(async move { cloud.deploy_actor(&host, &reference, count).await })
+            });
+        }
+    });
+    ui.data_mut(|d| d.insert_temp(deploy_id, (host_id, actor_ref, count)));
+    ui.separator();
+
+    ScrollArea::vertical().show(ui, |ui| {
+        for host in &view.hosts {
+            ui.group(|ui| {
+                ui.heading(if host.friendly_name.is_empty() { &host.id } else { &host.friendly_name });
+                ui.label(format!("{} · v{} · {} с", host.id, host.version, host.uptime_seconds));
+                let Some(inventory) = view.inventories.iter().find(|inventory| inventory.host_id == host.id) else {
+                    return;
+                };
+                egui::Grid::new(("devtools_lattice_actors", &host.id)).num_columns(3).striped(true).show(ui, |ui| {
+                    for actor in &inventory.actors {
+                        ui.label(egui::RichText::new(actor.name.as_deref().unwrap_or(&actor.id)).monospace())
+                            .on_hover_text(&actor.image_ref);
+                        ui.label(format!("×{}", actor.instances));
+                        ui.horizontal(|ui| {
+                            for (label, target) in [("+", actor.instances + 1), ("−", actor.instances.saturating_sub(1))] {
+                                if ui.small_button(label).clicked() {
+                                    let (host, reference, id) = (host.id.clone(), actor.image_ref.clone(), actor.id.clone());
+                                    lattice_command(devtools_state, move |cloud| {
+                                        Box::pin(async move { cloud.scale_actor(&host, &reference, Some(&id), target).await })
+                                    });
+                                }
+                            }
+                            if ui.small_button("Остановить").clicked() {
+                                let (host, id) = (host.id.clone(), actor.id.clone());
+                                lattice_command(devtools_state, move |cloud| {
+                                    Box::pin(async move { cloud.stop_actor(&host, &id).await })
+                                });
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+                for provider in &inventory.providers {
+                    ui.label(format!("провайдер {} ({}, {})", provider.id, provider.contract_id, provider.link_name));
+                }
+            });
+            ui.add_space(5.0);
+        }
+
+        ui.separator();
+        ui.label("События:");
+        for event in devtools_state.lattice_events.iter().rev() {
+            ui.label(
+                egui::RichText::new(format!("{} {} {} {}", event.time, event.source, event.short_type(), event.data))
+                    .monospace(),
+            );
+        }
+    });
+}
+
+// Правки из devtools проходят тот же путь, что и изменения из скриптов: сохранение и событие storage
+fn render_storage_tab(ui: &mut Ui, devtools_state: &DevToolsState) {
+    let areas = devtools_state.engine.page_storage(devtools_state.page_id);
+    if areas.is_empty() {
+        ui.label("У страницы нет доступного хранилища");
+        return;
+    }
+    let error_id = Id::new("devtools_storage_error");
+    let apply = |ui: &mut Ui, change: anyhow::Result<Option<StorageChange>>| match change {
+        Ok(Some(change)) => {
+            let engine = devtools_state.engine.clone();
+            tokio::spawn(async move { engine.storage_changed(change).await });
+            ui.data_mut(|d| d.remove::<String>(error_id));
+        }
+        Ok(None) => {}
+        Err(e) => ui.data_mut(|d| d.insert_temp(error_id, e.to_string())),
+    };
+
+    ScrollArea::vertical().show(ui, |ui| {
+        if let Some(error) = ui.data(|d| d.get_temp::<String>(error_id)) {
+            ui.colored_label(egui::Color32::from_rgb(255, 110, 110), error);
+        }
+        for area in &areas {
+            let area_id = Id::new(("devtools_storage", area.kind().as_str()));
+            ui.group(|ui| {
+                ui.horizontal(|ui| {
+                    ui.heading(area.kind().as_str());
+                    ui.label(area.partition());
+                    ui.label(format!(
+                        "{} / {}",
+                        DebugInfo::format_size(area.usage()),
+                        DebugInfo::format_size(area.quota())
+                    ));
+                    if ui.button("Очистить").clicked() {
+                        apply(ui, Ok(area.clear(None)));
+                    }
+                });
+                egui::Grid::new(area_id).num_columns(3).striped(true).show(ui, |ui| {
+                    for (key, value) in area.entries() {
+                        let edit_id = area_id.with(&key);
+                        let mut edited = ui.data(|d| d.get_temp::<String>(edit_id)).unwrap_or_else(|| value.clone());
+                        ui.label(egui::RichText::new(&key).monospace());
+                        let response = ui.add(TextEdit::singleline(&mut edited).font(TextStyle::Monospace));
+                        if response.changed() {
+                            ui.data_mut(|d| d.insert_temp(edit_id, edited.clone()));
+                        }
+                        if response.lost_focus() && edited != value {
+                            ui.data_mut(|d| d.remove::<String>(edit_id));
+                            apply(ui, area.set_item(&key, &edited, None));
+                        }
+                        if ui.button("Удалить").clicked() {
+                            apply(ui, Ok(area.remove_item(&key, None)));
+                        }
+                        ui.end_row();
+                    }
+                });
+                let new_id = area_id.with("new_entry");
+                let (mut new_key, mut new_value) =
+                    ui.data(|d| d.get_temp::<(String, String)>(new_id)).unwrap_or_default();
+                ui.horizontal(|ui| {
+                    ui.add(TextEdit::singleline(&mut new_key).hint_text("Ключ").desired_width(120.0));
+                    ui.add(TextEdit::singleline(&mut new_value).hint_text("Значение"));
+                    if ui.button("Добавить").clicked() && !new_key.is_empty() {
+                        apply(ui, area.set_item(&new_key, &new_value, None));
+                        new_key.clear();
+                        new_value.clear();
+                    }
+                });
+                ui.data_mut(|d| d.insert_temp(new_id, (new_key, new_value)));
+            });
+            ui.add_space(5.0);
+        }
+    });
+}
+
+// === FILE: ui\font.rs ===
+use anyhow::Result;
+pub struct Font;
+impl Font {
+    pub fn new() -> Result<Self> {
+        println!("Font initialized");
+        Ok(Self)
+    }
+}
+
+// === FILE: ui\heavy_calculator.rs ===
+use rayon::prelude::*;
+use std::time::{Duration, Instant};
+use sysinfo::{System, RefreshKind};
+#[derive(Clone, Default)]
+pub struct HeavyCalculator {
+    pub result: Option<(f64, Duration, i64, f32)>, // (значение, время, дельта памяти, дельта CPU)
+}
+impl HeavyCalculator {
+    pub fn compute(&mut self, n: u32) {
+        let rk = RefreshKind::everything();
+        let mut sys = System::new_with_specifics(rk);
+        sys.refresh_specifics(rk);
+        let before_mem = sys.used_memory();
+        let before_cpu = sys.global_cpu_usage();
+        let start = Instant::now();
+        let value = self.heavy(n);
+        let duration = start.elapsed();
+        sys.refresh_specifics(rk);
+        let after_mem = sys.used_memory();
+        let after_cpu = sys.global_cpu_usage();
+        let mem_delta = (after_mem as i64 - before_mem as i64) / 1024;
+        let cpu_delta = after_cpu - before_cpu;
+        self.result = Some((value, duration, mem_delta, cpu_delta));
+    }
+    pub fn format_result(&self, format_duration: impl Fn(Duration) -> String) -> Option<String> {
+        self.result.map(|(value, duration, mem_delta, cpu_delta)| {
+            format!(
+                "Результат: {:.4}\nВремя: {}\nПамять (delta): {} KB\nCPU (delta): {:.2}%",
+                value,
+                format_duration(duration),
+                mem_delta,
+                cpu_delta
+            )
+        })
+    }
+    pub fn heavy(&self, n: u32) -> f64 {
+        (0..n)
+            .into_par_iter()
+            .map(|i| {
+                let x = i as f64;
+                x.sqrt() * x.sin()
+            })
+            .sum()
+    }
+}
+
+// === FILE: ui\input.rs ===
+use anyhow::Result;
+pub struct Input;
+impl Input {
+    pub fn new() -> Result<Self> {
+        println!("Input initialized");
+        Ok(Self)
+    }
+}
+
+// === FILE: ui\mod.rs ===
+// === FILE: ui/mod.rs ===
+use anyhow::Result;
+use crate::core::engine::BroEngine;
+use log;
+
+pub mod aichat;
+pub mod devtools;
+pub mod render;
+pub mod settings;
+pub mod toolbar;
+pub mod window;
+pub mod window_manager;
+pub mod wgpu_render;
+pub mod heavy_calculator;
+
+pub use window::ToolbarPosition;
+
+// --- Точка входа UI-клиента ---
+// Функция, которая запускает UI-клиент, используя инжектированный Engine.
+pub async fn run(engine: std::sync::Arc<BroEngine>, config: crate::core::config::Config) -> Result<()> {
+    use std::sync::Arc;
+    use winit::event_loop::EventLoop;
+
+    log::info!("UI: стартуем реальный UI (winit/egui/wgpu)");
+
+    // Создаем EventLoop
+    let event_loop = EventLoop::new()?;
+
+    let ctx = crate::core::event_loop::EventLoopContext {
+        engine,
+        config,
+    };
+
+    crate::core::event_loop::run(event_loop, ctx)?;
+
+    Ok(())
+}
+
+// === FILE: ui\render.rs ===
+use crate::dom::hydrate::hydrate_and_render;
+use crate::dom::parser::{parse_and_process, ParsedNode, FrameworkType};
+use crate::dom::tree::DomRenderer;
+use crate::dom::live::{DomEvent, LiveDom, SharedDom};
+use crate::core::policy::{PagePolicy, PolicyViolation};
+use crate::core::interfaces::IoManagerTrait;
+use crate::wasm_api::signals::{PageDocument, Signals};
+use egui::Ui;
+use anyhow::Result;
+use std::sync::Arc;
+
+pub struct HtmlRenderer {
+    pub dom_renderer: DomRenderer,
+    pub scale_factor: f32,
+    last_html: String, // Кэш последнего HTML для проверки изменений
+    cached_node: Option<ParsedNode>, // Кэш распарсенного DOM-дерева
+    needs_repaint: bool, // Флаг необходимости перерисовки
+    scripting_enabled: bool, // Влияет на разбор <noscript>
+    live_dom: SharedDom, // Изменяемое дерево, доступное скриптам страницы
+    dom_version: u64, // Версия live_dom, с которой снят cached_node
+    signals: Arc<Signals>, // Сигналы текущего документа; заменяются при загрузке нового
+}
+
+impl HtmlRenderer {
+    pub fn new(scale_factor: f32) -> Self {
+        log::info!("Создание HtmlRenderer с масштабом: {}", scale_factor);
+        Self {
+            dom_renderer: DomRenderer::new(),
+            scale_factor,
+            last_html: String::new(),
+            cached_node: None,
+            needs_repaint: true, // Изначально требуется рендер
+            scripting_enabled: cfg!(feature = "js"),
+            live_dom: LiveDom::shared(),
+            dom_version: 0,
+            signals: Signals::shared(),
+        }
+    }
+
+    pub fn live_dom(&self) -> SharedDom {
+        self.live_dom.clone()
+    }
+
+    // Документ для гидрации и WASM-компонентов страницы
+    pub fn document(&self) -> PageDocument {
+        PageDocument { dom: self.live_dom.clone(), signals: self.signals.clone() }
+    }
+
+    // Разбор документа с заменой живого DOM; снимок сразу становится кэшем рендера
+    pub fn load_document(&mut self, html: &str) -> Result<(ParsedNode, Option<FrameworkType>)> {
+        let (parsed, framework) = parse_and_process(html, self.scripting_enabled)?;
+        let mut dom = self.live_dom.write();
+        let node = dom.load(&parsed);
+        self.dom_version = dom.version();
+        drop(dom);
+        // Состояние и привязки прежнего документа не переходят к новому
+        self.signals = Signals::shared();
+        self.last_html = html.to_string();
+        self.cached_node = Some(node.clone());
+        self.needs_repaint = true;
+        Ok((node, framework))
+    }
+
+    pub fn set_scripting_enabled(&mut self, enabled: bool) {
+        if self.scripting_enabled != enabled {
+            self.scripting_enabled = enabled;
+            self.last_html.clear(); // Дерево нужно разобрать заново
+            self.cached_node = None;
+            self.needs_repaint = true;
+        }
+    }
+
+    pub fn set_scale_factor(&mut self, scale: f32) {
+        if (self.scale_factor - scale).abs() > 0.01 {
+            log::info!("Изменение масштаба HtmlRenderer: {} -> {}", self.scale_factor, scale);
+            self.scale_factor = scale;
+            self.needs_repaint = true; // Масштаб изменился, нужна перерисовка
+        }
+    }
+
+    pub fn needs_repaint(&self) -> bool {
+        self.needs_repaint || self.dom_renderer.needs_repaint()
+    }
+
+    pub fn clear_repaint_flag(&mut self) {
+        log::info!("Сброс флага перерисовки в HtmlRenderer");
+        self.needs_repaint = false;
+        self.dom_renderer.clear_repaint_flag();
+    }
+
+
+
+
+
+
+
+
+// === FILE: ui/render.rs (Исправленная функция render_html_ui) ===
+
+pub async fn render_html_ui(&mut self, ui: &mut Ui, html: &str) -> Result<Option<FrameworkType>> {
+    let node: ParsedNode;
+    let mut framework_to_return: Option<FrameworkType> = None;
+    let mut should_hydrate = false;
+    
+    // Объявляем detected_framework заранее, чтобы избежать ошибки E0425.
+    let mut detected_framework: Option<FrameworkType> = None; // 👈 ИСПРАВЛЕНИЕ 1: Добавляем объявление
+
+    // 1. Определение, нужно ли парсить и гидрировать
+    let was_repaint_needed = self.needs_repaint; // Сохраняем начальное состояние needs_repaint
+
+    if self.last_html != html {
+        // HTML ИЗМЕНИЛСЯ -> ПАРСИНГ и ПОЛНЫЙ РЕНДЕР
+        log::info!("HTML изменился, выполняется парсинг: {} -> {} (длина: {})",
+                     self.last_html.len(), html.len(), html.len());
+        
+        let (new_node, framework) = self.load_document(html)?; // load_document возвращает фреймворк
+        node = new_node;
+        should_hydrate = true;
+        
+        detected_framework = framework; // 👈 ИСПРАВЛЕНИЕ 2: Присваиваем значение
+        
+        // Удаляем: framework_to_return = detected_framework; (Это произойдет ниже в конце функции)
+
+    } else if self.cached_node.is_some() && self.live_dom.read().version() != self.dom_version {
+        // DOM ИЗМЕНЁН СКРИПТОМ -> НОВЫЙ СНИМОК, неизменённые поддеревья берутся из кэша LiveDom
+        let mut dom = self.live_dom.write();
+        node = dom.snapshot();
+        self.dom_version = dom.version();
+        drop(dom);
+        log::info!("DOM изменён скриптом, версия {}", self.dom_version);
+        self.cached_node = Some(node.clone());
+    } else if let Some(cached) = &self.cached_node {
+        // HTML НЕ ИЗМЕНИЛСЯ -> ИСПОЛЬЗУЕМ КЭШ
+        log::info!("HTML не изменился, используется кэшированное дерево");
+        node = cached.clone();
+        
+        // Решаем, нужна ли гидрация/полный рендер по другим причинам
+        if was_repaint_needed {
+             // Флаг поднят (клик, масштаб и т.д.) -> НУЖЕН ПОЛНЫЙ РЕНДЕР
+             log::info!("Требуется полный рендер из-за флага needs_repaint.");
+             should_hydrate = true;
+        }
+    } else {
+        // Кэш пуст при неизменном HTML (случай первого запуска) -> ПАРСИНГ и ПОЛНЫЙ РЕНДЕР
+        log::info!("Кэш пуст при неизменном HTML, выполняется парсинг HTML");
+        
+        let (new_node, framework) = self.load_document(html)?; // load_document возвращает фреймворк
+        node = new_node;
+        should_hydrate = true;
+        
+        detected_framework = framework; // 👈 ИСПРАВЛЕНИЕ 3: Присваиваем значение
+    }
+
+    // 2. Выполнение Гидрации/Рендера
+    if should_hydrate {
+        // ВЫЗЫВАЕМ ПОЛНЫЙ ЦИКЛ: ГИДРАЦИЯ + РЕНДЕР
+        log::info!("Запуск (условной) гидрации и рендера для HTML (длина: {})", html.len());
+        
+        // Передаем detected_framework в framework_to_return, если он был обнаружен при парсинге.
+        let document = self.document();
+        let framework = hydrate_and_render(ui, &mut self.dom_renderer, &document, node).await?;
+        
+        if framework.is_some() {
+            framework_to_return = framework;
+        } else if detected_framework.is_some() { // 👈 ИСПРАВЛЕНИЕ 4: Если framework_to_return не был установлен
+             framework_to_return = detected_framework; 
+        }
+
+        self.needs_repaint = false; // Сброс флага
+        
+    } else {
+        // ВЫЗЫВАЕМ ТОЛЬКО РЕНДЕР (если не нужна гидрация)
+        log::info!("HTML и флаги чисты. Только рендер кэшированного DOM.");
+        self.dom_renderer.render_node(ui, &node).await;
+        // Здесь мы не сбрасываем needs_repaint, т.к. мы его не использовали.
+        // Если он был true, то should_hydrate был бы true.
+    }
+    
+    // 3. Управление флагами для следующего кадра
+    if ui.ctx().has_requested_repaint() || self.dom_renderer.needs_repaint() {
+        
+		//////////////////////////////////////////////////////////////////
+		self.needs_repaint = false; // true - если имеем дело с хтмл css анимацией!		
+        //log::info!("HtmlRenderer запросил перерисовку после рендера (needs_repaint=true для следующего кадра).");
+		//////////////////////////////////////////////////////////////////
+		
+		
+    } else {
+        self.needs_repaint = false;
+    }
+
+
+    Ok(framework_to_return)
+}
+
+
+
+
+
+
+
+
+
+
+
+
+    pub fn get_last_link_click(&mut self) -> Option<String> {
+        if let Some(link) = self.dom_renderer.clicked_links.pop_front() {
+            log::info!("Обнаружен клик по ссылке: {}", link);
+            self.needs_repaint = true; // Клик требует перерисовки
+            Some(link)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_last_button_click(&mut self) -> Option<String> {
+        if let Some(button) = self.dom_renderer.clicked_buttons.pop_front() {
+            log::info!("Обнаружен клик по кнопке: {}", button);
+            self.needs_repaint = true; // Клик требует перерисовки
+            Some(button)
+        } else {
+            None
+        }
+    }
+
+    pub fn process_image_responses(&mut self, ui: &mut Ui) {
+        let had_images = !self.dom_renderer.clicked_links.is_empty() || !self.dom_renderer.clicked_buttons.is_empty();
+        self.dom_renderer.process_image_responses(ui);
+        if had_images && self.dom_renderer.needs_repaint() {
+            log::info!("DomRenderer запросил перерисовку из-за обработки изображений");
+            self.needs_repaint = true;
+            ui.ctx().request_repaint();
+        }
+    }
+
+    pub fn set_egui_context(&mut self, ctx: egui::Context) {
+        log::info!("Установка контекста egui для HtmlRenderer");
+        self.dom_renderer.set_egui_context(ctx);
+        self.needs_repaint = true; // Изменение контекста требует перерисовки
+    }
+
+    pub fn set_page_policy(&mut self, policy: Arc<PagePolicy>) {
+        self.dom_renderer.set_page_policy(policy);
+        self.needs_repaint = true;
+    }
+
+    pub fn set_io_manager(&mut self, io: Arc<dyn IoManagerTrait + Send + Sync>) {
+        self.dom_renderer.set_io_manager(io);
+    }
+
+    pub fn take_policy_violations(&mut self) -> Vec<PolicyViolation> {
+        self.dom_renderer.policy_violations.drain(..).collect()
+    }
+
+    pub fn take_dom_events(&mut self) -> Vec<(DomEvent, Option<String>)> {
+        self.dom_renderer.dom_events.drain(..).collect()
+    }
+}
+
+
+// === FILE: ui\settings.rs ===
+use egui::{Context as EguiContext, SidePanel, ScrollArea, ComboBox, Ui};
+use crate::core::config::Config;
+use crate::ui::devtools::PanelAction; // Импорт из devtools
+use crate::core::plugins::PluginInfo;
+pub fn render_settings(
+    ctx: &EguiContext,
+    config: &mut Config,
+    save_config: &mut bool,
+    plugins: &[PluginInfo],
+) {
+    SidePanel::right("settings_panel")
+        .resizable(true)
+        .default_width(400.0)
+        .min_width(200.0)
+        .show(ctx, |ui| {
+            render_settings_ui(ui, config, save_config, false, plugins)
+        });
 }
 pub fn render_settings_ui(
     ui: &mut Ui,
     config: &mut Config,
     save_config: &mut bool,
     is_detached: bool,
+    plugins: &[PluginInfo],
 ) -> Option<PanelAction> {
     let mut action: Option<PanelAction> = None;
     ui.horizontal(|ui| {
 		if ui.button(if is_detached { "🗔" } else { "🗔" }).clicked() {
             action = Some(if is_detached { PanelAction::Attach } else { PanelAction::Detach });
         }
-		ui.heading("Настройки");
-    });
-    ScrollArea::vertical().show(ui, |ui| {
-        ui.group(|ui| {
-            ui.label("Модули:");
-            ui.checkbox(&mut config.modules.core_enabled, "Core");
-            ui.checkbox(&mut config.modules.ui_enabled, "UI");
-            ui.checkbox(&mut config.modules.network_enabled, "Network");
-            ui.checkbox(&mut config.modules.dom_enabled, "DOM");
-            ui.checkbox(&mut config.modules.js_enabled, "JS");
-            ui.checkbox(&mut config.modules.wasmcloud_enabled, "WasmCloud");
-            ui.checkbox(&mut config.modules.p2p_enabled, "P2P");
-            ui.checkbox(&mut config.modules.vdom_enabled, "VDOM");
-            ui.checkbox(&mut config.modules.security_enabled, "Security");
-            ui.checkbox(&mut config.modules.rendering_enabled, "Rendering");
-            ui.checkbox(&mut config.modules.wasm_enabled, "WASM");
-            ui.checkbox(&mut config.modules.reactive_enabled, "Reactive");
-        });
-        ui.add_space(20.0);
-        ui.group(|ui| {
-            ui.label("Общие настройки:");
-            ui.checkbox(&mut config.settings.cache_enabled, "Включить кэширование");
-            ui.label("Уровень логирования:");
-            ComboBox::from_id_source("logging_level")
-                .selected_text(&config.settings.logging_level)
-                .show_ui(ui, |ui| {
-                    ui.selectable_value(&mut config.settings.logging_level, "trace".to_string(), "Trace");
-                    ui.selectable_value(&mut config.settings.logging_level, "debug".to_string(), "Debug");
-                    ui.selectable_value(&mut config.settings.logging_level, "info".to_string(), "Info");
-                    ui.selectable_value(&mut config.settings.logging_level, "warn".to_string(), "Warn");
-                    ui.selectable_value(&mut config.settings.logging_level, "error".to_string(), "Error");
-                });
-            ui.label("Максимальное количество потоков:");
-            ui.add(
-                egui::Slider::new(&mut config.settings.max_threads, 1..=16)
-                    .text("потоков")
-                    .clamp_to_range(true),
-            );
-        });
-        ui.add_space(20.0);
-        if ui.button("Сохранить настройки").clicked() {
-            *save_config = true;
+		ui.heading("Настройки");
+    });
+    ScrollArea::vertical().show(ui, |ui| {
+        ui.group(|ui| {
+            ui.label("Модули:");
+            ui.checkbox(&mut config.modules.core_enabled, "Core");
+            ui.checkbox(&mut config.modules.ui_enabled, "UI");
+            ui.checkbox(&mut config.modules.network_enabled, "Network");
+            ui.checkbox(&mut config.modules.dom_enabled, "DOM");
+            ui.checkbox(&mut config.modules.js_enabled, "JS");
+            ui.checkbox(&mut config.modules.wasmcloud_enabled, "WasmCloud");
+            ui.checkbox(&mut config.modules.p2p_enabled, "P2P");
+            ui.checkbox(&mut config.modules.vdom_enabled, "VDOM");
+            ui.checkbox(&mut config.modules.security_enabled, "Security");
+            ui.checkbox(&mut config.modules.rendering_enabled, "Rendering");
+            ui.checkbox(&mut config.modules.wasm_enabled, "WASM");
+            ui.checkbox(&mut config.modules.reactive_enabled, "Reactive");
+        });
+        ui.add_space(20.0);
+        ui.group(|ui| {
+            ui.label("Общие настройки:");
+            ui.checkbox(&mut config.settings.cache_enabled, "Включить кэширование");
+            ui.label("Уровень логирования:");
+            ComboBox::from_id_source("logging_level")
+                .selected_text(&config.settings.logging_level)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut config.settings.logging_level, "trace".to_string(), "Trace");
+                    ui.selectable_value(&mut config.settings.logging_level, "debug".to_string(), "Debug");
+                    ui.selectable_value(&mut config.settings.logging_level, "info".to_string(), "Info");
+                    ui.selectable_value(&mut config.settings.logging_level, "warn".to_string(), "Warn");
+                    ui.selectable_value(&mut config.settings.logging_level, "error".to_string(), "Error");
+                });
+            ui.label("Максимальное количество потоков:");
+            ui.add(
+                egui::Slider::new(&mut config.settings.max_threads, 1..=16)
+                    .text("потоков")
+                    .clamp_to_range(true),
+            );
+        });
+        ui.add_space(20.0);
+        ui.group(|ui| {
+            ui.label("Плагины:");
+            if plugins.is_empty() {
+                ui.label("Плагины не установлены");
+            }
+            // Состояние применяется при сохранении настроек
+            for plugin in plugins {
+                let name = &plugin.manifest.name;
+                let mut enabled = !config.plugins.disabled.contains(name);
+                let response = ui
+                    .checkbox(&mut enabled, format!("{} {}", name, plugin.manifest.version))
+                    .on_hover_text(&plugin.manifest.description);
+                if response.changed() {
+                    config.plugins.disabled.retain(|disabled| disabled != name);
+                    if !enabled {
+                        config.plugins.disabled.push(name.clone());
+                    }
+                }
+            }
+            ui.checkbox(&mut config.plugins.allow_native, "Разрешить нативные плагины")
+                .on_hover_text("Вступает в силу после перезапуска");
+        });
+        ui.add_space(20.0);
+        if ui.button("Сохранить настройки").clicked() {
+            *save_config = true;
+        }
+    });
+    action
+}
+
+// === FILE: ui\toolbar.rs ===
+use egui::{Ui, TextEdit, Button, CornerRadius, Frame, Vec2, Margin, Rect, Layout, Align};
+use crate::core::plugins::PluginButton;
+use std::collections::VecDeque;
+use log;
+pub fn render_toolbar(
+    ui: &mut Ui,
+    url_input: &mut String,
+    url: &mut String,
+    bookmarks: &VecDeque<String>,
+    show_devtools: &mut bool,
+    show_settings: &mut bool,
+    status_message: &mut String,
+    do_refresh: &mut bool,
+    do_add_bookmark: &mut bool,
+    new_url: &mut Option<String>,
+    toolbar_position: &mut super::ToolbarPosition,
+    show_toolbar: &mut bool,
+    plugin_buttons: &[PluginButton],
+    plugin_clicked: &mut Option<PluginButton>,
+) {
+    let padding: f32 = 5.0;
+    let height: f32 = 36.0;
+    Frame::NONE
+        .outer_margin(Margin::same(padding as i8))
+        .show(ui, |ui| {
+            let mut style = (*ui.ctx().style()).clone();
+            let rounding = CornerRadius::same(12);
+            style.visuals.widgets.inactive.corner_radius = rounding;
+            style.visuals.widgets.hovered.corner_radius = rounding;
+            style.visuals.widgets.active.corner_radius = rounding;
+            ui.set_style(style);
+            ui.spacing_mut().button_padding = Vec2::splat(padding);
+            let available = ui.available_rect_before_wrap();
+            let total_width = available.width();
+            let col_width = total_width / 3.0;
+            let collapse_threshold = 150.0;
+            let menu_button_width = 24.0;
+            log::debug!("Total width: {}, Col width: {}", total_width, col_width);
+            let btn_w = 24.0;
+            let inner_padding = padding * 2.0;
+            let is_col1_collapsed = col_width < collapse_threshold;
+            let is_col3_collapsed = col_width < collapse_threshold;
+            let col2_width = if is_col1_collapsed || is_col3_collapsed {
+                let col1_width = if is_col1_collapsed { menu_button_width } else { col_width };
+                let col3_width = if is_col3_collapsed { menu_button_width } else { col_width };
+                total_width - col1_width - col3_width
+            } else {
+                col_width
+            };
+            let rect1 = Rect::from_min_size(available.min, Vec2::new(if is_col1_collapsed { menu_button_width } else { col_width }, height));
+            let rect2 = Rect::from_min_size(rect1.right_top(), Vec2::new(col2_width, height));
+            let rect3 = Rect::from_min_size(rect2.right_top(), Vec2::new(if is_col3_collapsed { menu_button_width } else { col_width }, height));
+            ui.allocate_ui_at_rect(rect1, |ui| {
+                ui.with_layout(Layout::left_to_right(Align::Center), |ui| {
+                    if is_col1_collapsed {
+                        ui.menu_button("☰", |ui| {
+                            let spacing = ui.spacing_mut();
+                            spacing.button_padding = Vec2::splat(padding);
+                            Frame::NONE.outer_margin(Margin::same(5)).show(ui, |ui| {
+                                if ui.add_sized([120.0, 24.0], Button::new("<")).clicked() {
+                                    *status_message = "Назад нажато".to_owned();
+                                    ui.close();
+                                }
+                                if ui.add_sized([120.0, 24.0], Button::new(">")).clicked() {
+                                    *status_message = "Вперёд нажато".to_owned();
+                                    ui.close();
+                                }
+                                if ui.add_sized([120.0, 24.0], Button::new("🔄")).clicked() {
+                                    *do_refresh = true;
+                                    ui.close();
+                                }
+                            });
+                        });
+                    } else {
+						if ui.add_sized([24.0, 24.0], Button::new("🗔")).clicked() {
+                            *toolbar_position = super::ToolbarPosition::Detached;
+                            *show_toolbar = false;
+                            *status_message = "Панель инструментов откреплена".to_string();
+                        }
+                        if ui.add_sized([24.0, 24.0], Button::new("<")).clicked() {
+                            *status_message = "Назад нажато".to_owned();
+                        }
+                        if ui.add_sized([24.0, 24.0], Button::new(">")).clicked() {
+                            *status_message = "Вперёд нажато".to_owned();
+                        }
+                        if ui.add_sized([24.0, 24.0], Button::new("🔄")).clicked() {
+                            *do_refresh = true;
+                        }
+                    }
+                });
+            });
+            ui.allocate_ui_at_rect(rect2, |ui| {
+                ui.with_layout(Layout::left_to_right(Align::Center), |ui| {
+                    let edit_w = col2_width - btn_w - inner_padding * 2.0;
+                    let text_edit = ui.add_sized(
+                        [edit_w.max(100.0), height - inner_padding],
+                        TextEdit::singleline(url_input)
+                            .margin(Margin::same(padding as i8))
+                            .hint_text("Куда собрался?"),
+                    );
+                    if text_edit.lost_focus() && ui.ctx().input(|i| i.key_pressed(egui::Key::Enter)) {
+                        *url = url_input.clone();
+                        *new_url = Some(url.clone());
+                    }
+                    if ui.add_sized([btn_w, 24.0], Button::new("▶")).clicked() {
+                        *url = url_input.clone();
+                        *new_url = Some(url.clone());
+                    }
+                });
+            });
+            ui.allocate_ui_at_rect(rect3, |ui| {
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                    let gap = 6.0;
+                    if is_col3_collapsed {
+                        ui.menu_button("⋮", |ui| {
+                            let spacing = ui.spacing_mut();
+                            spacing.button_padding = Vec2::splat(padding);
+                            Frame::NONE.outer_margin(Margin::same(0)).show(ui, |ui| {
+                                if ui.add_sized([120.0, 24.0], Button::new("Открепить")).clicked() {
+                                    *toolbar_position = super::ToolbarPosition::Detached;
+                                    *show_toolbar = false;
+                                    *status_message = "Панель инструментов откреплена".to_string();
+                                    ui.close();
+                                }
+                                if ui.add_sized([120.0, 24.0], Button::new("Настройки")).clicked() {
+                                    *show_settings = !*show_settings;
+                                    *status_message = if *show_settings {
+                                        "Настройки открыты".to_owned()
+                                    } else {
+                                        "Настройки закрыты".to_owned()
+                                    };
+                                    ui.close();
+                                }
+                                if ui.add_sized([120.0, 24.0], Button::new("Инструменты разработчика")).clicked() {
+                                    *show_devtools = !*show_devtools;
+                                    *status_message = if *show_devtools {
+                                        "Инструменты разработчика открыты".to_owned()
+                                    } else {
+                                        "Инструменты разработчика закрыты".to_owned()
+                                    };
+                                    ui.close();
+                                }
+                                if !plugin_buttons.is_empty() {
+                                    ui.separator();
+                                }
+                                for plugin_button in plugin_buttons {
+                                    if ui.add_sized([120.0, 24.0], Button::new(&plugin_button.button.label)).clicked() {
+                                        *plugin_clicked = Some(plugin_button.clone());
+                                        ui.close();
+                                    }
+                                }
+                                ui.separator();
+                                if ui.add_sized([120.0, 24.0], Button::new("➕ Добавить закладку")).clicked() {
+                                    *do_add_bookmark = true;
+                                    ui.close();
+                                }
+                                ui.separator();
+                                for b in bookmarks {
+                                    if ui.add_sized([120.0, 24.0], Button::new(b)).clicked() {
+                                        *url_input = b.clone();
+                                        *new_url = Some(b.clone());
+                                        ui.close();
+                                    }
+                                }
+                            });
+                        });
+                    } else {
+                        if ui.add_sized([24.0, 24.0], Button::new("⚙")).clicked() {
+                            *show_settings = !*show_settings;
+                            *status_message = if *show_settings {
+                                "Настройки открыты".to_owned()
+                            } else {
+                                "Настройки закрыты".to_owned()
+                            };
+                        }
+                        
+                        ui.add_space(gap);
+                        if ui.add_sized([24.0, 24.0], Button::new("💻")).clicked() {
+                            *show_devtools = !*show_devtools;
+                            *status_message = if *show_devtools {
+                                "Инструменты разработчика открыты".to_owned()
+                            } else {
+                                "Инструменты разработчика закрыты".to_owned()
+                            };
+                        }
+                        ui.add_space(gap);
+                        ui.menu_button("☆", |ui| {
+                            let spacing = ui.spacing_mut();
+                            spacing.button_padding = Vec2::splat(padding);
+                            Frame::NONE.outer_margin(Margin::same(0)).show(ui, |ui| {
+                                if ui.add_sized([120.0, 24.0], Button::new("➕ Добавить")).clicked() {
+                                    *do_add_bookmark = true;
+                                    ui.close();
+                                }
+                                ui.separator();
+                                for b in bookmarks {
+                                    if ui.add_sized([120.0, 24.0], Button::new(b)).clicked() {
+                                        *url_input = b.clone();
+                                        *new_url = Some(b.clone());
+                                        ui.close();
+                                    }
+                                }
+                            });
+                        });
+                        for plugin_button in plugin_buttons {
+                            ui.add_space(gap);
+                            let tooltip = plugin_button.button.tooltip.as_deref().unwrap_or(&plugin_button.button.label);
+                            let label = format!("{} ({})", tooltip, plugin_button.plugin);
+                            if ui.add_sized([24.0, 24.0], Button::new(&plugin_button.button.label)).on_hover_text(label).clicked() {
+                                *plugin_clicked = Some(plugin_button.clone());
+                            }
+                        }
+                    }
+                });
+            });
+        });
+}
+
+// === FILE: ui\wgpu_render.rs ===
+use wgpu::*;
+use egui_wgpu::{Renderer, ScreenDescriptor};
+use anyhow::Result;
+use egui::{Context};
+use std::time::{Instant, Duration};
+
+#[derive(Debug, Clone, Copy)]
+pub enum GraphicsBackend {
+    Auto,
+    Vulkan,
+    DirectX12,
+    Metal,
+    OpenGL,
+}
+
+impl GraphicsBackend {
+    pub fn from_str(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "vulkan" => Self::Vulkan,
+            "dx12" | "directx12" | "directx" => Self::DirectX12,
+            "metal" => Self::Metal,
+            "gl" | "opengl" => Self::OpenGL,
+            _ => Self::Auto,
+        }
+    }
+
+    pub fn to_wgpu_backends(&self) -> Backends {
+        match self {
+            Self::Auto => Backends::PRIMARY,
+            Self::Vulkan => Backends::VULKAN,
+            Self::DirectX12 => Backends::DX12,
+            Self::Metal => Backends::METAL,
+            Self::OpenGL => Backends::GL,
+        }
+    }
+
+    pub fn to_string(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Vulkan => "vulkan",
+            Self::DirectX12 => "dx12",
+            Self::Metal => "metal",
+            Self::OpenGL => "opengl",
+        }
+    }
+}
+
+pub struct RenderManager {
+    last_render_time: Option<Instant>,
+    last_activity: Instant,
+    frame_count: u32,
+    fps_update_time: Instant,
+    pub is_active: bool,
+    pub idle_mode: bool,
+    pub total_frames_rendered: u32,
+    pub total_frames_skipped: u32,
+    pub is_minimized: bool,
+    enable_fps_throttling: bool,
+    last_size: (u32, u32),
+}
+
+impl RenderManager {
+    pub fn new(enable_optimizations: bool) -> Self {
+        Self {
+            last_render_time: None,
+            last_activity: Instant::now(),
+            frame_count: 0,
+            fps_update_time: Instant::now(),
+            is_active: true,
+            idle_mode: false,
+            total_frames_rendered: 0,
+            total_frames_skipped: 0,
+            is_minimized: false,
+            enable_fps_throttling: enable_optimizations,
+            last_size: (0, 0),
+        }
+    }
+
+    pub fn set_window_active(&mut self, active: bool) {
+        self.is_active = active;
+        if active {
+            self.update_activity();
+        }
+    }
+
+    pub fn set_minimized(&mut self, minimized: bool) {
+        self.is_minimized = minimized;
+        if !minimized {
+            self.update_activity();
+        }
+    }
+
+    pub fn update_activity(&mut self) {
+        self.last_activity = Instant::now();
+        if self.idle_mode {
+            log::debug!("Выход из idle режима");
+        }
+        self.idle_mode = false;
+    }
+
+    pub fn should_render(&mut self, ctx: &Context) -> bool {
+        if self.is_minimized {
+            self.total_frames_skipped += 1;
+            return false;
+        }
+
+        let now = Instant::now();
+        let time_since_activity = self.last_activity.elapsed();
+        let (max_fps, mode_name) = if time_since_activity > Duration::from_secs(2) {
+            self.idle_mode = true;
+            (30, "idle")
+        } else if !self.is_active {
+            self.idle_mode = true;
+            (1, "inactive")
+        } else {
+            self.idle_mode = false;
+            (60, "active")
+        };
+
+        if self.enable_fps_throttling {
+            if let Some(last_render) = self.last_render_time {
+                let min_frame_time = Duration::from_millis(1000 / max_fps as u64);
+                if now.duration_since(last_render) < min_frame_time {
+                    self.total_frames_skipped += 1;
+                    return false;
+                }
+            }
+            if self.idle_mode && !ctx.has_requested_repaint() {
+                self.total_frames_skipped += 1;
+                return false;
+            }
+        }
+
+        self.last_render_time = Some(now);
+        self.total_frames_rendered += 1;
+        self.frame_count += 1;
+
+        if now.duration_since(self.fps_update_time) > Duration::from_secs(2) {
+            let fps = self.frame_count as f32 / 2.0;
+            let efficiency = if self.total_frames_rendered + self.total_frames_skipped > 0 {
+                (self.total_frames_rendered as f32 / (self.total_frames_rendered + self.total_frames_skipped) as f32) * 100.0
+            } else {
+                0.0
+            };
+            log::debug!(
+                "Режим: {}, FPS: {:.1}, Эффективность: {:.1}% (пропущено: {})",
+                mode_name,
+                fps,
+                efficiency,
+                self.total_frames_skipped
+            );
+            self.frame_count = 0;
+            self.fps_update_time = now;
+        }
+        true
+    }
+
+    pub fn tessellate(
+        &mut self,
+        ctx: &Context,
+        shapes: &[egui::epaint::ClippedShape],
+        pixels_per_point: f32,
+    ) -> Vec<egui::epaint::ClippedPrimitive> {
+        let primitives = ctx.tessellate(shapes.to_vec(), pixels_per_point);
+        for prim in &primitives {
+            if let egui::epaint::ClippedPrimitive {
+                primitive: egui::epaint::Primitive::Mesh(mesh),
+                ..
+            } = prim
+            {
+                if mesh.vertices.is_empty() || mesh.indices.is_empty() {
+                    log::warn!("Обнаружен пустой меш при тесселяции");
+                }
+            }
+        }
+        primitives
+    }
+
+    pub fn update_screen_descriptor(&mut self, width: u32, height: u32, pixels_per_point: f32) -> ScreenDescriptor {
+        let safe_width = width.max(1);
+        let safe_height = height.max(1);
+        if self.last_size != (safe_width, safe_height) {
+            log::debug!("Обновлены размеры экрана: {}x{}", safe_width, safe_height);
+            self.last_size = (safe_width, safe_height);
+        }
+        ScreenDescriptor {
+            size_in_pixels: [safe_width, safe_height],
+            pixels_per_point,
+        }
+    }
+
+    pub fn get_stats(&self) -> String {
+        let total = self.total_frames_rendered + self.total_frames_skipped;
+        let efficiency = if total > 0 {
+            (self.total_frames_rendered as f32 / total as f32) * 100.0
+        } else {
+            0.0
+        };
+        format!(
+            "Рендер: {} кадров, Пропущено: {} (Эффективность: {:.1}%)",
+            self.total_frames_rendered,
+            self.total_frames_skipped,
+            efficiency
+        )
+    }
+
+    pub fn clear_cache(&mut self) {
+        log::debug!("Очистка кэша рендера");
+    }
+
+    pub fn set_all_optimizations(&mut self, enabled: bool) {
+        self.enable_fps_throttling = enabled;
+    }
+
+    pub fn set_fps_throttling(&mut self, enabled: bool) {
+        self.enable_fps_throttling = enabled;
+    }
+}
+
+pub fn render_frame(
+    device: &Device,
+    queue: &Queue,
+    surface: &Surface,
+    surface_config: &SurfaceConfiguration,
+    renderer: &mut Renderer,
+    ctx: &Context,
+    full_output: &egui::FullOutput,
+    render_manager: &mut RenderManager,
+) -> Result<bool> {
+    // Проверка минимальных размеров и состояния минимизации
+    if render_manager.is_minimized || surface_config.width == 0 || surface_config.height == 0 {
+        render_manager.total_frames_skipped += 1;
+        log::trace!("Пропуск рендеринга: минимизировано или нулевые размеры");
+        return Ok(false);
+    }
+
+    // Проверка необходимости рендеринга
+    if !render_manager.should_render(ctx) {
+        log::trace!("Пропуск рендеринга: троттлинг или отсутствие изменений");
+        return Ok(false);
+    }
+
+    // Получение текущей текстуры поверхности
+    let output_frame = match surface.get_current_texture() {
+        Ok(frame) => frame,
+        Err(SurfaceError::Lost | SurfaceError::Outdated) => {
+            // Переконфигурируем поверхность
+            surface.configure(device, surface_config);
+            match surface.get_current_texture() {
+                Ok(frame) => frame,
+                Err(e) => {
+                    log::warn!("Повторная ошибка получения текстуры: {}", e);
+                    return Ok(false);
+                }
+            }
+        }
+        Err(SurfaceError::OutOfMemory) => {
+            return Err(anyhow::anyhow!("Недостаточно памяти для текстуры поверхности"));
+        }
+        Err(e) => {
+            log::warn!("Ошибка получения текстуры поверхности: {}", e);
+            return Ok(false);
+        }
+    };
+
+    // Создание представления текстуры
+    let view = output_frame
+        .texture
+        .create_view(&TextureViewDescriptor::default());
+
+
+
+
+// Обновление текстур egui с проверкой размеров
+for (id, image_delta) in &full_output.textures_delta.set {
+    let size = image_delta.image.size();
+    let [width, height] = size;
+    renderer.update_texture(device, queue, *id, image_delta);
+}
+
+
+
+
+
+
+
+
+
+
+
+
+    // Освобождение неиспользуемых текстур
+    for id in &full_output.textures_delta.free {
+        renderer.free_texture(id);
+    }
+
+    // Пропуск рендеринга, если нет данных для отрисовки
+    if full_output.shapes.is_empty() {
+        log::trace!("Пропуск рендеринга: нет shapes для отрисовки");
+        return Ok(true);
+    }
+
+    // Тесселяция UI
+    let paint_jobs = render_manager.tessellate(ctx, &full_output.shapes, full_output.pixels_per_point);
+
+    // Обновление дескриптора экрана
+    let screen_descriptor = render_manager.update_screen_descriptor(
+        surface_config.width,
+        surface_config.height,
+        full_output.pixels_per_point,
+    );
+
+ 
+ 
+ 
+
+
+
+// Создание командного энкодера
+let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+    label: Some("egui_encoder"),
+});
+
+// Обновление буферов для рендеринга
+if !paint_jobs.is_empty() {
+    // В этом месте encoder заимствуется, но это временное заимствование.
+    renderer.update_buffers(device, queue, &mut encoder, &paint_jobs, &screen_descriptor);
+}
+
+// Рендеринг кадра
+if !paint_jobs.is_empty() {
+    let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("egui_rpass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: &view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: wgpu::StoreOp::Store,
+            },
+            depth_slice: None,
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    })
+    // ВОЗВРАЩАЕМ .forget_lifetime() ИЗ-ЗА ТРЕБОВАНИЯ 'static / E0505
+    .forget_lifetime(); 
+    
+    // Используем rpass для рендеринга
+    renderer.render(&mut rpass, &paint_jobs, &screen_descriptor);
+
+    // rpass теперь можно игнорировать, так как он не держит &mut encoder.
+} 
+
+// Отправка команд в очередь
+if !paint_jobs.is_empty() {
+    queue.submit(std::iter::once(encoder.finish()));
+}
+ 
+ 
+ 
+ 
+ 
+ 
+
+ 
+
+    // Презентация кадра
+    output_frame.present();
+    log::trace!("Кадр успешно отрендерен: {}x{}", surface_config.width, surface_config.height);
+
+    Ok(true)
+}
+
+pub fn create_optimal_surface_config(
+    surface_caps: &SurfaceCapabilities,
+    size: (u32, u32),
+) -> Result<SurfaceConfiguration> {
+    let surface_format = surface_caps
+        .formats
+        .get(0)
+        .copied()
+        .ok_or_else(|| anyhow::anyhow!("Нет доступных surface formats"))?;
+    let preferred_modes = [
+		PresentMode::Mailbox, // Mailbox для лучшей производительности без tearing
+		PresentMode::Fifo, // FIFO (очередь) гарантирует отсутствие tearing, синхронизирует с вертикальной разверткой
+		PresentMode::FifoRelaxed, // Ослабленная FIFO: минимальная задержка, возможен tearing при пропуске кадра
+		PresentMode::Immediate, // Немедленная подача кадра: максимальная скорость, но возможен tearing
+
+    ];
+    let present_mode = preferred_modes
+        .iter()
+        .find(|mode| surface_caps.present_modes.contains(mode))
+        .copied()
+        .ok_or_else(|| anyhow::anyhow!("Нет доступных present modes"))?;
+    log::debug!(
+        "Выбран режим представления: {:?} (для предотвращения suboptimal present)",
+        present_mode
+    );
+    Ok(SurfaceConfiguration {
+        usage: TextureUsages::RENDER_ATTACHMENT,
+        format: surface_format,
+        width: size.0.max(1),
+        height: size.1.max(1),
+        present_mode,
+        alpha_mode: surface_caps.alpha_modes.get(0).copied().unwrap_or_default(),
+        view_formats: vec![surface_format],
+        desired_maximum_frame_latency: 2,
+    })
+}
+
+pub fn create_energy_efficient_instance() -> Instance {
+    Instance::new(&InstanceDescriptor {
+        backends: Backends::PRIMARY,
+        flags: InstanceFlags::empty(),
+        ..Default::default()
+    })
+}
+
+pub fn create_energy_efficient_adapter(instance: &Instance, surface: &Surface) -> Result<Adapter> {
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: PowerPreference::LowPower,
+        force_fallback_adapter: false,
+        compatible_surface: Some(surface),
+    }))
+    .map_err(|e| anyhow::anyhow!("Ошибка при запросе адаптера: {e}"))?;
+    Ok(adapter)
+}
+
+// === FILE: ui\window.rs ===
+use crate::core::config::Config;
+use crate::core::engine::{BroEngine, EngineEvent, UrlResponse};
+use crate::core::page_state::SharedPageState;
+use crate::core::policy::PagePolicy;
+use crate::core::js_runtime::RealmOptions;
+use crate::core::page_state::PageId;
+use crate::dom::parser::FrameworkType;
+use crate::dom::scripts::{collect_scripts, run_page_scripts};
+use crate::ui::devtools::{DebugInfo, DevToolsTab, LatticeView, RequestLog, PanelAction, DevToolsState};
+use crate::core::plugins::{PanelContent, PanelItem, PluginButton, SidePanelSpec};
+use crate::ui::wgpu_render;
+use crate::ui::window_manager::{DetachedWindowData, PanelType, WindowManager, WindowState};
+use crate::ui::render::HtmlRenderer;
+use crate::ui::{aichat, devtools, settings, toolbar};
+use anyhow::{Result, Context};
+use url::Url;
+use egui::{Context as EguiContext, FontDefinitions, FontId, FontFamily, TextStyle, Color32, CornerRadius, Style, TopBottomPanel, Frame, SidePanel, CentralPanel, ScrollArea};
+use egui::epaint::mutex::RwLock;
+use egui::widgets::text_edit::TextEditOutput;
+use egui_extras::{Column, TableBuilder};
+use egui_winit::State as EguiState;
+use pollster::FutureExt as _;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use winit::dpi::PhysicalSize;
+use winit::window::Window as WinitWindow;
+use winit::event::WindowEvent;
+use winit::event_loop::ActiveEventLoop;
+use wgpu::{Adapter, Device, Instance, Queue, Surface, SurfaceConfiguration, DeviceDescriptor, Limits};
+use egui_wgpu::{Renderer, RendererOptions};
+use reqwest::header::HeaderMap;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender, UnboundedReceiver};
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolbarPosition {
+    Top,
+    Bottom,
+    Floating,
+    Detached,
+    Hidden,
+}
+
+pub struct Window {
+    pub window: Arc<WinitWindow>,
+    pub config: Config,
+    // Конфигурация на момент запуска или последнего сохранения: в settings.toml пишется разница с ней
+    saved_config: Config,
+    pub egui_state: EguiState,
+    pub egui_ctx: EguiContext,
+    pub url: String,
+    pub html: String,
+    pub status_message: String,
+    pub bookmarks: VecDeque<String>,
+    pub show_devtools: bool,
+    pub show_settings: bool,
+    pub show_toolbar: bool,
+    pub devtools_tab: DevToolsTab,
+    pub headers: HeaderMap,
+    pub instance: Instance,
+    pub surface: Surface<'static>,
+    pub adapter: Adapter,
+    pub device: Device,
+    pub queue: Queue,
+    pub surface_config: SurfaceConfiguration,
+    pub renderer: Renderer,
+    pub html_scale_factor: f32,
+    pub html_renderer: HtmlRenderer,
+    pub tx_ssh: UnboundedSender<String>,
+    pub render_manager: wgpu_render::RenderManager,
+    pub url_input: String,
+    pub ai_chat_input: String,
+    pub ai_chat_history: Vec<(String, String)>,
+    pub send_ai_message: bool,
+    pub save_config: bool,
+    pub do_refresh: bool,
+    pub do_add_bookmark: bool,
+    pub new_url: Option<String>,
+    pub plugin_clicked: Option<PluginButton>,
+    // Открытая боковая панель плагина и её последнее содержимое
+    pub plugin_panel: Option<(String, SidePanelSpec)>,
+    pub plugin_panel_content: Arc<parking_lot::Mutex<Result<PanelContent, String>>>,
+    // Ссылка, чей click не отменён обработчиками страницы
+    pub pending_link: Arc<parking_lot::Mutex<Option<String>>>,
+    pub debug_info: DebugInfo,
+    pub last_request_time: Option<Instant>,
+    pub window_state: WindowState,
+    pub window_manager: WindowManager,
+    pub engine: Arc<BroEngine>,
+    pub devtools_state: DevToolsState,
+}
+
+impl Window {
+    pub async fn new(
+        window: Arc<WinitWindow>,
+        config: &Config,
+        engine: Arc<BroEngine>,
+        enable_optimizations: bool,
+        event_loop: &ActiveEventLoop,
+    ) -> Result<Self> {
+        log::info!("Создание нового окна с масштабом: {}", config.settings.scale_factor.unwrap_or(1.0));
+        let instance = wgpu_render::create_energy_efficient_instance();
+        let surface = instance
+            .create_surface(window.clone())
+            .context("Не удалось создать wgpu поверхность")?;
+        let adapter = wgpu_render::create_energy_efficient_adapter(&instance, &surface)?;
+        let (device, queue) = adapter.request_device(&DeviceDescriptor {
+            required_limits: Limits::default(),
+            ..Default::default()
+        }).await.context("Не удалось создать wgpu устройство")?;
+        let size = window.inner_size();
+        if size.width == 0 || size.height == 0 {
+            return Err(anyhow::anyhow!(
+                "Недопустимый размер окна: {}x{}",
+                size.width,
+                size.height
+            ));
+        }
+        let surface_caps = surface.get_capabilities(&adapter);
+        let surface_config = wgpu_render::create_optimal_surface_config(
+            &surface_caps,
+            (size.width, size.height),
+        )?;
+        surface.configure(&device, &surface_config);
+        let renderer = Renderer::new(&device, surface_config.format, RendererOptions::default());
+        let egui_ctx = EguiContext::default();
+        let system_scale_factor = window.scale_factor() as f32;
+        egui_ctx.set_pixels_per_point(system_scale_factor);
+        let fonts = egui::FontDefinitions::default();
+        egui_ctx.set_fonts(fonts);
+        let mut style = Style::default();
+        style.text_styles.insert(TextStyle::Body, FontId::new(12.0, FontFamily::Proportional));
+        style.text_styles.insert(TextStyle::Heading, FontId::new(20.0, FontFamily::Proportional));
+        style.text_styles.insert(TextStyle::Monospace, FontId::new(12.0, FontFamily::Monospace));
+        egui_ctx.set_style(style);
+        let egui_state = EguiState::new(
+            egui_ctx.clone(),
+            egui::ViewportId::ROOT,
+            event_loop,
+            None,
+            None,
+            None,
+        );
+        let render_manager = wgpu_render::RenderManager::new(enable_optimizations);
+        let mut html_renderer = HtmlRenderer::new(system_scale_factor);
+        html_renderer.set_egui_context(egui_ctx.clone());
+        html_renderer.set_scripting_enabled(cfg!(feature = "js") && config.modules.js_enabled);
+        html_renderer.set_io_manager(engine.io());
+        let window_manager = WindowManager::new(instance.clone(), adapter.clone(), device.clone(), queue.clone());
+        window.set_maximized(true);
+        window.set_min_inner_size(Some(winit::dpi::PhysicalSize::new(320, 480)));
+        let (tx_ssh, _rx_ssh) = unbounded_channel::<String>();
+        
+        // Создаем DevToolsState с ресивером событий движка
+        let page_id = PageId::next();
+        let devtools_state = DevToolsState {
+            engine: engine.clone(),
+            detected_framework: None,
+            network_logs: Vec::new(),
+            policy_violations: Vec::new(),
+            console_messages: Vec::new(),
+            wasm_output: VecDeque::new(),
+            lattice_events: VecDeque::new(),
+            lattice_view: Arc::new(parking_lot::Mutex::new(LatticeView::default())),
+            page_id,
+            realm_options: Arc::new(parking_lot::Mutex::new(RealmOptions::default())),
+            debug_info: DebugInfo::new(),
+            rx_engine: engine.event_receiver(),
+        };
+        let repaint_ctx = egui_ctx.clone();
+        let mut mutations = html_renderer.live_dom().read().subscribe();
+        tokio::spawn(async move {
+            while mutations.changed().await.is_ok() {
+                repaint_ctx.request_repaint();
+            }
+        });
+        engine.set_page_dom(page_id, html_renderer.live_dom());
+        if let Err(e) = engine.create_page_realm(page_id, RealmOptions::default()).await {
+            log::warn!("JS realm страницы не создан: {}", e);
+        }
+
+        let instance_self = Self {
+            window,
+            config: config.clone(),
+            saved_config: config.clone(),
+            egui_state,
+            egui_ctx,
+            url: config.settings.default_url.clone(),
+            url_input: config.settings.default_url.clone(),
+            html: r#"<html><body><h1>Добро пожаловать!</h1>
+<pre>
+____________________ ________   
+\______   \______   \\_____  \  
+ |    |  _/|       _/ /   |   \ 
+ |    |   \|    |   \/    |    \
+ |________/|____|___/\_________/
+ </pre>
+</body>
+</html>"#.to_string(),
+            status_message: "Готово".to_string(),
+            bookmarks: crate::core::about::saved_bookmarks().into(),
+            show_devtools: false,
+            show_settings: false,
+            show_toolbar: true,
+            devtools_tab: DevToolsTab::Html,
+            headers: HeaderMap::new(),
+            instance,
+            surface,
+            adapter,
+            device,
+            queue,
+            surface_config,
+            renderer,
+            render_manager,
+            html_scale_factor: system_scale_factor,
+            html_renderer,
+            tx_ssh,
+            ai_chat_input: String::new(),
+            ai_chat_history: Vec::new(),
+            send_ai_message: false,
+            save_config: false,
+            do_refresh: false,
+            do_add_bookmark: false,
+            new_url: None,
+            plugin_clicked: None,
+            plugin_panel: None,
+            plugin_panel_content: Arc::new(parking_lot::Mutex::new(Ok(PanelContent::default()))),
+            pending_link: Arc::new(parking_lot::Mutex::new(None)),
+            debug_info: DebugInfo::new(),
+            last_request_time: None,
+            window_state: WindowState::default(),
+            window_manager,           
+            engine,
+            devtools_state,
+        };
+        instance_self.window.set_visible(true);
+        log::info!("Окно инициализировано, начальный URL: {}", instance_self.url);
+        Ok(instance_self)
+    }
+
+    pub fn handle_event(&mut self, window_event: &WindowEvent, window_id: winit::window::WindowId) -> Result<()> {
+        log::info!("Обработка события окна: {:?}", window_event);
+        self.render_manager.update_activity();
+        if window_id == self.window.id() {
+            if let WindowEvent::ScaleFactorChanged { scale_factor, .. } = window_event {
+                let new_scale = *scale_factor as f32;
+                self.egui_ctx.set_pixels_per_point(new_scale);
+                self.html_renderer.set_scale_factor(new_scale);
+                self.html_scale_factor = new_scale;
+                log::info!("Изменение системного масштаба: {:.1}x, запрос перерисовки", new_scale);
+            }
+            let resp = self.egui_state.on_window_event(&self.window, window_event);
+            if resp.repaint {
+                log::info!("EguiState запросил перерисовку после события окна");
+                self.window.request_redraw();
+            }
+        } else {
+            for (panel_type, _) in self.window_state.detached_windows.iter() {
+                if self.window_state.detached_windows.get(panel_type).map(|dw| dw.window.id()) == Some(window_id) {
+                    self.window_manager.handle_window_event(panel_type, window_event)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn resize(&mut self, new_size: PhysicalSize<u32>, window_id: winit::window::WindowId) -> Result<()> {
+        log::info!("Изменение размера окна: {}x{}", new_size.width, new_size.height);
+        if window_id == self.window.id() {
+            if new_size.width > 0 && new_size.height > 0 {
+                let surface_caps = self.surface.get_capabilities(&self.adapter);
+                self.surface_config = wgpu_render::create_optimal_surface_config(
+                    &surface_caps,
+                    (new_size.width, new_size.height),
+                )?;
+                self.surface.configure(&self.device, &self.surface_config);
+                log::info!("Конфигурация поверхности обновлена, запрос перерисовки");
+            }
+        } else {
+            for (panel_type, _) in self.window_state.detached_windows.iter() {
+                if self.window_state.detached_windows.get(panel_type).map(|dw| dw.window.id()) == Some(window_id) {
+                    self.window_manager.resize_detached_window(panel_type, new_size)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn reset_scale_factor(&mut self) {
+        self.html_renderer.set_scale_factor(self.html_scale_factor);
+        self.egui_ctx.set_pixels_per_point(self.html_scale_factor);
+        self.status_message = format!("Масштаб сброшен: {:.1}x", self.html_scale_factor);
+        log::info!("Масштаб сброшен: {:.1}x, запрос перерисовки", self.html_scale_factor);
+    }
+
+    pub fn change_scale_factor(&mut self, delta: f32) {
+        let new_scale = (self.html_scale_factor + delta).max(0.5).min(3.0);
+        if (new_scale - self.html_scale_factor).abs() > 0.01 {
+            self.html_scale_factor = new_scale;
+            self.html_renderer.set_scale_factor(new_scale);
+            self.egui_ctx.set_pixels_per_point(new_scale);
+            self.status_message = format!("Масштаб: {:.1}x", new_scale);
+            log::info!("Масштаб изменён: {:.1}x, запрос перерисовки", new_scale);
+        }
+    }
+
+    // ИСПРАВЛЕННАЯ ФУНКЦИЯ: Загружает URL и обновляет UI через события движка
+    pub fn load_url(&mut self, url: String) {
+        log::info!("Загрузка URL: {}", url);
+        let engine = self.engine.clone();
+        let value = url.clone();
+        // Запускаем фоновую задачу для загрузки
+        tokio::spawn(async move {
+            match engine.fetch_url(value.clone()).await {
+                Ok(response) => {
+                    log::info!("URL загружен успешно: {}", value);
+                    engine.send_event(EngineEvent::UrlResponse(response));
+                }
+                Err(e) => {
+                    log::error!("Ошибка загрузки URL {}: {}", value, e);
+                    engine.send_event(EngineEvent::Error(format!("Ошибка загрузки {}: {}", value, e)));
+                }
+            }
+        });
+        
+        self.status_message = format!("Загрузка: {}...", url);
+        self.egui_ctx.request_repaint();
+    }
+
+    fn scripting_enabled(&self) -> bool {
+        cfg!(feature = "js") && self.config.modules.js_enabled
+    }
+
+    // Каждый загруженный документ получает чистый realm, затем исполняются его скрипты
+    fn reset_page_realm(&mut self, policy: Arc<PagePolicy>) {
+        self.devtools_state.console_messages.clear();
+        let scripting_enabled = self.scripting_enabled();
+        self.html_renderer.set_scripting_enabled(scripting_enabled);
+        // Скрипты берутся из того же дерева, что видят document и рендер
+        let scripts = match self.html_renderer.load_document(&self.html) {
+            Ok((node, _)) if scripting_enabled => collect_scripts(&node),
+            Ok(_) => Vec::new(),
+            Err(e) => {
+                log::error!("Ошибка разбора документа страницы: {}", e);
+                Vec::new()
+            }
+        };
+        let engine = self.engine.clone();
+        let page = self.devtools_state.page_id;
+        engine.set_page_dom(page, self.html_renderer.live_dom());
+        engine.set_page_policy(page, policy.clone());
+        let options = self.devtools_state.realm_options.lock().clone();
+        let url = self.url.clone();
+        tokio::spawn(async move {
+            if let Err(e) = engine.create_page_realm(page, options).await {
+                log::warn!("JS realm страницы не создан: {}", e);
+            } else {
+                run_page_scripts(engine.clone(), page, policy, scripts).await;
+            }
+            engine.send_event(EngineEvent::PageLoaded { page, url });
+        });
+    }
+
+    fn refresh_plugin_panel(&self) {
+        let Some((plugin, spec)) = self.plugin_panel.clone() else {
+            return;
+        };
+        let plugins = self.engine.plugins();
+        let content = self.plugin_panel_content.clone();
+        let ctx = self.egui_ctx.clone();
+        tokio::spawn(async move {
+            *content.lock() = plugins.render_panel(&plugin, &spec.id).await.map_err(|e| e.to_string());
+            ctx.request_repaint();
+        });
+    }
+
+    // Кнопка плагина: событие плагину и, если указано, переключение его панели
+    fn handle_plugin_click(&mut self, clicked: PluginButton) {
+        let plugins = self.engine.plugins();
+        if let Some(panel) = &clicked.button.panel {
+            let already_open = matches!(&self.plugin_panel, Some((plugin, spec)) if *plugin == clicked.plugin && spec.id == *panel);
+            self.plugin_panel = if already_open {
+                None
+            } else {
+                plugins.side_panel(&clicked.plugin, panel).map(|spec| (clicked.plugin.clone(), spec))
+            };
+            self.refresh_plugin_panel();
+        }
+        tokio::spawn(async move {
+            if let Err(e) = plugins.toolbar_clicked(&clicked.plugin, &clicked.button.id).await {
+                log::warn!("Кнопка плагина: {}", e);
+            }
+        });
+    }
+
+    // Ввод в адресной строке и закладки: «example.com» и пути к файлам дополняются до URL
+    pub fn navigate_to_input(&mut self, input: String) {
+        let url = self.engine.io().resolve_input(&input);
+        self.navigate_to_url(url);
+    }
+
+    // Переход по ссылке страницы: адрес уже абсолютный и дополнению не подлежит
+    pub fn follow_link(&mut self, link: String) {
+        let target = match Url::parse(&link) {
+            Ok(target) => target,
+            Err(e) => {
+                self.status_message = format!("Некорректная ссылка {}: {}", link, e);
+                return;
+            }
+        };
+        if let Err(reason) = self.check_link_navigation(&target) {
+            log::warn!("Переход {} -> {} заблокирован: {}", self.url, target, reason);
+            self.status_message = reason;
+            return;
+        }
+        self.navigate_to_url(target.to_string());
+    }
+
+    // Страница из сети не может открыть локальный файл, внутреннюю страницу или подменить себя документом data:
+    fn check_link_navigation(&self, target: &Url) -> std::result::Result<(), String> {
+        let from_local = Url::parse(&self.url).is_ok_and(|page| matches!(page.scheme(), "file" | "about"));
+        match target.scheme() {
+            "file" if !from_local => Err("Страница из сети не может открывать локальные файлы".to_string()),
+            "data" if !from_local => Err("Страница из сети не может открывать data: URL во вкладке".to_string()),
+            "about" if !from_local && target.as_str() != "about:blank" => {
+                Err("Страница из сети не может открывать внутренние страницы about:".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn navigate_to_url(&mut self, url: String) {
+        log::info!("Навигация к URL: {}", url);
+        self.url = url.clone();
+        self.url_input = url.clone();
+        self.load_url(url);
+    }
+
+    fn render_toolbar_panel(&mut self, ctx: &EguiContext, ui: &mut egui::Ui) {
+        toolbar::render_toolbar(
+            ui,
+            &mut self.url_input,
+            &mut self.url,
+            &self.bookmarks,
+            &mut self.show_devtools,
+            &mut self.show_settings,
+            &mut self.status_message,
+            &mut self.do_refresh,
+            &mut self.do_add_bookmark,
+            &mut self.new_url,
+            &mut self.window_state.toolbar_position,
+            &mut self.show_toolbar,
+            &self.engine.plugins().toolbar_buttons(),
+            &mut self.plugin_clicked,
+        );
+        if ctx.has_requested_repaint() {
+            log::info!("Панель инструментов запросила перерисовку");
+        }
+    }
+
+    pub async fn render(&mut self, event_loop: &ActiveEventLoop) -> Result<()> {
+        let html_snapshot = self.html.clone();
+        let start_time = Instant::now();
+        log::info!("Начало рендеринга окна, HTML длина: {}", html_snapshot.len());
+
+        // ОБНОВЛЕНИЕ: Обрабатываем события движка перед рендером
+        self.devtools_state.poll_events();
+
+        if self.save_config {
+            log::info!("Сохранение конфигурации");
+            if let Err(e) = self.config.save_edits(&self.saved_config, Config::get_config_path()) {
+                self.status_message = format!("Ошибка сохранения настроек: {}", e);
+                log::error!("Ошибка сохранения конфигурации: {}", e);
+            } else {
+                self.saved_config = self.config.clone();
+                self.status_message = "Настройки сохранены".to_string();
+                let plugins = self.engine.plugins();
+                for plugin in plugins.plugins() {
+                    let enabled = !self.config.plugins.disabled.contains(&plugin.manifest.name);
+                    if let Err(e) = plugins.set_enabled(&plugin.manifest.name, enabled) {
+                        log::warn!("{}", e);
+                    }
+                }
+                if self.url.is_empty() || self.url == "about:blank" {
+                    self.url = self.config.settings.default_url.clone();
+                    self.url_input = self.config.settings.default_url.clone();
+                    log::info!("Установка начального URL: {}", self.url);
+                    self.load_url(self.url.clone());
+                }
+                log::info!("Конфигурация сохранена, запрос перерисовки");
+            }
+            self.save_config = false;
+        }
+
+        if self.do_refresh {
+            log::info!("Обновление страницы: {}", self.url);
+            self.load_url(self.url.clone());
+            self.do_refresh = false;
+        }
+
+        if self.do_add_bookmark {
+            log::info!("Добавление закладки: {}", self.url);
+            if !self.bookmarks.contains(&self.url) {
+                self.bookmarks.push_back(self.url.clone());
+                self.engine.send_event(EngineEvent::BookmarkAdded(self.url.clone()));
+                self.status_message = format!("Добавлена закладка: {}", self.url);
+            }
+            self.do_add_bookmark = false;
+        }
+
+        if let Some(new_url) = self.new_url.take() {
+            log::info!("Навигация к новому URL: {}", new_url);
+            self.navigate_to_input(new_url);
+        }
+
+        if let Some(clicked) = self.plugin_clicked.take() {
+            self.handle_plugin_click(clicked);
         }
-    });
-    action
-}
 
-// === FILE: ui\toolbar.rs ===
-use egui::{Ui, TextEdit, Button, CornerRadius, Frame, Vec2, Margin, Rect, Layout, Align};
-use std::collections::VecDeque;
-use log;
-pub fn render_toolbar(
-    ui: &mut Ui,
-    url_input: &mut String,
-    url: &mut String,
-    bookmarks: &VecDeque<String>,
-    show_devtools: &mut bool,
-    show_settings: &mut bool,
-    status_message: &mut String,
-    do_refresh: &mut bool,
-    do_add_bookmark: &mut bool,
-    new_url: &mut Option<String>,
-    toolbar_position: &mut super::ToolbarPosition,
-    show_toolbar: &mut bool,
-) {
-    let padding: f32 = 5.0;
-    let height: f32 = 36.0;
-    Frame::NONE
-        .outer_margin(Margin::same(padding as i8))
-        .show(ui, |ui| {
-            let mut style = (*ui.ctx().style()).clone();
-            let rounding = CornerRadius::same(12);
-            style.visuals.widgets.inactive.corner_radius = rounding;
-            style.visuals.widgets.hovered.corner_radius = rounding;
-            style.visuals.widgets.active.corner_radius = rounding;
-            ui.set_style(style);
-            ui.spacing_mut().button_padding = Vec2::splat(padding);
-            let available = ui.available_rect_before_wrap();
-            let total_width = available.width();
-            let col_width = total_width / 3.0;
-            let collapse_threshold = 150.0;
-            let menu_button_width = 24.0;
-            log::debug!("Total width: {}, Col width: {}", total_width, col_width);
-            let btn_w = 24.0;
-            let inner_padding = padding * 2.0;
-            let is_col1_collapsed = col_width < collapse_threshold;
-            let is_col3_collapsed = col_width < collapse_threshold;
-            let col2_width = if is_col1_collapsed || is_col3_collapsed {
-                let col1_width = if is_col1_collapsed { menu_button_width } else { col_width };
-                let col3_width = if is_col3_collapsed { menu_button_width } else { col_width };
-                total_width - col1_width - col3_width
-            } else {
-                col_width
-            };
-            let rect1 = Rect::from_min_size(available.min, Vec2::new(if is_col1_collapsed { menu_button_width } else { col_width }, height));
-            let rect2 = Rect::from_min_size(rect1.right_top(), Vec2::new(col2_width, height));
-            let rect3 = Rect::from_min_size(rect2.right_top(), Vec2::new(if is_col3_collapsed { menu_button_width } else { col_width }, height));
-            ui.allocate_ui_at_rect(rect1, |ui| {
-                ui.with_layout(Layout::left_to_right(Align::Center), |ui| {
-                    if is_col1_collapsed {
-                        ui.menu_button("☰", |ui| {
-                            let spacing = ui.spacing_mut();
-                            spacing.button_padding = Vec2::splat(padding);
-                            Frame::NONE.outer_margin(Margin::same(5)).show(ui, |ui| {
-                                if ui.add_sized([120.0, 24.0], Button::new("<")).clicked() {
-                                    *status_message = "Назад нажато".to_owned();
-                                    ui.close();
-                                }
-                                if ui.add_sized([120.0, 24.0], Button::new(">")).clicked() {
-                                    *status_message = "Вперёд нажато".to_owned();
-                                    ui.close();
-                                }
-                                if ui.add_sized([120.0, 24.0], Button::new("🔄")).clicked() {
-                                    *do_refresh = true;
-                                    ui.close();
-                                }
-                            });
-                        });
-                    } else {
-						if ui.add_sized([24.0, 24.0], Button::new("🗔")).clicked() {
-                            *toolbar_position = super::ToolbarPosition::Detached;
-                            *show_toolbar = false;
-                            *status_message = "Панель инструментов откреплена".to_string();
-                        }
-                        if ui.add_sized([24.0, 24.0], Button::new("<")).clicked() {
-                            *status_message = "Назад нажато".to_owned();
-                        }
-                        if ui.add_sized([24.0, 24.0], Button::new(">")).clicked() {
-                            *status_message = "Вперёд нажато".to_owned();
-                        }
-                        if ui.add_sized([24.0, 24.0], Button::new("🔄")).clicked() {
-                            *do_refresh = true;
+        // ОБНОВЛЕНИЕ: Обработка событий движка из DevToolsState
+        // События уже обработаны в poll_events(), данные обновлены в devtools_state
+        // Копируем актуальные данные в UI состояние
+        if let Some(latest_log) = self.devtools_state.network_logs.last() {
+            if latest_log.url == self.url && self.html != latest_log.html {
+                self.html = latest_log.html.clone();
+                self.headers = latest_log.headers.clone();
+                let policy = Arc::new(PagePolicy::from_headers(&self.url, &self.headers));
+                self.html_renderer.set_page_policy(policy.clone());
+                self.reset_page_realm(policy);
+                self.status_message = format!("Загружено: {}", self.url);
+                log::info!("HTML обновлен из сетевого лога для URL: {}", self.url);
+            }
+        }
+
+        for violation in self.html_renderer.take_policy_violations() {
+            self.engine.report_violation(violation);
+        }
+
+        let dom_events = self.html_renderer.take_dom_events();
+        let mut followed_link = self.pending_link.lock().take();
+        if !dom_events.is_empty() && self.scripting_enabled() {
+            let engine = self.engine.clone();
+            let page = self.devtools_state.page_id;
+            let pending_link = self.pending_link.clone();
+            let ctx = self.egui_ctx.clone();
+            // События одной страницы доставляются по порядку
+            tokio::spawn(async move {
+                for (event, link) in dom_events {
+                    let follow = match engine.clone().dispatch_dom_event(page, event).await {
+                        Ok(follow) => follow,
+                        Err(e) => {
+                            log::warn!("Ошибка доставки DOM-события: {}", e);
+                            true
                         }
+                    };
+                    if let Some(link) = link.filter(|_| follow) {
+                        *pending_link.lock() = Some(link);
+                        ctx.request_repaint();
                     }
-                });
-            });
-            ui.allocate_ui_at_rect(rect2, |ui| {
-                ui.with_layout(Layout::left_to_right(Align::Center), |ui| {
-                    let edit_w = col2_width - btn_w - inner_padding * 2.0;
-                    let text_edit = ui.add_sized(
-                        [edit_w.max(100.0), height - inner_padding],
-                        TextEdit::singleline(url_input)
-                            .margin(Margin::same(padding as i8))
-                            .hint_text("Куда собрался?"),
-                    );
-                    if text_edit.lost_focus() && ui.ctx().input(|i| i.key_pressed(egui::Key::Enter)) {
-                        *url = url_input.clone();
-                        *new_url = Some(url.clone());
-                    }
-                    if ui.add_sized([btn_w, 24.0], Button::new("▶")).clicked() {
-                        *url = url_input.clone();
-                        *new_url = Some(url.clone());
-                    }
-                });
+                }
             });
-            ui.allocate_ui_at_rect(rect3, |ui| {
-                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
-                    let gap = 6.0;
-                    if is_col3_collapsed {
-                        ui.menu_button("⋮", |ui| {
-                            let spacing = ui.spacing_mut();
-                            spacing.button_padding = Vec2::splat(padding);
-                            Frame::NONE.outer_margin(Margin::same(0)).show(ui, |ui| {
-                                if ui.add_sized([120.0, 24.0], Button::new("Открепить")).clicked() {
-                                    *toolbar_position = super::ToolbarPosition::Detached;
-                                    *show_toolbar = false;
-                                    *status_message = "Панель инструментов откреплена".to_string();
-                                    ui.close();
-                                }
-                                if ui.add_sized([120.0, 24.0], Button::new("Настройки")).clicked() {
-                                    *show_settings = !*show_settings;
-                                    *status_message = if *show_settings {
-                                        "Настройки открыты".to_owned()
-                                    } else {
-                                        "Настройки закрыты".to_owned()
-                                    };
-                                    ui.close();
-                                }
-                                if ui.add_sized([120.0, 24.0], Button::new("Инструменты разработчика")).clicked() {
-                                    *show_devtools = !*show_devtools;
-                                    *status_message = if *show_devtools {
-                                        "Инструменты разработчика открыты".to_owned()
-                                    } else {
-                                        "Инструменты разработчика закрыты".to_owned()
-                                    };
-                                    ui.close();
-                                }
-                                ui.separator();
-                                if ui.add_sized([120.0, 24.0], Button::new("➕ Добавить закладку")).clicked() {
-                                    *do_add_bookmark = true;
-                                    ui.close();
-                                }
-                                ui.separator();
-                                for b in bookmarks {
-                                    if ui.add_sized([120.0, 24.0], Button::new(b)).clicked() {
-                                        *url_input = b.clone();
-                                        *new_url = Some(b.clone());
-                                        ui.close();
-                                    }
-                                }
-                            });
+        } else if let Some(link) = dom_events.into_iter().filter_map(|(_, link)| link).last() {
+            followed_link = Some(link);
+        }
+
+        let permission_prompt = self.engine.permissions().pending_prompt();
+        let mut permission_answer: Option<(u64, bool)> = None;
+
+        let clicked_link = self.html_renderer.get_last_link_click().or(followed_link);
+        let clicked_button = self.html_renderer.get_last_button_click();
+        let raw_input = self.egui_state.take_egui_input(&self.window);
+        let mut reset = false;
+        let mut scale_delta: f32 = 0.0;
+        let egui_ctx_clone = self.egui_ctx.clone();
+        let full_output = egui_ctx_clone.run(raw_input, |ctx| {
+            if !ctx.memory(|mem| mem.data.get_temp(egui::Id::new("style_configured")).unwrap_or(false)) {
+                let mut style = (*ctx.style()).clone();
+                let visuals = &mut style.visuals;
+                visuals.widgets.inactive.bg_fill = Color32::from_rgb(40, 40, 60);
+                visuals.widgets.hovered.bg_fill = Color32::from_rgb(60, 60, 90);
+                visuals.widgets.active.bg_fill = Color32::from_rgb(80, 80, 120);
+                visuals.widgets.inactive.fg_stroke.color = Color32::from_rgb(220, 220, 255);
+                visuals.widgets.hovered.fg_stroke.color = Color32::from_rgb(255, 255, 255);
+                visuals.panel_fill = Color32::from_rgb(0, 54, 0);
+                visuals.selection.bg_fill = Color32::from_rgb(70, 130, 180);
+                visuals.extreme_bg_color = Color32::from_rgb(0, 80, 0);
+                visuals.widgets.noninteractive.bg_stroke = egui::Stroke::NONE;
+                let rounding = CornerRadius::same(12);
+                visuals.widgets.inactive.corner_radius = rounding;
+                visuals.widgets.hovered.corner_radius = rounding;
+                visuals.widgets.active.corner_radius = rounding;
+                visuals.widgets.open.corner_radius = rounding;
+                visuals.widgets.noninteractive.corner_radius = rounding;
+                ctx.set_style(style);
+                ctx.memory_mut(|mem| mem.data.insert_temp(egui::Id::new("style_configured"), true));
+                log::info!("Стили egui инициализированы");
+            }
+
+            if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Num0)) {
+                log::info!("Обнаружено нажатие Ctrl+0, сброс масштаба");
+                reset = true;
+            }
+            if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Plus)) {
+                log::info!("Обнаружено нажатие Ctrl++, увеличение масштаба");
+                scale_delta = 0.1;
+            }
+            if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Minus)) {
+                log::info!("Обнаружено нажатие Ctrl+-, уменьшение масштаба");
+                scale_delta = -0.1;
+            }
+            if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Equals)) {
+                log::info!("Обнаружено нажатие Ctrl+=, увеличение масштаба");
+                scale_delta = 0.1;
+            }
+
+            if self.show_toolbar && !self.window_state.detached_windows.contains_key(&PanelType::Toolbar) {
+                match self.window_state.toolbar_position {
+                    ToolbarPosition::Top => {
+                        TopBottomPanel::top("nav_panel").show(ctx, |ui| {
+                            self.render_toolbar_panel(ctx, ui);
                         });
-                    } else {
-                        if ui.add_sized([24.0, 24.0], Button::new("⚙")).clicked() {
-                            *show_settings = !*show_settings;
-                            *status_message = if *show_settings {
-                                "Настройки открыты".to_owned()
-                            } else {
-                                "Настройки закрыты".to_owned()
-                            };
+                    }
+                    ToolbarPosition::Bottom => {
+                        TopBottomPanel::bottom("nav_panel").show(ctx, |ui| {
+                            self.render_toolbar_panel(ctx, ui);
+                        });
+                    }
+                    ToolbarPosition::Floating => {
+                        egui::Window::new("Панель инструментов")
+                            .default_pos(self.window_state.toolbar_floating_pos)
+                            .default_size((300.0, 60.0))
+                            .resizable(true)
+                            .frame(
+                                Frame::window(&ctx.style())
+                                    .corner_radius(12.0)
+                                    .inner_margin(egui::Margin::same(6))
+                            )
+                            .show(ctx, |ui| {
+                                self.render_toolbar_panel(ctx, ui);
+                            });
+                    }
+                    ToolbarPosition::Detached => {
+                        if !self.window_state.detached_windows.contains_key(&PanelType::Toolbar) {
+                            log::info!("Создание откреплённого окна для панели инструментов");
+                            if let Err(e) = self.window_manager.create_detached_window(
+                                event_loop,
+                                PanelType::Toolbar,
+                                (50.0, 50.0),
+                                (400.0, 60.0),
+                                &self.config,
+                            ) {
+                                self.status_message = format!("Ошибка создания окна toolbar: {}", e);
+                                log::error!("Ошибка создания окна toolbar: {}", e);
+                            }
                         }
-                        
-                        ui.add_space(gap);
-                        if ui.add_sized([24.0, 24.0], Button::new("💻")).clicked() {
-                            *show_devtools = !*show_devtools;
-                            *status_message = if *show_devtools {
-                                "Инструменты разработчика открыты".to_owned()
-                            } else {
-                                "Инструменты разработчика закрыты".to_owned()
-                            };
+                    }
+                    ToolbarPosition::Hidden => {}
+                }
+            }
+
+            if self.show_devtools && !self.window_state.detached_windows.contains_key(&PanelType::DevTools) {
+                let width = *self.window_state.panel_sizes.get(&PanelType::DevTools).unwrap_or(&400.0);
+                let mut new_width = width;
+                SidePanel::right("devtools_panel")
+                    .resizable(true)
+                    .default_width(width)
+                    .min_width(200.0)
+                    .show(ctx, |ui| {
+                        let action = devtools::render_devtools_ui(
+                            ui,
+                            &mut self.devtools_tab,
+                            &mut self.html,
+                            &self.headers,
+                            &self.debug_info,
+                            &self.devtools_state.network_logs,
+                            &self.tx_ssh,
+                            false,
+                            &self.devtools_state,
+                        );
+                        new_width = ui.available_width();
+                        if let Some(PanelAction::Detach) = action {
+                            log::info!("Запрос на открепление DevTools");
+                            if let Err(e) = self.window_manager.create_detached_window(
+                                event_loop,
+                                PanelType::DevTools,
+                                (ctx.viewport_rect().right() - new_width - 20.0, 20.0),
+                                (new_width, ctx.viewport_rect().height() - 40.0),
+                                &self.config,
+                            ) {
+                                self.status_message = format!("Ошибка создания окна DevTools: {}", e);
+                                log::error!("Ошибка создания окна DevTools: {}", e);
+                            }
                         }
-                        ui.add_space(gap);
-                        ui.menu_button("☆", |ui| {
-                            let spacing = ui.spacing_mut();
-                            spacing.button_padding = Vec2::splat(padding);
-                            Frame::NONE.outer_margin(Margin::same(0)).show(ui, |ui| {
-                                if ui.add_sized([120.0, 24.0], Button::new("➕ Добавить")).clicked() {
-                                    *do_add_bookmark = true;
-                                    ui.close();
-                                }
-                                ui.separator();
-                                for b in bookmarks {
-                                    if ui.add_sized([120.0, 24.0], Button::new(b)).clicked() {
-                                        *url_input = b.clone();
-                                        *new_url = Some(b.clone());
-                                        ui.close();
+                    });
+                self.window_state.panel_sizes.insert(PanelType::DevTools, new_width);
+                if ctx.has_requested_repaint() {
+                    log::info!("DevTools запросил перерисовку");
+                }
+            }
+
+            if let Some((_, spec)) = self.plugin_panel.clone() {
+                let (mut close, mut refresh, mut navigate) = (false, false, None);
+                SidePanel::left("plugin_panel")
+                    .resizable(true)
+                    .default_width(280.0)
+                    .min_width(160.0)
+                    .show(ctx, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.heading(&spec.title);
+                            refresh = ui.small_button("🔄").clicked();
+                            close = ui.small_button("✕").clicked();
+                        });
+                        ui.separator();
+                        ScrollArea::vertical().show(ui, |ui| match &*self.plugin_panel_content.lock() {
+                            Ok(content) => {
+                                for item in &content.items {
+                                    match item {
+                                        PanelItem::Heading { text } => {
+                                            ui.strong(text);
+                                        }
+                                        PanelItem::Text { text } => {
+                                            ui.label(text);
+                                        }
+                                        PanelItem::Link { label, url } => {
+                                            if ui.link(label).on_hover_text(url).clicked() {
+                                                navigate = Some(url.clone());
+                                            }
+                                        }
                                     }
                                 }
-                            });
+                            }
+                            Err(e) => {
+                                ui.colored_label(Color32::from_rgb(255, 110, 110), e);
+                            }
+                        });
+                    });
+                if close {
+                    self.plugin_panel = None;
+                } else if refresh {
+                    self.refresh_plugin_panel();
+                }
+                if let Some(url) = navigate {
+                    self.url_input = url.clone();
+                    self.new_url = Some(url);
+                }
+            }
+
+            if self.show_settings && !self.window_state.detached_windows.contains_key(&PanelType::Settings) {
+                let width = *self.window_state.panel_sizes.get(&PanelType::Settings).unwrap_or(&400.0);
+                let mut new_width = width;
+                SidePanel::right("settings_panel")
+                    .resizable(true)
+                    .default_width(width)
+                    .min_width(200.0)
+                    .show(ctx, |ui| {
+                        let action = settings::render_settings_ui(
+                            ui,
+                            &mut self.config,
+                            &mut self.save_config,
+                            false,
+                            &self.engine.plugins().plugins(),
+                        );
+                        new_width = ui.available_width();
+                        if let Some(PanelAction::Attach) = action {
+                            log::info!("Запрос на открепление Settings");
+                            if let Err(e) = self.window_manager.create_detached_window(
+                                event_loop,
+                                PanelType::Settings,
+                                (ctx.viewport_rect().right() - new_width - 20.0, 20.0),
+                                (new_width, ctx.viewport_rect().height() - 40.0),
+                                &self.config,
+                            ) {
+                                self.status_message = format!("Ошибка создания окна Settings: {}", e);
+                                log::error!("Ошибка создания окна Settings: {}", e);
+                            }
+                        }
+                    });
+                self.window_state.panel_sizes.insert(PanelType::Settings, new_width);
+                if ctx.has_requested_repaint() {
+                    log::info!("Settings запросил перерисовку");
+                }
+            }
+
+            CentralPanel::default()
+                .show(ctx, |ui| {
+                    ScrollArea::both().auto_shrink([false; 2])
+                        .show(ui, |ui| {
+                            self.html_renderer.process_image_responses(ui);
+                            if let Err(e) = pollster::block_on(self.html_renderer.render_html_ui(ui, &html_snapshot)) {
+                                ui.label(format!("Ошибка рендеринга: {}", e));
+                                ui.monospace(&html_snapshot);
+                                log::error!("Ошибка рендеринга HTML: {}", e);
+                            }
                         });
-                    }
                 });
-            });
+            if ctx.has_requested_repaint() {
+                log::info!("CentralPanel запросил перерисовку");
+            }
+
+            if let Some(prompt) = &permission_prompt {
+                egui::Window::new("Запрос разрешения")
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+                    .show(ctx, |ui| {
+                        ui.label(format!("Сайт {} запрашивает {}", prompt.origin, prompt.permission.description()));
+                        ui.horizontal(|ui| {
+                            if ui.button("Разрешить").clicked() {
+                                permission_answer = Some((prompt.id, true));
+                            }
+                            if ui.button("Запретить").clicked() {
+                                permission_answer = Some((prompt.id, false));
+                            }
+                        });
+                    });
+            }
+
+            TopBottomPanel::bottom("status_bar")
+                .show(ctx, |ui| {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label(format!("Статус: {}", self.status_message));
+                        ui.label(format!("Масштаб: {:.1}x (Ctrl+0 для сброса)", self.html_scale_factor));
+                    });
+                });
+            if ctx.has_requested_repaint() {
+                log::info!("StatusBar запросил перерисовку");
+            }
         });
+
+        let should_repaint = self.egui_ctx.has_requested_repaint();
+        if should_repaint {
+            log::info!("Egui контекст запросил перерисовку");
+        }
+
+        if reset {
+            log::info!("Сброс масштаба");
+            self.reset_scale_factor();
+        } else if scale_delta.abs() > 0.0 {
+            log::info!("Изменение масштаба на: {}", scale_delta);
+            self.change_scale_factor(scale_delta);
+        }
+
+        let platform_output = full_output.platform_output.clone();
+        self.egui_state.handle_platform_output(&self.window, platform_output);
+
+        if let Some((prompt_id, granted)) = permission_answer {
+            let permissions = self.engine.permissions();
+            tokio::spawn(async move {
+                if let Err(e) = permissions.resolve(prompt_id, granted).await {
+                    log::error!("Ошибка сохранения решения о разрешении: {}", e);
+                }
+            });
+        }
+
+        if let Some(link) = clicked_link {
+            log::info!("Навигация по клику на ссылке: {}", link);
+            self.follow_link(link);
+        }
+        if let Some(button) = clicked_button {
+            log::info!("Обработка клика по кнопке: {}", button);
+            self.status_message = format!("Нажата кнопка: {}", button);
+        }
+
+        let size = self.window.inner_size();
+        let minimized = self.window.is_minimized().unwrap_or(false);
+        let mut frame_rendered = false;
+        if !minimized && size.width > 0 && size.height > 0 && should_repaint {
+            log::info!("Запуск рендера кадра, размер окна: {}x{}", size.width, size.height);
+            frame_rendered = wgpu_render::render_frame(
+                &self.device,
+                &self.queue,
+                &self.surface,
+                &self.surface_config,
+                &mut self.renderer,
+                &self.egui_ctx,
+                &full_output,
+                &mut self.render_manager,
+            )?;
+        } else {
+            log::info!("Пропуск рендера кадра: минимизировано={} или размер=0", minimized);
+            self.render_manager.total_frames_skipped += 1;
+        }
+
+        if frame_rendered {
+            let is_active = self.render_manager.is_active;
+            log::info!("Кадр отрендерен, активность: {}", is_active);
+            self.debug_info.update(
+                &self.html,
+                start_time.elapsed(),
+                self.debug_info.server_response_time,
+            );
+        }
+
+        let panel_types = self.window_manager.get_detached_window_types();
+        let plugin_buttons = self.engine.plugins().toolbar_buttons();
+        let plugin_infos = self.engine.plugins().plugins();
+        for panel_type in panel_types {
+            let window_data = crate::ui::window_manager::DetachedWindowData {
+                devtools_tab: &mut self.devtools_tab,
+                html: &mut self.html,
+                headers: &self.headers,
+                debug_info: &self.debug_info,
+                network_logs: &self.devtools_state.network_logs,
+                tx_ssh: &self.tx_ssh,
+                devtools_state: &self.devtools_state,
+                url_input: &mut self.url_input,
+                url: &mut self.url,
+                bookmarks: &self.bookmarks,
+                show_devtools: &mut self.show_devtools,
+                show_settings: &mut self.show_settings,
+                status_message: &mut self.status_message,
+                do_refresh: &mut self.do_refresh,
+                do_add_bookmark: &mut self.do_add_bookmark,
+                new_url: &mut self.new_url,
+                toolbar_position: &mut self.window_state.toolbar_position,
+                show_toolbar: &mut self.show_toolbar,
+                plugin_buttons: &plugin_buttons,
+                plugin_clicked: &mut self.plugin_clicked,
+                plugins: &plugin_infos,
+                config: &mut self.config,
+                save_config: &mut self.save_config,
+                ai_chat_history: &self.ai_chat_history,
+                send_ai_message: &mut self.send_ai_message,
+                render_manager: &mut self.render_manager,
+            };
+            self.window_manager.render_detached_window(&panel_type, window_data)?;
+        }
+
+        self.window_manager.cleanup_closed_windows();
+        self.html_renderer.clear_repaint_flag();
+
+        Ok(())
+    }
+
+    pub fn set_focused(&mut self, focused: bool) {
+        log::info!("Установка фокуса окна: {}", focused);
+        self.render_manager.set_window_active(focused);
+    }
 }
 
-// === FILE: ui\wgpu_render.rs ===
-use wgpu::*;
-use egui_wgpu::{Renderer, ScreenDescriptor};
-use anyhow::Result;
-use egui::{Context};
-use std::time::{Instant, Duration};
+// === FILE: ui\window_manager.rs ===
+use crate::core::config::Config;
+use crate::ui::devtools::{render_devtools_ui, PanelAction, DevToolsState};
+use crate::ui::{toolbar, aichat, settings, wgpu_render};
+use anyhow::{Result, Context};
+use egui::{Context as EguiContext, Pos2, FontId, FontFamily, TextStyle, Color32, CornerRadius};
+use egui_winit::State as EguiState;
+use winit::window::Window as WinitWindow;
+use winit::event_loop::ActiveEventLoop;
+use winit::event::WindowEvent;
+use wgpu::{Instance, Surface, Adapter, Device, Queue, SurfaceConfiguration};
+use egui_wgpu::{Renderer, RendererOptions};
+use std::sync::Arc;
+use std::collections::HashMap;
+use tokio::sync::mpsc::UnboundedSender;
 
-#[derive(Debug, Clone, Copy)]
-pub enum GraphicsBackend {
-    Auto,
-    Vulkan,
-    DirectX12,
-    Metal,
-    OpenGL,
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PanelType {
+    DevTools,
+    AIChat,
+    Settings,
+    Toolbar,
 }
 
-impl GraphicsBackend {
-    pub fn from_str(s: &str) -> Self {
-        match s.to_lowercase().as_str() {
-            "vulkan" => Self::Vulkan,
-            "dx12" | "directx12" | "directx" => Self::DirectX12,
-            "metal" => Self::Metal,
-            "gl" | "opengl" => Self::OpenGL,
-            _ => Self::Auto,
+pub struct DetachedWindow {
+    pub panel_type: PanelType,
+    pub window: Arc<WinitWindow>,
+    pub surface: Surface<'static>,
+    pub renderer: Renderer,
+    pub egui_state: EguiState,
+    pub egui_ctx: EguiContext,
+    pub surface_config: SurfaceConfiguration,
+    pub position: (f32, f32),
+    pub size: (f32, f32),
+    pub visible: bool,
+    pub should_close: bool,
+}
+
+pub struct WindowState {
+    pub fullscreen: bool,
+    pub toolbar_position: super::ToolbarPosition,
+    pub toolbar_floating_pos: Pos2,
+    pub detached_windows: HashMap<PanelType, DetachedWindow>,
+    pub panel_sizes: HashMap<PanelType, f32>,
+}
+
+impl Default for WindowState {
+    fn default() -> Self {
+        let mut panel_sizes = HashMap::new();
+        panel_sizes.insert(PanelType::DevTools, 400.0);
+        panel_sizes.insert(PanelType::AIChat, 350.0);
+        panel_sizes.insert(PanelType::Settings, 400.0);
+        panel_sizes.insert(PanelType::Toolbar, 400.0);
+        Self {
+            fullscreen: true,
+            toolbar_position: super::ToolbarPosition::Top,
+            toolbar_floating_pos: Pos2::new(50.0, 50.0),
+            detached_windows: HashMap::new(),
+            panel_sizes,
         }
     }
+}
 
-    pub fn to_wgpu_backends(&self) -> Backends {
-        match self {
-            Self::Auto => Backends::PRIMARY,
-            Self::Vulkan => Backends::VULKAN,
-            Self::DirectX12 => Backends::DX12,
-            Self::Metal => Backends::METAL,
-            Self::OpenGL => Backends::GL,
+// === ДАННЫЕ ДЛЯ ОТКРЕПЛЁННЫХ ОКОН — ТОЛЬКО ЧЕРЕЗ API ===
+pub struct DetachedWindowData<'a> {
+    pub devtools_tab: &'a mut crate::ui::devtools::DevToolsTab,
+    pub html: &'a mut String,
+    pub headers: &'a reqwest::header::HeaderMap,
+    pub debug_info: &'a crate::ui::devtools::DebugInfo,
+    pub network_logs: &'a Vec<crate::ui::devtools::RequestLog>,
+    pub tx_ssh: &'a UnboundedSender<String>,
+    pub devtools_state: &'a DevToolsState,  // ← Единственный источник данных от движка
+    pub url_input: &'a mut String,
+    pub url: &'a mut String,
+    pub bookmarks: &'a std::collections::VecDeque<String>,
+    pub show_devtools: &'a mut bool,
+    pub show_settings: &'a mut bool,
+    pub status_message: &'a mut String,
+    pub do_refresh: &'a mut bool,
+    pub do_add_bookmark: &'a mut bool,
+    pub new_url: &'a mut Option<String>,
+    pub toolbar_position: &'a mut super::ToolbarPosition,
+    pub show_toolbar: &'a mut bool,
+    pub plugin_buttons: &'a [crate::core::plugins::PluginButton],
+    pub plugin_clicked: &'a mut Option<crate::core::plugins::PluginButton>,
+    pub plugins: &'a [crate::core::plugins::PluginInfo],
+    pub config: &'a mut Config,
+    pub save_config: &'a mut bool,
+    pub ai_chat_history: &'a Vec<(String, String)>,
+    pub send_ai_message: &'a mut bool,
+    pub render_manager: &'a mut crate::ui::wgpu_render::RenderManager,
+}
+
+pub struct WindowManager {
+    pub instance: Instance,
+    pub adapter: Adapter,
+    pub device: Device,
+    pub queue: Queue,
+    pub window_state: WindowState,
+}
+
+impl WindowManager {
+    pub fn new(instance: Instance, adapter: Adapter, device: Device, queue: Queue) -> Self {
+        Self {
+            instance,
+            adapter,
+            device,
+            queue,
+            window_state: WindowState::default(),
         }
     }
 
-    pub fn to_string(&self) -> &'static str {
-        match self {
-            Self::Auto => "auto",
-            Self::Vulkan => "vulkan",
-            Self::DirectX12 => "dx12",
-            Self::Metal => "metal",
-            Self::OpenGL => "opengl",
-        }
+    fn configure_egui_style(ctx: &EguiContext) {
+        let mut style = (*ctx.style()).clone();
+        style.text_styles = [
+            (TextStyle::Heading, FontId::new(15.0, FontFamily::Proportional)),
+            (TextStyle::Body, FontId::new(12.0, FontFamily::Proportional)),
+            (TextStyle::Monospace, FontId::new(12.0, FontFamily::Monospace)),
+            (TextStyle::Button, FontId::new(12.0, FontFamily::Proportional)),
+            (TextStyle::Small, FontId::new(10.0, FontFamily::Proportional)),
+        ].into();
+        
+        let visuals = &mut style.visuals;
+        visuals.widgets.inactive.bg_fill = Color32::from_rgb(40, 40, 60);
+        visuals.widgets.hovered.bg_fill  = Color32::from_rgb(60, 60, 90);
+        visuals.widgets.active.bg_fill   = Color32::from_rgb(80, 80, 120);
+        visuals.widgets.inactive.fg_stroke.color = Color32::from_rgb(220, 220, 255);
+        visuals.widgets.hovered.fg_stroke.color  = Color32::from_rgb(255, 255, 255);
+        visuals.panel_fill   = Color32::from_rgb(0, 54, 0);
+        visuals.selection.bg_fill = Color32::from_rgb(70, 130, 180);
+        visuals.extreme_bg_color = Color32::from_rgb(0, 80, 0);
+        visuals.widgets.noninteractive.bg_stroke = egui::Stroke::NONE;
+        
+        let rounding = CornerRadius::same(12);
+        visuals.widgets.inactive.corner_radius = rounding;
+        visuals.widgets.hovered.corner_radius  = rounding;
+        visuals.widgets.active.corner_radius   = rounding;
+        visuals.widgets.open.corner_radius     = rounding;
+        visuals.widgets.noninteractive.corner_radius = rounding;
+        
+        ctx.set_style(style);
     }
-}
 
-pub struct RenderManager {
-    last_render_time: Option<Instant>,
-    last_activity: Instant,
-    frame_count: u32,
-    fps_update_time: Instant,
-    pub is_active: bool,
-    pub idle_mode: bool,
-    pub total_frames_rendered: u32,
-    pub total_frames_skipped: u32,
-    pub is_minimized: bool,
-    enable_fps_throttling: bool,
-    last_size: (u32, u32),
-}
+    pub fn create_detached_window(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        panel_type: PanelType,
+        position: (f32, f32),
+        size: (f32, f32),
+        config: &Config,
+    ) -> Result<()> {
+        let title = match panel_type {
+            PanelType::DevTools => "Инструменты разработчика",
+            PanelType::AIChat => "Чат с ИИ",
+            PanelType::Settings => "Настройки",
+            PanelType::Toolbar => "Панель инструментов",
+        };
+
+        let window = Arc::new(
+            event_loop
+                .create_window(
+                    winit::window::WindowAttributes::default()
+                        .with_title(title)
+                        .with_position(winit::dpi::PhysicalPosition::new(position.0, position.1))
+                        .with_inner_size(winit::dpi::PhysicalSize::new(size.0, size.1))
+                        .with_decorations(true)
+                        .with_resizable(true),
+                )
+                .context("Не удалось создать окно")?,
+        );
+
+        let surface = self.instance
+            .create_surface(window.clone())
+            .context("Не удалось создать поверхность для окна")?;
+
+        let surface_caps = surface.get_capabilities(&self.adapter);
+        let surface_config = wgpu_render::create_optimal_surface_config(
+            &surface_caps,
+            (size.0 as u32, size.1 as u32),
+        )?;
 
-impl RenderManager {
-    pub fn new(enable_optimizations: bool) -> Self {
-        Self {
-            last_render_time: None,
-            last_activity: Instant::now(),
-            frame_count: 0,
-            fps_update_time: Instant::now(),
-            is_active: true,
-            idle_mode: false,
-            total_frames_rendered: 0,
-            total_frames_skipped: 0,
-            is_minimized: false,
-            enable_fps_throttling: enable_optimizations,
-            last_size: (0, 0),
-        }
-    }
+        surface.configure(&self.device, &surface_config);
 
-    pub fn set_window_active(&mut self, active: bool) {
-        self.is_active = active;
-        if active {
-            self.update_activity();
-        }
-    }
+        let renderer = Renderer::new(&self.device, surface_config.format, RendererOptions::default());
+        
+        let egui_ctx = EguiContext::default();
+        
+        egui_ctx.set_fonts(egui::FontDefinitions::default());
+        let scale_factor = config.settings.scale_factor.unwrap_or(1.0);
+        egui_ctx.set_pixels_per_point(scale_factor);
+        
+        Self::configure_egui_style(&egui_ctx);
 
-    pub fn set_minimized(&mut self, minimized: bool) {
-        self.is_minimized = minimized;
-        if !minimized {
-            self.update_activity();
-        }
-    }
+        let egui_state = EguiState::new(
+            egui_ctx.clone(),
+            egui::ViewportId::from_hash_of(&panel_type),
+            &window,
+            None,
+            None,
+            None,
+        );
 
-    pub fn update_activity(&mut self) {
-        self.last_activity = Instant::now();
-        if self.idle_mode {
-            log::debug!("Выход из idle режима");
-        }
-        self.idle_mode = false;
+        self.window_state.detached_windows.insert(
+            panel_type.clone(),
+            DetachedWindow {
+                panel_type,
+                window,
+                surface,
+                renderer,
+                egui_state,
+                egui_ctx,
+                surface_config,
+                position,
+                size,
+                visible: true,
+                should_close: false,
+            },
+        );
+
+        Ok(())
     }
 
-    pub fn should_render(&mut self, ctx: &Context) -> bool {
-        if self.is_minimized {
-            self.total_frames_skipped += 1;
-            return false;
-        }
+    pub fn render_detached_window(
+        &mut self,
+        panel_type: &PanelType,
+        window_data: DetachedWindowData,
+    ) -> Result<()> {
+        if let Some(detached) = self.window_state.detached_windows.get_mut(panel_type) {
+            if detached.should_close {
+                self.window_state.detached_windows.remove(panel_type);
+                return Ok(());
+            }
 
-        let now = Instant::now();
-        let time_since_activity = self.last_activity.elapsed();
-        let (max_fps, mode_name) = if time_since_activity > Duration::from_secs(2) {
-            self.idle_mode = true;
-            (30, "idle")
-        } else if !self.is_active {
-            self.idle_mode = true;
-            (1, "inactive")
-        } else {
-            self.idle_mode = false;
-            (60, "active")
-        };
+            if !detached.visible {
+                return Ok(());
+            }
 
-        if self.enable_fps_throttling {
-            if let Some(last_render) = self.last_render_time {
-                let min_frame_time = Duration::from_millis(1000 / max_fps as u64);
-                if now.duration_since(last_render) < min_frame_time {
-                    self.total_frames_skipped += 1;
-                    return false;
+            let raw_input = detached.egui_state.take_egui_input(&detached.window);
+            let panel_type_clone = panel_type.clone();
+            let mut should_attach = false;
+
+            let full_output = detached.egui_ctx.run(raw_input, |ctx| {
+                if !ctx.memory(|mem| mem.data.get_temp(egui::Id::new("style_configured")).unwrap_or(false)) {
+                    Self::configure_egui_style(ctx);
+                    ctx.memory_mut(|mem| mem.data.insert_temp(egui::Id::new("style_configured"), true));
                 }
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    match &panel_type_clone {
+                        PanelType::DevTools => {
+                            // ← ЧИТАЕМ ИЗ devtools_state (получает из движка)
+                            let action = render_devtools_ui(
+                                ui,
+                                window_data.devtools_tab,
+                                window_data.html,
+                                window_data.headers,
+                                window_data.debug_info,
+                                window_data.network_logs,
+                                window_data.tx_ssh,
+                                true,
+                                window_data.devtools_state,  // ← ВСЁ через DevToolsState
+                            );
+                            if let Some(PanelAction::Attach) = action {
+                                should_attach = true;
+                            }
+                        }
+                        PanelType::AIChat => {
+                            let action = aichat::render_aichat_ui(
+                                ui,
+                                window_data.url_input,
+                                window_data.ai_chat_history,
+                                window_data.send_ai_message,
+                                true,
+                            );
+                            if let Some(PanelAction::Attach) = action {
+                                should_attach = true;
+                            }
+                        }
+                        PanelType::Settings => {
+                            let action = settings::render_settings_ui(
+                                ui,
+                                window_data.config,
+                                window_data.save_config,
+                                true,
+                                window_data.plugins,
+                            );
+                            if let Some(PanelAction::Attach) = action {
+                                should_attach = true;
+                            }
+                        }
+                        PanelType::Toolbar => {
+                            toolbar::render_toolbar(
+                                ui,
+                                window_data.url_input,
+                                window_data.url,
+                                window_data.bookmarks,
+                                window_data.show_devtools,
+                                window_data.show_settings,
+                                window_data.status_message,
+                                window_data.do_refresh,
+                                window_data.do_add_bookmark,
+                                window_data.new_url,
+                                window_data.toolbar_position,
+                                window_data.show_toolbar,
+                                window_data.plugin_buttons,
+                                window_data.plugin_clicked,
+                            );
+                        }
+                    }
+                });
+            });
+
+            if should_attach {
+                detached.should_close = true;
+                return Ok(());
             }
-            if self.idle_mode && !ctx.has_requested_repaint() {
-                self.total_frames_skipped += 1;
-                return false;
+
+            let size = detached.window.inner_size();
+            if size.width > 0 && size.height > 0 && !detached.window.is_minimized().unwrap_or(false) {
+                wgpu_render::render_frame(
+                    &self.device,
+                    &self.queue,
+                    &detached.surface,
+                    &detached.surface_config,
+                    &mut detached.renderer,
+                    &detached.egui_ctx,
+                    &full_output,
+                    window_data.render_manager,
+                )?;
             }
-        }
 
-        self.last_render_time = Some(now);
-        self.total_frames_rendered += 1;
-        self.frame_count += 1;
+            let viewport_rect = detached.egui_ctx.viewport_rect();
+            if !viewport_rect.is_negative() {
+                detached.position = (viewport_rect.min.x, viewport_rect.min.y);
+                detached.size = (viewport_rect.width(), viewport_rect.height());
+            }
 
-        if now.duration_since(self.fps_update_time) > Duration::from_secs(2) {
-            let fps = self.frame_count as f32 / 2.0;
-            let efficiency = if self.total_frames_rendered + self.total_frames_skipped > 0 {
-                (self.total_frames_rendered as f32 / (self.total_frames_rendered + self.total_frames_skipped) as f32) * 100.0
-            } else {
-                0.0
-            };
-            log::debug!(
-                "Режим: {}, FPS: {:.1}, Эффективность: {:.1}% (пропущено: {})",
-                mode_name,
-                fps,
-                efficiency,
-                self.total_frames_skipped
-            );
-            self.frame_count = 0;
-            self.fps_update_time = now;
+            if detached.egui_ctx.has_requested_repaint() {
+                detached.window.request_redraw();
+            }
         }
-        true
+        Ok(())
     }
 
-    pub fn tessellate(
-        &mut self,
-        ctx: &Context,
-        shapes: &[egui::epaint::ClippedShape],
-        pixels_per_point: f32,
-    ) -> Vec<egui::epaint::ClippedPrimitive> {
-        let primitives = ctx.tessellate(shapes.to_vec(), pixels_per_point);
-        for prim in &primitives {
-            if let egui::epaint::ClippedPrimitive {
-                primitive: egui::epaint::Primitive::Mesh(mesh),
-                ..
-            } = prim
-            {
-                if mesh.vertices.is_empty() || mesh.indices.is_empty() {
-                    log::warn!("Обнаружен пустой меш при тесселяции");
-                }
+    pub fn handle_window_event(&mut self, panel_type: &PanelType, event: &WindowEvent) -> Result<()> {
+        if let Some(detached) = self.window_state.detached_windows.get_mut(panel_type) {
+            let resp = detached.egui_state.on_window_event(&detached.window, event);
+            if resp.repaint {
+                detached.window.request_redraw();
+            }
+            
+            if let WindowEvent::CloseRequested = event {
+                detached.should_close = true;
             }
         }
-        primitives
+        Ok(())
     }
 
-    pub fn update_screen_descriptor(&mut self, width: u32, height: u32, pixels_per_point: f32) -> ScreenDescriptor {
-        let safe_width = width.max(1);
-        let safe_height = height.max(1);
-        if self.last_size != (safe_width, safe_height) {
-            log::debug!("Обновлены размеры экрана: {}x{}", safe_width, safe_height);
-            self.last_size = (safe_width, safe_height);
-        }
-        ScreenDescriptor {
-            size_in_pixels: [safe_width, safe_height],
-            pixels_per_point,
+    pub fn resize_detached_window(&mut self, panel_type: &PanelType, new_size: winit::dpi::PhysicalSize<u32>) -> Result<()> {
+        if let Some(detached) = self.window_state.detached_windows.get_mut(panel_type) {
+            if new_size.width > 0 && new_size.height > 0 {
+                let surface_caps = detached.surface.get_capabilities(&self.adapter);
+                let config = wgpu_render::create_optimal_surface_config(&surface_caps, (new_size.width, new_size.height))?;
+                detached.surface.configure(&self.device, &config);
+                detached.surface_config = config;
+                detached.size = (new_size.width as f32, new_size.height as f32);
+            }
         }
+        Ok(())
     }
-
-    pub fn get_stats(&self) -> String {
-        let total = self.total_frames_rendered + self.total_frames_skipped;
-        let efficiency = if total > 0 {
-            (self.total_frames_rendered as f32 / total as f32) * 100.0
-        } else {
-            0.0
-        };
-        format!(
-            "Рендер: {} кадров, Пропущено: {} (Эффективность: {:.1}%)",
-            self.total_frames_rendered,
-            self.total_frames_skipped,
-            efficiency
-        )
-    }
-
-    pub fn clear_cache(&mut self) {
-        log::debug!("Очистка кэша рендера");
+    
+    pub fn close_all_detached_windows(&mut self) {
+        self.window_state.detached_windows.clear();
     }
 
-    pub fn set_all_optimizations(&mut self, enabled: bool) {
-        self.enable_fps_throttling = enabled;
+    pub fn cleanup_closed_windows(&mut self) {
+        self.window_state.detached_windows.retain(|_, detached| !detached.should_close);
     }
 
-    pub fn set_fps_throttling(&mut self, enabled: bool) {
-        self.enable_fps_throttling = enabled;
+    pub fn get_detached_window_types(&self) -> Vec<PanelType> {
+        self.window_state.detached_windows.keys().cloned().collect()
     }
 }
 
-pub fn render_frame(
-    device: &Device,
-    queue: &Queue,
-    surface: &Surface,
-    surface_config: &SurfaceConfiguration,
-    renderer: &mut Renderer,
-    ctx: &Context,
-    full_output: &egui::FullOutput,
-    render_manager: &mut RenderManager,
-) -> Result<bool> {
-    // Проверка минимальных размеров и состояния минимизации
-    if render_manager.is_minimized || surface_config.width == 0 || surface_config.height == 0 {
-        render_manager.total_frames_skipped += 1;
-        log::trace!("Пропуск рендеринга: минимизировано или нулевые размеры");
-        return Ok(false);
-    }
-
-    // Проверка необходимости рендеринга
-    if !render_manager.should_render(ctx) {
-        log::trace!("Пропуск рендеринга: троттлинг или отсутствие изменений");
-        return Ok(false);
+// === FILE: utils\config.rs ===
+use anyhow::Result;
+pub struct Config;
+impl Config {
+    pub fn new() -> Result<Self> {
+        println!("Config initialized");
+        Ok(Self)
     }
-
-    // Получение текущей текстуры поверхности
-    let output_frame = match surface.get_current_texture() {
-        Ok(frame) => frame,
-        Err(SurfaceError::Lost | SurfaceError::Outdated) => {
-            // Переконфигурируем поверхность
-            surface.configure(device, surface_config);
-            match surface.get_current_texture() {
-                Ok(frame) => frame,
-                Err(e) => {
-                    log::warn!("Повторная ошибка получения текстуры: {}", e);
-                    return Ok(false);
-                }
-            }
-        }
-        Err(SurfaceError::OutOfMemory) => {
-            return Err(anyhow::anyhow!("Недостаточно памяти для текстуры поверхности"));
-        }
-        Err(e) => {
-            log::warn!("Ошибка получения текстуры поверхности: {}", e);
-            return Ok(false);
-        }
-    };
-
-    // Создание представления текстуры
-    let view = output_frame
-        .texture
-        .create_view(&TextureViewDescriptor::default());
-
-
-
-
-// Обновление текстур egui с проверкой размеров
-for (id, image_delta) in &full_output.textures_delta.set {
-    let size = image_delta.image.size();
-    let [width, height] = size;
-    renderer.update_texture(device, queue, *id, image_delta);
 }
 
-
-
-
-
-
-
-
-
-
-
-
-    // Освобождение неиспользуемых текстур
-    for id in &full_output.textures_delta.free {
-        renderer.free_texture(id);
-    }
-
-    // Пропуск рендеринга, если нет данных для отрисовки
-    if full_output.shapes.is_empty() {
-        log::trace!("Пропуск рендеринга: нет shapes для отрисовки");
-        return Ok(true);
+// === FILE: utils\log.rs ===
+use anyhow::Result;
+use log::LevelFilter;
+pub struct Log;
+impl Log {
+    pub fn new() -> Result<Self> {
+        println!("Log initialized");
+        Ok(Self)
     }
-
-    // Тесселяция UI
-    let paint_jobs = render_manager.tessellate(ctx, &full_output.shapes, full_output.pixels_per_point);
-
-    // Обновление дескриптора экрана
-    let screen_descriptor = render_manager.update_screen_descriptor(
-        surface_config.width,
-        surface_config.height,
-        full_output.pixels_per_point,
-    );
-
- 
- 
- 
-
-
-
-// Создание командного энкодера
-let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
-    label: Some("egui_encoder"),
-});
-
-// Обновление буферов для рендеринга
-if !paint_jobs.is_empty() {
-    // В этом месте encoder заимствуется, но это временное заимствование.
-    renderer.update_buffers(device, queue, &mut encoder, &paint_jobs, &screen_descriptor);
-}
-
-// Рендеринг кадра
-if !paint_jobs.is_empty() {
-    let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-        label: Some("egui_rpass"),
-        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-            view: &view,
-            resolve_target: None,
-            ops: wgpu::Operations {
-                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                store: wgpu::StoreOp::Store,
-            },
-            depth_slice: None,
-        })],
-        depth_stencil_attachment: None,
-        occlusion_query_set: None,
-        timestamp_writes: None,
-    })
-    // ВОЗВРАЩАЕМ .forget_lifetime() ИЗ-ЗА ТРЕБОВАНИЯ 'static / E0505
-    .forget_lifetime(); 
-    
-    // Используем rpass для рендеринга
-    renderer.render(&mut rpass, &paint_jobs, &screen_descriptor);
-
-    // rpass теперь можно игнорировать, так как он не держит &mut encoder.
-} 
-
-// Отправка команд в очередь
-if !paint_jobs.is_empty() {
-    queue.submit(std::iter::once(encoder.finish()));
 }
- 
- 
- 
- 
- 
- 
-
- 
-
-    // Презентация кадра
-    output_frame.present();
-    log::trace!("Кадр успешно отрендерен: {}x{}", surface_config.width, surface_config.height);
-
-    Ok(true)
+pub fn init_logger(_level: LevelFilter) -> Result<()> {
+    println!("Logger initialized (stub)");
+    Ok(())
 }
 
-pub fn create_optimal_surface_config(
-    surface_caps: &SurfaceCapabilities,
-    size: (u32, u32),
-) -> Result<SurfaceConfiguration> {
-    let surface_format = surface_caps
-        .formats
-        .get(0)
-        .copied()
-        .ok_or_else(|| anyhow::anyhow!("Нет доступных surface formats"))?;
-    let preferred_modes = [
-		PresentMode::Mailbox, // Mailbox для лучшей производительности без tearing
-		PresentMode::Fifo, // FIFO (очередь) гарантирует отсутствие tearing, синхронизирует с вертикальной разверткой
-		PresentMode::FifoRelaxed, // Ослабленная FIFO: минимальная задержка, возможен tearing при пропуске кадра
-		PresentMode::Immediate, // Немедленная подача кадра: максимальная скорость, но возможен tearing
-
-    ];
-    let present_mode = preferred_modes
-        .iter()
-        .find(|mode| surface_caps.present_modes.contains(mode))
-        .copied()
-        .ok_or_else(|| anyhow::anyhow!("Нет доступных present modes"))?;
-    log::debug!(
-        "Выбран режим представления: {:?} (для предотвращения suboptimal present)",
-        present_mode
-    );
-    Ok(SurfaceConfiguration {
-        usage: TextureUsages::RENDER_ATTACHMENT,
-        format: surface_format,
-        width: size.0.max(1),
-        height: size.1.max(1),
-        present_mode,
-        alpha_mode: surface_caps.alpha_modes.get(0).copied().unwrap_or_default(),
-        view_formats: vec![surface_format],
-        desired_maximum_frame_latency: 2,
-    })
+// === FILE: utils\mem.rs ===
+use anyhow::Result;
+pub struct Mem;
+impl Mem {
+    pub fn new() -> Result<Self> {
+        println!("Mem initialized");
+        Ok(Self)
+    }
 }
 
-pub fn create_energy_efficient_instance() -> Instance {
-    Instance::new(&InstanceDescriptor {
-        backends: Backends::PRIMARY,
-        flags: InstanceFlags::empty(),
-        ..Default::default()
-    })
+// === FILE: utils\mod.rs ===
+pub mod log;
+pub mod config;
+pub mod timer;
+pub mod mem;
+pub mod platform;
+pub mod net_utils;
+
+// === FILE: utils\net_utils.rs ===
+use anyhow::Result;
+pub struct NetUtils;
+impl NetUtils{
+    pub fn new() -> Result<Self> {
+        println!("NetUtils initialized");
+        Ok(Self)
+    }
 }
 
-pub fn create_energy_efficient_adapter(instance: &Instance, surface: &Surface) -> Result<Adapter> {
-    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
-        power_preference: PowerPreference::LowPower,
-        force_fallback_adapter: false,
-        compatible_surface: Some(surface),
-    }))
-    .map_err(|e| anyhow::anyhow!("Ошибка при запросе адаптера: {e}"))?;
-    Ok(adapter)
+// === FILE: utils\platform.rs ===
+use anyhow::Result;
+pub struct Platform;
+impl Platform{
+    pub fn new() -> Result<Self> {
+        println!("Platform initialized");
+        Ok(Self)
+    }
 }
 
-// === FILE: ui\window.rs ===
-use crate::core::config::Config;
-use crate::core::engine::{BroEngine, EngineEvent, UrlResponse};
-use crate::core::page_state::SharedPageState;
-use crate::dom::parser::{parse_and_process, FrameworkType};
-use crate::ui::devtools::{DebugInfo, DevToolsTab, RequestLog, PanelAction, DevToolsState};
-use crate::ui::wgpu_render;
-use crate::ui::window_manager::{DetachedWindowData, PanelType, WindowManager, WindowState};
-use crate::ui::render::HtmlRenderer;
-use crate::ui::{aichat, devtools, settings, toolbar};
-use anyhow::{Result, Context};
-use egui::{Context as EguiContext, FontDefinitions, FontId, FontFamily, TextStyle, Color32, CornerRadius, Style, TopBottomPanel, Frame, SidePanel, CentralPanel, ScrollArea};
-use egui::epaint::mutex::RwLock;
-use egui::widgets::text_edit::TextEditOutput;
-use egui_extras::{Column, TableBuilder};
-use egui_winit::State as EguiState;
-use pollster::FutureExt as _;
-use std::collections::VecDeque;
+// === FILE: utils\timer.rs ===
+use anyhow::Result;
+pub struct Timer;
+impl Timer {
+    pub fn new() -> Result<Self> {
+        println!("Timer initialized");
+        Ok(Self)
+    }
+}
+
+// === FILE: wasm_api\bindings.rs ===
+use crate::core::engine::EngineEvent;
+use crate::core::interfaces::{NetworkTrait, SecurityManagerTrait, YuaidbTrait};
+use crate::core::runtime::Runtime;
+use anyhow::Result;
 use std::sync::Arc;
-use winit::dpi::PhysicalSize;
-use winit::window::Window as WinitWindow;
-use winit::event::WindowEvent;
-use winit::event_loop::ActiveEventLoop;
-use wgpu::{Adapter, Device, Instance, Queue, Surface, SurfaceConfiguration, DeviceDescriptor, Limits};
-use egui_wgpu::{Renderer, RendererOptions};
-use reqwest::header::HeaderMap;
-use std::time::{Duration, Instant};
-use tokio::sync::mpsc::{unbounded_channel, UnboundedSender, UnboundedReceiver};
+use tokio::sync::broadcast::Sender as BroadcastSender;
+#[cfg(feature = "wasm")]
+use crate::core::interfaces::{Credentials, NetRequest};
+#[cfg(feature = "wasm")]
+use crate::core::runtime::{EPOCH_DEADLINE_TICKS, FUEL_PER_CALL, MEMORY_LIMIT};
+#[cfg(feature = "wasm")]
+use crate::core::security::to_hex;
+#[cfg(feature = "wasm")]
+use crate::wasm_api::sandbox::{WasmOutput, WasmStream};
+use crate::wasm_api::signals::{OwnerId, PageDocument, Signals};
+#[cfg(feature = "wasm")]
+use crate::wasm_api::signals::GuestSignals;
+#[cfg(feature = "wasm")]
+use crate::wasm_api::web_sys::WebSys;
+#[cfg(feature = "wasm")]
+use parking_lot::Mutex;
+#[cfg(feature = "wasm")]
+use sha2::{Digest, Sha256};
+#[cfg(feature = "wasm")]
+use std::collections::HashMap;
 
+// Опубликованный мир yuaibro:host лежит в wit/yuaibro-host.wit; из него же авторы плагинов
+// генерируют гостевые привязки
+#[cfg(feature = "wasm")]
+wasmtime::component::bindgen!({
+    world: "plugin",
+    path: "wit",
+});
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum ToolbarPosition {
-    Top,
-    Bottom,
-    Floating,
-    Detached,
-    Hidden,
+#[cfg(feature = "wasm")]
+use self::yuaibro::host::{dom, fetch, logging, signals, storage, types};
+
+// Доступ компонента к возможностям хоста; по умолчанию закрыто всё, кроме журнала.
+// document — DOM и сигналы страницы, fetch — источники, к которым компонент может обращаться
+#[derive(Clone, Default)]
+pub struct ComponentGrants {
+    pub document: Option<PageDocument>,
+    pub fetch: Vec<url::Origin>,
+    pub storage: bool,
 }
 
-pub struct Window {
-    pub window: Arc<WinitWindow>,
-    pub config: Config,
-    pub egui_state: EguiState,
-    pub egui_ctx: EguiContext,
-    pub url: String,
-    pub html: String,
-    pub status_message: String,
-    pub bookmarks: VecDeque<String>,
-    pub show_devtools: bool,
-    pub show_settings: bool,
-    pub show_toolbar: bool,
-    pub devtools_tab: DevToolsTab,
-    pub headers: HeaderMap,
-    pub instance: Instance,
-    pub surface: Surface<'static>,
-    pub adapter: Adapter,
-    pub device: Device,
-    pub queue: Queue,
-    pub surface_config: SurfaceConfiguration,
-    pub renderer: Renderer,
-    pub html_scale_factor: f32,
-    pub html_renderer: HtmlRenderer,
-    pub tx_ssh: UnboundedSender<String>,
-    pub render_manager: wgpu_render::RenderManager,
-    pub url_input: String,
-    pub ai_chat_input: String,
-    pub ai_chat_history: Vec<(String, String)>,
-    pub send_ai_message: bool,
-    pub save_config: bool,
-    pub do_refresh: bool,
-    pub do_add_bookmark: bool,
-    pub new_url: Option<String>,
-    pub debug_info: DebugInfo,
-    pub last_request_time: Option<Instant>,
-    pub window_state: WindowState,
-    pub window_manager: WindowManager,
-    pub engine: Arc<BroEngine>,
-    pub devtools_state: DevToolsState,
+#[cfg(feature = "wasm")]
+struct HostBindings {
+    name: String,
+    fetch: Vec<url::Origin>,
+    storage: bool,
+    network: Arc<dyn NetworkTrait + Send + Sync>,
+    db: Arc<dyn YuaidbTrait + Send + Sync>,
+    events: BroadcastSender<EngineEvent>,
+    // Функции хоста синхронные и выполняются в spawn_blocking, async-сервисы вызываются через block_on
+    handle: tokio::runtime::Handle,
 }
 
-impl Window {
-    pub async fn new(
-        window: Arc<WinitWindow>,
-        config: &Config,
-        engine: Arc<BroEngine>,
-        enable_optimizations: bool,
-        event_loop: &ActiveEventLoop,
-    ) -> Result<Self> {
-        log::info!("Создание нового окна с масштабом: {}", config.settings.scale_factor.unwrap_or(1.0));
-        let instance = wgpu_render::create_energy_efficient_instance();
-        let surface = instance
-            .create_surface(window.clone())
-            .context("Не удалось создать wgpu поверхность")?;
-        let adapter = wgpu_render::create_energy_efficient_adapter(&instance, &surface)?;
-        let (device, queue) = adapter.request_device(&DeviceDescriptor {
-            required_limits: Limits::default(),
-            ..Default::default()
-        }).await.context("Не удалось создать wgpu устройство")?;
-        let size = window.inner_size();
-        if size.width == 0 || size.height == 0 {
-            return Err(anyhow::anyhow!(
-                "Недопустимый размер окна: {}x{}",
-                size.width,
-                size.height
-            ));
+#[cfg(feature = "wasm")]
+impl HostBindings {
+    // Длина имени в префиксе не даёт компоненту «a» прочитать ключи компонента «a/b»
+    fn storage_key(&self, key: &str) -> String {
+        format!("component:{}:{}/{}", self.name.len(), self.name, key)
+    }
+
+    // Источник должен быть в списке разрешённых. Имя хоста, кроме явно разрешённых
+    // IP-адресов, не должно указывать на локальную или внутреннюю сеть
+    fn check_fetch(&self, url: &str) -> std::result::Result<(), String> {
+        let url = url::Url::parse(url).map_err(|_| format!("Недопустимый URL: {}", url))?;
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(format!("Недопустимый URL: {}", url));
         }
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_config = wgpu_render::create_optimal_surface_config(
-            &surface_caps,
-            (size.width, size.height),
-        )?;
-        surface.configure(&device, &surface_config);
-        let renderer = Renderer::new(&device, surface_config.format, RendererOptions::default());
-        let egui_ctx = EguiContext::default();
-        let system_scale_factor = window.scale_factor() as f32;
-        egui_ctx.set_pixels_per_point(system_scale_factor);
-        let fonts = egui::FontDefinitions::default();
-        egui_ctx.set_fonts(fonts);
-        let mut style = Style::default();
-        style.text_styles.insert(TextStyle::Body, FontId::new(12.0, FontFamily::Proportional));
-        style.text_styles.insert(TextStyle::Heading, FontId::new(20.0, FontFamily::Proportional));
-        style.text_styles.insert(TextStyle::Monospace, FontId::new(12.0, FontFamily::Monospace));
-        egui_ctx.set_style(style);
-        let egui_state = EguiState::new(
-            egui_ctx.clone(),
-            egui::ViewportId::ROOT,
-            event_loop,
-            None,
-            None,
-            None,
-        );
-        let render_manager = wgpu_render::RenderManager::new(enable_optimizations);
-        let mut html_renderer = HtmlRenderer::new(system_scale_factor);
-        html_renderer.set_egui_context(egui_ctx.clone());
-        let window_manager = WindowManager::new(instance.clone(), adapter.clone(), device.clone(), queue.clone());
-        window.set_maximized(true);
-        window.set_min_inner_size(Some(winit::dpi::PhysicalSize::new(320, 480)));
-        let (tx_ssh, _rx_ssh) = unbounded_channel::<String>();
-        
-        // Создаем DevToolsState с ресивером событий движка
-        let devtools_state = DevToolsState {
-            engine: engine.clone(),
-            detected_framework: None,
-            network_logs: Vec::new(),
-            debug_info: DebugInfo::new(),
-            rx_engine: engine.event_receiver(),
-        };
+        if !self.fetch.contains(&url.origin()) {
+            return Err(format!("Компоненту {} не разрешены запросы к {}", self.name, url.origin().ascii_serialization()));
+        }
+        let Some(url::Host::Domain(host)) = url.host() else { return Ok(()) };
+        let port = url.port_or_known_default().unwrap_or(80);
+        let addrs = self
+            .handle
+            .block_on(tokio::net::lookup_host((host, port)))
+            .map_err(|e| format!("Не удалось разрешить {}: {}", host, e))?;
+        for addr in addrs {
+            if !is_public_address(addr.ip()) {
+                return Err(format!("{} указывает на внутренний адрес {}", host, addr.ip()));
+            }
+        }
+        Ok(())
+    }
 
-        let instance_self = Self {
-            window,
-            config: config.clone(),
-            egui_state,
-            egui_ctx,
-            url: config.settings.default_url.clone(),
-            url_input: config.settings.default_url.clone(),
-            html: r#"<html><body><h1>Добро пожаловать!</h1>
-<pre>
-____________________ ________   
-\______   \______   \\_____  \  
- |    |  _/|       _/ /   |   \ 
- |    |   \|    |   \/    |    \
- |________/|____|___/\_________/
- </pre>
-</body>
-</html>"#.to_string(),
-            status_message: "Готово".to_string(),
-            bookmarks: VecDeque::new(),
-            show_devtools: false,
-            show_settings: false,
-            show_toolbar: true,
-            devtools_tab: DevToolsTab::Html,
-            headers: HeaderMap::new(),
-            instance,
-            surface,
-            adapter,
-            device,
-            queue,
-            surface_config,
-            renderer,
-            render_manager,
-            html_scale_factor: system_scale_factor,
-            html_renderer,
-            tx_ssh,
-            ai_chat_input: String::new(),
-            ai_chat_history: Vec::new(),
-            send_ai_message: false,
-            save_config: false,
-            do_refresh: false,
-            do_add_bookmark: false,
-            new_url: None,
-            debug_info: DebugInfo::new(),
-            last_request_time: None,
-            window_state: WindowState::default(),
-            window_manager,           
-            engine,
-            devtools_state,
+    fn check_storage(&self) -> std::result::Result<(), String> {
+        if self.storage {
+            Ok(())
+        } else {
+            Err(format!("Компоненту {} не выдан доступ к хранилищу", self.name))
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl types::Host for HostBindings {}
+
+#[cfg(feature = "wasm")]
+impl fetch::Host for HostBindings {
+    fn send(&mut self, request: types::HttpRequest) -> std::result::Result<types::HttpResponse, String> {
+        if self.fetch.is_empty() {
+            return Err(format!("Компоненту {} не выдан доступ к сети", self.name));
+        }
+        self.check_fetch(&request.url)?;
+        let mut headers = reqwest::header::HeaderMap::new();
+        for header in &request.headers {
+            let name = reqwest::header::HeaderName::from_bytes(header.name.as_bytes()).map_err(|e| e.to_string())?;
+            let value = reqwest::header::HeaderValue::from_str(&header.value).map_err(|e| e.to_string())?;
+            headers.append(name, value);
+        }
+        // Компонент не получает cookie пользователя, а перенаправление не уводит его
+        // за пределы разрешённых источников: за Location он идёт сам, через ту же проверку
+        let net_request = NetRequest {
+            url: request.url,
+            method: request.method.to_ascii_uppercase(),
+            headers,
+            body: request.body,
+            credentials: Credentials::Omit,
+            follow_redirects: false,
         };
-        instance_self.window.set_visible(true);
-        log::info!("Окно инициализировано, начальный URL: {}", instance_self.url);
-        Ok(instance_self)
+        let response = self.handle.block_on(self.network.fetch_request(net_request)).map_err(|e| e.to_string())?;
+        Ok(types::HttpResponse {
+            status: response.status,
+            headers: response
+                .headers
+                .iter()
+                .map(|(name, value)| types::Header {
+                    name: name.to_string(),
+                    value: String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                })
+                .collect(),
+            body: response.body,
+        })
     }
+}
 
-    pub fn handle_event(&mut self, window_event: &WindowEvent, window_id: winit::window::WindowId) -> Result<()> {
-        log::info!("Обработка события окна: {:?}", window_event);
-        self.render_manager.update_activity();
-        if window_id == self.window.id() {
-            if let WindowEvent::ScaleFactorChanged { scale_factor, .. } = window_event {
-                let new_scale = *scale_factor as f32;
-                self.egui_ctx.set_pixels_per_point(new_scale);
-                self.html_renderer.set_scale_factor(new_scale);
-                self.html_scale_factor = new_scale;
-                log::info!("Изменение системного масштаба: {:.1}x, запрос перерисовки", new_scale);
-            }
-            let resp = self.egui_state.on_window_event(&self.window, window_event);
-            if resp.repaint {
-                log::info!("EguiState запросил перерисовку после события окна");
-                self.window.request_redraw();
-            }
-        } else {
-            for (panel_type, _) in self.window_state.detached_windows.iter() {
-                if self.window_state.detached_windows.get(panel_type).map(|dw| dw.window.id()) == Some(window_id) {
-                    self.window_manager.handle_window_event(panel_type, window_event)?;
-                }
+#[cfg(feature = "wasm")]
+impl storage::Host for HostBindings {
+    fn get(&mut self, key: String) -> Option<String> {
+        self.check_storage().ok()?;
+        self.handle.block_on(self.db.get(&self.storage_key(&key))).ok().flatten()
+    }
+
+    fn set(&mut self, key: String, value: String) -> std::result::Result<(), String> {
+        self.check_storage()?;
+        self.handle.block_on(self.db.insert(&self.storage_key(&key), &value)).map_err(|e| e.to_string())
+    }
+
+    fn delete(&mut self, key: String) -> std::result::Result<(), String> {
+        self.check_storage()?;
+        self.handle.block_on(self.db.delete(&self.storage_key(&key))).map_err(|e| e.to_string())
+    }
+
+    fn keys(&mut self) -> Vec<String> {
+        if self.check_storage().is_err() {
+            return Vec::new();
+        }
+        let prefix = self.storage_key("");
+        self.handle
+            .block_on(self.db.scan_prefix(&prefix))
+            .map(|entries| entries.into_iter().map(|(key, _)| key[prefix.len()..].to_string()).collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl logging::Host for HostBindings {
+    fn log(&mut self, level: types::LogLevel, message: String) {
+        let (level, stream) = match level {
+            types::LogLevel::Trace => (log::Level::Trace, WasmStream::Stdout),
+            types::LogLevel::Debug => (log::Level::Debug, WasmStream::Stdout),
+            types::LogLevel::Info => (log::Level::Info, WasmStream::Stdout),
+            types::LogLevel::Warn => (log::Level::Warn, WasmStream::Stderr),
+            types::LogLevel::Error => (log::Level::Error, WasmStream::Stderr),
+        };
+        log::log!(level, "[компонент {}] {}", self.name, message);
+        let _ = self.events.send(EngineEvent::WasmOutput(WasmOutput { module: self.name.clone(), stream, text: message }));
+    }
+}
+
+// IpAddr::is_global пока нестабилен
+#[cfg(feature = "wasm")]
+fn is_public_address(ip: std::net::IpAddr) -> bool {
+    match ip {
+        std::net::IpAddr::V4(ip) => {
+            let shared = ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64;
+            !(ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified() || ip.is_broadcast() || shared)
+        }
+        std::net::IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public_address(std::net::IpAddr::V4(mapped));
             }
+            let segment = ip.segments()[0];
+            let unique_local = (segment & 0xfe00) == 0xfc00;
+            let link_local = (segment & 0xffc0) == 0xfe80;
+            !(ip.is_loopback() || ip.is_unspecified() || unique_local || link_local)
         }
-        Ok(())
     }
+}
 
-    pub fn resize(&mut self, new_size: PhysicalSize<u32>, window_id: winit::window::WindowId) -> Result<()> {
-        log::info!("Изменение размера окна: {}x{}", new_size.width, new_size.height);
-        if window_id == self.window.id() {
-            if new_size.width > 0 && new_size.height > 0 {
-                let surface_caps = self.surface.get_capabilities(&self.adapter);
-                self.surface_config = wgpu_render::create_optimal_surface_config(
-                    &surface_caps,
-                    (new_size.width, new_size.height),
-                )?;
-                self.surface.configure(&self.device, &self.surface_config);
-                log::info!("Конфигурация поверхности обновлена, запрос перерисовки");
+#[cfg(feature = "wasm")]
+struct ComponentState {
+    limits: wasmtime::StoreLimits,
+    web_sys: WebSys,
+    signals: GuestSignals,
+    host: HostBindings,
+}
+
+// Экземпляр, оставшийся жить ради своих эффектов
+#[cfg(feature = "wasm")]
+struct LiveComponent {
+    store: wasmtime::Store<ComponentState>,
+    plugin: Plugin,
+}
+
+#[cfg(feature = "wasm")]
+fn reset_limits(store: &mut wasmtime::Store<ComponentState>) -> Result<()> {
+    store.set_fuel(FUEL_PER_CALL)?;
+    store.set_epoch_deadline(EPOCH_DEADLINE_TICKS);
+    Ok(())
+}
+
+// Запущенный компонент; его сигналы живут в графе документа, который ему выдан
+pub struct ComponentHandle {
+    pub owner: OwnerId,
+    signals: Arc<Signals>,
+}
+
+pub struct Bindings {
+    #[cfg(feature = "wasm")]
+    engine: wasmtime::Engine,
+    #[cfg(feature = "wasm")]
+    linker: Arc<wasmtime::component::Linker<ComponentState>>,
+    #[cfg(feature = "wasm")]
+    components: Mutex<HashMap<String, wasmtime::component::Component>>,
+    security: Arc<dyn SecurityManagerTrait + Send + Sync>,
+    network: Arc<dyn NetworkTrait + Send + Sync>,
+    db: Arc<dyn YuaidbTrait + Send + Sync>,
+    events: BroadcastSender<EngineEvent>,
+}
+
+impl Bindings {
+    pub fn new(
+        runtime: &Runtime,
+        security: Arc<dyn SecurityManagerTrait + Send + Sync>,
+        network: Arc<dyn NetworkTrait + Send + Sync>,
+        db: Arc<dyn YuaidbTrait + Send + Sync>,
+        events: BroadcastSender<EngineEvent>,
+    ) -> Result<Self> {
+        #[cfg(feature = "wasm")]
+        {
+            let engine = runtime.engine().clone();
+            let mut linker = wasmtime::component::Linker::new(&engine);
+            types::add_to_linker(&mut linker, |state: &mut ComponentState| &mut state.host)?;
+            dom::add_to_linker(&mut linker, |state: &mut ComponentState| &mut state.web_sys)?;
+            fetch::add_to_linker(&mut linker, |state: &mut ComponentState| &mut state.host)?;
+            storage::add_to_linker(&mut linker, |state: &mut ComponentState| &mut state.host)?;
+            logging::add_to_linker(&mut linker, |state: &mut ComponentState| &mut state.host)?;
+            signals::add_to_linker(&mut linker, |state: &mut ComponentState| &mut state.signals)?;
+            log::info!("Привязки yuaibro:host инициализированы");
+            Ok(Self {
+                engine,
+                linker: Arc::new(linker),
+                components: Mutex::new(HashMap::new()),
+                security,
+                network,
+                db,
+                events,
+            })
+        }
+        #[cfg(not(feature = "wasm"))]
+        {
+            let _ = runtime;
+            Ok(Self { security, network, db, events })
+        }
+    }
+
+    // Компонент принимается только с действительной подписью; возвращается его sha256
+    pub async fn load_component(&self, bytes: &[u8], signature: &[u8]) -> Result<String> {
+        self.security.verify_component(bytes, signature).await?;
+        #[cfg(feature = "wasm")]
+        {
+            let digest = to_hex(&Sha256::digest(bytes));
+            if !self.components.lock().contains_key(&digest) {
+                let component = wasmtime::component::Component::new(&self.engine, bytes)?;
+                self.components.lock().insert(digest.clone(), component);
             }
-        } else {
-            for (panel_type, _) in self.window_state.detached_windows.iter() {
-                if self.window_state.detached_windows.get(panel_type).map(|dw| dw.window.id()) == Some(window_id) {
-                    self.window_manager.resize_detached_window(panel_type, new_size)?;
+            log::info!("WASM-компонент {} скомпилирован ({} байт)", digest, bytes.len());
+            Ok(digest)
+        }
+        #[cfg(not(feature = "wasm"))]
+        Err(anyhow::anyhow!("WASM runtime отключён"))
+    }
+
+    // Инстанцирование и вызов экспорта run; ошибка, возвращённая компонентом, становится ошибкой вызова.
+    // Если компонент создал эффекты, экземпляр живёт до stop_component и перезапускает их сам
+    pub async fn run_component(&self, name: &str, digest: &str, grants: ComponentGrants) -> Result<ComponentHandle> {
+        #[cfg(feature = "wasm")]
+        {
+            let component = self
+                .components
+                .lock()
+                .get(digest)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Компонент {} не загружен", digest))?;
+            // Без документа у компонента собственный граф: состояние страниц ему недоступно
+            let (dom, signals) = match grants.document {
+                Some(document) => (Some(document.dom), document.signals),
+                None => (None, Signals::shared()),
+            };
+            let (owner, mut reactions) = signals.register_owner();
+            let handle = ComponentHandle { owner, signals: signals.clone() };
+            let state = ComponentState {
+                limits: wasmtime::StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).build(),
+                signals: GuestSignals::new(owner, signals.clone(), dom.clone()),
+                web_sys: WebSys::new(dom),
+                host: HostBindings {
+                    name: name.to_string(),
+                    fetch: grants.fetch,
+                    storage: grants.storage,
+                    network: self.network.clone(),
+                    db: self.db.clone(),
+                    events: self.events.clone(),
+                    handle: tokio::runtime::Handle::current(),
+                },
+            };
+            let mut store = wasmtime::Store::new(&self.engine, state);
+            store.limiter(|state| &mut state.limits);
+            reset_limits(&mut store)?;
+            store.epoch_deadline_trap();
+            let linker = self.linker.clone();
+            let component_name = name.to_string();
+            let started = tokio::task::spawn_blocking(move || {
+                let plugin = Plugin::instantiate(&mut store, &component, &linker)?;
+                plugin
+                    .call_run(&mut store)?
+                    .map_err(|e| anyhow::anyhow!("Компонент {} завершился с ошибкой: {}", component_name, e))?;
+                Ok::<_, anyhow::Error>(LiveComponent { store, plugin })
+            })
+            .await?;
+            let live = match started {
+                Ok(live) if signals.has_computations(owner) => live,
+                Ok(_) => {
+                    signals.drop_owner(owner);
+                    return Ok(handle);
                 }
-            }
+                Err(e) => {
+                    signals.drop_owner(owner);
+                    return Err(e);
+                }
+            };
+            let live = Arc::new(Mutex::new(live));
+            let name = name.to_string();
+            // Канал закрывается в stop_component, вместе с ним завершается цикл и освобождается экземпляр
+            tokio::spawn(async move {
+                while let Some(computation) = reactions.recv().await {
+                    let Some(run) = signals.begin(owner, computation) else { continue };
+                    let live = live.clone();
+                    let callback = run.callback;
+                    let result = tokio::task::spawn_blocking(move || {
+                        let LiveComponent { store, plugin } = &mut *live.lock();
+                        reset_limits(store)?;
+                        plugin
+                            .yuaibro_host_reactions()
+                            .call_run(store, callback)?
+                            .map_err(|e| anyhow::anyhow!(e))
+                    })
+                    .await
+                    .map_err(anyhow::Error::from)
+                    .and_then(|result| result);
+                    signals.finish(owner, &run, result);
+                }
+                log::debug!("Реакции компонента {} остановлены", name);
+            });
+            Ok(handle)
+        }
+        #[cfg(not(feature = "wasm"))]
+        {
+            let _ = (name, digest, grants, &self.network, &self.db, &self.events);
+            Err(anyhow::anyhow!("WASM runtime отключён"))
         }
-        Ok(())
     }
 
-    pub fn reset_scale_factor(&mut self) {
-        self.html_renderer.set_scale_factor(self.html_scale_factor);
-        self.egui_ctx.set_pixels_per_point(self.html_scale_factor);
-        self.status_message = format!("Масштаб сброшен: {:.1}x", self.html_scale_factor);
-        log::info!("Масштаб сброшен: {:.1}x, запрос перерисовки", self.html_scale_factor);
+    pub fn stop_component(&self, handle: &ComponentHandle) {
+        handle.signals.drop_owner(handle.owner);
     }
+}
 
-    pub fn change_scale_factor(&mut self, delta: f32) {
-        let new_scale = (self.html_scale_factor + delta).max(0.5).min(3.0);
-        if (new_scale - self.html_scale_factor).abs() > 0.01 {
-            self.html_scale_factor = new_scale;
-            self.html_renderer.set_scale_factor(new_scale);
-            self.egui_ctx.set_pixels_per_point(new_scale);
-            self.status_message = format!("Масштаб: {:.1}x", new_scale);
-            log::info!("Масштаб изменён: {:.1}x, запрос перерисовки", new_scale);
+// === FILE: wasm_api\cloud_bindings.rs ===
+use crate::core::config::LatticeConfig;
+use crate::core::engine::EngineEvent;
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+// Клиент управляющего интерфейса удалённого хоста по собственному протоколу cosmonaut:
+// команды — JSON поверх HTTP, события решётки — поток NDJSON в формате CloudEvents.
+// Совместимость с хостами wasmCloud не проверялась
+
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RemoteHost {
+    pub id: String,
+    #[serde(default)]
+    pub friendly_name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub uptime_seconds: u64,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RemoteActor {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub image_ref: String,
+    pub instances: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RemoteProvider {
+    pub id: String,
+    pub contract_id: String,
+    #[serde(default = "crate::wasm_api::provider_api::default_link_name")]
+    pub link_name: String,
+    #[serde(default)]
+    pub image_ref: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct HostInventory {
+    pub host_id: String,
+    #[serde(default)]
+    pub actors: Vec<RemoteActor>,
+    #[serde(default)]
+    pub providers: Vec<RemoteProvider>,
+}
+
+// Событие решётки: actor_scaled, provider_started, host_heartbeat и т.п.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RemoteLatticeEvent {
+    #[serde(default)]
+    pub id: String,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    // Хост-источник события
+    #[serde(default)]
+    pub source: String,
+    #[serde(default)]
+    pub time: String,
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
+
+impl RemoteLatticeEvent {
+    // com.wasmcloud.lattice.actor_scaled -> actor_scaled
+    pub fn short_type(&self) -> &str {
+        self.event_type.rsplit('.').next().unwrap_or(&self.event_type)
+    }
+}
+
+// Ответ управляющего интерфейса: success=false несёт текст ошибки в message
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CtlResponse<T> {
+    pub success: bool,
+    #[serde(default)]
+    pub message: String,
+    pub response: Option<T>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScaleActorCommand {
+    pub actor_ref: String,
+    #[serde(default)]
+    pub actor_id: Option<String>,
+    pub count: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StopActorCommand {
+    pub actor_id: String,
+}
+
+pub struct CloudBindings {
+    client: reqwest::Client,
+    // .../api/v1/lattices/<решётка>; сегменты пути добавляются с percent-кодированием
+    base: url::Url,
+    token: Option<String>,
+}
+
+impl CloudBindings {
+    pub fn new(config: &LatticeConfig) -> Result<Self> {
+        // Без общего таймаута: поток событий открыт сколько угодно долго
+        let client = reqwest::ClientBuilder::new()
+            .connect_timeout(Duration::from_secs(10))
+            .build()?;
+        let mut base = url::Url::parse(&config.url)?;
+        base.path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("lattice.url {} не может быть базовым адресом", config.url))?
+            .pop_if_empty()
+            .extend(["api", "v1", "lattices", config.lattice.as_str()]);
+        log::info!("Управление удалённой решёткой: {}", base);
+        Ok(Self { client, base, token: config.token.clone() })
+    }
+
+    // Идентификаторы хостов и акторов приходят извне и не должны менять маршрут
+    fn request(&self, method: reqwest::Method, segments: &[&str]) -> reqwest::RequestBuilder {
+        let mut url = self.base.clone();
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.extend(segments);
+        }
+        let builder = self.client.request(method, url);
+        match &self.token {
+            Some(token) => builder.bearer_auth(token),
+            None => builder,
         }
     }
 
-    // ИСПРАВЛЕННАЯ ФУНКЦИЯ: Загружает URL и обновляет UI через события движка
-    pub fn load_url(&mut self, url: String) {
-        log::info!("Загрузка URL: {}", url);
-        let engine = self.engine.clone();
-        let value = url.clone();
-        // Запускаем фоновую задачу для загрузки
-        tokio::spawn(async move {
-            match engine.fetch_url(value.clone()).await {
-                Ok(response) => {
-                    log::info!("URL загружен успешно: {}", value);
-                    engine.send_event(EngineEvent::UrlResponse(response));
+    // Команды, которые только подтверждаются, приходят с "response": null
+    async fn reply<T: DeserializeOwned>(&self, builder: reqwest::RequestBuilder) -> Result<Option<T>> {
+        let response = builder.timeout(Duration::from_secs(30)).send().await?;
+        let status = response.status();
+        let reply: CtlResponse<T> = response
+            .json()
+            .await
+            .map_err(|e| anyhow::anyhow!("Некорректный ответ управляющего интерфейса ({}): {}", status, e))?;
+        if !reply.success {
+            return Err(anyhow::anyhow!("Хост отклонил команду: {}", reply.message));
+        }
+        Ok(reply.response)
+    }
+
+    async fn send<T: DeserializeOwned>(&self, builder: reqwest::RequestBuilder) -> Result<T> {
+        self.reply(builder).await?.ok_or_else(|| anyhow::anyhow!("Пустой ответ управляющего интерфейса"))
+    }
+
+    async fn send_ack(&self, builder: reqwest::RequestBuilder) -> Result<()> {
+        self.reply::<serde_json::Value>(builder).await.map(|_| ())
+    }
+
+    pub async fn hosts(&self) -> Result<Vec<RemoteHost>> {
+        self.send(self.request(reqwest::Method::GET, &["hosts"])).await
+    }
+
+    pub async fn inventory(&self, host_id: &str) -> Result<HostInventory> {
+        self.send(self.request(reqwest::Method::GET, &["hosts", host_id, "inventory"])).await
+    }
+
+    // Развёртывание — масштабирование ещё не запущенного актора с ненулевым числом экземпляров
+    pub async fn deploy_actor(&self, host_id: &str, actor_ref: &str, count: usize) -> Result<()> {
+        self.scale_actor(host_id, actor_ref, None, count.max(1)).await
+    }
+
+    pub async fn scale_actor(&self, host_id: &str, actor_ref: &str, actor_id: Option<&str>, count: usize) -> Result<()> {
+        let command = ScaleActorCommand { actor_ref: actor_ref.to_string(), actor_id: actor_id.map(str::to_string), count };
+        self.send_ack(self.request(reqwest::Method::POST, &["hosts", host_id, "actors", "scale"]).json(&command))
+            .await
+    }
+
+    pub async fn stop_actor(&self, host_id: &str, actor_id: &str) -> Result<()> {
+        let command = StopActorCommand { actor_id: actor_id.to_string() };
+        self.send_ack(self.request(reqwest::Method::POST, &["hosts", host_id, "actors", "stop"]).json(&command))
+            .await
+    }
+
+    // Одно подключение к потоку событий; возвращается, когда сервер закрыл поток
+    async fn stream_events(&self, sink: &broadcast::Sender<EngineEvent>) -> Result<()> {
+        let mut response = self.request(reqwest::Method::GET, &["events"]).send().await?.error_for_status()?;
+        let mut buffer = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            buffer.extend_from_slice(&chunk);
+            while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                if line.iter().all(|b| b.is_ascii_whitespace()) {
+                    continue;
                 }
-                Err(e) => {
-                    log::error!("Ошибка загрузки URL {}: {}", value, e);
-                    engine.send_event(EngineEvent::Error(format!("Ошибка загрузки {}: {}", value, e)));
+                match serde_json::from_slice::<RemoteLatticeEvent>(&line) {
+                    Ok(event) => {
+                        let _ = sink.send(EngineEvent::Lattice(event));
+                    }
+                    Err(e) => log::warn!("Пропущено некорректное событие решётки: {}", e),
                 }
             }
-        });
-        
-        self.status_message = format!("Загрузка: {}...", url);
-        self.egui_ctx.request_repaint();
+        }
+        Ok(())
     }
 
-    pub fn navigate_to_url(&mut self, url: String) { 
-        log::info!("Навигация к URL: {}", url);
-        self.load_url(url);
+    // Фоновое наблюдение за событиями с переподключением
+    pub fn watch(self: &Arc<Self>, sink: broadcast::Sender<EngineEvent>) -> tokio::task::JoinHandle<()> {
+        let this = self.clone();
+        tokio::spawn(async move {
+            let mut delay = RECONNECT_MIN;
+            loop {
+                match this.stream_events(&sink).await {
+                    Ok(()) => delay = RECONNECT_MIN,
+                    Err(e) => {
+                        log::warn!("Поток событий решётки прерван: {}", e);
+                        delay = (delay * 2).min(RECONNECT_MAX);
+                    }
+                }
+                tokio::time::sleep(delay).await;
+            }
+        })
     }
+}
 
-    fn render_toolbar_panel(&mut self, ctx: &EguiContext, ui: &mut egui::Ui) {
-        toolbar::render_toolbar(
-            ui,
-            &mut self.url_input,
-            &mut self.url,
-            &self.bookmarks,
-            &mut self.show_devtools,
-            &mut self.show_settings,
-            &mut self.status_message,
-            &mut self.do_refresh,
-            &mut self.do_add_bookmark,
-            &mut self.new_url,
-            &mut self.window_state.toolbar_position,
-            &mut self.show_toolbar,
-        );
-        if ctx.has_requested_repaint() {
-            log::info!("Панель инструментов запросила перерисовку");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::io_manager::percent_decode;
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+
+    impl<T> CtlResponse<T> {
+        fn ok(response: T) -> Self {
+            Self { success: true, message: String::new(), response: Some(response) }
         }
-    }
 
-    pub async fn render(&mut self, event_loop: &ActiveEventLoop) -> Result<()> {
-        let html_snapshot = self.html.clone();
-        let start_time = Instant::now();
-        log::info!("Начало рендеринга окна, HTML длина: {}", html_snapshot.len());
+        fn error(message: impl Into<String>) -> Self {
+            Self { success: false, message: message.into(), response: None }
+        }
+    }
 
-        // ОБНОВЛЕНИЕ: Обрабатываем события движка перед рендером
-        self.devtools_state.poll_events();
+    // Локальный сервер с тем же протоколом, что и управляющий интерфейс, для проверки клиента
+    // без настоящего хоста
+    #[derive(Default)]
+    struct MockLattice {
+        hosts: Vec<RemoteHost>,
+        inventories: HashMap<String, HostInventory>,
+    }
 
-        if self.save_config {
-            log::info!("Сохранение конфигурации");
-            if let Err(e) = self.config.save_to_file(Config::get_config_path()) {
-                self.status_message = format!("Ошибка сохранения настроек: {}", e);
-                log::error!("Ошибка сохранения конфигурации: {}", e);
-            } else {
-                self.status_message = "Настройки сохранены".to_string();
-                if self.url.is_empty() || self.url == "about:blank" {
-                    self.url = self.config.settings.default_url.clone();
-                    self.url_input = self.config.settings.default_url.clone();
-                    log::info!("Установка начального URL: {}", self.url);
-                    self.load_url(self.url.clone());
+    struct MockControlServer {
+        addr: SocketAddr,
+        state: Arc<parking_lot::Mutex<MockLattice>>,
+        events: broadcast::Sender<RemoteLatticeEvent>,
+    }
+
+    impl MockControlServer {
+        async fn spawn(addr: SocketAddr, hosts: Vec<RemoteHost>) -> Result<Arc<Self>> {
+            let listener = TcpListener::bind(addr).await?;
+            let inventories = hosts
+                .iter()
+                .map(|host| (host.id.clone(), HostInventory { host_id: host.id.clone(), ..Default::default() }))
+                .collect();
+            let server = Arc::new(Self {
+                addr: listener.local_addr()?,
+                state: Arc::new(parking_lot::Mutex::new(MockLattice { hosts, inventories })),
+                events: broadcast::channel(256).0,
+            });
+            let accept = server.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let server = accept.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = server.serve(stream).await {
+                            log::debug!("Mock control interface: {}", e);
+                        }
+                    });
                 }
-                log::info!("Конфигурация сохранена, запрос перерисовки");
-            }
-            self.save_config = false;
+            });
+            Ok(server)
         }
 
-        if self.do_refresh {
-            log::info!("Обновление страницы: {}", self.url);
-            self.load_url(self.url.clone());
-            self.do_refresh = false;
+        fn url(&self) -> String {
+            format!("http://{}", self.addr)
         }
 
-        if self.do_add_bookmark {
-            log::info!("Добавление закладки: {}", self.url);
-            if !self.bookmarks.contains(&self.url) {
-                self.bookmarks.push_back(self.url.clone());
-                self.status_message = format!("Добавлена закладка: {}", self.url);
+        async fn serve(&self, stream: TcpStream) -> Result<()> {
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).await?;
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                if reader.read_line(&mut header).await? == 0 || header.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap_or(0);
+                    }
+                }
             }
-            self.do_add_bookmark = false;
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).await?;
+            let mut stream = reader.into_inner();
+
+            let mut parts = request_line.split_whitespace();
+            let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+            let segments: Vec<String> = path
+                .trim_start_matches('/')
+                .split('/')
+                .map(|segment| String::from_utf8_lossy(&percent_decode(segment)).into_owned())
+                .collect();
+            let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+            // api/v1/lattices/<решётка>/...
+            let route = segments.get(4..).unwrap_or(&[]);
+            if route == ["events"] {
+                return self.serve_events(stream).await;
+            }
+            let reply = match (method, route) {
+                ("GET", ["hosts"]) => serde_json::to_vec(&CtlResponse::ok(self.state.lock().hosts.clone()))?,
+                ("GET", ["hosts", host, "inventory"]) => match self.state.lock().inventories.get(*host) {
+                    Some(inventory) => serde_json::to_vec(&CtlResponse::ok(inventory.clone()))?,
+                    None => serde_json::to_vec(&CtlResponse::<()>::error(format!("Хост {} не найден", host)))?,
+                },
+                ("POST", ["hosts", host, "actors", "scale"]) => {
+                    let command: ScaleActorCommand = serde_json::from_slice(&body)?;
+                    serde_json::to_vec(&self.scale(host, command))?
+                }
+                ("POST", ["hosts", host, "actors", "stop"]) => {
+                    let command: StopActorCommand = serde_json::from_slice(&body)?;
+                    serde_json::to_vec(&self.scale_to_zero(host, &command.actor_id))?
+                }
+                _ => serde_json::to_vec(&CtlResponse::<()>::error(format!("Неизвестный маршрут {} {}", method, path)))?,
+            };
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                reply.len()
+            );
+            stream.write_all(head.as_bytes()).await?;
+            stream.write_all(&reply).await?;
+            Ok(())
         }
 
-        if let Some(new_url) = self.new_url.take() {
-            log::info!("Навигация к новому URL: {}", new_url);
-            self.navigate_to_url(new_url);
+        // Поток без Content-Length: конец потока — закрытие соединения
+        async fn serve_events(&self, mut stream: TcpStream) -> Result<()> {
+            let mut events = self.events.subscribe();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nConnection: close\r\n\r\n")
+                .await?;
+            stream.flush().await?;
+            while let Ok(event) = events.recv().await {
+                let mut line = serde_json::to_vec(&event)?;
+                line.push(b'\n');
+                stream.write_all(&line).await?;
+                stream.flush().await?;
+            }
+            Ok(())
         }
 
-        // ОБНОВЛЕНИЕ: Обработка событий движка из DevToolsState
-        // События уже обработаны в poll_events(), данные обновлены в devtools_state
-        // Копируем актуальные данные в UI состояние
-        if let Some(latest_log) = self.devtools_state.network_logs.last() {
-            if latest_log.url == self.url && self.html != latest_log.html {
-                self.html = latest_log.html.clone();
-                self.headers = latest_log.headers.clone();
-                self.status_message = format!("Загружено: {}", self.url);
-                log::info!("HTML обновлен из сетевого лога для URL: {}", self.url);
+        fn scale(&self, host: &str, command: ScaleActorCommand) -> CtlResponse<serde_json::Value> {
+            let mut state = self.state.lock();
+            let Some(inventory) = state.inventories.get_mut(host) else {
+                return CtlResponse::error(format!("Хост {} не найден", host));
+            };
+            let actor_id = command.actor_id.clone().unwrap_or_else(|| command.actor_ref.clone());
+            inventory.actors.retain(|actor| actor.id != actor_id);
+            if command.count > 0 {
+                inventory.actors.push(RemoteActor {
+                    id: actor_id.clone(),
+                    name: None,
+                    image_ref: command.actor_ref.clone(),
+                    instances: command.count,
+                });
             }
+            drop(state);
+            self.emit(host, "actor_scaled", serde_json::json!({
+                "actor_id": actor_id,
+                "actor_ref": command.actor_ref,
+                "max_instances": command.count,
+            }));
+            CtlResponse::ok(serde_json::Value::Null)
         }
 
-        let clicked_link = self.html_renderer.get_last_link_click();
-        let clicked_button = self.html_renderer.get_last_button_click();
-        let raw_input = self.egui_state.take_egui_input(&self.window);
-        let mut reset = false;
-        let mut scale_delta: f32 = 0.0;
-        let egui_ctx_clone = self.egui_ctx.clone();
-        let full_output = egui_ctx_clone.run(raw_input, |ctx| {
-            if !ctx.memory(|mem| mem.data.get_temp(egui::Id::new("style_configured")).unwrap_or(false)) {
-                let mut style = (*ctx.style()).clone();
-                let visuals = &mut style.visuals;
-                visuals.widgets.inactive.bg_fill = Color32::from_rgb(40, 40, 60);
-                visuals.widgets.hovered.bg_fill = Color32::from_rgb(60, 60, 90);
-                visuals.widgets.active.bg_fill = Color32::from_rgb(80, 80, 120);
-                visuals.widgets.inactive.fg_stroke.color = Color32::from_rgb(220, 220, 255);
-                visuals.widgets.hovered.fg_stroke.color = Color32::from_rgb(255, 255, 255);
-                visuals.panel_fill = Color32::from_rgb(0, 54, 0);
-                visuals.selection.bg_fill = Color32::from_rgb(70, 130, 180);
-                visuals.extreme_bg_color = Color32::from_rgb(0, 80, 0);
-                visuals.widgets.noninteractive.bg_stroke = egui::Stroke::NONE;
-                let rounding = CornerRadius::same(12);
-                visuals.widgets.inactive.corner_radius = rounding;
-                visuals.widgets.hovered.corner_radius = rounding;
-                visuals.widgets.active.corner_radius = rounding;
-                visuals.widgets.open.corner_radius = rounding;
-                visuals.widgets.noninteractive.corner_radius = rounding;
-                ctx.set_style(style);
-                ctx.memory_mut(|mem| mem.data.insert_temp(egui::Id::new("style_configured"), true));
-                log::info!("Стили egui инициализированы");
+        fn scale_to_zero(&self, host: &str, actor_id: &str) -> CtlResponse<serde_json::Value> {
+            let actor_ref = self
+                .state
+                .lock()
+                .inventories
+                .get(host)
+                .and_then(|inventory| inventory.actors.iter().find(|actor| actor.id == actor_id))
+                .map(|actor| actor.image_ref.clone());
+            match actor_ref {
+                Some(actor_ref) => self.scale(host, ScaleActorCommand { actor_ref, actor_id: Some(actor_id.to_string()), count: 0 }),
+                None => CtlResponse::error(format!("Актор {} не запущен на {}", actor_id, host)),
             }
+        }
 
-            if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Num0)) {
-                log::info!("Обнаружено нажатие Ctrl+0, сброс масштаба");
-                reset = true;
-            }
-            if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Plus)) {
-                log::info!("Обнаружено нажатие Ctrl++, увеличение масштаба");
-                scale_delta = 0.1;
-            }
-            if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Minus)) {
-                log::info!("Обнаружено нажатие Ctrl+-, уменьшение масштаба");
-                scale_delta = -0.1;
-            }
-            if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Equals)) {
-                log::info!("Обнаружено нажатие Ctrl+=, увеличение масштаба");
-                scale_delta = 0.1;
-            }
+        fn emit(&self, host: &str, kind: &str, data: serde_json::Value) {
+            let _ = self.events.send(RemoteLatticeEvent {
+                id: uuid::Uuid::new_v4().to_string(),
+                event_type: format!("com.wasmcloud.lattice.{}", kind),
+                source: host.to_string(),
+                time: std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs().to_string())
+                    .unwrap_or_default(),
+                data,
+            });
+        }
+    }
 
-            if self.show_toolbar && !self.window_state.detached_windows.contains_key(&PanelType::Toolbar) {
-                match self.window_state.toolbar_position {
-                    ToolbarPosition::Top => {
-                        TopBottomPanel::top("nav_panel").show(ctx, |ui| {
-                            self.render_toolbar_panel(ctx, ui);
-                        });
-                    }
-                    ToolbarPosition::Bottom => {
-                        TopBottomPanel::bottom("nav_panel").show(ctx, |ui| {
-                            self.render_toolbar_panel(ctx, ui);
-                        });
-                    }
-                    ToolbarPosition::Floating => {
-                        egui::Window::new("Панель инструментов")
-                            .default_pos(self.window_state.toolbar_floating_pos)
-                            .default_size((300.0, 60.0))
-                            .resizable(true)
-                            .frame(
-                                Frame::window(&ctx.style())
-                                    .corner_radius(12.0)
-                                    .inner_margin(egui::Margin::same(6))
-                            )
-                            .show(ctx, |ui| {
-                                self.render_toolbar_panel(ctx, ui);
-                            });
-                    }
-                    ToolbarPosition::Detached => {
-                        if !self.window_state.detached_windows.contains_key(&PanelType::Toolbar) {
-                            log::info!("Создание откреплённого окна для панели инструментов");
-                            if let Err(e) = self.window_manager.create_detached_window(
-                                event_loop,
-                                PanelType::Toolbar,
-                                (50.0, 50.0),
-                                (400.0, 60.0),
-                                &self.config,
-                            ) {
-                                self.status_message = format!("Ошибка создания окна toolbar: {}", e);
-                                log::error!("Ошибка создания окна toolbar: {}", e);
-                            }
-                        }
-                    }
-                    ToolbarPosition::Hidden => {}
-                }
-            }
+    fn host(id: &str) -> RemoteHost {
+        RemoteHost {
+            id: id.to_string(),
+            friendly_name: String::new(),
+            version: String::new(),
+            uptime_seconds: 0,
+            labels: BTreeMap::new(),
+        }
+    }
 
-            if self.show_devtools && !self.window_state.detached_windows.contains_key(&PanelType::DevTools) {
-                let width = *self.window_state.panel_sizes.get(&PanelType::DevTools).unwrap_or(&400.0);
-                let mut new_width = width;
-                SidePanel::right("devtools_panel")
-                    .resizable(true)
-                    .default_width(width)
-                    .min_width(200.0)
-                    .show(ctx, |ui| {
-                        let action = devtools::render_devtools_ui(
-                            ui,
-                            &mut self.devtools_tab,
-                            &mut self.html,
-                            &self.headers,
-                            &self.debug_info,
-                            &self.devtools_state.network_logs,
-                            &self.tx_ssh,
-                            false,
-                            &self.devtools_state,
-                        );
-                        new_width = ui.available_width();
-                        if let Some(PanelAction::Detach) = action {
-                            log::info!("Запрос на открепление DevTools");
-                            if let Err(e) = self.window_manager.create_detached_window(
-                                event_loop,
-                                PanelType::DevTools,
-                                (ctx.viewport_rect().right() - new_width - 20.0, 20.0),
-                                (new_width, ctx.viewport_rect().height() - 40.0),
-                                &self.config,
-                            ) {
-                                self.status_message = format!("Ошибка создания окна DevTools: {}", e);
-                                log::error!("Ошибка создания окна DevTools: {}", e);
-                            }
-                        }
-                    });
-                self.window_state.panel_sizes.insert(PanelType::DevTools, new_width);
-                if ctx.has_requested_repaint() {
-                    log::info!("DevTools запросил перерисовку");
-                }
-            }
+    async fn client(hosts: Vec<RemoteHost>) -> (Arc<MockControlServer>, CloudBindings) {
+        let server = MockControlServer::spawn("127.0.0.1:0".parse().unwrap(), hosts).await.unwrap();
+        let config = LatticeConfig { url: format!("{}/", server.url()), lattice: "default".to_string(), token: None };
+        (server, CloudBindings::new(&config).unwrap())
+    }
 
-            if self.show_settings && !self.window_state.detached_windows.contains_key(&PanelType::Settings) {
-                let width = *self.window_state.panel_sizes.get(&PanelType::Settings).unwrap_or(&400.0);
-                let mut new_width = width;
-                SidePanel::right("settings_panel")
-                    .resizable(true)
-                    .default_width(width)
-                    .min_width(200.0)
-                    .show(ctx, |ui| {
-                        let action = settings::render_settings_ui(
-                            ui,
-                            &mut self.config,
-                            &mut self.save_config,
-                            false,
-                        );
-                        new_width = ui.available_width();
-                        if let Some(PanelAction::Attach) = action {
-                            log::info!("Запрос на открепление Settings");
-                            if let Err(e) = self.window_manager.create_detached_window(
-                                event_loop,
-                                PanelType::Settings,
-                                (ctx.viewport_rect().right() - new_width - 20.0, 20.0),
-                                (new_width, ctx.viewport_rect().height() - 40.0),
-                                &self.config,
-                            ) {
-                                self.status_message = format!("Ошибка создания окна Settings: {}", e);
-                                log::error!("Ошибка создания окна Settings: {}", e);
-                            }
-                        }
-                    });
-                self.window_state.panel_sizes.insert(PanelType::Settings, new_width);
-                if ctx.has_requested_repaint() {
-                    log::info!("Settings запросил перерисовку");
+    #[tokio::test]
+    async fn scales_and_stops_actor_on_remote_host() {
+        let (_server, cloud) = client(vec![host("host-a")]).await;
+        assert_eq!(cloud.hosts().await.unwrap().len(), 1);
+        cloud.deploy_actor("host-a", "localhost:5000/echo:1.0", 0).await.unwrap();
+        let inventory = cloud.inventory("host-a").await.unwrap();
+        assert_eq!(inventory.actors.len(), 1);
+        assert_eq!(inventory.actors[0].instances, 1);
+        cloud.scale_actor("host-a", "localhost:5000/echo:1.0", Some("localhost:5000/echo:1.0"), 3).await.unwrap();
+        assert_eq!(cloud.inventory("host-a").await.unwrap().actors[0].instances, 3);
+        cloud.stop_actor("host-a", "localhost:5000/echo:1.0").await.unwrap();
+        assert!(cloud.inventory("host-a").await.unwrap().actors.is_empty());
+        assert!(cloud.stop_actor("host-a", "localhost:5000/echo:1.0").await.is_err());
+        assert!(cloud.inventory("host-b").await.is_err());
+    }
+
+    // Идентификатор с / и ? не должен менять маршрут запроса
+    #[tokio::test]
+    async fn host_and_actor_ids_are_percent_encoded() {
+        let (_server, cloud) = client(vec![host("hosts/evil?x=1")]).await;
+        cloud.deploy_actor("hosts/evil?x=1", "echo", 1).await.unwrap();
+        let inventory = cloud.inventory("hosts/evil?x=1").await.unwrap();
+        assert_eq!(inventory.host_id, "hosts/evil?x=1");
+        assert_eq!(inventory.actors[0].id, "echo");
+        assert!(cloud.inventory("hosts").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn watch_delivers_lattice_events() {
+        let (_server, cloud) = client(vec![host("host-a")]).await;
+        let cloud = Arc::new(cloud);
+        let (sink, mut events) = broadcast::channel(16);
+        let watcher = cloud.watch(sink);
+        // Команда повторяется, пока поток событий не подключится
+        let event = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                cloud.deploy_actor("host-a", "echo", 1).await.unwrap();
+                if let Ok(Ok(EngineEvent::Lattice(event))) =
+                    tokio::time::timeout(Duration::from_millis(200), events.recv()).await
+                {
+                    return event;
                 }
             }
+        })
+        .await
+        .expect("событие решётки не пришло");
+        watcher.abort();
+        assert_eq!(event.short_type(), "actor_scaled");
+        assert_eq!(event.source, "host-a");
+        assert_eq!(event.data["actor_id"], "echo");
+    }
+}
 
-            CentralPanel::default()
-                .show(ctx, |ui| {
-                    ScrollArea::both().auto_shrink([false; 2])
-                        .show(ui, |ui| {
-                            self.html_renderer.process_image_responses(ui);
-                            if let Err(e) = pollster::block_on(self.html_renderer.render_html_ui(ui, &html_snapshot)) {
-                                ui.label(format!("Ошибка рендеринга: {}", e));
-                                ui.monospace(&html_snapshot);
-                                log::error!("Ошибка рендеринга HTML: {}", e);
-                            }
-                        });
-                });
-            if ctx.has_requested_repaint() {
-                log::info!("CentralPanel запросил перерисовку");
-            }
+// === FILE: wasm_api\mod.rs ===
+pub mod bindings;
+pub mod signals;
+pub mod web_sys;
+pub mod sandbox;
+pub mod cloud_bindings;
+pub mod provider_api;
 
-            TopBottomPanel::bottom("status_bar")
-                .show(ctx, |ui| {
-                    ui.horizontal_wrapped(|ui| {
-                        ui.label(format!("Статус: {}", self.status_message));
-                        ui.label(format!("Масштаб: {:.1}x (Ctrl+0 для сброса)", self.html_scale_factor));
-                    });
-                });
-            if ctx.has_requested_repaint() {
-                log::info!("StatusBar запросил перерисовку");
-            }
-        });
+// === FILE: wasm_api\provider_api.rs ===
+use crate::core::provider::LinkDefinition;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+
+// Протокол провайдера один для отдельного процесса (JSON по строкам через stdin/stdout)
+// и для WASM-модуля (JSON через экспорт handle)
+
+// Двоичные данные в JSON передаются в base64
+mod base64_bytes {
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
 
-        let should_repaint = self.egui_ctx.has_requested_repaint();
-        if should_repaint {
-            log::info!("Egui контекст запросил перерисовку");
-        }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        base64::engine::general_purpose::STANDARD
+            .decode(text)
+            .map_err(serde::de::Error::custom)
+    }
+}
 
-        if reset {
-            log::info!("Сброс масштаба");
-            self.reset_scale_factor();
-        } else if scale_delta.abs() > 0.0 {
-            log::info!("Изменение масштаба на: {}", scale_delta);
-            self.change_scale_factor(scale_delta);
-        }
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProviderRequest {
+    pub actor_id: String,
+    pub link_name: String,
+    pub operation: String,
+    #[serde(with = "base64_bytes", default)]
+    pub payload: Vec<u8>,
+}
 
-        let platform_output = full_output.platform_output.clone();
-        self.egui_state.handle_platform_output(&self.window, platform_output);
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProviderMessage {
+    Start { config: BTreeMap<String, String> },
+    PutLink { link: LinkDefinition },
+    DeleteLink { link: LinkDefinition },
+    Request(ProviderRequest),
+    Shutdown,
+}
 
-        if let Some(link) = clicked_link {
-            log::info!("Навигация по клику на ссылке: {}", link);
-            self.navigate_to_url(link);
-        }
-        if let Some(button) = clicked_button {
-            log::info!("Обработка клика по кнопке: {}", button);
-            self.status_message = format!("Нажата кнопка: {}", button);
-        }
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ProviderReply {
+    Ok {
+        #[serde(with = "base64_bytes", default)]
+        payload: Vec<u8>,
+    },
+    Error { message: String },
+}
 
-        let size = self.window.inner_size();
-        let minimized = self.window.is_minimized().unwrap_or(false);
-        let mut frame_rendered = false;
-        if !minimized && size.width > 0 && size.height > 0 && should_repaint {
-            log::info!("Запуск рендера кадра, размер окна: {}x{}", size.width, size.height);
-            frame_rendered = wgpu_render::render_frame(
-                &self.device,
-                &self.queue,
-                &self.surface,
-                &self.surface_config,
-                &mut self.renderer,
-                &self.egui_ctx,
-                &full_output,
-                &mut self.render_manager,
-            )?;
-        } else {
-            log::info!("Пропуск рендера кадра: минимизировано={} или размер=0", minimized);
-            self.render_manager.total_frames_skipped += 1;
+impl ProviderReply {
+    pub fn into_result(self) -> Result<Vec<u8>> {
+        match self {
+            Self::Ok { payload } => Ok(payload),
+            Self::Error { message } => Err(anyhow::anyhow!(message)),
         }
+    }
+}
 
-        if frame_rendered {
-            let is_active = self.render_manager.is_active;
-            log::info!("Кадр отрендерен, активность: {}", is_active);
-            self.debug_info.update(
-                &self.html,
-                start_time.elapsed(),
-                self.debug_info.server_response_time,
-            );
+impl From<Result<Vec<u8>>> for ProviderReply {
+    fn from(result: Result<Vec<u8>>) -> Self {
+        match result {
+            Ok(payload) => Self::Ok { payload },
+            Err(e) => Self::Error { message: e.to_string() },
         }
+    }
+}
 
-        let panel_types = self.window_manager.get_detached_window_types();
-        for panel_type in panel_types {
-            let window_data = crate::ui::window_manager::DetachedWindowData {
-                devtools_tab: &mut self.devtools_tab,
-                html: &mut self.html,
-                headers: &self.headers,
-                debug_info: &self.debug_info,
-                network_logs: &self.devtools_state.network_logs,
-                tx_ssh: &self.tx_ssh,
-                devtools_state: &self.devtools_state,
-                url_input: &mut self.url_input,
-                url: &mut self.url,
-                bookmarks: &self.bookmarks,
-                show_devtools: &mut self.show_devtools,
-                show_settings: &mut self.show_settings,
-                status_message: &mut self.status_message,
-                do_refresh: &mut self.do_refresh,
-                do_add_bookmark: &mut self.do_add_bookmark,
-                new_url: &mut self.new_url,
-                toolbar_position: &mut self.window_state.toolbar_position,
-                show_toolbar: &mut self.show_toolbar,
-                config: &mut self.config,
-                save_config: &mut self.save_config,
-                ai_chat_history: &self.ai_chat_history,
-                send_ai_message: &mut self.send_ai_message,
-                render_manager: &mut self.render_manager,
-            };
-            self.window_manager.render_detached_window(&panel_type, window_data)?;
-        }
+// Строка протокола процесса: ответ сопоставляется с запросом по id
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProviderEnvelope<T> {
+    pub id: u64,
+    #[serde(flatten)]
+    pub body: T,
+}
 
-        self.window_manager.cleanup_closed_windows();
-        self.html_renderer.clear_repaint_flag();
+// Вызов провайдера из актора через импорт cosmonaut.call
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ActorCall {
+    pub contract_id: String,
+    #[serde(default = "default_link_name")]
+    pub link_name: String,
+    pub operation: String,
+    #[serde(with = "base64_bytes", default)]
+    pub payload: Vec<u8>,
+}
+
+pub fn default_link_name() -> String {
+    "default".to_string()
+}
 
+// Сторона провайдера: key-value, HTTP-клиент, SQL и т.п. реализуют этот трейт
+pub trait ProviderHandler {
+    fn start(&mut self, _config: &BTreeMap<String, String>) -> Result<()> {
+        Ok(())
+    }
+    fn put_link(&mut self, link: &LinkDefinition) -> Result<()>;
+    fn delete_link(&mut self, link: &LinkDefinition) -> Result<()>;
+    fn handle(&mut self, request: ProviderRequest) -> Result<Vec<u8>>;
+    fn shutdown(&mut self) -> Result<()> {
         Ok(())
     }
+}
+
+pub struct ProviderAPI;
+
+impl ProviderAPI {
+    pub fn dispatch(handler: &mut dyn ProviderHandler, message: ProviderMessage) -> ProviderReply {
+        let result = match message {
+            ProviderMessage::Start { config } => handler.start(&config).map(|_| Vec::new()),
+            ProviderMessage::PutLink { link } => handler.put_link(&link).map(|_| Vec::new()),
+            ProviderMessage::DeleteLink { link } => handler.delete_link(&link).map(|_| Vec::new()),
+            ProviderMessage::Request(request) => handler.handle(request),
+            ProviderMessage::Shutdown => handler.shutdown().map(|_| Vec::new()),
+        };
+        result.into()
+    }
 
-    pub fn set_focused(&mut self, focused: bool) {
-        log::info!("Установка фокуса окна: {}", focused);
-        self.render_manager.set_window_active(focused);
+    // Цикл отдельного процесса-провайдера; завершается после shutdown или закрытия stdin
+    pub fn serve_stdio(handler: &mut dyn ProviderHandler) -> Result<()> {
+        let stdin = std::io::stdin();
+        let mut stdout = std::io::stdout();
+        for line in stdin.lock().lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let envelope: ProviderEnvelope<ProviderMessage> = serde_json::from_str(&line)?;
+            let is_shutdown = matches!(envelope.body, ProviderMessage::Shutdown);
+            let reply = ProviderEnvelope { id: envelope.id, body: Self::dispatch(handler, envelope.body) };
+            writeln!(stdout, "{}", serde_json::to_string(&reply)?)?;
+            stdout.flush()?;
+            if is_shutdown {
+                break;
+            }
+        }
+        Ok(())
     }
 }
 
-// === FILE: ui\window_manager.rs ===
-use crate::core::config::Config;
-use crate::ui::devtools::{render_devtools_ui, PanelAction, DevToolsState};
-use crate::ui::{toolbar, aichat, settings, wgpu_render};
-use anyhow::{Result, Context};
-use egui::{Context as EguiContext, Pos2, FontId, FontFamily, TextStyle, Color32, CornerRadius};
-use egui_winit::State as EguiState;
-use winit::window::Window as WinitWindow;
-use winit::event_loop::ActiveEventLoop;
-use winit::event::WindowEvent;
-use wgpu::{Instance, Surface, Adapter, Device, Queue, SurfaceConfiguration};
-use egui_wgpu::{Renderer, RendererOptions};
+// === FILE: wasm_api\sandbox.rs ===
+use crate::core::engine::EngineEvent;
+use crate::core::installer::Installer;
+use crate::core::interfaces::WasmRuntimeTrait;
+use crate::core::runtime::{InstanceId, WasmValue};
+use crate::core::wasm_manifest::ComponentManifest;
+use anyhow::Result;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::collections::HashMap;
-use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::broadcast::Sender as BroadcastSender;
+
+// Каталог модуля внутри гостя; на хосте это ~/.cosmonaut/wasm_data/<модуль>
+pub const GUEST_DATA_DIR: &str = "/data";
+#[cfg(feature = "wasm")]
+const OUTPUT_CAPACITY: usize = 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum FsAccess {
+    #[default]
+    None,
+    ReadOnly,
+    ReadWrite,
+}
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub enum PanelType {
-    DevTools,
-    AIChat,
-    Settings,
-    Toolbar,
+// Разрешение на сокет: конкретный адрес и, если указан, порт
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NetworkGrant {
+    pub addr: IpAddr,
+    pub port: Option<u16>,
 }
 
-pub struct DetachedWindow {
-    pub panel_type: PanelType,
-    pub window: Arc<WinitWindow>,
-    pub surface: Surface<'static>,
-    pub renderer: Renderer,
-    pub egui_state: EguiState,
-    pub egui_ctx: EguiContext,
-    pub surface_config: SurfaceConfiguration,
-    pub position: (f32, f32),
-    pub size: (f32, f32),
-    pub visible: bool,
-    pub should_close: bool,
+impl NetworkGrant {
+    pub fn allows(&self, target: &SocketAddr) -> bool {
+        self.addr == target.ip() && self.port.is_none_or(|port| port == target.port())
+    }
 }
 
-pub struct WindowState {
-    pub fullscreen: bool,
-    pub toolbar_position: super::ToolbarPosition,
-    pub toolbar_floating_pos: Pos2,
-    pub detached_windows: HashMap<PanelType, DetachedWindow>,
-    pub panel_sizes: HashMap<PanelType, f32>,
+// Всё, что не выдано явно, модулю недоступно
+#[derive(Clone, Debug, Default)]
+pub struct Capabilities {
+    pub stdio: bool,
+    pub filesystem: FsAccess,
+    pub env: Vec<(String, String)>,
+    pub args: Vec<String>,
+    pub clock: bool,
+    pub random: bool,
+    pub network: Vec<NetworkGrant>,
 }
 
-impl Default for WindowState {
-    fn default() -> Self {
-        let mut panel_sizes = HashMap::new();
-        panel_sizes.insert(PanelType::DevTools, 400.0);
-        panel_sizes.insert(PanelType::AIChat, 350.0);
-        panel_sizes.insert(PanelType::Settings, 400.0);
-        panel_sizes.insert(PanelType::Toolbar, 400.0);
-        Self {
-            fullscreen: true,
-            toolbar_position: super::ToolbarPosition::Top,
-            toolbar_floating_pos: Pos2::new(50.0, 50.0),
-            detached_windows: HashMap::new(),
-            panel_sizes,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WasmStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Clone, Debug)]
+pub struct WasmOutput {
+    pub module: String,
+    pub stream: WasmStream,
+    pub text: String,
+}
+
+pub type OutputSink = Arc<dyn Fn(WasmStream, String) + Send + Sync>;
+// Обработчик импорта cosmonaut.call: запрос и ответ в JSON; выполняется в блокирующем потоке
+pub type HostCall = Arc<dyn Fn(Vec<u8>) -> Vec<u8> + Send + Sync>;
+
+// Параметры изоляции одного экземпляра
+#[derive(Clone, Default)]
+pub struct SandboxConfig {
+    pub name: String,
+    pub capabilities: Capabilities,
+    pub data_dir: Option<PathBuf>,
+    pub output: Option<OutputSink>,
+    pub host_call: Option<HostCall>,
+}
+
+// Имя модуля становится именем каталога. Остальные символы, включая сам '%' и точки
+// по краям, кодируются как %XX по байтам UTF-8, поэтому разные имена не совпадают
+fn data_dir_name(name: &str) -> String {
+    if name.is_empty() {
+        return "%".to_string();
+    }
+    let last = name.len() - 1;
+    let mut encoded = String::with_capacity(name.len());
+    for (index, byte) in name.bytes().enumerate() {
+        let edge_dot = byte == b'.' && (index == 0 || index == last);
+        if (byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.')) && !edge_dot {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
         }
     }
+    encoded
 }
 
-// === ДАННЫЕ ДЛЯ ОТКРЕПЛЁННЫХ ОКОН — ТОЛЬКО ЧЕРЕЗ API ===
-pub struct DetachedWindowData<'a> {
-    pub devtools_tab: &'a mut crate::ui::devtools::DevToolsTab,
-    pub html: &'a mut String,
-    pub headers: &'a reqwest::header::HeaderMap,
-    pub debug_info: &'a crate::ui::devtools::DebugInfo,
-    pub network_logs: &'a Vec<crate::ui::devtools::RequestLog>,
-    pub tx_ssh: &'a UnboundedSender<String>,
-    pub devtools_state: &'a DevToolsState,  // ← Единственный источник данных от движка
-    pub url_input: &'a mut String,
-    pub url: &'a mut String,
-    pub bookmarks: &'a std::collections::VecDeque<String>,
-    pub show_devtools: &'a mut bool,
-    pub show_settings: &'a mut bool,
-    pub status_message: &'a mut String,
-    pub do_refresh: &'a mut bool,
-    pub do_add_bookmark: &'a mut bool,
-    pub new_url: &'a mut Option<String>,
-    pub toolbar_position: &'a mut super::ToolbarPosition,
-    pub show_toolbar: &'a mut bool,
-    pub config: &'a mut Config,
-    pub save_config: &'a mut bool,
-    pub ai_chat_history: &'a Vec<(String, String)>,
-    pub send_ai_message: &'a mut bool,
-    pub render_manager: &'a mut crate::ui::wgpu_render::RenderManager,
+#[cfg(feature = "wasm")]
+struct FrozenClock;
+
+#[cfg(feature = "wasm")]
+impl wasmtime_wasi::HostWallClock for FrozenClock {
+    fn resolution(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(1)
+    }
+
+    fn now(&self) -> std::time::Duration {
+        std::time::Duration::ZERO
+    }
 }
 
-pub struct WindowManager {
-    pub instance: Instance,
-    pub adapter: Adapter,
-    pub device: Device,
-    pub queue: Queue,
-    pub window_state: WindowState,
+#[cfg(feature = "wasm")]
+impl wasmtime_wasi::HostMonotonicClock for FrozenClock {
+    fn resolution(&self) -> u64 {
+        1_000_000_000
+    }
+
+    fn now(&self) -> u64 {
+        0
+    }
+}
+
+// stdout/stderr гостя. Буфер забирается при каждом сбросе; байты сверх OUTPUT_CAPACITY
+// между двумя сбросами отбрасываются, а гость продолжает писать без ошибок
+#[cfg(feature = "wasm")]
+#[derive(Clone, Default)]
+struct OutputBuffer(Arc<parking_lot::Mutex<(Vec<u8>, usize)>>);
+
+#[cfg(feature = "wasm")]
+impl OutputBuffer {
+    fn take(&self) -> (Vec<u8>, usize) {
+        std::mem::take(&mut *self.0.lock())
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl wasmtime_wasi::StdoutStream for OutputBuffer {
+    fn stream(&self) -> Box<dyn wasmtime_wasi::HostOutputStream> {
+        Box::new(self.clone())
+    }
+
+    fn isatty(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl wasmtime_wasi::HostOutputStream for OutputBuffer {
+    fn write(&mut self, bytes: bytes::Bytes) -> wasmtime_wasi::StreamResult<()> {
+        let mut buffer = self.0.lock();
+        let room = OUTPUT_CAPACITY.saturating_sub(buffer.0.len());
+        let accepted = bytes.len().min(room);
+        buffer.0.extend_from_slice(&bytes[..accepted]);
+        buffer.1 += bytes.len() - accepted;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> wasmtime_wasi::StreamResult<()> {
+        Ok(())
+    }
+
+    fn check_write(&mut self) -> wasmtime_wasi::StreamResult<usize> {
+        Ok(OUTPUT_CAPACITY)
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[async_trait::async_trait]
+impl wasmtime_wasi::Subscribe for OutputBuffer {
+    async fn ready(&mut self) {}
+}
+
+// Захваченный stdout/stderr; новые байты передаются приёмнику после каждого вызова
+#[cfg(feature = "wasm")]
+pub(crate) struct SandboxOutput {
+    name: String,
+    pipes: Vec<(WasmStream, OutputBuffer)>,
+    sink: Option<OutputSink>,
+}
+
+#[cfg(feature = "wasm")]
+impl SandboxOutput {
+    pub(crate) fn flush(&mut self) {
+        for (stream, pipe) in &self.pipes {
+            let (contents, dropped) = pipe.take();
+            if contents.is_empty() && dropped == 0 {
+                continue;
+            }
+            let mut text = String::from_utf8_lossy(&contents).into_owned();
+            if dropped > 0 {
+                text.push_str(&format!("\n[отброшено {} байт вывода]\n", dropped));
+            }
+            match &self.sink {
+                Some(sink) => sink(*stream, text),
+                None => log::info!("[wasm {} {:?}] {}", self.name, stream, text.trim_end()),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl SandboxConfig {
+    pub(crate) fn build_wasi(&self) -> Result<(wasmtime_wasi::preview1::WasiP1Ctx, SandboxOutput)> {
+        use wasmtime_wasi::{DirPerms, FilePerms, WasiCtxBuilder};
+
+        let caps = &self.capabilities;
+        let mut builder = WasiCtxBuilder::new();
+        let mut output = SandboxOutput { name: self.name.clone(), pipes: Vec::new(), sink: self.output.clone() };
+        if caps.stdio {
+            let (stdout, stderr) = (OutputBuffer::default(), OutputBuffer::default());
+            builder.stdout(stdout.clone()).stderr(stderr.clone());
+            output.pipes.push((WasmStream::Stdout, stdout));
+            output.pipes.push((WasmStream::Stderr, stderr));
+        }
+        let mut args = vec![self.name.clone()];
+        args.extend(caps.args.iter().cloned());
+        builder.args(&args);
+        builder.envs(&caps.env);
+        if caps.filesystem != FsAccess::None {
+            let host_dir = self
+                .data_dir
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("Для модуля {} не задан каталог данных", self.name))?;
+            std::fs::create_dir_all(host_dir)?;
+            let (dir_perms, file_perms) = match caps.filesystem {
+                FsAccess::ReadWrite => (DirPerms::all(), FilePerms::all()),
+                _ => (DirPerms::READ, FilePerms::READ),
+            };
+            builder.preopened_dir(host_dir, GUEST_DATA_DIR, dir_perms, file_perms)?;
+        }
+        if !caps.clock {
+            builder.wall_clock(FrozenClock).monotonic_clock(FrozenClock);
+        }
+        // Без разрешения random вызов random_get завершается ловушкой (см. Runtime::host_linker)
+        let grants = Arc::new(caps.network.clone());
+        builder.allow_ip_name_lookup(false);
+        builder.allow_tcp(!grants.is_empty()).allow_udp(!grants.is_empty());
+        builder.socket_addr_check(move |addr, _use| {
+            let allowed = grants.iter().any(|grant| grant.allows(&addr));
+            Box::pin(async move { allowed })
+        });
+        Ok((builder.build_p1(), output))
+    }
+}
+
+pub struct Sandbox {
+    runtime: Arc<dyn WasmRuntimeTrait + Send + Sync>,
+    events: BroadcastSender<EngineEvent>,
+    data_root: PathBuf,
 }
 
-impl WindowManager {
-    pub fn new(instance: Instance, adapter: Adapter, device: Device, queue: Queue) -> Self {
-        Self {
-            instance,
-            adapter,
-            device,
-            queue,
-            window_state: WindowState::default(),
+impl Sandbox {
+    pub fn new(runtime: Arc<dyn WasmRuntimeTrait + Send + Sync>, events: BroadcastSender<EngineEvent>) -> Result<Self> {
+        let data_root = Installer::get_cosmonaut_dir()?.join("wasm_data");
+        Ok(Self { runtime, events, data_root })
+    }
+
+    pub fn data_dir(&self, name: &str) -> PathBuf {
+        self.data_root.join(data_dir_name(name))
+    }
+
+    fn config(&self, name: &str, capabilities: Capabilities) -> SandboxConfig {
+        let events = self.events.clone();
+        let module = name.to_string();
+        let sink: OutputSink = Arc::new(move |stream, text| {
+            let _ = events.send(EngineEvent::WasmOutput(WasmOutput { module: module.clone(), stream, text }));
+        });
+        SandboxConfig {
+            name: name.to_string(),
+            capabilities,
+            data_dir: Some(self.data_dir(name)),
+            output: Some(sink),
+            host_call: None,
         }
     }
 
-    fn configure_egui_style(ctx: &EguiContext) {
-        let mut style = (*ctx.style()).clone();
-        style.text_styles = [
-            (TextStyle::Heading, FontId::new(15.0, FontFamily::Proportional)),
-            (TextStyle::Body, FontId::new(12.0, FontFamily::Proportional)),
-            (TextStyle::Monospace, FontId::new(12.0, FontFamily::Monospace)),
-            (TextStyle::Button, FontId::new(12.0, FontFamily::Proportional)),
-            (TextStyle::Small, FontId::new(10.0, FontFamily::Proportional)),
-        ].into();
-        
-        let visuals = &mut style.visuals;
-        visuals.widgets.inactive.bg_fill = Color32::from_rgb(40, 40, 60);
-        visuals.widgets.hovered.bg_fill  = Color32::from_rgb(60, 60, 90);
-        visuals.widgets.active.bg_fill   = Color32::from_rgb(80, 80, 120);
-        visuals.widgets.inactive.fg_stroke.color = Color32::from_rgb(220, 220, 255);
-        visuals.widgets.hovered.fg_stroke.color  = Color32::from_rgb(255, 255, 255);
-        visuals.panel_fill   = Color32::from_rgb(0, 54, 0);
-        visuals.selection.bg_fill = Color32::from_rgb(70, 130, 180);
-        visuals.extreme_bg_color = Color32::from_rgb(0, 80, 0);
-        visuals.widgets.noninteractive.bg_stroke = egui::Stroke::NONE;
-        
-        let rounding = CornerRadius::same(12);
-        visuals.widgets.inactive.corner_radius = rounding;
-        visuals.widgets.hovered.corner_radius  = rounding;
-        visuals.widgets.active.corner_radius   = rounding;
-        visuals.widgets.open.corner_radius     = rounding;
-        visuals.widgets.noninteractive.corner_radius = rounding;
-        
-        ctx.set_style(style);
+    // Экземпляр уже загруженного модуля (по его sha256) с выданными возможностями
+    pub async fn instantiate(&self, name: &str, digest: &str, capabilities: Capabilities) -> Result<InstanceId> {
+        log::info!("Песочница {}: {:?}", name, capabilities);
+        self.runtime.instantiate(digest, self.config(name, capabilities)).await
     }
 
-    pub fn create_detached_window(
-        &mut self,
-        event_loop: &ActiveEventLoop,
-        panel_type: PanelType,
-        position: (f32, f32),
-        size: (f32, f32),
-        config: &Config,
-    ) -> Result<()> {
-        let title = match panel_type {
-            PanelType::DevTools => "Инструменты разработчика",
-            PanelType::AIChat => "Чат с ИИ",
-            PanelType::Settings => "Настройки",
-            PanelType::Toolbar => "Панель инструментов",
-        };
+    pub async fn call(&self, instance: InstanceId, export: &str, args: Vec<WasmValue>) -> Result<Vec<WasmValue>> {
+        self.runtime.call(instance, export, args).await
+    }
 
-        let window = Arc::new(
-            event_loop
-                .create_window(
-                    winit::window::WindowAttributes::default()
-                        .with_title(title)
-                        .with_position(winit::dpi::PhysicalPosition::new(position.0, position.1))
-                        .with_inner_size(winit::dpi::PhysicalSize::new(size.0, size.1))
-                        .with_decorations(true)
-                        .with_resizable(true),
-                )
-                .context("Не удалось создать окно")?,
-        );
+    pub async fn drop_instance(&self, instance: InstanceId) {
+        self.runtime.drop_instance(instance).await;
+    }
 
-        let surface = self.instance
-            .create_surface(window.clone())
-            .context("Не удалось создать поверхность для окна")?;
+    // Модуль, имя и разрешения берутся из манифеста
+    pub async fn run_manifest(&self, manifest: &ComponentManifest, export: &str, args: Vec<WasmValue>) -> Result<Vec<WasmValue>> {
+        let bytes = tokio::fs::read(manifest.entry_path()).await?;
+        let signature = manifest.signature_bytes()?;
+        self.run(&manifest.name, &bytes, &signature, manifest.capabilities.sandbox(), export, args).await
+    }
 
-        let surface_caps = surface.get_capabilities(&self.adapter);
-        let surface_config = wgpu_render::create_optimal_surface_config(
-            &surface_caps,
-            (size.0 as u32, size.1 as u32),
-        )?;
+    // Проверка подписи, запуск экспорта и удаление экземпляра
+    pub async fn run(
+        &self,
+        name: &str,
+        wasm_bytes: &[u8],
+        signature: &[u8],
+        capabilities: Capabilities,
+        export: &str,
+        args: Vec<WasmValue>,
+    ) -> Result<Vec<WasmValue>> {
+        let digest = self.runtime.load_module(wasm_bytes, signature).await?;
+        let instance = self.instantiate(name, &digest, capabilities).await?;
+        let result = self.call(instance, export, args).await;
+        self.drop_instance(instance).await;
+        result
+    }
+}
 
-        surface.configure(&self.device, &surface_config);
+// === FILE: wasm_api\signals.rs ===
+use crate::dom::live::{NodeId, SharedDom};
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+#[cfg(feature = "wasm")]
+use crate::wasm_api::bindings::yuaibro::host::signals;
 
-        let renderer = Renderer::new(&self.device, surface_config.format, RendererOptions::default());
-        
-        let egui_ctx = EguiContext::default();
-        
-        egui_ctx.set_fonts(egui::FontDefinitions::default());
-        let scale_factor = config.settings.scale_factor.unwrap_or(1.0);
-        egui_ctx.set_pixels_per_point(scale_factor);
-        
-        Self::configure_egui_style(&egui_ctx);
+static NEXT_GRAPH: AtomicU64 = AtomicU64::new(1);
 
-        let egui_state = EguiState::new(
-            egui_ctx.clone(),
-            egui::ViewportId::from_hash_of(&panel_type),
-            &window,
-            None,
-            None,
-            None,
-        );
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SignalId(pub u32);
 
-        self.window_state.detached_windows.insert(
-            panel_type.clone(),
-            DetachedWindow {
-                panel_type,
-                window,
-                surface,
-                renderer,
-                egui_state,
-                egui_ctx,
-                surface_config,
-                position,
-                size,
-                visible: true,
-                should_close: false,
-            },
-        );
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ComputationId(pub u32);
 
-        Ok(())
+// Экземпляр компонента, которому принадлежат эффекты и memo
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct OwnerId(pub u64);
+
+#[derive(Clone)]
+enum DomBinding {
+    Text(SharedDom, NodeId),
+    Attribute(SharedDom, NodeId, String),
+}
+
+impl DomBinding {
+    fn apply(&self, value: &JsonValue) -> Result<()> {
+        let text = match value {
+            JsonValue::String(text) => text.clone(),
+            JsonValue::Null => String::new(),
+            other => other.to_string(),
+        };
+        match self {
+            Self::Text(dom, node) => dom.write().set_text_content(*node, &text),
+            Self::Attribute(dom, node, name) => dom.write().set_attribute(*node, name, &text),
+        }
     }
+}
 
-    pub fn render_detached_window(
-        &mut self,
-        panel_type: &PanelType,
-        window_data: DetachedWindowData,
-    ) -> Result<()> {
-        if let Some(detached) = self.window_state.detached_windows.get_mut(panel_type) {
-            if detached.should_close {
-                self.window_state.detached_windows.remove(panel_type);
-                return Ok(());
-            }
+struct Signal {
+    value: JsonValue,
+    // None — состояние страницы, опубликованное хостом
+    owner: Option<OwnerId>,
+    subscribers: BTreeSet<ComputationId>,
+    // Владелец привязки: привязки компонента снимаются вместе с ним
+    bindings: Vec<(Option<OwnerId>, DomBinding)>,
+}
 
-            if !detached.visible {
-                return Ok(());
-            }
+impl Signal {
+    // Компонент видит свои сигналы и состояние страницы, но не сигналы других компонентов
+    fn accessible(&self, owner: Option<OwnerId>) -> bool {
+        owner.is_none() || self.owner.is_none() || self.owner == owner
+    }
+}
 
-            let raw_input = detached.egui_state.take_egui_input(&detached.window);
-            let panel_type_clone = panel_type.clone();
-            let mut should_attach = false;
+struct Computation {
+    owner: OwnerId,
+    callback: u32,
+    memo: Option<SignalId>,
+    sources: BTreeSet<SignalId>,
+    queued: bool,
+}
 
-            let full_output = detached.egui_ctx.run(raw_input, |ctx| {
-                if !ctx.memory(|mem| mem.data.get_temp(egui::Id::new("style_configured")).unwrap_or(false)) {
-                    Self::configure_egui_style(ctx);
-                    ctx.memory_mut(|mem| mem.data.insert_temp(egui::Id::new("style_configured"), true));
-                }
+// Запуск вычисления, полученный из begin: обратный вызов гостя и сигнал memo, если это memo
+pub struct PendingRun {
+    pub callback: u32,
+    pub memo: Option<SignalId>,
+}
 
-                egui::CentralPanel::default().show(ctx, |ui| {
-                    match &panel_type_clone {
-                        PanelType::DevTools => {
-                            // ← ЧИТАЕМ ИЗ devtools_state (получает из движка)
-                            let action = render_devtools_ui(
-                                ui,
-                                window_data.devtools_tab,
-                                window_data.html,
-                                window_data.headers,
-                                window_data.debug_info,
-                                window_data.network_logs,
-                                window_data.tx_ssh,
-                                true,
-                                window_data.devtools_state,  // ← ВСЁ через DevToolsState
-                            );
-                            if let Some(PanelAction::Attach) = action {
-                                should_attach = true;
-                            }
-                        }
-                        PanelType::AIChat => {
-                            let action = aichat::render_aichat_ui(
-                                ui,
-                                window_data.url_input,
-                                window_data.ai_chat_history,
-                                window_data.send_ai_message,
-                                true,
-                            );
-                            if let Some(PanelAction::Attach) = action {
-                                should_attach = true;
-                            }
-                        }
-                        PanelType::Settings => {
-                            let action = settings::render_settings_ui(
-                                ui,
-                                window_data.config,
-                                window_data.save_config,
-                                true,
-                            );
-                            if let Some(PanelAction::Attach) = action {
-                                should_attach = true;
-                            }
-                        }
-                        PanelType::Toolbar => {
-                            toolbar::render_toolbar(
-                                ui,
-                                window_data.url_input,
-                                window_data.url,
-                                window_data.bookmarks,
-                                window_data.show_devtools,
-                                window_data.show_settings,
-                                window_data.status_message,
-                                window_data.do_refresh,
-                                window_data.do_add_bookmark,
-                                window_data.new_url,
-                                window_data.toolbar_position,
-                                window_data.show_toolbar,
-                            );
-                        }
-                    }
-                });
-            });
+#[derive(Default)]
+struct SignalGraph {
+    signals: HashMap<SignalId, Signal>,
+    names: HashMap<String, SignalId>,
+    computations: HashMap<ComputationId, Computation>,
+    owners: HashMap<OwnerId, UnboundedSender<ComputationId>>,
+    // Вычисление, которое сейчас выполняет гость; его чтения становятся зависимостями
+    tracking: HashMap<OwnerId, ComputationId>,
+    next_signal: u32,
+    next_computation: u32,
+}
 
-            if should_attach {
-                detached.should_close = true;
-                return Ok(());
-            }
+impl SignalGraph {
+    fn schedule(&mut self, id: ComputationId) {
+        let Some(computation) = self.computations.get_mut(&id) else { return };
+        if computation.queued {
+            return;
+        }
+        if let Some(sender) = self.owners.get(&computation.owner) {
+            computation.queued = sender.send(id).is_ok();
+        }
+    }
 
-            let size = detached.window.inner_size();
-            if size.width > 0 && size.height > 0 && !detached.window.is_minimized().unwrap_or(false) {
-                wgpu_render::render_frame(
-                    &self.device,
-                    &self.queue,
-                    &detached.surface,
-                    &detached.surface_config,
-                    &mut detached.renderer,
-                    &detached.egui_ctx,
-                    &full_output,
-                    window_data.render_manager,
-                )?;
+    fn unsubscribe(&mut self, id: ComputationId) {
+        let Some(computation) = self.computations.get_mut(&id) else { return };
+        for source in std::mem::take(&mut computation.sources) {
+            if let Some(signal) = self.signals.get_mut(&source) {
+                signal.subscribers.remove(&id);
             }
+        }
+    }
+
+    fn signal_mut(&mut self, owner: Option<OwnerId>, id: SignalId) -> Result<&mut Signal> {
+        self.signals
+            .get_mut(&id)
+            .filter(|signal| signal.accessible(owner))
+            .ok_or_else(|| anyhow::anyhow!("Сигнал {:?} не найден", id))
+    }
+
+    // Новое значение и привязки, которые нужно обновить после снятия блокировки
+    fn write(&mut self, owner: Option<OwnerId>, id: SignalId, value: JsonValue) -> Result<Vec<DomBinding>> {
+        let writer = owner.and_then(|owner| self.tracking.get(&owner).copied());
+        let signal = self.signal_mut(owner, id)?;
+        if signal.value == value {
+            return Ok(Vec::new());
+        }
+        signal.value = value;
+        let bindings = signal.bindings.iter().map(|(_, binding)| binding.clone()).collect();
+        // Вычисление не перезапускается из-за собственной записи
+        let subscribers: Vec<_> = signal.subscribers.iter().copied().filter(|&sub| Some(sub) != writer).collect();
+        for subscriber in subscribers {
+            self.schedule(subscriber);
+        }
+        Ok(bindings)
+    }
+}
+
+fn apply_bindings(bindings: &[DomBinding], value: &JsonValue) {
+    for binding in bindings {
+        if let Err(e) = binding.apply(value) {
+            log::warn!("Не удалось обновить привязку сигнала: {}", e);
+        }
+    }
+}
+
+// Граф сигналов одного документа: хост публикует в него состояние страницы,
+// компоненты этой страницы создают в нём свои сигналы
+pub struct Signals {
+    graph: Mutex<SignalGraph>,
+    next_owner: AtomicU64,
+    id: u64,
+}
+
+impl Signals {
+    pub fn shared() -> Arc<Signals> {
+        Arc::new(Self {
+            graph: Mutex::new(SignalGraph::default()),
+            next_owner: AtomicU64::new(1),
+            id: NEXT_GRAPH.fetch_add(1, Ordering::Relaxed),
+        })
+    }
 
-            let viewport_rect = detached.egui_ctx.viewport_rect();
-            if !viewport_rect.is_negative() {
-                detached.position = (viewport_rect.min.x, viewport_rect.min.y);
-                detached.size = (viewport_rect.width(), viewport_rect.height());
-            }
+    pub fn id(&self) -> u64 {
+        self.id
+    }
 
-            if detached.egui_ctx.has_requested_repaint() {
-                detached.window.request_redraw();
-            }
+    // Перезапуски вычислений владельца приходят в возвращённый канал
+    pub fn register_owner(&self) -> (OwnerId, UnboundedReceiver<ComputationId>) {
+        let owner = OwnerId(self.next_owner.fetch_add(1, Ordering::Relaxed));
+        let (tx, rx) = unbounded_channel();
+        self.graph.lock().owners.insert(owner, tx);
+        (owner, rx)
+    }
+
+    pub fn has_computations(&self, owner: OwnerId) -> bool {
+        self.graph.lock().computations.values().any(|computation| computation.owner == owner)
+    }
+
+    // Закрывает канал владельца и удаляет его вычисления, сигналы и привязки к чужим сигналам
+    pub fn drop_owner(&self, owner: OwnerId) {
+        let mut graph = self.graph.lock();
+        graph.owners.remove(&owner);
+        graph.tracking.remove(&owner);
+        let owned: Vec<_> = graph.computations.iter().filter(|(_, c)| c.owner == owner).map(|(id, _)| *id).collect();
+        for id in owned {
+            graph.unsubscribe(id);
+            graph.computations.remove(&id);
+        }
+        graph.signals.retain(|_, signal| signal.owner != Some(owner));
+        for signal in graph.signals.values_mut() {
+            signal.bindings.retain(|(binding_owner, _)| *binding_owner != Some(owner));
         }
-        Ok(())
     }
 
-    pub fn handle_window_event(&mut self, panel_type: &PanelType, event: &WindowEvent) -> Result<()> {
-        if let Some(detached) = self.window_state.detached_windows.get_mut(panel_type) {
-            let resp = detached.egui_state.on_window_event(&detached.window, event);
-            if resp.repaint {
-                detached.window.request_redraw();
+    pub fn create(&self, owner: Option<OwnerId>, value: JsonValue) -> SignalId {
+        let mut graph = self.graph.lock();
+        graph.next_signal += 1;
+        let id = SignalId(graph.next_signal);
+        graph.signals.insert(id, Signal { value, owner, subscribers: BTreeSet::new(), bindings: Vec::new() });
+        id
+    }
+
+    pub fn named(&self, name: &str) -> Option<SignalId> {
+        self.graph.lock().names.get(name).copied()
+    }
+
+    // Обновление со стороны хоста: сигнал создаётся при первом обращении
+    pub fn set_named(&self, name: &str, value: JsonValue) -> Result<SignalId> {
+        let existing = self.named(name);
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                let id = self.create(None, JsonValue::Null);
+                self.graph.lock().names.insert(name.to_string(), id);
+                id
             }
-            
-            if let WindowEvent::CloseRequested = event {
-                detached.should_close = true;
+        };
+        self.set(id, value)?;
+        Ok(id)
+    }
+
+    // Чтение внутри вычисления подписывает его на сигнал
+    pub fn get(&self, owner: Option<OwnerId>, id: SignalId) -> Option<JsonValue> {
+        let mut guard = self.graph.lock();
+        let graph = &mut *guard;
+        let tracking = owner.and_then(|owner| graph.tracking.get(&owner).copied());
+        let signal = graph.signals.get_mut(&id).filter(|signal| signal.accessible(owner))?;
+        let value = signal.value.clone();
+        if let Some(computation) = tracking {
+            signal.subscribers.insert(computation);
+            if let Some(computation) = graph.computations.get_mut(&computation) {
+                computation.sources.insert(id);
             }
         }
+        Some(value)
+    }
+
+    pub fn set(&self, id: SignalId, value: JsonValue) -> Result<()> {
+        self.set_from(None, id, value)
+    }
+
+    fn set_from(&self, owner: Option<OwnerId>, id: SignalId, value: JsonValue) -> Result<()> {
+        let bindings = self.graph.lock().write(owner, id, value.clone())?;
+        apply_bindings(&bindings, &value);
         Ok(())
     }
 
-    pub fn resize_detached_window(&mut self, panel_type: &PanelType, new_size: winit::dpi::PhysicalSize<u32>) -> Result<()> {
-        if let Some(detached) = self.window_state.detached_windows.get_mut(panel_type) {
-            if new_size.width > 0 && new_size.height > 0 {
-                let surface_caps = detached.surface.get_capabilities(&self.adapter);
-                let config = wgpu_render::create_optimal_surface_config(&surface_caps, (new_size.width, new_size.height))?;
-                detached.surface.configure(&self.device, &config);
-                detached.surface_config = config;
-                detached.size = (new_size.width as f32, new_size.height as f32);
-            }
+    // Первый запуск ставится в очередь: гость не может быть вызван повторно, пока выполняется
+    pub fn create_computation(&self, owner: OwnerId, callback: u32, memo: bool) -> (ComputationId, Option<SignalId>) {
+        let memo = memo.then(|| self.create(Some(owner), JsonValue::Null));
+        let mut graph = self.graph.lock();
+        graph.next_computation += 1;
+        let id = ComputationId(graph.next_computation);
+        graph.computations.insert(id, Computation { owner, callback, memo, sources: BTreeSet::new(), queued: false });
+        graph.schedule(id);
+        (id, memo)
+    }
+
+    pub fn dispose(&self, owner: OwnerId, id: ComputationId) {
+        let mut graph = self.graph.lock();
+        if graph.computations.get(&id).is_some_and(|computation| computation.owner == owner) {
+            graph.unsubscribe(id);
+            graph.computations.remove(&id);
         }
-        Ok(())
     }
-    
-    pub fn close_all_detached_windows(&mut self) {
-        self.window_state.detached_windows.clear();
+
+    pub fn bind_text(&self, owner: Option<OwnerId>, id: SignalId, dom: SharedDom, node: NodeId) -> Result<()> {
+        self.bind(owner, id, DomBinding::Text(dom, node))
     }
 
-    pub fn cleanup_closed_windows(&mut self) {
-        self.window_state.detached_windows.retain(|_, detached| !detached.should_close);
+    pub fn bind_attribute(&self, owner: Option<OwnerId>, id: SignalId, dom: SharedDom, node: NodeId, name: &str) -> Result<()> {
+        self.bind(owner, id, DomBinding::Attribute(dom, node, name.to_ascii_lowercase()))
     }
 
-    pub fn get_detached_window_types(&self) -> Vec<PanelType> {
-        self.window_state.detached_windows.keys().cloned().collect()
+    fn bind(&self, owner: Option<OwnerId>, id: SignalId, binding: DomBinding) -> Result<()> {
+        let value = {
+            let mut graph = self.graph.lock();
+            let signal = graph.signal_mut(owner, id)?;
+            signal.bindings.push((owner, binding.clone()));
+            signal.value.clone()
+        };
+        binding.apply(&value)
     }
-}
 
-// === FILE: utils\config.rs ===
-use anyhow::Result;
-pub struct Config;
-impl Config {
-    pub fn new() -> Result<Self> {
-        println!("Config initialized");
-        Ok(Self)
+    // Зависимости пересобираются при каждом запуске
+    pub fn begin(&self, owner: OwnerId, id: ComputationId) -> Option<PendingRun> {
+        let mut graph = self.graph.lock();
+        let computation = graph.computations.get_mut(&id).filter(|c| c.owner == owner)?;
+        computation.queued = false;
+        let run = PendingRun { callback: computation.callback, memo: computation.memo };
+        graph.unsubscribe(id);
+        graph.tracking.insert(owner, id);
+        Some(run)
     }
-}
 
-// === FILE: utils\log.rs ===
-use anyhow::Result;
-use log::LevelFilter;
-pub struct Log;
-impl Log {
-    pub fn new() -> Result<Self> {
-        println!("Log initialized");
-        Ok(Self)
+    pub fn finish(&self, owner: OwnerId, run: &PendingRun, result: Result<String>) {
+        self.graph.lock().tracking.remove(&owner);
+        let value = match result.and_then(|json| Ok(serde_json::from_str::<JsonValue>(&json)?)) {
+            Ok(value) => value,
+            Err(e) => {
+                log::warn!("Реакция {} компонента {:?} завершилась с ошибкой: {}", run.callback, owner, e);
+                return;
+            }
+        };
+        if let Some(memo) = run.memo {
+            if let Err(e) = self.set(memo, value) {
+                log::warn!("Не удалось обновить memo: {}", e);
+            }
+        }
     }
 }
-pub fn init_logger(_level: LevelFilter) -> Result<()> {
-    println!("Logger initialized (stub)");
-    Ok(())
+
+// Документ страницы вместе с его графом сигналов. Именованные сигналы одной страницы
+// не видны компонентам другой
+#[derive(Clone)]
+pub struct PageDocument {
+    pub dom: SharedDom,
+    pub signals: Arc<Signals>,
 }
 
-// === FILE: utils\mem.rs ===
-use anyhow::Result;
-pub struct Mem;
-impl Mem {
-    pub fn new() -> Result<Self> {
-        println!("Mem initialized");
-        Ok(Self)
+impl PageDocument {
+    pub fn new(dom: SharedDom) -> Self {
+        Self { dom, signals: Signals::shared() }
     }
 }
 
-// === FILE: utils\mod.rs ===
-pub mod log;
-pub mod config;
-pub mod timer;
-pub mod mem;
-pub mod platform;
-pub mod net_utils;
+// Сигналы мира yuaibro:host для одного экземпляра компонента
+pub struct GuestSignals {
+    owner: OwnerId,
+    signals: Arc<Signals>,
+    dom: Option<SharedDom>,
+}
 
-// === FILE: utils\net_utils.rs ===
-use anyhow::Result;
-pub struct NetUtils;
-impl NetUtils{
-    pub fn new() -> Result<Self> {
-        println!("NetUtils initialized");
-        Ok(Self)
+impl GuestSignals {
+    pub fn new(owner: OwnerId, signals: Arc<Signals>, dom: Option<SharedDom>) -> Self {
+        Self { owner, signals, dom }
     }
-}
 
-// === FILE: utils\platform.rs ===
-use anyhow::Result;
-pub struct Platform;
-impl Platform{
-    pub fn new() -> Result<Self> {
-        println!("Platform initialized");
-        Ok(Self)
+    fn node(&self, node: u64) -> std::result::Result<(SharedDom, NodeId), String> {
+        let dom = self.dom.clone().ok_or_else(|| "Компоненту не выдан доступ к DOM".to_string())?;
+        let id = u32::try_from(node).map(NodeId).map_err(|_| format!("Неизвестный узел {}", node))?;
+        if !dom.read().contains(id) {
+            return Err(format!("Неизвестный узел {}", node));
+        }
+        Ok((dom, id))
     }
 }
 
-// === FILE: utils\timer.rs ===
-use anyhow::Result;
-pub struct Timer;
-impl Timer {
-    pub fn new() -> Result<Self> {
-        println!("Timer initialized");
-        Ok(Self)
+#[cfg(feature = "wasm")]
+impl signals::Host for GuestSignals {
+    fn create_signal(&mut self, initial: String) -> std::result::Result<u32, String> {
+        let value = serde_json::from_str(&initial).map_err(|e| e.to_string())?;
+        Ok(self.signals.create(Some(self.owner), value).0)
     }
-}
 
-// === FILE: wasm_api\bindings.rs ===
-use anyhow::Result;
-use std::sync::Arc;
-use crate::net::Network;
-pub struct Bindings;
-impl Bindings {
-    pub fn new(_network: Arc<Network>) -> Result<Self> {
-        println!("Bindings initialized");
-        Ok(Self)
+    fn named(&mut self, name: String) -> Option<u32> {
+        self.signals.named(&name).map(|id| id.0)
     }
-}
 
-// === FILE: wasm_api\cloud_bindings.rs ===
-use anyhow::Result;
-use std::sync::Arc;
-use crate::net::Network;
-pub struct CloudBindings;
-impl CloudBindings {
-    pub fn new(_network: Arc<Network>) -> Result<Self> {
-        println!("CloudBindings initialized");
-        Ok(Self)
+    fn get(&mut self, signal: u32) -> std::result::Result<String, String> {
+        self.signals
+            .get(Some(self.owner), SignalId(signal))
+            .map(|value| value.to_string())
+            .ok_or_else(|| format!("Сигнал {} не найден", signal))
     }
-}
 
-// === FILE: wasm_api\mod.rs ===
-pub mod bindings;
-pub mod signals;
-pub mod web_sys;
-pub mod sandbox;
-pub mod cloud_bindings;
-pub mod provider_api;
+    fn set(&mut self, signal: u32, value: String) -> std::result::Result<(), String> {
+        let value = serde_json::from_str(&value).map_err(|e| e.to_string())?;
+        self.signals.set_from(Some(self.owner), SignalId(signal), value).map_err(|e| e.to_string())
+    }
 
-// === FILE: wasm_api\provider_api.rs ===
-use anyhow::Result;
-use std::sync::Arc;
-use crate::net::Network;
-pub struct ProviderAPI;
-impl ProviderAPI {
-    pub fn new(_network: Arc<Network>) -> Result<Self> {
-        println!("ProviderAPI initialized");
-        Ok(Self)
+    fn create_effect(&mut self, callback: u32) -> u32 {
+        self.signals.create_computation(self.owner, callback, false).0 .0
     }
-}
 
-// === FILE: wasm_api\sandbox.rs ===
-use anyhow::Result;
-use std::sync::Arc;
-use crate::net::Network;
-pub struct Sandbox;
-impl Sandbox {
-    pub fn new(_network: Arc<Network>) -> Result<Self> {
-        println!("Sandbox initialized");
-        Ok(Self)
+    fn create_memo(&mut self, callback: u32) -> u32 {
+        let (_, memo) = self.signals.create_computation(self.owner, callback, true);
+        memo.expect("memo создаётся вместе с сигналом").0
     }
-}
 
-// === FILE: wasm_api\signals.rs ===
-use anyhow::Result;
-use std::sync::Arc;
-use crate::net::Network;
-pub struct Signals;
-impl Signals {
-    pub fn new(_network: Arc<Network>) -> Result<Self> {
-        println!("Signals initialized");
-        Ok(Self)
+    fn dispose(&mut self, computation: u32) {
+        self.signals.dispose(self.owner, ComputationId(computation));
+    }
+
+    fn bind_text(&mut self, signal: u32, node: u64) -> std::result::Result<(), String> {
+        let (dom, node) = self.node(node)?;
+        self.signals.bind_text(Some(self.owner), SignalId(signal), dom, node).map_err(|e| e.to_string())
+    }
+
+    fn bind_attribute(&mut self, signal: u32, node: u64, name: String) -> std::result::Result<(), String> {
+        let (dom, node) = self.node(node)?;
+        self.signals
+            .bind_attribute(Some(self.owner), SignalId(signal), dom, node, &name)
+            .map_err(|e| e.to_string())
     }
 }
 
 // === FILE: wasm_api\web_sys.rs ===
-use anyhow::Result;
-use std::sync::Arc;
-use crate::net::Network;
-pub struct WebSys;
+use crate::dom::live::{NodeId, SharedDom};
+#[cfg(feature = "wasm")]
+use crate::wasm_api::bindings::yuaibro::host::dom;
+
+// DOM-интерфейс мира yuaibro:host; без выданного документа чтение пустое, изменения отклоняются
+pub struct WebSys {
+    dom: Option<SharedDom>,
+}
+
 impl WebSys {
-    pub fn new(_network: Arc<Network>) -> Result<Self> {
-        println!("WebSys initialized");
-        Ok(Self)
+    pub fn new(dom: Option<SharedDom>) -> Self {
+        Self { dom }
+    }
+
+    fn shared_dom(&self) -> Result<&SharedDom, String> {
+        self.dom.as_ref().ok_or_else(|| "Компоненту не выдан доступ к DOM".to_string())
+    }
+
+    // Идентификаторы узлов проверяются при каждом обращении: компонент может хранить устаревшие
+    fn node(&self, id: u64) -> Result<NodeId, String> {
+        let node = u32::try_from(id).map(NodeId).map_err(|_| format!("Неизвестный узел {}", id))?;
+        if self.shared_dom()?.read().contains(node) {
+            Ok(node)
+        } else {
+            Err(format!("Неизвестный узел {}", id))
+        }
+    }
+
+    fn mutate(&self, apply: impl FnOnce(&mut crate::dom::live::LiveDom) -> anyhow::Result<()>) -> Result<(), String> {
+        apply(&mut self.shared_dom()?.write()).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl dom::Host for WebSys {
+    fn document(&mut self) -> Result<u64, String> {
+        Ok(u64::from(self.shared_dom()?.read().root().0))
+    }
+
+    fn tag_name(&mut self, node: u64) -> Option<String> {
+        let node = self.node(node).ok()?;
+        self.dom.as_ref()?.read().tag(node).map(str::to_string)
+    }
+
+    fn parent(&mut self, node: u64) -> Option<u64> {
+        let node = self.node(node).ok()?;
+        self.dom.as_ref()?.read().parent(node).map(|parent| u64::from(parent.0))
+    }
+
+    fn children(&mut self, node: u64) -> Vec<u64> {
+        match (self.node(node), &self.dom) {
+            (Ok(node), Some(dom)) => dom.read().children(node).into_iter().map(|child| u64::from(child.0)).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn get_attribute(&mut self, node: u64, name: String) -> Option<String> {
+        let node = self.node(node).ok()?;
+        self.dom.as_ref()?.read().get_attribute(node, &name)
+    }
+
+    fn set_attribute(&mut self, node: u64, name: String, value: String) -> Result<(), String> {
+        let node = self.node(node)?;
+        self.mutate(|dom| dom.set_attribute(node, &name, &value))
+    }
+
+    fn remove_attribute(&mut self, node: u64, name: String) -> Result<(), String> {
+        let node = self.node(node)?;
+        self.mutate(|dom| dom.remove_attribute(node, &name))
+    }
+
+    fn text_content(&mut self, node: u64) -> String {
+        match (self.node(node), &self.dom) {
+            (Ok(node), Some(dom)) => dom.read().text_content(node),
+            _ => String::new(),
+        }
+    }
+
+    fn set_text_content(&mut self, node: u64, text: String) -> Result<(), String> {
+        let node = self.node(node)?;
+        self.mutate(|dom| dom.set_text_content(node, &text))
+    }
+
+    fn query_selector_all(&mut self, scope: u64, selector: String) -> Result<Vec<u64>, String> {
+        let scope = self.node(scope)?;
+        let found = self.shared_dom()?.read().query_selector_all(scope, &selector).map_err(|e| e.to_string())?;
+        Ok(found.into_iter().map(|node| u64::from(node.0)).collect())
+    }
+
+    fn create_element(&mut self, tag: String) -> Result<u64, String> {
+        Ok(u64::from(self.shared_dom()?.write().create_element(&tag).0))
+    }
+
+    fn create_text(&mut self, text: String) -> Result<u64, String> {
+        Ok(u64::from(self.shared_dom()?.write().create_text(&text).0))
+    }
+
+    fn append_child(&mut self, parent: u64, child: u64) -> Result<(), String> {
+        let (parent, child) = (self.node(parent)?, self.node(child)?);
+        self.mutate(|dom| dom.append_child(parent, child))
+    }
+
+    fn remove_child(&mut self, parent: u64, child: u64) -> Result<(), String> {
+        let (parent, child) = (self.node(parent)?, self.node(child)?);
+        self.mutate(|dom| dom.remove_child(parent, child))
     }
 }
 
@@ -4900,22 +18994,26 @@ mod core;
 mod net;
 mod ui;
 mod dom;
+mod wasm_api;
 
 use anyhow::Result;
 use std::sync::Arc;
 use tokio::runtime::Builder;
 
-use crate::core::config::Config;
+use crate::core::config::{CliOptions, LayeredConfig};
 use crate::core::installer::Installer;
 use crate::core::interfaces::{
     NetworkTrait, JsRuntimeTrait, WasmRuntimeTrait, OrchestratorTrait, PluginManagerTrait,
     LibManagerTrait, YuaidbTrait, SecurityManagerTrait, ReactiveCoreTrait, SessionManagerTrait,
     ProfileManagerTrait, ServiceWorkerTrait, SchedulerTrait, IoManagerTrait, WasmManifestTrait,
+    NetRequest, NetResponse,
 };
 use crate::core::engine::{BroEngine, EngineEvent};
 use crate::core::runtime::Runtime as WasmRuntime;
-use crate::core::js_runtime::JsRuntime;
+use crate::core::js_runtime::{JsRuntime, RealmBindings, RealmOptions, ScriptError};
+use crate::core::page_state::PageId;
 use crate::core::orchestrator::Orchestrator;
+use crate::core::provider::Provider;
 use crate::core::plugins::PluginManager;
 use crate::core::libs::LibManager;
 use crate::core::yuaidb::Yuaidb;
@@ -4926,23 +19024,34 @@ use crate::core::profile::ProfileManager;
 use crate::core::service_worker::ServiceWorker;
 use crate::core::scheduler::Scheduler;
 use crate::core::io_manager::IoManager;
+use crate::core::about::AboutPages;
 use crate::core::wasm_manifest::WasmManifest;
+use crate::net::cache::CacheEntryInfo;
 use crate::net::fetch::{Network, run_network_service};
+use crate::wasm_api::bindings::Bindings;
+use crate::wasm_api::cloud_bindings::CloudBindings;
 
 
 fn main() -> Result<()> {
+    // 1. Настройка: конфигурация читается до запуска runtime, чтобы задать число потоков
+    env_logger::init();
+    Installer::setup()?;
+    let cosmonaut_dir = Installer::get_cosmonaut_dir()?;
+    let options = CliOptions::parse(std::env::args().skip(1))?;
+    let layered = LayeredConfig::load(&options)?;
+    if options.print_sources {
+        print!("{}", layered.report());
+        return Ok(());
+    }
+    let config = layered.config.clone();
+    config.validate()?;
+
     let rt = Builder::new_multi_thread()
-        .worker_threads(8) // Должно браться из config.settings.max_threads
+        .worker_threads(config.settings.max_threads)
         .enable_all()
         .build()?;
 
     rt.block_on(async {
-        // 1. Настройка
-        env_logger::init();
-        Installer::setup()?;
-        let cosmonaut_dir = Installer::get_cosmonaut_dir()?;
-        let config = Config::load_from_file(Config::get_config_path())?;
-        config.validate()?;
 
         // 2. Инициализация Core Services
         let db = Arc::new(Yuaidb::new(cosmonaut_dir.join("yuaidb")).await?) as Arc<dyn YuaidbTrait + Send + Sync>;
@@ -4954,33 +19063,55 @@ fn main() -> Result<()> {
             Arc::new(DummyNetwork) as Arc<dyn NetworkTrait + Send + Sync>
         };
 
-        let wasm_runtime = Arc::new(WasmRuntime::new(&config).await?) as Arc<dyn WasmRuntimeTrait + Send + Sync>;
+        // Канал событий создаётся заранее: в него пишут сервисы, созданные до движка (console из JS)
+        let (event_tx, event_rx) = tokio::sync::broadcast::channel::<EngineEvent>(1024);
+        // Запросы скриптов страниц к движку (fetch, XMLHttpRequest)
+        let (host_tx, host_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        // Безопасность инициализируется первой: без неё не загружается ни один компонент
+        let security = Arc::new(Security::new(&config).await?) as Arc<dyn SecurityManagerTrait + Send + Sync>;
+        let wasm_runtime = WasmRuntime::new(&config, security.clone()).await?;
+        // Компоненты yuaibro:host используют движок WASM runtime
+        let bindings = Arc::new(Bindings::new(&wasm_runtime, security.clone(), network.clone(), db.clone(), event_tx.clone())?);
+        let wasm_runtime = Arc::new(wasm_runtime) as Arc<dyn WasmRuntimeTrait + Send + Sync>;
         let js_runtime = if cfg!(feature = "js") {
-            Arc::new(JsRuntime::new()?) as Arc<dyn JsRuntimeTrait + Send + Sync>
+            Arc::new(JsRuntime::new(Some(event_tx.clone()), Some(host_tx))?) as Arc<dyn JsRuntimeTrait + Send + Sync>
         } else {
             Arc::new(DummyJsRuntime) as Arc<dyn JsRuntimeTrait + Send + Sync>
         };
-        let orchestrator = Arc::new(Orchestrator::new()?) as Arc<dyn OrchestratorTrait + Send + Sync>;
-        let plugin_manager = Arc::new(PluginManager::new(cosmonaut_dir.join("plugins"), cosmonaut_dir.join("providers")).await?) as Arc<dyn PluginManagerTrait + Send + Sync>;
-        let lib_manager = Arc::new(LibManager::new(cosmonaut_dir.join("libs")).await?) as Arc<dyn LibManagerTrait + Send + Sync>;
-        let security = Arc::new(Security::new(&config).await?) as Arc<dyn SecurityManagerTrait + Send + Sync>;
+        let orchestrator = Arc::new(Orchestrator::new(wasm_runtime.clone())?) as Arc<dyn OrchestratorTrait + Send + Sync>;
+        for provider in Provider::load_all(&cosmonaut_dir.join("providers"), wasm_runtime.clone(), security.clone()).await {
+            orchestrator.register_provider(provider);
+        }
+        // Плагины загружаются до движка; PluginLoaded попадает в общий канал событий
+        let plugin_manager = Arc::new(PluginManager::new(cosmonaut_dir.join("plugins"), &config, wasm_runtime.clone(), security.clone(), event_tx.clone()).await?) as Arc<dyn PluginManagerTrait + Send + Sync>;
+        let lib_manager = Arc::new(LibManager::new(cosmonaut_dir.join("libs"), wasm_runtime.clone(), security.clone()).await?) as Arc<dyn LibManagerTrait + Send + Sync>;
         let reactive_core = Arc::new(ReactiveCore::new().await) as Arc<dyn ReactiveCoreTrait + Send + Sync>;
         let session_manager = Arc::new(SessionManager::new(db.clone()).await?) as Arc<dyn SessionManagerTrait + Send + Sync>;
         let profile_manager = Arc::new(ProfileManager::new(cosmonaut_dir.join("certs").join("user")).await?) as Arc<dyn ProfileManagerTrait + Send + Sync>;
         let service_worker = Arc::new(ServiceWorker::new(network.clone(), db.clone()).await?) as Arc<dyn ServiceWorkerTrait + Send + Sync>;
-        let io_manager = Arc::new(IoManager::new(network.clone(), db.clone()).await?) as Arc<dyn IoManagerTrait + Send + Sync>;
-        let wasm_manifest = Arc::new(WasmManifest::new().await?) as Arc<dyn WasmManifestTrait + Send + Sync>;
-        let scheduler = Arc::new(Scheduler::new(Some(wasm_runtime.clone()), Some(js_runtime.clone()), Some(orchestrator.clone())).await?) as Arc<dyn SchedulerTrait + Send + Sync>;
+        let io_manager = Arc::new(IoManager::new(&config, network.clone(), db.clone()).await?) as Arc<dyn IoManagerTrait + Send + Sync>;
+        let wasm_manifest = Arc::new(WasmManifest::new(&config, security.clone()).await?) as Arc<dyn WasmManifestTrait + Send + Sync>;
+        let scheduler = Arc::new(Scheduler::new(config.settings.max_threads, Some(wasm_runtime.clone()), Some(js_runtime.clone()), Some(orchestrator.clone())).await?) as Arc<dyn SchedulerTrait + Send + Sync>;
+        // Управление удалённой решёткой; события идут в общий канал движка
+        let cloud = match &config.lattice {
+            Some(lattice) if config.modules.wasmcloud_enabled => {
+                let cloud = Arc::new(CloudBindings::new(lattice)?);
+                cloud.watch(event_tx.clone());
+                Some(cloud)
+            }
+            _ => None,
+        };
 
         // 3. Инициализация движка
         let engine = Arc::new(BroEngine::new(
             network.clone(),
             js_runtime,
             wasm_runtime,
-            orchestrator,
-            plugin_manager,
+            orchestrator.clone(),
+            plugin_manager.clone(),
             lib_manager,
-            db,
+            db.clone(),
             security,
             reactive_core,
             session_manager,
@@ -4989,23 +19120,37 @@ fn main() -> Result<()> {
             scheduler,
             io_manager,
             wasm_manifest,
+            bindings,
+            cloud,
             None,
-            None,
-            None,
+            Some(event_tx),
+            Some(event_rx),
         ).await?);
+        // Решения о разрешениях хранятся отдельно для каждого профиля конфигурации
+        if let Some(profile) = &layered.profile {
+            engine.permissions().set_profile(profile);
+        }
+        // Страницы about:* читают состояние движка и журнал его событий
+        AboutPages::install(&engine, layered, options);
 
         // 4. Запуск асинхронного сетевого сервиса, если сеть включена
         if cfg!(feature = "network") {
             tokio::spawn(run_network_service(network, engine.clone()));
         }
+        tokio::spawn(engine.clone().serve_host_requests(host_rx));
 
         // 5. Запуск UI-клиента, если включена feature "ui"
         let result = if cfg!(feature = "ui") {
-            crate::ui::run(engine).await
+            crate::ui::run(engine, config.clone()).await
         } else {
             log::info!("UI отключен, запуск в headless-режиме");
             Ok(())
         };
+        plugin_manager.shutdown().await;
+        orchestrator.shutdown().await;
+        if let Err(e) = db.flush().await {
+            log::error!("Не удалось сохранить базу данных при выходе: {}", e);
+        }
 
         log::info!("Приложение завершено с результатом: {:?}", result);
         result
@@ -5024,6 +19169,14 @@ impl NetworkTrait for DummyNetwork {
     async fn fetch_html_with_headers(&self, _url: &str) -> Result<(String, reqwest::header::HeaderMap)> {
         Err(anyhow::anyhow!("Сетевой модуль отключен"))
     }
+
+    async fn fetch_request(&self, _request: NetRequest) -> Result<NetResponse> {
+        Err(anyhow::anyhow!("Сетевой модуль отключен"))
+    }
+
+    fn cache_entries(&self) -> Vec<CacheEntryInfo> {
+        Vec::new()
+    }
 }
 
 // Заглушка для отключенного JS
@@ -5034,5 +19187,23 @@ impl JsRuntimeTrait for DummyJsRuntime {
     async fn execute_script(&self, _script: &str) -> Result<()> {
         Err(anyhow::anyhow!("JS runtime отключен"))
     }
+
+    async fn create_realm(&self, _page: PageId, _options: RealmOptions, _bindings: RealmBindings) -> Result<()> {
+        Err(anyhow::anyhow!("JS runtime отключен"))
+    }
+
+    async fn destroy_realm(&self, _page: PageId) {}
+
+    async fn evaluate(&self, _page: PageId, _script: &str) -> std::result::Result<String, ScriptError> {
+        Err(ScriptError::new("JS runtime отключен"))
+    }
+
+    async fn evaluate_module(&self, _page: PageId, _source: &str) -> std::result::Result<(), ScriptError> {
+        Err(ScriptError::new("JS runtime отключен"))
+    }
+
+    async fn execute_task(&self, _page: PageId, _script: &str) -> Result<()> {
+        Err(anyhow::anyhow!("JS runtime отключен"))
+    }
 }
 